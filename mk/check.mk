@@ -89,8 +89,18 @@ check-ref-gpu: reftest
 	@$(call E, check: reftests with GPU rendering)
 	$(Q)./reftest $(S)src/test/ref/*.list
 
+.PHONY: check-ref-headless
+check-ref-headless: reftest
+	@$(call E, check: reftests with the headless compositor)
+	$(Q)./reftest $(S)src/test/ref/*.list $(S)src/test/ref/headless/*.list -- -z
+
+.PHONY: check-ref-parallel
+check-ref-parallel: reftest
+	@$(call E, check: reftests with parallel layout)
+	$(Q)./reftest $(S)src/test/ref/parallel/*.list -- -c -y 4
+
 .PHONY: check-ref
-check-ref: check-ref-cpu check-ref-gpu
+check-ref: check-ref-cpu check-ref-gpu check-ref-headless check-ref-parallel
 
 .PHONY: check-content
 check-content: contenttest
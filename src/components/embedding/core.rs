@@ -6,9 +6,11 @@
 use azure;
 use command_line::command_line_init;
 use eutil::fptr_is_null;
+use geom::size::TypedSize2D;
 use libc::{c_int, c_void};
 use native;
 use servo;
+use servo_util::font_prefs::FontPrefs;
 use servo_util::opts;
 use std::mem;
 use types::{cef_app_t, cef_main_args_t, cef_settings_t};
@@ -50,6 +52,7 @@ pub extern "C" fn cef_run_message_loop() {
         n_render_threads: 1,
         cpu_painting: false,
         tile_size: 512,
+        initial_window_size: TypedSize2D(800, 600),
         device_pixels_per_px: None,
         profiler_period: None,
         layout_threads: 1,
@@ -59,7 +62,11 @@ pub extern "C" fn cef_run_message_loop() {
         headless: false,
         hard_fail: false,
         bubble_widths_separately: false,
-        native_threading: false
+        native_threading: false,
+        font_prefs: FontPrefs::new(),
+        send_keys: None,
+        send_clicks: vec!(),
+        profile_dir: None,
     };
     native::start(0, 0 as **u8, proc() {
        servo::run(opts);
@@ -15,7 +15,8 @@
 /// low-level drawing primitives.
 
 use color::Color;
-use render_context::RenderContext;
+use render_context::{LineThroughDecoration, OverlineDecoration, RenderContext};
+use render_context::UnderlineDecoration;
 use text::glyph::CharIndex;
 use text::TextRun;
 
@@ -30,7 +31,7 @@ use servo_util::range::Range;
 use std::fmt;
 use std::mem;
 use std::slice::Items;
-use style::computed_values::border_style;
+use style::computed_values::{border_style, text_decoration_style};
 use sync::Arc;
 
 pub mod optimizer;
@@ -383,15 +384,24 @@ pub struct SolidColorDisplayItem {
     pub color: Color,
 }
 
+/// A line drawn under, over or through text.
+#[deriving(Clone)]
+pub struct TextDecoration {
+    /// The color of the line.
+    pub color: Color,
+    /// The style of the line.
+    pub style: text_decoration_style::T,
+}
+
 /// Text decoration information.
 #[deriving(Clone)]
 pub struct TextDecorations {
-    /// The color to use for underlining, if any.
-    pub underline: Option<Color>,
-    /// The color to use for overlining, if any.
-    pub overline: Option<Color>,
-    /// The color to use for line-through, if any.
-    pub line_through: Option<Color>,
+    /// The underline, if any.
+    pub underline: Option<TextDecoration>,
+    /// The overline, if any.
+    pub overline: Option<TextDecoration>,
+    /// The line-through, if any.
+    pub line_through: Option<TextDecoration>,
 }
 
 /// Renders text.
@@ -530,24 +540,33 @@ impl DisplayItem {
                 let strikeout_size = font_metrics.strikeout_size;
                 let strikeout_offset = font_metrics.strikeout_offset;
 
-                for underline_color in text.text_decorations.underline.iter() {
+                for underline in text.text_decorations.underline.iter() {
                     let underline_y = baseline_origin.y - underline_offset;
                     let underline_bounds = Rect(Point2D(baseline_origin.x, underline_y),
                                                 Size2D(width, underline_size));
-                    render_context.draw_solid_color(&underline_bounds, *underline_color);
+                    render_context.draw_text_decoration(&underline_bounds,
+                                                        UnderlineDecoration,
+                                                        underline.color,
+                                                        underline.style);
                 }
 
-                for overline_color in text.text_decorations.overline.iter() {
+                for overline in text.text_decorations.overline.iter() {
                     let overline_bounds = Rect(Point2D(baseline_origin.x, origin.y),
                                                Size2D(width, underline_size));
-                    render_context.draw_solid_color(&overline_bounds, *overline_color);
+                    render_context.draw_text_decoration(&overline_bounds,
+                                                        OverlineDecoration,
+                                                        overline.color,
+                                                        overline.style);
                 }
 
-                for line_through_color in text.text_decorations.line_through.iter() {
+                for line_through in text.text_decorations.line_through.iter() {
                     let strikeout_y = baseline_origin.y - strikeout_offset;
                     let strikeout_bounds = Rect(Point2D(baseline_origin.x, strikeout_y),
                                                 Size2D(width, strikeout_size));
-                    render_context.draw_solid_color(&strikeout_bounds, *line_through_color);
+                    render_context.draw_text_decoration(&strikeout_bounds,
+                                                        LineThroughDecoration,
+                                                        line_through.color,
+                                                        line_through.style);
                 }
             }
 
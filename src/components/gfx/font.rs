@@ -14,7 +14,7 @@ use std::rc::Rc;
 use std::cell::RefCell;
 use servo_util::cache::{Cache, HashCache};
 use servo_util::range::Range;
-use style::computed_values::{text_decoration, font_weight, font_style};
+use style::computed_values::{font_weight, font_style};
 use sync::Arc;
 
 use color::Color;
@@ -24,7 +24,7 @@ use platform::font_context::FontContextHandle;
 use platform::font::{FontHandle, FontTable};
 use render_context::RenderContext;
 use text::glyph::{CharIndex, GlyphStore, GlyphId};
-use text::shaping::ShaperMethods;
+use text::shaping::{ShaperMethods, ShapingOptions};
 use text::{Shaper, TextRun};
 
 // FontHandle encapsulates access to the platform's font API,
@@ -133,6 +133,31 @@ impl FontDescriptor {
 #[deriving(Clone, Eq)]
 pub enum FontSelector {
     SelectorPlatformIdentifier(String),
+    /// A face defined by an `@font-face` rule, which carries its own font data.
+    SelectorWebFont(WebFontData),
+}
+
+/// The data of a font downloaded for an `@font-face` rule, along with the code points the rule
+/// allows it to render.
+#[deriving(Clone)]
+pub struct WebFontData {
+    pub url: String,
+    /// Inclusive ranges of code points, from the rule's `unicode-range` descriptor.
+    pub unicode_range: Vec<(u32, u32)>,
+    pub bytes: Arc<Vec<u8>>,
+}
+
+/// Web fonts are identified by where they were loaded from; the font data is never compared.
+impl Eq for WebFontData {
+    fn eq(&self, other: &WebFontData) -> bool {
+        self.url == other.url && self.unicode_range == other.unicode_range
+    }
+}
+
+impl WebFontData {
+    pub fn contains_char(&self, ch: char) -> bool {
+        self.unicode_range.iter().any(|&(start, end)| start <= ch as u32 && ch as u32 <= end)
+    }
 }
 
 // This struct is the result of mapping a specified FontStyle into the
@@ -159,11 +184,10 @@ impl FontGroup {
         }
     }
 
-    pub fn create_textrun(&self, text: String, decoration: text_decoration::T) -> TextRun {
-        assert!(self.fonts.len() > 0);
-
-        // TODO(Issue #177): Actually fall back through the FontGroup when a font is unsuitable.
-        TextRun::new(&mut *self.fonts.get(0).borrow_mut(), text.clone(), decoration)
+    /// Returns the index of the first font in this group that has a glyph for the given
+    /// character, if any.
+    pub fn find_font_index_for_char(&self, ch: char) -> Option<uint> {
+        self.fonts.iter().position(|font| font.borrow().covers_char(ch))
     }
 }
 
@@ -201,18 +225,23 @@ and the renderer can use it to render text.
 */
 pub struct Font {
     pub handle: FontHandle,
+    /// How to recreate this font from a `FontDescriptor`.
+    pub selector: FontSelector,
     pub azure_font: Option<ScaledFont>,
     pub shaper: Option<Shaper>,
     pub style: UsedFontStyle,
     pub metrics: FontMetrics,
     pub backend: BackendType,
-    pub shape_cache: HashCache<String, Arc<GlyphStore>>,
+    /// Shaped glyphs, keyed by text, whether the text was shaped right-to-left, and the shaping
+    /// options.
+    pub shape_cache: HashCache<(String, bool, ShapingOptions), Arc<GlyphStore>>,
     pub glyph_advance_cache: HashCache<u32, FractionalPixel>,
 }
 
 impl<'a> Font {
     pub fn new_from_buffer(ctx: &FontContext,
                            buffer: Vec<u8>,
+                           selector: FontSelector,
                            style: &SpecifiedFontStyle,
                            backend: BackendType)
             -> Result<Rc<RefCell<Font>>, ()> {
@@ -226,6 +255,7 @@ impl<'a> Font {
 
         return Ok(Rc::new(RefCell::new(Font {
             handle: handle,
+            selector: selector,
             azure_font: None,
             shaper: None,
             style: (*style).clone(),
@@ -240,9 +270,11 @@ impl<'a> Font {
                                style: &SpecifiedFontStyle, backend: BackendType)
                                -> Font {
         let metrics = handle.get_metrics();
+        let selector = SelectorPlatformIdentifier(handle.face_identifier());
 
         Font {
             handle: handle,
+            selector: selector,
             azure_font: None,
             shaper: None,
             style: (*style).clone(),
@@ -350,25 +382,34 @@ impl Font {
             fields: 0x0200 as uint16_t
         };
 
-        let mut origin = baseline_origin.clone();
-        let mut azglyphs = vec!();
-        azglyphs.reserve(range.length().to_uint());
-
+        // Glyph stores are in logical order, so gather the glyphs first and lay them out from the
+        // left edge in visual order afterward.
+        let mut glyphs_in_logical_order = vec!();
+        glyphs_in_logical_order.reserve(range.length().to_uint());
         for (glyphs, _offset, slice_range) in run.iter_slices_for_range(range) {
             for (_i, glyph) in glyphs.iter_glyphs_for_char_range(&slice_range) {
-                let glyph_advance = glyph.advance();
-                let glyph_offset = glyph.offset().unwrap_or(Zero::zero());
-
-                let azglyph = struct__AzGlyph {
-                    mIndex: glyph.id() as uint32_t,
-                    mPosition: struct__AzPoint {
-                        x: (origin.x + glyph_offset.x).to_nearest_px() as AzFloat,
-                        y: (origin.y + glyph_offset.y).to_nearest_px() as AzFloat
-                    }
-                };
-                origin = Point2D(origin.x + glyph_advance, origin.y);
-                azglyphs.push(azglyph)
+                glyphs_in_logical_order.push((glyph.id(),
+                                              glyph.advance(),
+                                              glyph.offset().unwrap_or(Zero::zero())));
+            }
+        }
+        if run.is_rtl() {
+            glyphs_in_logical_order.reverse()
+        }
+
+        let mut origin = baseline_origin.clone();
+        let mut azglyphs = vec!();
+        azglyphs.reserve(glyphs_in_logical_order.len());
+        for &(glyph_id, glyph_advance, glyph_offset) in glyphs_in_logical_order.iter() {
+            let azglyph = struct__AzGlyph {
+                mIndex: glyph_id as uint32_t,
+                mPosition: struct__AzPoint {
+                    x: (origin.x + glyph_offset.x).to_nearest_px() as AzFloat,
+                    y: (origin.y + glyph_offset.y).to_nearest_px() as AzFloat
+                }
             };
+            origin = Point2D(origin.x + glyph_advance, origin.y);
+            azglyphs.push(azglyph)
         }
 
         let azglyph_buf_len = azglyphs.len();
@@ -413,20 +454,36 @@ impl Font {
         RunMetrics::new(advance, self.metrics.ascent, self.metrics.descent)
     }
 
-    pub fn shape_text(&mut self, text: String, is_whitespace: bool) -> Arc<GlyphStore> {
+    pub fn shape_text(&mut self,
+                      text: String,
+                      is_whitespace: bool,
+                      is_rtl: bool,
+                      options: &ShapingOptions)
+                      -> Arc<GlyphStore> {
 
         //FIXME (ksh8281)
         self.make_shaper();
         let shaper = &self.shaper;
-        self.shape_cache.find_or_create(&text, |txt| {
-            let mut glyphs = GlyphStore::new(text.as_slice().char_len() as int, is_whitespace);
-            shaper.get_ref().shape_text(txt.as_slice(), &mut glyphs);
+        let key = (text, is_rtl, (*options).clone());
+        self.shape_cache.find_or_create(&key, |&(ref txt, is_rtl, ref options)| {
+            let mut glyphs = GlyphStore::new(txt.as_slice().char_len() as int, is_whitespace);
+            shaper.get_ref().shape_text(txt.as_slice(), is_rtl, options, &mut glyphs);
             Arc::new(glyphs)
         })
     }
 
     pub fn get_descriptor(&self) -> FontDescriptor {
-        FontDescriptor::new(self.style.clone(), SelectorPlatformIdentifier(self.handle.face_identifier()))
+        FontDescriptor::new(self.style.clone(), self.selector.clone())
+    }
+
+    /// Returns true if this font should render the given character: it has a glyph for it and, if
+    /// it is a web font, its `unicode-range` includes it.
+    pub fn covers_char(&self, ch: char) -> bool {
+        let in_range = match self.selector {
+            SelectorPlatformIdentifier(_) => true,
+            SelectorWebFont(ref data) => data.contains_char(ch),
+        };
+        in_range && self.glyph_index(ch).is_some()
     }
 
     pub fn glyph_index(&self, codepoint: char) -> Option<GlyphId> {
@@ -3,18 +3,29 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use font::{Font, FontDescriptor, FontGroup, FontHandleMethods, SelectorPlatformIdentifier};
-use font::{SpecifiedFontStyle, UsedFontStyle};
+use font::{SelectorWebFont, SpecifiedFontStyle, UsedFontStyle, WebFontData};
 use font_list::FontList;
 use platform::font::FontHandle;
 use platform::font_context::FontContextHandle;
+use text::bidi::{Level, is_rtl};
+use text::shaping::ShapingOptions;
+use text::text_run::{GlyphRun, TextRun};
 
 use azure::azure_hl::BackendType;
 use collections::hashmap::HashMap;
-use servo_util::cache::{Cache, LRUCache};
-use servo_util::time::ProfilerChan;
+use servo_util::cache::{Cache, HashLRUCache, LRUCache};
+use servo_util::font_prefs::FontPrefs;
+use servo_util::time::{CountMsg, ProfilerChan, ShapedRunCacheHitCounter};
+use servo_util::time::ShapedRunCacheMissCounter;
+use style::computed_values::{font_style, font_weight, text_decoration_line, word_break};
+use sync::Arc;
 
+use std::ascii::StrAsciiExt;
+use std::comm::Sender;
+use std::hash::{Hash, sip};
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::sync::atomics::{AtomicUint, Relaxed, SeqCst};
 
 /// Information needed to create a font context.
 #[deriving(Clone)]
@@ -25,10 +36,101 @@ pub struct FontContextInfo {
     /// Whether we need a font list.
     pub needs_font_list: bool,
 
+    /// The faces defined by `@font-face` rules.
+    pub web_fonts: Arc<Vec<WebFontFace>>,
+
+    /// A channel on which the URLs of `@font-face` sources that are needed but not yet downloaded
+    /// are sent, if web fonts are used.
+    pub web_font_requests: Option<Sender<String>>,
+
+    /// The generic family mappings, last resort families and font directories to use.
+    pub font_prefs: FontPrefs,
+
+    /// The shaped run cache hits and misses of every font context created from this information.
+    pub shaped_run_cache_counts: Arc<ShapedRunCacheCounts>,
+
     /// A channel up to the profiler.
     pub profiler_chan: ProfilerChan,
 }
 
+/// A face defined by an `@font-face` rule.
+#[deriving(Clone)]
+pub struct WebFontFace {
+    /// The name that `font-family` uses to select this face.
+    pub family: String,
+    pub weight: font_weight::T,
+    pub style: font_style::T,
+    /// Inclusive ranges of code points, from the rule's `unicode-range` descriptor.
+    pub unicode_range: Vec<(u32, u32)>,
+    /// The sources of the face that may be usable, in order of preference.
+    pub sources: Vec<WebFontFaceSource>,
+}
+
+#[deriving(Clone)]
+pub enum WebFontFaceSource {
+    /// An installed font family, named by `local()`. It is only usable if it is installed.
+    LocalFamilySource(String),
+    /// A font at a `url()` that has not been downloaded yet.
+    RemoteSource(String),
+    /// A font downloaded from a `url()`.
+    DownloadedSource(WebFontData),
+}
+
+/// The number of shaped text runs that each font context keeps for reuse by later layouts.
+static SHAPED_RUN_CACHE_SIZE: uint = 512;
+
+/// Everything that determines the glyphs of a shaped text run.
+#[deriving(Clone, Eq)]
+struct ShapedRunKey {
+    text: String,
+    font: FontDescriptor,
+    is_rtl: bool,
+    word_break: word_break::T,
+    options: ShapingOptions,
+}
+
+/// Font sizes are never NaN, so keys are always equal to themselves.
+impl TotalEq for ShapedRunKey {}
+
+/// Only the text and how it is shaped are hashed; runs of the same text in different fonts are
+/// told apart by comparing the keys.
+impl Hash for ShapedRunKey {
+    fn hash(&self, state: &mut sip::SipState) {
+        self.text.hash(state);
+        self.is_rtl.hash(state);
+        self.options.hash(state);
+    }
+}
+
+/// The number of lookups in the shaped run caches of a set of font contexts that found or did not
+/// find the run, since they were last reported to the profiler. Layout shares these between the
+/// font contexts of all of its threads so that it can report them once per pass.
+pub struct ShapedRunCacheCounts {
+    hits: AtomicUint,
+    misses: AtomicUint,
+}
+
+impl ShapedRunCacheCounts {
+    pub fn new() -> ShapedRunCacheCounts {
+        ShapedRunCacheCounts {
+            hits: AtomicUint::new(0),
+            misses: AtomicUint::new(0),
+        }
+    }
+
+    /// Sends the numbers of hits and misses since the last report to the profiler.
+    pub fn report(&self, profiler_chan: &ProfilerChan) {
+        let hits = self.hits.swap(0, SeqCst);
+        if hits > 0 {
+            profiler_chan.send(CountMsg(ShapedRunCacheHitCounter, hits));
+        }
+        let misses = self.misses.swap(0, SeqCst);
+        if misses > 0 {
+            profiler_chan.send(CountMsg(ShapedRunCacheMissCounter, misses));
+        }
+    }
+}
+
 pub trait FontContextHandleMethods {
     fn create_font_from_identifier(&self, String, UsedFontStyle) -> Result<FontHandle, ()>;
 }
@@ -36,10 +138,20 @@ pub trait FontContextHandleMethods {
 pub struct FontContext {
     pub instance_cache: LRUCache<FontDescriptor, Rc<RefCell<Font>>>,
     pub font_list: Option<FontList>, // only needed by layout
-    pub group_cache: LRUCache<SpecifiedFontStyle, Rc<RefCell<FontGroup>>>,
+    /// Font groups, keyed by style and the language of the text they render.
+    pub group_cache: LRUCache<(SpecifiedFontStyle, Option<String>), Rc<RefCell<FontGroup>>>,
     pub handle: FontContextHandle,
     pub backend: BackendType,
-    pub generic_fonts: HashMap<String,String>,
+    pub font_prefs: FontPrefs,
+    /// The families of the fallback fonts found for characters that no font in a group covers,
+    /// or `None` if no installed font covers the character.
+    pub fallback_families: HashMap<char, Option<String>>,
+    pub web_fonts: Arc<Vec<WebFontFace>>,
+    web_font_requests: Option<Sender<String>>,
+    /// The glyphs of recently shaped text runs, so that text that is laid out again does not need
+    /// to be shaped again.
+    shaped_run_cache: HashLRUCache<ShapedRunKey, Arc<Vec<GlyphRun>>>,
+    shaped_run_cache_counts: Arc<ShapedRunCacheCounts>,
     pub profiler_chan: ProfilerChan,
 }
 
@@ -47,41 +159,50 @@ impl FontContext {
     pub fn new(info: FontContextInfo) -> FontContext {
         let handle = FontContextHandle::new();
         let font_list = if info.needs_font_list {
-            Some(FontList::new(&handle, info.profiler_chan.clone()))
+            Some(FontList::new(&handle,
+                               info.font_prefs.font_directories.as_slice(),
+                               info.profiler_chan.clone()))
         } else {
             None
         };
 
-        // TODO: Allow users to specify these.
-        let mut generic_fonts = HashMap::with_capacity(5);
-        generic_fonts.insert("serif".to_string(), "Times New Roman".to_string());
-        generic_fonts.insert("sans-serif".to_string(), "Arial".to_string());
-        generic_fonts.insert("cursive".to_string(), "Apple Chancery".to_string());
-        generic_fonts.insert("fantasy".to_string(), "Papyrus".to_string());
-        generic_fonts.insert("monospace".to_string(), "Menlo".to_string());
-
         FontContext {
             instance_cache: LRUCache::new(10),
             font_list: font_list,
             group_cache: LRUCache::new(10),
             handle: handle,
             backend: info.backend,
-            generic_fonts: generic_fonts,
+            font_prefs: info.font_prefs.clone(),
+            fallback_families: HashMap::new(),
+            web_fonts: info.web_fonts.clone(),
+            web_font_requests: info.web_font_requests.clone(),
+            shaped_run_cache: HashLRUCache::new(SHAPED_RUN_CACHE_SIZE),
+            shaped_run_cache_counts: info.shaped_run_cache_counts.clone(),
             profiler_chan: info.profiler_chan.clone(),
         }
     }
 
     pub fn get_resolved_font_for_style(&mut self, style: &SpecifiedFontStyle)
                                        -> Rc<RefCell<FontGroup>> {
-        match self.group_cache.find(style) {
+        self.get_resolved_font_for_style_and_language(style, None)
+    }
+
+    /// Returns the font group for text in the given style and language. The language selects the
+    /// families that generic families map to.
+    pub fn get_resolved_font_for_style_and_language(&mut self,
+                                                    style: &SpecifiedFontStyle,
+                                                    language: Option<&String>)
+                                                    -> Rc<RefCell<FontGroup>> {
+        let key = (style.clone(), language.map(|language| language.clone()));
+        match self.group_cache.find(&key) {
             Some(fg) => {
                 debug!("font group cache hit");
                 fg
             },
             None => {
                 debug!("font group cache miss");
-                let fg = self.create_font_group(style);
-                self.group_cache.insert(style.clone(), fg.clone());
+                let fg = self.create_font_group(style, language);
+                self.group_cache.insert(key, fg.clone());
                 fg
             }
         }
@@ -107,22 +228,196 @@ impl FontContext {
         }
     }
 
-    fn transform_family(&self, family: &String) -> String {
+    /// Creates a text run for the given text with the font at the given index in the group,
+    /// reusing the glyphs of an identical run shaped earlier if there is one. Callers are
+    /// responsible for splitting text so that the font has glyphs for all of it; see
+    /// `find_font_index_for_char`.
+    pub fn create_textrun(&mut self,
+                          font_group: &Rc<RefCell<FontGroup>>,
+                          font_index: uint,
+                          text: String,
+                          decoration: text_decoration_line::T,
+                          bidi_level: Level,
+                          word_break: word_break::T,
+                          options: &ShapingOptions)
+                          -> TextRun {
+        let font = font_group.borrow().fonts.get(font_index).clone();
+        let mut font = font.borrow_mut();
+        let key = ShapedRunKey {
+            text: text.clone(),
+            font: font.get_descriptor(),
+            is_rtl: is_rtl(bidi_level),
+            word_break: word_break,
+            options: (*options).clone(),
+        };
+        let glyphs = match self.shaped_run_cache.find(&key) {
+            Some(glyphs) => {
+                self.shaped_run_cache_counts.hits.fetch_add(1, Relaxed);
+                glyphs
+            }
+            None => {
+                self.shaped_run_cache_counts.misses.fetch_add(1, Relaxed);
+                let glyphs = Arc::new(TextRun::break_and_shape(&mut *font,
+                                                               text.as_slice(),
+                                                               key.is_rtl,
+                                                               word_break,
+                                                               options));
+                self.shaped_run_cache.insert(key, glyphs.clone());
+                glyphs
+            }
+        };
+        TextRun::from_glyphs(&*font, text, decoration, bidi_level, glyphs)
+    }
+
+    /// Replaces the faces defined by `@font-face` rules. Cached font groups are dropped if the
+    /// faces changed, since their families may now resolve to other fonts.
+    pub fn set_web_fonts(&mut self, web_fonts: Arc<Vec<WebFontFace>>) {
+        // The faces are never mutated in place; any change, including a download finishing,
+        // replaces the whole list.
+        if &*web_fonts as *Vec<WebFontFace> == &*self.web_fonts as *Vec<WebFontFace> {
+            return
+        }
+        self.web_fonts = web_fonts;
+        self.group_cache.evict_all();
+    }
+
+    /// Returns the index of the font in the given group that should render the given character:
+    /// the first font that has a glyph for it. If no font in the group has one, a fallback font
+    /// chosen by the platform is appended to the group. Returns `None` if no installed font has a
+    /// glyph for the character.
+    pub fn find_font_index_for_char(&mut self, font_group: &Rc<RefCell<FontGroup>>, ch: char)
+                                    -> Option<uint> {
+        match font_group.borrow().find_font_index_for_char(ch) {
+            Some(index) => return Some(index),
+            None => {}
+        }
+
+        let style = font_group.borrow().style.clone();
+        match self.get_fallback_font_for_char(&style, ch) {
+            Some(font) => {
+                if font.borrow().handle.glyph_index(ch).is_none() {
+                    return None
+                }
+                let mut font_group = font_group.borrow_mut();
+                font_group.fonts.push(font);
+                Some(font_group.fonts.len() - 1)
+            }
+            None => None,
+        }
+    }
+
+    /// Returns a font in the given style from a family that the platform says has a glyph for the
+    /// given character.
+    fn get_fallback_font_for_char(&mut self, style: &SpecifiedFontStyle, ch: char)
+                                  -> Option<Rc<RefCell<Font>>> {
+        if !self.fallback_families.contains_key(&ch) {
+            let family = match self.font_list {
+                Some(ref font_list) => font_list.find_family_for_char(ch),
+                None => None,
+            };
+            self.fallback_families.insert(ch, family);
+        }
+        let family = self.fallback_families.get(&ch).clone();
+        let family = match family {
+            Some(family) => family,
+            None => return None,
+        };
+        debug!("(fallback font) using `{:s}` for U+{:04X}", family.as_slice(), ch as u32);
+
+        let font_desc = match self.font_list {
+            Some(ref mut font_list) => {
+                font_list.find_font_in_family(&family, style).map(|font_entry| {
+                    let font_id = SelectorPlatformIdentifier(font_entry.handle.face_identifier());
+                    FontDescriptor::new((*style).clone(), font_id)
+                })
+            }
+            None => None,
+        };
+        font_desc.and_then(|font_desc| self.get_font_by_descriptor(&font_desc).ok())
+    }
+
+    /// Returns descriptors for the `@font-face` faces of the given family that best match the given
+    /// style, in the order their rules appeared. More than one face matches if the family is split
+    /// into several `unicode-range`s. A face whose preferred source is still to be downloaded is
+    /// skipped, and its download is requested, so that only the faces that are used get fetched.
+    fn find_web_fonts(&mut self, family: &String, style: &SpecifiedFontStyle)
+                      -> Vec<FontDescriptor> {
+        let web_fonts = self.web_fonts.clone();
+        let faces: Vec<&WebFontFace> = web_fonts.iter().filter(|face| {
+            face.family.as_slice().eq_ignore_ascii_case(family.as_slice())
+        }).collect();
+
+        // TODO: Match weights and styles as CSS Fonts § 5.2 describes, rather than falling back to
+        // all of the family's faces when none has the exact style.
+        let matching_faces: Vec<&WebFontFace> = faces.iter().map(|face| *face).filter(|face| {
+            face.weight.is_bold() == style.weight.is_bold() && face.style == style.style
+        }).collect();
+        let faces = if matching_faces.is_empty() { faces } else { matching_faces };
+
+        let mut font_descs = vec!();
+        for face in faces.iter() {
+            for source in face.sources.iter() {
+                let selector = match *source {
+                    RemoteSource(ref url) => {
+                        match self.web_font_requests {
+                            Some(ref chan) => drop(chan.send_opt(url.clone())),
+                            None => {}
+                        }
+                        break
+                    }
+                    DownloadedSource(ref data) => Some(SelectorWebFont(data.clone())),
+                    LocalFamilySource(ref local_family) => {
+                        match self.font_list {
+                            Some(ref mut font_list) => {
+                                font_list.find_font_in_family(local_family, style).map(|entry| {
+                                    SelectorPlatformIdentifier(entry.handle.face_identifier())
+                                })
+                            }
+                            None => None,
+                        }
+                    }
+                };
+                match selector {
+                    Some(selector) => {
+                        font_descs.push(FontDescriptor::new((*style).clone(), selector));
+                        break
+                    }
+                    None => {}
+                }
+            }
+        }
+        font_descs
+    }
+
+    fn transform_family(&self, family: &String, language: Option<&String>) -> String {
         debug!("(transform family) searching for `{:s}`", family.as_slice());
-        match self.generic_fonts.find(family) {
+        let language = language.map(|language| language.as_slice());
+        match self.font_prefs.generic_family(family.as_slice(), language) {
             None => family.to_string(),
-            Some(mapped_family) => (*mapped_family).clone()
+            Some(mapped_family) => mapped_family,
         }
     }
 
-    fn create_font_group(&mut self, style: &SpecifiedFontStyle) -> Rc<RefCell<FontGroup>> {
+    fn create_font_group(&mut self, style: &SpecifiedFontStyle, language: Option<&String>)
+                         -> Rc<RefCell<FontGroup>> {
         let mut fonts = vec!();
 
         debug!("(create font group) --- starting ---");
 
         // TODO(Issue #193): make iteration over 'font-family' more robust.
         for family in style.families.iter() {
-            let transformed_family_name = self.transform_family(family);
+            // Families defined by `@font-face` rules take precedence over installed ones.
+            let web_font_descs = self.find_web_fonts(family, style);
+            if web_font_descs.len() > 0 {
+                debug!("(create font group) found web fonts for `{:s}`", family.as_slice());
+                for font_desc in web_font_descs.iter() {
+                    let instance = self.get_font_by_descriptor(font_desc);
+                    let _ = instance.map(|font| fonts.push(font.clone()));
+                }
+                continue
+            }
+
+            let transformed_family_name = self.transform_family(family, language);
             debug!("(create font group) transformed family is `{:s}`", transformed_family_name);
             let mut found = false;
 
@@ -159,7 +454,8 @@ impl FontContext {
         }
 
         if fonts.len() == 0 {
-            let last_resort = FontList::get_last_resort_font_families();
+            let mut last_resort = self.font_prefs.last_resort_families.clone();
+            last_resort.push_all_move(FontList::get_last_resort_font_families());
             for family in last_resort.iter() {
                 let font_desc = match self.font_list {
                     Some(ref mut font_list) => {
@@ -219,6 +515,13 @@ impl FontContext {
                                                               self.backend))))
                 })
             }
+            &SelectorWebFont(ref data) => {
+                Font::new_from_buffer(self,
+                                      (*data.bytes).clone(),
+                                      desc.selector.clone(),
+                                      &desc.style,
+                                      self.backend)
+            }
         };
     }
 }
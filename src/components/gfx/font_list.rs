@@ -18,6 +18,8 @@ pub type FontFamilyMap = HashMap<String, FontFamily>;
 trait FontListHandleMethods {
     fn get_available_families(&self, fctx: &FontContextHandle) -> FontFamilyMap;
     fn load_variations_for_family(&self, family: &mut FontFamily);
+    fn find_family_for_char(&self, ch: char) -> Option<String>;
+    fn add_font_directories(&self, directories: &[Path]);
     fn get_last_resort_font_families() -> Vec<String>;
 }
 
@@ -29,10 +31,16 @@ pub struct FontList {
 }
 
 impl FontList {
+    /// Creates the list of installed fonts, which also includes the fonts in the given
+    /// directories.
     pub fn new(fctx: &FontContextHandle,
+           font_directories: &[Path],
            prof_chan: ProfilerChan)
            -> FontList {
         let handle = FontListHandle::new(fctx);
+        if font_directories.len() > 0 {
+            handle.add_font_directories(font_directories);
+        }
         let mut list = FontList {
             handle: handle,
             family_map: HashMap::new(),
@@ -75,6 +83,12 @@ impl FontList {
         }
     }
 
+    /// Returns the name of a family with a font that has a glyph for the given character, for use
+    /// when no family in the `font-family` list has one.
+    pub fn find_family_for_char(&self, ch: char) -> Option<String> {
+        self.handle.find_family_for_char(ch)
+    }
+
     pub fn get_last_resort_font_families() -> Vec<String> {
         FontListHandle::get_last_resort_font_families()
     }
@@ -129,6 +129,22 @@ impl FontListHandle {
         }
     }
 
+    /// Returns the family of an installed font that has a glyph for the given character.
+    ///
+    /// TODO: Ask fontconfig, as on Linux.
+    pub fn find_family_for_char(&self, _: char) -> Option<String> {
+        None
+    }
+
+    /// Makes the fonts in the given directories available in addition to the installed ones.
+    ///
+    /// TODO: Add them to the application fonts of fontconfig, as on Linux.
+    pub fn add_font_directories(&self, directories: &[Path]) {
+        if !directories.is_empty() {
+            warn!("font directories are not supported on this platform")
+        }
+    }
+
     pub fn get_last_resort_font_families() -> Vec<String> {
         vec!("Roboto".to_string())
     }
@@ -8,7 +8,7 @@ extern crate freetype;
 extern crate fontconfig;
 
 use fontconfig::fontconfig::{
-    FcChar8, FcResultMatch, FcSetSystem, FcPattern,
+    FcChar8, FcChar32, FcCharSet, FcResultMatch, FcSetSystem, FcPattern,
     FcResultNoMatch, FcMatchPattern, FC_SLANT_ITALIC, FC_WEIGHT_BOLD, FC_SLANT_OBLIQUE
 };
 use fontconfig::fontconfig::{
@@ -16,7 +16,9 @@ use fontconfig::fontconfig::{
     FcPatternDestroy, FcFontSetDestroy, FcConfigSubstitute,
     FcDefaultSubstitute, FcPatternCreate, FcPatternAddString, FcPatternAddInteger,
     FcFontMatch, FcFontSetList, FcObjectSetCreate, FcObjectSetDestroy,
-    FcObjectSetAdd, FcPatternGetInteger
+    FcObjectSetAdd, FcPatternGetInteger, FcCharSetCreate, FcCharSetAddChar,
+    FcCharSetDestroy, FcCharSetHasChar, FcPatternAddCharSet, FcPatternGetCharSet,
+    FcConfigAppFontAddDir, FcFontSet, FcSetApplication
 };
 
 use style::computed_values::font_style;
@@ -32,6 +34,11 @@ use libc;
 use libc::{c_int, c_char};
 use std::ptr;
 use std::str;
+use sync::one::{Once, ONCE_INIT};
+
+/// Guards the fontconfig configuration, which is shared by every font list, against adding the
+/// font directories more than once.
+static mut ADD_FONT_DIRECTORIES: Once = ONCE_INIT;
 
 pub struct FontListHandle {
     pub fctx: FontContextHandle,
@@ -45,21 +52,21 @@ impl FontListHandle {
     pub fn get_available_families(&self) -> FontFamilyMap {
         let mut family_map : FontFamilyMap = HashMap::new();
         unsafe {
-            let config = FcConfigGetCurrent();
-            let fontSet = FcConfigGetFonts(config, FcSetSystem);
-            for i in range(0, (*fontSet).nfont as int) {
-                let font = (*fontSet).fonts.offset(i);
-                let family: *FcChar8 = ptr::null();
-                let mut v: c_int = 0;
-                "family".to_c_str().with_ref(|FC_FAMILY| {
-                    while FcPatternGetString(*font, FC_FAMILY, v, &family) == FcResultMatch {
-                        let family_name = str::raw::from_c_str(family as *c_char);
-                        debug!("Creating new FontFamily for family: {:s}", family_name);
-                        let new_family = FontFamily::new(family_name.as_slice());
-                        family_map.insert(family_name, new_family);
-                        v += 1;
-                    }
-                });
+            for &fontSet in get_font_sets().iter() {
+                for i in range(0, (*fontSet).nfont as int) {
+                    let font = (*fontSet).fonts.offset(i);
+                    let family: *FcChar8 = ptr::null();
+                    let mut v: c_int = 0;
+                    "family".to_c_str().with_ref(|FC_FAMILY| {
+                        while FcPatternGetString(*font, FC_FAMILY, v, &family) == FcResultMatch {
+                            let family_name = str::raw::from_c_str(family as *c_char);
+                            debug!("Creating new FontFamily for family: {:s}", family_name);
+                            let new_family = FontFamily::new(family_name.as_slice());
+                            family_map.insert(family_name, new_family);
+                            v += 1;
+                        }
+                    });
+                }
             }
         }
         return family_map;
@@ -69,8 +76,7 @@ impl FontListHandle {
         debug!("getting variations for {:?}", family);
         unsafe {
             let config = FcConfigGetCurrent();
-            let font_set = FcConfigGetFonts(config, FcSetSystem);
-            let font_set_array_ptr = &font_set;
+            let font_sets = get_font_sets();
             let pattern = FcPatternCreate();
             assert!(pattern.is_not_null());
             "family".to_c_str().with_ref(|FC_FAMILY| {
@@ -90,7 +96,11 @@ impl FontListHandle {
                 FcObjectSetAdd(object_set, FC_INDEX);
             });
 
-            let matches = FcFontSetList(config, font_set_array_ptr, 1, pattern, object_set);
+            let matches = FcFontSetList(config,
+                                        font_sets.as_ptr(),
+                                        font_sets.len() as c_int,
+                                        pattern,
+                                        object_set);
 
             debug!("found {} variations", (*matches).nfont);
 
@@ -131,11 +141,96 @@ impl FontListHandle {
         }
     }
 
+    /// Asks fontconfig for the family of an installed font that has a glyph for the given
+    /// character. Returns `None` if no installed font has one.
+    pub fn find_family_for_char(&self, ch: char) -> Option<String> {
+        unsafe {
+            let config = FcConfigGetCurrent();
+            let wrapper = AutoPattern { pattern: FcPatternCreate() };
+            let pattern = wrapper.pattern;
+
+            // The pattern takes its own reference to the character set.
+            let char_set = FcCharSetCreate();
+            assert!(char_set.is_not_null());
+            FcCharSetAddChar(char_set, ch as FcChar32);
+            let res = "charset".to_c_str().with_ref(|FC_CHARSET| {
+                FcPatternAddCharSet(pattern, FC_CHARSET, char_set)
+            });
+            FcCharSetDestroy(char_set);
+            if res != 1 {
+                debug!("adding charset to pattern failed");
+                return None;
+            }
+
+            if FcConfigSubstitute(config, pattern, FcMatchPattern) != 1 {
+                debug!("substitution failed");
+                return None;
+            }
+            FcDefaultSubstitute(pattern);
+            let result = FcResultNoMatch;
+            let result_wrapper = AutoPattern { pattern: FcFontMatch(config, pattern, &result) };
+            let result_pattern = result_wrapper.pattern;
+            if result != FcResultMatch || result_pattern.is_null() {
+                debug!("obtaining match to pattern failed");
+                return None;
+            }
+
+            // The best match is not guaranteed to cover the character.
+            let matched_char_set: *FcCharSet = ptr::null();
+            let res = "charset".to_c_str().with_ref(|FC_CHARSET| {
+                FcPatternGetCharSet(result_pattern, FC_CHARSET, 0, &matched_char_set)
+            });
+            if res != FcResultMatch || FcCharSetHasChar(matched_char_set, ch as FcChar32) == 0 {
+                debug!("no installed font has a glyph for U+{:04X}", ch as u32);
+                return None;
+            }
+
+            let family: *FcChar8 = ptr::null();
+            let res = "family".to_c_str().with_ref(|FC_FAMILY| {
+                FcPatternGetString(result_pattern, FC_FAMILY, 0, &family)
+            });
+            if res != FcResultMatch {
+                debug!("getting family for font failed");
+                return None;
+            }
+            Some(str::raw::from_c_str(family as *c_char))
+        }
+    }
+
+    /// Adds the fonts in the given directories to the application fonts of the fontconfig
+    /// configuration. Only the first call has any effect.
+    pub fn add_font_directories(&self, directories: &[Path]) {
+        unsafe {
+            ADD_FONT_DIRECTORIES.doit(|| {
+                let config = FcConfigGetCurrent();
+                for directory in directories.iter() {
+                    let res = directory.to_c_str().with_ref(|directory| {
+                        FcConfigAppFontAddDir(config, directory as *FcChar8)
+                    });
+                    if res == 0 {
+                        error!("couldn't add the fonts in {}", directory.display());
+                    }
+                }
+            })
+        }
+    }
+
     pub fn get_last_resort_font_families() -> Vec<String> {
         vec!("Arial".to_string())
     }
 }
 
+/// Returns the installed fonts and the fonts added from font directories, if any.
+unsafe fn get_font_sets() -> Vec<*FcFontSet> {
+    let config = FcConfigGetCurrent();
+    let mut font_sets = vec!(FcConfigGetFonts(config, FcSetSystem));
+    let application_fonts = FcConfigGetFonts(config, FcSetApplication);
+    if application_fonts.is_not_null() {
+        font_sets.push(application_fonts)
+    }
+    font_sets
+}
+
 struct AutoPattern {
     pattern: *FcPattern
 }
@@ -12,7 +12,6 @@ use font::{FontHandleMethods, FontMetrics, FontTableMethods};
 use font::FontTableTag;
 use font::{FractionalPixel, SpecifiedFontStyle};
 use servo_util::geometry::{Au, px_to_pt};
-use servo_util::geometry;
 use platform::macos::font_context::FontContextHandle;
 use text::glyph::GlyphId;
 use style::computed_values::font_weight;
@@ -160,18 +159,24 @@ impl FontHandleMethods for FontHandle {
 
         let scale = px_to_pt(self.ctfont.pt_size() as f64) / (self.ctfont.ascent() as f64 + self.ctfont.descent() as f64);
 
+        // Core Text doesn't expose the strikeout metrics, so center a line as thick as the
+        // underline at half the x-height, as the OS/2 table of most fonts does.
+        let underline_size = Au::from_pt(self.ctfont.underline_thickness() as f64);
+        let x_height = Au::from_pt(self.ctfont.x_height() as f64);
+        let strikeout_offset = x_height.scale_by(0.5) + underline_size.scale_by(0.5);
+
         let metrics =  FontMetrics {
-            underline_size:   Au::from_pt(self.ctfont.underline_thickness() as f64),
+            underline_size:   underline_size,
             // TODO(Issue #201): underline metrics are not reliable. Have to pull out of font table
             // directly.
             //
             // see also: https://bugs.webkit.org/show_bug.cgi?id=16768
             // see also: https://bugreports.qt-project.org/browse/QTBUG-13364
             underline_offset: Au::from_pt(self.ctfont.underline_position() as f64),
-            strikeout_size:   geometry::from_pt(0.0), // FIXME(Issue #942)
-            strikeout_offset: geometry::from_pt(0.0), // FIXME(Issue #942)
+            strikeout_size:   underline_size,
+            strikeout_offset: strikeout_offset,
             leading:          Au::from_pt(self.ctfont.leading() as f64),
-            x_height:         Au::from_pt(self.ctfont.x_height() as f64),
+            x_height:         x_height,
             em_size:          em_size,
             ascent:           ascent.scale_by(scale),
             descent:          descent.scale_by(scale),
@@ -3,7 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use font_context::FontContext;
-use style::computed_values::border_style;
+use style::computed_values::{border_style, text_decoration_style};
 
 use azure::azure_hl::{B8G8R8A8, Color, ColorPattern, DrawOptions, DrawSurfaceOptions, DrawTarget};
 use azure::azure_hl::{Linear, SourceOp, StrokeOptions};
@@ -43,6 +43,14 @@ enum DashSize {
     DashedBorder = 3
 }
 
+/// Which line of a text decoration is being drawn. This determines the side of the text that the
+/// second line of a `double` decoration goes on.
+pub enum TextDecorationKind {
+    UnderlineDecoration,
+    OverlineDecoration,
+    LineThroughDecoration,
+}
+
 impl<'a> RenderContext<'a>  {
     pub fn get_draw_target(&self) -> &'a DrawTarget {
         self.draw_target
@@ -76,6 +84,46 @@ impl<'a> RenderContext<'a>  {
         self.draw_line_segment(bounds, color, style);
     }
 
+    /// Draws a line of text decoration. The bounds are those of a solid line; the other styles
+    /// are drawn with the height of the bounds as their line thickness.
+    pub fn draw_text_decoration(&self,
+                                bounds: &Rect<Au>,
+                                kind: TextDecorationKind,
+                                color: Color,
+                                style: text_decoration_style::T) {
+        self.draw_target.make_current();
+
+        let thickness = SideOffsets2D::new_all_same(bounds.size.height).to_float_px();
+        match style {
+            text_decoration_style::solid => self.draw_solid_color(bounds, color),
+            text_decoration_style::double => {
+                // Two lines of the full thickness, with a gap of the same thickness between them.
+                // The second line goes away from the text: below an underline, above an overline,
+                // and a line-through is split into lines on both sides of its center.
+                let y = bounds.origin.y;
+                let line_height = bounds.size.height;
+                let (first_y, second_y) = match kind {
+                    UnderlineDecoration => (y, y + line_height.scale_by(2.0)),
+                    OverlineDecoration => (y, y - line_height.scale_by(2.0)),
+                    LineThroughDecoration => (y - line_height, y + line_height),
+                };
+                let mut first_bounds = bounds.clone();
+                first_bounds.origin.y = first_y;
+                let mut second_bounds = bounds.clone();
+                second_bounds.origin.y = second_y;
+                self.draw_solid_color(&first_bounds, color);
+                self.draw_solid_color(&second_bounds, color);
+            }
+            text_decoration_style::dotted => {
+                self.draw_dashed_border_segment(Top, bounds, thickness, color, DottedBorder);
+            }
+            text_decoration_style::dashed => {
+                self.draw_dashed_border_segment(Top, bounds, thickness, color, DashedBorder);
+            }
+            text_decoration_style::wavy => self.draw_wavy_line(bounds, color),
+        }
+    }
+
     pub fn draw_push_clip(&self, bounds: &Rect<Au>) {
         let rect = bounds.to_azure_rect();
         let path_builder = self.draw_target.create_path_builder();
@@ -299,6 +347,46 @@ impl<'a> RenderContext<'a>  {
                                      &draw_opts);
     }
 
+    /// Draws a zigzag line with the thickness of the height of the bounds. The line swings by one
+    /// thickness above and below the bounds, and is clipped to their horizontal extent.
+    fn draw_wavy_line(&self, bounds: &Rect<Au>, color: Color) {
+        let rect = bounds.to_azure_rect();
+        let thickness = if rect.size.height < 1.0 { 1.0 } else { rect.size.height };
+        let half_wavelength = thickness * 2.0;
+        let right = rect.origin.x + rect.size.width;
+
+        let mut clip_bounds = bounds.clone();
+        clip_bounds.origin.y = bounds.origin.y - bounds.size.height;
+        clip_bounds.size.height = bounds.size.height.scale_by(3.0);
+        self.draw_push_clip(&clip_bounds);
+
+        // Trace the top edge of the wave from left to right, then its bottom edge back.
+        let mut crests = vec!();
+        let mut x = rect.origin.x;
+        let mut up = true;
+        loop {
+            let y = if up { rect.origin.y - thickness } else { rect.origin.y + thickness };
+            crests.push(Point2D(x, y));
+            if x >= right {
+                break
+            }
+            x = x + half_wavelength;
+            up = !up;
+        }
+        let path_builder = self.draw_target.create_path_builder();
+        path_builder.move_to(*crests.get(0));
+        for crest in crests.iter().skip(1) {
+            path_builder.line_to(*crest);
+        }
+        for crest in crests.iter().rev() {
+            path_builder.line_to(*crest + Point2D(0.0, thickness));
+        }
+        let path = path_builder.finish();
+        self.draw_target.fill(&path, &ColorPattern(color), &DrawOptions(1.0, 0));
+
+        self.draw_pop_clip();
+    }
+
     fn draw_solid_border_segment(&self, direction: Direction, bounds: &Rect<Au>, border: SideOffsets2D<f32>, color: Color) {
         let rect = bounds.to_azure_rect();
         self.draw_border_path(rect, direction, border, color);
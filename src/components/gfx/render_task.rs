@@ -7,7 +7,7 @@
 use buffer_map::BufferMap;
 use display_list::optimizer::DisplayListOptimizer;
 use display_list::DisplayList;
-use font_context::{FontContext, FontContextInfo};
+use font_context::{FontContext, FontContextInfo, ShapedRunCacheCounts};
 use render_context::RenderContext;
 
 use azure::azure_hl::{B8G8R8A8, Color, DrawTarget, StolenGLResources};
@@ -18,7 +18,8 @@ use geom::size::Size2D;
 use layers::platform::surface::{NativePaintingGraphicsContext, NativeSurface};
 use layers::platform::surface::{NativeSurfaceMethods};
 use layers;
-use servo_msg::compositor_msg::{Epoch, IdleRenderState, LayerBuffer, LayerBufferSet, LayerId};
+use servo_msg::compositor_msg::{CpuContents, Epoch, IdleRenderState, LayerBuffer, LayerBufferSet};
+use servo_msg::compositor_msg::{LayerId, NativeSurfaceContents};
 use servo_msg::compositor_msg::{LayerMetadata, RenderListener, RenderingRenderState, ScrollPolicy};
 use servo_msg::constellation_msg::{ConstellationChan, Failure, FailureMsg, PipelineId};
 use servo_msg::constellation_msg::{RendererReadyMsg};
@@ -175,7 +176,9 @@ impl<C:RenderListener + Send> RenderTask<C> {
         builder.spawn(proc() {
 
             {
-                let cpu_painting = opts.cpu_painting;
+                // The headless compositor has no native graphics context, so tiles must be
+                // rendered on the CPU.
+                let cpu_painting = opts.cpu_painting || opts.headless;
 
                 let (worker_result_tx, worker_result_rx) = channel();
 
@@ -291,12 +294,21 @@ impl<C:RenderListener + Send> RenderTask<C> {
             let render_backend = self.opts.render_backend;
             let native_graphics_context = self.compositor.get_graphics_metadata().map(
                 |md| NativePaintingGraphicsContext::from_metadata(&md));
-            let native_graphics_context = native_graphics_context.expect("need native graphics context");
-            let native_graphics_context = Some(native_graphics_context);
+            match (graphics_context, &native_graphics_context) {
+                (GpuGraphicsContext, &None) => {
+                    fail!("need native graphics context to render on the GPU")
+                }
+                _ => {}
+            }
             let font_ctx_info = FontContextInfo {
                 backend: self.opts.render_backend,
                 needs_font_list: false,
+                // Text runs carry the data of any web fonts they use in their descriptors.
+                web_fonts: Arc::new(vec!()),
+                web_font_requests: None,
                 profiler_chan: self.profiler_chan.clone(),
+                font_prefs: self.opts.font_prefs.clone(),
+                shaped_run_cache_counts: Arc::new(ShapedRunCacheCounts::new()),
             };
             let profiler_chan = self.profiler_chan.clone();
             let buffer_map: BufferMap<Box<LayerBuffer>> = BufferMap::new(10000000);
@@ -309,13 +321,23 @@ impl<C:RenderListener + Send> RenderTask<C> {
                     let render_data = match render_msg {
                         WorkerRender(render_data) => render_data,
                         WorkerUnusedBuffer(buffer) => {
-                            buffer_map.insert(native_graphics_context.get_ref(), buffer);
+                            // Buffers with CPU contents are not worth recycling.
+                            match native_graphics_context {
+                                Some(ref native_graphics_context) => {
+                                    buffer_map.insert(native_graphics_context, buffer)
+                                }
+                                None => {}
+                            }
                             continue
                         }
                         WorkerExit(tx) => {
                             // Cleanup and tell the RenderTask we're done
-                            buffer_map.clear(native_graphics_context.get_ref());
-                            drop(native_graphics_context.take_unwrap());
+                            match native_graphics_context.take() {
+                                Some(native_graphics_context) => {
+                                    buffer_map.clear(&native_graphics_context)
+                                }
+                                None => {}
+                            }
                             tx.send(());
                             break
                         }
@@ -390,15 +412,31 @@ impl<C:RenderListener + Send> RenderTask<C> {
                     // FIXME(pcwalton): We should supply the texture and native surface *to* the
                     // draw target in GPU rendering mode, so that it doesn't have to recreate it.
                     let buffer = match graphics_context {
+                        CpuGraphicsContext if native_graphics_context.is_none() => {
+                            // There is nothing to upload to, so hand the pixels over as they are.
+                            let mut pixels = Vec::with_capacity(width * height * 4);
+                            draw_target.snapshot().get_data_surface().with_data(|data| {
+                                pixels.push_all(data);
+                            });
+
+                            box LayerBuffer {
+                                contents: CpuContents(pixels),
+                                rect: tile.page_rect,
+                                screen_pos: tile.screen_rect,
+                                resolution: scale,
+                                stride: (width * 4) as uint,
+                                render_idx: render_idx
+                            }
+                        }
                         CpuGraphicsContext => {
                             let maybe_buffer = buffer_map.find(tile.screen_rect.size);
-                            let buffer = match maybe_buffer {
+                            let mut buffer = match maybe_buffer {
                                 Some(buffer) => {
                                     let mut buffer = buffer;
                                     buffer.rect = tile.page_rect;
                                     buffer.screen_pos = tile.screen_rect;
                                     buffer.resolution = scale;
-                                    buffer.native_surface.mark_wont_leak();
+                                    buffer.contents.mark_wont_leak();
                                     buffer
                                 }
                                 None => {
@@ -412,7 +450,7 @@ impl<C:RenderListener + Send> RenderTask<C> {
                                     native_surface.mark_wont_leak();
 
                                     box LayerBuffer {
-                                        native_surface: native_surface,
+                                        contents: NativeSurfaceContents(native_surface),
                                         rect: tile.page_rect,
                                         screen_pos: tile.screen_rect,
                                         resolution: scale,
@@ -423,9 +461,15 @@ impl<C:RenderListener + Send> RenderTask<C> {
                             };
 
                             draw_target.snapshot().get_data_surface().with_data(|data| {
-                                buffer.native_surface.upload(native_graphics_context.get_ref(), data);
-                                debug!("RENDERER uploading to native surface {:d}",
-                                       buffer.native_surface.get_id() as int);
+                                match buffer.contents {
+                                    NativeSurfaceContents(ref mut native_surface) => {
+                                        native_surface.upload(native_graphics_context.get_ref(),
+                                                              data);
+                                        debug!("RENDERER uploading to native surface {:d}",
+                                               native_surface.get_id() as int);
+                                    }
+                                    CpuContents(_) => fail!("can't upload to CPU contents"),
+                                }
                             });
 
                             buffer
@@ -443,7 +487,7 @@ impl<C:RenderListener + Send> RenderTask<C> {
                             native_surface.mark_wont_leak();
 
                             box LayerBuffer {
-                                native_surface: native_surface,
+                                contents: NativeSurfaceContents(native_surface),
                                 rect: tile.page_rect,
                                 screen_pos: tile.screen_rect,
                                 resolution: scale,
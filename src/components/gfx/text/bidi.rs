@@ -0,0 +1,492 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An implementation of the Unicode Bidirectional Algorithm (UAX #9).
+//!
+//! The algorithm runs over a sequence of bidi classes rather than over text. This lets layout
+//! inject the explicit embeddings and overrides established by the CSS `unicode-bidi` property
+//! (CSS 2.1 § 9.10) as `LRE`/`RLE`/`LRO`/`RLO`/`PDF` classes without inserting control characters
+//! into the text runs themselves.
+//!
+//! Directional isolates (UAX #9 6.3) are not supported; level runs are treated as isolating run
+//! sequences.
+
+use std::cmp;
+
+/// A bidi embedding level. Even levels are left-to-right; odd levels are right-to-left.
+pub type Level = u8;
+
+/// The paragraph embedding level for left-to-right paragraphs.
+pub static LTR_LEVEL: Level = 0;
+
+/// The paragraph embedding level for right-to-left paragraphs.
+pub static RTL_LEVEL: Level = 1;
+
+/// The maximum explicit embedding depth (UAX #9 BD2).
+pub static MAX_DEPTH: Level = 61;
+
+/// Returns true if the given level is a right-to-left level.
+#[inline]
+pub fn is_rtl(level: Level) -> bool {
+    level & 1 == 1
+}
+
+/// The bidirectional character types of UAX #9, Table 4.
+#[deriving(Clone, Eq, Show)]
+pub enum BidiClass {
+    // Strong types.
+    L,
+    R,
+    AL,
+    // Weak types.
+    EN,
+    ES,
+    ET,
+    AN,
+    CS,
+    NSM,
+    BN,
+    // Neutral types.
+    B,
+    S,
+    WS,
+    ON,
+    // Explicit formatting types.
+    LRE,
+    LRO,
+    RLE,
+    RLO,
+    PDF,
+}
+
+/// Ranges of code points with a bidi class other than `L`, sorted by code point.
+///
+/// This is an abridged form of `DerivedBidiClass.txt` that covers the Latin, Hebrew, Arabic,
+/// Syriac, Thaana, N'Ko and general punctuation blocks along with the formatting characters. Code
+/// points not listed here are treated as `L`.
+static BIDI_CLASS_TABLE: &'static [(char, char, BidiClass)] = &[
+    ('\x00', '\x08', BN), ('\x09', '\x09', S), ('\x0a', '\x0a', B), ('\x0b', '\x0b', S),
+    ('\x0c', '\x0c', WS), ('\x0d', '\x0d', B), ('\x0e', '\x1b', BN), ('\x1c', '\x1e', B),
+    ('\x1f', '\x1f', S), ('\x20', '\x20', WS), ('\x21', '\x22', ON), ('\x23', '\x25', ET),
+    ('\x26', '\x2a', ON), ('\x2b', '\x2b', ES), ('\x2c', '\x2c', CS), ('\x2d', '\x2d', ES),
+    ('\x2e', '\x2f', CS), ('\x30', '\x39', EN), ('\x3a', '\x3a', CS), ('\x3b', '\x40', ON),
+    ('\x5b', '\x60', ON), ('\x7b', '\x7e', ON), ('\x7f', '\x84', BN), ('\x85', '\x85', B),
+    ('\x86', '\x9f', BN), ('\xa0', '\xa0', CS), ('\xa1', '\xa1', ON), ('\xa2', '\xa5', ET),
+    ('\xa6', '\xa9', ON), ('\xab', '\xac', ON), ('\xad', '\xad', BN), ('\xae', '\xaf', ON),
+    ('\xb0', '\xb1', ET), ('\xb2', '\xb3', EN), ('\xb4', '\xb4', ON), ('\xb6', '\xb8', ON),
+    ('\xb9', '\xb9', EN), ('\xbb', '\xbf', ON), ('\xd7', '\xd7', ON), ('\xf7', '\xf7', ON),
+    ('\u0300', '\u036f', NSM), ('\u0483', '\u0489', NSM),
+    ('\u0591', '\u05bd', NSM), ('\u05be', '\u05be', R), ('\u05bf', '\u05bf', NSM),
+    ('\u05c0', '\u05c0', R), ('\u05c1', '\u05c2', NSM), ('\u05c3', '\u05c3', R),
+    ('\u05c4', '\u05c5', NSM), ('\u05c6', '\u05c6', R), ('\u05c7', '\u05c7', NSM),
+    ('\u05c8', '\u05ff', R), ('\u0600', '\u0605', AN), ('\u0606', '\u0607', ON),
+    ('\u0608', '\u0608', AL), ('\u0609', '\u060a', ET), ('\u060b', '\u060b', AL),
+    ('\u060c', '\u060c', CS), ('\u060d', '\u060d', AL), ('\u060e', '\u060f', ON),
+    ('\u0610', '\u061a', NSM), ('\u061b', '\u064a', AL), ('\u064b', '\u065f', NSM),
+    ('\u0660', '\u0669', AN), ('\u066a', '\u066a', ET), ('\u066b', '\u066c', AN),
+    ('\u066d', '\u066f', AL), ('\u0670', '\u0670', NSM), ('\u0671', '\u06d5', AL),
+    ('\u06d6', '\u06dc', NSM), ('\u06dd', '\u06dd', AN), ('\u06de', '\u06de', ON),
+    ('\u06df', '\u06e4', NSM), ('\u06e5', '\u06e6', AL), ('\u06e7', '\u06e8', NSM),
+    ('\u06e9', '\u06e9', ON), ('\u06ea', '\u06ed', NSM), ('\u06ee', '\u06ef', AL),
+    ('\u06f0', '\u06f9', EN), ('\u06fa', '\u0710', AL), ('\u0711', '\u0711', NSM),
+    ('\u0712', '\u072f', AL), ('\u0730', '\u074a', NSM), ('\u074b', '\u07a5', AL),
+    ('\u07a6', '\u07b0', NSM), ('\u07b1', '\u07bf', AL), ('\u07c0', '\u07ea', R),
+    ('\u07eb', '\u07f3', NSM), ('\u07f4', '\u07f5', R), ('\u07f6', '\u07f9', ON),
+    ('\u07fa', '\u0815', R), ('\u0816', '\u082d', NSM), ('\u082e', '\u089f', R),
+    ('\u08a0', '\u08e3', AL), ('\u08e4', '\u08ff', NSM),
+    ('\u2000', '\u200a', WS), ('\u200b', '\u200d', BN), ('\u200f', '\u200f', R),
+    ('\u2010', '\u2027', ON), ('\u2028', '\u2028', WS), ('\u2029', '\u2029', B),
+    ('\u202a', '\u202a', LRE), ('\u202b', '\u202b', RLE), ('\u202c', '\u202c', PDF),
+    ('\u202d', '\u202d', LRO), ('\u202e', '\u202e', RLO), ('\u202f', '\u202f', CS),
+    ('\u2030', '\u2034', ET), ('\u2035', '\u2043', ON), ('\u2044', '\u2044', CS),
+    ('\u2045', '\u205e', ON), ('\u205f', '\u205f', WS), ('\u2060', '\u206f', BN),
+    ('\u2070', '\u2070', EN), ('\u2074', '\u2079', EN), ('\u207a', '\u207b', ES),
+    ('\u207c', '\u207e', ON), ('\u2080', '\u2089', EN), ('\u208a', '\u208b', ES),
+    ('\u208c', '\u208e', ON), ('\u20a0', '\u20cf', ET), ('\u20d0', '\u20f0', NSM),
+    ('\u2190', '\u2211', ON), ('\u2212', '\u2212', ES), ('\u2213', '\u2213', ET),
+    ('\u2214', '\u2335', ON), ('\u237b', '\u2394', ON), ('\u2396', '\u2487', ON),
+    ('\u2488', '\u249b', EN), ('\u24ea', '\u26ab', ON), ('\u26ad', '\u27ff', ON),
+    ('\u2900', '\u2bff', ON), ('\u3000', '\u3000', WS), ('\u3001', '\u3004', ON),
+    ('\u3008', '\u3020', ON), ('\ufb1d', '\ufb1d', R), ('\ufb1e', '\ufb1e', NSM),
+    ('\ufb1f', '\ufb28', R), ('\ufb29', '\ufb29', ES), ('\ufb2a', '\ufb4f', R),
+    ('\ufb50', '\ufd3d', AL), ('\ufd3e', '\ufd3f', ON), ('\ufd40', '\ufdff', AL),
+    ('\ufe00', '\ufe0f', NSM), ('\ufe20', '\ufe2f', NSM), ('\ufe50', '\ufe50', CS),
+    ('\ufe51', '\ufe51', ON), ('\ufe52', '\ufe52', CS), ('\ufe54', '\ufe54', ON),
+    ('\ufe55', '\ufe55', CS), ('\ufe56', '\ufe5e', ON), ('\ufe5f', '\ufe5f', ET),
+    ('\ufe60', '\ufe61', ON), ('\ufe62', '\ufe63', ES), ('\ufe64', '\ufe68', ON),
+    ('\ufe69', '\ufe6a', ET), ('\ufe6b', '\ufe6b', ON), ('\ufe70', '\ufefe', AL),
+    ('\ufeff', '\ufeff', BN), ('\uff01', '\uff02', ON), ('\uff03', '\uff05', ET),
+    ('\uff06', '\uff0a', ON), ('\uff0b', '\uff0b', ES), ('\uff0c', '\uff0c', CS),
+    ('\uff0d', '\uff0d', ES), ('\uff0e', '\uff0f', CS), ('\uff10', '\uff19', EN),
+    ('\uff1a', '\uff1a', CS), ('\uff1b', '\uff20', ON), ('\uff3b', '\uff40', ON),
+    ('\uff5b', '\uff65', ON), ('\uffe0', '\uffe1', ET), ('\uffe2', '\uffe4', ON),
+    ('\uffe5', '\uffe6', ET), ('\uffe8', '\uffee', ON), ('\ufff9', '\ufffd', ON),
+    ('\U00010800', '\U00010fff', R), ('\U0001e800', '\U0001edff', R),
+    ('\U0001ee00', '\U0001eeff', AL), ('\U0001ef00', '\U0001efff', R),
+];
+
+/// Returns the bidi class of the given character.
+pub fn bidi_class(ch: char) -> BidiClass {
+    let (mut low, mut high) = (0u, BIDI_CLASS_TABLE.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        let (first, last, class) = BIDI_CLASS_TABLE[mid];
+        if ch < first {
+            high = mid
+        } else if ch > last {
+            low = mid + 1
+        } else {
+            return class
+        }
+    }
+    L
+}
+
+/// Returns the strong class corresponding to the direction of the given level.
+#[inline]
+fn direction_of_level(level: Level) -> BidiClass {
+    if is_rtl(level) { R } else { L }
+}
+
+/// Returns true if the given class is removed from the paragraph by rule X9.
+#[inline]
+fn is_removed_by_x9(class: BidiClass) -> bool {
+    match class {
+        LRE | RLE | LRO | RLO | PDF | BN => true,
+        _ => false,
+    }
+}
+
+/// Returns true if the given class is treated as a neutral by rules N1 and N2.
+#[inline]
+fn is_neutral(class: BidiClass) -> bool {
+    match class {
+        B | S | WS | ON => true,
+        _ => false,
+    }
+}
+
+/// Resolves the embedding level of every entry of `classes` within a single paragraph whose
+/// embedding level is `paragraph_level`, per UAX #9 rules X1-I2.
+///
+/// Entries removed by rule X9 (explicit formatting codes and boundary neutrals) receive the level
+/// of the preceding entry so that callers can slice the result uniformly.
+pub fn resolve_levels(classes: &[BidiClass], paragraph_level: Level) -> Vec<Level> {
+    let mut levels = Vec::from_elem(classes.len(), paragraph_level);
+    let mut types: Vec<BidiClass> = classes.iter().map(|class| *class).collect();
+
+    // X1-X8: Explicit levels and directions.
+    {
+        let mut stack: Vec<(Level, Option<BidiClass>)> = vec!((paragraph_level, None));
+        let mut overflow_count = 0u;
+        for i in range(0, classes.len()) {
+            let (embedding_level, override_status) = *stack.last().unwrap();
+            match classes[i] {
+                RLE | LRE | RLO | LRO => {
+                    let next_level = match classes[i] {
+                        RLE | RLO => (embedding_level + 1) | 1,
+                        _ => (embedding_level + 2) & !1,
+                    };
+                    if next_level <= MAX_DEPTH && overflow_count == 0 {
+                        let next_override = match classes[i] {
+                            RLO => Some(R),
+                            LRO => Some(L),
+                            _ => None,
+                        };
+                        stack.push((next_level, next_override))
+                    } else {
+                        overflow_count += 1
+                    }
+                    *levels.get_mut(i) = embedding_level;
+                }
+                PDF => {
+                    if overflow_count > 0 {
+                        overflow_count -= 1
+                    } else if stack.len() > 1 {
+                        drop(stack.pop())
+                    }
+                    *levels.get_mut(i) = embedding_level;
+                }
+                B => *levels.get_mut(i) = paragraph_level,
+                class => {
+                    *levels.get_mut(i) = embedding_level;
+                    match (class, override_status) {
+                        (BN, _) | (_, None) => {}
+                        (_, Some(direction)) => *types.get_mut(i) = direction,
+                    }
+                }
+            }
+        }
+    }
+
+    // X9: Remove explicit formatting codes and boundary neutrals from consideration.
+    let indices: Vec<uint> = range(0, classes.len()).filter(|&i| {
+        !is_removed_by_x9(*types.get(i))
+    }).collect();
+
+    // X10: Resolve each level run separately.
+    let mut run_start = 0;
+    while run_start < indices.len() {
+        let run_level = *levels.get(*indices.get(run_start));
+        let mut run_end = run_start + 1;
+        while run_end < indices.len() && *levels.get(*indices.get(run_end)) == run_level {
+            run_end += 1
+        }
+
+        let previous_level = if run_start == 0 {
+            paragraph_level
+        } else {
+            *levels.get(*indices.get(run_start - 1))
+        };
+        let next_level = if run_end == indices.len() {
+            paragraph_level
+        } else {
+            *levels.get(*indices.get(run_end))
+        };
+        let sos = direction_of_level(cmp::max(previous_level, run_level));
+        let eos = direction_of_level(cmp::max(next_level, run_level));
+
+        let run = indices.slice(run_start, run_end);
+        resolve_weak_types(run, &mut types, sos);
+        resolve_neutral_types(run, &mut types, sos, eos, run_level);
+        resolve_implicit_levels(run, types.as_slice(), &mut levels);
+
+        run_start = run_end
+    }
+
+    // L1: Reset segment separators, paragraph separators and trailing whitespace to the paragraph
+    // level. Line breaks are not known here, so callers must also reset the whitespace at the end
+    // of each line once the paragraph has been broken into lines.
+    let mut trailing = true;
+    for i in range(0, classes.len()).rev() {
+        match classes[i] {
+            S | B => {
+                *levels.get_mut(i) = paragraph_level;
+                trailing = true
+            }
+            WS | LRE | RLE | LRO | RLO | PDF | BN if trailing => {
+                *levels.get_mut(i) = paragraph_level
+            }
+            _ => trailing = false,
+        }
+    }
+
+    // Give removed entries the level of their predecessor, per the note on X9.
+    for i in range(0, classes.len()) {
+        if is_removed_by_x9(*types.get(i)) {
+            *levels.get_mut(i) = if i == 0 { paragraph_level } else { *levels.get(i - 1) }
+        }
+    }
+
+    levels
+}
+
+/// Applies rules W1-W7 to a level run.
+fn resolve_weak_types(run: &[uint], types: &mut Vec<BidiClass>, sos: BidiClass) {
+    // W1: Non-spacing marks take the type of the previous character.
+    let mut previous = sos;
+    for &i in run.iter() {
+        if *types.get(i) == NSM {
+            *types.get_mut(i) = previous
+        }
+        previous = *types.get(i)
+    }
+
+    // W2: European numbers after Arabic letters become Arabic numbers. W3: Arabic letters become
+    // R.
+    let mut last_strong = sos;
+    for &i in run.iter() {
+        match *types.get(i) {
+            EN if last_strong == AL => *types.get_mut(i) = AN,
+            L | R => last_strong = *types.get(i),
+            AL => last_strong = AL,
+            _ => {}
+        }
+    }
+    for &i in run.iter() {
+        if *types.get(i) == AL {
+            *types.get_mut(i) = R
+        }
+    }
+
+    // W4: A single separator between two numbers of the same type becomes that type.
+    for j in range(1, cmp::max(run.len(), 1) - 1) {
+        let (before, here, after) = (*types.get(run[j - 1]), *types.get(run[j]), *types.get(run[j + 1]));
+        match (before, here, after) {
+            (EN, ES, EN) | (EN, CS, EN) => *types.get_mut(run[j]) = EN,
+            (AN, CS, AN) => *types.get_mut(run[j]) = AN,
+            _ => {}
+        }
+    }
+
+    // W5: Sequences of European terminators adjacent to European numbers become European numbers.
+    let mut j = 0;
+    while j < run.len() {
+        if *types.get(run[j]) != ET {
+            j += 1;
+            continue
+        }
+        let start = j;
+        while j < run.len() && *types.get(run[j]) == ET {
+            j += 1
+        }
+        let adjacent_to_number = (start > 0 && *types.get(run[start - 1]) == EN) ||
+            (j < run.len() && *types.get(run[j]) == EN);
+        if adjacent_to_number {
+            for k in range(start, j) {
+                *types.get_mut(run[k]) = EN
+            }
+        }
+    }
+
+    // W6: Remaining separators and terminators become Other Neutral.
+    for &i in run.iter() {
+        match *types.get(i) {
+            ES | ET | CS => *types.get_mut(i) = ON,
+            _ => {}
+        }
+    }
+
+    // W7: European numbers in a left-to-right context become L.
+    let mut last_strong = sos;
+    for &i in run.iter() {
+        match *types.get(i) {
+            EN if last_strong == L => *types.get_mut(i) = L,
+            L | R => last_strong = *types.get(i),
+            _ => {}
+        }
+    }
+}
+
+/// Applies rules N1 and N2 to a level run.
+fn resolve_neutral_types(run: &[uint],
+                         types: &mut Vec<BidiClass>,
+                         sos: BidiClass,
+                         eos: BidiClass,
+                         level: Level) {
+    // European and Arabic numbers act as R for the purposes of N1.
+    fn strong_direction(class: BidiClass) -> BidiClass {
+        match class {
+            L => L,
+            _ => R,
+        }
+    }
+
+    let embedding_direction = direction_of_level(level);
+    let mut j = 0;
+    while j < run.len() {
+        if !is_neutral(*types.get(run[j])) {
+            j += 1;
+            continue
+        }
+        let start = j;
+        while j < run.len() && is_neutral(*types.get(run[j])) {
+            j += 1
+        }
+        let before = if start == 0 { sos } else { strong_direction(*types.get(run[start - 1])) };
+        let after = if j == run.len() { eos } else { strong_direction(*types.get(run[j])) };
+        let resolved = if before == after { before } else { embedding_direction };
+        for k in range(start, j) {
+            *types.get_mut(run[k]) = resolved
+        }
+    }
+}
+
+/// Applies rules I1 and I2 to a level run.
+fn resolve_implicit_levels(run: &[uint], types: &[BidiClass], levels: &mut Vec<Level>) {
+    for &i in run.iter() {
+        let level = *levels.get(i);
+        let increment = match (is_rtl(level), types[i]) {
+            (false, R) => 1,
+            (false, AN) | (false, EN) => 2,
+            (true, L) | (true, EN) | (true, AN) => 1,
+            _ => 0,
+        };
+        *levels.get_mut(i) = level + increment
+    }
+}
+
+/// Returns the logical indices of `levels` in visual order, per UAX #9 rule L2: from the highest
+/// level down to the lowest odd level, every maximal sequence at that level or higher is reversed.
+pub fn reorder_visually(levels: &[Level]) -> Vec<uint> {
+    let mut order: Vec<uint> = range(0, levels.len()).collect();
+    if levels.is_empty() {
+        return order
+    }
+
+    let highest = *levels.iter().max().unwrap();
+    let lowest_odd = match levels.iter().filter(|level| is_rtl(**level)).min() {
+        None => return order,
+        Some(level) => *level,
+    };
+
+    let mut level = highest;
+    while level >= lowest_odd {
+        let mut i = 0;
+        while i < levels.len() {
+            if levels[*order.get(i)] < level {
+                i += 1;
+                continue
+            }
+            let start = i;
+            while i < levels.len() && levels[*order.get(i)] >= level {
+                i += 1
+            }
+            order.mut_slice(start, i).reverse();
+        }
+        if level == 0 {
+            break
+        }
+        level -= 1
+    }
+    order
+}
+
+#[cfg(test)]
+fn levels_for_str(text: &str, paragraph_level: Level) -> Vec<Level> {
+    let classes: Vec<BidiClass> = text.chars().map(bidi_class).collect();
+    resolve_levels(classes.as_slice(), paragraph_level)
+}
+
+#[test]
+fn test_bidi_class() {
+    assert_eq!(bidi_class('a'), L);
+    assert_eq!(bidi_class('7'), EN);
+    assert_eq!(bidi_class(' '), WS);
+    assert_eq!(bidi_class('\u05d0'), R);
+    assert_eq!(bidi_class('\u0627'), AL);
+    assert_eq!(bidi_class('\u0661'), AN);
+    assert_eq!(bidi_class('\u202e'), RLO);
+    assert_eq!(bidi_class('\u4e00'), L);
+}
+
+#[test]
+fn test_resolve_levels_ltr() {
+    assert_eq!(levels_for_str("abc def", LTR_LEVEL), vec!(0, 0, 0, 0, 0, 0, 0));
+    // Hebrew inside a left-to-right paragraph is raised to level 1, including the space between
+    // the two Hebrew words.
+    assert_eq!(levels_for_str("a א ב b", LTR_LEVEL), vec!(0, 0, 1, 1, 1, 0, 0));
+}
+
+#[test]
+fn test_resolve_levels_rtl() {
+    // Numbers in a right-to-left paragraph end up at level 2; trailing whitespace is reset.
+    assert_eq!(levels_for_str("א 12 ", RTL_LEVEL), vec!(1, 1, 2, 2, 1));
+    // Latin text in a right-to-left paragraph is raised to level 2.
+    assert_eq!(levels_for_str("abא", RTL_LEVEL), vec!(2, 2, 1));
+}
+
+#[test]
+fn test_resolve_levels_override() {
+    let classes = vec!(RLO, L, L, PDF, L);
+    assert_eq!(resolve_levels(classes.as_slice(), LTR_LEVEL), vec!(0, 1, 1, 1, 0));
+}
+
+#[test]
+fn test_reorder_visually() {
+    assert_eq!(reorder_visually([0, 0, 0]), vec!(0, 1, 2));
+    assert_eq!(reorder_visually([1, 1, 1]), vec!(2, 1, 0));
+    assert_eq!(reorder_visually([0, 1, 1, 0]), vec!(0, 2, 1, 3));
+    assert_eq!(reorder_visually([1, 2, 2, 1]), vec!(3, 1, 2, 0));
+}
@@ -0,0 +1,329 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! An implementation of the Unicode Line Breaking Algorithm (UAX #14).
+//!
+//! This finds the line break opportunities in a string using the pair rules of UAX #14 § 6.1.
+//! The `word-break` property (CSS Text Level 3 § 5.2) is applied by adjusting the break classes
+//! before the rules run. Hangul jamo sequences (LB26, LB27), regional indicators (LB30a) and
+//! Hebrew letters (LB21a, LB21b) are not treated specially; see `BREAK_CLASS_TABLE`.
+
+use style::computed_values::word_break;
+
+/// The line breaking classes of UAX #14, Table 1, after the resolution of LB1.
+#[deriving(Clone, Eq, Show)]
+pub enum BreakClass {
+    // Non-tailorable line breaking classes.
+    BK,
+    CR,
+    LF,
+    CM,
+    NL,
+    WJ,
+    ZW,
+    GL,
+    SP,
+    // Break opportunities.
+    B2,
+    BA,
+    BB,
+    HY,
+    // Characters prohibiting certain breaks.
+    CL,
+    CP,
+    EX,
+    IN,
+    NS,
+    OP,
+    QU,
+    // Numeric context.
+    IS,
+    NU,
+    PO,
+    PR,
+    SY,
+    // Other characters.
+    AL,
+    ID,
+}
+
+/// Ranges of code points with a break class other than `AL`, sorted by code point.
+///
+/// This is an abridged form of `LineBreak.txt` that covers Latin, Greek, Cyrillic, Hebrew, Arabic
+/// and the CJK blocks along with punctuation and formatting characters. Code points not listed
+/// here are treated as `AL`. Following LB1, `AI`, `SA`, `SG` and `XX` are resolved to `AL`, and
+/// `HL` is folded into `AL`. Conditional Japanese starters (`CJ`) and Hangul syllables and jamo
+/// are treated as `ID`, as in the `normal` value of the CSS `line-break` property.
+static BREAK_CLASS_TABLE: &'static [(char, char, BreakClass)] = &[
+    ('\x00', '\x08', CM), ('\x09', '\x09', BA), ('\x0a', '\x0a', LF), ('\x0b', '\x0c', BK),
+    ('\x0d', '\x0d', CR), ('\x0e', '\x1f', CM), ('\x20', '\x20', SP), ('\x21', '\x21', EX),
+    ('\x22', '\x22', QU), ('\x24', '\x24', PR), ('\x25', '\x25', PO), ('\x27', '\x27', QU),
+    ('\x28', '\x28', OP), ('\x29', '\x29', CP), ('\x2b', '\x2b', PR), ('\x2c', '\x2c', IS),
+    ('\x2d', '\x2d', HY), ('\x2e', '\x2e', IS), ('\x2f', '\x2f', SY), ('\x30', '\x39', NU),
+    ('\x3a', '\x3b', IS), ('\x3f', '\x3f', EX), ('\x5b', '\x5b', OP), ('\x5c', '\x5c', PR),
+    ('\x5d', '\x5d', CP), ('\x7b', '\x7b', OP), ('\x7d', '\x7d', CL), ('\x7f', '\x84', CM),
+    ('\x85', '\x85', NL), ('\x86', '\x9f', CM), ('\xa0', '\xa0', GL), ('\xa1', '\xa1', OP),
+    ('\xa2', '\xa2', PO), ('\xa3', '\xa5', PR), ('\xab', '\xab', QU), ('\xad', '\xad', BA),
+    ('\xb0', '\xb0', PO), ('\xb1', '\xb1', PR), ('\xb4', '\xb4', BB), ('\xbb', '\xbb', QU),
+    ('\xbf', '\xbf', OP), ('\u02c8', '\u02c8', BB), ('\u02cc', '\u02cc', BB),
+    ('\u02df', '\u02df', BB), ('\u0300', '\u034e', CM), ('\u034f', '\u034f', GL),
+    ('\u0350', '\u035b', CM), ('\u035c', '\u0362', GL), ('\u0363', '\u036f', CM),
+    ('\u037e', '\u037e', IS), ('\u0483', '\u0489', CM), ('\u0589', '\u0589', IS),
+    ('\u058a', '\u058a', BA), ('\u058f', '\u058f', PR), ('\u0591', '\u05bd', CM),
+    ('\u05be', '\u05be', BA), ('\u05bf', '\u05bf', CM), ('\u05c1', '\u05c2', CM),
+    ('\u05c4', '\u05c5', CM), ('\u05c6', '\u05c6', EX), ('\u05c7', '\u05c7', CM),
+    ('\u060b', '\u060b', PO), ('\u060c', '\u060d', IS), ('\u0610', '\u061a', CM),
+    ('\u061b', '\u061b', EX), ('\u061e', '\u061f', EX), ('\u064b', '\u065f', CM),
+    ('\u0660', '\u0669', NU), ('\u066a', '\u066a', PO), ('\u066b', '\u066c', NU),
+    ('\u0670', '\u0670', CM), ('\u06d4', '\u06d4', EX), ('\u06d6', '\u06dc', CM),
+    ('\u06df', '\u06e4', CM), ('\u06e7', '\u06e8', CM), ('\u06ea', '\u06ed', CM),
+    ('\u06f0', '\u06f9', NU), ('\u0964', '\u0965', BA), ('\u0966', '\u096f', NU),
+    ('\u0f0b', '\u0f0b', BA), ('\u0f0c', '\u0f0c', GL), ('\u1100', '\u11ff', ID),
+    ('\u1680', '\u1680', BA), ('\u17d6', '\u17d6', NS), ('\u180e', '\u180e', GL),
+    ('\u2000', '\u2006', BA), ('\u2007', '\u2007', GL), ('\u2008', '\u200a', BA),
+    ('\u200b', '\u200b', ZW), ('\u200c', '\u200f', CM), ('\u2010', '\u2010', BA),
+    ('\u2011', '\u2011', GL), ('\u2012', '\u2013', BA), ('\u2014', '\u2014', B2),
+    ('\u2018', '\u2019', QU), ('\u201a', '\u201a', OP), ('\u201b', '\u201d', QU),
+    ('\u201e', '\u201e', OP), ('\u201f', '\u201f', QU), ('\u2024', '\u2026', IN),
+    ('\u2027', '\u2027', BA), ('\u2028', '\u2029', BK), ('\u202a', '\u202e', CM),
+    ('\u202f', '\u202f', GL), ('\u2030', '\u2037', PO), ('\u2039', '\u203a', QU),
+    ('\u203c', '\u203d', NS), ('\u2044', '\u2044', IS), ('\u2045', '\u2045', OP),
+    ('\u2046', '\u2046', CL), ('\u2047', '\u2049', NS), ('\u205f', '\u205f', BA),
+    ('\u2060', '\u2060', WJ), ('\u206a', '\u206f', CM), ('\u207d', '\u207d', OP),
+    ('\u207e', '\u207e', CL), ('\u208d', '\u208d', OP), ('\u208e', '\u208e', CL),
+    ('\u20a0', '\u20a6', PR), ('\u20a7', '\u20a7', PO), ('\u20a8', '\u20b5', PR),
+    ('\u20b6', '\u20b6', PO), ('\u20d0', '\u20f0', CM), ('\u2103', '\u2103', PO),
+    ('\u2109', '\u2109', PO), ('\u2116', '\u2116', PR), ('\u2212', '\u2213', PR),
+    ('\u2329', '\u2329', OP), ('\u232a', '\u232a', CL), ('\u275b', '\u275e', QU),
+    ('\u2e80', '\u2fff', ID), ('\u3000', '\u3000', BA), ('\u3001', '\u3002', CL),
+    ('\u3003', '\u3004', ID), ('\u3005', '\u3005', NS), ('\u3006', '\u3007', ID),
+    ('\u3008', '\u3008', OP), ('\u3009', '\u3009', CL), ('\u300a', '\u300a', OP),
+    ('\u300b', '\u300b', CL), ('\u300c', '\u300c', OP), ('\u300d', '\u300d', CL),
+    ('\u300e', '\u300e', OP), ('\u300f', '\u300f', CL), ('\u3010', '\u3010', OP),
+    ('\u3011', '\u3011', CL), ('\u3012', '\u3013', ID), ('\u3014', '\u3014', OP),
+    ('\u3015', '\u3015', CL), ('\u3016', '\u3016', OP), ('\u3017', '\u3017', CL),
+    ('\u3018', '\u3018', OP), ('\u3019', '\u3019', CL), ('\u301a', '\u301a', OP),
+    ('\u301b', '\u301b', CL), ('\u301c', '\u301c', NS), ('\u301d', '\u301d', OP),
+    ('\u301e', '\u301f', CL), ('\u3020', '\u3029', ID), ('\u302a', '\u302f', CM),
+    ('\u3030', '\u303a', ID), ('\u303b', '\u303c', NS), ('\u303d', '\u3096', ID),
+    ('\u3099', '\u309a', CM), ('\u309b', '\u309e', NS), ('\u309f', '\u309f', ID),
+    ('\u30a0', '\u30a0', NS), ('\u30a1', '\u30fa', ID), ('\u30fb', '\u30fb', NS),
+    ('\u30fc', '\u30fc', ID), ('\u30fd', '\u30fe', NS), ('\u30ff', '\u4dbf', ID),
+    ('\u4e00', '\ua014', ID), ('\ua015', '\ua015', NS), ('\ua016', '\ua4cf', ID),
+    ('\uac00', '\ud7ff', ID), ('\uf900', '\ufaff', ID), ('\ufe00', '\ufe0f', CM),
+    ('\ufe10', '\ufe10', IS), ('\ufe11', '\ufe12', CL), ('\ufe13', '\ufe14', IS),
+    ('\ufe15', '\ufe16', EX), ('\ufe17', '\ufe17', OP), ('\ufe18', '\ufe18', CL),
+    ('\ufe19', '\ufe19', IN), ('\ufe20', '\ufe2f', CM), ('\ufe30', '\ufe34', ID),
+    ('\ufe35', '\ufe35', OP), ('\ufe36', '\ufe36', CL), ('\ufe50', '\ufe50', CL),
+    ('\ufe51', '\ufe51', ID), ('\ufe52', '\ufe52', CL), ('\ufe54', '\ufe55', NS),
+    ('\ufe56', '\ufe57', EX), ('\ufe59', '\ufe59', OP), ('\ufe5a', '\ufe5a', CL),
+    ('\ufe69', '\ufe69', PR), ('\ufe6a', '\ufe6a', PO), ('\ufeff', '\ufeff', WJ),
+    ('\uff01', '\uff01', EX), ('\uff02', '\uff03', ID), ('\uff04', '\uff04', PR),
+    ('\uff05', '\uff05', PO), ('\uff06', '\uff07', ID), ('\uff08', '\uff08', OP),
+    ('\uff09', '\uff09', CL), ('\uff0a', '\uff0b', ID), ('\uff0c', '\uff0c', CL),
+    ('\uff0d', '\uff0d', ID), ('\uff0e', '\uff0e', CL), ('\uff0f', '\uff19', ID),
+    ('\uff1a', '\uff1b', NS), ('\uff1c', '\uff1e', ID), ('\uff1f', '\uff1f', EX),
+    ('\uff20', '\uff3a', ID), ('\uff3b', '\uff3b', OP), ('\uff3c', '\uff3c', ID),
+    ('\uff3d', '\uff3d', CL), ('\uff3e', '\uff5a', ID), ('\uff5b', '\uff5b', OP),
+    ('\uff5c', '\uff5c', ID), ('\uff5d', '\uff5d', CL), ('\uff5e', '\uff5e', ID),
+    ('\uff5f', '\uff5f', OP), ('\uff60', '\uff61', CL), ('\uff62', '\uff62', OP),
+    ('\uff63', '\uff64', CL), ('\uff65', '\uff65', NS), ('\uff9e', '\uff9f', NS),
+    ('\uffe0', '\uffe0', PO), ('\uffe1', '\uffe1', PR), ('\uffe2', '\uffe4', ID),
+    ('\uffe5', '\uffe6', PR), ('\U00020000', '\U0002fffd', ID), ('\U00030000', '\U0003fffd', ID),
+];
+
+/// Returns the line breaking class of the given character.
+pub fn break_class(ch: char) -> BreakClass {
+    let (mut low, mut high) = (0u, BREAK_CLASS_TABLE.len());
+    while low < high {
+        let mid = (low + high) / 2;
+        let (first, last, class) = BREAK_CLASS_TABLE[mid];
+        if ch < first {
+            high = mid
+        } else if ch > last {
+            low = mid + 1
+        } else {
+            return class
+        }
+    }
+    AL
+}
+
+/// Adjusts a break class for the given value of the `word-break` property.
+fn apply_word_break(class: BreakClass, word_break: word_break::T) -> BreakClass {
+    match (word_break, class) {
+        // `break-all` allows breaks between any two letters, as though they were ideographs.
+        (word_break::break_all, AL) | (word_break::break_all, NU) => ID,
+        // `keep-all` suppresses breaks between ideographs, as though they were letters.
+        (word_break::keep_all, ID) => AL,
+        _ => class,
+    }
+}
+
+/// Returns a vector with one element per character of `text`. Each element is true if a line may
+/// be broken before the corresponding character.
+pub fn break_opportunities(text: &str, word_break: word_break::T) -> Vec<bool> {
+    let classes: Vec<BreakClass> = text.chars().map(|ch| {
+        apply_word_break(break_class(ch), word_break)
+    }).collect();
+    find_break_opportunities(classes.as_slice())
+}
+
+/// Finds the line break opportunities in a sequence of break classes. The result has one element
+/// per class, which is true if a line may be broken before the corresponding character.
+pub fn find_break_opportunities(classes: &[BreakClass]) -> Vec<bool> {
+    if classes.is_empty() {
+        return vec!()
+    }
+
+    // LB2: Never break at the start of text. LB10: Treat a combining mark with no base as `AL`.
+    let mut opportunities = vec!(false);
+    let mut before = match classes[0] {
+        CM => AL,
+        class => class,
+    };
+    // The class of the last character that is not a space, for the rules that look across spaces.
+    let mut before_spaces = before;
+
+    for &class in classes.slice_from(1).iter() {
+        // LB9: Combining marks take the class of their base, unless it is a space or a break.
+        let after = match (before, class) {
+            (BK, CM) | (CR, CM) | (LF, CM) | (NL, CM) | (SP, CM) | (ZW, CM) => AL,
+            (_, CM) => {
+                opportunities.push(false);
+                continue
+            }
+            (_, class) => class,
+        };
+
+        opportunities.push(can_break_between(before, before_spaces, after));
+        if after != SP {
+            before_spaces = after
+        }
+        before = after
+    }
+    opportunities
+}
+
+/// Applies the pair rules of UAX #14 § 6.1 to decide whether a line may be broken between a
+/// character of class `before` and one of class `after`. `before_spaces` is the class of the last
+/// character before `after` that is not a space.
+fn can_break_between(before: BreakClass, before_spaces: BreakClass, after: BreakClass) -> bool {
+    match (before, after) {
+        // LB4, LB5: Always break after hard line breaks, but never between CR and LF.
+        (CR, LF) => return false,
+        (BK, _) | (CR, _) | (LF, _) | (NL, _) => return true,
+        // LB6: Don't break before hard line breaks.
+        (_, BK) | (_, CR) | (_, LF) | (_, NL) => return false,
+        // LB7: Don't break before spaces or zero width spaces.
+        (_, SP) | (_, ZW) => return false,
+        _ => {}
+    }
+
+    // LB8: Break after a zero width space, even if it is followed by spaces.
+    if before_spaces == ZW {
+        return true
+    }
+
+    match (before, after) {
+        // LB11: Don't break before or after word joiners.
+        (WJ, _) | (_, WJ) => return false,
+        // LB12: Don't break after glue.
+        (GL, _) => return false,
+        // LB12a: Don't break before glue, except after spaces and hyphens.
+        (SP, GL) | (BA, GL) | (HY, GL) => {}
+        (_, GL) => return false,
+        // LB13: Don't break before closing punctuation, exclamations or symbols, even after
+        // spaces.
+        (_, CL) | (_, CP) | (_, EX) | (_, IS) | (_, SY) => return false,
+        _ => {}
+    }
+
+    match (before_spaces, after) {
+        // LB14: Don't break after opening punctuation, even after spaces.
+        (OP, _) => return false,
+        // LB15: Don't break within a quotation mark followed by opening punctuation.
+        (QU, OP) => return false,
+        // LB16: Don't break between closing punctuation and a non-starter.
+        (CL, NS) | (CP, NS) => return false,
+        // LB17: Don't break within a pair of em dashes.
+        (B2, B2) => return false,
+        _ => {}
+    }
+
+    match (before, after) {
+        // LB18: Break after spaces.
+        (SP, _) => true,
+        // LB19: Don't break before or after ambiguous quotation marks.
+        (_, QU) | (QU, _) => false,
+        // LB21: Don't break before hyphens and non-starters, or after break-before characters.
+        (_, BA) | (_, HY) | (_, NS) | (BB, _) => false,
+        // LB22: Don't break before ellipses.
+        (AL, IN) | (EX, IN) | (ID, IN) | (IN, IN) | (NU, IN) => false,
+        // LB23: Don't break between digits and letters.
+        (ID, PO) | (AL, NU) | (NU, AL) => false,
+        // LB24: Don't break between prefixes and letters or ideographs.
+        (PR, ID) | (PR, AL) | (PO, AL) => false,
+        // LB25: Don't break within numbers. This is the simplified form given in UAX #14.
+        (CL, PO) | (CP, PO) | (CL, PR) | (CP, PR) | (NU, PO) | (NU, PR) | (PO, OP) | (PO, NU) |
+        (PR, OP) | (PR, NU) | (HY, NU) | (IS, NU) | (NU, NU) | (SY, NU) => false,
+        // LB28: Don't break between alphabetics.
+        (AL, AL) => false,
+        // LB29: Don't break between numeric punctuation and alphabetics.
+        (IS, AL) => false,
+        // LB30: Don't break between letters or numbers and parentheses.
+        (AL, OP) | (NU, OP) | (CP, AL) | (CP, NU) => false,
+        // LB31: Break everywhere else.
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+fn breaks_for_str(text: &str, word_break: word_break::T) -> Vec<uint> {
+    break_opportunities(text, word_break).iter().enumerate().filter_map(|(i, &opportunity)| {
+        if opportunity { Some(i) } else { None }
+    }).collect()
+}
+
+#[test]
+fn test_break_class() {
+    assert_eq!(break_class('a'), AL);
+    assert_eq!(break_class('7'), NU);
+    assert_eq!(break_class(' '), SP);
+    assert_eq!(break_class('-'), HY);
+    assert_eq!(break_class('/'), SY);
+    assert_eq!(break_class('\u4e00'), ID);
+    assert_eq!(break_class('\u3002'), CL);
+    assert_eq!(break_class('\u0301'), CM);
+}
+
+#[test]
+fn test_break_class_table_is_sorted() {
+    for pair in BREAK_CLASS_TABLE.windows(2) {
+        let (_, last, _) = pair[0];
+        let (first, _, _) = pair[1];
+        assert!(last < first);
+    }
+}
+
+#[test]
+fn test_break_opportunities() {
+    // Breaks come after spaces, not before them.
+    assert_eq!(breaks_for_str("ab cd  ef", word_break::normal), vec!(3, 7));
+    // Hyphens and slashes allow a break after them, but not within numbers.
+    assert_eq!(breaks_for_str("foo-bar/baz", word_break::normal), vec!(4, 8));
+    assert_eq!(breaks_for_str("-12/34", word_break::normal), vec!());
+    // Ideographs can be broken between, but not before closing punctuation.
+    assert_eq!(breaks_for_str("\u4e00\u4e8c\u3002\u4e09", word_break::normal), vec!(1, 3));
+    // Combining marks stay with their base.
+    assert_eq!(breaks_for_str("\u4e00\u0301\u4e8c", word_break::normal), vec!(2));
+    // Opening punctuation stays with what follows, even across spaces.
+    assert_eq!(breaks_for_str("a ( b", word_break::normal), vec!(2));
+}
+
+#[test]
+fn test_word_break() {
+    assert_eq!(breaks_for_str("abc de", word_break::break_all), vec!(1, 2, 4, 5));
+    assert_eq!(breaks_for_str("\u4e00\u4e8c \u4e09", word_break::keep_all), vec!(3));
+}
@@ -7,20 +7,21 @@ extern crate harfbuzz;
 use font::{Font, FontHandleMethods, FontTableMethods, FontTableTag};
 use platform::font::FontTable;
 use text::glyph::{CharIndex, GlyphStore, GlyphId, GlyphData};
-use text::shaping::ShaperMethods;
+use text::shaping::{ShaperMethods, ShapingOptions};
 use text::util::{float_to_fixed, fixed_to_float};
 
 use geom::Point2D;
-use harfbuzz::{HB_MEMORY_MODE_READONLY, HB_DIRECTION_LTR};
+use harfbuzz::{HB_MEMORY_MODE_READONLY, HB_DIRECTION_LTR, HB_DIRECTION_RTL};
 use harfbuzz::{hb_blob_create, hb_face_create_for_tables};
 use harfbuzz::{hb_blob_t};
 use harfbuzz::{hb_bool_t};
 use harfbuzz::{hb_buffer_add_utf8};
 use harfbuzz::{hb_buffer_destroy};
 use harfbuzz::{hb_buffer_get_glyph_positions};
+use harfbuzz::{hb_buffer_reverse};
 use harfbuzz::{hb_buffer_set_direction};
 use harfbuzz::{hb_face_destroy};
-use harfbuzz::{hb_face_t, hb_font_t};
+use harfbuzz::{hb_face_t, hb_feature_t, hb_font_t};
 use harfbuzz::{hb_font_create};
 use harfbuzz::{hb_font_destroy, hb_buffer_create};
 use harfbuzz::{hb_font_funcs_create};
@@ -200,10 +201,15 @@ impl Shaper {
 impl ShaperMethods for Shaper {
     /// Calculate the layout metrics associated with the given text when rendered in a specific
     /// font.
-    fn shape_text(&self, text: &str, glyphs: &mut GlyphStore) {
+    fn shape_text(&self, text: &str, is_rtl: bool, options: &ShapingOptions,
+                  glyphs: &mut GlyphStore) {
         unsafe {
             let hb_buffer: *hb_buffer_t = hb_buffer_create();
-            hb_buffer_set_direction(hb_buffer, HB_DIRECTION_LTR);
+            hb_buffer_set_direction(hb_buffer, if is_rtl {
+                HB_DIRECTION_RTL
+            } else {
+                HB_DIRECTION_LTR
+            });
 
             // Using as_imm_buf because it never does a copy - we don't need the trailing null
             hb_buffer_add_utf8(hb_buffer,
@@ -212,15 +218,35 @@ impl ShaperMethods for Shaper {
                                0,
                                text.len() as c_int);
 
-            hb_shape(self.hb_font, hb_buffer, null(), 0);
-            self.save_glyph_results(text, glyphs, hb_buffer);
+            // Each feature applies to the whole buffer.
+            let features: Vec<hb_feature_t> = options.features.iter().map(|feature| {
+                hb_feature_t {
+                    tag: feature.tag as hb_tag_t,
+                    value: feature.value,
+                    start: 0,
+                    end: -1 as c_uint,
+                }
+            }).collect();
+            hb_shape(self.hb_font, hb_buffer, features.as_ptr(), features.len() as c_uint);
+
+            // HarfBuzz returns right-to-left glyphs in visual order. Put them back into logical
+            // order so that clusters increase monotonically, as `save_glyph_results` expects.
+            if is_rtl {
+                hb_buffer_reverse(hb_buffer);
+            }
+
+            self.save_glyph_results(text, options, glyphs, hb_buffer);
             hb_buffer_destroy(hb_buffer);
         }
     }
 }
 
 impl Shaper {
-    fn save_glyph_results(&self, text: &str, glyphs: &mut GlyphStore, buffer: *hb_buffer_t) {
+    fn save_glyph_results(&self,
+                          text: &str,
+                          options: &ShapingOptions,
+                          glyphs: &mut GlyphStore,
+                          buffer: *hb_buffer_t) {
         let glyph_data = ShapedGlyphData::new(buffer);
         let glyph_count = glyph_data.len();
         let byte_max = text.len() as int;
@@ -394,6 +420,11 @@ impl Shaper {
             let end = covered_byte_span.end(); // FIXME: borrow checker workaround
             covered_byte_span.extend_to(cmp::min(end, byte_max));
 
+            // Letter and word spacing go after the last glyph of the clump.
+            let extra_advance = Shaper::extra_advance_for_chars(
+                text.slice(covered_byte_span.begin() as uint, covered_byte_span.end() as uint),
+                options);
+
             // fast path: 1-to-1 mapping of single char and single glyph.
             if glyph_span.length() == 1 {
                 // TODO(Issue #214): cluster ranges need to be computed before
@@ -403,7 +434,7 @@ impl Shaper {
                 // 1-to-1 mapping of character to glyph also treated as ligature start.
                 let shape = glyph_data.get_entry_for_glyph(glyph_span.begin(), &mut y_pos);
                 let data = GlyphData::new(shape.codepoint,
-                                          shape.advance,
+                                          shape.advance + extra_advance,
                                           shape.offset,
                                           false,
                                           true,
@@ -415,8 +446,13 @@ impl Shaper {
 
                 for glyph_i in glyph_span.each_index() {
                     let shape = glyph_data.get_entry_for_glyph(glyph_i, &mut y_pos);
+                    let advance = if glyph_i == glyph_span.end() - 1 {
+                        shape.advance + extra_advance
+                    } else {
+                        shape.advance
+                    };
                     datas.push(GlyphData::new(shape.codepoint,
-                                              shape.advance,
+                                              advance,
                                               shape.offset,
                                               false, // not missing
                                               true,  // treat as cluster start
@@ -451,6 +487,19 @@ impl Shaper {
         // lookup table for finding detailed glyphs by associated char index.
         glyphs.finalize_changes();
     }
+
+    /// Returns the space that `letter-spacing` and `word-spacing` add after the given characters.
+    fn extra_advance_for_chars(chars: &str, options: &ShapingOptions) -> Au {
+        chars.chars().fold(Au(0), |advance, ch| {
+            // The word separators of CSS Text Level 3 § 8.1 that we are likely to see.
+            let is_word_separator = ch == ' ' || ch == '\u00a0';
+            if is_word_separator {
+                advance + options.letter_spacing + options.word_spacing
+            } else {
+                advance + options.letter_spacing
+            }
+        })
+    }
 }
 
 /// Callbacks from Harfbuzz when font map and glyph advance lookup needed.
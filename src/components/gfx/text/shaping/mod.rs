@@ -7,13 +7,64 @@
 ///
 /// Currently, only harfbuzz bindings are implemented.
 
+use servo_util::geometry::Au;
 use text::glyph::GlyphStore;
 
 pub use Shaper = text::shaping::harfbuzz::Shaper;
 
 pub mod harfbuzz;
 
+/// An OpenType feature setting: a feature tag and the value to set it to, usually 0 or 1.
+#[deriving(Clone, Eq, TotalEq, Hash)]
+pub struct ShapingFeature {
+    pub tag: u32,
+    pub value: u32,
+}
+
+impl ShapingFeature {
+    /// Creates a setting for the feature with the given four-character tag, such as `"liga"`.
+    pub fn new(tag: &str, value: u32) -> ShapingFeature {
+        assert!(tag.len() == 4);
+        ShapingFeature {
+            tag: tag.bytes().fold(0, |tag, byte| (tag << 8) | byte as u32),
+            value: value,
+        }
+    }
+}
+
+/// The options that the style of some text gives for shaping it.
+#[deriving(Clone, Eq, TotalEq, Hash)]
+pub struct ShapingOptions {
+    /// OpenType features to set. A later setting of a feature overrides an earlier one.
+    pub features: Vec<ShapingFeature>,
+    /// Extra space added after each character, from `letter-spacing`.
+    pub letter_spacing: Au,
+    /// Extra space added after each word separator, from `word-spacing`.
+    pub word_spacing: Au,
+}
+
+impl ShapingOptions {
+    /// Returns options that shape text with the font's default features and no extra spacing.
+    pub fn new() -> ShapingOptions {
+        ShapingOptions {
+            features: vec!(),
+            letter_spacing: Au(0),
+            word_spacing: Au(0),
+        }
+    }
+}
+
 pub trait ShaperMethods {
-    fn shape_text(&self, text: &str, glyphs: &mut GlyphStore);
+    /// Shapes `text` into `glyphs`. Right-to-left text is shaped in visual order but stored in
+    /// logical order, like all other glyph stores. Letter and word spacing are added to the
+    /// advances of the shaped glyphs.
+    fn shape_text(&self, text: &str, is_rtl: bool, options: &ShapingOptions,
+                  glyphs: &mut GlyphStore);
 }
 
+
+#[test]
+fn test_shaping_feature_tag() {
+    assert_eq!(ShapingFeature::new("liga", 0).tag, 0x6c696761);
+    assert_eq!(ShapingFeature::new("tnum", 1).tag, 0x746e756d);
+}
@@ -7,9 +7,12 @@ use servo_util::geometry::Au;
 use servo_util::range::Range;
 use servo_util::vec::{Comparator, FullBinarySearchMethods};
 use std::slice::Items;
-use style::computed_values::text_decoration;
+use style::computed_values::{text_decoration_line, word_break};
 use sync::Arc;
+use text::bidi::{Level, is_rtl};
 use text::glyph::{CharIndex, GlyphStore};
+use text::line_break;
+use text::shaping::ShapingOptions;
 
 /// A single "paragraph" of text in one font size and style.
 #[deriving(Clone)]
@@ -18,7 +21,9 @@ pub struct TextRun {
     pub font_descriptor: FontDescriptor,
     pub font_metrics: FontMetrics,
     pub font_style: FontStyle,
-    pub decoration: text_decoration::T,
+    pub decoration: text_decoration_line::T,
+    /// The embedding level assigned to all of this run's text by the bidi algorithm.
+    pub bidi_level: Level,
     /// The glyph runs that make up this text run.
     pub glyphs: Arc<Vec<GlyphRun>>,
 }
@@ -30,6 +35,8 @@ pub struct GlyphRun {
     glyph_store: Arc<GlyphStore>,
     /// The range of characters in the containing run.
     range: Range<CharIndex>,
+    /// Whether UAX #14 allows a line break before the first character of this glyph run.
+    can_break_before: bool,
 }
 
 pub struct SliceIterator<'a> {
@@ -72,67 +79,100 @@ impl<'a> Iterator<(&'a GlyphStore, CharIndex, Range<CharIndex>)> for SliceIterat
     }
 }
 
+/// Iterates over the pieces of a range of text that cannot be broken across lines: the text
+/// between consecutive line break opportunities, without the whitespace that surrounds it.
 pub struct LineIterator<'a> {
-    range:  Range<CharIndex>,
-    clump:  Option<Range<CharIndex>>,
-    slices: SliceIterator<'a>,
+    range: Range<CharIndex>,
+    glyph_iter: Items<'a, GlyphRun>,
+    clump: Option<Range<CharIndex>>,
+    /// The length of the whitespace after the current clump, which becomes part of the clump if
+    /// the line cannot be broken after it.
+    trailing_whitespace: CharIndex,
 }
 
 impl<'a> Iterator<Range<CharIndex>> for LineIterator<'a> {
     fn next(&mut self) -> Option<Range<CharIndex>> {
-        // Loop until we hit whitespace and are in a clump.
         loop {
-            match self.slices.next() {
-                Some((glyphs, offset, slice_range)) => {
-                    match (glyphs.is_whitespace(), self.clump) {
-                        (false, Some(ref mut c))  => {
-                            c.extend_by(slice_range.length());
-                        }
-                        (false, None) => {
-                            let mut c = slice_range;
-                            c.shift_by(offset);
-                            self.clump = Some(c);
-                        }
-                        (true, None)    => { /* chomp whitespace */ }
-                        (true, Some(c)) => {
-                            self.clump = None;
-                            // The final whitespace clump is not included.
-                            return Some(c);
-                        }
-                    }
-                },
-                None => {
-                    // flush any remaining chars as a line
-                    if self.clump.is_some() {
-                        let mut c = self.clump.take_unwrap();
-                        c.extend_to(self.range.end());
-                        return Some(c);
-                    } else {
-                        return None;
+            let glyph_run = match self.glyph_iter.next() {
+                Some(glyph_run) => glyph_run,
+                None => return self.clump.take(),
+            };
+            let slice_range = self.range.intersect(&glyph_run.range);
+            if slice_range.is_empty() {
+                return self.clump.take()
+            }
+
+            if glyph_run.glyph_store.is_whitespace() {
+                if self.clump.is_some() {
+                    self.trailing_whitespace = self.trailing_whitespace + slice_range.length();
+                }
+                continue
+            }
+
+            let can_break_before = glyph_run.can_break_before &&
+                slice_range.begin() == glyph_run.range.begin();
+            if !can_break_before {
+                match self.clump {
+                    Some(ref mut clump) => {
+                        clump.extend_by(self.trailing_whitespace + slice_range.length());
+                        self.trailing_whitespace = CharIndex(0);
+                        continue
                     }
+                    None => {}
                 }
             }
+            self.trailing_whitespace = CharIndex(0);
+            let line = self.clump.take();
+            self.clump = Some(slice_range);
+            if line.is_some() {
+                return line
+            }
         }
     }
 }
 
 impl<'a> TextRun {
-    pub fn new(font: &mut Font, text: String, decoration: text_decoration::T) -> TextRun {
-        let glyphs = TextRun::break_and_shape(font, text.as_slice());
+    pub fn new(font: &mut Font,
+               text: String,
+               decoration: text_decoration_line::T,
+               bidi_level: Level,
+               word_break: word_break::T,
+               options: &ShapingOptions)
+               -> TextRun {
+        let glyphs = TextRun::break_and_shape(font,
+                                              text.as_slice(),
+                                              is_rtl(bidi_level),
+                                              word_break,
+                                              options);
+        TextRun::from_glyphs(font, text, decoration, bidi_level, Arc::new(glyphs))
+    }
 
-        let run = TextRun {
+    /// Creates a text run from glyph runs that `break_and_shape` already produced for the same
+    /// text and font, such as those kept by the shaped run cache of the font context.
+    pub fn from_glyphs(font: &Font,
+                       text: String,
+                       decoration: text_decoration_line::T,
+                       bidi_level: Level,
+                       glyphs: Arc<Vec<GlyphRun>>)
+                       -> TextRun {
+        TextRun {
             text: Arc::new(text),
             font_style: font.style.clone(),
             font_metrics: font.metrics.clone(),
             font_descriptor: font.get_descriptor(),
             decoration: decoration,
-            glyphs: Arc::new(glyphs),
-        };
-        return run;
+            bidi_level: bidi_level,
+            glyphs: glyphs,
+        }
     }
 
-    pub fn break_and_shape(font: &mut Font, text: &str) -> Vec<GlyphRun> {
-        // TODO(Issue #230): do a better job. See Gecko's LineBreaker.
+    pub fn break_and_shape(font: &mut Font,
+                           text: &str,
+                           is_rtl: bool,
+                           word_break: word_break::T,
+                           options: &ShapingOptions)
+                           -> Vec<GlyphRun> {
+        let break_opportunities = line_break::break_opportunities(text, word_break);
 
         let mut glyphs = vec!();
         let (mut byte_i, mut char_i) = (0u, CharIndex(0));
@@ -143,38 +183,34 @@ impl<'a> TextRun {
             let ch = range.ch;
             let next = range.next;
 
-            // Slices alternate between whitespace and non-whitespace,
-            // representing line break opportunities.
-            let can_break_before = if cur_slice_is_whitespace {
-                match ch {
-                    ' ' | '\t' | '\n' => false,
-                    _ => {
-                        cur_slice_is_whitespace = false;
-                        true
-                    }
-                }
+            // Slices alternate between whitespace and non-whitespace. Non-whitespace is further
+            // divided at the line break opportunities found by UAX #14, so that every boundary
+            // between slices is a line break opportunity.
+            let is_whitespace = match ch {
+                ' ' | '\t' | '\n' => true,
+                _ => false,
+            };
+            let can_break_before = if is_whitespace != cur_slice_is_whitespace {
+                true
             } else {
-                match ch {
-                    ' ' | '\t' | '\n' => {
-                        cur_slice_is_whitespace = true;
-                        true
-                    },
-                    _ => false
-                }
+                !is_whitespace && *break_opportunities.get(char_i.to_uint())
             };
 
             // Create a glyph store for this slice if it's nonempty.
             if can_break_before && byte_i > byte_last_boundary {
                 let slice = text.slice(byte_last_boundary, byte_i).to_string();
+                let can_break_before = *break_opportunities.get(char_last_boundary.to_uint());
                 debug!("creating glyph store for slice {} (ws? {}), {} - {} in run {}",
-                        slice, !cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
+                        slice, cur_slice_is_whitespace, byte_last_boundary, byte_i, text);
                 glyphs.push(GlyphRun {
-                    glyph_store: font.shape_text(slice, !cur_slice_is_whitespace),
+                    glyph_store: font.shape_text(slice, cur_slice_is_whitespace, is_rtl, options),
                     range: Range::new(char_last_boundary, char_i - char_last_boundary),
+                    can_break_before: can_break_before,
                 });
                 byte_last_boundary = byte_i;
                 char_last_boundary = char_i;
             }
+            cur_slice_is_whitespace = is_whitespace;
 
             byte_i = next;
             char_i = char_i + CharIndex(1);
@@ -186,8 +222,9 @@ impl<'a> TextRun {
             debug!("creating glyph store for final slice {} (ws? {}), {} - {} in run {}",
                 slice, cur_slice_is_whitespace, byte_last_boundary, text.len(), text);
             glyphs.push(GlyphRun {
-                glyph_store: font.shape_text(slice, cur_slice_is_whitespace),
+                glyph_store: font.shape_text(slice, cur_slice_is_whitespace, is_rtl, options),
                 range: Range::new(char_last_boundary, char_i - char_last_boundary),
+                can_break_before: *break_opportunities.get(char_last_boundary.to_uint()),
             });
         }
 
@@ -212,6 +249,25 @@ impl<'a> TextRun {
         true
     }
 
+    /// Returns the index at which the whitespace that ends the given range begins, or the end of
+    /// the range if the range does not end with whitespace.
+    pub fn trailing_whitespace_start(&self, range: &Range<CharIndex>) -> CharIndex {
+        let mut start = range.end();
+        for (slice_glyphs, offset, slice_range) in self.iter_slices_for_range(range) {
+            if !slice_glyphs.is_whitespace() {
+                start = range.end()
+            } else if start == range.end() {
+                start = offset + slice_range.begin()
+            }
+        }
+        start
+    }
+
+    /// Returns true if this run's glyphs are laid out from right to left.
+    pub fn is_rtl(&self) -> bool {
+        is_rtl(self.bidi_level)
+    }
+
     pub fn ascent(&self) -> Au {
         self.font_metrics.ascent
     }
@@ -221,7 +277,6 @@ impl<'a> TextRun {
     }
 
     pub fn advance_for_range(&self, range: &Range<CharIndex>) -> Au {
-        // TODO(Issue #199): alter advance direction for RTL
         // TODO(Issue #98): using inter-char and inter-word spacing settings  when measuring text
         self.iter_slices_for_range(range)
             .fold(Au(0), |advance, (glyphs, _, slice_range)| {
@@ -241,12 +296,14 @@ impl<'a> TextRun {
                         self.font_metrics.descent)
     }
 
+    /// Returns the width of the widest piece of the given range that cannot be broken across
+    /// lines.
     pub fn min_width_for_range(&self, range: &Range<CharIndex>) -> Au {
         let mut max_piece_width = Au(0);
         debug!("iterating outer range {:?}", range);
-        for (_, offset, slice_range) in self.iter_slices_for_range(range) {
-            debug!("iterated on {:?}[{:?}]", offset, slice_range);
-            max_piece_width = Au::max(max_piece_width, self.advance_for_range(&slice_range));
+        for line_range in self.iter_natural_lines_for_range(range) {
+            debug!("iterated on {:?}", line_range);
+            max_piece_width = Au::max(max_piece_width, self.advance_for_range(&line_range));
         }
         max_piece_width
     }
@@ -268,10 +325,15 @@ impl<'a> TextRun {
     }
 
     pub fn iter_natural_lines_for_range(&'a self, range: &Range<CharIndex>) -> LineIterator<'a> {
+        let index = match self.index_of_first_glyph_run_containing(range.begin()) {
+            None => self.glyphs.len(),
+            Some(index) => index,
+        };
         LineIterator {
-            range:  *range,
-            clump:  None,
-            slices: self.iter_slices_for_range(range),
+            range: *range,
+            glyph_iter: self.glyphs.slice_from(index).iter(),
+            clump: None,
+            trailing_whitespace: CharIndex(0),
         }
     }
 }
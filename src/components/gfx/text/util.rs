@@ -4,6 +4,10 @@
 
 use text::glyph::CharIndex;
 
+use std::ascii::StrAsciiExt;
+use std::char;
+use style::computed_values::text_transform;
+
 #[deriving(Eq)]
 pub enum CompressionMode {
     CompressNone,
@@ -109,6 +113,70 @@ pub fn transform_text(text: &str, mode: CompressionMode,
     }
 }
 
+/// Applies a `text-transform` to the given text (CSS Text Level 3 § 2.1). Every character maps to
+/// exactly one character, so character indices into the text stay valid.
+///
+/// `lang` is the content language of the text, which selects the Turkic mappings of the dotted and
+/// dotless i. `incoming_in_word` says whether the text continues a word from the preceding text,
+/// for `capitalize`; whether the text ends within a word is returned alongside it.
+///
+/// TODO: Support the case mappings that change the length of the text, such as "ß" to "SS".
+pub fn transform_case(text: &str, transform: text_transform::T, lang: Option<&str>,
+                      incoming_in_word: bool) -> (String, bool) {
+    let is_turkic = match lang.and_then(|lang| lang.split('-').next()) {
+        Some(language) => {
+            language.eq_ignore_ascii_case("tr") || language.eq_ignore_ascii_case("az")
+        }
+        None => false,
+    };
+
+    let mut out_str = String::with_capacity(text.len());
+    let mut in_word = incoming_in_word;
+    for ch in text.chars() {
+        let out_ch = match transform {
+            text_transform::none => ch,
+            text_transform::uppercase => to_uppercase(ch, is_turkic),
+            text_transform::lowercase => to_lowercase(ch, is_turkic),
+            text_transform::capitalize if !in_word => to_uppercase(ch, is_turkic),
+            text_transform::capitalize => ch,
+            text_transform::full_width => to_full_width(ch),
+        };
+        out_str.push_char(out_ch);
+
+        // Apostrophes neither start nor end a word, so that "don't" capitalizes as "Don't".
+        if ch != '\'' && ch != '\u2019' {
+            in_word = ch.is_alphanumeric()
+        }
+    }
+    return (out_str, in_word);
+
+    fn to_uppercase(ch: char, is_turkic: bool) -> char {
+        match ch {
+            'i' if is_turkic => '\u0130',
+            _ => ch.to_uppercase(),
+        }
+    }
+
+    fn to_lowercase(ch: char, is_turkic: bool) -> char {
+        match ch {
+            'I' if is_turkic => '\u0131',
+            '\u0130' => 'i',
+            _ => ch.to_lowercase(),
+        }
+    }
+
+    // TODO: Also map half-width katakana to their full-width forms.
+    fn to_full_width(ch: char) -> char {
+        if ch == ' ' {
+            '\u3000'
+        } else if ch >= '!' && ch <= '~' {
+            char::from_u32(ch as u32 + 0xFEE0).unwrap()
+        } else {
+            ch
+        }
+    }
+}
+
 pub fn float_to_fixed(before: int, f: f64) -> i32 {
     (1i32 << before) * (f as i32)
 }
@@ -283,3 +351,29 @@ fn test_transform_compress_whitespace_newline_no_incoming() {
         assert_eq!(trimmed_str.as_slice(), *oracle)
     }
 }
+
+#[test]
+fn test_transform_case() {
+    let transform = |text: &str, transform: text_transform::T, lang: Option<&str>| {
+        let (transformed, _) = transform_case(text, transform, lang, false);
+        transformed
+    };
+    assert_eq!(transform("Hello, world", text_transform::uppercase, None).as_slice(),
+               "HELLO, WORLD");
+    assert_eq!(transform("Hello, World", text_transform::lowercase, None).as_slice(),
+               "hello, world");
+    assert_eq!(transform("don't stop-me now", text_transform::capitalize, None).as_slice(),
+               "Don't Stop-Me Now");
+    assert_eq!(transform("istanbul", text_transform::uppercase, Some("tr-TR")).as_slice(),
+               "\u0130STANBUL");
+    assert_eq!(transform("ISPARTA", text_transform::lowercase, Some("az")).as_slice(),
+               "\u0131sparta");
+    assert_eq!(transform("istanbul", text_transform::uppercase, Some("en")).as_slice(), "ISTANBUL");
+    assert_eq!(transform("a1 !", text_transform::full_width, None).as_slice(),
+               "\uff41\uff11\u3000\uff01");
+
+    // A word that continues from the preceding text is not capitalized again.
+    let (capitalized, in_word) = transform_case("bc de", text_transform::capitalize, None, true);
+    assert_eq!(capitalized.as_slice(), "bc De");
+    assert!(in_word);
+}
@@ -7,10 +7,12 @@ use compositing::compositor_layer::CompositorLayer;
 use compositing::*;
 use pipeline::CompositionPipeline;
 use platform::{Application, Window};
-use windowing::{FinishedWindowEvent, IdleWindowEvent, LoadUrlWindowEvent, MouseWindowClickEvent};
+use windowing::{FinishedWindowEvent, IdleWindowEvent, KeyWindowEvent, LoadUrlWindowEvent};
+use windowing::MouseWindowClickEvent;
 use windowing::{MouseWindowEvent, MouseWindowEventClass, MouseWindowMouseDownEvent};
 use windowing::{MouseWindowMouseUpEvent, MouseWindowMoveEventClass, NavigationWindowEvent};
-use windowing::{QuitWindowEvent, RefreshWindowEvent, ResizeWindowEvent, ScrollWindowEvent};
+use windowing::{PinchZoomWindowEvent, QuitWindowEvent, RefreshWindowEvent, ResizeWindowEvent};
+use windowing::ScrollWindowEvent;
 use windowing::{WindowEvent, WindowMethods, WindowNavigateMsg, ZoomWindowEvent};
 
 use azure::azure_hl::{SourceSurfaceMethods, Color};
@@ -29,10 +31,13 @@ use opengles::gl2;
 use png;
 use servo_msg::compositor_msg::{Blank, Epoch, FinishedLoading, IdleRenderState, LayerBufferSet};
 use servo_msg::compositor_msg::{LayerId, ReadyState, RenderState, ScrollPolicy, Scrollable};
-use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, LoadUrlMsg, NavigateMsg};
-use servo_msg::constellation_msg::{PipelineId, ResizedWindowMsg};
+use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, Key, KeyEvent, KeyModifiers};
+use servo_msg::constellation_msg::{KeyState, LoadUrlMsg, NavigateMsg};
+use servo_msg::constellation_msg::{PipelineId, ResizedWindowMsg, TickAnimationFramesMsg};
+use servo_msg::constellation_msg::ZoomMsg;
 use servo_msg::constellation_msg;
-use servo_util::geometry::{DevicePixel, PagePx, ScreenPx};
+use servo_net::resource_task::LoadData;
+use servo_util::geometry::{DevicePixel, PagePx, ScreenPx, ViewportPx};
 use servo_util::opts::Opts;
 use servo_util::time::{profile, ProfilerChan};
 use servo_util::{time, url};
@@ -82,13 +87,17 @@ pub struct IOCompositor {
     /// Tracks whether we need to re-composite a page.
     recomposite: bool,
 
-    /// Keeps track of the current zoom factor.
-    world_zoom: ScaleFactor<PagePx, ScreenPx, f32>,
+    /// The page zoom level of the current page, which reflows it. The constellation keeps the
+    /// level of each origin.
+    page_zoom: ScaleFactor<ViewportPx, ScreenPx, f32>,
 
-    /// Tracks whether the zoom action has happend recently.
+    /// The pinch zoom level, which magnifies the page without reflowing it.
+    viewport_zoom: ScaleFactor<PagePx, ViewportPx, f32>,
+
+    /// Tracks whether the pinch zoom action has happend recently.
     zoom_action: bool,
 
-    /// The time of the last zoom action has started.
+    /// The time of the last pinch zoom action has started.
     zoom_time: f64,
 
     /// Current display/reflow status of the page
@@ -112,7 +121,13 @@ pub struct IOCompositor {
     profiler_chan: ProfilerChan,
 
     /// Pending scroll to fragment event, if any
-    fragment_point: Option<Point2D<f32>>
+    fragment_point: Option<Point2D<f32>>,
+
+    /// Whether a page is waiting for the next animation frame.
+    animation_frame_requested: bool,
+
+    /// The time at which the last animation frame began.
+    last_animation_frame: f64,
 }
 
 impl IOCompositor {
@@ -121,7 +136,9 @@ impl IOCompositor {
                port: Receiver<Msg>,
                constellation_chan: ConstellationChan,
                profiler_chan: ProfilerChan) -> IOCompositor {
-        let window: Rc<Window> = WindowMethods::new(app, opts.output_file.is_none());
+        let window: Rc<Window> = WindowMethods::new(app,
+                                                    opts.output_file.is_none(),
+                                                    opts.initial_window_size);
 
         // Create an initial layer tree.
         //
@@ -146,7 +163,8 @@ impl IOCompositor {
             shutting_down: false,
             done: false,
             recomposite: false,
-            world_zoom: ScaleFactor(1.0),
+            page_zoom: ScaleFactor(1.0),
+            viewport_zoom: ScaleFactor(1.0),
             zoom_action: false,
             zoom_time: 0f64,
             ready_state: Blank,
@@ -154,7 +172,9 @@ impl IOCompositor {
             compositor_layer: None,
             constellation_chan: constellation_chan,
             profiler_chan: profiler_chan,
-            fragment_point: None
+            fragment_point: None,
+            animation_frame_requested: false,
+            last_animation_frame: 0f64,
         }
     }
 
@@ -200,6 +220,8 @@ impl IOCompositor {
                 self.composite();
             }
 
+            self.tick_animation_frames_if_necessary();
+
             sleep(10);
 
             // If a pinch-zoom happened recently, ask for tiles at the new resolution
@@ -265,6 +287,10 @@ impl IOCompositor {
                     self.set_ids(frame_tree, response_chan, new_constellation_chan);
                 }
 
+                // Only the layers of the root pipeline are composited, so subframes are not
+                // needed.
+                (Ok(SetFrameTree(..)), _) => {}
+
                 (Ok(GetGraphicsMetadata(chan)), false) => {
                     chan.send(Some(azure_hl::current_graphics_metadata()));
                 }
@@ -301,10 +327,22 @@ impl IOCompositor {
                     self.scroll_fragment_to_point(pipeline_id, layer_id, point);
                 }
 
+                (Ok(ScrollFragmentBy(pipeline_id, layer_id, delta)), false) => {
+                    self.scroll_fragment_by(pipeline_id, layer_id, delta);
+                }
+
                 (Ok(LoadComplete(..)), false) => {
                     self.load_complete = true;
                 }
 
+                (Ok(ChangePageZoom(page_zoom)), false) => {
+                    self.change_page_zoom(page_zoom);
+                }
+
+                (Ok(RequestAnimationFrame), false) => {
+                    self.animation_frame_requested = true;
+                }
+
                 // When we are shutting_down, we need to avoid performing operations
                 // such as Paint that may crash because we have begun tearing down
                 // the rest of our resources.
@@ -417,10 +455,11 @@ impl IOCompositor {
         self.window_size.as_f32() / self.device_pixels_per_page_px()
     }
 
-    /// The size of the window in screen px.
+    /// Sends the size of the window in screen px to the constellation, which divides it by the
+    /// page zoom level to find the size of the layout viewport.
     fn send_window_size(&self) {
         let ConstellationChan(ref chan) = self.constellation_chan;
-        chan.send(ResizedWindowMsg(self.page_window()));
+        chan.send(ResizedWindowMsg(self.window_size.as_f32() / self.device_pixels_per_screen_px()));
     }
 
     fn set_layer_page_size(&mut self,
@@ -517,6 +556,20 @@ impl IOCompositor {
         }
     }
 
+    fn scroll_fragment_by(&mut self,
+                          pipeline_id: PipelineId,
+                          layer_id: LayerId,
+                          delta: Point2D<f32>) {
+        // FIXME: Like `scroll_fragment_to_point`, this only knows how to scroll the root layer.
+        let origin = match self.compositor_layer {
+            Some(ref layer) if layer.pipeline.id == pipeline_id => {
+                layer.scroll_offset.to_untyped() * -1.0 + delta
+            }
+            Some(_) | None => return,
+        };
+        self.scroll_fragment_to_point(pipeline_id, layer_id, origin);
+    }
+
     fn handle_window_message(&mut self, event: WindowEvent) {
         match event {
             IdleWindowEvent => {}
@@ -549,10 +602,18 @@ impl IOCompositor {
                 self.on_zoom_window_event(magnification);
             }
 
+            PinchZoomWindowEvent(magnification) => {
+                self.on_pinch_zoom_window_event(magnification);
+            }
+
             NavigationWindowEvent(direction) => {
                 self.on_navigation_window_event(direction);
             }
 
+            KeyWindowEvent(key, state, modifiers, c) => {
+                self.on_key_window_event(key, state, modifiers, c);
+            }
+
             FinishedWindowEvent => {
                 let exit = self.opts.exit_after_load;
                 if exit {
@@ -596,7 +657,8 @@ impl IOCompositor {
             None => fail!("Compositor: Received LoadUrlWindowEvent without initialized compositor layers"),
         };
 
-        let msg = LoadUrlMsg(root_pipeline_id, url::parse_url(url_string.as_slice(), None));
+        let load_data = LoadData::new(url::parse_url(url_string.as_slice(), None));
+        let msg = LoadUrlMsg(root_pipeline_id, load_data, constellation_msg::Load);
         let ConstellationChan(ref chan) = self.constellation_chan;
         chan.send(msg);
     }
@@ -647,7 +709,7 @@ impl IOCompositor {
     }
 
     fn device_pixels_per_page_px(&self) -> ScaleFactor<PagePx, DevicePixel, f32> {
-        self.world_zoom * self.device_pixels_per_screen_px()
+        self.viewport_zoom * self.page_zoom * self.device_pixels_per_screen_px()
     }
 
     fn update_zoom_transform(&mut self) {
@@ -655,22 +717,42 @@ impl IOCompositor {
         self.root_layer.common.borrow_mut().set_transform(identity().scale(scale.get(), scale.get(), 1f32));
     }
 
+    /// Page zoom: asks the constellation to reflow the page at the new zoom level. The constellation
+    /// answers with `ChangePageZoom`.
     fn on_zoom_window_event(&mut self, magnification: f32) {
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(ZoomMsg(magnification))
+    }
+
+    fn change_page_zoom(&mut self, page_zoom: ScaleFactor<ViewportPx, ScreenPx, f32>) {
+        if self.page_zoom == page_zoom {
+            return
+        }
+        self.page_zoom = page_zoom;
+        self.update_zoom_transform();
+        self.recomposite = true;
+        self.ask_for_tiles();
+    }
+
+    /// Pinch zoom: magnifies the page without reflowing it. Tiles at the new resolution are asked
+    /// for once the zooming stops.
+    fn on_pinch_zoom_window_event(&mut self, magnification: f32) {
         self.zoom_action = true;
         self.zoom_time = precise_time_s();
-        let old_world_zoom = self.world_zoom;
-        let window_size = self.window_size.as_f32();
+        let old_viewport_zoom = self.viewport_zoom;
+        let window_size = self.window_size.as_f32() /
+            (self.page_zoom * self.device_pixels_per_screen_px());
 
         // Determine zoom amount
-        self.world_zoom = ScaleFactor((self.world_zoom.get() * magnification).max(1.0));
-        let world_zoom = self.world_zoom;
+        self.viewport_zoom = ScaleFactor((self.viewport_zoom.get() * magnification).max(1.0));
+        let viewport_zoom = self.viewport_zoom;
 
         self.update_zoom_transform();
 
         // Scroll as needed
         let page_delta = TypedPoint2D(
-            window_size.width.get() * (world_zoom.inv() - old_world_zoom.inv()).get() * 0.5,
-            window_size.height.get() * (world_zoom.inv() - old_world_zoom.inv()).get() * 0.5);
+            window_size.width.get() * (viewport_zoom.inv() - old_viewport_zoom.inv()).get() * 0.5,
+            window_size.height.get() * (viewport_zoom.inv() - old_viewport_zoom.inv()).get() * 0.5);
         // TODO: modify delta to snap scroll to pixels.
         let page_cursor = TypedPoint2D(-1f32, -1f32); // Make sure this hits the base layer
         let page_window = self.page_window();
@@ -691,6 +773,15 @@ impl IOCompositor {
         chan.send(NavigateMsg(direction))
     }
 
+    fn on_key_window_event(&self,
+                           key: Key,
+                           state: KeyState,
+                           modifiers: KeyModifiers,
+                           c: Option<char>) {
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(KeyEvent(key, state, modifiers, c))
+    }
+
     /// Get BufferRequests from each layer.
     fn ask_for_tiles(&mut self) {
         let scale = self.device_pixels_per_page_px();
@@ -773,6 +864,20 @@ impl IOCompositor {
         }
     }
 
+    /// Tells the constellation that a new frame has begun if a page is waiting for one and a
+    /// whole frame has passed since the last one.
+    fn tick_animation_frames_if_necessary(&mut self) {
+        let now = precise_time_s();
+        if !self.animation_frame_requested ||
+                now - self.last_animation_frame < ANIMATION_FRAME_INTERVAL {
+            return
+        }
+        self.animation_frame_requested = false;
+        self.last_animation_frame = now;
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(TickAnimationFramesMsg);
+    }
+
     fn recomposite_if(&mut self, result: bool) {
         self.recomposite = result || self.recomposite;
     }
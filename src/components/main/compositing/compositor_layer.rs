@@ -752,8 +752,8 @@ impl CompositorLayer {
                     // Make a new texture and bind the layer buffer's surface to it.
                     let texture = Texture::new(target);
                     debug!("COMPOSITOR binding to native surface {:d}",
-                           buffer.native_surface.get_id() as int);
-                    buffer.native_surface.bind_to_texture(graphics_context, &texture, size);
+                           buffer.native_surface().get_id() as int);
+                    buffer.native_surface().bind_to_texture(graphics_context, &texture, size);
 
                     // Make a texture layer and add it.
                     texture_layer = Rc::new(TextureLayer::new(texture,
@@ -767,7 +767,7 @@ impl CompositorLayer {
                     texture_layer = existing_texture_layer.clone();
 
                     let texture = &existing_texture_layer.texture;
-                    buffer.native_surface.bind_to_texture(graphics_context, texture, size);
+                    buffer.native_surface().bind_to_texture(graphics_context, texture, size);
 
                     // Move on to the next sibling.
                     current_layer_child.unwrap().with_common(|common| {
@@ -11,11 +11,13 @@ use platform::Application;
 use azure::azure_hl::{SourceSurfaceMethods, Color};
 use geom::point::Point2D;
 use geom::rect::Rect;
+use geom::scale_factor::ScaleFactor;
 use geom::size::Size2D;
 use layers::platform::surface::{NativeCompositingGraphicsContext, NativeGraphicsMetadata};
 use servo_msg::compositor_msg::{Epoch, LayerBufferSet, LayerId, LayerMetadata, ReadyState};
 use servo_msg::compositor_msg::{RenderListener, RenderState, ScriptListener, ScrollPolicy};
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId};
+use servo_util::geometry::{ScreenPx, ViewportPx};
 use servo_util::opts::Opts;
 use servo_util::time::ProfilerChan;
 use std::comm::{channel, Sender, Receiver};
@@ -52,6 +54,13 @@ impl ScriptListener for CompositorChan {
 	    self.chan.send(ScrollFragmentPoint(pipeline_id, layer_id, point));
     }
 
+    fn scroll_fragment_by(&self,
+                          pipeline_id: PipelineId,
+                          layer_id: LayerId,
+                          delta: Point2D<f32>) {
+        self.chan.send(ScrollFragmentBy(pipeline_id, layer_id, delta));
+    }
+
     fn close(&self) {
         let (chan, port) = channel();
         self.chan.send(Exit(chan));
@@ -173,6 +182,8 @@ pub enum Msg {
     SetLayerClipRect(PipelineId, LayerId, Rect<f32>),
     /// Scroll a page in a window
     ScrollFragmentPoint(PipelineId, LayerId, Point2D<f32>),
+    /// Scroll a page in a window by the given distance from its current scroll position
+    ScrollFragmentBy(PipelineId, LayerId, Point2D<f32>),
     /// Requests that the compositor paint the given layer buffer set for the given page size.
     Paint(PipelineId, LayerId, Box<LayerBufferSet>, Epoch),
     /// Alerts the compositor to the current status of page loading.
@@ -181,12 +192,23 @@ pub enum Msg {
     ChangeRenderState(RenderState),
     /// Sets the channel to the current layout and render tasks, along with their id
     SetIds(SendableFrameTree, Sender<()>, ConstellationChan),
+    /// Sends the current frame tree again after a subframe was added to it.
+    SetFrameTree(SendableFrameTree),
     /// Sets the color of unrendered content for a layer.
     SetUnRenderedColor(PipelineId, LayerId, Color),
     /// The load of a page for a given URL has completed.
     LoadComplete(PipelineId, Url),
+    /// Sets the page zoom level, which the constellation keeps for the origin of the current page.
+    ChangePageZoom(ScaleFactor<ViewportPx, ScreenPx, f32>),
+    /// Asks the compositor to tell the constellation when it begins the next frame, so that pages
+    /// can run their animation frame callbacks.
+    RequestAnimationFrame,
 }
 
+/// The shortest time, in seconds, between two animation frames. This is one frame of a 60 Hz
+/// display.
+pub static ANIMATION_FRAME_INTERVAL: f64 = 1.0 / 60.0;
+
 pub enum CompositorMode {
     Windowed(Application),
     Headless
@@ -237,9 +259,10 @@ impl CompositorTask {
                                                  profiler_chan)
             }
             Headless => {
-                headless::NullCompositor::create(port,
-                                                 constellation_chan.clone(),
-                                                 profiler_chan)
+                headless::HeadlessCompositor::create(opts,
+                                                     port,
+                                                     constellation_chan.clone(),
+                                                     profiler_chan)
             }
         };
     }
@@ -3,39 +3,169 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use compositing::*;
+use constellation::SendableFrameTree;
+use pipeline::CompositionPipeline;
 
-use geom::size::TypedSize2D;
-use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, ResizedWindowMsg};
+use azure::azure_hl::Color;
+use geom::point::Point2D;
+use geom::rect::Rect;
+use geom::size::{Size2D, TypedSize2D};
+use gfx::render_task::{BufferRequest, ReRenderMsg};
+use gfx;
+use png;
+use script::dom::event::{ClickEvent, KeyEvent, MouseDownEvent, MouseUpEvent};
+use script::script_task::{ReflowAndReplyMsg, ScriptChan, SendEventMsg};
+use servo_msg::compositor_msg::{Blank, CpuContents, Epoch, FinishedLoading, FixedPosition};
+use servo_msg::compositor_msg::{IdleRenderState, LayerBuffer, LayerId, NativeSurfaceContents};
+use servo_msg::compositor_msg::{ReadyState, ScrollPolicy, Scrollable};
+use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, Key, KeyModifiers};
+use servo_msg::constellation_msg::{PipelineId, Pressed, Released, ResizedWindowMsg, Shift};
+use servo_msg::constellation_msg::TickAnimationFramesMsg;
+use servo_util::opts::Opts;
 use servo_util::time::ProfilerChan;
 use servo_util::time;
+use std::cmp;
+use std::comm::Empty;
+use std::mem;
+use std::io::timer::sleep;
+use std::path::Path;
+use time::precise_time_s;
+
+/// A pipeline of the current frame tree, whose layers the headless compositor composites.
+struct HeadlessFrame {
+    pipeline: CompositionPipeline,
+
+    /// The pipeline of the frame that contains this one, if this is a subframe.
+    parent_id: Option<PipelineId>,
+
+    /// The position and size of a subframe in the page of its parent, once known.
+    rect: Option<Rect<f32>>,
+}
+
+/// The state of a single layer in the headless compositor.
+struct HeadlessLayer {
+    /// The pipeline that this layer belongs to.
+    pipeline_id: PipelineId,
+
+    /// The ID of this layer within its pipeline.
+    id: LayerId,
+
+    /// The size of the layer contents, if known. Tiles are only requested once this is known.
+    page_size: Option<Size2D<f32>>,
+
+    /// The position and size of this layer, relative to the origin of its pipeline's root layer.
+    clip_rect: Rect<f32>,
+
+    /// Whether this layer scrolls along with the root layer.
+    scroll_policy: ScrollPolicy,
+
+    /// The color of the parts of the layer that have not been rendered.
+    unrendered_color: Color,
+
+    /// The epoch of the most recent page size. Buffers from other epochs are stale.
+    epoch: Epoch,
+
+    /// The part of the layer, in page coordinates, that tiles were most recently requested for.
+    requested_rect: Option<Rect<f32>>,
+
+    /// True if tiles have been requested and have not arrived yet.
+    waiting_for_buffers: bool,
+
+    /// The rendered tiles of this layer.
+    buffers: Vec<Box<LayerBuffer>>,
+}
 
 /// Starts the compositor, which listens for messages on the specified port.
 ///
-/// This is the null compositor which doesn't draw anything to the screen.
-/// It's intended for headless testing.
-pub struct NullCompositor {
+/// This is the headless compositor, which doesn't draw anything to the screen. It's intended for
+/// testing. When an output file is requested, it asks the renderer for tiles, which are rendered
+/// on the CPU, and assembles them into a framebuffer in main memory instead of using OpenGL.
+pub struct HeadlessCompositor {
     /// The port on which we receive messages.
-    pub port: Receiver<Msg>,
+    port: Receiver<Msg>,
+
+    /// The command line options.
+    opts: Opts,
+
+    /// The channel on which messages can be sent to the constellation.
+    constellation_chan: ConstellationChan,
+
+    /// The size of the window that the compositor pretends to have.
+    window_size: Size2D<uint>,
+
+    /// The root pipeline, which clicks are sent to.
+    root_pipeline: Option<CompositionPipeline>,
+
+    /// The pipelines of the current frame tree, through which tiles are requested. The root
+    /// pipeline comes first.
+    frames: Vec<HeadlessFrame>,
+
+    /// The layers of the pipelines of the current frame tree, in the order they are drawn. The
+    /// root layer of the root pipeline, if any, comes first.
+    layers: Vec<HeadlessLayer>,
+
+    /// The offset of the root layer and of the layers that scroll with it.
+    scroll_offset: Point2D<f32>,
+
+    /// A scroll position that was requested before the root layer existed.
+    fragment_point: Option<Point2D<f32>>,
+
+    /// The current ready state of the page.
+    ready_state: ReadyState,
+
+    /// Whether the page has finished loading.
+    load_complete: bool,
+
+    /// Receives a reply once the page has been laid out again after the keys and clicks given on
+    /// the command line. The output file is not written before then.
+    input_reflow_port: Option<Receiver<()>>,
+
+    /// Whether the renderer has gone idle at least once.
+    composite_ready: bool,
+
+    /// Whether we have begun shutting down.
+    shutting_down: bool,
+
+    /// The time at which the last animation frame began.
+    last_animation_frame: f64,
 }
 
-impl NullCompositor {
-    fn new(port: Receiver<Msg>) -> NullCompositor {
-        NullCompositor {
+impl HeadlessCompositor {
+    fn new(port: Receiver<Msg>, opts: Opts, constellation_chan: ConstellationChan)
+           -> HeadlessCompositor {
+        let window_size = Size2D(opts.initial_window_size.width.get(),
+                                 opts.initial_window_size.height.get());
+        HeadlessCompositor {
             port: port,
+            opts: opts,
+            constellation_chan: constellation_chan,
+            window_size: window_size,
+            root_pipeline: None,
+            frames: vec!(),
+            layers: vec!(),
+            scroll_offset: Point2D(0f32, 0f32),
+            fragment_point: None,
+            ready_state: Blank,
+            load_complete: false,
+            input_reflow_port: None,
+            composite_ready: false,
+            shutting_down: false,
+            last_animation_frame: 0f64,
         }
     }
 
-    pub fn create(port: Receiver<Msg>,
+    pub fn create(opts: Opts,
+                  port: Receiver<Msg>,
                   constellation_chan: ConstellationChan,
                   profiler_chan: ProfilerChan) {
-        let compositor = NullCompositor::new(port);
+        let mut compositor = HeadlessCompositor::new(port, opts, constellation_chan);
 
         // Tell the constellation about the initial fake size.
-        {
-            let ConstellationChan(ref chan) = constellation_chan;
-            chan.send(ResizedWindowMsg(TypedSize2D(640_f32, 480_f32)));
-        }
-        compositor.handle_message(constellation_chan);
+        compositor.send_window_size();
+        compositor.handle_message();
+
+        // Drop the tiles so that the renderers are not kept waiting for them.
+        compositor.layers.clear();
 
         // Drain compositor port, sometimes messages contain channels that are blocking
         // another task from finishing (i.e. SetIds)
@@ -49,39 +179,604 @@ impl NullCompositor {
         profiler_chan.send(time::ExitMsg);
     }
 
-    fn handle_message(&self, constellation_chan: ConstellationChan) {
+    fn handle_message(&mut self) {
         loop {
-            match self.port.recv() {
-                Exit(chan) => {
+            match (self.port.recv(), self.shutting_down) {
+                (Exit(chan), _) => {
                     debug!("shutting down the constellation");
-                    let ConstellationChan(ref con_chan) = constellation_chan;
+                    let ConstellationChan(ref con_chan) = self.constellation_chan;
                     con_chan.send(ExitMsg);
                     chan.send(());
+                    self.shutting_down = true;
                 }
 
-                ShutdownComplete => {
+                (ShutdownComplete, _) => {
                     debug!("constellation completed shutdown");
                     break
                 }
 
-                GetGraphicsMetadata(chan) => {
+                (GetGraphicsMetadata(chan), _) => {
                     chan.send(None);
                 }
 
-                SetIds(_, response_chan, _) => {
+                (SetIds(frame_tree, response_chan, new_constellation_chan), _) => {
                     response_chan.send(());
+                    self.root_pipeline = Some(frame_tree.pipeline.clone());
+                    self.set_frame_tree(&frame_tree);
+                    self.constellation_chan = new_constellation_chan;
+                    self.send_window_size();
+                }
+
+                (SetFrameTree(frame_tree), false) => {
+                    self.set_frame_tree(&frame_tree);
+                }
+
+                (CreateRootCompositorLayerIfNecessary(pipeline_id, layer_id, size, color),
+                 false) => {
+                    self.create_root_layer_if_necessary(pipeline_id, layer_id, size, color);
+                }
+
+                (CreateDescendantCompositorLayerIfNecessary(pipeline_id,
+                                                            layer_id,
+                                                            rect,
+                                                            scroll_policy),
+                 false) => {
+                    self.create_descendant_layer_if_necessary(pipeline_id,
+                                                              layer_id,
+                                                              rect,
+                                                              scroll_policy);
+                }
+
+                (SetLayerPageSize(pipeline_id, layer_id, new_size, epoch), false) => {
+                    self.set_layer_page_size(pipeline_id, layer_id, new_size, epoch);
+                }
+
+                (SetLayerClipRect(pipeline_id, layer_id, new_rect), false) => {
+                    self.set_layer_clip_rect(pipeline_id, layer_id, new_rect);
+                }
+
+                (SetUnRenderedColor(pipeline_id, layer_id, color), false) => {
+                    match self.find_layer(pipeline_id, layer_id) {
+                        Some(layer) => layer.unrendered_color = color,
+                        None => {}
+                    }
+                }
+
+                (Paint(pipeline_id, layer_id, new_layer_buffer_set, epoch), false) => {
+                    match self.find_layer(pipeline_id, layer_id) {
+                        Some(layer) if layer.epoch == epoch => {
+                            layer.buffers = new_layer_buffer_set.buffers;
+                            layer.waiting_for_buffers = false;
+                        }
+                        Some(_) | None => {
+                            debug!("headless compositor: ignoring stale or unknown paint");
+                        }
+                    }
+                }
+
+                (ScrollFragmentPoint(pipeline_id, layer_id, point), false) => {
+                    self.scroll_fragment_to_point(pipeline_id, layer_id, point);
+                }
+
+                (ScrollFragmentBy(pipeline_id, layer_id, delta), false) => {
+                    let origin = self.scroll_offset * -1.0 + delta;
+                    self.scroll_fragment_to_point(pipeline_id, layer_id, origin);
+                }
+
+                (ChangeReadyState(ready_state), false) => {
+                    self.ready_state = ready_state;
+                }
+
+                (ChangeRenderState(render_state), false) => {
+                    if render_state == IdleRenderState {
+                        self.composite_ready = true;
+                    }
+                }
+
+                (LoadComplete(..), false) => {
+                    self.load_complete = true;
+                    self.send_input();
+                }
+
+                // Nobody zooms a headless page, so its zoom level stays at one.
+                (ChangePageZoom(..), _) => {}
+
+                (RequestAnimationFrame, false) => {
+                    self.tick_animation_frames();
+                }
+
+                // When we are shutting_down, we need to avoid performing operations
+                // such as Paint that may crash because we have begun tearing down
+                // the rest of our resources.
+                (_, true) => { }
+            }
+
+            if !self.shutting_down {
+                self.write_output_file_if_ready();
+            }
+        }
+    }
+
+    /// Tells the constellation that a new frame has begun. There is no display to keep pace with,
+    /// so this happens as soon as a page asks for a frame, but no sooner than a windowed
+    /// compositor would begin one.
+    fn tick_animation_frames(&mut self) {
+        let elapsed = precise_time_s() - self.last_animation_frame;
+        if elapsed < ANIMATION_FRAME_INTERVAL {
+            sleep(((ANIMATION_FRAME_INTERVAL - elapsed) * 1000.0) as u64);
+        }
+        self.last_animation_frame = precise_time_s();
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(TickAnimationFramesMsg);
+    }
+
+    fn send_window_size(&self) {
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(ResizedWindowMsg(TypedSize2D(self.window_size.width as f32,
+                                               self.window_size.height as f32)));
+    }
+
+    /// Records the pipelines of the given frame tree, and drops the layers of pipelines that are
+    /// no longer part of it.
+    fn set_frame_tree(&mut self, frame_tree: &SendableFrameTree) {
+        self.frames.clear();
+        self.add_frames(frame_tree, None, None);
+        let frames = &self.frames;
+        self.layers.retain(|layer| {
+            frames.iter().any(|frame| frame.pipeline.id == layer.pipeline_id)
+        });
+        self.ask_for_tiles();
+    }
+
+    fn add_frames(&mut self,
+                  frame_tree: &SendableFrameTree,
+                  parent_id: Option<PipelineId>,
+                  rect: Option<Rect<f32>>) {
+        self.frames.push(HeadlessFrame {
+            pipeline: frame_tree.pipeline.clone(),
+            parent_id: parent_id,
+            rect: rect,
+        });
+        for child in frame_tree.children.iter() {
+            self.add_frames(&child.frame_tree,
+                            Some(frame_tree.pipeline.id),
+                            child.rect.map(|rect| rect.to_untyped()));
+        }
+    }
+
+    /// Returns true if the given pipeline is the root pipeline.
+    fn is_root_pipeline(&self, pipeline_id: PipelineId) -> bool {
+        match self.root_pipeline {
+            Some(ref pipeline) => pipeline.id == pipeline_id,
+            None => false,
+        }
+    }
+
+    fn find_frame<'a>(&'a self, pipeline_id: PipelineId) -> Option<&'a HeadlessFrame> {
+        self.frames.iter().find(|frame| frame.pipeline.id == pipeline_id)
+    }
+
+    fn find_layer<'a>(&'a mut self, pipeline_id: PipelineId, layer_id: LayerId)
+                  -> Option<&'a mut HeadlessLayer> {
+        self.layers.mut_iter().find(|layer| {
+            layer.pipeline_id == pipeline_id && layer.id == layer_id
+        })
+    }
+
+    fn create_root_layer_if_necessary(&mut self,
+                                      pipeline_id: PipelineId,
+                                      layer_id: LayerId,
+                                      size: Size2D<f32>,
+                                      color: Color) {
+        if self.find_frame(pipeline_id).is_none() {
+            debug!("headless compositor: ignoring root layer of a pipeline that isn't shown");
+            return
+        }
+        match self.layers.iter().find(|layer| layer.pipeline_id == pipeline_id) {
+            Some(root_layer) if root_layer.id == layer_id => return,
+            Some(_) | None => {}
+        }
+
+        // A new root layer replaces all the layers of its pipeline. The root pipeline's comes
+        // first, so that the layers of subframes are drawn over it.
+        self.layers.retain(|layer| layer.pipeline_id != pipeline_id);
+        let mut layer = HeadlessLayer::new(pipeline_id,
+                                           layer_id,
+                                           Rect(Point2D(0f32, 0f32), size),
+                                           Scrollable);
+        layer.unrendered_color = color;
+        if self.is_root_pipeline(pipeline_id) {
+            self.scroll_offset = Point2D(0f32, 0f32);
+            self.layers.insert(0, layer);
+        } else {
+            self.layers.push(layer);
+        }
+    }
+
+    fn create_descendant_layer_if_necessary(&mut self,
+                                            pipeline_id: PipelineId,
+                                            layer_id: LayerId,
+                                            rect: Rect<f32>,
+                                            scroll_policy: ScrollPolicy) {
+        if self.find_frame(pipeline_id).is_none() {
+            return
+        }
+        if !self.layers.iter().any(|layer| layer.pipeline_id == pipeline_id) {
+            fail!("headless compositor: trying to create a descendant layer without a root layer")
+        }
+        if self.find_layer(pipeline_id, layer_id).is_some() {
+            return
+        }
+        self.layers.push(HeadlessLayer::new(pipeline_id, layer_id, rect, scroll_policy))
+    }
+
+    fn set_layer_page_size(&mut self,
+                           pipeline_id: PipelineId,
+                           layer_id: LayerId,
+                           new_size: Size2D<f32>,
+                           epoch: Epoch) {
+        match self.find_layer(pipeline_id, layer_id) {
+            Some(layer) => {
+                layer.page_size = Some(new_size);
+                layer.epoch = epoch;
+                layer.requested_rect = None;
+            }
+            None => return,
+        }
+
+        match self.fragment_point.take() {
+            Some(point) => self.scroll_fragment_to_point(pipeline_id, layer_id, point),
+            None => {}
+        }
+        self.ask_for_tiles();
+    }
+
+    /// Moves or resizes a layer. The constellation sends the rect of a subframe in the page of
+    /// its parent as the clip rect of the null layer of the subframe's pipeline.
+    fn set_layer_clip_rect(&mut self,
+                           pipeline_id: PipelineId,
+                           layer_id: LayerId,
+                           new_rect: Rect<f32>) {
+        let found_layer = match self.find_layer(pipeline_id, layer_id) {
+            Some(layer) => {
+                layer.clip_rect = new_rect;
+                true
+            }
+            None => false,
+        };
+        if !found_layer {
+            if layer_id != LayerId::null() {
+                return
+            }
+            match self.frames.mut_iter().find(|frame| frame.pipeline.id == pipeline_id) {
+                Some(frame) if frame.parent_id.is_some() => frame.rect = Some(new_rect),
+                Some(_) | None => return,
+            }
+        }
+        self.ask_for_tiles();
+    }
+
+    /// Sends the clicks and keys given on the command line to the root pipeline, and then asks
+    /// it to lay the page out again. This only happens for the first page that loads.
+    fn send_input(&mut self) {
+        let text = self.opts.send_keys.take();
+        let points = mem::replace(&mut self.opts.send_clicks, vec!());
+        if text.is_none() && points.is_empty() {
+            return
+        }
+        let reply_port = {
+            let pipeline = match self.root_pipeline {
+                Some(ref pipeline) => pipeline,
+                None => return,
+            };
+            self.send_input_to_pipeline(pipeline, text, points.as_slice())
+        };
+        self.input_reflow_port = Some(reply_port);
+    }
+
+    /// Sends the given clicks and keys to the given pipeline, followed by a request to lay the page
+    /// out again. Returns the port on which the reply to that request arrives.
+    fn send_input_to_pipeline(&self,
+                              pipeline: &CompositionPipeline,
+                              text: Option<String>,
+                              points: &[Point2D<f32>])
+                              -> Receiver<()> {
+        // The clicks come first, so that they can focus the element that the keys are typed into.
+        let ScriptChan(ref chan) = pipeline.script_chan;
+        for point in points.iter() {
+            // Points are given in page coordinates; the scroll offset is negative.
+            let point = *point - self.scroll_offset;
+            let _ = chan.send_opt(SendEventMsg(pipeline.id, MouseDownEvent(0, point)));
+            let _ = chan.send_opt(SendEventMsg(pipeline.id, MouseUpEvent(0, point)));
+            let _ = chan.send_opt(SendEventMsg(pipeline.id, ClickEvent(0, point)));
+        }
+
+        // The keys go straight to the script task, like the clicks, rather than through the
+        // constellation, so that they are handled before the reflow that is requested after them.
+        for c in text.iter().flat_map(|text| text.as_slice().chars()) {
+            let (key, shift) = Key::from_char(c);
+            let modifiers = if shift { Shift } else { KeyModifiers::empty() };
+            let _ = chan.send_opt(SendEventMsg(pipeline.id, KeyEvent(key, Pressed, modifiers,
+                                                                     Some(c))));
+            let _ = chan.send_opt(SendEventMsg(pipeline.id, KeyEvent(key, Released, modifiers,
+                                                                     Some(c))));
+        }
+
+        let (reply_chan, reply_port) = channel();
+        let _ = chan.send_opt(ReflowAndReplyMsg(pipeline.id, reply_chan));
+        reply_port
+    }
+
+    /// Returns true once the page has been laid out again after the keys and clicks given on the
+    /// command line, if any. Tiles are then requested again for every layer. Layout sent any new
+    /// display list to the renderers before it finished, so these requests are handled after it.
+    fn input_reflow_finished(&mut self) -> bool {
+        match self.input_reflow_port {
+            Some(ref port) => {
+                match port.try_recv() {
+                    Err(Empty) => return false,
+                    Ok(()) | Err(_) => {}
                 }
+            }
+            None => return true,
+        }
+        self.input_reflow_port = None;
+        for layer in self.layers.mut_iter() {
+            layer.requested_rect = None;
+        }
+        self.ask_for_tiles();
+        true
+    }
 
-                // Explicitly list ignored messages so that when we add a new one,
-                // we'll notice and think about whether it needs a response, like
-                // SetIds.
+    fn scroll_fragment_to_point(&mut self,
+                                pipeline_id: PipelineId,
+                                layer_id: LayerId,
+                                point: Point2D<f32>) {
+        // Only the root layer scrolls.
+        let page_size = match self.layers.as_slice().head() {
+            Some(root) if self.is_root_pipeline(pipeline_id) && root.id == layer_id => {
+                root.page_size
+            }
+            Some(_) | None => None,
+        };
+        let page_size = match page_size {
+            Some(page_size) => page_size,
+            None => {
+                self.fragment_point = Some(point);
+                return
+            }
+        };
 
-                CreateRootCompositorLayerIfNecessary(..) |
-                CreateDescendantCompositorLayerIfNecessary(..) | SetLayerPageSize(..) |
-                SetLayerClipRect(..) | Paint(..) |
-                ChangeReadyState(..) | ChangeRenderState(..) | ScrollFragmentPoint(..) |
-                SetUnRenderedColor(..) | LoadComplete(..) => ()
+        let min_x = (self.window_size.width as f32 - page_size.width).min(0.0);
+        let min_y = (self.window_size.height as f32 - page_size.height).min(0.0);
+        self.scroll_offset = Point2D((-point.x).clamp(&min_x, &0.0),
+                                     (-point.y).clamp(&min_y, &0.0));
+        self.ask_for_tiles();
+    }
+
+    /// Returns the rect that the page of the given subframe takes up in the root page, or `None`
+    /// if it is not known yet.
+    fn subframe_rect(&self, frame: &HeadlessFrame) -> Option<Rect<f32>> {
+        let rect = match frame.rect {
+            Some(rect) => rect,
+            None => return None,
+        };
+        match frame.parent_id.and_then(|parent_id| self.find_frame(parent_id)) {
+            Some(parent) if parent.parent_id.is_some() => {
+                self.subframe_rect(parent).map(|parent_rect| rect.translate(&parent_rect.origin))
+            }
+            Some(_) | None => Some(rect),
+        }
+    }
+
+    /// Returns the position of the given layer's origin in the window, and the part of the window
+    /// that the layer is clipped to, or `None` if the layer isn't shown.
+    fn layer_placement(&self, layer: &HeadlessLayer) -> Option<(Point2D<f32>, Rect<f32>)> {
+        if self.is_root_pipeline(layer.pipeline_id) {
+            let origin = match layer.scroll_policy {
+                Scrollable => layer.clip_rect.origin + self.scroll_offset,
+                FixedPosition => layer.clip_rect.origin,
+            };
+            return Some((origin, Rect(origin, layer.clip_rect.size)))
+        }
+
+        // The layers of subframes scroll along with the root page, and are clipped to their
+        // frame.
+        let frame_rect = match self.find_frame(layer.pipeline_id) {
+            Some(frame) => self.subframe_rect(frame),
+            None => None,
+        };
+        let frame_rect = match frame_rect {
+            Some(frame_rect) => frame_rect.translate(&self.scroll_offset),
+            None => return None,
+        };
+        let origin = frame_rect.origin + layer.clip_rect.origin;
+        Rect(origin, layer.clip_rect.size).intersection(&frame_rect).map(|clip_rect| {
+            (origin, clip_rect)
+        })
+    }
+
+    /// Requests tiles for the visible part of every layer whose visible part has changed since
+    /// tiles were last requested. Tiles are only needed when writing an output file.
+    fn ask_for_tiles(&mut self) {
+        if self.opts.output_file.is_none() {
+            return
+        }
+        let window_rect = Rect(Point2D(0f32, 0f32),
+                               Size2D(self.window_size.width as f32,
+                                      self.window_size.height as f32));
+        let tile_size = self.opts.tile_size as f32;
+
+        for i in range(0, self.layers.len()) {
+            let visible_rect = {
+                let layer = self.layers.get(i);
+                let page_size = match layer.page_size {
+                    Some(page_size) => page_size,
+                    None => continue,
+                };
+
+                // Find the part of the layer that is both inside its clip rect and the window,
+                // in the coordinate system of the layer.
+                let (origin, clip_rect) = match self.layer_placement(layer) {
+                    Some(placement) => placement,
+                    None => continue,
+                };
+                let layer_rect = Rect(origin, page_size).intersection(&clip_rect);
+                match layer_rect.and_then(|rect| rect.intersection(&window_rect)) {
+                    Some(rect) => {
+                        let rect = rect.translate(&(origin * -1.0));
+                        Rect(Point2D(rect.origin.x.floor(), rect.origin.y.floor()),
+                             Size2D(rect.size.width.ceil(), rect.size.height.ceil()))
+                    }
+                    None => Rect(Point2D(0f32, 0f32), Size2D(0f32, 0f32)),
+                }
+            };
+
+            let layer = self.layers.get_mut(i);
+            if layer.requested_rect == Some(visible_rect) {
+                continue
+            }
+            layer.requested_rect = Some(visible_rect);
+            if visible_rect.size.width <= 0.0 || visible_rect.size.height <= 0.0 {
+                layer.buffers.clear();
+                layer.waiting_for_buffers = false;
+                continue
+            }
+
+            let mut requests = vec!();
+            let mut y = visible_rect.origin.y;
+            while y < visible_rect.max_y() {
+                let height = tile_size.min(visible_rect.max_y() - y);
+                let mut x = visible_rect.origin.x;
+                while x < visible_rect.max_x() {
+                    let width = tile_size.min(visible_rect.max_x() - x);
+                    let page_rect = Rect(Point2D(x, y), Size2D(width, height));
+                    let screen_rect = Rect(Point2D(x as uint, y as uint),
+                                           Size2D(width as uint, height as uint));
+                    requests.push(BufferRequest(screen_rect, page_rect));
+                    x += width;
+                }
+                y += height;
+            }
+
+            layer.waiting_for_buffers = true;
+            let msg = ReRenderMsg(requests, 1.0, layer.id, layer.epoch);
+            match self.frames.iter().find(|frame| frame.pipeline.id == layer.pipeline_id) {
+                Some(frame) => drop(frame.pipeline.render_chan.send_opt(msg)),
+                None => {}
             }
         }
     }
+
+    /// Composites the layers and writes the result to the output file once the page has loaded
+    /// and every layer has been rendered. Then shuts down, like the windowed compositor.
+    fn write_output_file_if_ready(&mut self) {
+        let path = match self.opts.output_file {
+            Some(ref output_file) => from_str::<Path>(output_file.as_slice()).unwrap(),
+            None => return,
+        };
+        let has_root_layer = match self.layers.as_slice().head() {
+            Some(layer) => self.is_root_pipeline(layer.pipeline_id),
+            None => false,
+        };
+        if !self.load_complete || !self.input_reflow_finished() ||
+                self.ready_state != FinishedLoading || !self.composite_ready || !has_root_layer ||
+                self.layers.iter().any(|layer| {
+                    layer.page_size.is_none() || layer.waiting_for_buffers
+                }) {
+            return
+        }
+
+        let img = png::Image {
+            width: self.window_size.width as u32,
+            height: self.window_size.height as u32,
+            color_type: png::RGB8,
+            pixels: self.composite(),
+        };
+        let res = png::store_png(&img, &path);
+        assert!(res.is_ok());
+
+        debug!("shutting down the constellation after generating an output file");
+        let ConstellationChan(ref chan) = self.constellation_chan;
+        chan.send(ExitMsg);
+        self.shutting_down = true;
+    }
+
+    /// Blends the tiles of every layer, in order, into an RGB framebuffer the size of the window.
+    fn composite(&self) -> Vec<u8> {
+        let (width, height) = (self.window_size.width, self.window_size.height);
+        let stride = width * 3;
+        let background = self.layers.get(0).unrendered_color;
+        let mut pixels = Vec::with_capacity(stride * height);
+        for _ in range(0, width * height) {
+            pixels.push((background.r * 255.0) as u8);
+            pixels.push((background.g * 255.0) as u8);
+            pixels.push((background.b * 255.0) as u8);
+        }
+
+        for layer in self.layers.iter() {
+            let (origin, clip_rect) = match self.layer_placement(layer) {
+                Some(placement) => placement,
+                None => continue,
+            };
+            let (origin_x, origin_y) = (origin.x as int, origin.y as int);
+
+            // Clip to the layer's clip rect and to the window.
+            let clip_x0 = cmp::max(clip_rect.origin.x as int, 0);
+            let clip_y0 = cmp::max(clip_rect.origin.y as int, 0);
+            let clip_x1 = cmp::min(clip_rect.max_x() as int, width as int);
+            let clip_y1 = cmp::min(clip_rect.max_y() as int, height as int);
+
+            for buffer in layer.buffers.iter() {
+                let data = match buffer.contents {
+                    CpuContents(ref data) => data,
+                    NativeSurfaceContents(_) => {
+                        fail!("headless compositor: expected a buffer rendered on the CPU")
+                    }
+                };
+                let buffer_x = origin_x + buffer.rect.origin.x as int;
+                let buffer_y = origin_y + buffer.rect.origin.y as int;
+                let x0 = cmp::max(buffer_x, clip_x0);
+                let y0 = cmp::max(buffer_y, clip_y0);
+                let x1 = cmp::min(buffer_x + buffer.screen_pos.size.width as int, clip_x1);
+                let y1 = cmp::min(buffer_y + buffer.screen_pos.size.height as int, clip_y1);
+
+                for y in range(y0, y1) {
+                    for x in range(x0, x1) {
+                        // The source is premultiplied B8G8R8A8.
+                        let src = ((y - buffer_y) as uint) * buffer.stride +
+                            ((x - buffer_x) as uint) * 4;
+                        let dst = (y as uint) * stride + (x as uint) * 3;
+                        let alpha = *data.get(src + 3) as uint;
+                        for (channel, offset) in [2u, 1, 0].iter().enumerate() {
+                            let old = *pixels.get(dst + channel) as uint;
+                            let new = *data.get(src + *offset) as uint;
+                            *pixels.get_mut(dst + channel) =
+                                (new + old * (255 - alpha) / 255) as u8;
+                        }
+                    }
+                }
+            }
+        }
+
+        pixels
+    }
+}
+
+impl HeadlessLayer {
+    fn new(pipeline_id: PipelineId, id: LayerId, clip_rect: Rect<f32>, scroll_policy: ScrollPolicy)
+           -> HeadlessLayer {
+        HeadlessLayer {
+            pipeline_id: pipeline_id,
+            id: id,
+            page_size: None,
+            clip_rect: clip_rect,
+            scroll_policy: scroll_policy,
+            unrendered_color: gfx::color::rgba(0.0, 0.0, 0.0, 0.0),
+            epoch: Epoch(0),
+            requested_rect: None,
+            waiting_for_buffers: false,
+            buffers: vec!(),
+        }
+    }
 }
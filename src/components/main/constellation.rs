@@ -2,37 +2,48 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use compositing::{CompositorChan, LoadComplete, SetIds, SetLayerClipRect, ShutdownComplete};
+use compositing::{ChangePageZoom, CompositorChan, LoadComplete, SetIds, SetLayerClipRect};
+use compositing::{RequestAnimationFrame, SetFrameTree, ShutdownComplete};
 
 use collections::hashmap::{HashMap, HashSet};
 use geom::rect::{Rect, TypedRect};
+use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use gfx::render_task;
 use libc;
 use pipeline::{Pipeline, CompositionPipeline};
-use script::script_task::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg};
+use script::dom::event;
+use script::script_task::{ResizeMsg, ResizeInactiveMsg, ExitPipelineMsg, SendEventMsg};
+use script::script_task::{AnimationFrameMsg, RestoreHistoryEntryMsg, StorageEventMsg};
 use script::layout_interface;
 use script::layout_interface::LayoutChan;
 use script::script_task::ScriptChan;
 use servo_msg::compositor_msg::LayerId;
 use servo_msg::constellation_msg::{ConstellationChan, ExitMsg, FailureMsg, Failure, FrameRectMsg};
-use servo_msg::constellation_msg::{IFrameSandboxState, IFrameUnsandboxed, InitLoadUrlMsg};
+use servo_msg::constellation_msg::{GetHistoryLengthMsg, HistoryStateId, IFrameSandboxState};
+use servo_msg::constellation_msg::{IFrameUnsandboxed, InitLoadUrlMsg};
+use servo_msg::constellation_msg::{Key, KeyModifiers, KeyState};
 use servo_msg::constellation_msg::{LoadCompleteMsg, LoadIframeUrlMsg, LoadUrlMsg, Msg, NavigateMsg};
 use servo_msg::constellation_msg::{NavigationType, PipelineId, RendererReadyMsg, ResizedWindowMsg};
-use servo_msg::constellation_msg::SubpageId;
+use servo_msg::constellation_msg::{RequestAnimationFrameMsg, SameDocumentNavigationMsg, SubpageId};
+use servo_msg::constellation_msg::{StorageChangedMsg, TickAnimationFramesMsg, TraverseHistoryMsg};
+use servo_msg::constellation_msg::ZoomMsg;
 use servo_msg::constellation_msg;
 use servo_net::image_cache_task::{ImageCacheTask, ImageCacheTaskClient};
-use servo_net::resource_task::ResourceTask;
+use servo_net::resource_task::{LoadData, ResourceTask};
 use servo_net::resource_task;
-use servo_util::geometry::PagePx;
+use servo_net::storage_task::{StorageTask, StorageType};
+use servo_net::storage_task;
+use servo_util::geometry::{PagePx, ScreenPx, ViewportPx};
 use servo_util::opts::Opts;
 use servo_util::time::ProfilerChan;
-use servo_util::url::parse_url;
+use servo_util::url::{origin, parse_url};
 use servo_util::task::spawn_named;
 use std::cell::RefCell;
 use std::mem::replace;
 use std::io;
 use std::rc::Rc;
+use time::precise_time_s;
 use url::Url;
 
 /// Maintains the pipelines and navigation context and grants permission to composite
@@ -41,6 +52,7 @@ pub struct Constellation {
     pub request_port: Receiver<Msg>,
     pub compositor_chan: CompositorChan,
     pub resource_task: ResourceTask,
+    pub storage_task: StorageTask,
     pub image_cache_task: ImageCacheTask,
     pub pipelines: HashMap<PipelineId, Rc<Pipeline>>,
     navigation_context: NavigationContext,
@@ -48,10 +60,25 @@ pub struct Constellation {
     pending_frames: Vec<FrameChange>,
     pending_sizes: HashMap<(PipelineId, SubpageId), TypedRect<PagePx, f32>>,
     pub profiler_chan: ProfilerChan,
-    pub window_size: TypedSize2D<PagePx, f32>,
+    /// The size of the window, in screen px.
+    pub window_size: TypedSize2D<ScreenPx, f32>,
+    /// The page zoom level of each origin that the user has zoomed, keyed by `origin()`.
+    page_zooms: HashMap<String, ScaleFactor<ViewportPx, ScreenPx, f32>>,
+    /// The pipelines whose documents are waiting for an animation frame.
+    animation_frame_requests: HashSet<PipelineId>,
+    /// The time at which the pipelines that are not visible were last given an animation frame.
+    last_hidden_animation_frame: f64,
     pub opts: Opts,
 }
 
+/// The shortest time, in seconds, between two animation frames of a pipeline that is not
+/// visible.
+static HIDDEN_ANIMATION_FRAME_INTERVAL: f64 = 1.0;
+
+/// The smallest and largest page zoom levels.
+static MIN_PAGE_ZOOM: f32 = 0.3;
+static MAX_PAGE_ZOOM: f32 = 3.0;
+
 /// Stores the Id of the outermost frame's pipeline, along with a vector of children frames
 struct FrameTree {
     pub pipeline: Rc<Pipeline>,
@@ -168,11 +195,42 @@ struct FrameChange {
     pub navigation_type: NavigationType,
 }
 
-/// Stores the Id's of the pipelines previous and next in the browser's history
+/// An entry in the session history. An entry that a document added without loading a new
+/// document, by navigating to a fragment or with `pushState()`, shares the frame tree of the
+/// entry before it, and differs from it only in the URL and state of that document.
+#[deriving(Clone)]
+struct SessionHistoryEntry {
+    pub frame_tree: Rc<FrameTree>,
+    /// The pipeline whose document this entry is for.
+    pub pipeline_id: PipelineId,
+    /// The URL of that document in this entry.
+    pub url: Url,
+    /// The ID of the state object that the script task keeps for this entry, if it has one.
+    pub state_id: Option<HistoryStateId>,
+}
+
+impl SessionHistoryEntry {
+    /// Creates the entry for the document that a frame tree just loaded.
+    fn new(frame_tree: Rc<FrameTree>) -> SessionHistoryEntry {
+        SessionHistoryEntry {
+            pipeline_id: frame_tree.pipeline.id,
+            url: frame_tree.pipeline.load_data.url.clone(),
+            state_id: None,
+            frame_tree: frame_tree,
+        }
+    }
+
+    /// Returns true if both entries show the same documents.
+    fn shares_frame_tree(&self, other: &SessionHistoryEntry) -> bool {
+        &*self.frame_tree as *FrameTree == &*other.frame_tree as *FrameTree
+    }
+}
+
+/// Stores the session history entries previous and next in the browser's history
 struct NavigationContext {
-    pub previous: Vec<Rc<FrameTree>>,
-    pub next: Vec<Rc<FrameTree>>,
-    pub current: Option<Rc<FrameTree>>,
+    pub previous: Vec<SessionHistoryEntry>,
+    pub next: Vec<SessionHistoryEntry>,
+    pub current: Option<SessionHistoryEntry>,
 }
 
 impl NavigationContext {
@@ -187,43 +245,68 @@ impl NavigationContext {
     /* Note that the following two methods can fail. They should only be called  *
      * when it is known that there exists either a previous page or a next page. */
 
-    fn back(&mut self) -> Rc<FrameTree> {
+    fn back(&mut self) -> SessionHistoryEntry {
         self.next.push(self.current.take_unwrap());
         let prev = self.previous.pop().unwrap();
         self.current = Some(prev.clone());
         prev
     }
 
-    fn forward(&mut self) -> Rc<FrameTree> {
+    fn forward(&mut self) -> SessionHistoryEntry {
         self.previous.push(self.current.take_unwrap());
         let next = self.next.pop().unwrap();
         self.current = Some(next.clone());
         next
     }
 
-    /// Loads a new set of page frames, returning all evicted frame trees
-    fn load(&mut self, frame_tree: Rc<FrameTree>) -> Vec<Rc<FrameTree>> {
-        debug!("navigating to {:?}", frame_tree.pipeline.id);
+    /// Returns the number of entries in the session history.
+    fn len(&self) -> uint {
+        let current = if self.current.is_some() { 1 } else { 0 };
+        self.previous.len() + current + self.next.len()
+    }
+
+    /// Adds a new entry after the current one, returning the frame trees of all evicted entries
+    fn load(&mut self, entry: SessionHistoryEntry) -> Vec<Rc<FrameTree>> {
+        debug!("navigating to {:?}", entry.frame_tree.pipeline.id);
         let evicted = replace(&mut self.next, vec!());
         if self.current.is_some() {
             self.previous.push(self.current.take_unwrap());
         }
-        self.current = Some(frame_tree.clone());
-        evicted
+        self.current = Some(entry);
+        evicted.move_iter().map(|entry| entry.frame_tree).collect()
+    }
+
+    /// Replaces the current entry with a new one, returning the frame trees of all evicted
+    /// entries
+    fn replace(&mut self, entry: SessionHistoryEntry) -> Vec<Rc<FrameTree>> {
+        debug!("replacing the current entry with {:?}", entry.frame_tree.pipeline.id);
+        let mut evicted = replace(&mut self.next, vec!());
+        match self.current.take() {
+            Some(current) => evicted.push(current),
+            None => {}
+        }
+        self.current = Some(entry);
+        evicted.move_iter().map(|entry| entry.frame_tree).collect()
     }
 
-    /// Returns the frame trees whose keys are pipeline_id.
+    /// Returns the frame trees whose keys are pipeline_id. Entries that share a frame tree
+    /// only contribute it once.
     fn find_all(&mut self, pipeline_id: PipelineId) -> Vec<Rc<FrameTree>> {
-        let from_current = self.current.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
-        });
-        let from_next = self.next.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
-        });
-        let from_prev = self.previous.iter().filter_map(|frame_tree| {
-            frame_tree.find(pipeline_id)
-        });
-        from_prev.chain(from_current).chain(from_next).collect()
+        let mut found: Vec<Rc<FrameTree>> = vec!();
+        let all_entries = self.previous.iter().chain(self.current.iter()).chain(self.next.iter());
+        for entry in all_entries {
+            let frame_tree = match entry.frame_tree.find(pipeline_id) {
+                Some(frame_tree) => frame_tree,
+                None => continue,
+            };
+            let is_new = found.iter().all(|other| {
+                &**other as *FrameTree != &*frame_tree as *FrameTree
+            });
+            if is_new {
+                found.push(frame_tree);
+            }
+        }
+        found
     }
 
     fn contains(&mut self, pipeline_id: PipelineId) -> bool {
@@ -232,8 +315,8 @@ impl NavigationContext {
         let from_prev = self.previous.iter();
 
         let mut all_contained = from_prev.chain(from_current).chain(from_next);
-        all_contained.any(|frame_tree| {
-            frame_tree.contains(pipeline_id)
+        all_contained.any(|entry| {
+            entry.frame_tree.contains(pipeline_id)
         })
     }
 }
@@ -242,6 +325,7 @@ impl Constellation {
     pub fn start(compositor_chan: CompositorChan,
                  opts: &Opts,
                  resource_task: ResourceTask,
+                 storage_task: StorageTask,
                  image_cache_task: ImageCacheTask,
                  profiler_chan: ProfilerChan)
                  -> ConstellationChan {
@@ -254,6 +338,7 @@ impl Constellation {
                 request_port: constellation_port,
                 compositor_chan: compositor_chan,
                 resource_task: resource_task,
+                storage_task: storage_task,
                 image_cache_task: image_cache_task,
                 pipelines: HashMap::new(),
                 navigation_context: NavigationContext::new(),
@@ -262,6 +347,9 @@ impl Constellation {
                 pending_sizes: HashMap::new(),
                 profiler_chan: profiler_chan,
                 window_size: TypedSize2D(800_f32, 600_f32),
+                page_zooms: HashMap::new(),
+                animation_frame_requests: HashSet::new(),
+                last_hidden_animation_frame: 0f64,
                 opts: opts_clone,
             };
             constellation.run();
@@ -278,6 +366,21 @@ impl Constellation {
         }
     }
 
+    /// Returns the page zoom level of the origin of the given URL.
+    fn page_zoom(&self, url: &Url) -> ScaleFactor<ViewportPx, ScreenPx, f32> {
+        match self.page_zooms.find(&origin(url)) {
+            Some(page_zoom) => *page_zoom,
+            None => ScaleFactor(1.0),
+        }
+    }
+
+    /// Returns the size of the layout viewport of a page at the given URL.
+    fn viewport_size(&self, url: &Url) -> TypedSize2D<PagePx, f32> {
+        let size = self.window_size / self.page_zoom(url);
+        // Pinch zoom is left to the compositor, so layout sees one page px per viewport px.
+        TypedSize2D::from_untyped(&size.to_untyped())
+    }
+
     /// Helper function for getting a unique pipeline Id
     fn get_next_pipeline_id(&mut self) -> PipelineId {
         let id = self.next_pipeline_id;
@@ -288,8 +391,8 @@ impl Constellation {
 
     /// Convenience function for getting the currently active frame tree.
     /// The currently active frame tree should always be the current painter
-    fn current_frame<'a>(&'a self) -> &'a Option<Rc<FrameTree>> {
-        &self.navigation_context.current
+    fn current_frame<'a>(&'a self) -> Option<&'a Rc<FrameTree>> {
+        self.navigation_context.current.as_ref().map(|entry| &entry.frame_tree)
     }
 
     /// Returns both the navigation context and pending frame trees whose keys are pipeline_id.
@@ -330,9 +433,9 @@ impl Constellation {
             // Load a new page, usually -- but not always -- from a mouse click or typed url
             // If there is already a pending page (self.pending_frames), it will not be overridden;
             // However, if the id is not encompassed by another change, it will be.
-            LoadUrlMsg(source_id, url) => {
+            LoadUrlMsg(source_id, load_data, navigation_type) => {
                 debug!("constellation got URL load message");
-                self.handle_load_url_msg(source_id, url);
+                self.handle_load_url_msg(source_id, load_data, navigation_type);
             }
             // A page loaded through one of several methods above has completed all parsing,
             // script, and reflow messages have been sent.
@@ -345,6 +448,32 @@ impl Constellation {
                 debug!("constellation got navigation message");
                 self.handle_navigate_msg(direction);
             }
+            TraverseHistoryMsg(delta) => {
+                debug!("constellation got history traversal message");
+                self.handle_traverse_history_msg(delta);
+            }
+            // A document changed its URL without loading a new one.
+            SameDocumentNavigationMsg(pipeline_id, url, state_id, navigation_type) => {
+                debug!("constellation got same-document navigation message");
+                self.handle_same_document_navigation_msg(pipeline_id, url, state_id,
+                                                         navigation_type);
+            }
+            GetHistoryLengthMsg(sender) => {
+                sender.send(self.navigation_context.len());
+            }
+            RequestAnimationFrameMsg(pipeline_id) => {
+                debug!("constellation got animation frame request message");
+                self.animation_frame_requests.insert(pipeline_id);
+                self.compositor_chan.send(RequestAnimationFrame);
+            }
+            TickAnimationFramesMsg => {
+                self.handle_tick_animation_frames_msg();
+            }
+            StorageChangedMsg(pipeline_id, storage_type, url, key, old_value, new_value) => {
+                debug!("constellation got storage changed message");
+                self.handle_storage_changed_msg(pipeline_id, storage_type, url, key, old_value,
+                                                new_value);
+            }
             // Notification that rendering has finished and is requesting permission to paint.
             RendererReadyMsg(pipeline_id) => {
                 debug!("constellation got renderer ready message");
@@ -354,6 +483,14 @@ impl Constellation {
                 debug!("constellation got window resize message");
                 self.handle_resized_window_msg(new_size);
             }
+            ZoomMsg(magnification) => {
+                debug!("constellation got zoom message");
+                self.handle_zoom_msg(magnification);
+            }
+            constellation_msg::KeyEvent(key, state, modifiers, c) => {
+                debug!("constellation got key event message");
+                self.handle_key_msg(key, state, modifiers, c);
+            }
         }
         true
     }
@@ -364,6 +501,9 @@ impl Constellation {
         }
         self.image_cache_task.exit();
         self.resource_task.send(resource_task::Exit);
+        let (response_chan, response_port) = channel();
+        self.storage_task.send(storage_task::Exit(response_chan));
+        response_port.recv();
         self.compositor_chan.send(ShutdownComplete);
     }
 
@@ -413,16 +553,18 @@ impl Constellation {
         debug!("creating replacement pipeline for about:failure");
 
         let new_id = self.get_next_pipeline_id();
+        let url = parse_url("about:failure", None);
         let pipeline = Pipeline::create(new_id,
                                         subpage_id,
                                         self.chan.clone(),
                                         self.compositor_chan.clone(),
                                         self.image_cache_task.clone(),
                                         self.resource_task.clone(),
+                                        self.storage_task.clone(),
                                         self.profiler_chan.clone(),
-                                        self.window_size,
+                                        self.viewport_size(&url),
                                         self.opts.clone(),
-                                        parse_url("about:failure", None));
+                                        LoadData::new(url));
         pipeline.load();
 
         let pipeline_wrapped = Rc::new(pipeline);
@@ -446,10 +588,11 @@ impl Constellation {
                                         self.compositor_chan.clone(),
                                         self.image_cache_task.clone(),
                                         self.resource_task.clone(),
+                                        self.storage_task.clone(),
                                         self.profiler_chan.clone(),
-                                        self.window_size,
+                                        self.viewport_size(&url),
                                         self.opts.clone(),
-                                        url);
+                                        LoadData::new(url));
         pipeline.load();
         let pipeline_wrapped = Rc::new(pipeline);
 
@@ -554,7 +697,7 @@ impl Constellation {
             source Id of LoadIframeUrlMsg does have an associated pipeline in
             constellation. This should be impossible.").clone();
 
-        let source_url = source_pipeline.url.clone();
+        let source_url = source_pipeline.load_data.url.clone();
 
         let same_script = (source_url.host == url.host &&
                            source_url.port == url.port) && sandbox == IFrameUnsandboxed;
@@ -567,10 +710,11 @@ impl Constellation {
                                   self.chan.clone(),
                                   self.compositor_chan.clone(),
                                   self.image_cache_task.clone(),
+                                  self.resource_task.clone(),
                                   self.profiler_chan.clone(),
                                   self.opts.clone(),
                                   source_pipeline.clone(),
-                                  url)
+                                  LoadData::new(url))
         } else {
             debug!("Constellation: loading cross-origin iframe at {:?}", url);
             // Create a new script task if not same-origin url's
@@ -580,10 +724,11 @@ impl Constellation {
                              self.compositor_chan.clone(),
                              self.image_cache_task.clone(),
                              self.resource_task.clone(),
+                             self.storage_task.clone(),
                              self.profiler_chan.clone(),
-                             self.window_size,
+                             self.viewport_size(&url),
                              self.opts.clone(),
-                             url)
+                             LoadData::new(url))
         };
 
         debug!("Constellation: sending load msg to pipeline {:?}", pipeline.id);
@@ -601,12 +746,22 @@ impl Constellation {
             });
         }
         self.pipelines.insert(pipeline_wrapped.id, pipeline_wrapped);
+
+        // The compositor needs the channels of the new pipeline if it is going to be shown.
+        for current_frame in self.current_frame().iter() {
+            if current_frame.contains(source_pipeline_id) {
+                self.compositor_chan.send(SetFrameTree(current_frame.to_sendable()));
+            }
+        }
     }
 
-    fn handle_load_url_msg(&mut self, source_id: PipelineId, url: Url) {
-        debug!("Constellation: received message to load {:s}", url.to_str());
+    fn handle_load_url_msg(&mut self,
+                           source_id: PipelineId,
+                           load_data: LoadData,
+                           navigation_type: NavigationType) {
+        debug!("Constellation: received message to load {:s}", load_data.url.to_str());
         // Make sure no pending page would be overridden.
-        let source_frame = self.current_frame().get_ref().find(source_id).expect(
+        let source_frame = self.current_frame().unwrap().find(source_id).expect(
             "Constellation: received a LoadUrlMsg from a pipeline_id associated
             with a pipeline not in the active frame tree. This should be
             impossible.");
@@ -615,7 +770,7 @@ impl Constellation {
             let old_id = frame_change.before.expect("Constellation: Received load msg
                 from pipeline, but there is no currently active page. This should
                 be impossible.");
-            let changing_frame = self.current_frame().get_ref().find(old_id).expect("Constellation:
+            let changing_frame = self.current_frame().unwrap().find(old_id).expect("Constellation:
                 Pending change has non-active source pipeline. This should be
                 impossible.");
             if changing_frame.contains(source_id) || source_frame.contains(old_id) {
@@ -636,10 +791,11 @@ impl Constellation {
                                         self.compositor_chan.clone(),
                                         self.image_cache_task.clone(),
                                         self.resource_task.clone(),
+                                        self.storage_task.clone(),
                                         self.profiler_chan.clone(),
-                                        self.window_size,
+                                        self.viewport_size(&load_data.url),
                                         self.opts.clone(),
-                                        url);
+                                        load_data);
 
         pipeline.load();
         let pipeline_wrapped = Rc::new(pipeline);
@@ -651,50 +807,97 @@ impl Constellation {
                 parent: parent,
                 children: RefCell::new(vec!()),
             }),
-            navigation_type: constellation_msg::Load,
+            navigation_type: navigation_type,
         });
         self.pipelines.insert(pipeline_wrapped.id, pipeline_wrapped);
     }
 
     fn handle_navigate_msg(&mut self, direction: constellation_msg::NavigationDirection) {
         debug!("received message to navigate {:?}", direction);
+        match direction {
+            constellation_msg::Forward => self.handle_traverse_history_msg(1),
+            constellation_msg::Back => self.handle_traverse_history_msg(-1),
+        }
+    }
+
+    /// Makes the entry the given number of steps away from the current one current. Entries that
+    /// share the current frame tree only need their document to restore its URL and state, while
+    /// other entries bring back their frame tree.
+    // http://www.whatwg.org/html/#traverse-the-history-by-a-delta
+    fn handle_traverse_history_msg(&mut self, delta: int) {
+        debug!("received message to traverse the history by {}", delta);
+        let available = if delta < 0 {
+            self.navigation_context.previous.len()
+        } else {
+            self.navigation_context.next.len()
+        };
+        if delta == 0 || delta.abs() as uint > available {
+            debug!("no entry {} steps away to navigate to", delta);
+            return;
+        }
 
         // TODO(tkuehn): what is the "critical point" beyond which pending frames
         // should not be cleared? Currently, the behavior is that forward/back
         // navigation always has navigation priority, and after that new page loading is
         // first come, first served.
-        let destination_frame = match direction {
-            constellation_msg::Forward => {
-                if self.navigation_context.next.is_empty() {
-                    debug!("no next page to navigate to");
-                    return;
-                } else {
-                    let old = self.current_frame().get_ref();
-                    for frame in old.iter() {
-                        frame.pipeline.revoke_paint_permission();
-                    }
-                }
+        let old_entry = self.navigation_context.current.clone().unwrap();
+        let mut destination = old_entry.clone();
+        for _ in range(0, delta.abs()) {
+            destination = if delta < 0 {
+                self.navigation_context.back()
+            } else {
                 self.navigation_context.forward()
+            };
+        }
+
+        if !destination.shares_frame_tree(&old_entry) {
+            for frame in old_entry.frame_tree.iter() {
+                frame.pipeline.revoke_paint_permission();
             }
-            constellation_msg::Back => {
-                if self.navigation_context.previous.is_empty() {
-                    debug!("no previous page to navigate to");
-                    return;
-                } else {
-                    let old = self.current_frame().get_ref();
-                    for frame in old.iter() {
-                        frame.pipeline.revoke_paint_permission();
-                    }
-                }
-                self.navigation_context.back()
+            for frame in destination.frame_tree.iter() {
+                frame.pipeline.load_from_history();
             }
-        };
+            let frame_tree = destination.frame_tree.clone();
+            self.grant_paint_permission(frame_tree, constellation_msg::Navigate);
+        }
 
-        for frame in destination_frame.iter() {
-            frame.pipeline.load();
+        // The document may have changed its URL since it was loaded, so it has to restore the
+        // URL and state of the entry.
+        match self.pipelines.find(&destination.pipeline_id) {
+            Some(pipeline) => {
+                let ScriptChan(ref chan) = pipeline.script_chan;
+                let _ = chan.send_opt(RestoreHistoryEntryMsg(destination.pipeline_id,
+                                                             destination.url.clone(),
+                                                             destination.state_id.clone()));
+            }
+            None => {}
         }
-        self.grant_paint_permission(destination_frame, constellation_msg::Navigate);
+    }
 
+    fn handle_same_document_navigation_msg(&mut self,
+                                           pipeline_id: PipelineId,
+                                           url: Url,
+                                           state_id: Option<HistoryStateId>,
+                                           navigation_type: NavigationType) {
+        debug!("{:?} navigated to {:s} within its document", pipeline_id, url.to_str());
+        let frame_tree = match self.current_frame() {
+            Some(frame_tree) if frame_tree.contains(pipeline_id) => frame_tree.clone(),
+            _ => {
+                debug!("ignoring same-document navigation of an inactive pipeline");
+                return;
+            }
+        };
+        let entry = SessionHistoryEntry {
+            frame_tree: frame_tree,
+            pipeline_id: pipeline_id,
+            url: url,
+            state_id: state_id,
+        };
+        let evicted = match navigation_type {
+            constellation_msg::Replace => self.navigation_context.replace(entry),
+            _ => self.navigation_context.load(entry),
+        };
+        self.handle_evicted_frames(evicted);
     }
 
     fn handle_renderer_ready_msg(&mut self, pipeline_id: PipelineId) {
@@ -728,7 +931,7 @@ impl Constellation {
             // Create the next frame tree that will be given to the compositor
             let next_frame_tree = if to_add.parent.borrow().is_some() {
                 // NOTE: work around borrowchk issues
-                self.current_frame().get_ref().clone()
+                self.current_frame().unwrap().clone()
             } else {
                 to_add.clone()
             };
@@ -737,7 +940,7 @@ impl Constellation {
             match frame_change.before {
                 Some(revoke_id) if self.current_frame().is_some() => {
                     debug!("Constellation: revoking permission from {:?}", revoke_id);
-                    let current_frame = self.current_frame().get_ref();
+                    let current_frame = self.current_frame().unwrap();
 
                     let to_revoke = current_frame.find(revoke_id).expect(
                         "Constellation: pending frame change refers to an old \
@@ -787,23 +990,28 @@ impl Constellation {
         }
     }
 
-    /// Called when the window is resized.
-    fn handle_resized_window_msg(&mut self, new_size: TypedSize2D<PagePx, f32>) {
+    /// Called when the window is resized, and when a page zoom level changes. Each page is given
+    /// a layout viewport that fits the window at the zoom level of its origin.
+    fn handle_resized_window_msg(&mut self, new_size: TypedSize2D<ScreenPx, f32>) {
+        self.window_size = new_size;
+
         let mut already_seen = HashSet::new();
         for frame_tree in self.current_frame().iter() {
             debug!("constellation sending resize message to active frame");
             let pipeline = &frame_tree.pipeline;
             let ScriptChan(ref chan) = pipeline.script_chan;
-            let _ = chan.send_opt(ResizeMsg(pipeline.id, new_size));
+            let viewport_size = self.viewport_size(&pipeline.load_data.url);
+            let _ = chan.send_opt(ResizeMsg(pipeline.id, viewport_size));
             already_seen.insert(pipeline.id);
         }
-        for frame_tree in self.navigation_context.previous.iter()
+        for entry in self.navigation_context.previous.iter()
             .chain(self.navigation_context.next.iter()) {
-            let pipeline = &frame_tree.pipeline;
+            let pipeline = &entry.frame_tree.pipeline;
             if !already_seen.contains(&pipeline.id) {
                 debug!("constellation sending resize message to inactive frame");
                 let ScriptChan(ref chan) = pipeline.script_chan;
-                let _ = chan.send_opt(ResizeInactiveMsg(pipeline.id, new_size));
+                let viewport_size = self.viewport_size(&pipeline.load_data.url);
+                let _ = chan.send_opt(ResizeInactiveMsg(pipeline.id, viewport_size));
                 already_seen.insert(pipeline.id);
             }
         }
@@ -816,11 +1024,117 @@ impl Constellation {
                 debug!("constellation sending resize message to pending outer frame ({:?})",
                        frame_tree.pipeline.id);
                 let ScriptChan(ref chan) = frame_tree.pipeline.script_chan;
-                let _ = chan.send_opt(ResizeMsg(frame_tree.pipeline.id, new_size));
+                let viewport_size = self.viewport_size(&frame_tree.pipeline.load_data.url);
+                let _ = chan.send_opt(ResizeMsg(frame_tree.pipeline.id, viewport_size));
             }
         }
+    }
 
-        self.window_size = new_size;
+    /// Called when the user zooms the current page. The new page zoom level applies to every page
+    /// of the same origin.
+    fn handle_zoom_msg(&mut self, magnification: f32) {
+        let url = match self.current_frame() {
+            Some(frame_tree) => frame_tree.pipeline.load_data.url.clone(),
+            None => return,
+        };
+        let page_zoom = self.page_zoom(&url).get() * magnification;
+        let page_zoom = ScaleFactor(page_zoom.max(MIN_PAGE_ZOOM).min(MAX_PAGE_ZOOM));
+        self.page_zooms.insert(origin(&url), page_zoom);
+
+        self.compositor_chan.send(ChangePageZoom(page_zoom));
+        let window_size = self.window_size;
+        self.handle_resized_window_msg(window_size);
+    }
+
+    /// Tells the other documents of the current frame tree that share the changed storage area's
+    /// origin to fire `storage` events.
+    // http://www.whatwg.org/html/#send-a-storage-notification
+    fn handle_storage_changed_msg(&self,
+                                  source_id: PipelineId,
+                                  storage_type: StorageType,
+                                  url: Url,
+                                  key: Option<String>,
+                                  old_value: Option<String>,
+                                  new_value: Option<String>) {
+        let frame_tree = match self.current_frame() {
+            Some(frame_tree) => frame_tree,
+            None => return,
+        };
+        let source_origin = origin(&url);
+        for frame in frame_tree.iter() {
+            let pipeline = &frame.pipeline;
+            if pipeline.id == source_id || origin(&pipeline.load_data.url) != source_origin {
+                continue
+            }
+            let ScriptChan(ref chan) = pipeline.script_chan;
+            let _ = chan.send_opt(StorageEventMsg(pipeline.id, storage_type, url.clone(),
+                                                  key.clone(), old_value.clone(),
+                                                  new_value.clone()));
+        }
+    }
+
+    /// Sends a key event to the script task of the current page.
+    // FIXME: This should go to the pipeline of the focused frame, which may be an iframe.
+    fn handle_key_msg(&self, key: Key, state: KeyState, modifiers: KeyModifiers, c: Option<char>) {
+        match self.current_frame() {
+            Some(frame_tree) => {
+                let ScriptChan(ref chan) = frame_tree.pipeline.script_chan;
+                let event = event::KeyEvent(key, state, modifiers, c);
+                let _ = chan.send_opt(SendEventMsg(frame_tree.pipeline.id, event));
+            }
+            None => {}
+        }
+    }
+
+    /// Gives an animation frame to the pipelines waiting for one. Pipelines that are not
+    /// visible, such as those of iframes without a size or of pages in the session history, only
+    /// get one once a second, and the compositor is asked for more frames until they have.
+    fn handle_tick_animation_frames_msg(&mut self) {
+        let now = precise_time_s();
+        let tick_hidden = now - self.last_hidden_animation_frame >= HIDDEN_ANIMATION_FRAME_INTERVAL;
+        if tick_hidden {
+            self.last_hidden_animation_frame = now;
+        }
+
+        let requests = replace(&mut self.animation_frame_requests, HashSet::new());
+        for pipeline_id in requests.move_iter() {
+            let pipeline = match self.pipelines.find(&pipeline_id) {
+                Some(pipeline) => pipeline.clone(),
+                None => continue,
+            };
+            if !tick_hidden && !self.is_pipeline_visible(pipeline_id) {
+                self.animation_frame_requests.insert(pipeline_id);
+                continue;
+            }
+            let ScriptChan(ref chan) = pipeline.script_chan;
+            let _ = chan.send_opt(AnimationFrameMsg(pipeline_id));
+        }
+
+        if !self.animation_frame_requests.is_empty() {
+            self.compositor_chan.send(RequestAnimationFrame);
+        }
+    }
+
+    /// Returns true if the given pipeline is shown in the current frame tree: it is either the
+    /// root pipeline or in an iframe with a size, all of whose ancestor iframes have one too.
+    fn is_pipeline_visible(&self, pipeline_id: PipelineId) -> bool {
+        fn is_visible_in(frame_tree: &Rc<FrameTree>, pipeline_id: PipelineId) -> bool {
+            if frame_tree.pipeline.id == pipeline_id {
+                return true
+            }
+            frame_tree.children.borrow().iter().any(|child| {
+                let has_size = match child.rect {
+                    Some(rect) => rect.size.width.get() > 0.0 && rect.size.height.get() > 0.0,
+                    None => false,
+                };
+                has_size && is_visible_in(&child.frame_tree, pipeline_id)
+            })
+        }
+
+        match self.current_frame() {
+            Some(frame_tree) => is_visible_in(frame_tree, pipeline_id),
+            None => false,
+        }
     }
 
     // Close all pipelines at and beneath a given frame
@@ -855,16 +1169,27 @@ impl Constellation {
         match navigation_type {
             constellation_msg::Load => {
                 debug!("evicting old frames due to load");
-                let evicted = self.navigation_context.load(frame_tree);
+                let evicted = self.navigation_context.load(SessionHistoryEntry::new(frame_tree));
                 self.handle_evicted_frames(evicted);
             }
-            _ => {
+            constellation_msg::Replace => {
+                debug!("evicting old frames due to replacement");
+                let entry = SessionHistoryEntry::new(frame_tree);
+                let evicted = self.navigation_context.replace(entry);
+                self.handle_evicted_frames(evicted);
+            }
+            constellation_msg::Navigate => {
                 debug!("ignoring non-load navigation type");
             }
         }
     }
 
     fn set_ids(&self, frame_tree: &Rc<FrameTree>) {
+        if frame_tree.parent.borrow().is_none() {
+            let page_zoom = self.page_zoom(&frame_tree.pipeline.load_data.url);
+            self.compositor_chan.send(ChangePageZoom(page_zoom));
+        }
+
         let (chan, port) = channel();
         debug!("Constellation sending SetIds");
         self.compositor_chan.send(SetIds(frame_tree.to_sendable(), chan, self.chan.clone()));
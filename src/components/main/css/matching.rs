@@ -13,6 +13,7 @@ use layout::wrapper::{LayoutElement, LayoutNode, PostorderNodeMutTraversal, Thre
 
 use gfx::font_context::FontContext;
 use servo_util::cache::{Cache, LRUCache, SimpleHashCache};
+use servo_util::font_prefs::FontPrefs;
 use servo_util::namespace::Null;
 use servo_util::smallvec::{SmallVec, SmallVec16};
 use servo_util::str::DOMString;
@@ -310,7 +311,8 @@ pub trait MatchMethods {
     unsafe fn cascade_node(&self,
                            parent: Option<LayoutNode>,
                            applicable_declarations: &ApplicableDeclarations,
-                           applicable_declarations_cache: &mut ApplicableDeclarationsCache);
+                           applicable_declarations_cache: &mut ApplicableDeclarationsCache,
+                           font_prefs: &FontPrefs);
 }
 
 trait PrivateMatchMethods {
@@ -320,7 +322,8 @@ trait PrivateMatchMethods {
                                    style: &mut Option<Arc<ComputedValues>>,
                                    applicable_declarations_cache: &mut
                                    ApplicableDeclarationsCache,
-                                   shareable: bool);
+                                   shareable: bool,
+                                   font_prefs: &FontPrefs);
 
     fn share_style_with_candidate_if_possible(&self,
                                               parent_node: Option<LayoutNode>,
@@ -335,7 +338,8 @@ impl<'ln> PrivateMatchMethods for LayoutNode<'ln> {
                                    style: &mut Option<Arc<ComputedValues>>,
                                    applicable_declarations_cache: &mut
                                    ApplicableDeclarationsCache,
-                                   shareable: bool) {
+                                   shareable: bool,
+                                   font_prefs: &FontPrefs) {
         let this_style;
         let cacheable;
         match parent_style {
@@ -348,7 +352,8 @@ impl<'ln> PrivateMatchMethods for LayoutNode<'ln> {
                 let (the_style, is_cacheable) = cascade(applicable_declarations,
                                                         shareable,
                                                         Some(&***parent_style),
-                                                        cached_computed_values);
+                                                        cached_computed_values,
+                                                        font_prefs);
                 cacheable = is_cacheable;
                 this_style = Arc::new(the_style);
             }
@@ -356,7 +361,8 @@ impl<'ln> PrivateMatchMethods for LayoutNode<'ln> {
                 let (the_style, is_cacheable) = cascade(applicable_declarations,
                                                         shareable,
                                                         None,
-                                                        None);
+                                                        None,
+                                                        font_prefs);
                 cacheable = is_cacheable;
                 this_style = Arc::new(the_style);
             }
@@ -488,7 +494,8 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
                 unsafe {
                     self.cascade_node(parent,
                                       applicable_declarations,
-                                      applicable_declarations_cache)
+                                      applicable_declarations_cache,
+                                      &layout_context.opts.font_prefs)
                 }
 
                 applicable_declarations.clear();
@@ -521,7 +528,8 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
     unsafe fn cascade_node(&self,
                            parent: Option<LayoutNode>,
                            applicable_declarations: &ApplicableDeclarations,
-                           applicable_declarations_cache: &mut ApplicableDeclarationsCache) {
+                           applicable_declarations_cache: &mut ApplicableDeclarationsCache,
+                           font_prefs: &FontPrefs) {
         // Get our parent's style. This must be unsafe so that we don't touch the parent's
         // borrow flags.
         //
@@ -551,20 +559,23 @@ impl<'ln> MatchMethods for LayoutNode<'ln> {
                                                  applicable_declarations.normal.as_slice(),
                                                  &mut layout_data.shared_data.style,
                                                  applicable_declarations_cache,
-                                                 applicable_declarations.normal_shareable);
+                                                 applicable_declarations.normal_shareable,
+                                                 font_prefs);
                 if applicable_declarations.before.len() > 0 {
                     self.cascade_node_pseudo_element(parent_style,
                                                      applicable_declarations.before.as_slice(),
                                                      &mut layout_data.data.before_style,
                                                      applicable_declarations_cache,
-                                                     false);
+                                                     false,
+                                                     font_prefs);
                 }
                 if applicable_declarations.after.len() > 0 {
                     self.cascade_node_pseudo_element(parent_style,
                                                      applicable_declarations.after.as_slice(),
                                                      &mut layout_data.data.after_style,
                                                      applicable_declarations_cache,
-                                                     false);
+                                                     false,
+                                                     font_prefs);
                 }
             }
         }
@@ -16,11 +16,12 @@
 
 use layout::construct::FlowConstructor;
 use layout::context::LayoutContext;
-use layout::floats::{ClearBoth, ClearLeft, ClearRight, FloatKind, Floats, PlacementInfo};
+use layout::floats::{ClearBoth, ClearLeft, ClearRight, FloatKind, FloatLeft, FloatRight};
+use layout::floats::{Floats, PlacementInfo};
 use layout::flow::{BaseFlow, BlockFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use layout::flow::{MutableFlowUtils, PreorderFlowTraversal, PostorderFlowTraversal, mut_base};
 use layout::flow;
-use layout::fragment::{Fragment, ImageFragment, ScannedTextFragment};
+use layout::fragment::{Fragment, ImageFragment, InputFragment, ScannedTextFragment};
 use layout::model::{Auto, IntrinsicWidths, MarginCollapseInfo, MarginsCollapse};
 use layout::model::{MarginsCollapseThrough, MaybeAuto, NoCollapsibleMargins, Specified, specified};
 use layout::model::{specified_or_none};
@@ -283,11 +284,11 @@ impl HeightConstraintSolution {
 /// current calculated value of `height`.
 ///
 /// See CSS 2.1 § 10.7.
-struct CandidateHeightIterator {
+pub struct CandidateHeightIterator {
     height: MaybeAuto,
     max_height: Option<Au>,
     min_height: Au,
-    candidate_value: Au,
+    pub candidate_value: Au,
     status: CandidateHeightIteratorStatus,
 }
 
@@ -472,7 +473,7 @@ enum FormattingContextType {
 //
 // TODO(#1244, #2007, pcwalton): Do this for CSS transforms and opacity too, at least if they're
 // animating.
-fn propagate_layer_flag_from_child(layers_needed_for_descendants: &mut bool, kid: &mut Flow) {
+pub fn propagate_layer_flag_from_child(layers_needed_for_descendants: &mut bool, kid: &mut Flow) {
     if kid.is_absolute_containing_block() {
         let kid_base = flow::mut_base(kid);
         if kid_base.flags.needs_layer() {
@@ -506,7 +507,20 @@ pub struct BlockFlow {
     previous_float_width: Option<Au>,
 
     /// Additional floating flow members.
-    pub float: Option<Box<FloatedBlockInfo>>
+    pub float: Option<Box<FloatedBlockInfo>>,
+
+    /// The content width assigned to this block by its flex container, if it is a flex item, or by
+    /// its inline flow, if it is a shrink-to-fit inline block. This takes the place of the
+    /// computed value of `width`.
+    pub flex_item_width: Option<Au>,
+
+    /// The width of the containing block of this block, if its parent assigned it only the space
+    /// that it fits into, as flex containers do for their items and inline flows for inline
+    /// blocks. Percentages in the width, margins, and padding of this block resolve against this.
+    pub containing_block_width: Option<Au>,
+
+    /// Whether this block is a flex item, which establishes a new formatting context.
+    pub is_flex_item: bool,
 }
 
 impl BlockFlow {
@@ -517,7 +531,10 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
-            float: None
+            float: None,
+            flex_item_width: None,
+            containing_block_width: None,
+            is_flex_item: node.is_flex_item(),
         }
     }
 
@@ -528,7 +545,10 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
-            float: None
+            float: None,
+            flex_item_width: None,
+            containing_block_width: None,
+            is_flex_item: node.is_flex_item(),
         }
     }
 
@@ -542,7 +562,10 @@ impl BlockFlow {
             is_root: false,
             static_y_offset: Au::new(0),
             previous_float_width: None,
-            float: Some(box FloatedBlockInfo::new(float_kind))
+            float: Some(box FloatedBlockInfo::new(float_kind)),
+            flex_item_width: None,
+            containing_block_width: None,
+            is_flex_item: false,
         }
     }
 
@@ -693,11 +716,11 @@ impl BlockFlow {
 
     /// Return true if this has a replaced fragment.
     ///
-    /// The only two types of replaced fragments currently are text fragments
-    /// and image fragments.
+    /// The only types of replaced fragments currently are text fragments, image fragments and
+    /// form control fragments.
     fn is_replaced_content(&self) -> bool {
         match self.fragment.specific {
-            ScannedTextFragment(_) | ImageFragment(_) => true,
+            ScannedTextFragment(_) | ImageFragment(_) | InputFragment(_) => true,
             _ => false,
         }
     }
@@ -805,6 +828,11 @@ impl BlockFlow {
         // Our current border-box position.
         let mut cur_y = Au(0);
 
+        // The floats that come in. These pass by a block formatting context root untouched.
+        let establishes_formatting_context =
+            self.formatting_context_type() != NonformattingContext;
+        let floats_in = self.base.floats.clone();
+
         // Absolute positioning establishes a block formatting context. Don't propagate floats
         // in or out. (But do propagate them between kids.)
         if self.is_absolutely_positioned() {
@@ -882,19 +910,34 @@ impl BlockFlow {
             // Mark flows for layerization if necessary to handle painting order correctly.
             propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
 
+            // A child that clears floats below the current position has clearance, which
+            // prevents its top margin from collapsing with ours. See CSS 2.1 § 8.3.1 and § 9.5.2.
+            let clear_type = match kid.float_clearance() {
+                clear::none => None,
+                clear::left => Some(ClearLeft),
+                clear::right => Some(ClearRight),
+                clear::both => Some(ClearBoth),
+            };
+            match clear_type {
+                Some(clear_type) if floats.clearance(clear_type) > Au(0) => {
+                    margin_collapse_info.stop_collapsing_top_margin()
+                }
+                _ => {}
+            }
+
             // Handle any (possibly collapsed) top margin.
             let delta =
                 margin_collapse_info.advance_top_margin(&flow::base(kid).collapsible_margins);
             translate_including_floats(&mut cur_y, delta, &mut floats);
 
-            // Clear past the floats that came in, if necessary.
-            let clearance = match kid.float_clearance() {
-                clear::none => Au(0),
-                clear::left => floats.clearance(ClearLeft),
-                clear::right => floats.clearance(ClearRight),
-                clear::both => floats.clearance(ClearBoth),
+            // Clear past the floats that came in, if necessary. The border edge of the child ends
+            // up even with the bottom of the lowest float, unless its margin already moved it
+            // further down.
+            let clearance = match clear_type {
+                None => Au(0),
+                Some(clear_type) => floats.clearance(clear_type),
             };
-            cur_y = cur_y + clearance;
+            translate_including_floats(&mut cur_y, clearance, &mut floats);
 
             // At this point, `cur_y` is at the border edge of the child.
             flow::mut_base(kid).position.origin.y = cur_y;
@@ -923,11 +966,13 @@ impl BlockFlow {
         // Collect various offsets needed by absolutely positioned descendants.
         self.collect_static_y_offsets_from_kids();
 
-        // Add in our bottom margin and compute our collapsible margins.
+        // Add in our bottom margin and compute our collapsible margins. The bottom margin of the
+        // last child only collapses with ours if our height is `auto`.
         let can_collapse_bottom_margin_with_kids =
             margins_may_collapse == MarginsMayCollapse &&
             !self.is_absolutely_positioned() &&
-            self.fragment.border_padding.bottom == Au(0);
+            self.fragment.border_padding.bottom == Au(0) &&
+            self.fragment.style().get_box().height == LPA_Auto;
         let (collapsible_margins, delta) =
             margin_collapse_info.finish_and_compute_collapsible_margins(
             &self.fragment,
@@ -944,6 +989,14 @@ impl BlockFlow {
             height = Au::max(layout_context.screen_size.height, height)
         }
 
+        // The height of a block formatting context root includes its floats. See CSS 2.1
+        // § 10.6.7.
+        if establishes_formatting_context && !self.is_absolutely_positioned() {
+            let clearance = floats.clearance(ClearBoth);
+            translate_including_floats(&mut cur_y, clearance, &mut floats);
+            height = height + clearance;
+        }
+
         if self.is_absolutely_positioned() {
             // The content height includes all the floats per CSS 2.1 § 10.6.7. The easiest way to
             // handle this is to just treat this as clearance.
@@ -984,9 +1037,21 @@ impl BlockFlow {
         self.fragment.border_box.origin.y = Au(0);
         self.base.position.size.height = cur_y;
 
-        self.base.floats = floats.clone();
+        if establishes_formatting_context {
+            // Our floats stay inside us; hand back the floats that came in, past our border box.
+            self.base.floats = floats_in;
+            self.base.floats.translate(Point2D(-self.fragment.left_offset(), -cur_y));
+        } else {
+            self.base.floats = floats.clone();
+        }
         self.adjust_fragments_for_collapsed_margins_if_root();
+        self.assign_height_for_absolute_flow_tree_if_necessary(layout_context);
+    }
 
+    /// If this is the root of an absolute flow tree, assigns heights for all the flows in that
+    /// tree and stores their overflow.
+    pub fn assign_height_for_absolute_flow_tree_if_necessary(&mut self,
+                                                             layout_context: &mut LayoutContext) {
         if self.is_root_of_absolute_flow_tree() {
             // Assign heights for all flows in this Absolute flow tree.
             // This is preorder because the height of an absolute flow may depend on
@@ -1385,11 +1450,12 @@ impl BlockFlow {
     /// `FormattingContextType`.
     fn formatting_context_type(&self) -> FormattingContextType {
         let style = self.fragment.style();
-        if style.get_box().float != float::none {
+        if style.get_box().float != float::none || self.is_flex_item {
             return OtherFormattingContext
         }
         match style.get_box().display {
-            display::table_cell | display::table_caption | display::inline_block => {
+            display::table_cell | display::table_caption | display::inline_block |
+            display::flex | display::inline_flex => {
                 OtherFormattingContext
             }
             _ if style.get_box().position == position::static_ &&
@@ -1415,6 +1481,20 @@ impl Flow for BlockFlow {
         self.fragment.style().get_box().clear
     }
 
+    /// Returns the baseline of the first in-flow child that has one.
+    fn first_baseline(&self) -> Option<Au> {
+        for kid in self.base.children.iter() {
+            if kid.is_float() || kid.is_absolutely_positioned() {
+                continue
+            }
+            match kid.first_baseline() {
+                Some(baseline) => return Some(flow::base(kid).position.origin.y + baseline),
+                None => {}
+            }
+        }
+        None
+    }
+
     /// Pass 1 of reflow: computes minimum and preferred widths.
     ///
     /// Recursively (bottom-up) determine the flow's minimum and preferred widths. When called on
@@ -1433,7 +1513,8 @@ impl Flow for BlockFlow {
         for child_ctx in self.base.child_iter() {
             assert!(child_ctx.is_block_flow() ||
                     child_ctx.is_inline_flow() ||
-                    child_ctx.is_table_kind());
+                    child_ctx.is_table_kind() ||
+                    child_ctx.is_flex());
 
             let child_base = flow::mut_base(child_ctx);
             intrinsic_widths.minimum_width =
@@ -1454,10 +1535,16 @@ impl Flow for BlockFlow {
         intrinsic_widths.surround_width = fragment_intrinsic_widths.surround_width;
         self.base.intrinsic_widths = intrinsic_widths;
 
-        match self.fragment.style().get_box().float {
-            float::none => {}
-            float::left => flags.set_has_left_floated_descendants(true),
-            float::right => flags.set_has_right_floated_descendants(true),
+        // The `float` property does not apply to every block, such as flex items, so this goes by
+        // whether the block was laid out as a float.
+        match self.float {
+            None => {}
+            Some(ref float) => {
+                match float.float_kind {
+                    FloatLeft => flags.set_has_left_floated_descendants(true),
+                    FloatRight => flags.set_has_right_floated_descendants(true),
+                }
+            }
         }
         self.base.flags = flags
     }
@@ -1557,6 +1644,11 @@ impl Flow for BlockFlow {
         if self.is_float() {
             debug!("assign_height_float: assigning height for float");
             self.assign_height_float(ctx);
+        } else if self.formatting_context_type() != NonformattingContext {
+            // Block formatting context roots do not collapse margins with their children. See
+            // CSS 2.1 § 8.3.1.
+            debug!("assign_height: assigning height for block formatting context root");
+            self.assign_height_block_base(ctx, MarginsMayNotCollapse);
         } else {
             debug!("assign_height: assigning height for block");
             self.assign_height_block_base(ctx, MarginsMayCollapse);
@@ -1793,7 +1885,11 @@ pub trait WidthAndMarginsComputer {
         let (left, right) =
             (MaybeAuto::from_style(style.get_positionoffsets().left, containing_block_width),
              MaybeAuto::from_style(style.get_positionoffsets().right, containing_block_width));
-        let available_width = containing_block_width - block.fragment.border_padding.horizontal();
+        // A block that was assigned only the space that it fits into is solved within that space.
+        let available_width = match block.containing_block_width {
+            Some(_) => parent_flow_width,
+            None => containing_block_width,
+        } - block.fragment.border_padding.horizontal();
         return WidthConstraintInput::new(computed_width,
                                          margin_left,
                                          margin_right,
@@ -1850,16 +1946,21 @@ pub trait WidthAndMarginsComputer {
                               parent_flow_width: Au,
                               ctx: &mut LayoutContext)
                               -> MaybeAuto {
-        MaybeAuto::from_style(block.fragment().style().get_box().width,
-                              self.containing_block_width(block, parent_flow_width, ctx))
+        match block.flex_item_width {
+            Some(width) => Specified(width),
+            None => {
+                MaybeAuto::from_style(block.fragment().style().get_box().width,
+                                      self.containing_block_width(block, parent_flow_width, ctx))
+            }
+        }
     }
 
     fn containing_block_width(&self,
-                              _: &mut BlockFlow,
+                              block: &mut BlockFlow,
                               parent_flow_width: Au,
                               _: &mut LayoutContext)
                               -> Au {
-        parent_flow_width
+        block.containing_block_width.unwrap_or(parent_flow_width)
     }
 
     /// Compute the used value of width, taking care of min-width and max-width.
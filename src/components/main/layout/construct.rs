@@ -22,13 +22,17 @@
 use css::node_style::StyledNode;
 use layout::block::BlockFlow;
 use layout::context::LayoutContext;
+use layout::flex::FlexFlow;
 use layout::floats::FloatKind;
 use layout::flow::{Flow, ImmutableFlowUtils, MutableOwnedFlowUtils};
 use layout::flow::{Descendants, AbsDescendants};
 use layout::flow;
 use layout::flow_ref::FlowRef;
-use layout::fragment::{Fragment, GenericFragment, IframeFragment, IframeFragmentInfo};
-use layout::fragment::{ImageFragment, ImageFragmentInfo, SpecificFragmentInfo, TableFragment};
+use layout::fragment::{Fragment, GeneratedContentFragment, GeneratedContentInfo, GenericFragment};
+use layout::fragment::{IframeFragment, IframeFragmentInfo, ImageFragment, ImageFragmentInfo};
+use layout::fragment::{InlineBlockFragment, InlineBlockFragmentInfo, InputFragment};
+use layout::fragment::{InputFragmentInfo};
+use layout::fragment::{SpecificFragmentInfo, TableFragment};
 use layout::fragment::{TableCellFragment, TableColumnFragment, TableColumnFragmentInfo};
 use layout::fragment::{TableRowFragment, TableWrapperFragment, UnscannedTextFragment};
 use layout::fragment::{UnscannedTextFragmentInfo};
@@ -49,10 +53,11 @@ use layout::wrapper::{Before, BeforeBlock, After, AfterBlock, Normal};
 use gfx::display_list::OpaqueNode;
 use gfx::font_context::FontContext;
 use script::dom::element::{HTMLIFrameElementTypeId, HTMLImageElementTypeId};
-use script::dom::element::{HTMLObjectElementTypeId};
+use script::dom::element::{HTMLInputElementTypeId, HTMLObjectElementTypeId};
 use script::dom::element::{HTMLTableColElementTypeId, HTMLTableDataCellElementTypeId};
 use script::dom::element::{HTMLTableElementTypeId, HTMLTableHeaderCellElementTypeId};
 use script::dom::element::{HTMLTableRowElementTypeId, HTMLTableSectionElementTypeId};
+use script::dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId};
 use script::dom::node::{CommentNodeTypeId, DoctypeNodeTypeId, DocumentFragmentNodeTypeId};
 use script::dom::node::{DocumentNodeTypeId, ElementNodeTypeId, ProcessingInstructionNodeTypeId};
 use script::dom::node::{TextNodeTypeId};
@@ -62,7 +67,7 @@ use servo_util::url::{is_image_data, parse_url};
 use std::mem;
 use std::sync::atomics::Relaxed;
 use style::ComputedValues;
-use style::computed_values::{display, position, float};
+use style::computed_values::{content, display, position, float};
 use sync::Arc;
 use url::Url;
 
@@ -248,6 +253,11 @@ impl<'a> FlowConstructor<'a> {
             Some(ElementNodeTypeId(HTMLIFrameElementTypeId)) => {
                 IframeFragment(IframeFragmentInfo::new(node))
             }
+            Some(ElementNodeTypeId(HTMLInputElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => {
+                InputFragment(InputFragmentInfo::new(node, self.font_context()))
+            }
             Some(ElementNodeTypeId(HTMLObjectElementTypeId)) => {
                 let data = node.get_object_data(&self.layout_context.url);
                 self.build_fragment_info_for_image(node, data)
@@ -280,6 +290,13 @@ impl<'a> FlowConstructor<'a> {
         let mut fragments = fragment_accumulator.finish();
         if fragments.is_empty() { return };
 
+        // Runs of text in a flex container that contain only whitespace do not become flex items.
+        if flow.get().is_flex() {
+            fragments.strip_ignorable_whitespace_from_start();
+            fragments.strip_ignorable_whitespace_from_end();
+            if fragments.is_empty() { return };
+        }
+
         match whitespace_stripping {
             NoWhitespaceStripping => {}
             StripWhitespaceFromStart => {
@@ -292,11 +309,26 @@ impl<'a> FlowConstructor<'a> {
             }
         }
 
+        // The flows of inline blocks become children of the inline flow that holds them, so that
+        // the layout traversals reach them.
+        let mut inline_block_flows = vec!();
+        for fragment in fragments.fragments.iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref info) => inline_block_flows.push(info.flow_ref.clone()),
+                _ => {}
+            }
+        }
+
         let mut inline_flow = box InlineFlow::from_fragments((*node).clone(), fragments);
         inline_flow.compute_minimum_ascent_and_descent(self.font_context(), &**node.style());
+        inline_flow.compute_paragraph_level(&**node.style());
+        inline_flow.compute_text_indent(&**node.style(), flow.get().is_leaf());
         let mut inline_flow = inline_flow as Box<Flow>;
         TextRunScanner::new().scan_for_runs(self.font_context(), inline_flow);
         let mut inline_flow = FlowRef::new(inline_flow);
+        for inline_block_flow in inline_block_flows.move_iter() {
+            inline_flow.add_new_child(inline_block_flow)
+        }
         inline_flow.finish(self.layout_context);
 
         if flow.get().need_anonymous_flow(inline_flow.get()) {
@@ -484,6 +516,37 @@ impl<'a> FlowConstructor<'a> {
         self.build_flow_using_children(FlowRef::new(flow), node)
     }
 
+    /// Builds a flow for a node with `display: flex` or `display: inline-flex`. This yields a
+    /// `FlexFlow` whose children are its flex items.
+    fn build_flow_for_flex(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
+        let flow = box FlexFlow::from_node(self, node) as Box<Flow>;
+        self.build_flow_using_children(FlowRef::new(flow), node)
+    }
+
+    /// Builds an inline block fragment for a node with `display: inline-flex`, which lays out the
+    /// flex flow of the node as a unit within its line. This yields an
+    /// `InlineFragmentsConstructionResult`.
+    fn build_fragments_for_inline_flex(&mut self, node: &ThreadSafeLayoutNode)
+                                       -> ConstructionResult {
+        let (flow, abs_descendants) = match self.build_flow_for_flex(node) {
+            FlowConstructionResult(flow, abs_descendants) => (flow, abs_descendants),
+            _ => fail!("building a flex flow didn't yield a flow"),
+        };
+
+        // The flex flow draws the borders and background of the box, so the fragment is not
+        // given a range of its own.
+        let mut fragments = InlineFragments::new();
+        let info = InlineBlockFragmentInfo::new(flow);
+        fragments.fragments.push(Fragment::new_from_specific_info(node, InlineBlockFragment(info)));
+
+        let construction_item = InlineFragmentsConstructionItem(InlineFragmentsConstructionResult {
+            splits: Vec::new(),
+            fragments: fragments,
+            abs_descendants: abs_descendants,
+        });
+        ConstructionItemConstructionResult(construction_item)
+    }
+
     /// Builds the flow for a node with `float: {left|right}`. This yields a float `BlockFlow` with
     /// a `BlockFlow` underneath it.
     fn build_flow_for_floated_block(&mut self, node: &ThreadSafeLayoutNode, float_kind: FloatKind)
@@ -599,7 +662,12 @@ impl<'a> FlowConstructor<'a> {
         }
 
         let mut fragments = InlineFragments::new();
-        fragments.push(Fragment::new(self, node), node.style().clone());
+        match node.get_pseudo_element_type() {
+            Before | After => self.build_fragments_for_generated_content(node, &mut fragments),
+            Normal | BeforeBlock | AfterBlock => {
+                fragments.push(Fragment::new(self, node), node.style().clone())
+            }
+        }
 
         let construction_item = InlineFragmentsConstructionItem(InlineFragmentsConstructionResult {
             splits: Vec::new(),
@@ -609,6 +677,35 @@ impl<'a> FlowConstructor<'a> {
         ConstructionItemConstructionResult(construction_item)
     }
 
+    /// Builds the fragments for the `content` property of a `::before` or `::after`
+    /// pseudo-element. Strings and `attr()` references become text and `url()` references become
+    /// images. Runs of items that involve counters or quotes become generated content fragments,
+    /// which are resolved once the whole flow tree has been built. All of the fragments share a
+    /// single range, so that the style of the pseudo-element applies to them once.
+    fn build_fragments_for_generated_content(&mut self,
+                                             node: &ThreadSafeLayoutNode,
+                                             fragments: &mut InlineFragments) {
+        let mut pending_items = vec!();
+        for item in node.generated_content().move_iter() {
+            match item {
+                content::UrlContent(url) => {
+                    flush_generated_content_items(node, &mut pending_items, fragments);
+                    let specific = self.build_fragment_info_for_image(node, Some(url));
+                    fragments.fragments.push(Fragment::new_from_specific_info(node, specific))
+                }
+                content::AttrContent(name) => {
+                    let value = node.as_element().get_attr(&namespace::Null, name.as_slice());
+                    pending_items.push(content::StringContent(value.unwrap_or("").to_string()))
+                }
+                item => pending_items.push(item),
+            }
+        }
+        flush_generated_content_items(node, &mut pending_items, fragments);
+
+        let fragment_count = FragmentIndex(fragments.len() as int);
+        fragments.push_range(node.style().clone(), Range::new(FragmentIndex(0), fragment_count))
+    }
+
     /// Builds one or more fragments for a node with `display: inline`. This yields an
     /// `InlineFragmentsConstructionResult`.
     fn build_fragments_for_inline(&mut self, node: &ThreadSafeLayoutNode) -> ConstructionResult {
@@ -817,6 +914,12 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
                 let style = node.style();
                 (display::inline, style.get_box().float, style.get_box().position)
             }
+            Some(ElementNodeTypeId(_)) if node.is_flex_item() => {
+                // The children of a flex container are blockified, and `float` does not apply to
+                // them. See CSS Flexible Box Layout Module Level 1 § 4.
+                let style = node.style();
+                (blockify(style.get_box().display), float::none, style.get_box().position)
+            }
             Some(ElementNodeTypeId(_)) => {
                 let style = node.style();
                 (style.get_box().display, style.get_box().float, style.get_box().position)
@@ -859,12 +962,33 @@ impl<'a> PostorderNodeMutTraversal for FlowConstructor<'a> {
                 node.set_flow_construction_result(self.build_flow_for_block(node))
             }
 
+            // Flex containers contribute flex flow construction results. Floated flex containers
+            // fall through to the block case below.
+            (display::flex, float::none, _) => {
+                node.set_flow_construction_result(self.build_flow_for_flex(node))
+            }
+
+            // Inline flex containers contribute inline fragment construction results holding their
+            // flex flows.
+            (display::inline_flex, float::none, _) => {
+                let construction_result = self.build_fragments_for_inline_flex(node);
+                node.set_flow_construction_result(construction_result)
+            }
+
             // Inline items contribute inline fragment construction results.
             (display::inline, float::none, _) => {
                 let construction_result = self.build_fragments_for_inline(node);
                 node.set_flow_construction_result(construction_result)
             }
 
+            // Replaced content is laid out the same way whether it is `inline` or
+            // `inline-block`, so replaced inline blocks such as form controls contribute inline
+            // fragment construction results too.
+            (display::inline_block, float::none, _) if node.is_replaced_content() => {
+                let construction_result = self.build_fragments_for_replaced_inline_content(node);
+                node.set_flow_construction_result(construction_result)
+            }
+
             // Table items contribute table flow construction results.
             (display::table_caption, _, _) => {
                 let construction_result = self.build_flow_for_table_caption(node);
@@ -946,7 +1070,10 @@ impl<'ln> NodeUtils for ThreadSafeLayoutNode<'ln> {
             Some(DocumentFragmentNodeTypeId) |
             Some(DocumentNodeTypeId) |
             None |
-            Some(ElementNodeTypeId(HTMLImageElementTypeId)) => true,
+            Some(ElementNodeTypeId(HTMLImageElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLInputElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) |
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => true,
             Some(ElementNodeTypeId(HTMLObjectElementTypeId)) => self.has_object_data(),
             Some(ElementNodeTypeId(_)) => false,
         }
@@ -1074,3 +1201,49 @@ impl FlowConstructionUtils for FlowRef {
     }
 }
 
+
+/// Turns the pending items of a pseudo-element's `content` into a single fragment. Items that are
+/// all strings become text right away; anything else has to wait for generated content
+/// resolution.
+fn flush_generated_content_items(node: &ThreadSafeLayoutNode,
+                                 pending_items: &mut Vec<content::Content>,
+                                 fragments: &mut InlineFragments) {
+    if pending_items.is_empty() {
+        return
+    }
+
+    let items = mem::replace(pending_items, vec!());
+    let all_strings = items.iter().all(|item| {
+        match *item {
+            content::StringContent(_) => true,
+            _ => false,
+        }
+    });
+    let specific = if all_strings {
+        let mut text = String::new();
+        for item in items.iter() {
+            match *item {
+                content::StringContent(ref string) => text.push_str(string.as_slice()),
+                _ => {}
+            }
+        }
+        UnscannedTextFragment(UnscannedTextFragmentInfo::from_text(text))
+    } else {
+        GeneratedContentFragment(box GeneratedContentInfo::new(items))
+    };
+    fragments.fragments.push(Fragment::new_from_specific_info(node, specific))
+}
+
+/// Returns the block-level equivalent of the given value of `display`. See CSS 2.1 § 9.7.
+fn blockify(display: display::T) -> display::T {
+    match display {
+        display::inline_table => display::table,
+        display::inline_flex => display::flex,
+        display::inline | display::inline_block | display::table_row_group |
+        display::table_column | display::table_column_group | display::table_header_group |
+        display::table_footer_group | display::table_row | display::table_cell |
+        display::table_caption => display::block,
+        display::block | display::table | display::list_item | display::flex |
+        display::none => display,
+    }
+}
@@ -106,7 +106,10 @@ impl LayoutContext {
                 let context = box FontContext::new(self.font_context_info.clone());
                 FONT_CONTEXT = mem::transmute(context)
             }
-            mem::transmute(FONT_CONTEXT)
+            let context: &'a mut FontContext = mem::transmute(FONT_CONTEXT);
+            // The font context outlives this layout, so catch it up with any new web fonts.
+            context.set_web_fonts(self.font_context_info.web_fonts.clone());
+            context
         }
     }
 
@@ -174,7 +177,10 @@ impl LayoutContext {
                 }
             }
             font_context.replace(Some(context));
-            mem::transmute(context)
+            let context: &'a mut FontContext = mem::transmute(context);
+            // The font context outlives this layout, so catch it up with any new web fonts.
+            context.set_web_fonts(self.font_context_info.web_fonts.clone());
+            context
         }
     }
 
@@ -0,0 +1,954 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! CSS flexible box layout.
+//!
+//! See CSS Flexible Box Layout Module Level 1: http://dev.w3.org/csswg/css-flexbox/
+//!
+//! A flex container is laid out in two passes that fit into the usual traversals. During width
+//! assignment the container resolves the sizes of its items along the horizontal axis, which is
+//! the main axis for `row` containers and the cross axis for `column` containers. During height
+//! assignment, once the items have laid out their contents, it resolves the vertical axis. This is
+//! also when a wrapping `column` container breaks its items into lines.
+
+#![deny(unsafe_block)]
+
+use layout::block::{BlockFlow, CandidateHeightIterator, propagate_layer_flag_from_child};
+use layout::construct::FlowConstructor;
+use layout::context::LayoutContext;
+use layout::flow::{FlexFlowClass, FlowClass, Flow, ImmutableFlowUtils};
+use layout::flow;
+use layout::model::{Auto, MarginCollapseInfo, MaybeAuto, Specified, specified, specified_or_none};
+use layout::model;
+use layout::wrapper::ThreadSafeLayoutNode;
+
+use geom::Rect;
+use servo_msg::compositor_msg::LayerId;
+use servo_util::geometry::Au;
+use servo_util::geometry;
+use std::fmt;
+use std::mem;
+use style::computed_values::{LPA_Auto, LPA_Length, LPA_Percentage, LPN_Length, LPN_Percentage};
+use style::computed_values::{LP_Length, LP_Percentage};
+use style::computed_values::{align_items, align_self, clear, flex_direction, flex_wrap};
+use style::computed_values::{justify_content, position};
+
+/// A single in-flow child of a flex container.
+struct FlexItem {
+    /// The index of the flow of this item among the children of the flex container.
+    index: uint,
+    /// The value of the `order` property of this item.
+    order: int,
+    /// The flex grow factor.
+    grow: f64,
+    /// The flex shrink factor.
+    shrink: f64,
+    /// The flex base size of the content box of this item.
+    base_size: Au,
+    /// The minimum main size of the content box of this item.
+    min_size: Au,
+    /// The maximum main size of the content box of this item, if any.
+    max_size: Option<Au>,
+    /// The sum of the margins, borders, and padding of this item along the main axis.
+    main_surround: Au,
+    /// The used main size of the content box of this item.
+    main_size: Au,
+    /// The offset of the margin box of this item from the main-start content edge of the
+    /// container.
+    main_position: Au,
+    /// The alignment of this item along the cross axis, with `align-self: auto` resolved.
+    align: align_items::T,
+    /// Whether the main size of this item is no longer subject to flexing.
+    frozen: bool,
+}
+
+impl FlexItem {
+    fn new(index: uint) -> FlexItem {
+        FlexItem {
+            index: index,
+            order: 0,
+            grow: 0.0,
+            shrink: 1.0,
+            base_size: Au(0),
+            min_size: Au(0),
+            max_size: None,
+            main_surround: Au(0),
+            main_size: Au(0),
+            main_position: Au(0),
+            align: align_items::stretch,
+            frozen: false,
+        }
+    }
+
+    /// Clamps the given content main size by the minimum and maximum main sizes of this item.
+    fn clamp(&self, size: Au) -> Au {
+        let size = match self.max_size {
+            Some(max_size) if size > max_size => max_size,
+            _ => size,
+        };
+        geometry::max(size, self.min_size)
+    }
+
+    /// Returns the size of the margin box of this item along the main axis.
+    fn outer_main_size(&self) -> Au {
+        self.main_size + self.main_surround
+    }
+}
+
+/// A flex line: a run of items, in `order`-modified document order, laid out along the main axis
+/// together.
+struct FlexLine {
+    /// Indices into the items of the flex container.
+    items: Vec<uint>,
+    /// The size of this line along the cross axis.
+    cross_size: Au,
+    /// The distance from the cross-start edge of this line to the baseline that its
+    /// baseline-aligned items share.
+    baseline: Au,
+}
+
+impl FlexLine {
+    fn new(items: Vec<uint>) -> FlexLine {
+        FlexLine {
+            items: items,
+            cross_size: Au(0),
+            baseline: Au(0),
+        }
+    }
+}
+
+/// A flex container.
+pub struct FlexFlow {
+    /// Data common to all block flows.
+    pub block_flow: BlockFlow,
+
+    /// The in-flow children of this flex container, in document order.
+    items: Vec<FlexItem>,
+
+    /// The flex lines of this container.
+    lines: Vec<FlexLine>,
+}
+
+impl FlexFlow {
+    pub fn from_node(constructor: &mut FlowConstructor, node: &ThreadSafeLayoutNode) -> FlexFlow {
+        FlexFlow {
+            block_flow: BlockFlow::from_node(constructor, node),
+            items: vec!(),
+            lines: vec!(),
+        }
+    }
+
+    /// Returns true if the main axis of this container is horizontal.
+    fn is_row(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row | flex_direction::row_reverse => true,
+            flex_direction::column | flex_direction::column_reverse => false,
+        }
+    }
+
+    /// Returns true if items are placed from the main-end edge of this container.
+    fn is_main_reversed(&self) -> bool {
+        match self.block_flow.fragment.style().get_flex().flex_direction {
+            flex_direction::row_reverse | flex_direction::column_reverse => true,
+            flex_direction::row | flex_direction::column => false,
+        }
+    }
+
+    /// Returns the indices of the items in `order`-modified document order.
+    fn ordered_items(&self) -> Vec<uint> {
+        let mut ordered: Vec<uint> = range(0, self.items.len()).collect();
+        // This sort is stable, so items with equal `order` keep their document order.
+        ordered.sort_by(|a, b| self.items.get(*a).order.cmp(&self.items.get(*b).order));
+        ordered
+    }
+
+    /// Collects the in-flow children of this container as flex items. If the main axis is
+    /// horizontal, also computes their flex base sizes and main size constraints against the
+    /// given content width of the container.
+    fn collect_items(&mut self, content_width: Au) {
+        let is_row = self.is_row();
+        let align_items = self.block_flow.fragment.style().get_flex().align_items;
+
+        let mut items = vec!();
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+
+            let mut item = FlexItem::new(index);
+            let minimum_width = flow::base(kid).intrinsic_widths.minimum_width;
+            let preferred_width = flow::base(kid).intrinsic_widths.preferred_width;
+
+            // Anonymous runs of inline content have no style of their own and use the initial
+            // values of the flex item properties.
+            if kid.is_inline_flow() {
+                item.base_size = preferred_width;
+                item.min_size = minimum_width;
+                items.push(item);
+                continue
+            }
+
+            let style = kid.as_block().fragment.style();
+            let flex_style = style.get_flex();
+            item.order = flex_style.order;
+            item.grow = flex_style.flex_grow;
+            item.shrink = flex_style.flex_shrink;
+            item.align = match flex_style.align_self {
+                align_self::auto => align_items,
+                align_self::stretch => align_items::stretch,
+                align_self::flex_start => align_items::flex_start,
+                align_self::flex_end => align_items::flex_end,
+                align_self::center => align_items::center,
+                align_self::baseline => align_items::baseline,
+            };
+
+            if is_row {
+                let margin = style.get_margin();
+                item.main_surround =
+                    MaybeAuto::from_style(margin.margin_left, content_width).specified_or_zero() +
+                    MaybeAuto::from_style(margin.margin_right, content_width).specified_or_zero() +
+                    model::padding_from_style(style, content_width).horizontal() +
+                    model::border_from_style(style).horizontal();
+
+                let width = MaybeAuto::from_style(style.get_box().width, content_width);
+                item.base_size = match flex_style.flex_basis {
+                    LPA_Auto => width.specified_or_default(preferred_width),
+                    basis => MaybeAuto::from_style(basis, content_width).specified_or_zero(),
+                };
+
+                // The automatic minimum size of a flex item is its min-content size, capped by
+                // its specified size.
+                item.min_size = match specified(style.get_box().min_width, content_width) {
+                    Au(0) => {
+                        match width {
+                            Specified(width) => geometry::min(width, minimum_width),
+                            Auto => minimum_width,
+                        }
+                    }
+                    min_width => min_width,
+                };
+                item.max_size = specified_or_none(style.get_box().max_width, content_width);
+            }
+
+            items.push(item);
+        }
+        self.items = items;
+    }
+
+    /// Resolves the widths of the items of a `row` container and positions them horizontally.
+    fn assign_widths_row(&mut self, left_content_edge: Au, content_width: Au) {
+        let ordered = self.ordered_items();
+        let style = self.block_flow.fragment.style();
+        let wraps = style.get_flex().flex_wrap != flex_wrap::nowrap;
+        let justify_content = style.get_flex().justify_content;
+        let reversed = self.is_main_reversed();
+
+        self.lines = break_into_lines(self.items.as_slice(), ordered.as_slice(), content_width, wraps);
+        for line in self.lines.iter() {
+            resolve_flexible_lengths(self.items.as_mut_slice(), line.items.as_slice(), content_width);
+            justify_line(self.items.as_mut_slice(),
+                         line.items.as_slice(),
+                         content_width,
+                         justify_content,
+                         reversed);
+        }
+
+        let mut items = self.items.iter();
+        let mut next_item = items.next();
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            let item = match next_item {
+                Some(item) if item.index == index => item,
+                _ => continue,
+            };
+            next_item = items.next();
+
+            if !kid.is_inline_flow() {
+                let block = kid.as_block();
+                block.flex_item_width = Some(item.main_size);
+                block.containing_block_width = Some(content_width);
+            }
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.origin.x = left_content_edge + item.main_position;
+            kid_base.position.size.width = item.outer_main_size();
+        }
+    }
+
+    /// Sizes and aligns the items of a `column` container horizontally, which is their cross
+    /// axis.
+    ///
+    /// A wrapping container can only break its items into lines once their heights are known, so
+    /// its items are all sized to fit their contents here and placed when heights are assigned.
+    fn assign_widths_column(&mut self, left_content_edge: Au, content_width: Au) {
+        let wraps = self.block_flow.fragment.style().get_flex().flex_wrap != flex_wrap::nowrap;
+        self.lines = vec!(FlexLine::new(self.ordered_items()));
+
+        let mut items = self.items.iter();
+        let mut next_item = items.next();
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            let item = match next_item {
+                Some(item) if item.index == index => item,
+                _ => continue,
+            };
+            next_item = items.next();
+
+            let minimum_width = flow::base(kid).intrinsic_widths.minimum_width;
+            let preferred_width = flow::base(kid).intrinsic_widths.preferred_width;
+            if kid.is_inline_flow() {
+                if wraps {
+                    let available = geometry::max(content_width, minimum_width);
+                    flow::mut_base(kid).position.size.width = geometry::min(available,
+                                                                            preferred_width);
+                }
+                continue
+            }
+
+            // Stretched items keep the full width handed to them by the block width assignment.
+            let block = kid.as_block();
+            block.flex_item_width = None;
+            block.containing_block_width = Some(content_width);
+            let (width, surround) = {
+                let style = block.fragment.style();
+                let margin = style.get_margin();
+                let surround =
+                    MaybeAuto::from_style(margin.margin_left, content_width).specified_or_zero() +
+                    MaybeAuto::from_style(margin.margin_right, content_width).specified_or_zero() +
+                    model::padding_from_style(style, content_width).horizontal() +
+                    model::border_from_style(style).horizontal();
+                (MaybeAuto::from_style(style.get_box().width, content_width), surround)
+            };
+            if item.align == align_items::stretch && !wraps {
+                continue
+            }
+
+            // Items that are not stretched are sized to fit their contents.
+            let content_size = match width {
+                Specified(width) => width,
+                Auto => {
+                    let available = geometry::max(content_width - surround, minimum_width);
+                    let size = geometry::min(available, preferred_width);
+                    block.flex_item_width = Some(size);
+                    size
+                }
+            };
+            let outer_width = content_size + surround;
+
+            // The inline axis of the items is the cross axis, so baseline alignment is the same
+            // as `flex-start`. See CSS Flexible Box Layout Module Level 1 § 8.3.
+            let offset = match item.align {
+                _ if wraps => Au(0),
+                align_items::flex_end => content_width - outer_width,
+                align_items::center => (content_width - outer_width).scale_by(0.5),
+                align_items::stretch | align_items::flex_start | align_items::baseline => Au(0),
+            };
+            let kid_base = &mut block.base;
+            kid_base.position.origin.x = left_content_edge + offset;
+            kid_base.position.size.width = outer_width;
+        }
+    }
+
+    /// Sizes the lines of a `row` container, aligns the items within them, and returns the
+    /// content height of the container.
+    fn assign_height_row(&mut self) -> Au {
+        let top_content_edge = self.block_flow.fragment.border_padding.top;
+        let wrap_reverse = self.block_flow.fragment.style().get_flex().flex_wrap ==
+            flex_wrap::wrap_reverse;
+
+        // Gather the laid-out border box heights, vertical margins, and baselines of the items.
+        // An item without a baseline has one synthesized from the bottom of its border box.
+        let mut cross_sizes =
+            Vec::from_elem(self.items.len(), (Au(0), Au(0), Au(0), false, Au(0)));
+        {
+            let mut item_index = 0;
+            for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+                if item_index == self.items.len() || self.items.get(item_index).index != index {
+                    continue
+                }
+                let height = flow::base(kid).position.size.height;
+                let baseline = kid.first_baseline().unwrap_or(height);
+                let (margin_top, margin_bottom, height_is_auto) = if kid.is_inline_flow() {
+                    (Au(0), Au(0), false)
+                } else {
+                    let fragment = &kid.as_block().fragment;
+                    (fragment.margin.top,
+                     fragment.margin.bottom,
+                     fragment.style().get_box().height == LPA_Auto)
+                };
+                *cross_sizes.get_mut(item_index) =
+                    (height, margin_top, margin_bottom, height_is_auto, baseline);
+                item_index += 1;
+            }
+        }
+
+        // Each line is as tall as its tallest item, or as its baseline-aligned items are once
+        // they share a baseline.
+        let mut auto_height = Au(0);
+        for line in self.lines.mut_iter() {
+            let mut cross_size = Au(0);
+            let (mut max_ascent, mut max_descent) = (Au(0), Au(0));
+            for &i in line.items.iter() {
+                let (height, margin_top, margin_bottom, _, baseline) = *cross_sizes.get(i);
+                if self.items.get(i).align == align_items::baseline {
+                    max_ascent = geometry::max(max_ascent, margin_top + baseline);
+                    max_descent = geometry::max(max_descent, height - baseline + margin_bottom);
+                } else {
+                    cross_size = geometry::max(cross_size, height + margin_top + margin_bottom);
+                }
+            }
+            line.cross_size = geometry::max(cross_size, max_ascent + max_descent);
+            line.baseline = max_ascent;
+            auto_height = auto_height + line.cross_size;
+        }
+
+        let mut candidate_height_iterator =
+            CandidateHeightIterator::new(self.block_flow.fragment.style(), None);
+        for (candidate_height, new_candidate_height) in candidate_height_iterator {
+            *new_candidate_height = match candidate_height {
+                Auto => auto_height,
+                Specified(value) => value,
+            }
+        }
+        let content_height = candidate_height_iterator.candidate_value;
+
+        // A single-line container sizes its line to its own height.
+        if self.lines.len() == 1 {
+            self.lines.get_mut(0).cross_size = content_height;
+        }
+
+        // Align the items within their lines.
+        let mut positions = Vec::from_elem(self.items.len(), (Au(0), None));
+        let mut line_start = Au(0);
+        for line in self.lines.iter() {
+            let line_position = if wrap_reverse {
+                content_height - line_start - line.cross_size
+            } else {
+                line_start
+            };
+            line_start = line_start + line.cross_size;
+
+            for &i in line.items.iter() {
+                let (height, margin_top, margin_bottom, height_is_auto, baseline) =
+                    *cross_sizes.get(i);
+                let align = match (self.items.get(i).align, wrap_reverse) {
+                    (align_items::flex_start, true) => align_items::flex_end,
+                    (align_items::flex_end, true) => align_items::flex_start,
+                    (align, _) => align,
+                };
+                let free_space = line.cross_size - height - margin_top - margin_bottom;
+                let (offset, new_height) = match align {
+                    align_items::stretch if height_is_auto => {
+                        (margin_top, Some(geometry::max(height + free_space, Au(0))))
+                    }
+                    align_items::flex_end => (free_space + margin_top, None),
+                    align_items::center => (free_space.scale_by(0.5) + margin_top, None),
+                    align_items::baseline => (line.baseline - baseline, None),
+                    align_items::stretch | align_items::flex_start => (margin_top, None),
+                };
+                *positions.get_mut(i) = (top_content_edge + line_position + offset, new_height);
+            }
+        }
+
+        self.place_items_vertically(positions.as_slice());
+        content_height
+    }
+
+    /// Resolves the heights of the items of a `column` container, positions them vertically, and
+    /// returns the content height of the container.
+    fn assign_height_column(&mut self) -> Au {
+        let top_content_edge = self.block_flow.fragment.border_padding.top;
+        let definite_height = match self.block_flow.fragment.style().get_box().height {
+            LPA_Length(length) => Some(length),
+            LPA_Percentage(_) | LPA_Auto => None,
+        };
+
+        // Compute the flex base sizes from the laid-out contents of the items.
+        let mut margins_top = Vec::from_elem(self.items.len(), Au(0));
+        {
+            let mut item_index = 0;
+            for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+                if item_index == self.items.len() || self.items.get(item_index).index != index {
+                    continue
+                }
+                let height = flow::base(kid).position.size.height;
+                let item = self.items.get_mut(item_index);
+                item_index += 1;
+
+                if kid.is_inline_flow() {
+                    item.base_size = height;
+                    item.min_size = height;
+                    continue
+                }
+
+                let fragment = &kid.as_block().fragment;
+                let style = fragment.style();
+                let surround = fragment.margin.top + fragment.margin.bottom +
+                    fragment.border_padding.vertical();
+                let content_height = height - fragment.border_padding.vertical();
+                item.main_surround = surround;
+                item.base_size = match (style.get_flex().flex_basis, definite_height) {
+                    (LPA_Length(length), _) => length,
+                    (LPA_Percentage(percent), Some(height)) => height.scale_by(percent),
+                    (LPA_Percentage(_), None) | (LPA_Auto, _) => content_height,
+                };
+                item.min_size = match (style.get_box().min_height, definite_height) {
+                    (LP_Length(length), _) if length != Au(0) => length,
+                    (LP_Percentage(percent), Some(height)) if percent != 0.0 => {
+                        height.scale_by(percent)
+                    }
+                    // The automatic minimum size of a flex item is its content size.
+                    _ => content_height,
+                };
+                item.max_size = match (style.get_box().max_height, definite_height) {
+                    (LPN_Length(length), _) => Some(length),
+                    (LPN_Percentage(percent), Some(height)) => Some(height.scale_by(percent)),
+                    _ => None,
+                };
+                *margins_top.get_mut(item_index - 1) = fragment.margin.top;
+            }
+        }
+
+        let auto_height = self.items.iter().fold(Au(0), |sum, item| {
+            sum + item.clamp(item.base_size) + item.main_surround
+        });
+        let mut candidate_height_iterator =
+            CandidateHeightIterator::new(self.block_flow.fragment.style(), None);
+        for (candidate_height, new_candidate_height) in candidate_height_iterator {
+            *new_candidate_height = match candidate_height {
+                Auto => auto_height,
+                Specified(value) => value,
+            }
+        }
+        let content_height = candidate_height_iterator.candidate_value;
+
+        // Now that the main sizes of the items are known, a wrapping container can break them
+        // into lines.
+        let wraps = self.block_flow.fragment.style().get_flex().flex_wrap != flex_wrap::nowrap;
+        if wraps {
+            let ordered = self.ordered_items();
+            self.lines = break_into_lines(self.items.as_slice(),
+                                          ordered.as_slice(),
+                                          content_height,
+                                          true);
+        }
+
+        let justify_content = self.block_flow.fragment.style().get_flex().justify_content;
+        let reversed = self.is_main_reversed();
+        for line in self.lines.iter() {
+            resolve_flexible_lengths(self.items.as_mut_slice(), line.items.as_slice(), content_height);
+            justify_line(self.items.as_mut_slice(),
+                         line.items.as_slice(),
+                         content_height,
+                         justify_content,
+                         reversed);
+        }
+
+        let mut positions = Vec::with_capacity(self.items.len());
+        for (item, margin_top) in self.items.iter().zip(margins_top.iter()) {
+            positions.push((top_content_edge + item.main_position + *margin_top,
+                            Some(item.main_size)));
+        }
+        self.place_items_vertically(positions.as_slice());
+        if wraps {
+            self.place_lines_horizontally();
+        }
+        content_height
+    }
+
+    /// Sizes the lines of a wrapping `column` container, which sit side by side, and aligns the
+    /// items within them horizontally.
+    fn place_lines_horizontally(&mut self) {
+        let left_content_edge = self.block_flow.fragment.border_box.origin.x +
+            self.block_flow.fragment.border_padding.left;
+        let content_width = self.block_flow.fragment.border_box.size.width -
+            self.block_flow.fragment.border_padding.horizontal();
+        let wrap_reverse = self.block_flow.fragment.style().get_flex().flex_wrap ==
+            flex_wrap::wrap_reverse;
+
+        // Gather the margin box widths of the items, which were sized to fit their contents.
+        let mut outer_widths = Vec::from_elem(self.items.len(), Au(0));
+        {
+            let mut item_index = 0;
+            for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+                if item_index == self.items.len() || self.items.get(item_index).index != index {
+                    continue
+                }
+                *outer_widths.get_mut(item_index) = flow::base(kid).position.size.width;
+                item_index += 1;
+            }
+        }
+
+        // Each line is as wide as its widest item.
+        let mut positions = Vec::from_elem(self.items.len(), Au(0));
+        let mut line_start = Au(0);
+        for line in self.lines.mut_iter() {
+            line.cross_size = line.items.iter().fold(Au(0), |size, &i| {
+                geometry::max(size, *outer_widths.get(i))
+            });
+            let line_position = if wrap_reverse {
+                content_width - line_start - line.cross_size
+            } else {
+                line_start
+            };
+            line_start = line_start + line.cross_size;
+
+            for &i in line.items.iter() {
+                let free_space = line.cross_size - *outer_widths.get(i);
+                let align = match (self.items.get(i).align, wrap_reverse) {
+                    (align_items::flex_start, true) => align_items::flex_end,
+                    (align_items::flex_end, true) => align_items::flex_start,
+                    (align, _) => align,
+                };
+                let offset = match align {
+                    align_items::flex_end => free_space,
+                    align_items::center => free_space.scale_by(0.5),
+                    align_items::stretch | align_items::flex_start | align_items::baseline => {
+                        Au(0)
+                    }
+                };
+                *positions.get_mut(i) = left_content_edge + line_position + offset;
+            }
+        }
+
+        let mut item_index = 0;
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            if item_index == self.items.len() || self.items.get(item_index).index != index {
+                continue
+            }
+            flow::mut_base(kid).position.origin.x = *positions.get(item_index);
+            item_index += 1;
+        }
+    }
+
+    /// Moves each item to the given vertical border edge position and, if a content height is
+    /// given, resizes it.
+    fn place_items_vertically(&mut self, positions: &[(Au, Option<Au>)]) {
+        let mut item_index = 0;
+        for (index, kid) in self.block_flow.base.child_iter().enumerate() {
+            if item_index == self.items.len() || self.items.get(item_index).index != index {
+                continue
+            }
+            let (y, new_height) = positions[item_index];
+            item_index += 1;
+
+            let new_height = match new_height {
+                None => None,
+                Some(height) if kid.is_inline_flow() => Some(height),
+                Some(height) => {
+                    let fragment = &mut kid.as_block().fragment;
+                    let border_box_height = height + fragment.border_padding.vertical();
+                    fragment.border_box.size.height = border_box_height;
+                    Some(border_box_height)
+                }
+            };
+
+            let kid_base = flow::mut_base(kid);
+            kid_base.position.origin.y = y;
+            match new_height {
+                Some(height) => kid_base.position.size.height = height,
+                None => {}
+            }
+        }
+    }
+
+    pub fn build_display_list_flex(&mut self, layout_context: &LayoutContext) {
+        debug!("build_display_list_flex: same process as block flow");
+        self.block_flow.build_display_list_block(layout_context);
+    }
+}
+
+/// Breaks the items, given in `order`-modified document order, into flex lines that fit within
+/// the available main size.
+fn break_into_lines(items: &[FlexItem], ordered: &[uint], available: Au, wraps: bool)
+                    -> Vec<FlexLine> {
+    let mut lines = vec!();
+    let mut current_line = vec!();
+    let mut current_size = Au(0);
+    for &i in ordered.iter() {
+        let item = &items[i];
+        let size = item.clamp(item.base_size) + item.main_surround;
+        if wraps && !current_line.is_empty() && current_size + size > available {
+            lines.push(FlexLine::new(mem::replace(&mut current_line, vec!())));
+            current_size = Au(0);
+        }
+        current_line.push(i);
+        current_size = current_size + size;
+    }
+    if !current_line.is_empty() || lines.is_empty() {
+        lines.push(FlexLine::new(current_line));
+    }
+    lines
+}
+
+/// Resolves the main sizes of the items of one line against the available main size.
+///
+/// See CSS Flexible Box Layout Module Level 1 § 9.7.
+fn resolve_flexible_lengths(items: &mut [FlexItem], line: &[uint], available: Au) {
+    // Determine whether to use the flex grow or the flex shrink factors.
+    let hypothetical_size = line.iter().fold(Au(0), |size, &i| {
+        size + items[i].clamp(items[i].base_size) + items[i].main_surround
+    });
+    let growing = hypothetical_size < available;
+
+    // Freeze inflexible items at their hypothetical main size.
+    for &i in line.iter() {
+        let item = &mut items[i];
+        let factor = if growing { item.grow } else { item.shrink };
+        item.main_size = item.clamp(item.base_size);
+        item.frozen = factor == 0.0 ||
+            (growing && item.base_size > item.main_size) ||
+            (!growing && item.base_size < item.main_size);
+    }
+
+    loop {
+        // Compute the remaining free space and the sum of the flex factors.
+        let mut free_space = available;
+        let mut total_factor = 0.0;
+        for &i in line.iter() {
+            let item = &items[i];
+            if item.frozen {
+                free_space = free_space - item.main_size - item.main_surround;
+            } else {
+                free_space = free_space - item.base_size - item.main_surround;
+                total_factor += if growing {
+                    item.grow
+                } else {
+                    item.shrink * item.base_size.to_f64().unwrap()
+                };
+            }
+        }
+        if total_factor == 0.0 {
+            break
+        }
+
+        // Distribute the free space in proportion to the flex factors and fix min/max
+        // violations.
+        let mut targets = vec!();
+        let mut total_violation = Au(0);
+        for &i in line.iter() {
+            let item = &mut items[i];
+            if item.frozen {
+                continue
+            }
+            let ratio = if growing {
+                item.grow / total_factor
+            } else {
+                item.shrink * item.base_size.to_f64().unwrap() / total_factor
+            };
+            let target = item.base_size + free_space.scale_by(ratio);
+            item.main_size = item.clamp(geometry::max(target, Au(0)));
+            total_violation = total_violation + item.main_size - target;
+            targets.push((i, target));
+        }
+
+        // Freeze items whose violations point the same way as the total violation. If there is
+        // no total violation, every item is resolved.
+        for &(i, target) in targets.iter() {
+            let item = &mut items[i];
+            item.frozen = if total_violation == Au(0) {
+                true
+            } else if total_violation > Au(0) {
+                item.main_size > target
+            } else {
+                item.main_size < target
+            };
+        }
+    }
+}
+
+/// Distributes the leftover main space of one line per `justify-content` and computes the main
+/// positions of its items.
+fn justify_line(items: &mut [FlexItem],
+                line: &[uint],
+                available: Au,
+                justify_content: justify_content::T,
+                reversed: bool) {
+    let count = line.len();
+    if count == 0 {
+        return
+    }
+
+    let used_size = line.iter().fold(Au(0), |size, &i| size + items[i].outer_main_size());
+    let free_space = geometry::max(available - used_size, Au(0));
+    let (mut position, gap) = match justify_content {
+        justify_content::flex_start => (Au(0), Au(0)),
+        justify_content::flex_end => (free_space, Au(0)),
+        justify_content::center => (free_space.scale_by(0.5), Au(0)),
+        justify_content::space_between if count > 1 => {
+            (Au(0), free_space.scale_by(1.0 / ((count - 1) as f64)))
+        }
+        justify_content::space_between => (Au(0), Au(0)),
+        justify_content::space_around => {
+            let gap = free_space.scale_by(1.0 / (count as f64));
+            (gap.scale_by(0.5), gap)
+        }
+    };
+
+    for &i in line.iter() {
+        let item = &mut items[i];
+        let outer_size = item.outer_main_size();
+        item.main_position = if reversed {
+            available - position - outer_size
+        } else {
+            position
+        };
+        position = position + outer_size + gap;
+    }
+}
+
+impl Flow for FlexFlow {
+    fn class(&self) -> FlowClass {
+        FlexFlowClass
+    }
+
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        self
+    }
+
+    fn as_block<'a>(&'a mut self) -> &'a mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn float_clearance(&self) -> clear::T {
+        self.block_flow.float_clearance()
+    }
+
+    fn first_baseline(&self) -> Option<Au> {
+        self.block_flow.first_baseline()
+    }
+
+    /// Computes the intrinsic widths as a block does, except that the items of a `row` container
+    /// sit side by side.
+    fn bubble_widths(&mut self, layout_context: &mut LayoutContext) {
+        self.block_flow.bubble_widths(layout_context);
+        if !self.is_row() {
+            return
+        }
+
+        let wraps = self.block_flow.fragment.style().get_flex().flex_wrap != flex_wrap::nowrap;
+        let mut minimum_width = Au(0);
+        let mut preferred_width = Au(0);
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_absolutely_positioned() {
+                continue
+            }
+            let kid_intrinsic_widths = flow::base(kid).intrinsic_widths;
+            minimum_width = if wraps {
+                geometry::max(minimum_width, kid_intrinsic_widths.total_minimum_width())
+            } else {
+                minimum_width + kid_intrinsic_widths.total_minimum_width()
+            };
+            preferred_width = preferred_width + kid_intrinsic_widths.total_preferred_width();
+        }
+
+        let fragment_intrinsic_widths = self.block_flow.fragment.intrinsic_widths(None);
+        let intrinsic_widths = &mut self.block_flow.base.intrinsic_widths;
+        intrinsic_widths.minimum_width = geometry::max(minimum_width,
+                                                       fragment_intrinsic_widths.minimum_width);
+        intrinsic_widths.preferred_width =
+            geometry::max(preferred_width, fragment_intrinsic_widths.preferred_width);
+    }
+
+    /// Assigns widths as a block does, then resizes and repositions the items along the
+    /// horizontal axis.
+    fn assign_widths(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_widths(flex): assigning width for flow");
+        self.block_flow.assign_widths(layout_context);
+
+        let left_content_edge = self.block_flow.fragment.border_box.origin.x +
+            self.block_flow.fragment.border_padding.left;
+        let content_width = self.block_flow.fragment.border_box.size.width -
+            self.block_flow.fragment.border_padding.horizontal();
+
+        self.collect_items(content_width);
+        if self.is_row() {
+            self.assign_widths_row(left_content_edge, content_width)
+        } else {
+            self.assign_widths_column(left_content_edge, content_width)
+        }
+    }
+
+    /// Lays out the items along the vertical axis once their contents have been laid out.
+    fn assign_height(&mut self, layout_context: &mut LayoutContext) {
+        debug!("assign_height: assigning height for flex flow");
+
+        let mut layers_needed_for_descendants = false;
+        for kid in self.block_flow.base.child_iter() {
+            kid.assign_height_for_inorder_child_if_necessary(layout_context);
+            propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
+        }
+        self.block_flow.base.flags.set_layers_needed_for_descendants(layers_needed_for_descendants);
+
+        let content_height = if self.is_row() {
+            self.assign_height_row()
+        } else {
+            self.assign_height_column()
+        };
+
+        if self.block_flow.is_absolutely_positioned() {
+            // The final height of an absolutely-positioned container is computed during the
+            // absolute flow tree traversal.
+            self.block_flow.fragment.border_box.size.height = content_height;
+            if self.block_flow.is_fixed() {
+                self.block_flow.base.flags.set_needs_layer(true)
+            }
+            return
+        }
+
+        let height = content_height + self.block_flow.fragment.border_padding.vertical();
+        self.block_flow.fragment.border_box.origin.y = Au(0);
+        self.block_flow.fragment.border_box.size.height = height;
+        self.block_flow.base.position.size.height = height;
+
+        // The margins of a flex container never collapse with those of its items.
+        let mut margin_collapse_info = MarginCollapseInfo::new();
+        margin_collapse_info.initialize_top_margin(&self.block_flow.fragment, false);
+        let (collapsible_margins, _) =
+            margin_collapse_info.finish_and_compute_collapsible_margins(&self.block_flow.fragment,
+                                                                        false);
+        self.block_flow.base.collapsible_margins = collapsible_margins;
+
+        self.block_flow.assign_height_for_absolute_flow_tree_if_necessary(layout_context);
+    }
+
+    fn compute_absolute_position(&mut self) {
+        self.block_flow.compute_absolute_position()
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root()
+    }
+
+    fn is_store_overflow_delayed(&mut self) -> bool {
+        self.block_flow.is_store_overflow_delayed()
+    }
+
+    fn is_root(&self) -> bool {
+        self.block_flow.is_root()
+    }
+
+    fn positioning(&self) -> position::T {
+        self.block_flow.positioning()
+    }
+
+    fn is_root_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.is_root_of_absolute_flow_tree()
+    }
+
+    fn generated_containing_block_rect(&self) -> Rect<Au> {
+        self.block_flow.generated_containing_block_rect()
+    }
+
+    fn layer_id(&self, fragment_index: uint) -> LayerId {
+        self.block_flow.layer_id(fragment_index)
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+}
+
+impl fmt::Show for FlexFlow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FlexFlow: {}", self.block_flow)
+    }
+}
@@ -24,10 +24,15 @@
 ///   fragments/flows that are subject to inline layout and line breaking and structs to represent
 ///   line breaks and mapping to CSS boxes, for the purpose of handling `getClientRects()` and
 ///   similar methods.
+///
+/// * `FlexFlow`: A flow that establishes a flex formatting context. Its child flows are flex items,
+///   which are sized and positioned along a main axis and a cross axis according to CSS Flexible
+///   Box Layout.
 
 use css::node_style::StyledNode;
 use layout::block::BlockFlow;
 use layout::context::LayoutContext;
+use layout::flex::FlexFlow;
 use layout::floats::Floats;
 use layout::flow_list::{FlowList, Link, FlowListIterator, MutFlowListIterator};
 use layout::flow_ref::FlowRef;
@@ -79,6 +84,11 @@ pub trait Flow: fmt::Show + ToStr + Share {
         fail!("called as_block() on a non-block flow")
     }
 
+    /// If this is a flex flow, returns the underlying object. Fails otherwise.
+    fn as_flex<'a>(&'a mut self) -> &'a mut FlexFlow {
+        fail!("called as_flex() on a non-flex flow")
+    }
+
     /// If this is an inline flow, returns the underlying object, borrowed immutably. Fails
     /// otherwise.
     fn as_immutable_inline<'a>(&'a self) -> &'a InlineFlow {
@@ -193,6 +203,12 @@ pub trait Flow: fmt::Show + ToStr + Share {
         false
     }
 
+    /// Returns the distance from the top of this flow to the baseline of its first line box, if
+    /// it has any. Heights must have been assigned. The default implementation returns `None`.
+    fn first_baseline(&self) -> Option<Au> {
+        None
+    }
+
     fn compute_collapsible_top_margin(&mut self,
                                       _layout_context: &mut LayoutContext,
                                       _margin_collapse_info: &mut MarginCollapseInfo) {
@@ -348,6 +364,9 @@ pub trait ImmutableFlowUtils {
     /// Returns true if this flow is an inline flow.
     fn is_inline_flow(self) -> bool;
 
+    /// Returns true if this flow is a flex flow.
+    fn is_flex(self) -> bool;
+
     /// Dumps the flow tree for debugging.
     fn dump(self);
 
@@ -384,6 +403,7 @@ pub trait MutableOwnedFlowUtils {
 pub enum FlowClass {
     BlockFlowClass,
     InlineFlowClass,
+    FlexFlowClass,
     TableWrapperFlowClass,
     TableFlowClass,
     TableColGroupFlowClass,
@@ -871,7 +891,7 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
     fn is_block_container(self) -> bool {
         match self.class() {
             // TODO: Change this when inline-blocks are supported.
-            BlockFlowClass | TableCaptionFlowClass | TableCellFlowClass => {
+            BlockFlowClass | FlexFlowClass | TableCaptionFlowClass | TableCellFlowClass => {
                 // FIXME: Actually check the type of the node
                 self.child_count() != 0
             }
@@ -895,6 +915,14 @@ impl<'a> ImmutableFlowUtils for &'a Flow {
         }
     }
 
+    /// Returns true if this flow is a flex flow.
+    fn is_flex(self) -> bool {
+        match self.class() {
+            FlexFlowClass => true,
+            _ => false,
+        }
+    }
+
     /// Dumps the flow tree for debugging.
     fn dump(self) {
         self.dump_with_level(0)
@@ -1001,6 +1029,7 @@ impl<'a> MutableFlowUtils for &'a mut Flow {
         match self.class() {
             BlockFlowClass => self.as_block().build_display_list_block(layout_context),
             InlineFlowClass => self.as_inline().build_display_list_inline(layout_context),
+            FlexFlowClass => self.as_flex().build_display_list_flex(layout_context),
             TableWrapperFlowClass => {
                 self.as_table_wrapper().build_display_list_table_wrapper(layout_context)
             }
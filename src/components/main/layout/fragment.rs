@@ -12,9 +12,11 @@ use layout::context::LayoutContext;
 use layout::floats::{ClearBoth, ClearLeft, ClearRight, ClearType};
 use layout::flow::Flow;
 use layout::flow;
+use layout::flow_ref::FlowRef;
 use layout::inline::{InlineFragmentContext, InlineMetrics};
 use layout::model::{Auto, IntrinsicWidths, MaybeAuto, Specified, specified};
 use layout::model;
+use layout::table::CollapsedBorders;
 use layout::text;
 use layout::util::{OpaqueNodeMethods, ToGfxColor};
 use layout::wrapper::{TLayoutNode, ThreadSafeLayoutNode};
@@ -26,12 +28,22 @@ use gfx::display_list::{BackgroundAndBorderLevel, BaseDisplayItem, BorderDisplay
 use gfx::display_list::{BorderDisplayItemClass, ClipDisplayItem, ClipDisplayItemClass};
 use gfx::display_list::{ContentStackingLevel, DisplayItem, DisplayList, ImageDisplayItem};
 use gfx::display_list::{ImageDisplayItemClass, LineDisplayItem};
-use gfx::display_list::{LineDisplayItemClass, OpaqueNode, PseudoDisplayItemClass};
+use gfx::display_list::{LineDisplayItemClass, OpaqueNode, PositionedDescendantStackingLevel};
+use gfx::display_list::{PseudoDisplayItemClass};
 use gfx::display_list::{SolidColorDisplayItem, SolidColorDisplayItemClass, StackingLevel};
-use gfx::display_list::{TextDecorations, TextDisplayItem, TextDisplayItemClass};
+use gfx::display_list::{TextDecoration, TextDecorations, TextDisplayItem, TextDisplayItemClass};
 use gfx::font::FontStyle;
+use gfx::font_context::FontContext;
+use gfx::text::bidi::{Level, LTR_LEVEL};
 use gfx::text::glyph::CharIndex;
+use gfx::text::shaping::ShapingOptions;
 use gfx::text::text_run::TextRun;
+use script::dom::element::{HTMLOptGroupElementTypeId, HTMLOptionElementTypeId};
+use script::dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId};
+use script::dom::htmlinputelement::{InputButton, InputCheckbox, InputEmail, InputHidden};
+use script::dom::htmlinputelement::{InputNumber, InputPassword, InputRadio, InputReset};
+use script::dom::htmlinputelement::{InputSubmit, InputText, InputUrl};
+use script::dom::node::{ElementNodeTypeId, TextNodeTypeId};
 use servo_msg::constellation_msg::{ConstellationChan, FrameRectMsg, PipelineId, SubpageId};
 use servo_net::image::holder::{ImageHolder, LocalImageCacheHandle};
 use servo_util::geometry::Au;
@@ -39,16 +51,20 @@ use servo_util::geometry;
 use servo_util::range::*;
 use servo_util::namespace;
 use servo_util::smallvec::SmallVec;
-use servo_util::str::is_whitespace;
+use servo_util::str::{is_whitespace, split_html_space_chars};
+use std::cmp;
 use std::fmt;
 use std::from_str::FromStr;
+use std::i32;
 use std::iter::AdditiveIterator;
 use std::mem;
 use std::num::Zero;
 use style::{ComputedValues, TElement, TNode, cascade_anonymous};
+use style::longhands::_servo_text_decorations_in_effect::Decoration;
 use style::computed_values::{LengthOrPercentageOrAuto, overflow, LPA_Auto, background_attachment};
-use style::computed_values::{background_repeat, border_style, clear, position, text_align};
-use style::computed_values::{text_decoration, vertical_align, visibility, white_space};
+use style::computed_values::{background_repeat, border_style, clear, content, position};
+use style::computed_values::{overflow_wrap, text_align, text_decoration_line, vertical_align};
+use style::computed_values::{visibility, white_space};
 use sync::Arc;
 use url::Url;
 
@@ -98,14 +114,25 @@ pub struct Fragment {
     ///
     /// FIXME(#2260, pcwalton): This is very inefficient; remove.
     pub new_line_pos: Vec<CharIndex>,
+
+    /// The embedding level assigned to this fragment by the bidi algorithm. Text fragments are
+    /// split so that they never span more than one level.
+    pub bidi_level: Level,
+
+    /// The borders of this fragment after conflict resolution, if it is a table or a table cell
+    /// in a table with `border-collapse: collapse`. These replace the borders from the style.
+    pub collapsed_borders: Option<CollapsedBorders>,
 }
 
 /// Info specific to the kind of fragment. Keep this enum small.
 #[deriving(Clone)]
 pub enum SpecificFragmentInfo {
     GenericFragment,
+    GeneratedContentFragment(Box<GeneratedContentInfo>),
     ImageFragment(ImageFragmentInfo),
     IframeFragment(IframeFragmentInfo),
+    InlineBlockFragment(InlineBlockFragmentInfo),
+    InputFragment(InputFragmentInfo),
     ScannedTextFragment(ScannedTextFragmentInfo),
     TableFragment,
     TableCellFragment,
@@ -218,6 +245,335 @@ impl IframeFragmentInfo {
     }
 }
 
+/// A fragment that represents an atomic inline-level box, such as an inline flex container, whose
+/// contents are laid out by a flow of its own. That flow is a child of the inline flow that holds
+/// this fragment, and it draws the box, borders and background included. The fragment only
+/// places the margin box of the flow within its line.
+#[deriving(Clone)]
+pub struct InlineBlockFragmentInfo {
+    /// The flow that lays out the box.
+    pub flow_ref: FlowRef,
+    /// The distance from the top of the margin box to the baseline of the box.
+    pub ascent: Au,
+}
+
+impl InlineBlockFragmentInfo {
+    pub fn new(flow_ref: FlowRef) -> InlineBlockFragmentInfo {
+        InlineBlockFragmentInfo {
+            flow_ref: flow_ref,
+            ascent: Au(0),
+        }
+    }
+
+    /// Assigns the width of the flow from the width of the containing block, shrinking it to fit
+    /// its contents if its `width` is `auto`, per CSS 2.1 § 10.3.9. Returns the width of its
+    /// margin box.
+    fn assign_width(&mut self, container_width: Au) -> Au {
+        let flow = self.flow_ref.get_mut();
+        let minimum_width = flow::base(flow).intrinsic_widths.minimum_width;
+        let preferred_width = flow::base(flow).intrinsic_widths.preferred_width;
+
+        let block = flow.as_block();
+        block.containing_block_width = Some(container_width);
+        let (width, surround) = {
+            let style = block.fragment.style();
+            let margin = style.get_margin();
+            let surround =
+                MaybeAuto::from_style(margin.margin_left, container_width).specified_or_zero() +
+                MaybeAuto::from_style(margin.margin_right, container_width).specified_or_zero() +
+                model::padding_from_style(style, container_width).horizontal() +
+                model::border_from_style(style).horizontal();
+            (MaybeAuto::from_style(style.get_box().width, container_width), surround)
+        };
+        block.flex_item_width = None;
+        let width = match width {
+            Specified(width) => width,
+            Auto => {
+                let available = geometry::max(container_width - surround, minimum_width);
+                let width = geometry::min(available, preferred_width);
+                block.flex_item_width = Some(width);
+                width
+            }
+        };
+
+        block.base.position.size.width = width + surround;
+        width + surround
+    }
+}
+
+/// What an `InputFragment` draws inside its borders.
+#[deriving(Clone, Eq)]
+pub enum FormControlKind {
+    /// A text field or a text area, which draws lines of text and a caret.
+    TextControl,
+    /// A button, which draws its label.
+    ButtonControl,
+    /// A checkbox or a radio button, which draws a mark when checked.
+    CheckControl,
+    /// A `<select>`: either a drop-down box, which draws the label of its selected option, an
+    /// arrow, and its list of options when it is open, or a list box, which draws a row for each
+    /// option.
+    SelectControl,
+}
+
+/// An option of a `<select>`, which draws a row in the list of options.
+#[deriving(Clone)]
+pub struct SelectOptionInfo {
+    /// The `<option>` node, to which the display items of the row belong so that clicking the
+    /// row targets the option.
+    pub node: OpaqueNode,
+    /// The text run of the label of the option.
+    pub run: Arc<Box<TextRun>>,
+    pub selected: bool,
+    pub disabled: bool,
+}
+
+/// A fragment that represents a form control: an `<input>`, a `<select>` or a `<textarea>`. Its
+/// text is shaped when the fragment is built, and it is sized like replaced content.
+#[deriving(Clone)]
+pub struct InputFragmentInfo {
+    pub kind: FormControlKind,
+    /// One text run for each line of the text that the control shows.
+    pub runs: Vec<Arc<Box<TextRun>>>,
+    /// True if the text is the placeholder, which is drawn greyed out, rather than the value.
+    pub is_placeholder: bool,
+    /// The line and the character within it before which the caret is drawn, if the control has
+    /// the focus.
+    pub caret: Option<(uint, CharIndex)>,
+    pub checked: bool,
+    /// The options of a `<select>`.
+    pub options: Vec<SelectOptionInfo>,
+    /// The number of options that a `<select>` shows at once: 1 for a drop-down box.
+    pub display_size: u32,
+    /// The option that the list of options of a drop-down box highlights, if the list is open.
+    pub list_highlight: Option<uint>,
+    /// The index of the first option that a list box shows.
+    pub list_scroll: uint,
+    /// The height of each line of text.
+    pub line_height: Au,
+    /// The size of the content box when the style doesn't specify one.
+    pub intrinsic_width: Au,
+    pub intrinsic_height: Au,
+    pub computed_width: Option<Au>,
+    pub computed_height: Option<Au>,
+}
+
+/// The width and height of the box of a checkbox or a radio button.
+static CHECK_CONTROL_SIZE: i32 = 13;
+
+/// The width of the space at the right of a drop-down box in which its arrow is drawn.
+static SELECT_ARROW_WIDTH: i32 = 16;
+
+/// The stacking level of the open list of options of a drop-down box, which is drawn over
+/// everything else on the page.
+static SELECT_LIST_STACKING_LEVEL: StackingLevel = PositionedDescendantStackingLevel(i32::MAX);
+
+impl InputFragmentInfo {
+    /// Creates the information specific to a form control fragment, shaping the text that the
+    /// control shows.
+    pub fn new(node: &ThreadSafeLayoutNode, font_context: &mut FontContext) -> InputFragmentInfo {
+        let kind = match node.type_id() {
+            Some(ElementNodeTypeId(HTMLTextAreaElementTypeId)) => TextControl,
+            Some(ElementNodeTypeId(HTMLSelectElementTypeId)) => SelectControl,
+            _ => {
+                match node.input_type() {
+                    InputText | InputPassword | InputEmail | InputUrl | InputNumber |
+                    InputHidden => TextControl,
+                    InputSubmit | InputReset | InputButton => ButtonControl,
+                    InputCheckbox | InputRadio => CheckControl,
+                }
+            }
+        };
+
+        let style = node.style();
+        let font_style = text::computed_style_to_font_style(&**style);
+        let line_height = text::line_height_from_style(&**style, style.get_font().font_size);
+        let mut info = InputFragmentInfo {
+            kind: kind,
+            runs: vec!(),
+            is_placeholder: false,
+            caret: None,
+            checked: false,
+            options: vec!(),
+            display_size: 1,
+            list_highlight: None,
+            list_scroll: 0,
+            line_height: line_height,
+            intrinsic_width: Au(0),
+            intrinsic_height: Au(0),
+            computed_width: None,
+            computed_height: None,
+        };
+
+        if kind == CheckControl {
+            info.checked = node.input_checked();
+            info.intrinsic_width = Au::from_px(CHECK_CONTROL_SIZE as int);
+            info.intrinsic_height = Au::from_px(CHECK_CONTROL_SIZE as int);
+            return info
+        }
+
+        let mut value = if kind == SelectControl {
+            String::new()
+        } else {
+            node.form_control_value()
+        };
+        if kind == TextControl && value.is_empty() {
+            let placeholder = node.as_element().get_attr(&namespace::Null, "placeholder");
+            match placeholder {
+                Some(placeholder) if !placeholder.is_empty() => {
+                    value = placeholder.chars().filter(|&c| c != '\r').collect();
+                    info.is_placeholder = true;
+                }
+                Some(_) | None => {}
+            }
+        }
+
+        // Shape each line of the text on its own, and measure the width of a digit to size text
+        // controls by their number of columns.
+        let fontgroup = font_context.get_resolved_font_for_style(&font_style);
+        let decoration = style.get_text().text_decoration_line;
+        let word_break = style.get_inheritedtext().word_break;
+        let shaping_options = text::computed_style_to_shaping_options(&**style);
+        let mut shape = |text: &str| {
+            Arc::new(box font_context.create_textrun(&fontgroup,
+                                                     0,
+                                                     text.to_string(),
+                                                     decoration,
+                                                     LTR_LEVEL,
+                                                     word_break,
+                                                     &shaping_options))
+        };
+        if kind == SelectControl {
+            for &(ref option, disabled) in select_options(node).iter() {
+                let label = option_label(option);
+                info.options.push(SelectOptionInfo {
+                    node: OpaqueNodeMethods::from_thread_safe_layout_node(option),
+                    run: shape(label.as_slice()),
+                    selected: option.option_selected(),
+                    disabled: disabled,
+                })
+            }
+            let label_width = info.options.iter().fold(Au(0), |width, option| {
+                let run = &option.run;
+                Au::max(width, run.advance_for_range(&Range::new(CharIndex(0), run.char_len())))
+            });
+
+            // A drop-down box shows the label of its first selected option, and a list box shows
+            // as many rows as its display size.
+            info.display_size = node.select_display_size();
+            if info.display_size == 1 {
+                let label = match info.options.iter().find(|option| option.selected) {
+                    Some(option) => option.run.clone(),
+                    None => shape(""),
+                };
+                info.runs.push(label);
+                info.list_highlight = node.select_list_highlight();
+                info.intrinsic_width = label_width + Au::from_px(SELECT_ARROW_WIDTH as int);
+                info.intrinsic_height = line_height;
+            } else {
+                // Keep the last page of the list box full.
+                let last_page = info.options.len().saturating_sub(info.display_size as uint);
+                info.list_scroll = cmp::min(node.select_list_scroll(), last_page);
+                info.intrinsic_width = label_width;
+                info.intrinsic_height = line_height.scale_by(info.display_size as f64);
+            }
+            return info
+        }
+
+        for line in value.as_slice().split('\n') {
+            info.runs.push(shape(line))
+        }
+        if kind == TextControl {
+            let (cols, rows) = node.text_control_size();
+            let digit = shape("0");
+            let char_width = digit.advance_for_range(&Range::new(CharIndex(0), CharIndex(1)));
+            info.intrinsic_width = char_width.scale_by(cols as f64);
+            info.intrinsic_height = line_height.scale_by(rows as f64);
+        } else {
+            info.intrinsic_width = info.runs.iter().fold(Au(0), |width, run| {
+                Au::max(width, run.advance_for_range(&Range::new(CharIndex(0), run.char_len())))
+            });
+            info.intrinsic_height = line_height.scale_by(info.runs.len() as f64);
+        }
+
+        // Find the line that the caret is on by skipping the lines and line breaks before it.
+        if !info.is_placeholder {
+            let caret = node.form_control_caret().map(|caret| {
+                let mut line = 0;
+                let mut column = caret;
+                for run in info.runs.iter() {
+                    let length = run.char_len().to_uint();
+                    if column <= length {
+                        break
+                    }
+                    column -= length + 1;
+                    line += 1;
+                }
+                (line, CharIndex(column as int))
+            });
+            info.caret = caret;
+        }
+
+        info
+    }
+
+    /// Returns the calculated width of the content box of the control.
+    pub fn computed_width(&self) -> Au {
+        self.computed_width.expect("form control width is not computed yet!")
+    }
+
+    /// Returns the calculated height of the content box of the control.
+    pub fn computed_height(&self) -> Au {
+        self.computed_height.expect("form control height is not computed yet!")
+    }
+}
+
+/// Returns the options of a `<select>` node in tree order: its option children and the option
+/// children of its `<optgroup>` children, along with whether each option is disabled.
+fn select_options<'ln>(node: &ThreadSafeLayoutNode<'ln>)
+                       -> Vec<(ThreadSafeLayoutNode<'ln>, bool)> {
+    let is_disabled = |node: &ThreadSafeLayoutNode| {
+        node.as_element().get_attr(&namespace::Null, "disabled").is_some()
+    };
+    let mut options = vec!();
+    for kid in node.children() {
+        match kid.type_id() {
+            Some(ElementNodeTypeId(HTMLOptionElementTypeId)) => {
+                let disabled = is_disabled(&kid);
+                options.push((kid, disabled))
+            }
+            Some(ElementNodeTypeId(HTMLOptGroupElementTypeId)) => {
+                let group_disabled = is_disabled(&kid);
+                for grandkid in kid.children() {
+                    if grandkid.type_id() == Some(ElementNodeTypeId(HTMLOptionElementTypeId)) {
+                        let disabled = group_disabled || is_disabled(&grandkid);
+                        options.push((grandkid, disabled))
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    options
+}
+
+/// Returns the label of an `<option>` node: its `label` attribute, or else its text with the
+/// whitespace stripped and collapsed.
+fn option_label(node: &ThreadSafeLayoutNode) -> String {
+    match node.as_element().get_attr(&namespace::Null, "label") {
+        Some(label) => return label.to_string(),
+        None => {}
+    }
+    let mut text = String::new();
+    for kid in node.children() {
+        if kid.type_id() == Some(TextNodeTypeId) {
+            text.push_str(kid.text().as_slice())
+        }
+    }
+    let words: Vec<&str> = split_html_space_chars(text.as_slice()).collect();
+    words.connect(" ")
+}
+
 /// A scanned text fragment represents a single run of text with a distinct style. A `TextFragment`
 /// may be split into two or more fragments across line breaks. Several `TextFragment`s may
 /// correspond to a single DOM text node. Split text fragments are implemented by referring to
@@ -264,6 +620,9 @@ impl SplitInfo {
 pub struct UnscannedTextFragmentInfo {
     /// The text inside the fragment.
     pub text: String,
+    /// The content language of the text, used for language-sensitive `text-transform` case
+    /// mappings.
+    pub lang: Option<String>,
 }
 
 impl UnscannedTextFragmentInfo {
@@ -272,6 +631,7 @@ impl UnscannedTextFragmentInfo {
         // FIXME(pcwalton): Don't copy text; atomically reference count it instead.
         UnscannedTextFragmentInfo {
             text: node.text(),
+            lang: node.language(),
         }
     }
 
@@ -280,6 +640,25 @@ impl UnscannedTextFragmentInfo {
     pub fn from_text(text: String) -> UnscannedTextFragmentInfo {
         UnscannedTextFragmentInfo {
             text: text,
+            lang: None,
+        }
+    }
+}
+
+/// Data for generated content that refers to counters or quotes. The text of these fragments
+/// depends on every preceding element in the document, so it is not known until the generated
+/// content resolution pass has run over the whole flow tree.
+#[deriving(Clone)]
+pub struct GeneratedContentInfo {
+    /// The items of the `content` property, with `attr()` references already replaced by strings.
+    pub items: Vec<content::Content>,
+}
+
+impl GeneratedContentInfo {
+    /// Creates the information specific to a generated content fragment.
+    pub fn new(items: Vec<content::Content>) -> GeneratedContentInfo {
+        GeneratedContentInfo {
+            items: items,
         }
     }
 }
@@ -324,6 +703,8 @@ impl Fragment {
             margin: Zero::zero(),
             specific: constructor.build_specific_fragment_info_for_node(node),
             new_line_pos: vec!(),
+            bidi_level: LTR_LEVEL,
+            collapsed_borders: None,
         }
     }
 
@@ -337,6 +718,8 @@ impl Fragment {
             margin: Zero::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: LTR_LEVEL,
+            collapsed_borders: None,
         }
     }
 
@@ -360,6 +743,8 @@ impl Fragment {
             margin: Zero::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: LTR_LEVEL,
+            collapsed_borders: None,
         }
     }
 
@@ -376,6 +761,8 @@ impl Fragment {
             margin: Zero::zero(),
             specific: specific,
             new_line_pos: vec!(),
+            bidi_level: LTR_LEVEL,
+            collapsed_borders: None,
         }
     }
 
@@ -396,6 +783,8 @@ impl Fragment {
             margin: self.margin,
             specific: specific,
             new_line_pos: self.new_line_pos.clone(),
+            bidi_level: self.bidi_level,
+            collapsed_borders: self.collapsed_borders.clone(),
         }
     }
 
@@ -403,11 +792,14 @@ impl Fragment {
     /// replaced elements.
     fn style_specified_intrinsic_width(&self) -> IntrinsicWidths {
         let (use_margins, use_padding) = match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) => (true, true),
+            GenericFragment | IframeFragment(_) | ImageFragment(_) | InputFragment(_) => {
+                (true, true)
+            }
             TableFragment | TableCellFragment => (false, true),
             TableWrapperFragment => (true, false),
             TableRowFragment => (false, false),
-            ScannedTextFragment(_) | TableColumnFragment(_) | UnscannedTextFragment(_) => {
+            GeneratedContentFragment(_) | InlineBlockFragment(_) | ScannedTextFragment(_) |
+            TableColumnFragment(_) | UnscannedTextFragment(_) => {
                 // Styles are irrelevant for these kinds of fragments.
                 return IntrinsicWidths::new()
             }
@@ -452,9 +844,10 @@ impl Fragment {
     #[inline]
     fn border_width(&self, inline_fragment_context: Option<InlineFragmentContext>)
                     -> SideOffsets2D<Au> {
-        match inline_fragment_context {
-            None => model::border_from_style(self.style()),
-            Some(inline_fragment_context) => {
+        match (inline_fragment_context, &self.collapsed_borders) {
+            (None, &Some(ref collapsed_borders)) => collapsed_borders.widths(),
+            (None, &None) => model::border_from_style(self.style()),
+            (Some(inline_fragment_context), _) => {
                 inline_fragment_context.ranges().map(|range| range.border()).sum()
             }
         }
@@ -485,16 +878,12 @@ impl Fragment {
         }
 
         // Compute border.
-        let border = match inline_fragment_context {
-            None => model::border_from_style(self.style()),
-            Some(inline_fragment_context) => {
-                inline_fragment_context.ranges().map(|range| range.border()).sum()
-            }
-        };
+        let border = self.border_width(inline_fragment_context);
 
-        // Compute padding.
+        // Compute padding. Tables with collapsing borders have no padding.
         let padding = match self.specific {
             TableColumnFragment(_) | TableRowFragment | TableWrapperFragment => Zero::zero(),
+            TableFragment if self.collapsed_borders.is_some() => Zero::zero(),
             _ => {
                 match inline_fragment_context {
                     None => model::padding_from_style(self.style(), containing_block_width),
@@ -583,6 +972,11 @@ impl Fragment {
         text::computed_style_to_font_style(self.style())
     }
 
+    /// Converts this fragment's computed style to the options used to shape its text.
+    pub fn shaping_options(&self) -> ShapingOptions {
+        text::computed_style_to_shaping_options(self.style())
+    }
+
     #[inline(always)]
     pub fn style<'a>(&'a self) -> &'a ComputedValues {
         &*self.style
@@ -609,8 +1003,8 @@ impl Fragment {
     /// CSS 2.1 § 16.3.1. Unfortunately, computing this properly doesn't really fit into Servo's
     /// model. Therefore, this is a best lower bound approximation, but the end result may actually
     /// have the various decoration flags turned on afterward.
-    pub fn text_decoration(&self) -> text_decoration::T {
-        self.style().get_text().text_decoration
+    pub fn text_decoration(&self) -> text_decoration_line::T {
+        self.style().get_text().text_decoration_line
     }
 
     /// Returns the left offset from margin edge to content edge.
@@ -755,23 +1149,39 @@ impl Fragment {
         }
 
         let style = self.style();
-        let top_color = style.resolve_color(style.get_border().border_top_color);
-        let right_color = style.resolve_color(style.get_border().border_right_color);
-        let bottom_color = style.resolve_color(style.get_border().border_bottom_color);
-        let left_color = style.resolve_color(style.get_border().border_left_color);
+        let (color, border_style) = match (inline_fragment_context, &self.collapsed_borders) {
+            (None, &Some(ref collapsed_borders)) => {
+                (SideOffsets2D::new(collapsed_borders.top.color.to_gfx_color(),
+                                    collapsed_borders.right.color.to_gfx_color(),
+                                    collapsed_borders.bottom.color.to_gfx_color(),
+                                    collapsed_borders.left.color.to_gfx_color()),
+                 SideOffsets2D::new(collapsed_borders.top.style,
+                                    collapsed_borders.right.style,
+                                    collapsed_borders.bottom.style,
+                                    collapsed_borders.left.style))
+            }
+            _ => {
+                let top_color = style.resolve_color(style.get_border().border_top_color);
+                let right_color = style.resolve_color(style.get_border().border_right_color);
+                let bottom_color = style.resolve_color(style.get_border().border_bottom_color);
+                let left_color = style.resolve_color(style.get_border().border_left_color);
+                (SideOffsets2D::new(top_color.to_gfx_color(),
+                                    right_color.to_gfx_color(),
+                                    bottom_color.to_gfx_color(),
+                                    left_color.to_gfx_color()),
+                 SideOffsets2D::new(style.get_border().border_top_style,
+                                    style.get_border().border_right_style,
+                                    style.get_border().border_bottom_style,
+                                    style.get_border().border_left_style))
+            }
+        };
 
         // Append the border to the display list.
         let border_display_item = box BorderDisplayItem {
             base: BaseDisplayItem::new(*abs_bounds, self.node, level),
             border: border,
-            color: SideOffsets2D::new(top_color.to_gfx_color(),
-                                      right_color.to_gfx_color(),
-                                      bottom_color.to_gfx_color(),
-                                      left_color.to_gfx_color()),
-            style: SideOffsets2D::new(style.get_border().border_top_style,
-                                      style.get_border().border_right_style,
-                                      style.get_border().border_bottom_style,
-                                      style.get_border().border_left_style)
+            color: color,
+            style: border_style,
         };
 
         list.push(BorderDisplayItemClass(border_display_item))
@@ -826,6 +1236,265 @@ impl Fragment {
         display_list.push(BorderDisplayItemClass(border_display_item))
     }
 
+    /// Adds the display items that draw a row of the list of options of a `<select>`: the label
+    /// of the option, on the highlight color if `highlighted` is true. The first and the last row
+    /// of the open list of a drop-down box also draw its frame, as given by `frame`.
+    fn build_display_list_for_select_option(&self,
+                                            display_list: &mut DisplayList,
+                                            option: &SelectOptionInfo,
+                                            row_bounds: &Rect<Au>,
+                                            highlighted: bool,
+                                            frame: Option<SideOffsets2D<Au>>,
+                                            level: StackingLevel) {
+        let text_color = if highlighted {
+            rgb(255, 255, 255)
+        } else if option.disabled {
+            rgb(128, 128, 128)
+        } else {
+            self.style().get_color().color.to_gfx_color()
+        };
+        let background_color = if highlighted {
+            Some(rgb(56, 117, 215))
+        } else if frame.is_some() {
+            Some(rgb(255, 255, 255))
+        } else {
+            None
+        };
+
+        match background_color {
+            Some(color) => {
+                let background_display_item = box SolidColorDisplayItem {
+                    base: BaseDisplayItem::new(*row_bounds, option.node, level),
+                    color: color,
+                };
+                display_list.push(SolidColorDisplayItemClass(background_display_item))
+            }
+            None => {}
+        }
+        match frame {
+            Some(frame) => {
+                let frame_display_item = box BorderDisplayItem {
+                    base: BaseDisplayItem::new(*row_bounds, option.node, level),
+                    border: frame,
+                    color: SideOffsets2D::new_all_same(rgb(128, 128, 128)),
+                    style: SideOffsets2D::new_all_same(border_style::solid),
+                };
+                display_list.push(BorderDisplayItemClass(frame_display_item))
+            }
+            None => {}
+        }
+
+        let run = &option.run;
+        let text_height = run.font_metrics.ascent + run.font_metrics.descent;
+        let text_origin = Point2D(row_bounds.origin.x + self.border_padding.left,
+                                  row_bounds.origin.y +
+                                  (row_bounds.size.height - text_height).scale_by(0.5));
+        let text_display_item = box TextDisplayItem {
+            base: BaseDisplayItem::new(Rect(text_origin, Size2D(row_bounds.size.width,
+                                                                text_height)),
+                                       option.node,
+                                       level),
+            text_run: run.clone(),
+            range: Range::new(CharIndex(0), run.char_len()),
+            text_color: text_color,
+            text_decorations: TextDecorations {
+                underline: None,
+                overline: None,
+                line_through: None,
+            },
+        };
+        display_list.push(TextDisplayItemClass(text_display_item))
+    }
+
+    /// Adds the display items that draw the rows of a list box to `clipped_list`, scrolled by
+    /// its scroll position, or the arrow of a drop-down box to `clipped_list` and its list of
+    /// options, when it is open, to `display_list`. The open list is drawn in the page, right
+    /// below the border box, and isn't clipped by the control.
+    fn build_display_list_for_select(&self,
+                                     display_list: &mut DisplayList,
+                                     clipped_list: &mut DisplayList,
+                                     input_fragment: &InputFragmentInfo,
+                                     bounds: &Rect<Au>) {
+        let line_height = input_fragment.line_height;
+        if input_fragment.display_size != 1 {
+            let first_row_y = bounds.origin.y -
+                line_height.scale_by(input_fragment.list_scroll as f64);
+            for (i, option) in input_fragment.options.iter().enumerate() {
+                let row_bounds = Rect(Point2D(bounds.origin.x - self.border_padding.left,
+                                              first_row_y + line_height.scale_by(i as f64)),
+                                      Size2D(bounds.size.width + self.border_padding.horizontal(),
+                                             line_height));
+                self.build_display_list_for_select_option(clipped_list,
+                                                          option,
+                                                          &row_bounds,
+                                                          option.selected,
+                                                          None,
+                                                          ContentStackingLevel)
+            }
+            return
+        }
+
+        // Draw the arrow as a downward-pointing triangle, one row of pixels at a time, centered
+        // in the space at the right of the content box.
+        let arrow_rows = 4;
+        let arrow_center_x = bounds.origin.x + bounds.size.width -
+            Au::from_px(SELECT_ARROW_WIDTH as int).scale_by(0.5);
+        let arrow_top = bounds.origin.y +
+            (bounds.size.height - Au::from_px(arrow_rows)).scale_by(0.5);
+        for row in range(0, arrow_rows) {
+            let row_width = Au::from_px((arrow_rows - row) * 2 - 1);
+            let row_bounds = Rect(Point2D(arrow_center_x - row_width.scale_by(0.5),
+                                          arrow_top + Au::from_px(row)),
+                                  Size2D(row_width, Au::from_px(1)));
+            let arrow_display_item = box SolidColorDisplayItem {
+                base: BaseDisplayItem::new(row_bounds, self.node, ContentStackingLevel),
+                color: self.style().get_color().color.to_gfx_color(),
+            };
+            clipped_list.push(SolidColorDisplayItemClass(arrow_display_item))
+        }
+
+        let highlight = match input_fragment.list_highlight {
+            Some(highlight) => highlight,
+            None => return,
+        };
+        let frame_width = Au::from_px(1);
+        let option_count = input_fragment.options.len();
+        let list_origin = Point2D(bounds.origin.x - self.border_padding.left,
+                                  bounds.origin.y + bounds.size.height +
+                                  self.border_padding.bottom);
+        for (i, option) in input_fragment.options.iter().enumerate() {
+            let row_bounds = Rect(Point2D(list_origin.x,
+                                          list_origin.y + line_height.scale_by(i as f64)),
+                                  Size2D(bounds.size.width + self.border_padding.horizontal(),
+                                         line_height));
+            let frame = SideOffsets2D::new(if i == 0 { frame_width } else { Au(0) },
+                                           frame_width,
+                                           if i + 1 == option_count { frame_width } else { Au(0) },
+                                           frame_width);
+            self.build_display_list_for_select_option(display_list,
+                                                      option,
+                                                      &row_bounds,
+                                                      i == highlight,
+                                                      Some(frame),
+                                                      SELECT_LIST_STACKING_LEVEL)
+        }
+    }
+
+    /// Adds the display items that draw the inside of a form control: its lines of text and its
+    /// caret, the mark of a checked checkbox or radio button, or the options of a `<select>`.
+    /// `bounds` is the absolute content box of the control. Everything but the open list of a
+    /// drop-down box is clipped to the padding box, and the text of a text control is scrolled
+    /// to keep the caret in view.
+    fn build_display_list_for_form_control(&self,
+                                           accumulator: &mut ChildDisplayListAccumulator,
+                                           display_list: &mut DisplayList,
+                                           input_fragment: &InputFragmentInfo,
+                                           bounds: &Rect<Au>) {
+        let text_color = self.style().get_color().color.to_gfx_color();
+        if input_fragment.kind == CheckControl {
+            if input_fragment.checked {
+                let inset_x = bounds.size.width.scale_by(0.25);
+                let inset_y = bounds.size.height.scale_by(0.25);
+                let mark_bounds = Rect(Point2D(bounds.origin.x + inset_x,
+                                               bounds.origin.y + inset_y),
+                                       Size2D(bounds.size.width - inset_x.scale_by(2.0),
+                                              bounds.size.height - inset_y.scale_by(2.0)));
+                let mark_display_item = box SolidColorDisplayItem {
+                    base: BaseDisplayItem::new(mark_bounds, self.node, ContentStackingLevel),
+                    color: text_color,
+                };
+                accumulator.push(display_list, SolidColorDisplayItemClass(mark_display_item))
+            }
+            return
+        }
+
+        let border = self.border_width(None);
+        let padding_box = Rect(Point2D(bounds.origin.x - self.border_padding.left + border.left,
+                                       bounds.origin.y - self.border_padding.top + border.top),
+                               Size2D(bounds.size.width + self.border_padding.horizontal() -
+                                      border.horizontal(),
+                                      bounds.size.height + self.border_padding.vertical() -
+                                      border.vertical()));
+        let mut clip_display_item = box ClipDisplayItem {
+            base: BaseDisplayItem::new(padding_box, self.node, ContentStackingLevel),
+            children: DisplayList::new(),
+        };
+        if input_fragment.kind == SelectControl {
+            self.build_display_list_for_select(display_list,
+                                               &mut clip_display_item.children,
+                                               input_fragment,
+                                               bounds);
+        }
+
+        let line_color = if input_fragment.is_placeholder {
+            rgb(117, 117, 117)
+        } else {
+            text_color
+        };
+        let line_height = input_fragment.line_height;
+
+        // Scroll the text as little as possible to show the caret.
+        let scroll_offset = match input_fragment.caret {
+            Some((caret_line, caret_column)) => {
+                let run = input_fragment.runs.get(caret_line);
+                let caret_x = run.advance_for_range(&Range::new(CharIndex(0), caret_column)) +
+                    Au::from_px(1);
+                let caret_bottom = line_height.scale_by((caret_line + 1) as f64);
+                Point2D(geometry::max(caret_x - bounds.size.width, Au(0)),
+                        geometry::max(caret_bottom - bounds.size.height, Au(0)))
+            }
+            None => Point2D(Au(0), Au(0)),
+        };
+
+        for (i, run) in input_fragment.runs.iter().enumerate() {
+            // Center the text of each line vertically within the line, and the label of a button
+            // horizontally within the button.
+            let run_range = Range::new(CharIndex(0), run.char_len());
+            let text_height = run.font_metrics.ascent + run.font_metrics.descent;
+            let mut line_origin = Point2D(bounds.origin.x - scroll_offset.x,
+                                          bounds.origin.y + line_height.scale_by(i as f64) +
+                                          (line_height - text_height).scale_by(0.5) -
+                                          scroll_offset.y);
+            if input_fragment.kind == ButtonControl {
+                let slack = bounds.size.width - run.advance_for_range(&run_range);
+                line_origin.x = line_origin.x + geometry::max(slack, Au(0)).scale_by(0.5);
+            }
+
+            let text_display_item = box TextDisplayItem {
+                base: BaseDisplayItem::new(Rect(line_origin, Size2D(bounds.size.width,
+                                                                    text_height)),
+                                           self.node,
+                                           ContentStackingLevel),
+                text_run: run.clone(),
+                range: run_range,
+                text_color: line_color,
+                text_decorations: TextDecorations {
+                    underline: None,
+                    overline: None,
+                    line_through: None,
+                },
+            };
+            clip_display_item.children.push(TextDisplayItemClass(text_display_item));
+
+            match input_fragment.caret {
+                Some((caret_line, caret_column)) if caret_line == i => {
+                    let caret_x = run.advance_for_range(&Range::new(CharIndex(0), caret_column));
+                    let caret_bounds = Rect(Point2D(line_origin.x + caret_x, line_origin.y),
+                                            Size2D(Au::from_px(1), text_height));
+                    let caret_display_item = box SolidColorDisplayItem {
+                        base: BaseDisplayItem::new(caret_bounds, self.node, ContentStackingLevel),
+                        color: text_color,
+                    };
+                    clip_display_item.children
+                                     .push(SolidColorDisplayItemClass(caret_display_item))
+                }
+                Some(_) | None => {}
+            }
+        }
+
+        accumulator.push(display_list, ClipDisplayItemClass(clip_display_item))
+    }
+
     /// Adds the display items for this fragment to the given stacking context.
     ///
     /// Arguments:
@@ -893,7 +1562,9 @@ impl Fragment {
         // Add a clip, if applicable.
         match self.specific {
             UnscannedTextFragment(_) => fail!("Shouldn't see unscanned fragments here."),
+            GeneratedContentFragment(_) => fail!("Shouldn't see unresolved generated content here."),
             TableColumnFragment(_) => fail!("Shouldn't see table column fragments here."),
+            InlineBlockFragment(_) => fail!("Inline block fragments are drawn by their flows."),
             ScannedTextFragment(ref text_fragment) => {
                 // Compute text color.
                 let text_color = self.style().get_color().color.to_gfx_color();
@@ -902,11 +1573,17 @@ impl Fragment {
                 let text_decorations_in_effect = self.style()
                                                      .get_inheritedtext()
                                                      ._servo_text_decorations_in_effect;
+                fn to_gfx_decoration(decoration: Decoration) -> TextDecoration {
+                    TextDecoration {
+                        color: decoration.color.to_gfx_color(),
+                        style: decoration.style,
+                    }
+                }
                 let text_decorations = TextDecorations {
-                    underline: text_decorations_in_effect.underline.map(|c| c.to_gfx_color()),
-                    overline: text_decorations_in_effect.overline.map(|c| c.to_gfx_color()),
+                    underline: text_decorations_in_effect.underline.map(to_gfx_decoration),
+                    overline: text_decorations_in_effect.overline.map(to_gfx_decoration),
                     line_through: text_decorations_in_effect.line_through
-                                                            .map(|c| c.to_gfx_color()),
+                                                            .map(to_gfx_decoration),
                 };
 
                 let mut bounds = absolute_fragment_bounds.clone();
@@ -937,6 +1614,21 @@ impl Fragment {
                 // should have a real `SERVO_DEBUG` system.
                 debug!("{:?}", self.build_debug_borders_around_fragment(display_list, flow_origin))
             },
+            InputFragment(ref input_fragment) => {
+                let mut bounds = absolute_fragment_bounds.clone();
+                bounds.origin.x = bounds.origin.x + self.border_padding.left;
+                bounds.origin.y = bounds.origin.y + self.border_padding.top;
+                bounds.size.width = bounds.size.width - self.border_padding.horizontal();
+                bounds.size.height = bounds.size.height - self.border_padding.vertical();
+                self.build_display_list_for_form_control(&mut accumulator,
+                                                         display_list,
+                                                         input_fragment,
+                                                         &bounds);
+
+                // FIXME(pcwalton): This is a bit of an abuse of the logging
+                // infrastructure. We should have a real `SERVO_DEBUG` system.
+                debug!("{:?}", self.build_debug_borders_around_fragment(display_list, flow_origin))
+            }
             ImageFragment(_) => {
                 let mut bounds = absolute_fragment_bounds.clone();
                 bounds.origin.x = bounds.origin.x + self.border_padding.left;
@@ -1006,7 +1698,17 @@ impl Fragment {
 
         match self.specific {
             GenericFragment | IframeFragment(_) | TableFragment | TableCellFragment | TableColumnFragment(_) | TableRowFragment |
-            TableWrapperFragment => {}
+            TableWrapperFragment | GeneratedContentFragment(_) => {}
+            InlineBlockFragment(ref info) => {
+                let flow_intrinsic_widths = flow::base(info.flow_ref.get()).intrinsic_widths;
+                result.minimum_width = flow_intrinsic_widths.total_minimum_width();
+                result.preferred_width = flow_intrinsic_widths.total_preferred_width();
+            }
+            InputFragment(ref input_fragment_info) => {
+                let input_width = input_fragment_info.intrinsic_width;
+                result.minimum_width = geometry::max(result.minimum_width, input_width);
+                result.preferred_width = geometry::max(result.preferred_width, input_width);
+            }
             ImageFragment(ref mut image_fragment_info) => {
                 let image_width = image_fragment_info.image_width();
                 result.minimum_width = geometry::max(result.minimum_width, image_width);
@@ -1053,6 +1755,8 @@ impl Fragment {
             ImageFragment(ref image_fragment_info) => {
                 image_fragment_info.computed_width()
             }
+            InlineBlockFragment(_) => self.border_box.size.width,
+            InputFragment(ref input_fragment_info) => input_fragment_info.computed_width(),
             ScannedTextFragment(ref text_fragment_info) => {
                 let (range, run) = (&text_fragment_info.range, &text_fragment_info.run);
                 let text_bounds = run.metrics_for_range(range).bounding_box;
@@ -1060,6 +1764,9 @@ impl Fragment {
             }
             TableColumnFragment(_) => fail!("Table column fragments do not have width"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
         }
     }
 
@@ -1071,6 +1778,8 @@ impl Fragment {
             ImageFragment(ref image_fragment_info) => {
                 image_fragment_info.computed_height()
             }
+            InlineBlockFragment(_) => self.border_box.size.height,
+            InputFragment(ref input_fragment_info) => input_fragment_info.computed_height(),
             ScannedTextFragment(ref text_fragment_info) => {
                 // Compute the height based on the line-height and font size.
                 //
@@ -1083,6 +1792,9 @@ impl Fragment {
             }
             TableColumnFragment(_) => fail!("Table column fragments do not have height"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
         }
     }
 
@@ -1111,10 +1823,14 @@ impl Fragment {
     pub fn find_split_info_by_new_line(&self)
             -> Option<(SplitInfo, Option<SplitInfo>, Arc<Box<TextRun>> /* TODO(bjz): remove */)> {
         match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) | TableFragment | TableCellFragment |
-            TableRowFragment | TableWrapperFragment => None,
+            GenericFragment | IframeFragment(_) | ImageFragment(_) | InlineBlockFragment(_) |
+            InputFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
+            TableWrapperFragment => None,
             TableColumnFragment(_) => fail!("Table column fragments do not need to split"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
             ScannedTextFragment(ref text_fragment_info) => {
                 let mut new_line_pos = self.new_line_pos.clone();
                 let cur_new_line_pos = new_line_pos.shift().unwrap();
@@ -1151,10 +1867,14 @@ impl Fragment {
     pub fn find_split_info_for_width(&self, start: CharIndex, max_width: Au, starts_line: bool)
             -> Option<(Option<SplitInfo>, Option<SplitInfo>, Arc<Box<TextRun>> /* TODO(bjz): remove */)> {
         match self.specific {
-            GenericFragment | IframeFragment(_) | ImageFragment(_) | TableFragment | TableCellFragment |
-            TableRowFragment | TableWrapperFragment => None,
+            GenericFragment | IframeFragment(_) | ImageFragment(_) | InlineBlockFragment(_) |
+            InputFragment(_) | TableFragment | TableCellFragment | TableRowFragment |
+            TableWrapperFragment => None,
             TableColumnFragment(_) => fail!("Table column fragments do not have width"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
             ScannedTextFragment(ref text_fragment_info) => {
                 let mut pieces_processed_count: uint = 0;
                 let mut remaining_width: Au = max_width;
@@ -1207,6 +1927,30 @@ impl Fragment {
                                 debug!("split_to_width: case=skipping trimmable trailing \
                                         whitespace");
                             }
+                        } else if starts_line && left_range.length() == CharIndex(0) &&
+                                self.style().get_inheritedtext().overflow_wrap ==
+                                    overflow_wrap::break_word {
+                            // The slice would overflow an otherwise empty line and there is no
+                            // break opportunity within it, so break it after as many characters
+                            // as fit, but at least one.
+                            let mut fitting_length = CharIndex(1);
+                            while fitting_length < slice_range.length() {
+                                let prefix = Range::new(slice_range.begin(),
+                                                        fitting_length + CharIndex(1));
+                                if glyphs.advance_for_char_range(&prefix) > remaining_width {
+                                    break
+                                }
+                                fitting_length = fitting_length + CharIndex(1);
+                            }
+                            left_range.extend_by(fitting_length);
+
+                            let split_point = slice_begin + fitting_length;
+                            if split_point < text_fragment_info.range.end() {
+                                let right_range_end = text_fragment_info.range.end() - split_point;
+                                right_range = Some(Range::new(split_point, right_range_end));
+                            }
+                            debug!("split_to_width: case=breaking word with right range={:?}",
+                                   right_range);
                         } else if slice_begin < text_fragment_info.range.end() {
                             // There are still some things left over at the end of the line. Create
                             // the right chunk.
@@ -1261,7 +2005,16 @@ impl Fragment {
             TableWrapperFragment => return,
             TableColumnFragment(_) => fail!("Table column fragments do not have width"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
-            ImageFragment(_) | ScannedTextFragment(_) => {}
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
+            InlineBlockFragment(ref mut info) => {
+                // The flow of the box resolves its own margins, borders, and padding, so this
+                // fragment is only as wide as the margin box of that flow.
+                self.border_box.size.width = info.assign_width(container_width);
+                return
+            }
+            ImageFragment(_) | InputFragment(_) | ScannedTextFragment(_) => {}
         };
 
         self.compute_border_padding_margins(container_width, inline_fragment_context);
@@ -1298,6 +2051,12 @@ impl Fragment {
                 self.border_box.size.width = width + noncontent_width;
                 image_fragment_info.computed_width = Some(width);
             }
+            InputFragment(ref mut input_fragment_info) => {
+                let width = MaybeAuto::from_style(style_width, container_width)
+                                      .specified_or_default(input_fragment_info.intrinsic_width);
+                self.border_box.size.width = width + noncontent_width;
+                input_fragment_info.computed_width = Some(width);
+            }
             _ => fail!("this case should have been handled above"),
         }
     }
@@ -1312,7 +2071,22 @@ impl Fragment {
             TableWrapperFragment => return,
             TableColumnFragment(_) => fail!("Table column fragments do not have height"),
             UnscannedTextFragment(_) => fail!("Unscanned text fragments should have been scanned by now!"),
-            ImageFragment(_) | ScannedTextFragment(_) => {}
+            GeneratedContentFragment(_) => {
+                fail!("Generated content fragments should have been resolved by now!")
+            }
+            InlineBlockFragment(ref mut info) => {
+                let block = info.flow_ref.get_mut().as_block();
+                let fragment = &block.fragment;
+                let height = fragment.margin.top + fragment.border_box.size.height +
+                    fragment.margin.bottom;
+                info.ascent = match block.first_baseline() {
+                    Some(baseline) => fragment.margin.top + baseline,
+                    None => height,
+                };
+                self.border_box.size.height = height;
+                return
+            }
+            ImageFragment(_) | InputFragment(_) | ScannedTextFragment(_) => {}
         }
 
         let style_width = self.style().get_box().width;
@@ -1346,6 +2120,12 @@ impl Fragment {
                 image_fragment_info.computed_height = Some(height);
                 self.border_box.size.height = height + noncontent_height
             }
+            InputFragment(ref mut input_fragment_info) => {
+                let height = MaybeAuto::from_style(style_height, Au(0))
+                                       .specified_or_default(input_fragment_info.intrinsic_height);
+                input_fragment_info.computed_height = Some(height);
+                self.border_box.size.height = height + noncontent_height
+            }
             ScannedTextFragment(_) => {
                 // Scanned text fragments' content heights are calculated by the text run scanner
                 // during flow construction.
@@ -1367,6 +2147,21 @@ impl Fragment {
                     ascent: computed_height + self.border_padding.bottom,
                 }
             }
+            InputFragment(ref input_fragment_info) => {
+                let computed_height = input_fragment_info.computed_height();
+                InlineMetrics {
+                    height_above_baseline: computed_height + self.border_padding.vertical(),
+                    depth_below_baseline: Au(0),
+                    ascent: computed_height + self.border_padding.bottom,
+                }
+            }
+            InlineBlockFragment(ref info) => {
+                InlineMetrics {
+                    height_above_baseline: info.ascent,
+                    depth_below_baseline: self.border_box.size.height - info.ascent,
+                    ascent: info.ascent,
+                }
+            }
             ScannedTextFragment(ref text_fragment) => {
                 // See CSS 2.1 § 10.8.1.
                 let font_size = self.style().get_font().font_size;
@@ -1386,9 +2181,17 @@ impl Fragment {
     /// Returns true if this fragment can merge with another adjacent fragment or false otherwise.
     pub fn can_merge_with_fragment(&self, other: &Fragment) -> bool {
         match (&self.specific, &other.specific) {
-            (&UnscannedTextFragment(_), &UnscannedTextFragment(_)) => {
+            (&UnscannedTextFragment(ref info), &UnscannedTextFragment(ref other_info)) => {
+                // The shaping options cover `letter-spacing`, `word-spacing`, `font-variant`,
+                // `font-feature-settings`, and `font-kerning`.
                 self.font_style() == other.font_style() &&
-                    self.text_decoration() == other.text_decoration()
+                    self.shaping_options() == other.shaping_options() &&
+                    self.text_decoration() == other.text_decoration() &&
+                    self.style().get_inheritedtext().word_break ==
+                        other.style().get_inheritedtext().word_break &&
+                    self.style().get_inheritedtext().overflow_wrap ==
+                        other.style().get_inheritedtext().overflow_wrap &&
+                    info.lang == other_info.lang
             }
             _ => false,
         }
@@ -1446,8 +2249,11 @@ impl fmt::Show for Fragment {
         try!(write!(f, "({} ",
             match self.specific {
                 GenericFragment => "GenericFragment",
+                GeneratedContentFragment(_) => "GeneratedContentFragment",
                 IframeFragment(_) => "IframeFragment",
                 ImageFragment(_) => "ImageFragment",
+                InlineBlockFragment(_) => "InlineBlockFragment",
+                InputFragment(_) => "InputFragment",
                 ScannedTextFragment(_) => "ScannedTextFragment",
                 TableFragment => "TableFragment",
                 TableCellFragment => "TableCellFragment",
@@ -0,0 +1,326 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Resolution of counters and quotes in generated content, per CSS 2.1 § 12.3 and § 12.4.
+//!
+//! The value of a counter or the depth of quote nesting at a given point depends on every element
+//! that precedes that point in the document. Flow construction runs bottom-up and possibly in
+//! parallel, so it leaves `GeneratedContentFragment`s behind for any content that refers to
+//! counters or quotes. This module replaces them with text in a single sequential traversal of
+//! the flow tree in tree order.
+
+#![deny(unsafe_block)]
+
+use layout::context::LayoutContext;
+use layout::flow::{Flow, InlineFlowClass, TableColGroupFlowClass};
+use layout::flow;
+use layout::fragment::{Fragment, GeneratedContentFragment, GenericFragment, ScannedTextFragment};
+use layout::fragment::{ScannedTextFragmentInfo};
+use layout::inline::InlineFlow;
+
+use collections::hashmap::HashMap;
+use geom::Size2D;
+use gfx::display_list::OpaqueNode;
+use gfx::text::glyph::CharIndex;
+use servo_util::geometry::Au;
+use servo_util::range::Range;
+use std::ascii::StrAsciiExt;
+use std::cmp;
+use style::ComputedValues;
+use style::computed_values::content;
+use sync::Arc;
+
+/// A traversal that computes counters and quotes in tree order and resolves generated content
+/// fragments to text.
+pub struct ResolveGeneratedContent<'a> {
+    layout_context: &'a mut LayoutContext,
+
+    /// The instances of each counter that are in scope, innermost last. Each instance records
+    /// the depth of the flow tree at which it was created, so that it can be dropped once the
+    /// traversal leaves its scope.
+    counters: HashMap<String, Vec<(uint, int)>>,
+
+    /// The current nesting level of quotes.
+    quote_depth: uint,
+
+    /// The node and style of the block-like flow most recently visited. A block-level
+    /// pseudo-element and the inline content inside it share a style, as do a table wrapper and
+    /// its table, and their counter operations must only apply once.
+    last_block: Option<(OpaqueNode, uint)>,
+
+    /// Whether any fragment was resolved. If so, intrinsic widths need to be bubbled again.
+    resolved_any: bool,
+}
+
+impl<'a> ResolveGeneratedContent<'a> {
+    pub fn new(layout_context: &'a mut LayoutContext) -> ResolveGeneratedContent<'a> {
+        ResolveGeneratedContent {
+            layout_context: layout_context,
+            counters: HashMap::new(),
+            quote_depth: 0,
+            last_block: None,
+            resolved_any: false,
+        }
+    }
+
+    /// Returns true if any generated content fragment was replaced during the traversal.
+    pub fn resolved_any(&self) -> bool {
+        self.resolved_any
+    }
+
+    /// Visits the given flow and its descendants in tree order. `level` is the depth of the flow
+    /// in the flow tree.
+    pub fn traverse(&mut self, flow: &mut Flow, level: uint) {
+        match flow.class() {
+            InlineFlowClass => self.process_inline_flow(flow.as_inline(), level),
+            TableColGroupFlowClass => {}
+            _ => {
+                let fragment = &flow.as_block().fragment;
+                let block = (fragment.node, style_address(&fragment.style));
+                if self.last_block != Some(block) {
+                    self.apply_counter_operations(&*fragment.style, level);
+                    self.last_block = Some(block)
+                }
+            }
+        }
+
+        for kid in flow::child_iter(flow) {
+            self.traverse(kid, level + 1)
+        }
+    }
+
+    /// Applies the counter operations of the inline elements in the given inline flow and
+    /// resolves its generated content, in tree order.
+    fn process_inline_flow(&mut self, flow: &mut InlineFlow, level: uint) {
+        // Ranges are stored in no particular order, so sort them so that outer elements come
+        // before the elements that they contain.
+        let mut ranges: Vec<(uint, uint, Arc<ComputedValues>)> =
+            flow.fragments.ranges.iter().map(|range| {
+                (range.range.begin().to_uint(), range.range.end().to_uint(), range.style.clone())
+            }).collect();
+        ranges.sort_by(|&(a_start, a_end, _), &(b_start, b_end, _)| {
+            (a_start, -(a_end as int)).cmp(&(b_start, -(b_end as int)))
+        });
+
+        let block_style = self.last_block.map(|(_, style)| style);
+        let mut open_range_ends: Vec<uint> = vec!();
+        let mut range_iter = ranges.iter().peekable();
+        for fragment_i in range(0, flow.fragments.len() + 1) {
+            while open_range_ends.last().map_or(false, |end| *end <= fragment_i) {
+                drop(open_range_ends.pop())
+            }
+            loop {
+                match range_iter.peek() {
+                    Some(&&(start, _, _)) if start <= fragment_i => {}
+                    Some(_) | None => break,
+                }
+                let &(_, end, ref style) = range_iter.next().unwrap();
+                if block_style != Some(style_address(style)) {
+                    self.apply_counter_operations(&**style, level + 1 + open_range_ends.len())
+                }
+                if end > fragment_i {
+                    open_range_ends.push(end)
+                }
+            }
+
+            if fragment_i < flow.fragments.len() {
+                let fragment = flow.fragments.get_mut(fragment_i);
+                self.resolve_fragment(fragment, level + 1 + open_range_ends.len())
+            }
+        }
+    }
+
+    /// Replaces the given fragment with text if it is a generated content fragment.
+    fn resolve_fragment(&mut self, fragment: &mut Fragment, level: uint) {
+        let text = match fragment.specific {
+            GeneratedContentFragment(ref info) => {
+                self.exit_scopes(level);
+                let mut text = String::new();
+                for item in info.items.iter() {
+                    self.push_item_text(item, &*fragment.style, &mut text)
+                }
+                text
+            }
+            _ => return,
+        };
+
+        let new_fragment = if text.is_empty() {
+            fragment.transform(Size2D(Au(0), Au(0)), GenericFragment)
+        } else {
+            let font_context = self.layout_context.font_context();
+            let fontgroup = font_context.get_resolved_font_for_style(&fragment.font_style());
+            let word_break = fragment.style().get_inheritedtext().word_break;
+            let run = box font_context.create_textrun(&fontgroup,
+                                                      0,
+                                                      text,
+                                                      fragment.text_decoration(),
+                                                      fragment.bidi_level,
+                                                      word_break,
+                                                      &fragment.shaping_options());
+            let range = Range::new(CharIndex(0), run.char_len());
+            let size = run.metrics_for_range(&range).bounding_box.size;
+            fragment.transform(size, ScannedTextFragment(ScannedTextFragmentInfo::new(Arc::new(run),
+                                                                                       range)))
+        };
+        *fragment = new_fragment;
+        self.resolved_any = true
+    }
+
+    /// Appends the text of a single `content` item to `text`, updating the quote depth as
+    /// necessary.
+    fn push_item_text(&mut self, item: &content::Content, style: &ComputedValues, text: &mut String) {
+        match *item {
+            content::StringContent(ref string) => text.push_str(string.as_slice()),
+            content::CounterContent(ref name, counter_style) => {
+                let value = match self.counters.find(name).and_then(|instances| instances.last()) {
+                    Some(&(_, value)) => value,
+                    None => 0,
+                };
+                text.push_str(format_counter(value, counter_style).as_slice())
+            }
+            content::CountersContent(ref name, ref separator, counter_style) => {
+                let values: Vec<int> = match self.counters.find(name) {
+                    Some(instances) if !instances.is_empty() => {
+                        instances.iter().map(|&(_, value)| value).collect()
+                    }
+                    Some(_) | None => vec!(0),
+                };
+                let formatted: Vec<String> = values.iter().map(|value| {
+                    format_counter(*value, counter_style)
+                }).collect();
+                text.push_str(formatted.connect(separator.as_slice()).as_slice())
+            }
+            content::OpenQuote => {
+                match quote_at_depth(style, self.quote_depth) {
+                    Some(&(ref open, _)) => text.push_str(open.as_slice()),
+                    None => {}
+                }
+                self.quote_depth += 1
+            }
+            content::CloseQuote => {
+                if self.quote_depth > 0 {
+                    self.quote_depth -= 1;
+                    match quote_at_depth(style, self.quote_depth) {
+                        Some(&(_, ref close)) => text.push_str(close.as_slice()),
+                        None => {}
+                    }
+                }
+            }
+            content::NoOpenQuote => self.quote_depth += 1,
+            content::NoCloseQuote => {
+                if self.quote_depth > 0 {
+                    self.quote_depth -= 1
+                }
+            }
+            content::AttrContent(_) | content::UrlContent(_) => {
+                fail!("`attr()` and `url()` should have been handled during flow construction!")
+            }
+        }
+    }
+
+    /// Applies `counter-reset` and then `counter-increment` from the given style to an element at
+    /// the given level of the tree.
+    fn apply_counter_operations(&mut self, style: &ComputedValues, level: uint) {
+        self.exit_scopes(level);
+
+        let counters = style.get_counters();
+        for &(ref name, value) in counters.counter_reset.iter() {
+            // A reset by a sibling of the element that created the innermost instance replaces
+            // that instance rather than nesting inside it.
+            let instances = self.counters.find_or_insert(name.clone(), vec!());
+            if instances.last().map_or(false, |&(instance_level, _)| instance_level == level) {
+                drop(instances.pop())
+            }
+            instances.push((level, value))
+        }
+
+        for &(ref name, value) in counters.counter_increment.iter() {
+            // Incrementing a counter that is not in scope implicitly resets it.
+            let instances = self.counters.find_or_insert(name.clone(), vec!());
+            if instances.is_empty() {
+                instances.push((level, 0))
+            }
+            let (instance_level, instance_value) = instances.pop().unwrap();
+            instances.push((instance_level, instance_value + value))
+        }
+    }
+
+    /// Drops the counter instances created by elements deeper than the given level. These are
+    /// out of scope for any element at this level that follows them.
+    fn exit_scopes(&mut self, level: uint) {
+        for (_, instances) in self.counters.mut_iter() {
+            while instances.last().map_or(false, |&(instance_level, _)| instance_level > level) {
+                drop(instances.pop())
+            }
+        }
+    }
+}
+
+/// Returns the address of a style, which identifies it for the purpose of avoiding applying
+/// counter operations twice.
+fn style_address(style: &Arc<ComputedValues>) -> uint {
+    &**style as *ComputedValues as uint
+}
+
+/// Returns the pair of quotes to use at the given nesting depth. The last pair is reused for
+/// any deeper nesting.
+fn quote_at_depth<'a>(style: &'a ComputedValues, depth: uint) -> Option<&'a (String, String)> {
+    let quotes = &style.get_list().quotes;
+    if quotes.is_empty() {
+        None
+    } else {
+        Some(quotes.get(cmp::min(depth, quotes.len() - 1)))
+    }
+}
+
+/// Formats a counter value in the given style. Values that the style cannot represent fall back
+/// to decimal.
+fn format_counter(value: int, counter_style: content::CounterStyle) -> String {
+    match counter_style {
+        content::none => String::new(),
+        content::decimal => format!("{}", value),
+        content::lower_alpha => format_alphabetic(value),
+        content::upper_alpha => format_alphabetic(value).as_slice().to_ascii_upper(),
+        content::lower_roman => format_roman(value),
+        content::upper_roman => format_roman(value).as_slice().to_ascii_upper(),
+    }
+}
+
+/// Formats a counter value as lowercase letters: a, b, ..., z, aa, ab, and so on.
+fn format_alphabetic(value: int) -> String {
+    if value < 1 {
+        return format!("{}", value)
+    }
+
+    let mut letters = vec!();
+    let mut value = value;
+    while value > 0 {
+        value -= 1;
+        letters.push(('a' as u8 + (value % 26) as u8) as char);
+        value /= 26
+    }
+    letters.iter().rev().map(|letter| *letter).collect()
+}
+
+/// Formats a counter value as lowercase Roman numerals.
+fn format_roman(value: int) -> String {
+    static NUMERALS: [(int, &'static str), ..13] = [
+        (1000, "m"), (900, "cm"), (500, "d"), (400, "cd"), (100, "c"), (90, "xc"), (50, "l"),
+        (40, "xl"), (10, "x"), (9, "ix"), (5, "v"), (4, "iv"), (1, "i")
+    ];
+
+    if value < 1 || value > 3999 {
+        return format!("{}", value)
+    }
+
+    let mut result = String::new();
+    let mut value = value;
+    for &(numeral_value, numeral) in NUMERALS.iter() {
+        while value >= numeral_value {
+            result.push_str(numeral);
+            value -= numeral_value
+        }
+    }
+    result
+}
@@ -9,17 +9,21 @@ use layout::context::LayoutContext;
 use layout::floats::{FloatLeft, Floats, PlacementInfo};
 use layout::flow::{BaseFlow, FlowClass, Flow, InlineFlowClass};
 use layout::flow;
-use layout::fragment::{Fragment, ScannedTextFragment, ScannedTextFragmentInfo, SplitInfo};
-use layout::model::IntrinsicWidths;
+use layout::fragment::{Fragment, InlineBlockFragment, ScannedTextFragment};
+use layout::fragment::{ScannedTextFragmentInfo, SplitInfo};
+use layout::model::{AdjoiningMargins, IntrinsicWidths, MarginsCollapseThrough};
+use layout::model::NoCollapsibleMargins;
 use layout::model;
 use layout::text;
 use layout::wrapper::ThreadSafeLayoutNode;
 
 use collections::{Deque, RingBuf};
 use geom::{Point2D, Rect, SideOffsets2D, Size2D};
-use gfx::display_list::ContentLevel;
+use gfx::display_list::{ContentLevel, DisplayList};
 use gfx::font::FontMetrics;
 use gfx::font_context::FontContext;
+use gfx::text::bidi::{Level, LTR_LEVEL, RTL_LEVEL};
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
 use servo_util::geometry::Au;
 use servo_util::geometry;
@@ -31,7 +35,8 @@ use std::mem;
 use std::num;
 use std::slice::{Items, MutItems};
 use std::u16;
-use style::computed_values::{text_align, vertical_align, white_space};
+use style::computed_values::{direction, text_align, vertical_align, white_space};
+use style::computed_values::{LengthOrPercentage, LP_Length};
 use style::ComputedValues;
 use sync::Arc;
 
@@ -264,10 +269,12 @@ struct LineBreaker {
     pub pending_line: Line,
     pub lines: Vec<Line>,
     pub cur_y: Au,
+    /// The `text-indent` of the first line, which is reserved at the start of that line.
+    pub first_line_indentation: Au,
 }
 
 impl LineBreaker {
-    pub fn new(float_ctx: Floats) -> LineBreaker {
+    pub fn new(float_ctx: Floats, first_line_indentation: Au) -> LineBreaker {
         LineBreaker {
             floats: float_ctx,
             new_fragments: Vec::new(),
@@ -278,7 +285,8 @@ impl LineBreaker {
                 green_zone: Size2D(Au::new(0), Au::new(0))
             },
             lines: Vec::new(),
-            cur_y: Au::new(0)
+            cur_y: Au::new(0),
+            first_line_indentation: first_line_indentation,
         }
     }
 
@@ -295,8 +303,13 @@ impl LineBreaker {
     }
 
     fn reset_line(&mut self) {
+        let indentation = if self.lines.is_empty() {
+            self.first_line_indentation
+        } else {
+            Au::new(0)
+        };
         self.pending_line.range.reset(num::zero(), num::zero());
-        self.pending_line.bounds = Rect(Point2D(Au::new(0), self.cur_y), Size2D(Au::new(0), Au::new(0)));
+        self.pending_line.bounds = Rect(Point2D(Au::new(0), self.cur_y), Size2D(indentation, Au::new(0)));
         self.pending_line.green_zone = Size2D(Au::new(0), Au::new(0))
     }
 
@@ -333,7 +346,7 @@ impl LineBreaker {
                 if !fragment_was_appended {
                     debug!("LineBreaker: Fragment wasn't appended, because line {:u} was full.",
                             self.lines.len());
-                    self.flush_current_line();
+                    self.flush_current_line(flow.paragraph_level);
                 } else {
                     debug!("LineBreaker: appended a fragment to line {:u}", self.lines.len());
                 }
@@ -342,7 +355,7 @@ impl LineBreaker {
             if self.pending_line.range.length() > num::zero() {
                 debug!("LineBreaker: Partially full line {:u} left at end of scanning.",
                         self.lines.len());
-                self.flush_current_line();
+                self.flush_current_line(flow.paragraph_level);
             }
         }
 
@@ -351,10 +364,12 @@ impl LineBreaker {
         flow.lines = mem::replace(&mut self.lines, Vec::new());
     }
 
-    fn flush_current_line(&mut self) {
+    fn flush_current_line(&mut self, paragraph_level: Level) {
         debug!("LineBreaker: Flushing line {:u}: {:?}",
                self.lines.len(), self.pending_line);
 
+        self.reset_trailing_whitespace_levels(paragraph_level);
+
         // clear line and add line mapping
         debug!("LineBreaker: Saving information for flushed line {:u}.", self.lines.len());
         self.lines.push(self.pending_line);
@@ -362,6 +377,57 @@ impl LineBreaker {
         self.reset_line();
     }
 
+    /// Resets the bidi levels of the whitespace at the end of the pending line to the paragraph
+    /// level, per UAX #9 rule L1, splitting that whitespace off the last text fragment on the line
+    /// if it was resolved to another level.
+    fn reset_trailing_whitespace_levels(&mut self, paragraph_level: Level) {
+        let line_start = self.pending_line.range.begin().fragment_index.to_uint();
+        let mut i = self.new_fragments.len();
+        while i > line_start {
+            i -= 1;
+            let (run, range) = match self.new_fragments.get(i).specific {
+                ScannedTextFragment(ref info) => (info.run.clone(), info.range),
+                _ => return,
+            };
+
+            let whitespace_start = run.trailing_whitespace_start(&range);
+            if whitespace_start == range.begin() {
+                // The whole fragment is trailing whitespace; keep looking before it.
+                self.new_fragments.get_mut(i).bidi_level = paragraph_level;
+                continue
+            }
+            if whitespace_start == range.end() ||
+                    self.new_fragments.get(i).bidi_level == paragraph_level {
+                return
+            }
+
+            debug!("LineBreaker: Splitting trailing whitespace off fragment {}",
+                   self.new_fragments.get(i).debug_id());
+            let text_range = Range::new(range.begin(), whitespace_start - range.begin());
+            let whitespace_range = Range::new(whitespace_start, range.end() - whitespace_start);
+            let whitespace_width = run.advance_for_range(&whitespace_range);
+            let mut whitespace_fragment = {
+                let fragment = self.new_fragments.get(i);
+                let size = Size2D(whitespace_width, fragment.border_box.size.height);
+                let info = ScannedTextFragmentInfo::new(run.clone(), whitespace_range);
+                fragment.transform(size, ScannedTextFragment(info))
+            };
+            whitespace_fragment.bidi_level = paragraph_level;
+            {
+                let fragment = self.new_fragments.get_mut(i);
+                fragment.border_box.size.width = fragment.border_box.size.width - whitespace_width;
+                fragment.specific = ScannedTextFragment(ScannedTextFragmentInfo::new(run,
+                                                                                     text_range));
+            }
+            self.new_fragments.insert(i + 1, whitespace_fragment);
+            self.pending_line.range.extend_by(LineIndices {
+                fragment_index: FragmentIndex(1),
+                char_index: CharIndex(0) /* unused for now */,
+            });
+            return
+        }
+    }
+
     // FIXME(eatkinson): this assumes that the tallest fragment in the line determines the line height
     // This might not be the case with some weird text fonts.
     fn new_height_for_line(&self, new_fragment: &Fragment) -> Au {
@@ -924,6 +990,16 @@ pub struct InlineFlow {
     /// The minimum depth below the baseline for each line, as specified by the line height and
     /// font style.
     pub minimum_depth_below_baseline: Au,
+
+    /// The base embedding level of this paragraph, as determined by the `direction` property of
+    /// the block.
+    pub paragraph_level: Level,
+
+    /// The `text-indent` of the block, if this flow contains the first line of the block.
+    pub text_indent: LengthOrPercentage,
+
+    /// The distance from the top of this flow to the baseline of its first line, if it has any.
+    pub first_line_baseline: Option<Au>,
 }
 
 impl InlineFlow {
@@ -934,6 +1010,9 @@ impl InlineFlow {
             lines: Vec::new(),
             minimum_height_above_baseline: Au(0),
             minimum_depth_below_baseline: Au(0),
+            paragraph_level: LTR_LEVEL,
+            text_indent: LP_Length(Au(0)),
+            first_line_baseline: None,
         }
     }
 
@@ -948,6 +1027,19 @@ impl InlineFlow {
         debug!("Flow: building display list for {:u} inline fragments", self.fragments.len());
 
         for (fragment, context) in self.fragments.mut_iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref mut info) => {
+                    // The flow of an inline block has already built its display list as a child
+                    // of this flow.
+                    let flow = info.flow_ref.get_mut();
+                    let kid_display_list = mem::replace(&mut flow::mut_base(flow).display_list,
+                                                        DisplayList::new());
+                    self.base.display_list.push_all_move(kid_display_list);
+                    continue
+                }
+                _ => {}
+            }
+
             let rel_offset = fragment.relative_position(&self.base
                                                              .absolute_position_info
                                                              .relative_containing_block_size,
@@ -958,11 +1050,6 @@ impl InlineFlow {
                                              ContentLevel,
                                              Some(context)));
         }
-
-        // TODO(#225): Should `inline-block` elements have flows as children of the inline flow or
-        // should the flow be nested inside the fragment somehow?
-
-        // For now, don't traverse the subtree rooted here.
     }
 
     /// Returns the distance from the baseline for the logical top left corner of this fragment,
@@ -1041,15 +1128,17 @@ impl InlineFlow {
         }
     }
 
-    /// Sets fragment X positions based on alignment for one line.
+    /// Sets fragment X positions based on alignment for one line. `indentation` is the space
+    /// reserved before the fragments, which is already included in the width of the line.
     fn set_horizontal_fragment_positions(fragments: &mut InlineFragments,
                                          line: &Line,
-                                         line_align: text_align::T) {
+                                         line_align: text_align::T,
+                                         indentation: Au) {
         // Figure out how much width we have.
         let slack_width = Au::max(Au(0), line.green_zone.width - line.bounds.size.width);
 
         // Set the fragment x positions based on that alignment.
-        let mut offset_x = line.bounds.origin.x;
+        let mut offset_x = line.bounds.origin.x + indentation;
         offset_x = offset_x + match line_align {
             // So sorry, but justified text is more complicated than shuffling line
             // coordinates.
@@ -1060,8 +1149,13 @@ impl InlineFlow {
             text_align::right => slack_width,
         };
 
-        for i in each_fragment_index(&line.range) {
-            let fragment = fragments.get_mut(i.to_uint());
+        // Position the fragments in visual order, as determined by their bidi levels.
+        let levels: Vec<Level> = each_fragment_index(&line.range).map(|i| {
+            fragments.get(i.to_uint()).bidi_level
+        }).collect();
+        for visual_index in bidi::reorder_visually(levels.as_slice()).iter() {
+            let i = line.range.begin().fragment_index.to_uint() + *visual_index;
+            let fragment = fragments.get_mut(i);
             let size = fragment.border_box.size;
             fragment.border_box = Rect(Point2D(offset_x, fragment.border_box.origin.y), size);
             offset_x = offset_x + size.width;
@@ -1082,6 +1176,27 @@ impl InlineFlow {
         self.minimum_height_above_baseline = inline_metrics.height_above_baseline;
         self.minimum_depth_below_baseline = inline_metrics.depth_below_baseline;
     }
+
+    /// Computes the base embedding level used by the bidi algorithm for this paragraph. This is
+    /// done during flow construction.
+    ///
+    /// `style` is the style of the block.
+    pub fn compute_paragraph_level(&mut self, style: &ComputedValues) {
+        self.paragraph_level = match style.get_inheritedbox().direction {
+            direction::ltr => LTR_LEVEL,
+            direction::rtl => RTL_LEVEL,
+        }
+    }
+
+    /// Records the `text-indent` of the block if this flow holds the block's first line, which is
+    /// the case when no flows precede it in the block. This is done during flow construction.
+    ///
+    /// `style` is the style of the block.
+    pub fn compute_text_indent(&mut self, style: &ComputedValues, contains_first_line: bool) {
+        if contains_first_line {
+            self.text_indent = style.get_inheritedtext().text_indent
+        }
+    }
 }
 
 impl Flow for InlineFlow {
@@ -1097,6 +1212,10 @@ impl Flow for InlineFlow {
         self
     }
 
+    fn first_baseline(&self) -> Option<Au> {
+        self.first_line_baseline
+    }
+
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
         for kid in self.base.child_iter() {
             flow::mut_base(kid).floats = Floats::new();
@@ -1133,16 +1252,8 @@ impl Flow for InlineFlow {
             }
         }
 
-        assert!(self.base.children.len() == 0,
-                "InlineFlow: should not have children flows in the current layout implementation.");
-
-        // There are no child contexts, so stop here.
-
-        // TODO(Issue #225): once there are 'inline-block' elements, this won't be
-        // true.  In that case, set the InlineBlockFragment's width to the
-        // shrink-to-fit width, perform inline flow, and set the block
-        // flow context's width as the assigned width of the
-        // 'inline-block' fragment that created this flow before recursing.
+        // The only children of an inline flow are the flows of its inline block fragments, whose
+        // widths were assigned along with those of the fragments above.
     }
 
     /// Calculate and set the height of this flow. See CSS 2.1 § 10.6.1.
@@ -1165,18 +1276,33 @@ impl Flow for InlineFlow {
             fragment.assign_replaced_height_if_necessary();
         }
 
+        // Percentages of `text-indent` refer to the width of the containing block, which is the
+        // width of this flow.
+        let first_line_indentation = model::specified(self.text_indent,
+                                                      self.base.position.size.width);
+
         let scanner_floats = self.base.floats.clone();
-        let mut scanner = LineBreaker::new(scanner_floats);
+        let mut scanner = LineBreaker::new(scanner_floats, first_line_indentation);
         scanner.scan_for_lines(self);
 
         // All lines use text alignment of the flow.
         let text_align = self.base.flags.text_align();
 
         // Now, go through each line and lay out the fragments inside.
+        self.first_line_baseline = None;
         let mut line_distance_from_flow_top = Au(0);
-        for line in self.lines.mut_iter() {
-            // Lay out fragments horizontally.
-            InlineFlow::set_horizontal_fragment_positions(&mut self.fragments, line, text_align);
+        for (line_i, line) in self.lines.mut_iter().enumerate() {
+            // Lay out fragments horizontally. In right-to-left paragraphs the indentation of the
+            // first line is at its right end, after the fragments.
+            let indentation = if line_i == 0 && !bidi::is_rtl(self.paragraph_level) {
+                first_line_indentation
+            } else {
+                Au(0)
+            };
+            InlineFlow::set_horizontal_fragment_positions(&mut self.fragments,
+                                                          line,
+                                                          text_align,
+                                                          indentation);
 
             // Set the top y position of the current line.
             // `line_height_offset` is updated at the end of the previous loop.
@@ -1264,6 +1390,10 @@ impl Flow for InlineFlow {
             // Now, the distance from the logical top of the line to the baseline can be
             // computed as `largest_height_above_baseline`.
             let baseline_distance_from_top = largest_height_above_baseline;
+            if line_i == 0 {
+                self.first_line_baseline = Some(line_distance_from_flow_top +
+                                                baseline_distance_from_top)
+            }
 
             // Compute the final positions in the block direction of each fragment. Recall that
             // `fragment.border_box.origin.y` was set to the distance from the baseline above.
@@ -1299,8 +1429,40 @@ impl Flow for InlineFlow {
                 Au::new(0)
             };
 
+        // Margins collapse through an inline flow without line boxes. See CSS 2.1 § 8.3.1.
+        self.base.collapsible_margins = if self.lines.len() > 0 {
+            NoCollapsibleMargins(Au(0), Au(0))
+        } else {
+            MarginsCollapseThrough(AdjoiningMargins::new())
+        };
+
         self.base.floats = scanner.floats();
         self.base.floats.translate(Point2D(Au::new(0), -self.base.position.size.height));
+
+        // Move the flows of inline blocks to where their fragments ended up in the lines.
+        for fragment in self.fragments.fragments.iter() {
+            match fragment.specific {
+                InlineBlockFragment(ref info) => {
+                    let mut flow_ref = info.flow_ref.clone();
+                    let block = flow_ref.get_mut().as_block();
+                    block.base.position.origin =
+                        Point2D(fragment.border_box.origin.x,
+                                fragment.border_box.origin.y + block.fragment.margin.top);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn compute_absolute_position(&mut self) {
+        // Inline blocks are positioned relative to this flow, like the children of a block.
+        let this_position = self.base.abs_position;
+        let absolute_position_info = self.base.absolute_position_info;
+        for kid in self.base.child_iter() {
+            let kid_base = flow::mut_base(kid);
+            kid_base.abs_position = this_position + kid_base.position.origin;
+            kid_base.absolute_position_info = absolute_position_info
+        }
     }
 }
 
@@ -15,6 +15,7 @@ use layout::flow::{Flow, ImmutableFlowUtils, MutableFlowUtils, MutableOwnedFlowU
 use layout::flow::{PreorderFlowTraversal, PostorderFlowTraversal};
 use layout::flow;
 use layout::flow_ref::FlowRef;
+use layout::generated_content::ResolveGeneratedContent;
 use layout::incremental::RestyleDamage;
 use layout::parallel::UnsafeFlow;
 use layout::parallel;
@@ -22,12 +23,15 @@ use layout::util::{LayoutDataAccess, LayoutDataWrapper, OpaqueNodeMethods, ToGfx
 use layout::wrapper::{LayoutNode, TLayoutNode, ThreadSafeLayoutNode};
 
 use collections::dlist::DList;
+use collections::hashmap::HashSet;
 use geom::point::Point2D;
 use geom::rect::Rect;
 use geom::size::Size2D;
 use gfx::display_list::{ClipDisplayItemClass, ContentStackingLevel, DisplayItem};
 use gfx::display_list::{DisplayItemIterator, DisplayList, OpaqueNode};
-use gfx::font_context::{FontContext, FontContextInfo};
+use gfx::font::WebFontData;
+use gfx::font_context::{DownloadedSource, FontContext, FontContextInfo, LocalFamilySource};
+use gfx::font_context::{RemoteSource, ShapedRunCacheCounts, WebFontFace};
 use gfx::render_task::{RenderMsg, RenderChan, RenderLayer};
 use gfx::{render_task, color};
 use script::dom::bindings::js::JS;
@@ -39,18 +43,20 @@ use script::layout_interface::{ContentBoxesQuery, ContentBoxesResponse, ExitNowM
 use script::layout_interface::{HitTestQuery, ContentBoxResponse, HitTestResponse, MouseOverQuery, MouseOverResponse};
 use script::layout_interface::{ContentChangedDocumentDamage, LayoutChan, Msg, PrepareToExitMsg};
 use script::layout_interface::{QueryMsg, ReapLayoutDataMsg, Reflow, UntrustedNodeAddress};
-use script::layout_interface::{ReflowForDisplay, ReflowMsg};
+use script::layout_interface::{ReflowForDisplay, ReflowMsg, WebFontLoadedMsg};
 use script::script_task::{ReflowCompleteMsg, ScriptChan, SendEventMsg};
 use servo_msg::compositor_msg::Scrollable;
 use servo_msg::constellation_msg::{ConstellationChan, PipelineId, Failure, FailureMsg};
 use servo_net::image::holder::LocalImageCacheHandle;
 use servo_net::image_cache_task::{ImageCacheTask, ImageResponseMsg};
 use servo_net::local_image_cache::{ImageResponder, LocalImageCache};
+use servo_net::resource_task::{ResourceTask, load_whole_resource};
 use servo_util::geometry::Au;
 use servo_util::geometry;
 use servo_util::opts::Opts;
 use servo_util::smallvec::{SmallVec, SmallVec1};
 use servo_util::time::{ProfilerChan, profile};
+use servo_util::url::try_parse_url;
 use servo_util::time;
 use servo_util::task::send_on_failure;
 use servo_util::workqueue::WorkQueue;
@@ -58,7 +64,8 @@ use std::comm::{channel, Sender, Receiver};
 use std::mem;
 use std::ptr;
 use std::task::TaskBuilder;
-use style::{AuthorOrigin, Stylesheet, Stylist};
+use style::{AuthorOrigin, Device, LocalSource, Screen, Stylesheet, Stylist, UrlSource};
+use style::iter_font_face_rules;
 use sync::{Arc, Mutex};
 use url::Url;
 
@@ -88,6 +95,25 @@ pub struct LayoutTask {
     /// The local image cache.
     pub local_image_cache: LocalImageCacheHandle,
 
+    /// The channel on which messages can be sent to the resource task.
+    pub resource_task: ResourceTask,
+
+    /// The faces defined by the `@font-face` rules of the stylesheets added so far.
+    pub web_fonts: Arc<Vec<WebFontFace>>,
+
+    /// The port on which font contexts send the URLs of the web fonts that they need.
+    pub web_font_request_port: Receiver<String>,
+
+    /// The channel on which font contexts send the URLs of the web fonts that they need.
+    pub web_font_request_chan: Sender<String>,
+
+    /// The URLs of the web fonts that have been requested, so that each is only downloaded once.
+    pub requested_web_fonts: HashSet<String>,
+
+    /// The shaped run cache hits and misses of the font contexts of every layout thread, which
+    /// are reported to the profiler after each reflow.
+    pub shaped_run_cache_counts: Arc<ShapedRunCacheCounts>,
+
     /// The size of the viewport.
     pub screen_size: Size2D<Au>,
 
@@ -279,6 +305,7 @@ impl LayoutTask {
                   script_chan: ScriptChan,
                   render_chan: RenderChan,
                   img_cache_task: ImageCacheTask,
+                  resource_task: ResourceTask,
                   opts: Opts,
                   profiler_chan: ProfilerChan,
                   shutdown_chan: Sender<()>) {
@@ -294,6 +321,7 @@ impl LayoutTask {
                                                  script_chan,
                                                  render_chan,
                                                  img_cache_task,
+                                                 resource_task,
                                                  &opts,
                                                  profiler_chan);
                 layout.start();
@@ -310,6 +338,7 @@ impl LayoutTask {
            script_chan: ScriptChan,
            render_chan: RenderChan,
            image_cache_task: ImageCacheTask,
+           resource_task: ResourceTask,
            opts: &Opts,
            profiler_chan: ProfilerChan)
            -> LayoutTask {
@@ -326,6 +355,8 @@ impl LayoutTask {
             None
         };
 
+        let (web_font_request_chan, web_font_request_port) = channel();
+
         LayoutTask {
             id: id,
             port: port,
@@ -335,6 +366,12 @@ impl LayoutTask {
             render_chan: render_chan,
             image_cache_task: image_cache_task.clone(),
             local_image_cache: local_image_cache,
+            resource_task: resource_task,
+            web_fonts: Arc::new(vec!()),
+            web_font_request_port: web_font_request_port,
+            web_font_request_chan: web_font_request_chan,
+            requested_web_fonts: HashSet::new(),
+            shaped_run_cache_counts: Arc::new(ShapedRunCacheCounts::new()),
             screen_size: screen_size,
 
             display_list: None,
@@ -358,7 +395,11 @@ impl LayoutTask {
         let font_context_info = FontContextInfo {
             backend: self.opts.render_backend,
             needs_font_list: true,
+            web_fonts: self.web_fonts.clone(),
+            web_font_requests: Some(self.web_font_request_chan.clone()),
             profiler_chan: self.profiler_chan.clone(),
+            font_prefs: self.opts.font_prefs.clone(),
+            shaped_run_cache_counts: self.shaped_run_cache_counts.clone(),
         };
 
         LayoutContext {
@@ -383,6 +424,8 @@ impl LayoutTask {
                 profile(time::LayoutPerformCategory, self.profiler_chan.clone(), || {
                     self.handle_reflow(data);
                 });
+                self.shaped_run_cache_counts.report(&self.profiler_chan);
+                self.load_requested_web_fonts();
             }
             QueryMsg(query) => {
                 let mut query = Some(query);
@@ -395,6 +438,7 @@ impl LayoutTask {
                     self.handle_reap_layout_data(dead_layout_data)
                 }
             }
+            WebFontLoadedMsg(url, bytes) => self.handle_web_font_loaded(url, bytes),
             PrepareToExitMsg(response_chan) => {
                 debug!("layout: PrepareToExitMsg received");
                 self.prepare_to_exit(response_chan);
@@ -411,8 +455,8 @@ impl LayoutTask {
     }
 
     /// Enters a quiescent state in which no new messages except for `ReapLayoutDataMsg` will be
-    /// processed until an `ExitNowMsg` is received. A pong is immediately sent on the given
-    /// response channel.
+    /// processed until an `ExitNowMsg` is received. Web fonts that finish downloading are
+    /// ignored. A pong is immediately sent on the given response channel.
     fn prepare_to_exit(&mut self, response_chan: Sender<()>) {
         response_chan.send(());
         loop {
@@ -422,6 +466,7 @@ impl LayoutTask {
                         self.handle_reap_layout_data(dead_layout_data)
                     }
                 }
+                WebFontLoadedMsg(..) => {}
                 ExitNowMsg => {
                     debug!("layout task is exiting...");
                     self.exit_now();
@@ -450,9 +495,118 @@ impl LayoutTask {
     }
 
     fn handle_add_stylesheet(&mut self, sheet: Stylesheet) {
+        self.add_web_fonts(&sheet);
         self.stylist.add_stylesheet(sheet, AuthorOrigin)
     }
 
+    /// Adds the faces defined by the `@font-face` rules of the given stylesheet, so that
+    /// `font-family` can select them. Nothing is downloaded yet: a font context requests the
+    /// `url()` sources of the faces that it actually uses, and whether a `local()` font is
+    /// installed is only known to the font context.
+    fn add_web_fonts(&mut self, sheet: &Stylesheet) {
+        let mut new_faces = vec!();
+        let device = &Device { media_type: Screen };  // TODO, use Print when printing
+        iter_font_face_rules(sheet.rules.as_slice(), device, |rule| {
+            let mut sources = vec!();
+            for source in rule.sources.iter() {
+                match *source {
+                    LocalSource(ref family) => sources.push(LocalFamilySource(family.clone())),
+                    UrlSource(ref url_source) => {
+                        // FreeType can only load TrueType and OpenType data.
+                        if url_source.format_hints.len() > 0 &&
+                                !url_source.format_hints.iter().any(|hint| {
+                                    hint.as_slice() == "truetype" || hint.as_slice() == "opentype"
+                                }) {
+                            continue
+                        }
+                        sources.push(RemoteSource(url_source.url.to_str()))
+                    }
+                }
+            }
+
+            if sources.len() > 0 {
+                new_faces.push(WebFontFace {
+                    family: rule.family.clone(),
+                    weight: rule.weight,
+                    style: rule.style,
+                    unicode_range: rule.unicode_range.clone(),
+                    sources: sources,
+                })
+            }
+        });
+
+        if new_faces.len() > 0 {
+            let mut web_fonts = (*self.web_fonts).clone();
+            web_fonts.push_all_move(new_faces);
+            self.web_fonts = Arc::new(web_fonts);
+        }
+    }
+
+    /// Starts downloading the web fonts that font contexts requested during the last layout. Each
+    /// download sends a `WebFontLoadedMsg` back to this task when it finishes.
+    fn load_requested_web_fonts(&mut self) {
+        loop {
+            let url = match self.web_font_request_port.try_recv() {
+                Ok(url) => url,
+                Err(_) => break,
+            };
+            if !self.requested_web_fonts.insert(url.clone()) {
+                continue
+            }
+
+            let resource_task = self.resource_task.clone();
+            let LayoutChan(ref chan) = self.chan;
+            let chan = chan.clone();
+            spawn(proc() {
+                let result = try_parse_url(url.as_slice(), None).and_then(|parsed_url| {
+                    load_whole_resource(&resource_task, parsed_url)
+                });
+                let bytes = match result {
+                    Ok((_, bytes)) => Some(bytes),
+                    Err(error) => {
+                        debug!("layout: failed to load web font {}: {}", url, error);
+                        None
+                    }
+                };
+                drop(chan.send_opt(WebFontLoadedMsg(url, bytes)))
+            })
+        }
+    }
+
+    /// Replaces the sources that were waiting for the given URL with the downloaded font, or
+    /// drops them if it could not be loaded so that the next source of each face is tried, and
+    /// asks script for a reflow so that the text is laid out again.
+    fn handle_web_font_loaded(&mut self, url: String, bytes: Option<Vec<u8>>) {
+        let bytes = bytes.map(|bytes| Arc::new(bytes));
+        let web_fonts = self.web_fonts.iter().map(|face| {
+            let sources = face.sources.iter().filter_map(|source| {
+                match *source {
+                    RemoteSource(ref source_url) if *source_url == url => {
+                        bytes.as_ref().map(|bytes| {
+                            DownloadedSource(WebFontData {
+                                url: url.clone(),
+                                unicode_range: face.unicode_range.clone(),
+                                bytes: bytes.clone(),
+                            })
+                        })
+                    }
+                    _ => Some(source.clone()),
+                }
+            }).collect();
+            WebFontFace {
+                family: face.family.clone(),
+                weight: face.weight,
+                style: face.style,
+                unicode_range: face.unicode_range.clone(),
+                sources: sources,
+            }
+        }).collect();
+        self.web_fonts = Arc::new(web_fonts);
+
+        let ScriptChan(ref chan) = self.script_chan;
+        drop(chan.send_opt(SendEventMsg(self.id.clone(), ReflowEvent)))
+    }
+
     /// Retrieves the flow tree root from the root node.
     fn get_layout_root(&self, node: LayoutNode) -> FlowRef {
         let mut layout_data_ref = node.mutate_layout_data();
@@ -639,6 +793,20 @@ impl LayoutTask {
             layout_root.get_mut().traverse_postorder(&mut ComputeDamageTraversal.clone());
         });
 
+        // Resolve counters and quotes in generated content. This needs to see the whole document
+        // in order, so it runs sequentially even when the rest of layout is parallel.
+        let resolved_generated_content = {
+            let mut traversal = ResolveGeneratedContent::new(&mut layout_ctx);
+            traversal.traverse(layout_root.get_mut(), 0);
+            traversal.resolved_any()
+        };
+        if resolved_generated_content && !layout_ctx.opts.bubble_widths_separately {
+            let mut traversal = BubbleWidthsTraversal {
+                layout_context: &mut layout_ctx,
+            };
+            layout_root.get_mut().traverse_postorder(&mut traversal);
+        }
+
         // Perform the primary layout passes over the flow tree to compute the locations of all
         // the boxes.
         profile(time::LayoutMainCategory, self.profiler_chan.clone(), || {
@@ -144,9 +144,10 @@ impl MarginCollapseInfo {
         if !can_collapse_bottom_margin_with_kids {
             match state {
                 MarginsCollapseThroughFinalMarginState => {
-                    let advance = self.top_margin.collapse();
+                    // The margins of the children collapsed with our top margin, outside of our
+                    // border box, so they take up no room inside it.
                     self.margin_in.union(AdjoiningMargins::from_margin(bottom_margin));
-                    (MarginsCollapse(self.top_margin, self.margin_in), advance)
+                    (MarginsCollapse(self.top_margin, self.margin_in), Au(0))
                 }
                 BottomMarginCollapsesFinalMarginState => {
                     let advance = self.margin_in.collapse();
@@ -168,6 +169,12 @@ impl MarginCollapseInfo {
         }
     }
 
+    /// Stops collapsing the top margins of children with the top margin of this flow, as when a
+    /// child has clearance. See CSS 2.1 § 8.3.1.
+    pub fn stop_collapsing_top_margin(&mut self) {
+        self.state = AccumulatingMarginIn
+    }
+
     pub fn current_float_ceiling(&mut self) -> Au {
         match self.state {
             AccumulatingCollapsibleTopMargin => self.top_margin.collapse(),
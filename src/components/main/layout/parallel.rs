@@ -254,7 +254,8 @@ fn recalc_style_for_node(unsafe_layout_node: UnsafeLayoutNode,
                 // Perform the CSS cascade.
                 node.cascade_node(parent_opt,
                                   &applicable_declarations,
-                                  layout_context.applicable_declarations_cache());
+                                  layout_context.applicable_declarations_cache(),
+                                  &layout_context.opts.font_prefs);
 
                 // Add ourselves to the LRU cache.
                 if shareable {
@@ -6,20 +6,28 @@
 
 #![deny(unsafe_block)]
 
-use layout::block::{BlockFlow, MarginsMayNotCollapse, WidthAndMarginsComputer};
+use layout::block::{BlockFlow, CandidateHeightIterator, WidthAndMarginsComputer};
 use layout::block::{WidthConstraintInput, WidthConstraintSolution};
+use layout::block::propagate_layer_flag_from_child;
 use layout::construct::FlowConstructor;
 use layout::context::LayoutContext;
 use layout::floats::FloatKind;
 use layout::flow::{TableFlowClass, FlowClass, Flow, ImmutableFlowUtils};
+use layout::flow;
 use layout::fragment::Fragment;
+use layout::model::{Auto, MarginCollapseInfo, Specified};
+use layout::table_row::TableRowFlow;
 use layout::table_wrapper::{TableLayout, FixedLayout, AutoLayout};
 use layout::wrapper::ThreadSafeLayoutNode;
 
+use geom::SideOffsets2D;
 use servo_util::geometry::Au;
 use servo_util::geometry;
+use std::cmp;
 use std::fmt;
-use style::computed_values::table_layout;
+use std::uint;
+use style::ComputedValues;
+use style::computed_values::{RGBA, border_collapse, border_spacing, border_style, table_layout};
 
 /// A table flow corresponded to the table's internal table fragment under a table wrapper flow.
 /// The properties `position`, `float`, and `margin-*` are used on the table wrapper fragment,
@@ -36,6 +44,9 @@ pub struct TableFlow {
     /// Column pref widths.
     pub col_pref_widths: Vec<Au>,
 
+    /// The intrinsic widths of the cells that span several columns.
+    pub spanning_cells: Vec<SpanningCellWidths>,
+
     /// Table-layout property
     pub table_layout: TableLayout,
 }
@@ -44,57 +55,43 @@ impl TableFlow {
     pub fn from_node_and_fragment(node: &ThreadSafeLayoutNode,
                                   fragment: Fragment)
                                   -> TableFlow {
-        let mut block_flow = BlockFlow::from_node_and_fragment(node, fragment);
-        let table_layout = if block_flow.fragment().style().get_table().table_layout ==
-                              table_layout::fixed {
-            FixedLayout
-        } else {
-            AutoLayout
-        };
-        TableFlow {
-            block_flow: block_flow,
-            col_widths: vec!(),
-            col_min_widths: vec!(),
-            col_pref_widths: vec!(),
-            table_layout: table_layout
-        }
+        TableFlow::from_block_flow(BlockFlow::from_node_and_fragment(node, fragment))
     }
 
     pub fn from_node(constructor: &mut FlowConstructor,
                      node: &ThreadSafeLayoutNode)
                      -> TableFlow {
-        let mut block_flow = BlockFlow::from_node(constructor, node);
-        let table_layout = if block_flow.fragment().style().get_table().table_layout ==
-                              table_layout::fixed {
-            FixedLayout
-        } else {
-            AutoLayout
-        };
-        TableFlow {
-            block_flow: block_flow,
-            col_widths: vec!(),
-            col_min_widths: vec!(),
-            col_pref_widths: vec!(),
-            table_layout: table_layout
-        }
+        TableFlow::from_block_flow(BlockFlow::from_node(constructor, node))
     }
 
     pub fn float_from_node(constructor: &mut FlowConstructor,
                            node: &ThreadSafeLayoutNode,
                            float_kind: FloatKind)
                            -> TableFlow {
-        let mut block_flow = BlockFlow::float_from_node(constructor, node, float_kind);
+        TableFlow::from_block_flow(BlockFlow::float_from_node(constructor, node, float_kind))
+    }
+
+    fn from_block_flow(mut block_flow: BlockFlow) -> TableFlow {
         let table_layout = if block_flow.fragment().style().get_table().table_layout ==
                               table_layout::fixed {
             FixedLayout
         } else {
             AutoLayout
         };
+
+        // With collapsing borders, the borders of the table are drawn by the cells along its
+        // edges, and the table has no padding.
+        let (_, collapse) = border_spacing_and_collapse(block_flow.fragment.style());
+        if collapse {
+            block_flow.fragment.collapsed_borders = Some(CollapsedBorders::none())
+        }
+
         TableFlow {
             block_flow: block_flow,
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            spanning_cells: vec!(),
             table_layout: table_layout
         }
     }
@@ -118,15 +115,221 @@ impl TableFlow {
         sum_widths
     }
 
-    /// Assign height for table flow.
-    ///
-    /// TODO(#2014, pcwalton): This probably doesn't handle margin collapse right.
+    /// Like `update_col_widths`, but also adopts the widths of any columns that the kid has and
+    /// `self_widths` lacks.
+    pub fn merge_col_widths(self_widths: &mut Vec<Au>, kid_widths: &Vec<Au>) {
+        TableFlow::update_col_widths(self_widths, kid_widths);
+        for i in range(self_widths.len(), kid_widths.len()) {
+            self_widths.push(*kid_widths.get(i));
+        }
+    }
+
+    /// Widens the columns spanned by each cell that spans several columns so that together they
+    /// are at least as wide as the cell, per CSS 2.1 § 17.5.2.2. The excess is distributed evenly
+    /// over the spanned columns.
+    pub fn distribute_spanning_cell_widths(col_min_widths: &mut Vec<Au>,
+                                           col_pref_widths: &mut Vec<Au>,
+                                           spanning_cells: &[SpanningCellWidths],
+                                           horizontal_spacing: Au) {
+        for cell in spanning_cells.iter() {
+            let end = cell.start_column + cell.colspan;
+            while col_min_widths.len() < end {
+                col_min_widths.push(Au(0));
+                col_pref_widths.push(Au(0));
+            }
+
+            let spacing = horizontal_spacing * Au::new((cell.colspan - 1) as i32);
+            let columns = Au::new(cell.colspan as i32);
+            let spanned_min = col_min_widths.slice(cell.start_column, end).iter()
+                                            .fold(spacing, |sum, width| sum + *width);
+            if cell.minimum_width > spanned_min {
+                let extra = (cell.minimum_width - spanned_min) / columns;
+                for width in col_min_widths.mut_slice(cell.start_column, end).mut_iter() {
+                    *width = *width + extra
+                }
+            }
+
+            let spanned_pref = col_pref_widths.slice(cell.start_column, end).iter()
+                                              .fold(spacing, |sum, width| sum + *width);
+            if cell.preferred_width > spanned_pref {
+                let extra = (cell.preferred_width - spanned_pref) / columns;
+                for width in col_pref_widths.mut_slice(cell.start_column, end).mut_iter() {
+                    *width = *width + extra
+                }
+            }
+
+            for i in range(cell.start_column, end) {
+                if *col_pref_widths.get(i) < *col_min_widths.get(i) {
+                    *col_pref_widths.get_mut(i) = *col_min_widths.get(i)
+                }
+            }
+        }
+    }
+
+    /// Resolves the collapsed borders of all the cells in this table per CSS 2.1 § 17.6.2.1,
+    /// against the borders of adjacent cells and of the table. Each border between two cells is
+    /// drawn by only one of them: the one below or to the right. The borders of rows, row groups,
+    /// and columns do not take part in the resolution.
+    fn collapse_cell_borders(&mut self) {
+        // Gather the grid positions and specified borders of the cells.
+        let mut cells = vec!();
+        let mut row_count = 0;
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_rowgroup() {
+                let group_end = row_count + kid.child_count();
+                for row in flow::mut_base(kid).child_iter() {
+                    gather_cell_borders(row.as_table_row(), row_count, group_end, &mut cells);
+                    row_count += 1;
+                }
+            } else if kid.is_table_row() {
+                gather_cell_borders(kid.as_table_row(), row_count, uint::MAX, &mut cells);
+                row_count += 1;
+            }
+        }
+
+        let grid = CellGrid::new(cells.as_mut_slice(), row_count);
+        let (row_count, column_count) = (grid.row_count, grid.column_count);
+
+        let table_borders = CollapsedBorders::from_style(self.block_flow.fragment.style());
+        let mut resolved_borders = Vec::with_capacity(cells.len());
+        for cell in cells.iter() {
+            let mut borders = cell.borders.clone();
+            let (row_end, column_end) = (cell.row + cell.rowspan, cell.column + cell.colspan);
+
+            if cell.row == 0 {
+                borders.top = borders.top.resolve(&table_borders.top)
+            }
+            if cell.column == 0 {
+                borders.left = borders.left.resolve(&table_borders.left)
+            }
+            for column in range(cell.column, column_end) {
+                if cell.row > 0 {
+                    match grid.get(cell.row - 1, column) {
+                        Some(above) => {
+                            borders.top = borders.top.resolve(&cells.get(above).borders.bottom)
+                        }
+                        None => {}
+                    }
+                }
+            }
+            for row in range(cell.row, row_end) {
+                if cell.column > 0 {
+                    match grid.get(row, cell.column - 1) {
+                        Some(before) => {
+                            borders.left = borders.left.resolve(&cells.get(before).borders.right)
+                        }
+                        None => {}
+                    }
+                }
+            }
+
+            // The cell to the right or below draws the shared border, if there is one.
+            if column_end == column_count {
+                borders.right = borders.right.resolve(&table_borders.right)
+            } else if range(cell.row, row_end).any(|row| grid.get(row, column_end).is_some()) {
+                borders.right = CollapsedBorder::none()
+            }
+            if row_end == row_count {
+                borders.bottom = borders.bottom.resolve(&table_borders.bottom)
+            } else if range(cell.column, column_end).any(|column| {
+                    grid.get(row_end, column).is_some()
+                }) {
+                borders.bottom = CollapsedBorder::none()
+            }
+
+            resolved_borders.push(borders);
+        }
+
+        // Hand the resolved borders to the cells, which visits them in the same order as above.
+        let mut resolved_borders = resolved_borders.move_iter();
+        for kid in self.block_flow.base.child_iter() {
+            if kid.is_table_rowgroup() {
+                for row in flow::mut_base(kid).child_iter() {
+                    for cell in flow::mut_base(row).child_iter() {
+                        cell.as_block().fragment.collapsed_borders = resolved_borders.next()
+                    }
+                }
+            } else if kid.is_table_row() {
+                for cell in flow::mut_base(kid).child_iter() {
+                    cell.as_block().fragment.collapsed_borders = resolved_borders.next()
+                }
+            }
+        }
+    }
+
+    /// Assign height for table flow. Rows and row groups are stacked with `border-spacing`
+    /// between them and around them.
     ///
     /// inline(always) because this is only ever called by in-order or non-in-order top-level
     /// methods
     #[inline(always)]
     fn assign_height_table_base(&mut self, layout_context: &mut LayoutContext) {
-        self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse);
+        let (spacing, _) = border_spacing_and_collapse(self.block_flow.fragment.style());
+        let top_offset = self.block_flow.fragment.border_padding.top;
+
+        let mut layers_needed_for_descendants = false;
+        for kid in self.block_flow.base.child_iter() {
+            kid.assign_height_for_inorder_child_if_necessary(layout_context);
+            propagate_layer_flag_from_child(&mut layers_needed_for_descendants, kid);
+        }
+        self.block_flow.base.flags.set_layers_needed_for_descendants(layers_needed_for_descendants);
+
+        // Lay out the runs of rows that are not in a row group, which may have cells spanning
+        // several rows.
+        {
+            let mut run = vec!();
+            for kid in self.block_flow.base.child_iter() {
+                if kid.is_table_row() {
+                    run.push(kid);
+                } else if !run.is_empty() {
+                    lay_out_rows(run.as_mut_slice(), spacing.vertical);
+                    run = vec!();
+                }
+            }
+            lay_out_rows(run.as_mut_slice(), spacing.vertical);
+        }
+
+        let mut cur_y = top_offset;
+        let mut has_rows = false;
+        for kid in self.block_flow.base.child_iter() {
+            let is_table_colgroup = kid.is_table_colgroup();
+            let kid_base = flow::mut_base(kid);
+            if is_table_colgroup {
+                kid_base.position.origin.y = cur_y;
+                continue
+            }
+            cur_y = cur_y + spacing.vertical;
+            kid_base.position.origin.y = cur_y;
+            cur_y = cur_y + kid_base.position.size.height;
+            has_rows = true;
+        }
+        if has_rows {
+            cur_y = cur_y + spacing.vertical;
+        }
+
+        let mut candidate_height_iterator =
+            CandidateHeightIterator::new(self.block_flow.fragment.style(), None);
+        for (candidate_height, new_candidate_height) in candidate_height_iterator {
+            *new_candidate_height = match candidate_height {
+                Auto => cur_y - top_offset,
+                Specified(value) => value,
+            }
+        }
+        let height = candidate_height_iterator.candidate_value +
+            self.block_flow.fragment.border_padding.vertical();
+
+        self.block_flow.fragment.border_box.origin.y = Au(0);
+        self.block_flow.fragment.border_box.size.height = height;
+        self.block_flow.base.position.size.height = height;
+
+        let mut margin_collapse_info = MarginCollapseInfo::new();
+        margin_collapse_info.initialize_top_margin(&self.block_flow.fragment, false);
+        let (collapsible_margins, _) =
+            margin_collapse_info.finish_and_compute_collapsible_margins(&self.block_flow.fragment,
+                                                                        false);
+        self.block_flow.base.collapsible_margins = collapsible_margins;
+
+        self.block_flow.assign_height_for_absolute_flow_tree_if_necessary(layout_context);
     }
 
     pub fn build_display_list_table(&mut self, layout_context: &LayoutContext) {
@@ -165,9 +368,13 @@ impl Flow for TableFlow {
     /// The maximum min/pref widths of each column are set from the rows for the automatic
     /// table layout calculation.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
-        let mut min_width = Au(0);
-        let mut pref_width = Au(0);
+        let (spacing, _) = border_spacing_and_collapse(self.block_flow.fragment.style());
         let mut did_first_row = false;
+        self.spanning_cells = vec!();
+
+        // The rows that are not in a row group form a group of their own for the purposes of
+        // cells spanning several rows.
+        let mut rowspans = vec!();
 
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_proper_table_child());
@@ -177,6 +384,14 @@ impl Flow for TableFlow {
                 self.col_min_widths = self.col_widths.clone();
                 self.col_pref_widths = self.col_widths.clone();
             } else if kid.is_table_rowgroup() || kid.is_table_row() {
+                if kid.is_table_row() {
+                    let row = kid.as_table_row();
+                    row.assign_columns(&mut rowspans);
+                    self.spanning_cells.push_all(row.spanning_cells.as_slice());
+                } else {
+                    self.spanning_cells.push_all(kid.as_table_rowgroup().spanning_cells.as_slice());
+                }
+
                 // read column widths from table-row-group/table-row, and assign
                 // width=0 for the columns not defined in column-group
                 // FIXME: need to read widths from either table-header-group OR
@@ -207,27 +422,31 @@ impl Flow for TableFlow {
                         }
                     },
                     AutoLayout => {
-                        min_width = TableFlow::update_col_widths(&mut self.col_min_widths, kid.col_min_widths());
-                        pref_width = TableFlow::update_col_widths(&mut self.col_pref_widths, kid.col_pref_widths());
-
-                        // update the number of column widths from table-rows.
-                        let num_cols = self.col_min_widths.len();
-                        let num_child_cols = kid.col_min_widths().len();
                         debug!("table until the previous row has {} column(s) and this row has {} column(s)",
-                               num_cols, num_child_cols);
-                        for i in range(num_cols, num_child_cols) {
-                            self.col_widths.push(Au::new(0));
-                            let new_kid_min = *kid.col_min_widths().get(i);
-                            self.col_min_widths.push( new_kid_min );
-                            let new_kid_pref = *kid.col_pref_widths().get(i);
-                            self.col_pref_widths.push( new_kid_pref );
-                            min_width = min_width + new_kid_min;
-                            pref_width = pref_width + new_kid_pref;
-                        }
+                               self.col_min_widths.len(), kid.col_min_widths().len());
+                        TableFlow::merge_col_widths(&mut self.col_min_widths, kid.col_min_widths());
+                        TableFlow::merge_col_widths(&mut self.col_pref_widths, kid.col_pref_widths());
                     }
                 }
             }
         }
+
+        match self.table_layout {
+            FixedLayout => {}
+            AutoLayout => {
+                TableFlow::distribute_spanning_cell_widths(&mut self.col_min_widths,
+                                                           &mut self.col_pref_widths,
+                                                           self.spanning_cells.as_slice(),
+                                                           spacing.horizontal);
+                for _ in range(self.col_widths.len(), self.col_min_widths.len()) {
+                    self.col_widths.push(Au::new(0));
+                }
+            }
+        }
+
+        let spacing_width = total_spacing(self.col_widths.len(), spacing.horizontal);
+        let min_width = self.col_min_widths.iter().fold(spacing_width, |sum, width| sum + *width);
+        let pref_width = self.col_pref_widths.iter().fold(spacing_width, |sum, width| sum + *width);
         self.block_flow.base.intrinsic_widths.minimum_width = min_width;
         self.block_flow.base.intrinsic_widths.preferred_width =
             geometry::max(min_width, pref_width);
@@ -241,6 +460,11 @@ impl Flow for TableFlow {
         // The position was set to the containing block by the flow's parent.
         let containing_block_width = self.block_flow.base.position.size.width;
 
+        let (spacing, collapse) = border_spacing_and_collapse(self.block_flow.fragment.style());
+        if collapse {
+            self.collapse_cell_borders()
+        }
+
         let mut num_unspecified_widths = 0;
         let mut total_column_width = Au::new(0);
         for col_width in self.col_widths.iter() {
@@ -258,6 +482,10 @@ impl Flow for TableFlow {
         let padding_and_borders = self.block_flow.fragment.border_padding.horizontal();
         let content_width = self.block_flow.fragment.border_box.size.width - padding_and_borders;
 
+        // The columns share the content width with the spacing around them.
+        let spacing_width = total_spacing(self.col_widths.len(), spacing.horizontal);
+        let content_width = content_width - spacing_width;
+
         match self.table_layout {
             FixedLayout => {
                 // In fixed table layout, we distribute extra space among the unspecified columns if there are
@@ -279,7 +507,9 @@ impl Flow for TableFlow {
             _ => {}
         }
 
-        self.block_flow.propagate_assigned_width_to_children(left_content_edge, content_width, Some(self.col_widths.clone()));
+        self.block_flow.propagate_assigned_width_to_children(left_content_edge,
+                                                             content_width + spacing_width,
+                                                             Some(self.col_widths.clone()));
     }
 
     fn assign_height(&mut self, ctx: &mut LayoutContext) {
@@ -299,6 +529,257 @@ impl fmt::Show for TableFlow {
     }
 }
 
+/// The intrinsic widths of a cell that spans several columns. These are distributed over the
+/// spanned columns once the widths of the cells that span a single column are known.
+#[deriving(Clone)]
+pub struct SpanningCellWidths {
+    /// The index of the first column spanned by the cell.
+    pub start_column: uint,
+    /// The number of columns spanned by the cell.
+    pub colspan: uint,
+    /// The minimum width of the cell.
+    pub minimum_width: Au,
+    /// The preferred width of the cell.
+    pub preferred_width: Au,
+}
+
+/// Returns the spacing between adjacent cells, and between the outermost cells and the edges of
+/// the table, and whether the table collapses its borders. Tables with collapsing borders have no
+/// spacing. See CSS 2.1 § 17.6.
+pub fn border_spacing_and_collapse(style: &ComputedValues) -> (border_spacing::T, bool) {
+    match style.get_inheritedtable().border_collapse {
+        border_collapse::separate => (style.get_inheritedtable().border_spacing, false),
+        border_collapse::collapse => {
+            (border_spacing::T {
+                horizontal: Au(0),
+                vertical: Au(0),
+            }, true)
+        }
+    }
+}
+
+/// Returns the total width of the spacing around and between the given number of columns.
+pub fn total_spacing(column_count: uint, horizontal_spacing: Au) -> Au {
+    if column_count == 0 {
+        Au(0)
+    } else {
+        horizontal_spacing * Au::new((column_count + 1) as i32)
+    }
+}
+
+/// Sizes the given consecutive rows, which form a row group, and stretches the cells that span
+/// several of them. A row is made taller if the cells that end in it would not fit otherwise.
+pub fn lay_out_rows(rows: &mut [&mut Flow], vertical_spacing: Au) {
+    let mut heights: Vec<Au> = rows.iter().map(|row| flow::base(&**row).position.size.height)
+                                          .collect();
+    let spanned_height = |heights: &Vec<Au>, start: uint, end: uint| -> Au {
+        let spacing = vertical_spacing * Au::new((end - start - 1) as i32);
+        heights.slice(start, end).iter().fold(spacing, |sum, height| sum + *height)
+    };
+
+    for (index, row) in rows.mut_iter().enumerate() {
+        for cell in flow::mut_base(&mut **row).child_iter() {
+            let cell = cell.as_table_cell();
+            if cell.rowspan <= 1 {
+                continue
+            }
+            let end = cmp::min(index + cell.rowspan, heights.len());
+            let available = spanned_height(&heights, index, end);
+            if cell.natural_height > available {
+                *heights.get_mut(end - 1) = *heights.get(end - 1) + cell.natural_height - available
+            }
+        }
+    }
+
+    for (index, row) in rows.mut_iter().enumerate() {
+        let row = row.as_table_row();
+        if row.block_flow.base.position.size.height != *heights.get(index) {
+            row.set_height(*heights.get(index))
+        }
+        for cell in row.block_flow.base.child_iter() {
+            let cell = cell.as_table_cell();
+            if cell.rowspan > 1 {
+                let end = cmp::min(index + cell.rowspan, heights.len());
+                cell.set_height_and_align_contents(spanned_height(&heights, index, end))
+            }
+        }
+    }
+}
+
+/// One side of the border of a cell in a table with `border-collapse: collapse`.
+#[deriving(Clone)]
+pub struct CollapsedBorder {
+    pub style: border_style::T,
+    pub width: Au,
+    pub color: RGBA,
+}
+
+impl CollapsedBorder {
+    /// Returns a border that is not drawn.
+    pub fn none() -> CollapsedBorder {
+        CollapsedBorder {
+            style: border_style::none,
+            width: Au(0),
+            color: RGBA {
+                red: 0.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0.0,
+            },
+        }
+    }
+
+    /// Resolves the conflict between this border and an adjacent one per CSS 2.1 § 17.6.2.1:
+    /// `hidden` wins, then the wider border, then the border with the more prominent style. Ties
+    /// go to this border.
+    pub fn resolve(&self, other: &CollapsedBorder) -> CollapsedBorder {
+        fn precedence(style: border_style::T) -> uint {
+            match style {
+                border_style::hidden => 9,
+                border_style::double => 8,
+                border_style::solid => 7,
+                border_style::dashed => 6,
+                border_style::dotted => 5,
+                border_style::ridge => 4,
+                border_style::outset => 3,
+                border_style::groove => 2,
+                border_style::inset => 1,
+                border_style::none => 0,
+            }
+        }
+
+        if self.style == border_style::hidden {
+            return self.clone()
+        }
+        if other.style == border_style::hidden ||
+                other.width > self.width ||
+                (other.width == self.width && precedence(other.style) > precedence(self.style)) {
+            other.clone()
+        } else {
+            self.clone()
+        }
+    }
+}
+
+/// The borders of a cell in a table with `border-collapse: collapse`, after conflict resolution.
+#[deriving(Clone)]
+pub struct CollapsedBorders {
+    pub top: CollapsedBorder,
+    pub right: CollapsedBorder,
+    pub bottom: CollapsedBorder,
+    pub left: CollapsedBorder,
+}
+
+impl CollapsedBorders {
+    /// Returns borders none of which are drawn.
+    pub fn none() -> CollapsedBorders {
+        CollapsedBorders {
+            top: CollapsedBorder::none(),
+            right: CollapsedBorder::none(),
+            bottom: CollapsedBorder::none(),
+            left: CollapsedBorder::none(),
+        }
+    }
+
+    /// Returns the borders specified by the given style, before any conflict resolution.
+    pub fn from_style(style: &ComputedValues) -> CollapsedBorders {
+        let border = style.get_border();
+        CollapsedBorders {
+            top: CollapsedBorder {
+                style: border.border_top_style,
+                width: border.border_top_width,
+                color: style.resolve_color(border.border_top_color),
+            },
+            right: CollapsedBorder {
+                style: border.border_right_style,
+                width: border.border_right_width,
+                color: style.resolve_color(border.border_right_color),
+            },
+            bottom: CollapsedBorder {
+                style: border.border_bottom_style,
+                width: border.border_bottom_width,
+                color: style.resolve_color(border.border_bottom_color),
+            },
+            left: CollapsedBorder {
+                style: border.border_left_style,
+                width: border.border_left_width,
+                color: style.resolve_color(border.border_left_color),
+            },
+        }
+    }
+
+    /// Returns the widths of these borders.
+    pub fn widths(&self) -> SideOffsets2D<Au> {
+        SideOffsets2D::new(self.top.width, self.right.width, self.bottom.width, self.left.width)
+    }
+}
+
+/// The position of a cell in the grid of a table, along with its specified borders.
+struct CellBorderInfo {
+    row: uint,
+    column: uint,
+    rowspan: uint,
+    colspan: uint,
+    borders: CollapsedBorders,
+}
+
+/// Records the position in the grid and the specified borders of each cell of a row. Cells do not
+/// span past `group_end`, the end of their row group.
+fn gather_cell_borders(row: &mut TableRowFlow,
+                       row_index: uint,
+                       group_end: uint,
+                       cells: &mut Vec<CellBorderInfo>) {
+    let cell_columns = row.cell_columns.clone();
+    for (cell, column) in row.block_flow.base.child_iter().zip(cell_columns.iter()) {
+        let cell = cell.as_table_cell();
+        cells.push(CellBorderInfo {
+            row: row_index,
+            column: *column,
+            rowspan: cmp::min(cell.rowspan, group_end - row_index),
+            colspan: cell.colspan,
+            borders: CollapsedBorders::from_style(cell.block_flow.fragment.style()),
+        })
+    }
+}
+
+/// Maps each slot of the grid of a table to the index of the cell that occupies it, if any.
+struct CellGrid {
+    slots: Vec<Option<uint>>,
+    row_count: uint,
+    column_count: uint,
+}
+
+impl CellGrid {
+    /// Builds the grid, clipping the row spans of the cells to the number of rows.
+    fn new(cells: &mut [CellBorderInfo], row_count: uint) -> CellGrid {
+        let column_count = cells.iter().fold(0, |count, cell| {
+            cmp::max(count, cell.column + cell.colspan)
+        });
+        let mut slots = Vec::from_elem(row_count * column_count, None);
+        for (index, cell) in cells.mut_iter().enumerate() {
+            cell.rowspan = cmp::min(cell.rowspan, row_count - cell.row);
+            for row in range(cell.row, cell.row + cell.rowspan) {
+                for column in range(cell.column, cell.column + cell.colspan) {
+                    *slots.get_mut(row * column_count + column) = Some(index)
+                }
+            }
+        }
+        CellGrid {
+            slots: slots,
+            row_count: row_count,
+            column_count: column_count,
+        }
+    }
+
+    fn get(&self, row: uint, column: uint) -> Option<uint> {
+        if row < self.row_count && column < self.column_count {
+            *self.slots.get(row * self.column_count + column)
+        } else {
+            None
+        }
+    }
+}
+
 /// Table, TableRowGroup, TableRow, TableCell types.
 /// Their widths are calculated in the same way and do not have margins.
 pub struct InternalTable;
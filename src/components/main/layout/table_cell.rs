@@ -9,24 +9,56 @@
 use layout::block::{BlockFlow, MarginsMayNotCollapse, WidthAndMarginsComputer};
 use layout::context::LayoutContext;
 use layout::flow::{TableCellFlowClass, FlowClass, Flow};
+use layout::flow;
 use layout::fragment::Fragment;
 use layout::model::{MaybeAuto};
 use layout::table::InternalTable;
-use layout::wrapper::ThreadSafeLayoutNode;
+use layout::wrapper::{TLayoutNode, ThreadSafeLayoutNode};
 
+use script::dom::element::{HTMLTableDataCellElementTypeId, HTMLTableHeaderCellElementTypeId};
+use script::dom::node::ElementNodeTypeId;
 use servo_util::geometry::Au;
+use servo_util::geometry;
+use servo_util::namespace;
+use std::cmp;
 use std::fmt;
+use std::from_str::FromStr;
+use style::computed_values::vertical_align;
+
+/// The most columns a cell can span, per HTML § 4.9.11.
+static MAX_COLSPAN: uint = 1000;
+
+/// The most rows a cell can span, per HTML § 4.9.11. A `rowspan` of zero spans the rest of the
+/// row group, so it is treated as this many rows, since spans are cut off at the end of the group.
+static MAX_ROWSPAN: uint = 65534;
 
 /// A table formatting context.
 pub struct TableCellFlow {
     /// Data common to all flows.
     pub block_flow: BlockFlow,
+
+    /// The number of columns this cell spans.
+    pub colspan: uint,
+
+    /// The number of rows this cell spans.
+    pub rowspan: uint,
+
+    /// The height of the border box of this cell as determined by its contents, before it is
+    /// stretched to the height of its rows.
+    pub natural_height: Au,
+
+    /// The distance the contents of this cell have been moved down per `vertical-align`.
+    content_offset: Au,
 }
 
 impl TableCellFlow {
     pub fn from_node_and_fragment(node: &ThreadSafeLayoutNode, fragment: Fragment) -> TableCellFlow {
         TableCellFlow {
-            block_flow: BlockFlow::from_node_and_fragment(node, fragment)
+            block_flow: BlockFlow::from_node_and_fragment(node, fragment),
+            colspan: span_attribute(node, "colspan", MAX_COLSPAN),
+            rowspan: span_attribute(node, "rowspan", MAX_ROWSPAN),
+            natural_height: Au(0),
+            content_offset: Au(0),
         }
     }
 
@@ -46,7 +78,33 @@ impl TableCellFlow {
     /// methods
     #[inline(always)]
     fn assign_height_table_cell_base(&mut self, layout_context: &mut LayoutContext) {
-        self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse)
+        self.block_flow.assign_height_block_base(layout_context, MarginsMayNotCollapse);
+        self.natural_height = self.block_flow.base.position.size.height;
+        self.content_offset = Au(0);
+    }
+
+    /// Stretches this cell to the given border box height, which is the height of the rows it
+    /// spans, and moves its contents within it per `vertical-align`. Baseline alignment is
+    /// treated as top alignment.
+    pub fn set_height_and_align_contents(&mut self, height: Au) {
+        let free_space = geometry::max(height - self.natural_height, Au(0));
+        let content_offset = match self.block_flow.fragment.style().get_box().vertical_align {
+            vertical_align::middle => free_space.scale_by(0.5),
+            vertical_align::bottom => free_space,
+            _ => Au(0),
+        };
+
+        let delta = content_offset - self.content_offset;
+        if delta != Au(0) {
+            for kid in self.block_flow.base.child_iter() {
+                let kid_base = flow::mut_base(kid);
+                kid_base.position.origin.y = kid_base.position.origin.y + delta
+            }
+        }
+        self.content_offset = content_offset;
+
+        self.block_flow.fragment.border_box.size.height = height;
+        self.block_flow.base.position.size.height = height;
     }
 
     pub fn build_display_list_table_cell(&mut self, layout_context: &LayoutContext) {
@@ -114,6 +172,29 @@ impl Flow for TableCellFlow {
     }
 }
 
+/// Reads the value of the given span attribute (`colspan` or `rowspan`) of a table cell element,
+/// clamped to `max_span`. Anonymous cells, and cells whose attribute is missing or malformed, span
+/// one column or row. A zero `colspan` spans one column, and a zero `rowspan` spans `max_span`
+/// rows.
+fn span_attribute(node: &ThreadSafeLayoutNode, name: &str, max_span: uint) -> uint {
+    match node.type_id() {
+        Some(ElementNodeTypeId(HTMLTableDataCellElementTypeId)) |
+        Some(ElementNodeTypeId(HTMLTableHeaderCellElementTypeId)) => {}
+        _ => return 1,
+    }
+
+    let element = node.as_element();
+    let span: Option<uint> = element.get_attr(&namespace::Null, name).and_then(|string| {
+        FromStr::from_str(string.trim())
+    });
+    match span {
+        None => 1,
+        Some(0) if name == "rowspan" => max_span,
+        Some(0) => 1,
+        Some(span) => cmp::min(span, max_span),
+    }
+}
+
 impl fmt::Show for TableCellFlow {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "TableCellFlow: {}", self.block_flow)
@@ -13,12 +13,13 @@ use layout::context::LayoutContext;
 use layout::flow::{TableRowFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use layout::flow;
 use layout::fragment::Fragment;
-use layout::table::InternalTable;
+use layout::table::{InternalTable, SpanningCellWidths, border_spacing_and_collapse};
 use layout::model::{MaybeAuto, Specified, Auto};
 use layout::wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
 use servo_util::geometry;
+use std::cmp;
 use std::fmt;
 
 /// A table formatting context.
@@ -33,6 +34,24 @@ pub struct TableRowFlow {
 
     /// Column pref widths.
     pub col_pref_widths: Vec<Au>,
+
+    /// The index of the first column occupied by each cell.
+    pub cell_columns: Vec<uint>,
+
+    /// The intrinsic widths of the cells that span several columns.
+    pub spanning_cells: Vec<SpanningCellWidths>,
+
+    /// The spans and widths of each cell, gathered from the cells during width bubbling.
+    cell_widths: Vec<CellWidths>,
+}
+
+/// The spans and widths of a cell, from which the columns of a row are assigned.
+struct CellWidths {
+    colspan: uint,
+    rowspan: uint,
+    specified_width: Au,
+    minimum_width: Au,
+    preferred_width: Au,
 }
 
 impl TableRowFlow {
@@ -44,6 +63,9 @@ impl TableRowFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            cell_columns: vec!(),
+            spanning_cells: vec!(),
+            cell_widths: vec!(),
         }
     }
 
@@ -55,6 +77,9 @@ impl TableRowFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            cell_columns: vec!(),
+            spanning_cells: vec!(),
+            cell_widths: vec!(),
         }
     }
 
@@ -62,13 +87,74 @@ impl TableRowFlow {
         &self.block_flow.fragment
     }
 
-    fn initialize_offsets(&mut self) -> (Au, Au, Au) {
-        // TODO: If border-collapse: collapse, top_offset, bottom_offset, and left_offset
-        // should be updated. Currently, they are set as Au(0).
-        (Au(0), Au(0), Au(0))
+    /// Assigns the columns occupied by the cells of this row, skipping the columns occupied by
+    /// cells spanning down from the rows above, and computes the column widths of this row.
+    /// `rowspans` holds, for each column, the number of rows that remain occupied by a cell from
+    /// a previous row, and is updated for the next row. See HTML 4.01 § 11.2.6.1.
+    pub fn assign_columns(&mut self, rowspans: &mut Vec<uint>) {
+        self.cell_columns = vec!();
+        self.spanning_cells = vec!();
+        self.col_widths = vec!();
+        self.col_min_widths = vec!();
+        self.col_pref_widths = vec!();
+
+        let mut column = 0;
+        for cell in self.cell_widths.iter() {
+            while column < rowspans.len() && *rowspans.get(column) > 0 {
+                column += 1
+            }
+            self.cell_columns.push(column);
+
+            let end = column + cell.colspan;
+            for _ in range(rowspans.len(), end) {
+                rowspans.push(0)
+            }
+            for _ in range(self.col_widths.len(), end) {
+                self.col_widths.push(Au(0));
+                self.col_min_widths.push(Au(0));
+                self.col_pref_widths.push(Au(0));
+            }
+            for i in range(column, end) {
+                *rowspans.get_mut(i) = cell.rowspan
+            }
+
+            if cell.colspan == 1 {
+                *self.col_widths.get_mut(column) = cell.specified_width;
+                *self.col_min_widths.get_mut(column) = cell.minimum_width;
+                *self.col_pref_widths.get_mut(column) = cell.preferred_width;
+            } else {
+                // The specified width of a spanning cell is shared evenly by its columns for the
+                // purposes of fixed table layout.
+                let share = cell.specified_width / Au::new(cell.colspan as i32);
+                for width in self.col_widths.mut_slice(column, end).mut_iter() {
+                    *width = share
+                }
+                self.spanning_cells.push(SpanningCellWidths {
+                    start_column: column,
+                    colspan: cell.colspan,
+                    minimum_width: cell.minimum_width,
+                    preferred_width: cell.preferred_width,
+                })
+            }
+            column = end
+        }
+
+        // This row is done; the cells spanning down occupy one row less of the ones that remain.
+        for rowspan in rowspans.mut_iter() {
+            if *rowspan > 0 {
+                *rowspan -= 1
+            }
+        }
+
+        let min_width = self.col_min_widths.iter().fold(Au(0), |sum, width| sum + *width);
+        let pref_width = self.col_pref_widths.iter().fold(Au(0), |sum, width| sum + *width);
+        self.block_flow.base.intrinsic_widths.minimum_width = min_width;
+        self.block_flow.base.intrinsic_widths.preferred_width = geometry::max(min_width,
+                                                                              pref_width);
     }
 
-    /// Assign height for table-row flow.
+    /// Assign height for table-row flow. The row is as tall as its tallest cell that spans only
+    /// this row; the cells spanning several rows are sized by the enclosing row group.
     ///
     /// TODO(pcwalton): This doesn't handle floats and positioned elements right.
     ///
@@ -76,27 +162,25 @@ impl TableRowFlow {
     /// methods
     #[inline(always)]
     fn assign_height_table_row_base(&mut self, layout_context: &mut LayoutContext) {
-        let (top_offset, _, _) = self.initialize_offsets();
-
-        let /* mut */ cur_y = top_offset;
-
         // Per CSS 2.1 § 17.5.3, find max_y = max( computed `height`, minimum height of all cells )
         let mut max_y = Au::new(0);
         for kid in self.block_flow.base.child_iter() {
             kid.assign_height_for_inorder_child_if_necessary(layout_context);
 
-            {
-                let child_fragment = kid.as_table_cell().fragment();
-                // TODO: Percentage height
-                let child_specified_height = MaybeAuto::from_style(child_fragment.style().get_box().height,
-                                                                   Au::new(0)).specified_or_zero();
-                max_y =
-                    geometry::max(max_y,
-                                  child_specified_height + child_fragment.border_padding.vertical());
+            let cell = kid.as_table_cell();
+            cell.block_flow.base.position.origin.y = Au(0);
+            if cell.rowspan > 1 {
+                continue
             }
-            let child_node = flow::mut_base(kid);
-            child_node.position.origin.y = cur_y;
-            max_y = geometry::max(max_y, child_node.position.size.height);
+
+            max_y = geometry::max(max_y, cell.natural_height);
+
+            // TODO: Percentage height
+            let child_fragment = cell.fragment();
+            let child_specified_height = MaybeAuto::from_style(child_fragment.style().get_box().height,
+                                                               Au::new(0)).specified_or_zero();
+            max_y = geometry::max(max_y,
+                                  child_specified_height + child_fragment.border_padding.vertical());
         }
 
         let mut height = max_y;
@@ -105,26 +189,21 @@ impl TableRowFlow {
             Auto => height,
             Specified(value) => geometry::max(value, height)
         };
-        // cur_y = cur_y + height;
-
-        // Assign the height of own fragment
-        //
-        // FIXME(pcwalton): Take `cur_y` into account.
-        let mut position = self.block_flow.fragment.border_box;
-        position.size.height = height;
-        self.block_flow.fragment.border_box = position;
+
+        self.set_height(height)
+    }
+
+    /// Sets the height of this row and of the cells that span only this row, aligning their
+    /// contents per `vertical-align`.
+    pub fn set_height(&mut self, height: Au) {
+        self.block_flow.fragment.border_box.size.height = height;
         self.block_flow.base.position.size.height = height;
 
-        // Assign the height of kid fragments, which is the same value as own height.
         for kid in self.block_flow.base.child_iter() {
-            {
-                let kid_fragment = kid.as_table_cell().mut_fragment();
-                let mut position = kid_fragment.border_box;
-                position.size.height = height;
-                kid_fragment.border_box = position;
+            let cell = kid.as_table_cell();
+            if cell.rowspan <= 1 {
+                cell.set_height_and_align_contents(height)
             }
-            let child_node = flow::mut_base(kid);
-            child_node.position.size.height = height;
         }
     }
 
@@ -166,30 +245,28 @@ impl Flow for TableRowFlow {
     /// Min/pref widths set by this function are used in automatic table layout calculation.
     /// The specified column widths of children cells are used in fixed table layout calculation.
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
-        let mut min_width = Au(0);
-        let mut pref_width = Au(0);
+        self.cell_widths = vec!();
         /* find the specified widths from child table-cell contexts */
         for kid in self.block_flow.base.child_iter() {
             assert!(kid.is_table_cell());
 
             // collect the specified column widths of cells. These are used in fixed table layout calculation.
-            {
-                let child_fragment = kid.as_table_cell().fragment();
-                let child_specified_width = MaybeAuto::from_style(child_fragment.style().get_box().width,
-                                                                  Au::new(0)).specified_or_zero();
-                self.col_widths.push(child_specified_width);
-            }
-
             // collect min_width & pref_width of children cells for automatic table layout calculation.
-            let child_base = flow::mut_base(kid);
-            self.col_min_widths.push(child_base.intrinsic_widths.minimum_width);
-            self.col_pref_widths.push(child_base.intrinsic_widths.preferred_width);
-            min_width = min_width + child_base.intrinsic_widths.minimum_width;
-            pref_width = pref_width + child_base.intrinsic_widths.preferred_width;
+            let cell = kid.as_table_cell();
+            let child_specified_width = MaybeAuto::from_style(cell.fragment().style().get_box().width,
+                                                              Au::new(0)).specified_or_zero();
+            self.cell_widths.push(CellWidths {
+                colspan: cell.colspan,
+                rowspan: cell.rowspan,
+                specified_width: child_specified_width,
+                minimum_width: cell.block_flow.base.intrinsic_widths.minimum_width,
+                preferred_width: cell.block_flow.base.intrinsic_widths.preferred_width,
+            });
         }
-        self.block_flow.base.intrinsic_widths.minimum_width = min_width;
-        self.block_flow.base.intrinsic_widths.preferred_width = geometry::max(min_width,
-                                                                              pref_width);
+
+        // A row that is not in a row group is assigned its columns again by the table, which
+        // knows about the cells spanning down from the rows above.
+        self.assign_columns(&mut vec!())
     }
 
     /// Recursively (top-down) determines the actual width of child contexts and fragments. When called
@@ -199,13 +276,36 @@ impl Flow for TableRowFlow {
 
         // The position was set to the containing block by the flow's parent.
         let containing_block_width = self.block_flow.base.position.size.width;
-        // FIXME: In case of border-collapse: collapse, left_content_edge should be border-left
         let left_content_edge = Au::new(0);
 
         let width_computer = InternalTable;
         width_computer.compute_used_width(&mut self.block_flow, ctx, containing_block_width);
 
-        self.block_flow.propagate_assigned_width_to_children(left_content_edge, Au(0), Some(self.col_widths.clone()));
+        // Each cell is as wide as the columns it spans and the spacing between them.
+        let (spacing, _) = border_spacing_and_collapse(self.block_flow.fragment.style());
+        let mut cell_widths = vec!();
+        let mut cell_offsets = vec!();
+        for (cell, &column) in self.cell_widths.iter().zip(self.cell_columns.iter()) {
+            let end = cmp::min(column + cell.colspan, self.col_widths.len());
+            let start = cmp::min(column, end);
+            let offset = self.col_widths.slice_to(start).iter().fold(spacing.horizontal,
+                |sum, width| sum + *width + spacing.horizontal);
+            let width = self.col_widths.slice(start, end).iter().fold(Au(0),
+                |sum, width| sum + *width);
+            let inner_spacing = if end > start {
+                spacing.horizontal * Au::new((end - start - 1) as i32)
+            } else {
+                Au(0)
+            };
+            cell_offsets.push(offset);
+            cell_widths.push(width + inner_spacing);
+        }
+
+        self.block_flow.propagate_assigned_width_to_children(left_content_edge, Au(0), Some(cell_widths));
+
+        for (kid, offset) in self.block_flow.base.child_iter().zip(cell_offsets.iter()) {
+            flow::mut_base(kid).position.origin.x = *offset
+        }
     }
 
     fn assign_height(&mut self, ctx: &mut LayoutContext) {
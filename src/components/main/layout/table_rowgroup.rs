@@ -13,7 +13,8 @@ use layout::context::LayoutContext;
 use layout::flow::{TableRowGroupFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use layout::flow;
 use layout::fragment::Fragment;
-use layout::table::{InternalTable, TableFlow};
+use layout::table::{InternalTable, SpanningCellWidths, TableFlow, border_spacing_and_collapse};
+use layout::table::lay_out_rows;
 use layout::wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
@@ -32,6 +33,9 @@ pub struct TableRowGroupFlow {
 
     /// Column pref widths.
     pub col_pref_widths: Vec<Au>,
+
+    /// The intrinsic widths of the cells of this row group that span several columns.
+    pub spanning_cells: Vec<SpanningCellWidths>,
 }
 
 impl TableRowGroupFlow {
@@ -43,6 +47,7 @@ impl TableRowGroupFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            spanning_cells: vec!(),
         }
     }
 
@@ -54,6 +59,7 @@ impl TableRowGroupFlow {
             col_widths: vec!(),
             col_min_widths: vec!(),
             col_pref_widths: vec!(),
+            spanning_cells: vec!(),
         }
     }
 
@@ -61,13 +67,8 @@ impl TableRowGroupFlow {
         &self.block_flow.fragment
     }
 
-    fn initialize_offsets(&mut self) -> (Au, Au, Au) {
-        // TODO: If border-collapse: collapse, top_offset, bottom_offset, and left_offset
-        // should be updated. Currently, they are set as Au(0).
-        (Au(0), Au(0), Au(0))
-    }
-
-    /// Assign height for table-rowgroup flow.
+    /// Assign height for table-rowgroup flow. Rows are stacked with `border-spacing` between
+    /// them; the spacing around the row group belongs to the table.
     ///
     /// FIXME(pcwalton): This doesn't handle floats right.
     ///
@@ -75,19 +76,28 @@ impl TableRowGroupFlow {
     /// methods
     #[inline(always)]
     fn assign_height_table_rowgroup_base(&mut self, layout_context: &mut LayoutContext) {
-        let (top_offset, _, _) = self.initialize_offsets();
-
-        let mut cur_y = top_offset;
+        let (spacing, _) = border_spacing_and_collapse(self.block_flow.fragment.style());
 
         for kid in self.block_flow.base.child_iter() {
             kid.assign_height_for_inorder_child_if_necessary(layout_context);
+        }
+
+        {
+            let mut rows: Vec<&mut Flow> = self.block_flow.base.child_iter().collect();
+            lay_out_rows(rows.as_mut_slice(), spacing.vertical);
+        }
 
+        let mut cur_y = Au(0);
+        for (i, kid) in self.block_flow.base.child_iter().enumerate() {
+            if i > 0 {
+                cur_y = cur_y + spacing.vertical
+            }
             let child_node = flow::mut_base(kid);
             child_node.position.origin.y = cur_y;
             cur_y = cur_y + child_node.position.size.height;
         }
 
-        let height = cur_y - top_offset;
+        let height = cur_y;
 
         let mut position = self.block_flow.fragment.border_box;
         position.size.height = height;
@@ -134,40 +144,38 @@ impl Flow for TableRowGroupFlow {
     /// Also, this function finds the specified column widths from the first row.
     /// Those are used in fixed table layout calculation
     fn bubble_widths(&mut self, _: &mut LayoutContext) {
-        let mut min_width = Au(0);
-        let mut pref_width = Au(0);
+        let mut rowspans = vec!();
+        self.col_widths = vec!();
+        self.col_min_widths = vec!();
+        self.col_pref_widths = vec!();
+        self.spanning_cells = vec!();
 
-        for kid in self.block_flow.base.child_iter() {
+        for (i, kid) in self.block_flow.base.child_iter().enumerate() {
             assert!(kid.is_table_row());
 
+            // Cells spanning several rows occupy columns in the rows below them.
+            let row = kid.as_table_row();
+            row.assign_columns(&mut rowspans);
+            self.spanning_cells.push_all(row.spanning_cells.as_slice());
+
             // calculate min_width & pref_width for automatic table layout calculation
             // 'self.col_min_widths' collects the maximum value of cells' min-widths for each column.
             // 'self.col_pref_widths' collects the maximum value of cells' pref-widths for each column.
-            if self.col_widths.is_empty() { // First Row
-                assert!(self.col_min_widths.is_empty() && self.col_pref_widths.is_empty());
+            if i == 0 {
                 // 'self.col_widths' collects the specified column widths from the first table-row for fixed table layout calculation.
-                self.col_widths = kid.col_widths().clone();
-                self.col_min_widths = kid.col_min_widths().clone();
-                self.col_pref_widths = kid.col_pref_widths().clone();
-            } else {
-                min_width = TableFlow::update_col_widths(&mut self.col_min_widths, kid.col_min_widths());
-                pref_width = TableFlow::update_col_widths(&mut self.col_pref_widths, kid.col_pref_widths());
-
-                // update the number of column widths from table-rows.
-                let num_cols = self.col_widths.len();
-                let num_child_cols = kid.col_min_widths().len();
-                for i in range(num_cols, num_child_cols) {
-                    self.col_widths.push(Au::new(0));
-                    let new_kid_min = *kid.col_min_widths().get(i);
-                    self.col_min_widths.push(*kid.col_min_widths().get(i));
-                    let new_kid_pref = *kid.col_pref_widths().get(i);
-                    self.col_pref_widths.push(*kid.col_pref_widths().get(i));
-                    min_width = min_width + new_kid_min;
-                    pref_width = pref_width + new_kid_pref;
-                }
+                self.col_widths = row.col_widths.clone();
             }
+            TableFlow::merge_col_widths(&mut self.col_min_widths, &row.col_min_widths);
+            TableFlow::merge_col_widths(&mut self.col_pref_widths, &row.col_pref_widths);
+        }
+
+        // update the number of column widths from table-rows.
+        for _ in range(self.col_widths.len(), self.col_min_widths.len()) {
+            self.col_widths.push(Au::new(0));
         }
 
+        let min_width = self.col_min_widths.iter().fold(Au(0), |sum, width| sum + *width);
+        let pref_width = self.col_pref_widths.iter().fold(Au(0), |sum, width| sum + *width);
         self.block_flow.base.intrinsic_widths.minimum_width = min_width;
         self.block_flow.base.intrinsic_widths.preferred_width = geometry::max(min_width,
                                                                               pref_width);
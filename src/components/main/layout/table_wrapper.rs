@@ -13,7 +13,9 @@ use layout::context::LayoutContext;
 use layout::floats::FloatKind;
 use layout::flow::{TableWrapperFlowClass, FlowClass, Flow, ImmutableFlowUtils};
 use layout::fragment::Fragment;
-use layout::model::{Specified, Auto, specified};
+use layout::model::{Specified, Auto};
+use layout::model;
+use layout::table::{CollapsedBorders, border_spacing_and_collapse, total_spacing};
 use layout::wrapper::ThreadSafeLayoutNode;
 
 use servo_util::geometry::Au;
@@ -48,11 +50,7 @@ impl TableWrapperFlow {
         } else {
             AutoLayout
         };
-        TableWrapperFlow {
-            block_flow: block_flow,
-            col_widths: vec!(),
-            table_layout: table_layout
-        }
+        TableWrapperFlow::from_block_flow(block_flow, table_layout)
     }
 
     pub fn from_node(constructor: &mut FlowConstructor,
@@ -65,11 +63,7 @@ impl TableWrapperFlow {
         } else {
             AutoLayout
         };
-        TableWrapperFlow {
-            block_flow: block_flow,
-            col_widths: vec!(),
-            table_layout: table_layout
-        }
+        TableWrapperFlow::from_block_flow(block_flow, table_layout)
     }
 
     pub fn float_from_node(constructor: &mut FlowConstructor,
@@ -83,6 +77,15 @@ impl TableWrapperFlow {
         } else {
             AutoLayout
         };
+        TableWrapperFlow::from_block_flow(block_flow, table_layout)
+    }
+
+    fn from_block_flow(mut block_flow: BlockFlow, table_layout: TableLayout) -> TableWrapperFlow {
+        // The borders of a table with collapsing borders are drawn by its cells.
+        let (_, collapse) = border_spacing_and_collapse(block_flow.fragment.style());
+        if collapse {
+            block_flow.fragment.collapsed_borders = Some(CollapsedBorders::none())
+        }
         TableWrapperFlow {
             block_flow: block_flow,
             col_widths: vec!(),
@@ -228,27 +231,33 @@ impl TableWrapper {
         let mut input = self.compute_width_constraint_inputs(&mut table_wrapper.block_flow,
                                                              parent_flow_width,
                                                              ctx);
+        // Get the spacing around and between the columns, and the left and right paddings and
+        // borders of the table. We get these values from the fragment's style since table_wrapper
+        // doesn't have its own padding. Tables with collapsing borders have neither, as their
+        // borders are drawn by the cells.
+        // input.available_width is same as containing_block_width in table_wrapper.
+        let (spacing, collapse) =
+            border_spacing_and_collapse(table_wrapper.block_flow.fragment.style());
+        let spacing_width = total_spacing(table_wrapper.col_widths.len(), spacing.horizontal);
+        let padding_and_borders = if collapse {
+            Au(0)
+        } else {
+            let style = table_wrapper.block_flow.fragment.style();
+            model::padding_from_style(style, input.available_width).horizontal() +
+                model::border_from_style(style).horizontal()
+        };
+
         let computed_width = match table_wrapper.table_layout {
             FixedLayout => {
                 let fixed_cells_width = table_wrapper.col_widths.iter().fold(Au(0),
                                                                              |sum, width| sum.add(width));
 
                 let mut computed_width = input.computed_width.specified_or_zero();
-                let style = table_wrapper.block_flow.fragment.style();
-
-                // Get left and right paddings, borders for table.
-                // We get these values from the fragment's style since table_wrapper doesn't have it's own border or padding.
-                // input.available_width is same as containing_block_width in table_wrapper.
-                let padding_left = specified(style.get_padding().padding_left,
-                                             input.available_width);
-                let padding_right = specified(style.get_padding().padding_right,
-                                              input.available_width);
-                let border_left = style.get_border().border_left_width;
-                let border_right = style.get_border().border_right_width;
-                let padding_and_borders = padding_left + padding_right + border_left + border_right;
+
                 // Compare border-edge widths. Because fixed_cells_width indicates content-width,
-                // padding and border values are added to fixed_cells_width.
-                computed_width = geometry::max(fixed_cells_width + padding_and_borders, computed_width);
+                // padding, border, and spacing values are added to fixed_cells_width.
+                computed_width = geometry::max(fixed_cells_width + spacing_width + padding_and_borders,
+                                               computed_width);
                 computed_width
             },
             AutoLayout => {
@@ -269,44 +278,25 @@ impl TableWrapper {
                         col_pref_widths = kid.col_pref_widths();
                     }
                 }
-                // 'extra_width': difference between the calculated table width and minimum width
-                // required by all columns. It will be distributed over the columns.
-                let (width, extra_width) = match input.computed_width {
+
+                // The intrinsic widths of the table include the spacing but not the borders and
+                // padding, so compare border-edge widths.
+                let cols_min = cols_min + padding_and_borders;
+                let cols_max = cols_max + padding_and_borders;
+                let width = match input.computed_width {
                     Auto => {
-                        if input.available_width > geometry::max(cols_max, cap_min) {
-                            if cols_max > cap_min {
-                                table_wrapper.col_widths = col_pref_widths.clone();
-                                (cols_max, Au(0))
-                            } else {
-                                (cap_min, cap_min - cols_min)
-                            }
-                        } else {
-                            let max = if cols_min >= input.available_width && cols_min >= cap_min {
-                                table_wrapper.col_widths = col_min_widths.clone();
-                                cols_min
-                            } else {
-                                geometry::max(input.available_width, cap_min)
-                            };
-                            (max, max - cols_min)
-                        }
+                        let width = geometry::min(cols_max, input.available_width);
+                        geometry::max(geometry::max(width, cols_min), cap_min)
                     },
                     Specified(width) => {
-                        let max = if cols_min >= width && cols_min >= cap_min {
-                            table_wrapper.col_widths = col_min_widths.clone();
-                            cols_min
-                        } else {
-                            geometry::max(width, cap_min)
-                        };
-                        (max, max - cols_min)
+                        geometry::max(geometry::max(width, cols_min), cap_min)
                     }
                 };
-                // The extra width is distributed over the columns
-                if extra_width > Au(0) {
-                    let cell_len = table_wrapper.col_widths.len() as f64;
-                    table_wrapper.col_widths = col_min_widths.iter().map(|width| {
-                        width + extra_width.scale_by(1.0 / cell_len)
-                    }).collect();
-                }
+
+                table_wrapper.col_widths =
+                    distribute_width_over_columns(col_min_widths.as_slice(),
+                                                  col_pref_widths.as_slice(),
+                                                  width - padding_and_borders - spacing_width);
                 width
             }
         };
@@ -315,6 +305,31 @@ impl TableWrapper {
     }
 }
 
+/// Distributes the given width over columns with the given minimum and preferred widths. Each
+/// column gets at least its minimum width; the width beyond the minimums goes to the columns in
+/// proportion to how much they want to grow, up to their preferred widths, and any width beyond
+/// the preferred widths goes to the columns in proportion to their preferred widths.
+fn distribute_width_over_columns(col_min_widths: &[Au], col_pref_widths: &[Au], width: Au)
+                                 -> Vec<Au> {
+    let min_sum = col_min_widths.iter().fold(Au(0), |sum, width| sum + *width);
+    let pref_sum = col_pref_widths.iter().fold(Au(0), |sum, width| sum + *width);
+
+    if width <= min_sum {
+        col_min_widths.iter().map(|width| *width).collect()
+    } else if width <= pref_sum {
+        let ratio = (width - min_sum).to_f64().unwrap() / (pref_sum - min_sum).to_f64().unwrap();
+        col_min_widths.iter().zip(col_pref_widths.iter()).map(|(min, pref)| {
+            *min + (*pref - *min).scale_by(ratio)
+        }).collect()
+    } else if pref_sum > Au(0) {
+        let ratio = width.to_f64().unwrap() / pref_sum.to_f64().unwrap();
+        col_pref_widths.iter().map(|pref| pref.scale_by(ratio)).collect()
+    } else {
+        let share = width.scale_by(1.0 / col_pref_widths.len() as f64);
+        col_pref_widths.iter().map(|_| share).collect()
+    }
+}
+
 impl WidthAndMarginsComputer for TableWrapper {
     /// Solve the width and margins constraints for this block flow.
     fn solve_width_constraints(&self, block: &mut BlockFlow, input: &WidthConstraintInput)
@@ -8,20 +8,84 @@
 
 use layout::flow::Flow;
 use layout::fragment::{Fragment, ScannedTextFragment, ScannedTextFragmentInfo, UnscannedTextFragment};
+use layout::inline::InlineFragments;
 
 use gfx::font::{FontMetrics, FontStyle};
 use gfx::font_context::FontContext;
+use gfx::text::bidi::{BidiClass, Level, LRE, LRO, ON, PDF, RLE, RLO};
+use gfx::text::bidi;
 use gfx::text::glyph::CharIndex;
+use gfx::text::line_break;
+use gfx::text::shaping::{ShapingFeature, ShapingOptions};
 use gfx::text::text_run::TextRun;
-use gfx::text::util::{CompressWhitespaceNewline, transform_text, CompressNone};
+use gfx::text::util::{CompressWhitespaceNewline, transform_case, transform_text, CompressNone};
 use servo_util::geometry::Au;
 use servo_util::range::Range;
 use style::ComputedValues;
-use style::computed_values::{font_family, line_height, white_space};
+use style::computed_values::{direction, font_family, font_kerning, font_variant, line_height};
+use style::computed_values::{unicode_bidi, white_space};
 use sync::Arc;
 
-struct NewLinePositions {
+/// The text of a single fragment after whitespace compression, along with the bidi level of each
+/// of its characters. Non-text fragments have no text and a single level.
+struct TransformedText {
+    text: String,
     new_line_pos: Vec<CharIndex>,
+    levels: Vec<Level>,
+}
+
+/// A maximal sequence of characters within a clump that share a bidi level and are rendered with
+/// the same font of the font group. Each of these is shaped into its own text run.
+struct ShapingRun {
+    level: Level,
+    font_index: uint,
+    range: Range<CharIndex>,
+    text: String,
+}
+
+/// Concatenates the given transformed texts, splitting them into shaping runs wherever the bidi
+/// level or the font that renders the character changes. `find_font_index` returns the index in
+/// the font group of the font for a character. Returns the range of each text within the
+/// concatenation, along with the runs.
+fn split_into_shaping_runs(transformed_texts: &[TransformedText],
+                           find_font_index: |char| -> uint)
+                           -> (Vec<Range<CharIndex>>, Vec<ShapingRun>) {
+    let mut new_ranges: Vec<Range<CharIndex>> = vec![];
+    let mut shaping_runs: Vec<ShapingRun> = vec![];
+    let mut char_total = CharIndex(0);
+    for transformed_text in transformed_texts.iter() {
+        let added_chars = CharIndex(transformed_text.levels.len() as int);
+        new_ranges.push(Range::new(char_total, added_chars));
+
+        for (ch, &level) in transformed_text.text.as_slice().chars()
+                                            .zip(transformed_text.levels.iter()) {
+            // Whitespace and combining marks stay with the font of the preceding character, so
+            // that they don't split runs.
+            let last_font_index = match shaping_runs.last() {
+                Some(shaping_run) if shaping_run.level == level => Some(shaping_run.font_index),
+                Some(_) | None => None,
+            };
+            let font_index = match last_font_index {
+                Some(font_index) if ch.is_whitespace() ||
+                        line_break::break_class(ch) == line_break::CM => font_index,
+                Some(_) | None => find_font_index(ch),
+            };
+
+            if last_font_index != Some(font_index) {
+                shaping_runs.push(ShapingRun {
+                    level: level,
+                    font_index: font_index,
+                    range: Range::new(char_total, CharIndex(0)),
+                    text: String::new(),
+                })
+            }
+            let shaping_run = shaping_runs.mut_last().unwrap();
+            shaping_run.range.extend_by(CharIndex(1));
+            shaping_run.text.push_char(ch);
+            char_total = char_total + CharIndex(1);
+        }
+    }
+    (new_ranges, shaping_runs)
 }
 
 // A helper function.
@@ -30,6 +94,125 @@ fn can_coalesce_text_nodes(fragments: &[Fragment], left_i: uint, right_i: uint)
     fragments[left_i].can_merge_with_fragment(&fragments[right_i])
 }
 
+/// Compresses whitespace in the text of every fragment and applies its `text-transform`. The
+/// whitespace and word state is carried from each text fragment to the next, and each clump uses
+/// the `white-space` property of its first fragment.
+fn transform_fragments(fragments: &[Fragment]) -> Vec<TransformedText> {
+    let mut last_whitespace = true;
+    let mut in_word = false;
+    let mut compression = CompressWhitespaceNewline;
+    let mut transformed_texts = Vec::with_capacity(fragments.len());
+    for (fragment_i, fragment) in fragments.iter().enumerate() {
+        if fragment_i == 0 || !can_coalesce_text_nodes(fragments, fragment_i - 1, fragment_i) {
+            // TODO(#115): Use the actual CSS `white-space` property of the relevant style.
+            compression = match fragment.white_space() {
+                white_space::normal => CompressWhitespaceNewline,
+                white_space::pre => CompressNone,
+            };
+        }
+
+        let mut new_line_pos = vec![];
+        let text = match fragment.specific {
+            UnscannedTextFragment(ref text_fragment_info) => {
+                // TODO(#113): Fragments starting and/or ending with whitespace are not yet
+                // compressed correctly with respect to the text run.
+                let (text, whitespace) = transform_text(text_fragment_info.text.as_slice(),
+                                                        compression,
+                                                        last_whitespace,
+                                                        &mut new_line_pos);
+                last_whitespace = whitespace;
+
+                let (text, text_in_word) =
+                    transform_case(text.as_slice(),
+                                   fragment.style().get_inheritedtext().text_transform,
+                                   text_fragment_info.lang.as_ref().map(|lang| lang.as_slice()),
+                                   in_word);
+                in_word = text_in_word;
+                text
+            }
+            _ => {
+                in_word = false;
+                String::new()
+            }
+        };
+
+        transformed_texts.push(TransformedText {
+            text: text,
+            new_line_pos: new_line_pos,
+            levels: vec![],
+        })
+    }
+    transformed_texts
+}
+
+/// Runs the bidi algorithm over the whole paragraph and stores the resolved level of each
+/// character into `transformed_texts`. Non-text fragments are treated as U+FFFC OBJECT
+/// REPLACEMENT CHARACTER, and inline elements with `unicode-bidi` other than `normal` contribute
+/// the corresponding explicit embedding or override, per CSS 2.1 § 9.10.
+fn resolve_bidi_levels(fragments: &InlineFragments,
+                       transformed_texts: &mut [TransformedText],
+                       paragraph_level: Level) {
+    // Gather the explicit embeddings as (start, end, opening class) triples, with the outermost
+    // embedding first among those starting at the same fragment.
+    let mut embeddings: Vec<(int, int, BidiClass)> = fragments.ranges.iter().filter_map(|range| {
+        if range.range.is_empty() {
+            return None
+        }
+        let is_rtl = range.style.get_inheritedbox().direction == direction::rtl;
+        let opening_class = match (range.style.get_box().unicode_bidi, is_rtl) {
+            (unicode_bidi::normal, _) => return None,
+            (unicode_bidi::embed, false) => LRE,
+            (unicode_bidi::embed, true) => RLE,
+            (unicode_bidi::bidi_override, false) => LRO,
+            (unicode_bidi::bidi_override, true) => RLO,
+        };
+        Some((range.range.begin().to_int(), range.range.end().to_int(), opening_class))
+    }).collect();
+    embeddings.sort_by(|&(a_start, a_end, _), &(b_start, b_end, _)| {
+        (a_start, -a_end).cmp(&(b_start, -b_end))
+    });
+
+    let mut classes = vec![];
+    let mut fragment_starts = Vec::with_capacity(transformed_texts.len());
+    let mut open_embedding_ends: Vec<int> = vec![];
+    let mut embedding_iter = embeddings.iter().peekable();
+    for (fragment_i, fragment) in fragments.fragments.iter().enumerate() {
+        let fragment_i = fragment_i as int;
+        while open_embedding_ends.last().map_or(false, |end| *end <= fragment_i) {
+            drop(open_embedding_ends.pop());
+            classes.push(PDF)
+        }
+        loop {
+            match embedding_iter.peek() {
+                Some(&&(start, _, _)) if start == fragment_i => {}
+                Some(_) | None => break,
+            }
+            let &(_, end, opening_class) = embedding_iter.next().unwrap();
+            open_embedding_ends.push(end);
+            classes.push(opening_class)
+        }
+
+        fragment_starts.push(classes.len());
+        match fragment.specific {
+            UnscannedTextFragment(_) => {
+                let transformed_text = &transformed_texts[fragment_i as uint];
+                classes.extend(transformed_text.text.as_slice().chars().map(|ch| bidi::bidi_class(ch)))
+            }
+            _ => classes.push(ON),
+        }
+    }
+
+    let levels = bidi::resolve_levels(classes.as_slice(), paragraph_level);
+    for (fragment_i, transformed_text) in transformed_texts.mut_iter().enumerate() {
+        let start = *fragment_starts.get(fragment_i);
+        let length = match fragments.fragments.get(fragment_i).specific {
+            UnscannedTextFragment(_) => transformed_text.text.as_slice().char_len(),
+            _ => 1,
+        };
+        transformed_text.levels = Vec::from_slice(levels.slice(start, start + length));
+    }
+}
+
 /// A stack-allocated object for scanning an inline flow into `TextRun`-containing `TextFragment`s.
 pub struct TextRunScanner {
     pub clump: Range<CharIndex>,
@@ -48,17 +231,23 @@ impl TextRunScanner {
             debug!("TextRunScanner: scanning {:u} fragments for text runs...", inline.fragments.len());
         }
 
-        let fragments = &mut flow.as_inline().fragments;
+        let inline = flow.as_inline();
+        let paragraph_level = inline.paragraph_level;
+        let fragments = &mut inline.fragments;
+
+        // Whitespace compression and bidi resolution both need to see the whole paragraph, so do
+        // them up front.
+        let mut transformed_texts = transform_fragments(fragments.fragments.as_slice());
+        resolve_bidi_levels(fragments, transformed_texts.as_mut_slice(), paragraph_level);
 
-        let mut last_whitespace = true;
         let mut new_fragments = Vec::new();
         for fragment_i in range(0, fragments.fragments.len()) {
             debug!("TextRunScanner: considering fragment: {:u}", fragment_i);
             if fragment_i > 0 && !can_coalesce_text_nodes(fragments.fragments.as_slice(), fragment_i - 1, fragment_i) {
-                last_whitespace = self.flush_clump_to_list(font_context,
-                                                           fragments.fragments.as_slice(),
-                                                           &mut new_fragments,
-                                                           last_whitespace);
+                self.flush_clump_to_list(font_context,
+                                         fragments.fragments.as_slice(),
+                                         transformed_texts.as_slice(),
+                                         &mut new_fragments);
             }
 
             self.clump.extend_by(CharIndex(1));
@@ -66,10 +255,10 @@ impl TextRunScanner {
 
         // Handle remaining clumps.
         if self.clump.length() > CharIndex(0) {
-            drop(self.flush_clump_to_list(font_context,
-                                          fragments.fragments.as_slice(),
-                                          &mut new_fragments,
-                                          last_whitespace))
+            self.flush_clump_to_list(font_context,
+                                     fragments.fragments.as_slice(),
+                                     transformed_texts.as_slice(),
+                                     &mut new_fragments)
         }
 
         debug!("TextRunScanner: swapping out fragments.");
@@ -84,14 +273,17 @@ impl TextRunScanner {
     /// for correct painting order. Since we compress several leaf fragments here, the mapping must
     /// be adjusted.
     ///
+    /// Text fragments that span more than one bidi level, or contain characters that must be
+    /// rendered with different fonts, are split at those boundaries, since each level and font is
+    /// shaped into a separate text run.
+    ///
     /// FIXME(#2267, pcwalton): Stop cloning fragments. Instead we will need to replace each
     /// `in_fragment` with some smaller stub.
-    pub fn flush_clump_to_list(&mut self,
-                               font_context: &mut FontContext,
-                               in_fragments: &[Fragment],
-                               out_fragments: &mut Vec<Fragment>,
-                               last_whitespace: bool)
-                               -> bool {
+    fn flush_clump_to_list(&mut self,
+                           font_context: &mut FontContext,
+                           in_fragments: &[Fragment],
+                           transformed_texts: &[TransformedText],
+                           out_fragments: &mut Vec<Fragment>) {
         assert!(self.clump.length() > CharIndex(0));
 
         debug!("TextRunScanner: flushing fragments in range={}", self.clump);
@@ -102,7 +294,6 @@ impl TextRunScanner {
             _ => false,
         };
 
-        let mut new_whitespace = last_whitespace;
         match (is_singleton, is_text_clump) {
             (false, false) => {
                 fail!("WAT: can't coalesce non-text nodes in flush_clump_to_list()!")
@@ -110,123 +301,50 @@ impl TextRunScanner {
             (true, false) => {
                 // FIXME(pcwalton): Stop cloning fragments, as above.
                 debug!("TextRunScanner: pushing single non-text fragment in range: {}", self.clump);
-                let new_fragment = in_fragments[self.clump.begin().to_uint()].clone();
+                let fragment_i = self.clump.begin().to_uint();
+                let mut new_fragment = in_fragments[fragment_i].clone();
+                new_fragment.bidi_level = *transformed_texts[fragment_i].levels.get(0);
                 out_fragments.push(new_fragment)
             },
-            (true, true)  => {
-                let old_fragment = &in_fragments[self.clump.begin().to_uint()];
-                let text = match old_fragment.specific {
-                    UnscannedTextFragment(ref text_fragment_info) => &text_fragment_info.text,
-                    _ => fail!("Expected an unscanned text fragment!"),
-                };
-
-                let font_style = old_fragment.font_style();
-                let decoration = old_fragment.text_decoration();
-
-                // TODO(#115): Use the actual CSS `white-space` property of the relevant style.
-                let compression = match old_fragment.white_space() {
-                    white_space::normal => CompressWhitespaceNewline,
-                    white_space::pre => CompressNone,
-                };
-
-                let mut new_line_pos = vec![];
-
-                let (transformed_text, whitespace) = transform_text(text.as_slice(),
-                                                                    compression,
-                                                                    last_whitespace,
-                                                                    &mut new_line_pos);
-
-                new_whitespace = whitespace;
-
-                if transformed_text.len() > 0 {
-                    // TODO(#177): Text run creation must account for the renderability of text by
-                    // font group fonts. This is probably achieved by creating the font group above
-                    // and then letting `FontGroup` decide which `Font` to stick into the text run.
-                    let fontgroup = font_context.get_resolved_font_for_style(&font_style);
-                    let run = box fontgroup.borrow().create_textrun(
-                        transformed_text.clone(), decoration);
-
-                    debug!("TextRunScanner: pushing single text fragment in range: {} ({})",
-                           self.clump,
-                           *text);
-                    let range = Range::new(CharIndex(0), run.char_len());
-                    let new_metrics = run.metrics_for_range(&range);
-                    let new_text_fragment_info = ScannedTextFragmentInfo::new(Arc::new(run), range);
-                    let mut new_fragment = old_fragment.transform(new_metrics.bounding_box.size,
-                                                                  ScannedTextFragment(new_text_fragment_info));
-                    new_fragment.new_line_pos = new_line_pos;
-                    out_fragments.push(new_fragment)
-                }
-            },
-            (false, true) => {
-                // TODO(#177): Text run creation must account for the renderability of text by
-                // font group fonts. This is probably achieved by creating the font group above
-                // and then letting `FontGroup` decide which `Font` to stick into the text run.
+            (_, true) => {
                 let in_fragment = &in_fragments[self.clump.begin().to_uint()];
                 let font_style = in_fragment.font_style();
-                let fontgroup = font_context.get_resolved_font_for_style(&font_style);
-                let decoration = in_fragment.text_decoration();
-
-                // TODO(#115): Use the actual CSS `white-space` property of the relevant style.
-                let compression = match in_fragment.white_space() {
-                    white_space::normal => CompressWhitespaceNewline,
-                    white_space::pre => CompressNone,
+                let lang = match in_fragment.specific {
+                    UnscannedTextFragment(ref info) => info.lang.clone(),
+                    _ => None,
                 };
-
-                let mut new_line_positions: Vec<NewLinePositions> = vec![];
-
-                // First, transform/compress text of all the nodes.
-                let mut last_whitespace_in_clump = new_whitespace;
-                let transformed_strs: Vec<String> = Vec::from_fn(self.clump.length().to_uint(), |i| {
-                    // TODO(#113): We should be passing the compression context between calls to
-                    // `transform_text`, so that fragments starting and/or ending with whitespace can
-                    // be compressed correctly with respect to the text run.
-                    let idx = CharIndex(i as int) + self.clump.begin();
-                    let in_fragment = match in_fragments[idx.to_uint()].specific {
-                        UnscannedTextFragment(ref text_fragment_info) => &text_fragment_info.text,
-                        _ => fail!("Expected an unscanned text fragment!"),
-                    };
-
-                    let mut new_line_pos = vec![];
-
-                    let (new_str, new_whitespace) = transform_text(in_fragment.as_slice(),
-                                                                   compression,
-                                                                   last_whitespace_in_clump,
-                                                                   &mut new_line_pos);
-                    new_line_positions.push(NewLinePositions { new_line_pos: new_line_pos });
-
-                    last_whitespace_in_clump = new_whitespace;
-                    new_str
+                let fontgroup =
+                    font_context.get_resolved_font_for_style_and_language(&font_style,
+                                                                          lang.as_ref());
+                let decoration = in_fragment.text_decoration();
+                let word_break = in_fragment.style().get_inheritedtext().word_break;
+                let shaping_options = in_fragment.shaping_options();
+
+                // First, concatenate the transformed text of all the fragments together, saving
+                // the new character indices and splitting the text wherever the bidi level or the
+                // font that renders the character changes.
+                let clump_texts = transformed_texts.slice(self.clump.begin().to_uint(),
+                                                          self.clump.end().to_uint());
+                let (new_ranges, shaping_runs) = split_into_shaping_runs(clump_texts, |ch| {
+                    font_context.find_font_index_for_char(&fontgroup, ch).unwrap_or(0)
                 });
-                new_whitespace = last_whitespace_in_clump;
-
-                // Next, concatenate all of the transformed strings together, saving the new
-                // character indices.
-                let mut run_str = String::new();
-                let mut new_ranges: Vec<Range<CharIndex>> = vec![];
-                let mut char_total = CharIndex(0);
-                for i in range(0, transformed_strs.len() as int) {
-                    let added_chars = CharIndex(transformed_strs.get(i as uint).as_slice().char_len() as int);
-                    new_ranges.push(Range::new(char_total, added_chars));
-                    run_str.push_str(transformed_strs.get(i as uint).as_slice());
-                    char_total = char_total + added_chars;
-                }
 
-                // Now create the run.
+                // Now create the runs, each shaped with its font in the direction of its level.
                 // TextRuns contain a cycle which is usually resolved by the teardown
                 // sequence. If no clump takes ownership, however, it will leak.
-                let clump = self.clump;
-                let run = if clump.length() != CharIndex(0) && run_str.len() > 0 {
-                    Some(Arc::new(box TextRun::new(
-                        &mut *fontgroup.borrow().fonts.get(0).borrow_mut(),
-                        run_str.into_owned(), decoration)))
-                } else {
-                    None
-                };
-
-                // Make new fragments with the run and adjusted text indices.
+                let runs: Vec<Arc<Box<TextRun>>> = shaping_runs.iter().map(|shaping_run| {
+                    Arc::new(box font_context.create_textrun(&fontgroup,
+                                                             shaping_run.font_index,
+                                                             shaping_run.text.clone(),
+                                                             decoration,
+                                                             shaping_run.level,
+                                                             word_break,
+                                                             &shaping_options))
+                }).collect();
+
+                // Make new fragments with the runs and adjusted text indices.
                 debug!("TextRunScanner: pushing fragment(s) in range: {}", self.clump);
-                for i in clump.each_index() {
+                for i in self.clump.each_index() {
                     let logical_offset = i - self.clump.begin();
                     let range = new_ranges.get(logical_offset.to_uint());
                     if range.length() == CharIndex(0) {
@@ -235,20 +353,39 @@ impl TextRunScanner {
                         continue
                     }
 
-                    let new_text_fragment_info = ScannedTextFragmentInfo::new(run.get_ref().clone(), *range);
-                    let new_metrics = new_text_fragment_info.run.metrics_for_range(range);
-                    let mut new_fragment = in_fragments[i.to_uint()].transform(new_metrics.bounding_box.size,
-                                                                               ScannedTextFragment(new_text_fragment_info));
-                    new_fragment.new_line_pos = new_line_positions.get(logical_offset.to_uint()).new_line_pos.clone();
-                    out_fragments.push(new_fragment)
+                    for (shaping_run, run) in shaping_runs.iter().zip(runs.iter()) {
+                        let piece_range = range.intersect(&shaping_run.range);
+                        if piece_range.is_empty() {
+                            continue
+                        }
+
+                        let mut run_range = piece_range;
+                        run_range.shift_by(-shaping_run.range.begin());
+                        let new_text_fragment_info = ScannedTextFragmentInfo::new(run.clone(),
+                                                                                  run_range);
+                        let new_metrics = run.metrics_for_range(&run_range);
+                        let mut new_fragment = in_fragments[i.to_uint()].transform(new_metrics.bounding_box.size,
+                                                                                   ScannedTextFragment(new_text_fragment_info));
+
+                        // Newline positions are relative to the start of the fragment.
+                        let piece_offset = piece_range.begin() - range.begin();
+                        new_fragment.new_line_pos =
+                            transformed_texts[i.to_uint()].new_line_pos.iter().filter_map(|pos| {
+                                if *pos >= piece_offset && *pos < piece_offset + piece_range.length() {
+                                    Some(*pos - piece_offset)
+                                } else {
+                                    None
+                                }
+                            }).collect();
+                        new_fragment.bidi_level = shaping_run.level;
+                        out_fragments.push(new_fragment)
+                    }
                 }
             }
         } // End of match.
 
         let end = self.clump.end(); // FIXME: borrow checker workaround
         self.clump.reset(end, CharIndex(0));
-
-        new_whitespace
     } // End of `flush_clump_to_list`.
 }
 
@@ -288,6 +425,40 @@ pub fn computed_style_to_font_style(style: &ComputedValues) -> FontStyle {
     }
 }
 
+/// Converts a computed style to the options used to shape text in that style.
+pub fn computed_style_to_shaping_options(style: &ComputedValues) -> ShapingOptions {
+    let font = style.get_font();
+    let mut features = vec!();
+    match font.font_kerning {
+        font_kerning::auto => {}
+        font_kerning::normal => features.push(ShapingFeature::new("kern", 1)),
+        font_kerning::none => features.push(ShapingFeature::new("kern", 0)),
+    }
+
+    // TODO: Synthesize small capitals from scaled-down capitals for fonts without `smcp`.
+    if font.font_variant == font_variant::small_caps {
+        features.push(ShapingFeature::new("smcp", 1))
+    }
+
+    // Optional ligatures would hide the spacing between their letters (CSS Text Level 3 § 8.2).
+    let letter_spacing = style.get_inheritedtext().letter_spacing.unwrap_or(Au(0));
+    if letter_spacing != Au(0) {
+        features.push(ShapingFeature::new("liga", 0));
+        features.push(ShapingFeature::new("clig", 0));
+    }
+
+    // Explicit settings come last so that they override everything above.
+    for setting in font.font_feature_settings.iter() {
+        features.push(ShapingFeature::new(setting.tag.as_slice(), setting.value))
+    }
+
+    ShapingOptions {
+        features: features,
+        letter_spacing: letter_spacing,
+        word_spacing: style.get_inheritedtext().word_spacing.unwrap_or(Au(0)),
+    }
+}
+
 /// Returns the line height needed by the given computed style and font size.
 ///
 /// FIXME(pcwalton): I believe this should not take a separate `font-size` parameter.
@@ -301,3 +472,58 @@ pub fn line_height_from_style(style: &ComputedValues, font_size: Au) -> Au {
     Au::max(from_inline, minimum)
 }
 
+
+#[test]
+fn test_split_into_shaping_runs() {
+    fn transformed_text(text: &str, levels: Vec<Level>) -> TransformedText {
+        TransformedText {
+            text: text.to_string(),
+            new_line_pos: vec!(),
+            levels: levels,
+        }
+    }
+
+    // A font group whose first font covers Latin, whose second covers Greek and whose third
+    // covers everything else.
+    fn find_font_index(ch: char) -> uint {
+        match ch as u32 {
+            0x0000..0x036f => 0,
+            0x0370..0x03ff => 1,
+            _ => 2,
+        }
+    }
+
+    let transformed_texts = vec!(
+        transformed_text("ab \u03b1\u0301\u03b2 ", vec!(0, 0, 0, 0, 0, 0, 0)),
+        transformed_text("\u6f22\u5b57 c", vec!(0, 0, 0, 0)),
+        transformed_text("d", vec!(1)));
+    let (new_ranges, shaping_runs) = split_into_shaping_runs(transformed_texts.as_slice(), |ch| {
+        find_font_index(ch)
+    });
+
+    let new_ranges: Vec<(CharIndex, CharIndex)> =
+        new_ranges.iter().map(|range| (range.begin(), range.length())).collect();
+    assert_eq!(new_ranges,
+               vec!((CharIndex(0), CharIndex(7)),
+                    (CharIndex(7), CharIndex(4)),
+                    (CharIndex(11), CharIndex(1))));
+
+    // The combining acute accent and the spaces after the Greek and Han letters stay in the run of
+    // the preceding letter, even though the Latin font covers them. A change of level starts a
+    // new run even in the same font.
+    let expected = vec!(
+        (0, 0, 0, 3, "ab "),
+        (0, 1, 3, 4, "\u03b1\u0301\u03b2 "),
+        (0, 2, 7, 3, "\u6f22\u5b57 "),
+        (0, 0, 10, 1, "c"),
+        (1, 0, 11, 1, "d"));
+    assert_eq!(shaping_runs.len(), expected.len());
+    for (shaping_run, &(level, font_index, begin, length, text)) in
+            shaping_runs.iter().zip(expected.iter()) {
+        assert_eq!(shaping_run.level, level);
+        assert_eq!(shaping_run.font_index, font_index);
+        assert_eq!(shaping_run.range.begin(), CharIndex(begin));
+        assert_eq!(shaping_run.range.length(), CharIndex(length));
+        assert_eq!(shaping_run.text.as_slice(), text);
+    }
+}
@@ -37,12 +37,21 @@ use css::node_style::StyledNode;
 use layout::util::LayoutDataWrapper;
 
 use script::dom::bindings::codegen::InheritTypes::{HTMLIFrameElementDerived};
-use script::dom::bindings::codegen::InheritTypes::{HTMLImageElementDerived, TextDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLImageElementDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLInputElementDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLOptionElementDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLSelectElementDerived};
+use script::dom::bindings::codegen::InheritTypes::{HTMLTextAreaElementDerived, TextDerived};
 use script::dom::bindings::js::JS;
 use script::dom::element::{Element, HTMLAreaElementTypeId, HTMLAnchorElementTypeId};
 use script::dom::element::{HTMLLinkElementTypeId, LayoutElementHelpers, RawLayoutElementHelpers};
 use script::dom::htmliframeelement::HTMLIFrameElement;
 use script::dom::htmlimageelement::{HTMLImageElement, LayoutHTMLImageElementHelpers};
+use script::dom::htmlinputelement::{HTMLInputElement, InputType};
+use script::dom::htmlinputelement::{LayoutHTMLInputElementHelpers};
+use script::dom::htmloptionelement::{HTMLOptionElement, LayoutHTMLOptionElementHelpers};
+use script::dom::htmlselectelement::{HTMLSelectElement, LayoutHTMLSelectElementHelpers};
+use script::dom::htmltextareaelement::{HTMLTextAreaElement, LayoutHTMLTextAreaElementHelpers};
 use script::dom::node::{DocumentNodeTypeId, ElementNodeTypeId, Node, NodeTypeId};
 use script::dom::node::{LayoutNodeHelpers, RawLayoutNodeHelpers, TextNodeTypeId};
 use script::dom::text::Text;
@@ -117,6 +126,126 @@ pub trait TLayoutNode {
         }
     }
 
+    /// If this is an `<input>` element, returns its type. Fails otherwise.
+    fn input_type(&self) -> InputType {
+        unsafe {
+            if !self.get().is_htmlinputelement() {
+                fail!("not an input element!")
+            }
+            let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+            input_element.get_input_type_for_layout()
+        }
+    }
+
+    /// If this is an `<input>` element, returns whether it is checked. Fails otherwise.
+    fn input_checked(&self) -> bool {
+        unsafe {
+            if !self.get().is_htmlinputelement() {
+                fail!("not an input element!")
+            }
+            let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+            input_element.get_checked_state_for_layout()
+        }
+    }
+
+    /// If this is an `<input>` or a `<textarea>` element, returns the text that it shows. Fails
+    /// otherwise.
+    fn form_control_value(&self) -> String {
+        unsafe {
+            if self.get().is_htmlinputelement() {
+                let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+                input_element.get_value_for_layout()
+            } else if self.get().is_htmltextareaelement() {
+                let textarea_element: JS<HTMLTextAreaElement> =
+                    self.get_jsmanaged().transmute_copy();
+                textarea_element.get_value_for_layout()
+            } else {
+                fail!("not a form control!")
+            }
+        }
+    }
+
+    /// If this is an `<input>` or a `<textarea>` element, returns the position of its caret in
+    /// the text that it shows, or `None` if it doesn't have the focus. Fails otherwise.
+    fn form_control_caret(&self) -> Option<uint> {
+        unsafe {
+            if self.get().is_htmlinputelement() {
+                let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+                input_element.get_caret_for_layout()
+            } else if self.get().is_htmltextareaelement() {
+                let textarea_element: JS<HTMLTextAreaElement> =
+                    self.get_jsmanaged().transmute_copy();
+                textarea_element.get_caret_for_layout()
+            } else {
+                fail!("not a form control!")
+            }
+        }
+    }
+
+    /// If this is an `<input>` or a `<textarea>` element, returns the number of columns and rows
+    /// of text that it is sized for. Fails otherwise.
+    fn text_control_size(&self) -> (u32, u32) {
+        unsafe {
+            if self.get().is_htmlinputelement() {
+                let input_element: JS<HTMLInputElement> = self.get_jsmanaged().transmute_copy();
+                (input_element.get_size_for_layout(), 1)
+            } else if self.get().is_htmltextareaelement() {
+                let textarea_element: JS<HTMLTextAreaElement> =
+                    self.get_jsmanaged().transmute_copy();
+                (textarea_element.get_cols_for_layout(), textarea_element.get_rows_for_layout())
+            } else {
+                fail!("not a form control!")
+            }
+        }
+    }
+
+    /// If this is an `<option>` element, returns whether it is selected. Fails otherwise.
+    fn option_selected(&self) -> bool {
+        unsafe {
+            if !self.get().is_htmloptionelement() {
+                fail!("not an option element!")
+            }
+            let option_element: JS<HTMLOptionElement> = self.get_jsmanaged().transmute_copy();
+            option_element.get_selectedness_for_layout()
+        }
+    }
+
+    /// If this is a `<select>` element, returns the number of options that it shows at once: 1
+    /// for a drop-down box, and more for a list box. Fails otherwise.
+    fn select_display_size(&self) -> u32 {
+        unsafe {
+            if !self.get().is_htmlselectelement() {
+                fail!("not a select element!")
+            }
+            let select_element: JS<HTMLSelectElement> = self.get_jsmanaged().transmute_copy();
+            select_element.get_display_size_for_layout()
+        }
+    }
+
+    /// If this is a `<select>` element, returns the index of the option that its list of options
+    /// highlights, or `None` if the list isn't open. Fails otherwise.
+    fn select_list_highlight(&self) -> Option<uint> {
+        unsafe {
+            if !self.get().is_htmlselectelement() {
+                fail!("not a select element!")
+            }
+            let select_element: JS<HTMLSelectElement> = self.get_jsmanaged().transmute_copy();
+            select_element.get_list_highlight_for_layout()
+        }
+    }
+
+    /// If this is a `<select>` element, returns the index of the first option that its list box
+    /// shows. Fails otherwise.
+    fn select_list_scroll(&self) -> uint {
+        unsafe {
+            if !self.get().is_htmlselectelement() {
+                fail!("not a select element!")
+            }
+            let select_element: JS<HTMLSelectElement> = self.get_jsmanaged().transmute_copy();
+            select_element.get_list_scroll_for_layout()
+        }
+    }
+
     /// If this is a text node, copies out the text. If this is not a text node, fails.
     ///
     /// FIXME(pcwalton): Don't copy text. Atomically reference count instead.
@@ -392,6 +521,24 @@ impl<'le> TElement for LayoutElement<'le> {
             self.element.node.get_hover_state_for_layout()
         }
     }
+
+    fn get_focus_state(&self) -> bool {
+        unsafe {
+            self.element.node.get_focus_state_for_layout()
+        }
+    }
+
+    fn get_valid_state(&self) -> bool {
+        unsafe {
+            self.element.node.get_valid_state_for_layout()
+        }
+    }
+
+    fn get_invalid_state(&self) -> bool {
+        unsafe {
+            self.element.node.get_invalid_state_for_layout()
+        }
+    }
 }
 
 fn get_content(content_list: &content::T) -> String {
@@ -576,6 +723,77 @@ impl<'ln> ThreadSafeLayoutNode<'ln> {
         display == display::block
     }
 
+    /// Returns the language of this node: the value of the `lang` attribute of the nearest
+    /// inclusive ancestor element that has one, if any.
+    ///
+    /// Attributes cannot change during layout, so walking up the tree here does not race.
+    pub fn language(&self) -> Option<String> {
+        let mut current_node = Some(self.node.clone());
+        loop {
+            let node = match current_node {
+                Some(node) => node,
+                None => return None,
+            };
+            if node.is_element() {
+                match node.as_element().get_attr(&namespace::Null, "lang") {
+                    Some(lang) => return Some(lang.to_string()),
+                    None => {}
+                }
+            }
+            current_node = node.parent_node()
+        }
+    }
+
+    /// Returns true if this is an element whose parent is a flex container, which makes it a
+    /// flex item whose `display` is blockified.
+    ///
+    /// The parent is not constructed until all of its children are, and styles cannot change
+    /// during layout, so reading the parent's style here does not race.
+    pub fn is_flex_item(&self) -> bool {
+        if self.pseudo != Normal || !self.node.is_element() {
+            return false
+        }
+        let parent = match self.node.parent_node() {
+            Some(parent) if parent.is_element() => parent,
+            _ => return false,
+        };
+        unsafe {
+            let layout_data: *Option<LayoutDataWrapper> =
+                mem::transmute(parent.get().layout_data.borrow_unchecked());
+            match *layout_data {
+                Some(ref layout_data) => {
+                    match layout_data.shared_data.style {
+                        Some(ref style) => {
+                            match style.get_box().display {
+                                display::flex | display::inline_flex => true,
+                                _ => false,
+                            }
+                        }
+                        None => false,
+                    }
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// Returns the items of the `content` property of this pseudo-element. Returns an empty list
+    /// if this is not a pseudo-element or if its content is `normal` or `none`.
+    pub fn generated_content(&self) -> Vec<content::Content> {
+        let layout_data_ref = self.borrow_layout_data();
+        let node_layout_data_wrapper = layout_data_ref.get_ref();
+
+        let style = match self.pseudo {
+            Before | BeforeBlock => node_layout_data_wrapper.data.before_style.get_ref(),
+            After | AfterBlock => node_layout_data_wrapper.data.after_style.get_ref(),
+            Normal => return vec!(),
+        };
+        match style.get_box().content {
+            content::Content(ref items) => items.clone(),
+            content::normal | content::none => vec!(),
+        }
+    }
+
     pub fn has_before_pseudo(&self) -> bool {
         let layout_data_wrapper = self.borrow_layout_data();
         let layout_data_wrapper_ref = layout_data_wrapper.get_ref();
@@ -9,17 +9,17 @@ use geom::size::TypedSize2D;
 use gfx::render_task::{PaintPermissionGranted, PaintPermissionRevoked};
 use gfx::render_task::{RenderChan, RenderTask};
 use script::layout_interface::LayoutChan;
-use script::script_task::LoadMsg;
+use script::script_task::{LoadFromHistoryMsg, LoadMsg};
 use script::script_task::{AttachLayoutMsg, NewLayoutInfo, ScriptTask, ScriptChan};
 use script::script_task;
 use servo_msg::constellation_msg::{ConstellationChan, Failure, PipelineId, SubpageId};
 use servo_net::image_cache_task::ImageCacheTask;
-use servo_net::resource_task::ResourceTask;
+use servo_net::resource_task::{LoadData, ResourceTask};
+use servo_net::storage_task::StorageTask;
 use servo_util::geometry::PagePx;
 use servo_util::opts::Opts;
 use servo_util::time::ProfilerChan;
 use std::rc::Rc;
-use url::Url;
 
 /// A uniquely-identifiable pipeline of script task, layout task, and render task.
 pub struct Pipeline {
@@ -30,8 +30,8 @@ pub struct Pipeline {
     pub render_chan: RenderChan,
     pub layout_shutdown_port: Receiver<()>,
     pub render_shutdown_port: Receiver<()>,
-    /// The most recently loaded url
-    pub url: Url,
+    /// The most recently loaded page, and how it was requested.
+    pub load_data: LoadData,
 }
 
 /// The subset of the pipeline that is needed for layer composition.
@@ -50,10 +50,11 @@ impl Pipeline {
                        constellation_chan: ConstellationChan,
                        compositor_chan: CompositorChan,
                        image_cache_task: ImageCacheTask,
+                       resource_task: ResourceTask,
                        profiler_chan: ProfilerChan,
                        opts: Opts,
                        script_pipeline: Rc<Pipeline>,
-                       url: Url)
+                       load_data: LoadData)
                        -> Pipeline {
         let (layout_port, layout_chan) = LayoutChan::new();
         let (render_port, render_chan) = RenderChan::new();
@@ -82,6 +83,7 @@ impl Pipeline {
                            script_pipeline.script_chan.clone(),
                            render_chan.clone(),
                            image_cache_task.clone(),
+                           resource_task,
                            opts.clone(),
                            profiler_chan,
                            layout_shutdown_chan);
@@ -103,7 +105,7 @@ impl Pipeline {
                       render_chan,
                       layout_shutdown_port,
                       render_shutdown_port,
-                      url)
+                      load_data)
     }
 
     pub fn create(id: PipelineId,
@@ -112,10 +114,11 @@ impl Pipeline {
                   compositor_chan: CompositorChan,
                   image_cache_task: ImageCacheTask,
                   resource_task: ResourceTask,
+                  storage_task: StorageTask,
                   profiler_chan: ProfilerChan,
                   window_size: TypedSize2D<PagePx, f32>,
                   opts: Opts,
-                  url: Url)
+                  load_data: LoadData)
                   -> Pipeline {
         let (script_port, script_chan) = ScriptChan::new();
         let (layout_port, layout_chan) = LayoutChan::new();
@@ -129,7 +132,7 @@ impl Pipeline {
                                      render_chan.clone(),
                                      layout_shutdown_port,
                                      render_shutdown_port,
-                                     url);
+                                     load_data);
 
         let failure = Failure {
             pipeline_id: id,
@@ -143,7 +146,8 @@ impl Pipeline {
                            script_chan.clone(),
                            constellation_chan.clone(),
                            failure.clone(),
-                           resource_task,
+                           resource_task.clone(),
+                           storage_task,
                            image_cache_task.clone(),
                            window_size);
 
@@ -164,6 +168,7 @@ impl Pipeline {
                            script_chan.clone(),
                            render_chan.clone(),
                            image_cache_task,
+                           resource_task,
                            opts.clone(),
                            profiler_chan,
                            layout_shutdown_chan);
@@ -178,7 +183,7 @@ impl Pipeline {
                render_chan: RenderChan,
                layout_shutdown_port: Receiver<()>,
                render_shutdown_port: Receiver<()>,
-               url: Url)
+               load_data: LoadData)
                -> Pipeline {
         Pipeline {
             id: id,
@@ -188,13 +193,20 @@ impl Pipeline {
             render_chan: render_chan,
             layout_shutdown_port: layout_shutdown_port,
             render_shutdown_port: render_shutdown_port,
-            url: url,
+            load_data: load_data,
         }
     }
 
     pub fn load(&self) {
         let ScriptChan(ref chan) = self.script_chan;
-        chan.send(LoadMsg(self.id, self.url.clone()));
+        chan.send(LoadMsg(self.id, self.load_data.clone()));
+    }
+
+    /// Shows the document this pipeline loaded earlier again, when the session history returns
+    /// to it.
+    pub fn load_from_history(&self) {
+        let ScriptChan(ref chan) = self.script_chan;
+        chan.send(LoadFromHistoryMsg(self.id));
     }
 
     pub fn grant_paint_permission(&self) {
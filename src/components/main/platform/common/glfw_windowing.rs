@@ -7,8 +7,9 @@
 use windowing::{ApplicationMethods, WindowEvent, WindowMethods};
 use windowing::{IdleWindowEvent, ResizeWindowEvent, LoadUrlWindowEvent, MouseWindowEventClass,  MouseWindowMoveEventClass};
 use windowing::{ScrollWindowEvent, ZoomWindowEvent, NavigationWindowEvent, FinishedWindowEvent};
+use windowing::PinchZoomWindowEvent;
 use windowing::{QuitWindowEvent, MouseWindowClickEvent, MouseWindowMouseDownEvent, MouseWindowMouseUpEvent};
-use windowing::RefreshWindowEvent;
+use windowing::{RefreshWindowEvent, KeyWindowEvent};
 use windowing::{Forward, Back};
 
 use alert::{Alert, AlertMethods};
@@ -24,6 +25,8 @@ use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use servo_msg::compositor_msg::{IdleRenderState, RenderState, RenderingRenderState};
 use servo_msg::compositor_msg::{FinishedLoading, Blank, Loading, PerformingLayout, ReadyState};
+use servo_msg::constellation_msg::{Key, KeyModifiers, KeyState, Pressed, Released, Repeated};
+use servo_msg::constellation_msg;
 use servo_util::geometry::{ScreenPx, DevicePixel};
 
 use glfw;
@@ -101,14 +104,26 @@ pub struct Window {
     pub render_state: Cell<RenderState>,
 
     pub last_title_set_time: Cell<Timespec>,
+
+    /// A key that went down or repeated, held back until GLFW reports the character it types,
+    /// which follows the key.
+    pub pending_key_event: Cell<Option<(Key, KeyState, KeyModifiers)>>,
+    /// The characters typed by the keys that are down, which their releases report too.
+    pub pressed_key_chars: RefCell<Vec<(Key, char)>>,
+    /// The keys that went down as browser shortcuts, whose releases the page doesn't see.
+    pub shortcut_keys: RefCell<Vec<glfw::Key>>,
 }
 
 impl WindowMethods<Application> for Window {
     /// Creates a new window.
-    fn new(app: &Application, is_foreground: bool) -> Rc<Window> {
+    fn new(app: &Application, is_foreground: bool, size: TypedSize2D<ScreenPx, uint>)
+           -> Rc<Window> {
         // Create the GLFW window.
         app.glfw.window_hint(glfw::Visible(is_foreground));
-        let (glfw_window, events) = app.glfw.create_window(800, 600, "Servo", glfw::Windowed)
+        let (glfw_window, events) = app.glfw.create_window(size.width.get() as u32,
+                                                           size.height.get() as u32,
+                                                           "Servo",
+                                                           glfw::Windowed)
             .expect("Failed to create GLFW window");
         glfw_window.make_current();
 
@@ -130,12 +145,17 @@ impl WindowMethods<Application> for Window {
             render_state: Cell::new(IdleRenderState),
 
             last_title_set_time: Cell::new(Timespec::new(0, 0)),
+
+            pending_key_event: Cell::new(None),
+            pressed_key_chars: RefCell::new(vec!()),
+            shortcut_keys: RefCell::new(vec!()),
         };
 
         // Register event handlers.
         window.glfw_window.set_framebuffer_size_polling(true);
         window.glfw_window.set_refresh_polling(true);
         window.glfw_window.set_key_polling(true);
+        window.glfw_window.set_char_polling(true);
         window.glfw_window.set_mouse_button_polling(true);
         window.glfw_window.set_cursor_pos_polling(true);
         window.glfw_window.set_scroll_polling(true);
@@ -174,6 +194,7 @@ impl WindowMethods<Application> for Window {
         for (_, event) in glfw::flush_messages(&self.events) {
             self.handle_window_event(&self.glfw_window, event);
         }
+        self.flush_pending_key_event();
 
         if self.glfw_window.should_close() {
             QuitWindowEvent
@@ -210,10 +231,65 @@ impl WindowMethods<Application> for Window {
 
 impl Window {
     fn handle_window_event(&self, window: &glfw::Window, event: glfw::WindowEvent) {
+        match event {
+            glfw::CharEvent(_) => {}
+            _ => self.flush_pending_key_event(),
+        }
+
         match event {
             glfw::KeyEvent(key, _, action, mods) => {
-                if action == glfw::Press {
-                    self.handle_key(key, mods)
+                if action != glfw::Release && self.handle_key(key, mods) {
+                    let mut shortcut_keys = self.shortcut_keys.borrow_mut();
+                    if !shortcut_keys.contains(&key) {
+                        shortcut_keys.push(key);
+                    }
+                    return
+                }
+                if action == glfw::Release {
+                    let mut shortcut_keys = self.shortcut_keys.borrow_mut();
+                    match shortcut_keys.iter().position(|&shortcut_key| shortcut_key == key) {
+                        Some(index) => {
+                            shortcut_keys.remove(index);
+                            return
+                        }
+                        None => {}
+                    }
+                }
+
+                let state = match action {
+                    glfw::Press => Pressed,
+                    glfw::Release => Released,
+                    glfw::Repeat => Repeated,
+                };
+                let (key, mods) = (glfw_key_to_key(key), glfw_mods_to_mods(mods));
+                if state != Released {
+                    self.pending_key_event.set(Some((key, state, mods)));
+                    return
+                }
+
+                // The release reports the character that the press typed.
+                let mut pressed_key_chars = self.pressed_key_chars.borrow_mut();
+                let index = pressed_key_chars.iter().position(|&(pressed_key, _)| {
+                    pressed_key == key
+                });
+                let c = index.map(|index| {
+                    let (_, c) = pressed_key_chars.remove(index).unwrap();
+                    c
+                });
+                self.event_queue.borrow_mut().push(KeyWindowEvent(key, state, mods, c));
+            },
+            glfw::CharEvent(c) => {
+                match self.pending_key_event.get() {
+                    Some((key, state, mods)) => {
+                        self.pending_key_event.set(None);
+                        let mut pressed_key_chars = self.pressed_key_chars.borrow_mut();
+                        if !pressed_key_chars.iter().any(|&(pressed_key, _)| pressed_key == key) {
+                            pressed_key_chars.push((key, c));
+                        }
+                        self.event_queue.borrow_mut().push(KeyWindowEvent(key, state, mods,
+                                                                          Some(c)));
+                    }
+                    None => {}
                 }
             },
             glfw::FramebufferSizeEvent(width, height) => {
@@ -258,6 +334,17 @@ impl Window {
         }
     }
 
+    /// Sends on a key that went down or repeated without typing a character.
+    fn flush_pending_key_event(&self) {
+        match self.pending_key_event.get() {
+            Some((key, state, mods)) => {
+                self.pending_key_event.set(None);
+                self.event_queue.borrow_mut().push(KeyWindowEvent(key, state, mods, None));
+            }
+            None => {}
+        }
+    }
+
     /// Helper function to set the window title in accordance with the ready state.
     fn update_window_title(&self) {
         let now = time::get_time();
@@ -289,25 +376,36 @@ impl Window {
         }
     }
 
-    /// Helper function to handle keyboard events.
-    fn handle_key(&self, key: glfw::Key, mods: glfw::Modifiers) {
+    /// Helper function to handle the keyboard shortcuts of the browser chrome. Returns false if the
+    /// key is not a shortcut, in which case it is sent on to the page. The shortcuts are all
+    /// chords, so that the keys that pages use on their own always reach them.
+    fn handle_key(&self, key: glfw::Key, mods: glfw::Modifiers) -> bool {
         match key {
-            glfw::KeyEscape => self.glfw_window.set_should_close(true),
+            glfw::KeyQ if mods.contains(glfw::Control) => { // Ctrl+Q
+                self.glfw_window.set_should_close(true)
+            }
             glfw::KeyL if mods.contains(glfw::Control) => self.load_url(), // Ctrl+L
+            glfw::KeyEqual if mods.contains(glfw::Control | glfw::Alt) => { // Ctrl-Alt-+
+                self.event_queue.borrow_mut().push(PinchZoomWindowEvent(1.1));
+            }
+            glfw::KeyMinus if mods.contains(glfw::Control | glfw::Alt) => { // Ctrl-Alt--
+                self.event_queue.borrow_mut().push(PinchZoomWindowEvent(0.90909090909));
+            }
             glfw::KeyEqual if mods.contains(glfw::Control) => { // Ctrl-+
                 self.event_queue.borrow_mut().push(ZoomWindowEvent(1.1));
             }
             glfw::KeyMinus if mods.contains(glfw::Control) => { // Ctrl--
                 self.event_queue.borrow_mut().push(ZoomWindowEvent(0.90909090909));
             }
-            glfw::KeyBackspace if mods.contains(glfw::Shift) => { // Shift-Backspace
+            glfw::KeyRight if mods.contains(glfw::Alt) => { // Alt-Right
                 self.event_queue.borrow_mut().push(NavigationWindowEvent(Forward));
             }
-            glfw::KeyBackspace => { // Backspace
+            glfw::KeyLeft if mods.contains(glfw::Alt) => { // Alt-Left
                 self.event_queue.borrow_mut().push(NavigationWindowEvent(Back));
             }
-            _ => {}
+            _ => return false,
         }
+        true
     }
 
     /// Helper function to handle a click
@@ -355,3 +453,110 @@ impl Window {
         }
     }
 }
+
+fn glfw_mods_to_mods(mods: glfw::Modifiers) -> KeyModifiers {
+    let mut result = KeyModifiers::empty();
+    if mods.contains(glfw::Shift) {
+        result.insert(constellation_msg::Shift);
+    }
+    if mods.contains(glfw::Control) {
+        result.insert(constellation_msg::Control);
+    }
+    if mods.contains(glfw::Alt) {
+        result.insert(constellation_msg::Alt);
+    }
+    if mods.contains(glfw::Super) {
+        result.insert(constellation_msg::Super);
+    }
+    result
+}
+
+fn glfw_key_to_key(key: glfw::Key) -> Key {
+    match key {
+        glfw::KeySpace => constellation_msg::KeySpace,
+        glfw::KeyApostrophe => constellation_msg::KeyApostrophe,
+        glfw::KeyComma => constellation_msg::KeyComma,
+        glfw::KeyMinus => constellation_msg::KeyMinus,
+        glfw::KeyPeriod => constellation_msg::KeyPeriod,
+        glfw::KeySlash => constellation_msg::KeySlash,
+        glfw::Key0 => constellation_msg::Key0,
+        glfw::Key1 => constellation_msg::Key1,
+        glfw::Key2 => constellation_msg::Key2,
+        glfw::Key3 => constellation_msg::Key3,
+        glfw::Key4 => constellation_msg::Key4,
+        glfw::Key5 => constellation_msg::Key5,
+        glfw::Key6 => constellation_msg::Key6,
+        glfw::Key7 => constellation_msg::Key7,
+        glfw::Key8 => constellation_msg::Key8,
+        glfw::Key9 => constellation_msg::Key9,
+        glfw::KeySemicolon => constellation_msg::KeySemicolon,
+        glfw::KeyEqual => constellation_msg::KeyEqual,
+        glfw::KeyA => constellation_msg::KeyA,
+        glfw::KeyB => constellation_msg::KeyB,
+        glfw::KeyC => constellation_msg::KeyC,
+        glfw::KeyD => constellation_msg::KeyD,
+        glfw::KeyE => constellation_msg::KeyE,
+        glfw::KeyF => constellation_msg::KeyF,
+        glfw::KeyG => constellation_msg::KeyG,
+        glfw::KeyH => constellation_msg::KeyH,
+        glfw::KeyI => constellation_msg::KeyI,
+        glfw::KeyJ => constellation_msg::KeyJ,
+        glfw::KeyK => constellation_msg::KeyK,
+        glfw::KeyL => constellation_msg::KeyL,
+        glfw::KeyM => constellation_msg::KeyM,
+        glfw::KeyN => constellation_msg::KeyN,
+        glfw::KeyO => constellation_msg::KeyO,
+        glfw::KeyP => constellation_msg::KeyP,
+        glfw::KeyQ => constellation_msg::KeyQ,
+        glfw::KeyR => constellation_msg::KeyR,
+        glfw::KeyS => constellation_msg::KeyS,
+        glfw::KeyT => constellation_msg::KeyT,
+        glfw::KeyU => constellation_msg::KeyU,
+        glfw::KeyV => constellation_msg::KeyV,
+        glfw::KeyW => constellation_msg::KeyW,
+        glfw::KeyX => constellation_msg::KeyX,
+        glfw::KeyY => constellation_msg::KeyY,
+        glfw::KeyZ => constellation_msg::KeyZ,
+        glfw::KeyLeftBracket => constellation_msg::KeyLeftBracket,
+        glfw::KeyBackslash => constellation_msg::KeyBackslash,
+        glfw::KeyRightBracket => constellation_msg::KeyRightBracket,
+        glfw::KeyGraveAccent => constellation_msg::KeyGraveAccent,
+        glfw::KeyEscape => constellation_msg::KeyEscape,
+        glfw::KeyEnter => constellation_msg::KeyEnter,
+        glfw::KeyTab => constellation_msg::KeyTab,
+        glfw::KeyBackspace => constellation_msg::KeyBackspace,
+        glfw::KeyInsert => constellation_msg::KeyInsert,
+        glfw::KeyDelete => constellation_msg::KeyDelete,
+        glfw::KeyRight => constellation_msg::KeyRight,
+        glfw::KeyLeft => constellation_msg::KeyLeft,
+        glfw::KeyDown => constellation_msg::KeyDown,
+        glfw::KeyUp => constellation_msg::KeyUp,
+        glfw::KeyPageUp => constellation_msg::KeyPageUp,
+        glfw::KeyPageDown => constellation_msg::KeyPageDown,
+        glfw::KeyHome => constellation_msg::KeyHome,
+        glfw::KeyEnd => constellation_msg::KeyEnd,
+        glfw::KeyCapsLock => constellation_msg::KeyCapsLock,
+        glfw::KeyF1 => constellation_msg::KeyF1,
+        glfw::KeyF2 => constellation_msg::KeyF2,
+        glfw::KeyF3 => constellation_msg::KeyF3,
+        glfw::KeyF4 => constellation_msg::KeyF4,
+        glfw::KeyF5 => constellation_msg::KeyF5,
+        glfw::KeyF6 => constellation_msg::KeyF6,
+        glfw::KeyF7 => constellation_msg::KeyF7,
+        glfw::KeyF8 => constellation_msg::KeyF8,
+        glfw::KeyF9 => constellation_msg::KeyF9,
+        glfw::KeyF10 => constellation_msg::KeyF10,
+        glfw::KeyF11 => constellation_msg::KeyF11,
+        glfw::KeyF12 => constellation_msg::KeyF12,
+        glfw::KeyLeftShift => constellation_msg::KeyLeftShift,
+        glfw::KeyLeftControl => constellation_msg::KeyLeftControl,
+        glfw::KeyLeftAlt => constellation_msg::KeyLeftAlt,
+        glfw::KeyLeftSuper => constellation_msg::KeyLeftSuper,
+        glfw::KeyRightShift => constellation_msg::KeyRightShift,
+        glfw::KeyRightControl => constellation_msg::KeyRightControl,
+        glfw::KeyRightAlt => constellation_msg::KeyRightAlt,
+        glfw::KeyRightSuper => constellation_msg::KeyRightSuper,
+        glfw::KeyMenu => constellation_msg::KeyMenu,
+        _ => constellation_msg::KeyUnidentified,
+    }
+}
@@ -8,7 +8,7 @@ use windowing::{ApplicationMethods, WindowEvent, WindowMethods};
 use windowing::{IdleWindowEvent, ResizeWindowEvent, LoadUrlWindowEvent, MouseWindowEventClass};
 use windowing::{ScrollWindowEvent, ZoomWindowEvent, NavigationWindowEvent, FinishedWindowEvent};
 use windowing::{MouseWindowClickEvent, MouseWindowMouseDownEvent, MouseWindowMouseUpEvent};
-use windowing::{Forward, Back};
+use windowing::{Forward, Back, KeyWindowEvent, PinchZoomWindowEvent, QuitWindowEvent};
 
 use alert::{Alert, AlertMethods};
 use libc::{c_int, c_uchar};
@@ -19,9 +19,11 @@ use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use servo_msg::compositor_msg::{IdleRenderState, RenderState, RenderingRenderState};
 use servo_msg::compositor_msg::{FinishedLoading, Blank, ReadyState};
+use servo_msg::constellation_msg::{Key, KeyModifiers, Pressed, Released};
+use servo_msg::constellation_msg;
 use servo_util::geometry::{ScreenPx, DevicePixel};
 
-use glut::glut::{ACTIVE_SHIFT, DOUBLE, WindowHeight};
+use glut::glut::{ACTIVE_ALT, ACTIVE_CTRL, ACTIVE_SHIFT, DOUBLE, WindowHeight};
 use glut::glut::WindowWidth;
 use glut::glut;
 
@@ -62,9 +64,9 @@ pub struct Window {
 
 impl WindowMethods<Application> for Window {
     /// Creates a new window.
-    fn new(_: &Application, _: bool) -> Rc<Window> {
+    fn new(_: &Application, _: bool, size: TypedSize2D<ScreenPx, uint>) -> Rc<Window> {
         // Create the GLUT window.
-        glut::init_window_size(800, 600);
+        glut::init_window_size(size.width.get() as c_int, size.height.get() as c_int);
         let glut_window = glut::create_window("Servo".to_string());
 
         // Create our window object.
@@ -110,6 +112,14 @@ impl WindowMethods<Application> for Window {
             }
         }
         glut::keyboard_func(box KeyboardCallbackState);
+        struct SpecialCallbackState;
+        impl glut::SpecialCallback for SpecialCallbackState {
+            fn call(&self, key: c_int, _x: c_int, _y: c_int) {
+                let tmp = local_window();
+                tmp.handle_special_key(key)
+            }
+        }
+        glut::special_func(box SpecialCallbackState);
         struct MouseCallbackState;
         impl glut::MouseCallback for MouseCallbackState {
             fn call(&self, button: c_int, state: c_int, x: c_int, y: c_int) {
@@ -221,25 +231,62 @@ impl Window {
     //     }
     // }
 
-    /// Helper function to handle keyboard events.
+    /// Helper function to handle keyboard events. As in the GLFW backend, browser shortcuts are
+    /// chords with Ctrl or Alt, so that every other key can be typed into the page.
     fn handle_key(&self, key: u8) {
         debug!("got key: {}", key);
         let modifiers = glut::get_modifiers();
+        let ctrl = (modifiers & ACTIVE_CTRL) != 0;
+        let alt = (modifiers & ACTIVE_ALT) != 0;
         match key {
-            42 => self.load_url(),
-            43 => self.event_queue.borrow_mut().push(ZoomWindowEvent(1.1)),
-            45 => self.event_queue.borrow_mut().push(ZoomWindowEvent(0.909090909)),
-            56 => self.event_queue.borrow_mut().push(ScrollWindowEvent(TypedPoint2D(0.0, 5.0 as f32),
-                                                                       TypedPoint2D(0.0 as i32, 5.0 as i32))),
-            50 => self.event_queue.borrow_mut().push(ScrollWindowEvent(TypedPoint2D(0.0, -5.0 as f32),
-                                                                       TypedPoint2D(0.0 as i32, -5.0 as i32))),
-            127 => {
-                if (modifiers & ACTIVE_SHIFT) != 0 {
-                    self.event_queue.borrow_mut().push(NavigationWindowEvent(Forward));
+            // GLUT reports Ctrl with a letter as the corresponding control character.
+            17 if ctrl => { // Ctrl+Q
+                self.event_queue.borrow_mut().push(QuitWindowEvent);
+            }
+            12 if ctrl => self.load_url(), // Ctrl+L
+            43 | 61 if ctrl && alt => { // Ctrl-Alt-+
+                self.event_queue.borrow_mut().push(PinchZoomWindowEvent(1.1));
+            }
+            45 if ctrl && alt => { // Ctrl-Alt--
+                self.event_queue.borrow_mut().push(PinchZoomWindowEvent(0.90909090909));
+            }
+            43 | 61 if ctrl => { // Ctrl-+
+                self.event_queue.borrow_mut().push(ZoomWindowEvent(1.1));
+            }
+            45 if ctrl => { // Ctrl--
+                self.event_queue.borrow_mut().push(ZoomWindowEvent(0.90909090909));
+            }
+            _ => {
+                // GLUT only tells us about key presses, so follow each one with a release.
+                let c = key as char;
+                let (key, shift) = Key::from_char(c);
+                let mut mods = KeyModifiers::empty();
+                if shift || (modifiers & ACTIVE_SHIFT) != 0 {
+                    mods.insert(constellation_msg::Shift);
                 }
-                else {
-                    self.event_queue.borrow_mut().push(NavigationWindowEvent(Back));
+                if ctrl {
+                    mods.insert(constellation_msg::Control);
                 }
+                if alt {
+                    mods.insert(constellation_msg::Alt);
+                }
+                let c = Some(c).filtered(|c| !c.is_control());
+                self.event_queue.borrow_mut().push(KeyWindowEvent(key, Pressed, mods, c));
+                self.event_queue.borrow_mut().push(KeyWindowEvent(key, Released, mods, c));
+            }
+        }
+    }
+
+    /// Helper function to handle keys that do not type characters, such as the arrow keys.
+    fn handle_special_key(&self, key: c_int) {
+        debug!("got special key: {}", key);
+        let alt = (glut::get_modifiers() & ACTIVE_ALT) != 0;
+        match key {
+            102 if alt => { // Alt-Right
+                self.event_queue.borrow_mut().push(NavigationWindowEvent(Forward));
+            }
+            100 if alt => { // Alt-Left
+                self.event_queue.borrow_mut().push(NavigationWindowEvent(Back));
             }
             _ => {}
         }
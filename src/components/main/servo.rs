@@ -57,6 +57,7 @@ use servo_msg::constellation_msg::{ConstellationChan, InitLoadUrlMsg};
 
 use servo_net::image_cache_task::{ImageCacheTask, SyncImageCacheTask};
 use servo_net::resource_task::ResourceTask;
+use servo_net::storage_task::StorageTask;
 use servo_util::time::Profiler;
 
 use servo_util::opts;
@@ -89,11 +90,13 @@ pub mod layout {
     pub mod block;
     pub mod construct;
     pub mod context;
+    pub mod flex;
     pub mod floats;
     pub mod flow;
     pub mod flow_list;
     pub mod flow_ref;
     pub mod fragment;
+    pub mod generated_content;
     pub mod layout_task;
     pub mod inline;
     pub mod model;
@@ -198,6 +201,9 @@ pub fn run(opts: opts::Opts) {
         let opts = &opts_clone;
         // Create a Servo instance.
         let resource_task = ResourceTask();
+        let storage_task = StorageTask(opts.profile_dir.as_ref().map(|profile_dir| {
+            Path::new(profile_dir.as_slice())
+        }));
         // If we are emitting an output file, then we need to block on
         // image load or we risk emitting an output file missing the
         // image.
@@ -209,6 +215,7 @@ pub fn run(opts: opts::Opts) {
         let constellation_chan = Constellation::start(compositor_chan,
                                                       opts,
                                                       resource_task,
+                                                      storage_task,
                                                       image_cache_task,
                                                       profiler_chan_clone);
 
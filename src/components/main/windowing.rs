@@ -8,6 +8,7 @@ use geom::point::TypedPoint2D;
 use geom::scale_factor::ScaleFactor;
 use geom::size::TypedSize2D;
 use servo_msg::compositor_msg::{ReadyState, RenderState};
+use servo_msg::constellation_msg::{Key, KeyState, KeyModifiers};
 use servo_util::geometry::{ScreenPx, DevicePixel};
 use std::rc::Rc;
 
@@ -41,10 +42,15 @@ pub enum WindowEvent {
     MouseWindowMoveEventClass(TypedPoint2D<DevicePixel, f32>),
     /// Sent when the user scrolls. Includes the current cursor position.
     ScrollWindowEvent(TypedPoint2D<DevicePixel, f32>, TypedPoint2D<DevicePixel, i32>),
-    /// Sent when the user zooms.
+    /// Sent when the user zooms the page, which reflows it at the new zoom level.
     ZoomWindowEvent(f32),
-    /// Sent when the user uses chrome navigation (i.e. backspace or shift-backspace).
+    /// Sent when the user pinch zooms, which magnifies the page without reflowing it.
+    PinchZoomWindowEvent(f32),
+    /// Sent when the user uses chrome navigation (i.e. alt-left or alt-right).
     NavigationWindowEvent(WindowNavigateMsg),
+    /// Sent when a key is pressed, released or repeated and the browser chrome doesn't handle it,
+    /// along with the character that the key types, if any.
+    KeyWindowEvent(Key, KeyState, KeyModifiers, Option<char>),
     /// Sent when rendering is finished.
     FinishedWindowEvent,
     /// Sent when the user quits the application
@@ -57,8 +63,8 @@ pub trait ApplicationMethods {
 }
 
 pub trait WindowMethods<A> {
-    /// Creates a new window.
-    fn new(app: &A, is_foreground: bool) -> Rc<Self>;
+    /// Creates a new window of the given size.
+    fn new(app: &A, is_foreground: bool, size: TypedSize2D<ScreenPx, uint>) -> Rc<Self>;
     /// Returns the size of the window in hardware pixels.
     fn framebuffer_size(&self) -> TypedSize2D<DevicePixel, uint>;
     /// Returns the size of the window in density-independent "px" units.
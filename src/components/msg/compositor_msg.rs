@@ -14,10 +14,35 @@ use std::fmt;
 
 use constellation_msg::PipelineId;
 
-pub struct LayerBuffer {
-    /// The native surface which can be shared between threads or processes. On Mac this is an
+/// The pixels of a layer buffer.
+pub enum LayerBufferContents {
+    /// A native surface which can be shared between threads or processes. On Mac this is an
     /// `IOSurface`; on Linux this is an X Pixmap; on Android this is an `EGLImageKHR`.
-    pub native_surface: NativeSurface,
+    NativeSurfaceContents(NativeSurface),
+    /// Pixels in B8G8R8A8 format, in main memory. These are used by the headless compositor,
+    /// which has no native graphics context and composites on the CPU.
+    CpuContents(Vec<u8>),
+}
+
+impl LayerBufferContents {
+    pub fn mark_will_leak(&mut self) {
+        match *self {
+            NativeSurfaceContents(ref mut native_surface) => native_surface.mark_will_leak(),
+            CpuContents(_) => {}
+        }
+    }
+
+    pub fn mark_wont_leak(&mut self) {
+        match *self {
+            NativeSurfaceContents(ref mut native_surface) => native_surface.mark_wont_leak(),
+            CpuContents(_) => {}
+        }
+    }
+}
+
+pub struct LayerBuffer {
+    /// The pixels of this buffer.
+    pub contents: LayerBufferContents,
 
     /// The rect in the containing RenderLayer that this represents.
     pub rect: Rect<f32>,
@@ -45,7 +70,18 @@ impl LayerBufferSet {
     /// Notes all buffer surfaces will leak if not destroyed via a call to `destroy`.
     pub fn mark_will_leak(&mut self) {
         for buffer in self.buffers.mut_iter() {
-            buffer.native_surface.mark_will_leak()
+            buffer.contents.mark_will_leak()
+        }
+    }
+}
+
+impl LayerBuffer {
+    /// Returns the native surface of this buffer. Fails if the buffer holds CPU pixels, which
+    /// only the headless compositor asks for.
+    pub fn native_surface<'a>(&'a self) -> &'a NativeSurface {
+        match self.contents {
+            NativeSurfaceContents(ref native_surface) => native_surface,
+            CpuContents(_) => fail!("expected a native surface but found CPU pixels"),
         }
     }
 }
@@ -154,6 +190,10 @@ pub trait ScriptListener : Clone {
                              pipeline_id: PipelineId,
                              layer_id: LayerId,
                              point: Point2D<f32>);
+    fn scroll_fragment_by(&self,
+                          pipeline_id: PipelineId,
+                          layer_id: LayerId,
+                          delta: Point2D<f32>);
     fn close(&self);
     fn dup(&self) -> Box<ScriptListener>;
 }
@@ -193,11 +233,14 @@ impl Tile for Box<LayerBuffer> {
         self.screen_pos.size
     }
     fn mark_wont_leak(&mut self) {
-        self.native_surface.mark_wont_leak()
+        self.contents.mark_wont_leak()
     }
     fn destroy(self, graphics_context: &NativePaintingGraphicsContext) {
         let mut this = self;
-        this.native_surface.destroy(graphics_context)
+        match this.contents {
+            NativeSurfaceContents(ref mut native_surface) => native_surface.destroy(graphics_context),
+            CpuContents(_) => {}
+        }
     }
 }
 
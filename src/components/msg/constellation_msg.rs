@@ -7,7 +7,9 @@
 
 use geom::rect::Rect;
 use geom::size::TypedSize2D;
-use servo_util::geometry::PagePx;
+use servo_net::resource_task::LoadData;
+use servo_net::storage_task::StorageType;
+use servo_util::geometry::ScreenPx;
 use std::comm::{channel, Sender, Receiver};
 use url::Url;
 
@@ -41,11 +43,241 @@ pub enum Msg {
     InitLoadUrlMsg(Url),
     LoadCompleteMsg(PipelineId, Url),
     FrameRectMsg(PipelineId, SubpageId, Rect<f32>),
-    LoadUrlMsg(PipelineId, Url),
+    /// Loads a new page in the given pipeline's frame. A `Replace` load takes the place of the
+    /// current session history entry rather than adding one after it.
+    LoadUrlMsg(PipelineId, LoadData, NavigationType),
     LoadIframeUrlMsg(Url, PipelineId, SubpageId, IFrameSandboxState),
     NavigateMsg(NavigationDirection),
+    /// Moves through the session history by the given number of entries, as `history.go()` does.
+    TraverseHistoryMsg(int),
+    /// The document in the given pipeline changed its URL without loading a new document, by
+    /// navigating to a fragment or with `pushState()` or `replaceState()`. A `Load` adds a
+    /// session history entry that shares the current frame tree, while a `Replace` updates the
+    /// current entry. The ID identifies the state object that the script task keeps for the entry.
+    SameDocumentNavigationMsg(PipelineId, Url, Option<HistoryStateId>, NavigationType),
+    /// Asks for the number of entries in the session history.
+    GetHistoryLengthMsg(Sender<uint>),
+    /// The document in the given pipeline has animation frame callbacks waiting for the next
+    /// frame.
+    RequestAnimationFrameMsg(PipelineId),
+    /// The compositor has begun a new frame, so the pipelines waiting for one can run their
+    /// animation frame callbacks.
+    TickAnimationFramesMsg,
+    /// A document in the given pipeline, at the given URL, changed an item of its origin's
+    /// storage area: the key, old value and new value of the item follow. A change with no key
+    /// cleared the whole area. The other documents of the origin fire `storage` events.
+    StorageChangedMsg(PipelineId, StorageType, Url, Option<String>, Option<String>,
+                      Option<String>),
     RendererReadyMsg(PipelineId),
-    ResizedWindowMsg(TypedSize2D<PagePx, f32>),
+    /// The window has been resized to the given size.
+    ResizedWindowMsg(TypedSize2D<ScreenPx, f32>),
+    /// The user zoomed the page in or out by the given factor. The page zoom level is kept for the
+    /// origin of the current page, and the page is reflowed at the new zoom level.
+    ZoomMsg(f32),
+    /// A key was pressed, released or auto-repeated, typing the given character if any. The event
+    /// is sent on to the script task of the current page.
+    KeyEvent(Key, KeyState, KeyModifiers, Option<char>),
+}
+
+/// Whether a key went down, came up, or is being held down and auto-repeating.
+#[deriving(Clone, Eq, Show)]
+pub enum KeyState {
+    Pressed,
+    Released,
+    Repeated,
+}
+
+/// A physical key, named after the key at that position on a US keyboard. This mirrors the keys
+/// that GLFW reports.
+#[deriving(Clone, Eq, Show)]
+pub enum Key {
+    KeySpace,
+    KeyApostrophe,
+    KeyComma,
+    KeyMinus,
+    KeyPeriod,
+    KeySlash,
+    Key0,
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    KeySemicolon,
+    KeyEqual,
+    KeyA,
+    KeyB,
+    KeyC,
+    KeyD,
+    KeyE,
+    KeyF,
+    KeyG,
+    KeyH,
+    KeyI,
+    KeyJ,
+    KeyK,
+    KeyL,
+    KeyM,
+    KeyN,
+    KeyO,
+    KeyP,
+    KeyQ,
+    KeyR,
+    KeyS,
+    KeyT,
+    KeyU,
+    KeyV,
+    KeyW,
+    KeyX,
+    KeyY,
+    KeyZ,
+    KeyLeftBracket,
+    KeyBackslash,
+    KeyRightBracket,
+    KeyGraveAccent,
+
+    KeyEscape,
+    KeyEnter,
+    KeyTab,
+    KeyBackspace,
+    KeyInsert,
+    KeyDelete,
+    KeyRight,
+    KeyLeft,
+    KeyDown,
+    KeyUp,
+    KeyPageUp,
+    KeyPageDown,
+    KeyHome,
+    KeyEnd,
+    KeyCapsLock,
+    KeyF1,
+    KeyF2,
+    KeyF3,
+    KeyF4,
+    KeyF5,
+    KeyF6,
+    KeyF7,
+    KeyF8,
+    KeyF9,
+    KeyF10,
+    KeyF11,
+    KeyF12,
+    KeyLeftShift,
+    KeyLeftControl,
+    KeyLeftAlt,
+    KeyLeftSuper,
+    KeyRightShift,
+    KeyRightControl,
+    KeyRightAlt,
+    KeyRightSuper,
+    KeyMenu,
+
+    /// A key that Servo does not know about.
+    KeyUnidentified,
+}
+
+impl Key {
+    /// Returns the key that types the given character on a US keyboard, and whether Shift has to
+    /// be held down to type it.
+    pub fn from_char(c: char) -> (Key, bool) {
+        let unshifted = match c {
+            '"' => '\'',
+            '<' => ',',
+            '_' => '-',
+            '>' => '.',
+            '?' => '/',
+            ')' => '0',
+            '!' => '1',
+            '@' => '2',
+            '#' => '3',
+            '$' => '4',
+            '%' => '5',
+            '^' => '6',
+            '&' => '7',
+            '*' => '8',
+            '(' => '9',
+            ':' => ';',
+            '+' => '=',
+            '{' => '[',
+            '|' => '\\',
+            '}' => ']',
+            '~' => '`',
+            c if c.is_uppercase() => c.to_lowercase(),
+            c => c,
+        };
+        let key = match unshifted {
+            ' ' => KeySpace,
+            '\'' => KeyApostrophe,
+            ',' => KeyComma,
+            '-' => KeyMinus,
+            '.' => KeyPeriod,
+            '/' => KeySlash,
+            '0' => Key0,
+            '1' => Key1,
+            '2' => Key2,
+            '3' => Key3,
+            '4' => Key4,
+            '5' => Key5,
+            '6' => Key6,
+            '7' => Key7,
+            '8' => Key8,
+            '9' => Key9,
+            ';' => KeySemicolon,
+            '=' => KeyEqual,
+            'a' => KeyA,
+            'b' => KeyB,
+            'c' => KeyC,
+            'd' => KeyD,
+            'e' => KeyE,
+            'f' => KeyF,
+            'g' => KeyG,
+            'h' => KeyH,
+            'i' => KeyI,
+            'j' => KeyJ,
+            'k' => KeyK,
+            'l' => KeyL,
+            'm' => KeyM,
+            'n' => KeyN,
+            'o' => KeyO,
+            'p' => KeyP,
+            'q' => KeyQ,
+            'r' => KeyR,
+            's' => KeyS,
+            't' => KeyT,
+            'u' => KeyU,
+            'v' => KeyV,
+            'w' => KeyW,
+            'x' => KeyX,
+            'y' => KeyY,
+            'z' => KeyZ,
+            '[' => KeyLeftBracket,
+            '\\' => KeyBackslash,
+            ']' => KeyRightBracket,
+            '`' => KeyGraveAccent,
+            '\x08' => KeyBackspace,
+            '\t' => KeyTab,
+            '\r' | '\n' => KeyEnter,
+            '\x1b' => KeyEscape,
+            '\x7f' => KeyDelete,
+            _ => KeyUnidentified,
+        };
+        (key, unshifted != c)
+    }
+}
+
+bitflags! {
+    #[doc = "The modifier keys held down during a key event."]
+    flags KeyModifiers: u8 {
+        static Shift = 0x01,
+        static Control = 0x02,
+        static Alt = 0x04,
+        static Super = 0x08
+    }
 }
 
 /// Represents the two different ways to which a page can be navigated
@@ -53,6 +285,7 @@ pub enum Msg {
 pub enum NavigationType {
     Load,               // entered or clicked on a url
     Navigate,           // browser forward/back buttons
+    Replace,            // location.replace() and reloads, which replace the current entry
 }
 
 #[deriving(Clone, Eq, Hash)]
@@ -66,3 +299,7 @@ pub struct PipelineId(pub uint);
 
 #[deriving(Clone, Eq, TotalEq, Hash, Encodable)]
 pub struct SubpageId(pub uint);
+
+/// Identifies the state object of a session history entry within the document that added it.
+#[deriving(Clone, Eq, TotalEq, Hash, Encodable)]
+pub struct HistoryStateId(pub uint);
@@ -11,6 +11,7 @@ extern crate azure;
 extern crate geom;
 extern crate layers;
 extern crate serialize;
+extern crate servo_net = "net";
 extern crate servo_util = "util";
 extern crate std;
 extern crate url;
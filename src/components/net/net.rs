@@ -38,4 +38,5 @@ pub mod data_loader;
 pub mod image_cache_task;
 pub mod local_image_cache;
 pub mod resource_task;
+pub mod storage_task;
 
@@ -0,0 +1,426 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! A task that keeps the `localStorage` and `sessionStorage` areas of every origin. The
+//! `localStorage` areas are written to a file in the profile directory, if there is one, so that
+//! they outlive the browser.
+
+use collections::HashMap;
+use collections::TreeMap;
+use serialize::json;
+use serialize::json::ToJson;
+use std::comm::{channel, Receiver, Select, Sender};
+use std::io;
+use std::io::{File, fs};
+use std::io::timer::Timer;
+use std::task::TaskBuilder;
+
+#[cfg(test)]
+use std::io::TempDir;
+
+/// The largest number of bytes that the names and values of the items in the storage area of
+/// one origin can take up.
+pub static QUOTA_SIZE_LIMIT: uint = 5 * 1024 * 1024;
+
+/// The file in the profile directory that the `localStorage` areas are kept in.
+static LOCAL_STORAGE_FILE_NAME: &'static str = "local_storage.json";
+
+/// The file that the `localStorage` areas are written to before it replaces
+/// `LOCAL_STORAGE_FILE_NAME`, so that a crash while writing cannot leave a truncated file behind.
+static TEMP_LOCAL_STORAGE_FILE_NAME: &'static str = "local_storage.json.tmp";
+
+/// How long, in milliseconds, the `localStorage` areas are left unsaved after they change, so
+/// that a burst of changes is written to disk once.
+static SAVE_DELAY: u64 = 1000;
+
+/// Which of the two storage areas of an origin a message is about.
+#[deriving(Clone, Eq, Show)]
+pub enum StorageType {
+    Local,
+    Session,
+}
+
+/// Messages to the storage task. Each storage area is identified by an origin, as serialized by
+/// the script task, and a type.
+pub enum StorageTaskMsg {
+    /// Gets the number of items in a storage area.
+    Length(Sender<uint>, String, StorageType),
+    /// Gets the name of the item at the given index. Items are ordered by name.
+    Key(Sender<Option<String>>, String, StorageType, uint),
+    /// Gets the value of the item with the given name.
+    GetItem(Sender<Option<String>>, String, StorageType, String),
+    /// Sets the value of the item with the given name. Replies with whether the value changed
+    /// and the old value, or with `Err` if the new value would not fit in the origin's quota.
+    SetItem(Sender<Result<(bool, Option<String>), ()>>, String, StorageType, String, String),
+    /// Removes the item with the given name, replying with its value if there was one.
+    RemoveItem(Sender<Option<String>>, String, StorageType, String),
+    /// Removes all items from a storage area, replying with whether there were any.
+    Clear(Sender<bool>, String, StorageType),
+    /// Shuts the storage task down, replying once any unsaved changes have been written.
+    Exit(Sender<()>),
+}
+
+/// Handle to a storage task
+pub type StorageTask = Sender<StorageTaskMsg>;
+
+/// Creates a storage task, which keeps `localStorage` in the given profile directory if there is
+/// one.
+pub fn StorageTask(profile_dir: Option<Path>) -> StorageTask {
+    let (chan, port) = channel();
+    let builder = TaskBuilder::new().named("StorageManager");
+    builder.spawn(proc() {
+        StorageManager::new(port, profile_dir).start();
+    });
+    chan
+}
+
+struct StorageManager {
+    port: Receiver<StorageTaskMsg>,
+    /// The `localStorage` areas, keyed by origin.
+    local_data: HashMap<String, TreeMap<String, String>>,
+    /// The `sessionStorage` areas, keyed by origin.
+    session_data: HashMap<String, TreeMap<String, String>>,
+    /// The file that the `localStorage` areas are kept in, if any.
+    local_storage_path: Option<Path>,
+    /// Whether the `localStorage` areas changed since they were last written.
+    local_data_dirty: bool,
+}
+
+impl StorageManager {
+    fn new(port: Receiver<StorageTaskMsg>, profile_dir: Option<Path>) -> StorageManager {
+        let local_storage_path = profile_dir.map(|profile_dir| {
+            match fs::mkdir_recursive(&profile_dir, io::UserRWX) {
+                Ok(()) => {}
+                Err(error) => {
+                    error!("storage_task: couldn't create {}: {}", profile_dir.display(), error)
+                }
+            }
+            profile_dir.join(LOCAL_STORAGE_FILE_NAME)
+        });
+        let local_data = match local_storage_path {
+            Some(ref path) if path.exists() => read_storage_file(path),
+            _ => HashMap::new(),
+        };
+        StorageManager {
+            port: port,
+            local_data: local_data,
+            session_data: HashMap::new(),
+            local_storage_path: local_storage_path,
+            local_data_dirty: false,
+        }
+    }
+
+    fn start(&mut self) {
+        let mut timer = Timer::new().unwrap();
+        let mut save_timeout: Option<Receiver<()>> = None;
+        loop {
+            // While there are unsaved changes, wait for either the next message or the time to
+            // write them.
+            let msg = match save_timeout {
+                None => Some(self.port.recv()),
+                Some(ref timeout_port) => {
+                    let select = Select::new();
+                    let mut port_handle = select.handle(&self.port);
+                    unsafe { port_handle.add() };
+                    let mut timeout_handle = select.handle(timeout_port);
+                    unsafe { timeout_handle.add() };
+                    if select.wait() == timeout_handle.id() {
+                        timeout_port.recv();
+                        None
+                    } else {
+                        Some(port_handle.recv())
+                    }
+                }
+            };
+            let msg = match msg {
+                Some(msg) => msg,
+                None => {
+                    save_timeout = None;
+                    self.save();
+                    continue
+                }
+            };
+
+            match msg {
+                Length(sender, origin, storage_type) => {
+                    sender.send(self.length(origin, storage_type))
+                }
+                Key(sender, origin, storage_type, index) => {
+                    sender.send(self.key(origin, storage_type, index))
+                }
+                GetItem(sender, origin, storage_type, name) => {
+                    sender.send(self.get_item(origin, storage_type, name))
+                }
+                SetItem(sender, origin, storage_type, name, value) => {
+                    sender.send(self.set_item(origin, storage_type, name, value))
+                }
+                RemoveItem(sender, origin, storage_type, name) => {
+                    sender.send(self.remove_item(origin, storage_type, name))
+                }
+                Clear(sender, origin, storage_type) => {
+                    sender.send(self.clear(origin, storage_type))
+                }
+                Exit(response_chan) => {
+                    self.save();
+                    response_chan.send(());
+                    break
+                }
+            }
+
+            if self.local_data_dirty && save_timeout.is_none() {
+                save_timeout = Some(timer.oneshot(SAVE_DELAY));
+            }
+        }
+    }
+
+    fn select_data<'a>(&'a self, storage_type: StorageType)
+                       -> &'a HashMap<String, TreeMap<String, String>> {
+        match storage_type {
+            Local => &self.local_data,
+            Session => &self.session_data,
+        }
+    }
+
+    fn select_data_mut<'a>(&'a mut self, storage_type: StorageType)
+                           -> &'a mut HashMap<String, TreeMap<String, String>> {
+        match storage_type {
+            Local => &mut self.local_data,
+            Session => &mut self.session_data,
+        }
+    }
+
+    fn length(&self, origin: String, storage_type: StorageType) -> uint {
+        match self.select_data(storage_type).find(&origin) {
+            Some(items) => items.len(),
+            None => 0,
+        }
+    }
+
+    fn key(&self, origin: String, storage_type: StorageType, index: uint) -> Option<String> {
+        self.select_data(storage_type).find(&origin).and_then(|items| {
+            items.keys().nth(index).map(|name| name.clone())
+        })
+    }
+
+    fn get_item(&self, origin: String, storage_type: StorageType, name: String)
+                -> Option<String> {
+        self.select_data(storage_type).find(&origin).and_then(|items| {
+            items.find(&name).map(|value| value.clone())
+        })
+    }
+
+    fn set_item(&mut self, origin: String, storage_type: StorageType, name: String,
+                value: String) -> Result<(bool, Option<String>), ()> {
+        let old_value = {
+            let items = self.select_data_mut(storage_type).find_or_insert_with(origin, |_| {
+                TreeMap::new()
+            });
+            let old_value = items.find(&name).map(|value| value.clone());
+            if old_value.as_ref() == Some(&value) {
+                return Ok((false, old_value))
+            }
+
+            let old_size = items.iter().fold(0, |size, (name, value)| {
+                size + name.len() + value.len()
+            });
+            let new_size = match old_value {
+                Some(ref old_value) => old_size - old_value.len() + value.len(),
+                None => old_size + name.len() + value.len(),
+            };
+            if new_size > QUOTA_SIZE_LIMIT {
+                return Err(())
+            }
+            items.insert(name, value);
+            old_value
+        };
+        self.mark_dirty(storage_type);
+        Ok((true, old_value))
+    }
+
+    fn remove_item(&mut self, origin: String, storage_type: StorageType, name: String)
+                   -> Option<String> {
+        let old_value = match self.select_data_mut(storage_type).find_mut(&origin) {
+            Some(items) => items.pop(&name),
+            None => None,
+        };
+        if old_value.is_some() {
+            self.mark_dirty(storage_type);
+        }
+        old_value
+    }
+
+    fn clear(&mut self, origin: String, storage_type: StorageType) -> bool {
+        let cleared = match self.select_data_mut(storage_type).pop(&origin) {
+            Some(items) => !items.is_empty(),
+            None => false,
+        };
+        if cleared {
+            self.mark_dirty(storage_type);
+        }
+        cleared
+    }
+
+    /// Notes that a storage area of the given type changed, so that it gets written to the
+    /// profile directory if it is kept there.
+    fn mark_dirty(&mut self, storage_type: StorageType) {
+        if storage_type == Local && self.local_storage_path.is_some() {
+            self.local_data_dirty = true
+        }
+    }
+
+    /// Writes the `localStorage` areas to the profile directory if they changed since they were
+    /// last written. The new contents replace the old file only once they have been written in
+    /// full.
+    fn save(&mut self) {
+        if !self.local_data_dirty {
+            return
+        }
+        self.local_data_dirty = false;
+        let path = match self.local_storage_path {
+            Some(ref path) => path,
+            None => return,
+        };
+        let mut origins = TreeMap::new();
+        for (origin, items) in self.local_data.iter() {
+            origins.insert(origin.clone(), items.clone());
+        }
+        let contents = origins.to_json().to_str();
+        let temp_path = path.with_filename(TEMP_LOCAL_STORAGE_FILE_NAME);
+        let result = File::create(&temp_path).write_str(contents.as_slice()).and_then(|()| {
+            fs::rename(&temp_path, path)
+        });
+        match result {
+            Ok(()) => {}
+            Err(error) => error!("storage_task: couldn't write {}: {}", path.display(), error),
+        }
+    }
+}
+
+/// Reads the `localStorage` areas that an earlier session wrote. Anything that is not an object
+/// of origins mapped to objects of string values is skipped.
+fn read_storage_file(path: &Path) -> HashMap<String, TreeMap<String, String>> {
+    let mut data = HashMap::new();
+    let contents = match File::open(path).read_to_str() {
+        Ok(contents) => contents,
+        Err(error) => {
+            error!("storage_task: couldn't read {}: {}", path.display(), error);
+            return data
+        }
+    };
+    let origins = match json::from_str(contents.as_slice()) {
+        Ok(json) => match json.as_object() {
+            Some(origins) => origins.clone(),
+            None => return data,
+        },
+        Err(_) => return data,
+    };
+    for (origin, items) in origins.iter() {
+        let items = match items.as_object() {
+            Some(items) => items,
+            None => continue,
+        };
+        let mut area = TreeMap::new();
+        for (name, value) in items.iter() {
+            match value.as_string() {
+                Some(value) => {
+                    area.insert(name.clone(), value.to_string());
+                }
+                None => {}
+            }
+        }
+        data.insert(origin.clone(), area);
+    }
+    data
+}
+
+/// Shuts the given storage task down and waits until it has saved its changes.
+#[cfg(test)]
+fn exit(storage_task: &StorageTask) {
+    let (chan, port) = channel();
+    storage_task.send(Exit(chan));
+    port.recv()
+}
+
+#[test]
+fn test_items() {
+    let storage_task = StorageTask(None);
+    let origin = "http://example.com".to_string();
+    let (chan, port) = channel();
+    storage_task.send(SetItem(chan.clone(), origin.clone(), Local, "b".to_string(),
+                              "1".to_string()));
+    assert!(port.recv() == Ok((true, None)));
+    storage_task.send(SetItem(chan.clone(), origin.clone(), Local, "a".to_string(),
+                              "2".to_string()));
+    assert!(port.recv() == Ok((true, None)));
+    storage_task.send(SetItem(chan, origin.clone(), Local, "a".to_string(), "2".to_string()));
+    assert!(port.recv() == Ok((false, Some("2".to_string()))));
+
+    let (chan, port) = channel();
+    storage_task.send(Length(chan, origin.clone(), Local));
+    assert_eq!(port.recv(), 2);
+    let (chan, port) = channel();
+    storage_task.send(Key(chan, origin.clone(), Local, 0));
+    assert_eq!(port.recv(), Some("a".to_string()));
+
+    // The two storage areas and different origins are kept apart.
+    let (chan, port) = channel();
+    storage_task.send(GetItem(chan.clone(), origin.clone(), Session, "a".to_string()));
+    assert_eq!(port.recv(), None);
+    storage_task.send(GetItem(chan, "http://example.org".to_string(), Local, "a".to_string()));
+    assert_eq!(port.recv(), None);
+
+    let (chan, port) = channel();
+    storage_task.send(RemoveItem(chan, origin.clone(), Local, "b".to_string()));
+    assert_eq!(port.recv(), Some("1".to_string()));
+    let (chan, port) = channel();
+    storage_task.send(Clear(chan.clone(), origin.clone(), Local));
+    assert_eq!(port.recv(), true);
+    storage_task.send(Clear(chan, origin, Local));
+    assert_eq!(port.recv(), false);
+    exit(&storage_task);
+}
+
+#[test]
+fn test_quota() {
+    let storage_task = StorageTask(None);
+    let origin = "http://example.com".to_string();
+    let (chan, port) = channel();
+    let value = String::from_char(QUOTA_SIZE_LIMIT - 1, 'x');
+    storage_task.send(SetItem(chan.clone(), origin.clone(), Local, "a".to_string(), value));
+    assert!(port.recv().is_ok());
+    storage_task.send(SetItem(chan.clone(), origin.clone(), Local, "b".to_string(),
+                              "y".to_string()));
+    assert!(port.recv().is_err());
+
+    // Replacing a value only counts the difference in size.
+    storage_task.send(SetItem(chan, origin, Local, "a".to_string(), "z".to_string()));
+    assert!(port.recv().is_ok());
+    exit(&storage_task);
+}
+
+#[test]
+fn test_persistence() {
+    let profile_dir = TempDir::new("servo-storage").unwrap();
+    let origin = "http://example.com".to_string();
+
+    let storage_task = StorageTask(Some(profile_dir.path().clone()));
+    let (chan, port) = channel();
+    storage_task.send(SetItem(chan.clone(), origin.clone(), Local, "a".to_string(),
+                              "1".to_string()));
+    assert!(port.recv().is_ok());
+    storage_task.send(SetItem(chan, origin.clone(), Session, "b".to_string(), "2".to_string()));
+    assert!(port.recv().is_ok());
+    exit(&storage_task);
+    assert!(profile_dir.path().join(LOCAL_STORAGE_FILE_NAME).exists());
+    assert!(!profile_dir.path().join(TEMP_LOCAL_STORAGE_FILE_NAME).exists());
+
+    // Only localStorage outlives the storage task.
+    let storage_task = StorageTask(Some(profile_dir.path().clone()));
+    let (chan, port) = channel();
+    storage_task.send(GetItem(chan.clone(), origin.clone(), Local, "a".to_string()));
+    assert_eq!(port.recv(), Some("1".to_string()));
+    storage_task.send(GetItem(chan, origin, Session, "b".to_string()));
+    assert_eq!(port.recv(), None);
+    exit(&storage_task);
+}
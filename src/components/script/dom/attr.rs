@@ -7,6 +7,7 @@ use dom::bindings::codegen::InheritTypes::NodeCast;
 use dom::bindings::js::{JS, JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::element::{Element, AttributeHandlers};
+use dom::mutationobserver::{AttributeMutation, queue_mutation_record};
 use dom::node::Node;
 use dom::window::Window;
 use dom::virtualmethods::vtable_for;
@@ -100,6 +101,13 @@ impl Attr {
         let node: &JSRef<Node> = NodeCast::from_ref(&*owner);
         let namespace_is_null = self.namespace == namespace::Null;
 
+        let old_value = match set_type {
+            ReplacedAttr => Some(self.value.as_slice().to_string()),
+            FirstSetAttr => None,
+        };
+        queue_mutation_record(node, AttributeMutation(self.local_name.clone(),
+                                                      self.namespace.clone(), old_value));
+
         match set_type {
             ReplacedAttr => {
                 if namespace_is_null {
@@ -30,7 +30,8 @@ pub enum Error {
     Security,
     Network,
     Abort,
-    Timeout
+    Timeout,
+    QuotaExceeded
 }
 
 pub type Fallible<T> = Result<T, Error>;
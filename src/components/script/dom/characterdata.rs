@@ -11,6 +11,7 @@ use dom::bindings::trace::Traceable;
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::document::Document;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
+use dom::mutationobserver::{CharacterDataMutation, queue_mutation_record};
 use dom::node::{CommentNodeTypeId, Node, NodeTypeId, TextNodeTypeId, ProcessingInstructionNodeTypeId, NodeHelpers};
 use servo_util::str::DOMString;
 
@@ -60,6 +61,8 @@ impl<'a> CharacterDataMethods for JSRef<'a, CharacterData> {
     }
 
     fn SetData(&self, arg: DOMString) -> ErrorResult {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        queue_mutation_record(node, CharacterDataMutation(self.Data()));
         *self.data.deref().borrow_mut() = arg;
         Ok(())
     }
@@ -73,6 +76,8 @@ impl<'a> CharacterDataMethods for JSRef<'a, CharacterData> {
     }
 
     fn AppendData(&self, arg: DOMString) -> ErrorResult {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        queue_mutation_record(node, CharacterDataMutation(self.Data()));
         self.data.deref().borrow_mut().push_str(arg.as_slice());
         Ok(())
     }
@@ -95,6 +100,8 @@ impl<'a> CharacterDataMethods for JSRef<'a, CharacterData> {
         } else {
             count
         };
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        queue_mutation_record(node, CharacterDataMutation(self.Data()));
         let mut data = self.data.deref().borrow().as_slice().slice(0, offset as uint).to_string();
         data.push_str(arg.as_slice());
         data.push_str(self.data.deref().borrow().as_slice().slice((offset + count) as uint, length as uint));
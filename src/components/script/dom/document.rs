@@ -20,7 +20,7 @@ use dom::customevent::CustomEvent;
 use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
 use dom::domimplementation::DOMImplementation;
-use dom::element::{Element, AttributeHandlers, get_attribute_parts};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, get_attribute_parts};
 use dom::element::{HTMLHtmlElementTypeId, HTMLHeadElementTypeId, HTMLTitleElementTypeId};
 use dom::element::{HTMLBodyElementTypeId, HTMLFrameSetElementTypeId};
 use dom::event::Event;
@@ -30,6 +30,8 @@ use dom::htmlelement::HTMLElement;
 use dom::htmlheadelement::HTMLHeadElement;
 use dom::htmlhtmlelement::HTMLHtmlElement;
 use dom::htmltitleelement::HTMLTitleElement;
+use dom::focusevent::FocusEvent;
+use dom::keyboardevent::KeyboardEvent;
 use dom::mouseevent::MouseEvent;
 use dom::node::{Node, ElementNodeTypeId, DocumentNodeTypeId, NodeHelpers, NodeMethods};
 use dom::node::{CloneChildren, DoNotCloneChildren};
@@ -42,6 +44,7 @@ use dom::location::Location;
 use html::hubbub_html_parser::build_element_from_tag;
 use hubbub::hubbub::{QuirksMode, NoQuirks, LimitedQuirks, FullQuirks};
 use layout_interface::{DocumentDamageLevel, ContentChangedDocumentDamage};
+use layout_interface::MatchSelectorsDocumentDamage;
 use servo_util::namespace;
 use servo_util::namespace::{Namespace, Null};
 use servo_util::str::{DOMString, null_str_as_empty_ref};
@@ -50,6 +53,7 @@ use collections::hashmap::HashMap;
 use js::jsapi::JSContext;
 use std::ascii::StrAsciiExt;
 use std::cell::{Cell, RefCell};
+use std::i32;
 use url::{Url, from_str};
 
 #[deriving(Eq,Encodable)]
@@ -68,8 +72,13 @@ pub struct Document {
     pub content_type: DOMString,
     pub encoding_name: Traceable<RefCell<DOMString>>,
     pub is_html_document: bool,
-    pub url: Untraceable<Url>,
+    pub url: Untraceable<RefCell<Url>>,
     pub quirks_mode: Untraceable<Cell<QuirksMode>>,
+    /// The element that has the focus, if any.
+    focused: Cell<Option<JS<Element>>>,
+    /// The number of entries in the registered observer lists of the nodes of this document, so
+    /// that mutations of documents that nobody observes skip looking for observers.
+    registered_observer_count: Untraceable<Cell<uint>>,
 }
 
 impl DocumentDerived for EventTarget {
@@ -79,7 +88,8 @@ impl DocumentDerived for EventTarget {
 }
 
 pub trait DocumentHelpers {
-    fn url<'a>(&'a self) -> &'a Url;
+    fn url(&self) -> Url;
+    fn set_url(&self, url: Url);
     fn quirks_mode(&self) -> QuirksMode;
     fn set_quirks_mode(&self, mode: QuirksMode);
     fn set_encoding_name(&self, name: DOMString);
@@ -89,11 +99,23 @@ pub trait DocumentHelpers {
     fn unregister_named_element(&self, to_unregister: &JSRef<Element>, id: DOMString);
     fn register_named_element(&self, element: &JSRef<Element>, id: DOMString);
     fn load_anchor_href(&self, href: DOMString);
+    fn focused_element(&self) -> Option<Temporary<Element>>;
+    fn request_focus(&self, element: Option<&JSRef<Element>>);
+    fn focus_fixup(&self);
+    fn focus_next_sequentially(&self, backward: bool);
+    fn has_registered_observers(&self) -> bool;
+    fn add_registered_observers(&self, count: uint);
+    fn remove_registered_observers(&self, count: uint);
 }
 
 impl<'a> DocumentHelpers for JSRef<'a, Document> {
-    fn url<'a>(&'a self) -> &'a Url {
-        &*self.url
+    fn url(&self) -> Url {
+        self.url.deref().borrow().clone()
+    }
+
+    /// Changes the document's address, as `pushState()` and fragment navigations do.
+    fn set_url(&self, url: Url) {
+        *self.url.deref().borrow_mut() = url;
     }
 
     fn quirks_mode(&self) -> QuirksMode {
@@ -189,6 +211,135 @@ impl<'a> DocumentHelpers for JSRef<'a, Document> {
         let mut window = self.window.root();
         window.load_url(href);
     }
+
+    fn focused_element(&self) -> Option<Temporary<Element>> {
+        self.focused.get().map(|element| Temporary::new(element))
+    }
+
+    /// Moves the focus to the given element, or clears it, firing `blur` and `focusout` at the
+    /// element that loses the focus and `focus` and `focusin` at the one that gains it.
+    /// http://www.whatwg.org/specs/web-apps/current-work/#focus-update-steps
+    fn request_focus(&self, element: Option<&JSRef<Element>>) {
+        let old_focused = self.focused_element().root();
+        if old_focused.as_ref().map(|old| old.deref()) == element {
+            return;
+        }
+
+        let window = self.window.root();
+        match old_focused {
+            Some(ref old) => {
+                let old_node: &JSRef<Node> = NodeCast::from_ref(&**old);
+                old_node.set_focus_state(false);
+                self.focused.assign(None);
+
+                let related = element.map(|element| EventTargetCast::from_ref(element));
+                let target: &JSRef<EventTarget> = EventTargetCast::from_ref(&**old);
+                fire_focus_event(&*window, target, "blur", false, related);
+                fire_focus_event(&*window, target, "focusout", true, related);
+            }
+            None => {}
+        }
+
+        match element {
+            Some(element) => {
+                let node: &JSRef<Node> = NodeCast::from_ref(element);
+                node.set_focus_state(true);
+                self.focused.assign(Some(element.clone()));
+
+                let related = old_focused.as_ref().map(|old| EventTargetCast::from_ref(&**old));
+                let target: &JSRef<EventTarget> = EventTargetCast::from_ref(element);
+                fire_focus_event(&*window, target, "focus", false, related);
+                fire_focus_event(&*window, target, "focusin", true, related);
+            }
+            None => {}
+        }
+
+        self.damage_and_reflow(MatchSelectorsDocumentDamage);
+    }
+
+    /// Drops the focus without firing any events, as when the focused element is removed from
+    /// the document.
+    /// http://www.whatwg.org/specs/web-apps/current-work/#focus-fixup-rule
+    fn focus_fixup(&self) {
+        match self.focused_element().root() {
+            Some(ref focused) => {
+                let node: &JSRef<Node> = NodeCast::from_ref(&**focused);
+                node.set_focus_state(false);
+            }
+            None => return,
+        }
+        self.focused.assign(None);
+    }
+
+    /// Returns whether any node of this document has a mutation observer registered on it.
+    fn has_registered_observers(&self) -> bool {
+        self.registered_observer_count.deref().get() > 0
+    }
+
+    /// Counts entries that were added to the registered observer lists of this document's nodes.
+    fn add_registered_observers(&self, count: uint) {
+        let registered_observer_count = self.registered_observer_count.deref();
+        registered_observer_count.set(registered_observer_count.get() + count);
+    }
+
+    /// Counts entries that were removed from the registered observer lists of this document's
+    /// nodes.
+    fn remove_registered_observers(&self, count: uint) {
+        let registered_observer_count = self.registered_observer_count.deref();
+        assert!(registered_observer_count.get() >= count);
+        registered_observer_count.set(registered_observer_count.get() - count);
+    }
+
+    /// Moves the focus to the next (or, if `backward` is true, the previous) element in the
+    /// sequential focus navigation order, wrapping around at either end.
+    /// http://www.whatwg.org/specs/web-apps/current-work/#sequential-focus-navigation
+    fn focus_next_sequentially(&self, backward: bool) {
+        let root: &JSRef<Node> = NodeCast::from_ref(self);
+        let mut candidates: Vec<JSRef<Element>> = root.traverse_preorder()
+            .filter_map(|node| ElementCast::to_ref(&node).map(|element| element.clone()))
+            .filter(|element| element.is_sequentially_focusable())
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+
+        // Elements with a positive tabindex come first, in increasing order; the rest follow in
+        // tree order. The sort is stable, so ties keep their tree order.
+        candidates.sort_by(|a, b| {
+            let key = |element: &JSRef<Element>| match element.tab_index() {
+                Some(index) if index > 0 => index,
+                _ => i32::MAX,
+            };
+            key(a).cmp(&key(b))
+        });
+
+        let focused = self.focused_element().root();
+        let current = focused.and_then(|focused| {
+            candidates.iter().position(|element| element == &*focused)
+        });
+        let last = candidates.len() - 1;
+        let next = match (current, backward) {
+            (None, false) => 0,
+            (None, true) => last,
+            (Some(index), false) => if index == last { 0 } else { index + 1 },
+            (Some(index), true) => if index == 0 { last } else { index - 1 },
+        };
+        self.request_focus(Some(candidates.get(next)));
+    }
+}
+
+/// Fires a trusted focus event of the given type at the target.
+fn fire_focus_event(window: &JSRef<Window>,
+                    target: &JSRef<EventTarget>,
+                    type_: &str,
+                    bubbles: bool,
+                    related_target: Option<&JSRef<EventTarget>>) {
+    let event = FocusEvent::new(window, type_.to_string(), bubbles, false,
+                                Some(window.clone()), 0,
+                                related_target.map(|target| target.clone())).root();
+    let event: &JSRef<Event> = EventCast::from_ref(&*event);
+    event.trusted.deref().set(true);
+    let _ = target.dispatch_event_with_target(None, event);
 }
 
 impl Document {
@@ -227,12 +378,14 @@ impl Document {
                     NonHTMLDocument => "application/xml".to_string()
                 }
             },
-            url: Untraceable::new(url),
+            url: Untraceable::new(RefCell::new(url)),
             // http://dom.spec.whatwg.org/#concept-document-quirks
             quirks_mode: Untraceable::new(Cell::new(NoQuirks)),
             // http://dom.spec.whatwg.org/#concept-document-encoding
             encoding_name: Traceable::new(RefCell::new("utf-8".to_string())),
             is_html_document: is_html_document == HTMLDocument,
+            focused: Cell::new(None),
+            registered_observer_count: Untraceable::new(Cell::new(0)),
         }
     }
 
@@ -316,6 +469,7 @@ pub trait DocumentMethods {
     fn GetHead(&self) -> Option<Temporary<HTMLHeadElement>>;
     fn GetBody(&self) -> Option<Temporary<HTMLElement>>;
     fn SetBody(&self, new_body: Option<JSRef<HTMLElement>>) -> ErrorResult;
+    fn GetActiveElement(&self) -> Option<Temporary<Element>>;
     fn GetElementsByName(&self, name: DOMString) -> Temporary<NodeList>;
     fn Images(&self) -> Temporary<HTMLCollection>;
     fn Embeds(&self) -> Temporary<HTMLCollection>;
@@ -549,6 +703,8 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
             // FIXME: Implement CustomEvent (http://dom.spec.whatwg.org/#customevent)
             "uievents" | "uievent" => Ok(EventCast::from_temporary(UIEvent::new_uninitialized(&*window))),
             "mouseevents" | "mouseevent" => Ok(EventCast::from_temporary(MouseEvent::new_uninitialized(&*window))),
+            "focusevent" => Ok(EventCast::from_temporary(FocusEvent::new_uninitialized(&*window))),
+            "keyevents" | "keyboardevent" => Ok(EventCast::from_temporary(KeyboardEvent::new_uninitialized(&*window))),
             "customevent" => Ok(EventCast::from_temporary(CustomEvent::new_uninitialized(&*window))),
             "htmlevents" | "events" | "event" => Ok(Event::new_uninitialized(&*window)),
             _ => Err(NotSupported)
@@ -685,6 +841,19 @@ impl<'a> DocumentMethods for JSRef<'a, Document> {
         Ok(())
     }
 
+    // http://www.whatwg.org/specs/web-apps/current-work/#dom-document-activeelement
+    fn GetActiveElement(&self) -> Option<Temporary<Element>> {
+        match self.focused_element() {
+            Some(element) => return Some(element),
+            None => {}
+        }
+        match self.GetBody() {
+            Some(body) => return Some(ElementCast::from_temporary(body)),
+            None => {}
+        }
+        self.GetDocumentElement()
+    }
+
     // http://www.whatwg.org/specs/web-apps/current-work/#dom-document-getelementsbyname
     fn GetElementsByName(&self, name: DOMString) -> Temporary<NodeList> {
         self.createNodeList(|node| {
@@ -53,6 +53,7 @@ impl DOMErrorName {
             error::Network => NetworkError,
             error::Abort => AbortError,
             error::Timeout => TimeoutError,
+            error::QuotaExceeded => QuotaExceededError,
             error::FailureUnknown => fail!(),
         }
     }
@@ -22,16 +22,18 @@ use dom::domtokenlist::DOMTokenList;
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlcollection::HTMLCollection;
 use dom::htmlserializer::serialize;
+use dom::mutationobserver::{AttributeMutation, queue_mutation_record};
 use dom::node::{ElementNodeTypeId, Node, NodeHelpers, NodeIterator, document_from_node};
 use dom::node::{window_from_node, LayoutNodeHelpers};
 use dom::nodelist::NodeList;
+use dom::validitystate::validity_state;
 use dom::virtualmethods::{VirtualMethods, vtable_for};
 use layout_interface::ContentChangedDocumentDamage;
 use layout_interface::MatchSelectorsDocumentDamage;
 use style;
 use servo_util::namespace;
 use servo_util::namespace::{Namespace, Null};
-use servo_util::str::{DOMString, null_str_as_empty_ref, split_html_space_chars};
+use servo_util::str::{DOMString, null_str_as_empty_ref, parse_integer, split_html_space_chars};
 
 use std::ascii::StrAsciiExt;
 use std::cell::{Cell, RefCell};
@@ -200,6 +202,11 @@ pub trait ElementHelpers {
     fn html_element_in_html_document(&self) -> bool;
     fn get_local_name<'a>(&'a self) -> &'a str;
     fn get_namespace<'a>(&'a self) -> &'a Namespace;
+    fn tab_index(&self) -> Option<i32>;
+    fn is_focusable_by_default(&self) -> bool;
+    fn is_actually_disabled(&self) -> bool;
+    fn is_focusable_area(&self) -> bool;
+    fn is_sequentially_focusable(&self) -> bool;
 }
 
 impl<'a> ElementHelpers for JSRef<'a, Element> {
@@ -216,6 +223,67 @@ impl<'a> ElementHelpers for JSRef<'a, Element> {
     fn get_namespace<'a>(&'a self) -> &'a Namespace {
         &self.deref().namespace
     }
+
+    /// Returns the value of the `tabindex` attribute, if it is a valid integer.
+    fn tab_index(&self) -> Option<i32> {
+        let attr = self.get_attribute(Null, "tabindex").root();
+        attr.and_then(|attr| parse_integer(attr.deref().Value().as_slice()))
+    }
+
+    /// Returns true for the elements that can be focused without a `tabindex` attribute: links
+    /// and enabled form controls.
+    fn is_focusable_by_default(&self) -> bool {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        match node.type_id() {
+            ElementNodeTypeId(HTMLAnchorElementTypeId) |
+            ElementNodeTypeId(HTMLAreaElementTypeId) => {
+                self.get_attribute(Null, "href").is_some()
+            }
+            ElementNodeTypeId(HTMLInputElementTypeId) => {
+                let input_type = self.get_string_attribute("type");
+                !input_type.as_slice().eq_ignore_ascii_case("hidden")
+            }
+            ElementNodeTypeId(HTMLButtonElementTypeId) |
+            ElementNodeTypeId(HTMLSelectElementTypeId) |
+            ElementNodeTypeId(HTMLTextAreaElementTypeId) |
+            ElementNodeTypeId(HTMLIFrameElementTypeId) => true,
+            _ => false,
+        }
+    }
+
+    // http://www.whatwg.org/html/#concept-element-disabled
+    fn is_actually_disabled(&self) -> bool {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        match node.type_id() {
+            ElementNodeTypeId(HTMLInputElementTypeId) |
+            ElementNodeTypeId(HTMLButtonElementTypeId) |
+            ElementNodeTypeId(HTMLSelectElementTypeId) |
+            ElementNodeTypeId(HTMLTextAreaElementTypeId) => {
+                self.get_attribute(Null, "disabled").is_some()
+            }
+            _ => false,
+        }
+    }
+
+    // http://www.whatwg.org/html/#focusable-area
+    fn is_focusable_area(&self) -> bool {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if !node.is_in_doc() {
+            return false
+        }
+
+        // Disabled form controls can't be focused, whatever their `tabindex`.
+        if self.is_actually_disabled() {
+            return false
+        }
+
+        self.tab_index().is_some() || self.is_focusable_by_default()
+    }
+
+    // http://www.whatwg.org/html/#sequentially-focusable
+    fn is_sequentially_focusable(&self) -> bool {
+        self.is_focusable_area() && self.tab_index().map_or(true, |index| index >= 0)
+    }
 }
 
 pub trait AttributeHandlers {
@@ -242,6 +310,8 @@ pub trait AttributeHandlers {
     fn set_tokenlist_attribute(&self, name: &str, value: DOMString);
     fn get_uint_attribute(&self, name: &str) -> u32;
     fn set_uint_attribute(&self, name: &str, value: u32);
+    fn get_bool_attribute(&self, name: &str) -> bool;
+    fn set_bool_attribute(&self, name: &str, value: bool);
 }
 
 impl<'a> AttributeHandlers for JSRef<'a, Element> {
@@ -326,6 +396,11 @@ impl<'a> AttributeHandlers for JSRef<'a, Element> {
                 {
                     let node: &JSRef<Node> = NodeCast::from_ref(self);
                     node.wait_until_safe_to_modify_dom();
+
+                    let attr = self.deref().attrs.borrow().get(idx).root();
+                    queue_mutation_record(node, AttributeMutation(attr.local_name.clone(),
+                                                                  attr.namespace.clone(),
+                                                                  Some(attr.Value())));
                 }
 
                 if namespace == namespace::Null {
@@ -403,6 +478,21 @@ impl<'a> AttributeHandlers for JSRef<'a, Element> {
         assert!(name == name.to_ascii_lower().as_slice());
         self.set_attribute(name, UIntAttrValue(value.to_str(), value));
     }
+
+    fn get_bool_attribute(&self, name: &str) -> bool {
+        assert!(name == name.to_ascii_lower().as_slice());
+        self.get_attribute(Null, name).is_some()
+    }
+    fn set_bool_attribute(&self, name: &str, value: bool) {
+        if self.get_bool_attribute(name) == value {
+            return;
+        }
+        if value {
+            self.set_string_attribute(name, "".to_string());
+        } else {
+            let _ = self.remove_attribute(Null, name.to_string());
+        }
+    }
 }
 
 impl Element {
@@ -787,7 +877,7 @@ impl<'a> VirtualMethods for JSRef<'a, Element> {
         match name.as_slice() {
             "style" => {
                 let doc = document_from_node(self).root();
-                let base_url = doc.deref().url().clone();
+                let base_url = doc.deref().url();
                 let style = Some(style::parse_style_attribute(value.as_slice(), &base_url));
                 *self.deref().style_attribute.deref().borrow_mut() = style;
             }
@@ -862,6 +952,12 @@ impl<'a> VirtualMethods for JSRef<'a, Element> {
             }
             _ => ()
         }
+
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.get_focus_state() {
+            let doc = document_from_node(self).root();
+            doc.deref().focus_fixup();
+        }
     }
 }
 
@@ -893,4 +989,14 @@ impl<'a> style::TElement for JSRef<'a, Element> {
         let node: &JSRef<Node> = NodeCast::from_ref(self);
         node.get_hover_state()
     }
+    fn get_focus_state(&self) -> bool {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.get_focus_state()
+    }
+    fn get_valid_state(&self) -> bool {
+        validity_state(self) == Some(true)
+    }
+    fn get_invalid_state(&self) -> bool {
+        validity_state(self) == Some(false)
+    }
 }
@@ -11,6 +11,7 @@ use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::eventtarget::EventTarget;
 use dom::window::Window;
 use servo_util::str::DOMString;
+use servo_msg::constellation_msg::{Key, KeyModifiers, KeyState};
 use servo_util::geometry::PagePx;
 use std::cell::{Cell, RefCell};
 
@@ -25,7 +26,8 @@ pub enum Event_ {
     ClickEvent(uint, Point2D<f32>),
     MouseDownEvent(uint, Point2D<f32>),
     MouseUpEvent(uint, Point2D<f32>),
-    MouseMoveEvent(Point2D<f32>)
+    MouseMoveEvent(Point2D<f32>),
+    KeyEvent(Key, KeyState, KeyModifiers, Option<char>),
 }
 
 #[deriving(Encodable)]
@@ -39,10 +41,14 @@ pub enum EventPhase {
 #[deriving(Eq, Encodable)]
 pub enum EventTypeId {
     CustomEventTypeId,
+    FocusEventTypeId,
+    HashChangeEventTypeId,
     HTMLEventTypeId,
-    KeyEventTypeId,
+    KeyboardEventTypeId,
     MouseEventTypeId,
+    PopStateEventTypeId,
     ProgressEventTypeId,
+    StorageEventTypeId,
     UIEventTypeId
 }
 
@@ -0,0 +1,85 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::FocusEventBinding;
+use dom::bindings::codegen::InheritTypes::{UIEventCast, FocusEventDerived};
+use dom::bindings::error::Fallible;
+use dom::bindings::js::{JS, JSRef, RootedReference, Temporary, OptionalSettable};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::event::{Event, FocusEventTypeId};
+use dom::eventtarget::EventTarget;
+use dom::uievent::{UIEvent, UIEventMethods};
+use dom::window::Window;
+use servo_util::str::DOMString;
+use std::cell::Cell;
+
+#[deriving(Encodable)]
+pub struct FocusEvent {
+    pub uievent: UIEvent,
+    pub related_target: Cell<Option<JS<EventTarget>>>
+}
+
+impl FocusEventDerived for Event {
+    fn is_focusevent(&self) -> bool {
+        self.type_id == FocusEventTypeId
+    }
+}
+
+impl FocusEvent {
+    pub fn new_inherited() -> FocusEvent {
+        FocusEvent {
+            uievent: UIEvent::new_inherited(FocusEventTypeId),
+            related_target: Cell::new(None)
+        }
+    }
+
+    pub fn new_uninitialized(window: &JSRef<Window>) -> Temporary<FocusEvent> {
+        reflect_dom_object(box FocusEvent::new_inherited(),
+                           window,
+                           FocusEventBinding::Wrap)
+    }
+
+    pub fn new(window: &JSRef<Window>,
+               type_: DOMString,
+               canBubble: bool,
+               cancelable: bool,
+               view: Option<JSRef<Window>>,
+               detail: i32,
+               relatedTarget: Option<JSRef<EventTarget>>) -> Temporary<FocusEvent> {
+        let ev = FocusEvent::new_uninitialized(window).root();
+        {
+            let uievent: &JSRef<UIEvent> = UIEventCast::from_ref(&*ev);
+            uievent.InitUIEvent(type_, canBubble, cancelable, view, detail);
+        }
+        ev.deref().related_target.assign(relatedTarget);
+        Temporary::from_rooted(&*ev)
+    }
+
+    pub fn Constructor(owner: &JSRef<Window>,
+                       type_: DOMString,
+                       init: &FocusEventBinding::FocusEventInit) -> Fallible<Temporary<FocusEvent>> {
+        let event = FocusEvent::new(owner, type_, init.parent.parent.bubbles,
+                                    init.parent.parent.cancelable,
+                                    init.parent.view.root_ref(),
+                                    init.parent.detail,
+                                    init.relatedTarget.root_ref());
+        Ok(event)
+    }
+}
+
+pub trait FocusEventMethods {
+    fn GetRelatedTarget(&self) -> Option<Temporary<EventTarget>>;
+}
+
+impl<'a> FocusEventMethods for JSRef<'a, FocusEvent> {
+    fn GetRelatedTarget(&self) -> Option<Temporary<EventTarget>> {
+        self.related_target.get().clone().map(|target| Temporary::new(target))
+    }
+}
+
+impl Reflectable for FocusEvent {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.uievent.reflector()
+    }
+}
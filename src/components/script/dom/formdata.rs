@@ -8,7 +8,7 @@ use dom::bindings::js::{JS, JSRef, Temporary, OptionalUnrootable};
 use dom::bindings::trace::Traceable;
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
 use dom::blob::Blob;
-use dom::htmlformelement::HTMLFormElement;
+use dom::htmlformelement::{HTMLFormElement, HTMLFormElementHelpers};
 use dom::window::Window;
 use servo_util::str::DOMString;
 use collections::hashmap::HashMap;
@@ -43,7 +43,12 @@ impl FormData {
     }
 
     pub fn Constructor(window: &JSRef<Window>, form: Option<JSRef<HTMLFormElement>>) -> Fallible<Temporary<FormData>> {
-        Ok(FormData::new(form, window))
+        let dataset = form.as_ref().map_or(vec!(), |form| form.get_form_dataset(None));
+        let formdata = FormData::new(form, window).root();
+        for (name, value) in dataset.move_iter() {
+            formdata.deref().Append_(name, value);
+        }
+        Ok(Temporary::from_rooted(&*formdata))
     }
 }
 
@@ -0,0 +1,187 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::HistoryBinding;
+use dom::bindings::error::{ErrorResult, Security};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::trace::{Traceable, Untraceable};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::window::{Window, WindowHelpers};
+use servo_msg::constellation_msg::{ConstellationChan, GetHistoryLengthMsg, HistoryStateId};
+use servo_msg::constellation_msg::{Load, NavigationType, Replace};
+use servo_msg::constellation_msg::{SameDocumentNavigationMsg, TraverseHistoryMsg};
+use servo_net::resource_task::LoadData;
+use servo_util::str::DOMString;
+use servo_util::url::try_parse_url;
+
+use js::jsapi::JSContext;
+use js::jsval::{JSVal, NullValue};
+
+use collections::hashmap::HashMap;
+use std::cell::{Cell, RefCell};
+use std::comm::channel;
+
+use serialize::{Encoder, Encodable};
+
+#[deriving(Encodable)]
+pub struct History {
+    pub reflector_: Reflector,
+    pub window: JS<Window>,
+    /// The state objects of the session history entries this document added, which the
+    /// constellation refers to by their ids.
+    // FIXME: these should be structured clones, and should be dropped once the constellation
+    // evicts their entries.
+    pub states: Traceable<RefCell<HashMap<HistoryStateId, Traceable<JSVal>>>>,
+    pub current_state: Untraceable<Cell<Option<HistoryStateId>>>,
+    pub next_state_id: Untraceable<Cell<uint>>,
+}
+
+impl History {
+    pub fn new_inherited(window: &JSRef<Window>) -> History {
+        History {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            states: Traceable::new(RefCell::new(HashMap::new())),
+            current_state: Untraceable::new(Cell::new(None)),
+            next_state_id: Untraceable::new(Cell::new(0)),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>) -> Temporary<History> {
+        reflect_dom_object(box History::new_inherited(window),
+                           window,
+                           HistoryBinding::Wrap)
+    }
+}
+
+pub trait HistoryHelpers {
+    fn current_state(&self) -> JSVal;
+    fn set_current_state(&self, state_id: Option<HistoryStateId>);
+}
+
+impl<'a> HistoryHelpers for JSRef<'a, History> {
+    fn current_state(&self) -> JSVal {
+        match self.current_state.deref().get() {
+            Some(state_id) => match self.states.deref().borrow().find(&state_id) {
+                Some(state) => *state.deref(),
+                None => NullValue(),
+            },
+            None => NullValue(),
+        }
+    }
+
+    fn set_current_state(&self, state_id: Option<HistoryStateId>) {
+        self.current_state.deref().set(state_id);
+    }
+}
+
+trait PrivateHistoryHelpers {
+    fn add_state(&self, data: JSVal, url: Option<DOMString>,
+                 navigation_type: NavigationType) -> ErrorResult;
+}
+
+impl<'a> PrivateHistoryHelpers for JSRef<'a, History> {
+    /// Adds (or, for a `Replace` navigation, replaces) a session history entry for this
+    /// document with the given state object, and changes the document's address to `url`.
+    // http://www.whatwg.org/html/#dom-history-pushstate
+    fn add_state(&self, data: JSVal, url: Option<DOMString>,
+                 navigation_type: NavigationType) -> ErrorResult {
+        let window = self.window.root();
+        let old_url = window.deref().get_url();
+        let url = match url {
+            Some(url) => match try_parse_url(url.as_slice(), Some(old_url.clone())) {
+                Ok(url) => url,
+                Err(_) => return Err(Security),
+            },
+            None => old_url.clone(),
+        };
+        if url.scheme != old_url.scheme || url.host != old_url.host ||
+           url.port != old_url.port {
+            return Err(Security);
+        }
+
+        let state_id = HistoryStateId(self.next_state_id.deref().get());
+        self.next_state_id.deref().set(self.next_state_id.deref().get() + 1);
+        {
+            let mut states = self.states.deref().borrow_mut();
+            match (navigation_type, self.current_state.deref().get()) {
+                (Replace, Some(old_state_id)) => {
+                    states.pop(&old_state_id);
+                }
+                _ => (),
+            }
+            states.insert(state_id, Traceable::new(data));
+        }
+        self.set_current_state(Some(state_id));
+
+        window.deref().set_url(url.clone());
+        let page = window.deref().page();
+        let ConstellationChan(ref chan) = *page.constellation_chan;
+        chan.send(SameDocumentNavigationMsg(page.id, url, Some(state_id), navigation_type));
+        Ok(())
+    }
+}
+
+pub trait HistoryMethods {
+    fn Length(&self) -> u32;
+    fn State(&self, _cx: *mut JSContext) -> JSVal;
+    fn Go(&self, delta: i32);
+    fn Back(&self);
+    fn Forward(&self);
+    fn PushState(&self, _cx: *mut JSContext, data: JSVal, title: DOMString,
+                 url: Option<DOMString>) -> ErrorResult;
+    fn ReplaceState(&self, _cx: *mut JSContext, data: JSVal, title: DOMString,
+                    url: Option<DOMString>) -> ErrorResult;
+}
+
+impl<'a> HistoryMethods for JSRef<'a, History> {
+    fn Length(&self) -> u32 {
+        let window = self.window.root();
+        let (chan, port) = channel();
+        let ConstellationChan(ref constellation_chan) = *window.deref().page().constellation_chan;
+        constellation_chan.send(GetHistoryLengthMsg(chan));
+        port.recv() as u32
+    }
+
+    fn State(&self, _cx: *mut JSContext) -> JSVal {
+        self.current_state()
+    }
+
+    // http://www.whatwg.org/html/#dom-history-go
+    fn Go(&self, delta: i32) {
+        let window = self.window.root();
+        if delta == 0 {
+            let url = window.deref().get_url();
+            window.deref().load_data(LoadData::new(url), Replace);
+            return;
+        }
+        let ConstellationChan(ref chan) = *window.deref().page().constellation_chan;
+        chan.send(TraverseHistoryMsg(delta as int));
+    }
+
+    fn Back(&self) {
+        self.Go(-1)
+    }
+
+    fn Forward(&self) {
+        self.Go(1)
+    }
+
+    // The title is ignored, as other browsers do.
+    fn PushState(&self, _cx: *mut JSContext, data: JSVal, _title: DOMString,
+                 url: Option<DOMString>) -> ErrorResult {
+        self.add_state(data, url, Load)
+    }
+
+    fn ReplaceState(&self, _cx: *mut JSContext, data: JSVal, _title: DOMString,
+                    url: Option<DOMString>) -> ErrorResult {
+        self.add_state(data, url, Replace)
+    }
+}
+
+impl Reflectable for History {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
@@ -42,11 +42,47 @@ impl HTMLBodyElement {
 }
 
 pub trait HTMLBodyElementMethods {
+    fn GetOnhashchange(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnhashchange(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOnpopstate(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnpopstate(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOnstorage(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnstorage(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnunload(&self) -> Option<EventHandlerNonNull>;
     fn SetOnunload(&self, listener: Option<EventHandlerNonNull>);
 }
 
 impl<'a> HTMLBodyElementMethods for JSRef<'a, HTMLBodyElement> {
+    fn GetOnhashchange(&self) -> Option<EventHandlerNonNull> {
+        let win = window_from_node(self).root();
+        win.deref().GetOnhashchange()
+    }
+
+    fn SetOnhashchange(&self, listener: Option<EventHandlerNonNull>) {
+        let win = window_from_node(self).root();
+        win.deref().SetOnhashchange(listener)
+    }
+
+    fn GetOnpopstate(&self) -> Option<EventHandlerNonNull> {
+        let win = window_from_node(self).root();
+        win.deref().GetOnpopstate()
+    }
+
+    fn SetOnpopstate(&self, listener: Option<EventHandlerNonNull>) {
+        let win = window_from_node(self).root();
+        win.deref().SetOnpopstate(listener)
+    }
+
+    fn GetOnstorage(&self) -> Option<EventHandlerNonNull> {
+        let win = window_from_node(self).root();
+        win.deref().GetOnstorage()
+    }
+
+    fn SetOnstorage(&self, listener: Option<EventHandlerNonNull>) {
+        let win = window_from_node(self).root();
+        win.deref().SetOnstorage(listener)
+    }
+
     fn GetOnunload(&self) -> Option<EventHandlerNonNull> {
         let win = window_from_node(self).root();
         win.deref().GetOnunload()
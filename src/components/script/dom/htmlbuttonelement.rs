@@ -2,20 +2,33 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrMethods;
 use dom::bindings::codegen::Bindings::HTMLButtonElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLButtonElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
-use dom::document::Document;
-use dom::element::HTMLButtonElementTypeId;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLButtonElementDerived};
+use dom::bindings::codegen::InheritTypes::NodeCast;
+use dom::bindings::js::{JSRef, Temporary, OptionalRootable};
+use dom::bindings::trace::Traceable;
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, HTMLButtonElementTypeId};
+use dom::event::{Event, EventMethods};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
-use dom::validitystate::ValidityState;
+use dom::htmlformelement::{FormEncType, FormMethod, HTMLFormElementHelpers, ButtonElement};
+use dom::htmlformelement::{NotFromFormSubmitMethod, form_owner, resolve_action};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
+use dom::validitystate::{Validatable, ValidityFlags, ValidityState, check_validity};
+use dom::validitystate::{report_validity, validation_message, will_validate};
+use dom::virtualmethods::VirtualMethods;
+use servo_util::namespace::Null;
 use servo_util::str::DOMString;
 
+use std::ascii::StrAsciiExt;
+use std::cell::RefCell;
+
 #[deriving(Encodable)]
 pub struct HTMLButtonElement {
-    pub htmlelement: HTMLElement
+    pub htmlelement: HTMLElement,
+    custom_validity: Traceable<RefCell<DOMString>>,
 }
 
 impl HTMLButtonElementDerived for EventTarget {
@@ -27,7 +40,8 @@ impl HTMLButtonElementDerived for EventTarget {
 impl HTMLButtonElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLButtonElement {
         HTMLButtonElement {
-            htmlelement: HTMLElement::new_inherited(HTMLButtonElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLButtonElementTypeId, localName, document),
+            custom_validity: Traceable::new(RefCell::new(String::new())),
         }
     }
 
@@ -37,13 +51,256 @@ impl HTMLButtonElement {
     }
 }
 
+/// The states of the `type` attribute of a button.
+#[deriving(Eq, Clone)]
+pub enum ButtonType {
+    ButtonSubmit,
+    ButtonReset,
+    ButtonButton,
+}
+
+impl ButtonType {
+    // http://www.whatwg.org/html/#attr-button-type
+    fn from_attribute(value: &str) -> ButtonType {
+        match value.to_ascii_lower().as_slice() {
+            "reset" => ButtonReset,
+            "button" => ButtonButton,
+            _ => ButtonSubmit,
+        }
+    }
+
+    fn to_str(&self) -> &'static str {
+        match *self {
+            ButtonSubmit => "submit",
+            ButtonReset => "reset",
+            ButtonButton => "button",
+        }
+    }
+}
+
+pub trait HTMLButtonElementHelpers {
+    fn button_type(&self) -> ButtonType;
+}
+
+impl<'a> HTMLButtonElementHelpers for JSRef<'a, HTMLButtonElement> {
+    fn button_type(&self) -> ButtonType {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ButtonType::from_attribute(element.get_string_attribute("type").as_slice())
+    }
+}
+
 pub trait HTMLButtonElementMethods {
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn FormAction(&self) -> DOMString;
+    fn SetFormAction(&self, form_action: DOMString);
+    fn FormEnctype(&self) -> DOMString;
+    fn SetFormEnctype(&self, form_enctype: DOMString);
+    fn FormMethod(&self) -> DOMString;
+    fn SetFormMethod(&self, form_method: DOMString);
+    fn FormNoValidate(&self) -> bool;
+    fn SetFormNoValidate(&self, form_no_validate: bool);
+    fn FormTarget(&self) -> DOMString;
+    fn SetFormTarget(&self, form_target: DOMString);
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn Type(&self) -> DOMString;
+    fn SetType(&self, type_: DOMString);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+    fn WillValidate(&self) -> bool;
     fn Validity(&self) -> Temporary<ValidityState>;
+    fn ValidationMessage(&self) -> DOMString;
+    fn CheckValidity(&self) -> bool;
+    fn ReportValidity(&self) -> bool;
+    fn SetCustomValidity(&self, error: DOMString);
 }
 
 impl<'a> HTMLButtonElementMethods for JSRef<'a, HTMLButtonElement> {
+    fn Disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("disabled")
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("disabled", disabled)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-formaction
+    fn FormAction(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        resolve_action(element, element.get_string_attribute("formaction"))
+    }
+
+    fn SetFormAction(&self, form_action: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("formaction", form_action)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-formenctype
+    fn FormEnctype(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_attribute(Null, "formenctype").root().map_or(String::new(), |attr| {
+            FormEncType::from_attribute(attr.deref().Value().as_slice()).to_str().to_string()
+        })
+    }
+
+    fn SetFormEnctype(&self, form_enctype: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("formenctype", form_enctype)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-formmethod
+    fn FormMethod(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_attribute(Null, "formmethod").root().map_or(String::new(), |attr| {
+            FormMethod::from_attribute(attr.deref().Value().as_slice()).to_str().to_string()
+        })
+    }
+
+    fn SetFormMethod(&self, form_method: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("formmethod", form_method)
+    }
+
+    fn FormNoValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("formnovalidate")
+    }
+
+    fn SetFormNoValidate(&self, form_no_validate: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("formnovalidate", form_no_validate)
+    }
+
+    fn FormTarget(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("formtarget")
+    }
+
+    fn SetFormTarget(&self, form_target: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("formtarget", form_target)
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    // http://www.whatwg.org/html/#dom-button-type
+    fn Type(&self) -> DOMString {
+        self.button_type().to_str().to_string()
+    }
+
+    fn SetType(&self, type_: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("type", type_)
+    }
+
+    fn Value(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("value")
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("value", value)
+    }
+
+    fn WillValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        will_validate(element)
+    }
+
     fn Validity(&self) -> Temporary<ValidityState> {
         let window = window_from_node(self).root();
-        ValidityState::new(&*window)
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
+    }
+
+    fn ValidationMessage(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        validation_message(element)
+    }
+
+    fn CheckValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        check_validity(element)
+    }
+
+    fn ReportValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        report_validity(element)
+    }
+
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
+}
+
+/// Only submit buttons take part in constraint validation, and only with a custom error.
+// http://www.whatwg.org/html/#the-button-element
+impl<'a> Validatable for JSRef<'a, HTMLButtonElement> {
+    fn is_candidate_for_validation(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        self.button_type() == ButtonSubmit && !element.is_actually_disabled()
+    }
+
+    fn validity_flags(&self) -> ValidityFlags {
+        let mut flags = ValidityFlags::new();
+        flags.custom_error = !self.custom_validity.deref().borrow().is_empty();
+        flags
+    }
+
+    fn custom_validity(&self) -> DOMString {
+        self.custom_validity.deref().borrow().clone()
+    }
+
+    fn set_custom_validity(&self, error: DOMString) {
+        *self.custom_validity.deref().borrow_mut() = error;
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = document_from_node(self).root();
+            document.deref().content_changed();
+        }
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLButtonElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    // http://www.whatwg.org/html/#the-button-element:activation-behavior
+    fn handle_event(&self, event: &JSRef<Event>) {
+        match self.super_type() {
+            Some(s) => s.handle_event(event),
+            None => {}
+        }
+
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if event.Type().as_slice() != "click" || event.DefaultPrevented() ||
+                element.is_actually_disabled() {
+            return;
+        }
+        let form = match form_owner(element).root() {
+            Some(form) => form,
+            None => return,
+        };
+        match self.button_type() {
+            ButtonSubmit => {
+                form.deref().submit(NotFromFormSubmitMethod, ButtonElement(self.clone()))
+            }
+            ButtonReset => form.deref().reset(),
+            ButtonButton => {}
+        }
     }
 }
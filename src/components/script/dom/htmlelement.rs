@@ -5,14 +5,17 @@
 use dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
 use dom::bindings::codegen::Bindings::HTMLElementBinding;
 use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLFrameSetElementDerived};
-use dom::bindings::codegen::InheritTypes::EventTargetCast;
+use dom::bindings::codegen::InheritTypes::{EventCast, EventTargetCast, NodeCast};
 use dom::bindings::codegen::InheritTypes::{HTMLElementDerived, HTMLBodyElementDerived};
 use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::Reflectable;
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::element::{Element, ElementTypeId, HTMLElementTypeId};
-use dom::eventtarget::{EventTarget, EventTargetHelpers, NodeTargetTypeId};
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
+use dom::element::{AttributeHandlers, ElementHelpers};
+use dom::event::Event;
+use dom::eventtarget::{EventTarget, EventTargetHelpers, EventTargetMethods, NodeTargetTypeId};
+use dom::mouseevent::MouseEvent;
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
 use dom::virtualmethods::VirtualMethods;
 use dom::window::WindowMethods;
 use servo_util::namespace;
@@ -62,6 +65,11 @@ pub trait HTMLElementMethods {
     fn SetOnclick(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnload(&self) -> Option<EventHandlerNonNull>;
     fn SetOnload(&self, listener: Option<EventHandlerNonNull>);
+    fn TabIndex(&self) -> i32;
+    fn SetTabIndex(&self, tab_index: i32);
+    fn Click(&self);
+    fn Focus(&self);
+    fn Blur(&self);
 }
 
 impl<'a> HTMLElementMethods for JSRef<'a, HTMLElement> {
@@ -90,6 +98,52 @@ impl<'a> HTMLElementMethods for JSRef<'a, HTMLElement> {
             win.deref().SetOnload(listener)
         }
     }
+
+    // http://www.whatwg.org/html/#dom-tabindex
+    fn TabIndex(&self) -> i32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.tab_index().unwrap_or(if element.is_focusable_by_default() { 0 } else { -1 })
+    }
+
+    fn SetTabIndex(&self, tab_index: i32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("tabindex", tab_index.to_str());
+    }
+
+    // http://www.whatwg.org/html/#dom-click
+    fn Click(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.is_actually_disabled() {
+            return;
+        }
+        let window = window_from_node(self).root();
+        let event = MouseEvent::new(&*window, "click".to_string(), true, true,
+                                    Some(window.deref().clone()), 0, 0, 0, 0, 0,
+                                    false, false, false, false, 0, None).root();
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        let event: &JSRef<Event> = EventCast::from_ref(&*event);
+        let _ = eventtarget.DispatchEvent(event);
+    }
+
+    // http://www.whatwg.org/html/#dom-focus
+    fn Focus(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if !element.is_focusable_area() {
+            return;
+        }
+        let document = document_from_node(self).root();
+        document.deref().request_focus(Some(element));
+    }
+
+    // http://www.whatwg.org/html/#dom-blur
+    fn Blur(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if !node.get_focus_state() {
+            return;
+        }
+        let document = document_from_node(self).root();
+        document.deref().request_focus(None);
+    }
 }
 
 impl<'a> VirtualMethods for JSRef<'a, HTMLElement> {
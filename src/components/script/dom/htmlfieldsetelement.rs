@@ -63,6 +63,7 @@ impl<'a> HTMLFieldSetElementMethods for JSRef<'a, HTMLFieldSetElement> {
 
     fn Validity(&self) -> Temporary<ValidityState> {
         let window = window_from_node(self).root();
-        ValidityState::new(&*window)
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
     }
 }
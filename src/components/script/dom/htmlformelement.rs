@@ -2,15 +2,38 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrMethods;
 use dom::bindings::codegen::Bindings::HTMLFormElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLFormElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
-use dom::document::Document;
-use dom::element::HTMLFormElementTypeId;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLFormElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLButtonElementCast, HTMLFormElementDerived};
+use dom::bindings::codegen::InheritTypes::{HTMLInputElementCast, HTMLSelectElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLTextAreaElementCast, NodeCast};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable};
+use dom::document::{Document, DocumentHelpers, DocumentMethods};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, HTMLFormElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
-use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
+use dom::htmlbuttonelement::{HTMLButtonElement, HTMLButtonElementHelpers};
+use dom::htmlbuttonelement::{HTMLButtonElementMethods, ButtonSubmit};
+use dom::htmlelement::{HTMLElement, HTMLElementMethods};
+use dom::htmlinputelement::{HTMLInputElement, HTMLInputElementHelpers, HTMLInputElementMethods};
+use dom::htmlinputelement::{InputCheckbox, InputRadio, InputHidden, InputSubmit, InputReset};
+use dom::htmlinputelement::InputButton;
+use dom::htmloptionelement::{HTMLOptionElementHelpers, HTMLOptionElementMethods};
+use dom::htmlselectelement::{HTMLSelectElementHelpers, HTMLSelectElementMethods};
+use dom::htmltextareaelement::{HTMLTextAreaElementHelpers, HTMLTextAreaElementMethods};
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, document_from_node, window_from_node};
+use dom::validitystate::{check_constraints, report_problem};
+use dom::window::WindowHelpers;
+use servo_msg::constellation_msg::Load;
+use servo_util::namespace::Null;
 use servo_util::str::DOMString;
+use servo_util::url::{form_urlencode, try_parse_url};
+
+use http::headers::content_type::MediaType;
+use http::method::Post;
+use net::resource_task::LoadData;
+use std::ascii::StrAsciiExt;
+use std::rand;
 
 #[deriving(Encodable)]
 pub struct HTMLFormElement {
@@ -36,5 +59,578 @@ impl HTMLFormElement {
     }
 }
 
+/// The states of the `method` and `formmethod` attributes.
+#[deriving(Eq, Clone)]
+pub enum FormMethod {
+    FormGet,
+    FormPost,
+    FormDialog,
+}
+
+impl FormMethod {
+    // http://www.whatwg.org/html/#attr-fs-method
+    pub fn from_attribute(value: &str) -> FormMethod {
+        match value.to_ascii_lower().as_slice() {
+            "post" => FormPost,
+            "dialog" => FormDialog,
+            _ => FormGet,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            FormGet => "get",
+            FormPost => "post",
+            FormDialog => "dialog",
+        }
+    }
+}
+
+/// The states of the `enctype` and `formenctype` attributes.
+#[deriving(Eq, Clone)]
+pub enum FormEncType {
+    UrlEncoded,
+    MultipartFormData,
+    TextPlain,
+}
+
+impl FormEncType {
+    // http://www.whatwg.org/html/#attr-fs-enctype
+    pub fn from_attribute(value: &str) -> FormEncType {
+        match value.to_ascii_lower().as_slice() {
+            "multipart/form-data" => MultipartFormData,
+            "text/plain" => TextPlain,
+            _ => UrlEncoded,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            UrlEncoded => "application/x-www-form-urlencoded",
+            MultipartFormData => "multipart/form-data",
+            TextPlain => "text/plain",
+        }
+    }
+}
+
+/// Whether a submission comes from the `submit()` method, which fires no `submit` event.
+#[deriving(Eq)]
+pub enum SubmittedFrom {
+    FromFormSubmitMethod,
+    NotFromFormSubmitMethod,
+}
+
+/// The element that submits a form: a submit button, or the form itself.
+// http://www.whatwg.org/html/#submitter
+pub enum FormSubmitter<'a> {
+    FormElement(JSRef<'a, HTMLFormElement>),
+    InputElement(JSRef<'a, HTMLInputElement>),
+    ButtonElement(JSRef<'a, HTMLButtonElement>),
+}
+
+impl<'a> FormSubmitter<'a> {
+    /// Returns the value of one of the `formaction`, `formenctype`, `formmethod` and
+    /// `formtarget` attributes, which a submit button uses to override those of its form.
+    fn override_attribute(&self, name: &str) -> Option<DOMString> {
+        let element: &JSRef<Element> = match *self {
+            FormElement(_) => return None,
+            InputElement(ref input) => ElementCast::from_ref(input),
+            ButtonElement(ref button) => ElementCast::from_ref(button),
+        };
+        element.get_attribute(Null, name).root().map(|attr| attr.deref().Value())
+    }
+
+    fn is_element(&self, element: &JSRef<Element>) -> bool {
+        let submitter: &JSRef<Element> = match *self {
+            FormElement(ref form) => ElementCast::from_ref(form),
+            InputElement(ref input) => ElementCast::from_ref(input),
+            ButtonElement(ref button) => ElementCast::from_ref(button),
+        };
+        submitter == element
+    }
+}
+
+/// Returns the form that owns the given form-associated element: the one its `form` attribute
+/// names if it has one, or else its nearest form ancestor.
+// http://www.whatwg.org/html/#reset-the-form-owner
+pub fn form_owner(element: &JSRef<Element>) -> Option<Temporary<HTMLFormElement>> {
+    match element.get_attribute(Null, "form").root() {
+        Some(attr) => {
+            let document = document_from_node(element).root();
+            let target = document.deref().GetElementById(attr.deref().Value()).root();
+            return target.and_then(|target| {
+                HTMLFormElementCast::to_ref(&*target).map(|form| Temporary::from_rooted(form))
+            });
+        }
+        None => {}
+    }
+
+    let node: &JSRef<Node> = NodeCast::from_ref(element);
+    for ancestor in node.ancestors() {
+        match HTMLFormElementCast::to_ref(&ancestor) {
+            Some(form) => return Some(Temporary::from_rooted(form)),
+            None => {}
+        }
+    }
+    None
+}
+
+/// Resolves the URL in an `action` or a `formaction` attribute against the document URL. An
+/// empty URL stands for the document URL.
+// http://www.whatwg.org/html/#dom-fs-action
+pub fn resolve_action(element: &JSRef<Element>, action: DOMString) -> DOMString {
+    let document = document_from_node(element).root();
+    let base = document.deref().url();
+    if action.is_empty() {
+        return base.to_str();
+    }
+    match try_parse_url(action.as_slice(), Some(base)) {
+        Ok(url) => url.to_str(),
+        Err(_) => action,
+    }
+}
+
+/// Adds an entry to a form data set, unless its name is empty, with CRLF line breaks.
+fn append_entry(dataset: &mut Vec<(DOMString, DOMString)>, name: DOMString, value: DOMString) {
+    if name.is_empty() {
+        return;
+    }
+    dataset.push((normalize_line_breaks(name.as_slice()),
+                  normalize_line_breaks(value.as_slice())));
+}
+
+/// Turns every line break into a CRLF pair.
+// http://www.whatwg.org/html/#constructing-the-form-data-set
+fn normalize_line_breaks(value: &str) -> DOMString {
+    let mut result = String::with_capacity(value.len());
+    let mut after_cr = false;
+    for c in value.chars() {
+        match c {
+            '\r' => result.push_str("\r\n"),
+            '\n' if after_cr => {}
+            '\n' => result.push_str("\r\n"),
+            _ => result.push_char(c),
+        }
+        after_cr = c == '\r';
+    }
+    result
+}
+
+// http://www.whatwg.org/html/#multipart/form-data-encoding-algorithm
+fn multipart_form_data_encode(pairs: &[(DOMString, DOMString)], boundary: &str) -> String {
+    let mut result = String::new();
+    for &(ref name, ref value) in pairs.iter() {
+        let name = name.as_slice().replace("\"", "%22").replace("\r", "%0D").replace("\n", "%0A");
+        result.push_str(format!("--{}\r\n", boundary).as_slice());
+        result.push_str(format!("Content-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                                name).as_slice());
+        result.push_str(value.as_slice());
+        result.push_str("\r\n");
+    }
+    result.push_str(format!("--{}--\r\n", boundary).as_slice());
+    result
+}
+
+// http://www.whatwg.org/html/#text/plain-encoding-algorithm
+fn text_plain_encode(pairs: &[(DOMString, DOMString)]) -> String {
+    let mut result = String::new();
+    for &(ref name, ref value) in pairs.iter() {
+        result.push_str(format!("{}={}\r\n", name, value).as_slice());
+    }
+    result
+}
+
+pub trait HTMLFormElementHelpers {
+    fn submit(&self, submitted_from: SubmittedFrom, submitter: FormSubmitter);
+    fn get_form_dataset(&self, submitter: Option<&FormSubmitter>) -> Vec<(DOMString, DOMString)>;
+    fn reset(&self);
+    fn implicitly_submit(&self);
+    fn owns(&self, element: &JSRef<Element>) -> bool;
+    fn statically_validate(&self) -> Result<(), Vec<JS<Element>>>;
+    fn interactively_validate(&self) -> bool;
+}
+
+impl<'a> HTMLFormElementHelpers for JSRef<'a, HTMLFormElement> {
+    // http://www.whatwg.org/html/#concept-form-submit
+    fn submit(&self, submitted_from: SubmittedFrom, submitter: FormSubmitter) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if !node.is_in_doc() {
+            return;
+        }
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if submitted_from == NotFromFormSubmitMethod {
+            let no_validate = element.get_bool_attribute("novalidate") ||
+                              submitter.override_attribute("formnovalidate").is_some();
+            if !no_validate && !self.interactively_validate() {
+                return;
+            }
+            if !node.fire_simple_event("submit", true, true) {
+                return;
+            }
+        }
+
+        let action = submitter.override_attribute("formaction").map_or(self.Action(), |action| {
+            resolve_action(element, action)
+        });
+        let method = submitter.override_attribute("formmethod")
+                              .unwrap_or_else(|| element.get_string_attribute("method"));
+        let enctype = submitter.override_attribute("formenctype")
+                               .unwrap_or_else(|| element.get_string_attribute("enctype"));
+        let (method, enctype) = (FormMethod::from_attribute(method.as_slice()),
+                                 FormEncType::from_attribute(enctype.as_slice()));
+
+        let document = document_from_node(self).root();
+        let mut url = match try_parse_url(action.as_slice(), Some(document.deref().url())) {
+            Ok(url) => url,
+            Err(_) => return,
+        };
+        let dataset = self.get_form_dataset(Some(&submitter));
+
+        let load_data = match (url.scheme.as_slice(), method) {
+            // Dialogs are not supported, so there is nothing to close.
+            (_, FormDialog) => return,
+            ("http", FormGet) | ("https", FormGet) => {
+                url.query = dataset;
+                LoadData::new(url)
+            }
+            ("http", FormPost) | ("https", FormPost) => {
+                let mut load_data = LoadData::new(url);
+                let (body, type_, subtype, parameters) = match enctype {
+                    UrlEncoded => {
+                        (form_urlencode(dataset.as_slice()), "application",
+                         "x-www-form-urlencoded", vec!())
+                    }
+                    MultipartFormData => {
+                        let boundary = format!("----servoformboundary{:08x}{:08x}",
+                                               rand::random::<u32>(), rand::random::<u32>());
+                        (multipart_form_data_encode(dataset.as_slice(), boundary.as_slice()),
+                         "multipart", "form-data",
+                         vec!((String::from_str("boundary"), boundary)))
+                    }
+                    TextPlain => {
+                        (text_plain_encode(dataset.as_slice()), "text", "plain",
+                         vec!((String::from_str("charset"), String::from_str("UTF-8"))))
+                    }
+                };
+                load_data.headers.content_type = Some(MediaType {
+                    type_: String::from_str(type_),
+                    subtype: String::from_str(subtype),
+                    parameters: parameters,
+                });
+                load_data.method = Post;
+                load_data.data = Some(body);
+                load_data
+            }
+            // Other schemes navigate to the action URL as it is.
+            _ => LoadData::new(url),
+        };
+
+        let window = window_from_node(self).root();
+        window.deref().load_data(load_data, Load);
+    }
+
+    // http://www.whatwg.org/html/#constructing-the-form-data-set
+    fn get_form_dataset(&self, submitter: Option<&FormSubmitter>) -> Vec<(DOMString, DOMString)> {
+        let mut dataset = vec!();
+        let is_submitter = |element: &JSRef<Element>| {
+            submitter.map_or(false, |submitter| submitter.is_element(element))
+        };
+        let document = document_from_node(self).root();
+        let root: &JSRef<Node> = NodeCast::from_ref(&*document);
+        for node in root.traverse_preorder() {
+            let element = match ElementCast::to_ref(&node) {
+                Some(element) => element,
+                None => continue,
+            };
+            if element.is_actually_disabled() || !self.owns(element) {
+                continue;
+            }
+
+            match HTMLInputElementCast::to_ref(&node) {
+                Some(input) => {
+                    let name = input.Name();
+                    let value = match input.input_type() {
+                        InputSubmit if is_submitter(element) => input.Value(),
+                        InputSubmit | InputReset | InputButton => continue,
+                        InputCheckbox | InputRadio if !input.Checked() => continue,
+                        InputHidden if name.as_slice().eq_ignore_ascii_case("_charset_") => {
+                            "UTF-8".to_string()
+                        }
+                        _ => input.Value(),
+                    };
+                    append_entry(&mut dataset, name, value);
+                    continue;
+                }
+                None => {}
+            }
+            match HTMLButtonElementCast::to_ref(&node) {
+                Some(button) => {
+                    if button.button_type() == ButtonSubmit && is_submitter(element) {
+                        append_entry(&mut dataset, button.Name(), button.Value());
+                    }
+                    continue;
+                }
+                None => {}
+            }
+            match HTMLSelectElementCast::to_ref(&node) {
+                Some(select) => {
+                    for option in select.list_of_options().iter() {
+                        let option = option.root();
+                        if option.deref().selectedness() && !option.deref().is_disabled() {
+                            append_entry(&mut dataset, select.Name(), option.deref().Value());
+                        }
+                    }
+                    continue;
+                }
+                None => {}
+            }
+            match HTMLTextAreaElementCast::to_ref(&node) {
+                Some(textarea) => append_entry(&mut dataset, textarea.Name(), textarea.Value()),
+                None => {}
+            }
+        }
+        dataset
+    }
+
+    // http://www.whatwg.org/html/#concept-form-reset
+    fn reset(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if !node.fire_simple_event("reset", true, true) {
+            return;
+        }
+
+        let document = document_from_node(self).root();
+        let root: &JSRef<Node> = NodeCast::from_ref(&*document);
+        for node in root.traverse_preorder() {
+            match ElementCast::to_ref(&node) {
+                Some(element) if self.owns(element) => {}
+                _ => continue,
+            }
+            match HTMLInputElementCast::to_ref(&node) {
+                Some(input) => input.reset(),
+                None => {}
+            }
+            match HTMLSelectElementCast::to_ref(&node) {
+                Some(select) => select.reset(),
+                None => {}
+            }
+            match HTMLTextAreaElementCast::to_ref(&node) {
+                Some(textarea) => textarea.reset(),
+                None => {}
+            }
+        }
+    }
+
+    /// Submits the form in response to the user pressing Enter in one of its text fields.
+    // http://www.whatwg.org/html/#implicit-submission
+    fn implicitly_submit(&self) {
+        let document = document_from_node(self).root();
+        let root: &JSRef<Node> = NodeCast::from_ref(&*document);
+        let mut blocking_fields = 0u;
+        for node in root.traverse_preorder() {
+            match ElementCast::to_ref(&node) {
+                Some(element) if self.owns(element) => {}
+                _ => continue,
+            }
+            let (is_submit_button, is_textual) = match HTMLInputElementCast::to_ref(&node) {
+                Some(input) => (input.input_type() == InputSubmit, input.input_type().is_textual()),
+                None => match HTMLButtonElementCast::to_ref(&node) {
+                    Some(button) => (button.button_type() == ButtonSubmit, false),
+                    None => continue,
+                },
+            };
+            if is_submit_button {
+                // The first submit button is the default button, which does the submitting
+                // unless it is disabled.
+                let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::to_ref(&node).unwrap();
+                htmlelement.Click();
+                return;
+            }
+            if is_textual {
+                blocking_fields += 1;
+            }
+        }
+
+        if blocking_fields <= 1 {
+            self.submit(NotFromFormSubmitMethod, FormElement(self.clone()));
+        }
+    }
+
+    fn owns(&self, element: &JSRef<Element>) -> bool {
+        form_owner(element).root().map_or(false, |owner| *owner == *self)
+    }
+
+    /// Checks the constraints of the controls that the form owns, firing `invalid` at each one
+    /// that fails them. Returns the ones whose `invalid` event was not canceled if any failed.
+    // http://www.whatwg.org/html/#statically-validate-the-constraints
+    fn statically_validate(&self) -> Result<(), Vec<JS<Element>>> {
+        let mut invalid = false;
+        let mut unhandled = vec!();
+        let document = document_from_node(self).root();
+        let root: &JSRef<Node> = NodeCast::from_ref(&*document);
+        for node in root.traverse_preorder() {
+            let element: &JSRef<Element> = match ElementCast::to_ref(&node) {
+                Some(element) => element,
+                None => continue,
+            };
+            if !self.owns(element) {
+                continue;
+            }
+            match check_constraints(element) {
+                Ok(()) => {}
+                Err(not_canceled) => {
+                    invalid = true;
+                    if not_canceled {
+                        unhandled.push(JS::from_rooted(element));
+                    }
+                }
+            }
+        }
+        if invalid { Err(unhandled) } else { Ok(()) }
+    }
+
+    /// Checks the constraints of the controls that the form owns, and reports the problem with
+    /// the first one whose `invalid` event was not canceled. Returns whether they all passed.
+    // http://www.whatwg.org/html/#interactively-validate-the-constraints
+    fn interactively_validate(&self) -> bool {
+        match self.statically_validate() {
+            Ok(()) => true,
+            Err(unhandled) => {
+                match unhandled.iter().next() {
+                    Some(element) => report_problem(&*element.root()),
+                    None => {}
+                }
+                false
+            }
+        }
+    }
+}
+
 pub trait HTMLFormElementMethods {
+    fn AcceptCharset(&self) -> DOMString;
+    fn SetAcceptCharset(&self, accept_charset: DOMString);
+    fn Action(&self) -> DOMString;
+    fn SetAction(&self, action: DOMString);
+    fn Enctype(&self) -> DOMString;
+    fn SetEnctype(&self, enctype: DOMString);
+    fn Encoding(&self) -> DOMString;
+    fn SetEncoding(&self, encoding: DOMString);
+    fn Method(&self) -> DOMString;
+    fn SetMethod(&self, method: DOMString);
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn NoValidate(&self) -> bool;
+    fn SetNoValidate(&self, no_validate: bool);
+    fn Target(&self) -> DOMString;
+    fn SetTarget(&self, target: DOMString);
+    fn Submit(&self);
+    fn Reset(&self);
+    fn CheckValidity(&self) -> bool;
+    fn ReportValidity(&self) -> bool;
+}
+
+impl<'a> HTMLFormElementMethods for JSRef<'a, HTMLFormElement> {
+    fn AcceptCharset(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("accept-charset")
+    }
+
+    fn SetAcceptCharset(&self, accept_charset: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("accept-charset", accept_charset)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-action
+    fn Action(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        resolve_action(element, element.get_string_attribute("action"))
+    }
+
+    fn SetAction(&self, action: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("action", action)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-enctype
+    fn Enctype(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let enctype = element.get_string_attribute("enctype");
+        FormEncType::from_attribute(enctype.as_slice()).to_str().to_string()
+    }
+
+    fn SetEnctype(&self, enctype: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("enctype", enctype)
+    }
+
+    // http://www.whatwg.org/html/#dom-form-encoding
+    fn Encoding(&self) -> DOMString {
+        self.Enctype()
+    }
+
+    fn SetEncoding(&self, encoding: DOMString) {
+        self.SetEnctype(encoding)
+    }
+
+    // http://www.whatwg.org/html/#dom-fs-method
+    fn Method(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let method = element.get_string_attribute("method");
+        FormMethod::from_attribute(method.as_slice()).to_str().to_string()
+    }
+
+    fn SetMethod(&self, method: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("method", method)
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    fn NoValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("novalidate")
+    }
+
+    fn SetNoValidate(&self, no_validate: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("novalidate", no_validate)
+    }
+
+    fn Target(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("target")
+    }
+
+    fn SetTarget(&self, target: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("target", target)
+    }
+
+    // http://www.whatwg.org/html/#dom-form-submit
+    fn Submit(&self) {
+        self.submit(FromFormSubmitMethod, FormElement(self.clone()));
+    }
+
+    // http://www.whatwg.org/html/#dom-form-reset
+    fn Reset(&self) {
+        self.reset();
+    }
+
+    // http://www.whatwg.org/html/#dom-form-checkvalidity
+    fn CheckValidity(&self) -> bool {
+        self.statically_validate().is_ok()
+    }
+
+    // http://www.whatwg.org/html/#dom-form-reportvalidity
+    fn ReportValidity(&self) -> bool {
+        self.interactively_validate()
+    }
 }
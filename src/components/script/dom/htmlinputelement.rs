@@ -2,19 +2,135 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::{AttrMethods, AttrValue};
 use dom::bindings::codegen::Bindings::HTMLInputElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLInputElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
-use dom::document::Document;
-use dom::element::HTMLInputElementTypeId;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLInputElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLInputElementDerived, KeyboardEventCast, NodeCast};
+use dom::bindings::error::{ErrorResult, IndexSize};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable};
+use dom::bindings::trace::{Traceable, Untraceable};
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, HTMLInputElementTypeId};
+use dom::element::RawLayoutElementHelpers;
+use dom::event::{Event, EventMethods};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
-use servo_util::str::DOMString;
+use dom::htmlformelement::{HTMLFormElementHelpers, InputElement, NotFromFormSubmitMethod};
+use dom::htmlformelement::form_owner;
+use dom::keyboardevent::KeyboardEventMethods;
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId, RawLayoutNodeHelpers, document_from_node};
+use dom::node::window_from_node;
+use dom::validitystate::{Validatable, ValidityFlags, ValidityState, check_validity};
+use dom::validitystate::{length_flags, length_limit, report_validity, validation_message};
+use dom::validitystate::will_validate;
+use dom::virtualmethods::VirtualMethods;
+use dom::window::Window;
+use servo_util::namespace::Null;
+use servo_util::str::{DOMString, HTML_SPACE_CHARACTERS, parse_floating_point_number};
+use textinput::{TextInput, Single, SelectionDirection, NoDirection, NotHandled, Edited};
+
+use js::jsapi::{JS_ClearPendingException, JS_ExecuteRegExpNoStatics};
+use js::jsapi::JS_NewUCRegExpObjectNoStatics;
+use js::jsapi::JSObject;
+use js::jsval::{JSVal, NullValue, ObjectValue, UndefinedValue};
+use libc::size_t;
+
+use std::ascii::StrAsciiExt;
+use std::cell::{Cell, RefCell, RefMut};
+use std::cmp::max;
+use url;
+
+/// The default width of a text field, in characters.
+static DEFAULT_INPUT_SIZE: u32 = 20;
+
+/// The character that stands for each character of a password.
+static PASSWORD_REPLACEMENT_CHAR: char = '•';
+
+/// The step of a number input without a valid `step` attribute.
+static DEFAULT_STEP: f64 = 1.0;
+
+/// How far from a whole number of steps a number can be without suffering from a step mismatch,
+/// to allow for rounding errors.
+static STEP_EPSILON: f64 = 1e-9;
+
+/// The states of the `type` attribute that we support. Any other keyword is a text field.
+#[deriving(Eq, Clone)]
+pub enum InputType {
+    InputText,
+    InputPassword,
+    InputEmail,
+    InputUrl,
+    InputNumber,
+    InputCheckbox,
+    InputRadio,
+    InputHidden,
+    InputSubmit,
+    InputReset,
+    InputButton,
+}
+
+impl InputType {
+    // http://www.whatwg.org/html/#attr-input-type
+    fn from_attribute(value: &str) -> InputType {
+        match value.to_ascii_lower().as_slice() {
+            "password" => InputPassword,
+            "email" => InputEmail,
+            "url" => InputUrl,
+            "number" => InputNumber,
+            "checkbox" => InputCheckbox,
+            "radio" => InputRadio,
+            "hidden" => InputHidden,
+            "submit" => InputSubmit,
+            "reset" => InputReset,
+            "button" => InputButton,
+            _ => InputText,
+        }
+    }
+
+    fn to_str(&self) -> &'static str {
+        match *self {
+            InputText => "text",
+            InputPassword => "password",
+            InputEmail => "email",
+            InputUrl => "url",
+            InputNumber => "number",
+            InputCheckbox => "checkbox",
+            InputRadio => "radio",
+            InputHidden => "hidden",
+            InputSubmit => "submit",
+            InputReset => "reset",
+            InputButton => "button",
+        }
+    }
+
+    /// Returns true if the user edits the value of controls of this type as text.
+    pub fn is_textual(&self) -> bool {
+        match *self {
+            InputText | InputPassword | InputEmail | InputUrl | InputNumber => true,
+            _ => false,
+        }
+    }
+}
 
 #[deriving(Encodable)]
 pub struct HTMLInputElement {
     pub htmlelement: HTMLElement,
+    input_type: Untraceable<Cell<InputType>>,
+    checked: Traceable<Cell<bool>>,
+    /// Whether the checkedness was changed by the user or by script, after which the `checked`
+    /// attribute no longer affects it.
+    checked_changed: Traceable<Cell<bool>>,
+    /// Whether the value was changed by the user or by script, after which the `value`
+    /// attribute no longer affects it.
+    value_changed: Traceable<Cell<bool>>,
+    /// Whether the user edited the value since the element got the focus, in which case a
+    /// `change` event is due when it loses the focus.
+    edited_since_focus: Traceable<Cell<bool>>,
+    textinput: Untraceable<RefCell<TextInput>>,
+    custom_validity: Traceable<RefCell<DOMString>>,
+    /// The regular expression compiled from the `pattern` attribute: undefined until it is first
+    /// needed and after the attribute changes, and null if the attribute doesn't compile.
+    compiled_pattern: Traceable<Cell<Traceable<JSVal>>>,
 }
 
 impl HTMLInputElementDerived for EventTarget {
@@ -26,7 +142,15 @@ impl HTMLInputElementDerived for EventTarget {
 impl HTMLInputElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLInputElement {
         HTMLInputElement {
-            htmlelement: HTMLElement::new_inherited(HTMLInputElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLInputElementTypeId, localName, document),
+            input_type: Untraceable::new(Cell::new(InputText)),
+            checked: Traceable::new(Cell::new(false)),
+            checked_changed: Traceable::new(Cell::new(false)),
+            value_changed: Traceable::new(Cell::new(false)),
+            edited_since_focus: Traceable::new(Cell::new(false)),
+            textinput: Untraceable::new(RefCell::new(TextInput::new(Single, "".to_string()))),
+            custom_validity: Traceable::new(RefCell::new(String::new())),
+            compiled_pattern: Traceable::new(Cell::new(Traceable::new(UndefinedValue()))),
         }
     }
 
@@ -36,5 +160,864 @@ impl HTMLInputElement {
     }
 }
 
+pub trait LayoutHTMLInputElementHelpers {
+    unsafe fn get_input_type_for_layout(&self) -> InputType;
+    unsafe fn get_value_for_layout(&self) -> String;
+    unsafe fn get_caret_for_layout(&self) -> Option<uint>;
+    unsafe fn get_checked_state_for_layout(&self) -> bool;
+    unsafe fn get_size_for_layout(&self) -> u32;
+}
+
+impl LayoutHTMLInputElementHelpers for JS<HTMLInputElement> {
+    unsafe fn get_input_type_for_layout(&self) -> InputType {
+        (*self.unsafe_get()).input_type.deref().get()
+    }
+
+    /// Returns the text that the control shows: the value of a text field, with every character
+    /// of a password masked, or the label of a button.
+    unsafe fn get_value_for_layout(&self) -> String {
+        let input = &*self.unsafe_get();
+        match input.input_type.deref().get() {
+            InputText | InputEmail | InputUrl | InputNumber => {
+                input.textinput.deref().borrow().get_content()
+            }
+            InputPassword => {
+                let len = input.textinput.deref().borrow().len();
+                String::from_char(len, PASSWORD_REPLACEMENT_CHAR)
+            }
+            InputSubmit | InputReset | InputButton => {
+                match input.htmlelement.element.get_attr_val_for_layout(&Null, "value") {
+                    Some(value) => value.to_string(),
+                    None if input.input_type.deref().get() == InputSubmit => "Submit".to_string(),
+                    None if input.input_type.deref().get() == InputReset => "Reset".to_string(),
+                    None => "".to_string(),
+                }
+            }
+            InputCheckbox | InputRadio | InputHidden => "".to_string(),
+        }
+    }
+
+    /// Returns the position of the caret if this is a text field that has the focus.
+    unsafe fn get_caret_for_layout(&self) -> Option<uint> {
+        let input = &*self.unsafe_get();
+        let focused = input.htmlelement.element.node.get_focus_state_for_layout();
+        if focused && input.input_type.deref().get().is_textual() {
+            Some(input.textinput.deref().borrow().caret())
+        } else {
+            None
+        }
+    }
+
+    unsafe fn get_checked_state_for_layout(&self) -> bool {
+        (*self.unsafe_get()).checked.deref().get()
+    }
+
+    unsafe fn get_size_for_layout(&self) -> u32 {
+        let element = &(*self.unsafe_get()).htmlelement.element;
+        element.get_attr_val_for_layout(&Null, "size")
+               .and_then(|size| from_str(size))
+               .and_then(|size| if size == 0 { None } else { Some(size) })
+               .unwrap_or(DEFAULT_INPUT_SIZE)
+    }
+}
+
+pub trait HTMLInputElementHelpers {
+    fn input_type(&self) -> InputType;
+    fn reset(&self);
+}
+
+impl<'a> HTMLInputElementHelpers for JSRef<'a, HTMLInputElement> {
+    fn input_type(&self) -> InputType {
+        self.input_type.deref().get()
+    }
+
+    /// Restores the value and the checkedness from the attributes.
+    // http://www.whatwg.org/html/#the-input-element:concept-form-reset-control
+    fn reset(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        self.value_changed.deref().set(false);
+        self.checked_changed.deref().set(false);
+        let value = element.get_string_attribute("value");
+        self.textinput_mut().set_content(value);
+        self.set_checked_state(element.get_bool_attribute("checked"), false);
+    }
+}
+
+trait PrivateHTMLInputElementHelpers {
+    fn set_checked_state(&self, checked: bool, dirty: bool);
+    fn uncheck_radio_group(&self);
+    fn radio_group(&self) -> Vec<JS<HTMLInputElement>>;
+    fn value_edited(&self);
+    fn textinput_mut<'b>(&'b self) -> RefMut<'b, TextInput>;
+    fn force_relayout(&self);
+    fn handle_key_event(&self, event: &JSRef<Event>);
+    fn handle_event_impl(&self, event: &JSRef<Event>);
+    fn radio_group_checked(&self) -> bool;
+    fn number_flags(&self, number: f64, flags: &mut ValidityFlags);
+    fn compiled_pattern(&self, pattern: &str) -> Option<*mut JSObject>;
+}
+
+impl<'a> PrivateHTMLInputElementHelpers for JSRef<'a, HTMLInputElement> {
+    /// Sets the checkedness. `dirty` says whether the change comes from the user or from script
+    /// rather than from the `checked` attribute.
+    fn set_checked_state(&self, checked: bool, dirty: bool) {
+        self.checked.deref().set(checked);
+        if dirty {
+            self.checked_changed.deref().set(true);
+        }
+        if checked && self.input_type.deref().get() == InputRadio {
+            self.uncheck_radio_group();
+        }
+        self.force_relayout();
+    }
+
+    /// Unchecks the other radio buttons in the radio button group.
+    fn uncheck_radio_group(&self) {
+        for other in self.radio_group().iter() {
+            other.root().deref().checked.deref().set(false);
+        }
+    }
+
+    /// Returns the other radio buttons in the radio button group of this one: those with the
+    /// same name and form owner in the same tree. A radio button without a name is in no group.
+    // http://www.whatwg.org/html/#radio-button-group
+    fn radio_group(&self) -> Vec<JS<HTMLInputElement>> {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let name = element.get_string_attribute("name");
+        if name.is_empty() {
+            return vec!();
+        }
+
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let form = form_owner(element).map(|form| JS::from_rooted(&form));
+        let root = Some(node.clone()).move_iter().chain(node.ancestors()).last().unwrap();
+        root.traverse_preorder().filter_map(|other| {
+            if &other == node {
+                return None;
+            }
+            match HTMLInputElementCast::to_ref(&other) {
+                Some(input) if input.input_type.deref().get() == InputRadio => {
+                    let other_element: &JSRef<Element> = ElementCast::from_ref(input);
+                    let other_form = form_owner(other_element).map(|form| JS::from_rooted(&form));
+                    if other_element.get_string_attribute("name") == name && other_form == form {
+                        Some(JS::from_rooted(input))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }).collect()
+    }
+
+    /// Records that the user edited the value and fires `input`.
+    fn value_edited(&self) {
+        self.value_changed.deref().set(true);
+        self.edited_since_focus.deref().set(true);
+        self.force_relayout();
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.fire_simple_event("input", true, false);
+    }
+
+    /// Borrows the editing state for a change, once layout is done reading it.
+    fn textinput_mut<'b>(&'b self) -> RefMut<'b, TextInput> {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.wait_until_safe_to_modify_dom();
+        self.textinput.deref().borrow_mut()
+    }
+
+    fn force_relayout(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = document_from_node(self).root();
+            document.deref().content_changed();
+        }
+    }
+
+    /// Edits the value of a text field in response to a key that the user pressed.
+    fn handle_key_event(&self, event: &JSRef<Event>) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if !event.IsTrusted() || !self.input_type.deref().get().is_textual() ||
+                element.get_bool_attribute("readonly") {
+            return;
+        }
+        let keyboard_event = match KeyboardEventCast::to_ref(event) {
+            Some(keyboard_event) => keyboard_event,
+            None => return,
+        };
+
+        // http://www.whatwg.org/html/#implicit-submission
+        if event.Type().as_slice() == "keydown" && keyboard_event.Key().as_slice() == "Enter" {
+            event.PreventDefault();
+            form_owner(element).root().map(|form| form.deref().implicitly_submit());
+            return;
+        }
+
+        let reaction = match event.Type().as_slice() {
+            "keydown" => self.textinput_mut().handle_keydown(keyboard_event),
+            "keypress" => self.textinput_mut().handle_keypress(keyboard_event),
+            _ => NotHandled,
+        };
+        if reaction == NotHandled {
+            return;
+        }
+
+        // Keep the page from scrolling or typing into anything else.
+        event.PreventDefault();
+        if reaction == Edited {
+            self.value_edited();
+        } else {
+            self.force_relayout();
+        }
+    }
+
+    fn handle_event_impl(&self, event: &JSRef<Event>) {
+        if event.DefaultPrevented() {
+            return;
+        }
+
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        match event.Type().as_slice() {
+            // http://www.whatwg.org/html/#checkbox-state-(type=checkbox)
+            // http://www.whatwg.org/html/#radio-button-state-(type=radio)
+            "click" => {
+                let element: &JSRef<Element> = ElementCast::from_ref(self);
+                if element.is_actually_disabled() {
+                    return;
+                }
+                let checked = match self.input_type.deref().get() {
+                    InputCheckbox => !self.checked.deref().get(),
+                    InputRadio if !self.checked.deref().get() => true,
+                    // http://www.whatwg.org/html/#submit-button-state-(type=submit)
+                    InputSubmit => {
+                        form_owner(element).root().map(|form| {
+                            form.deref().submit(NotFromFormSubmitMethod,
+                                                InputElement(self.clone()))
+                        });
+                        return;
+                    }
+                    // http://www.whatwg.org/html/#reset-button-state-(type=reset)
+                    InputReset => {
+                        form_owner(element).root().map(|form| form.deref().reset());
+                        return;
+                    }
+                    _ => return,
+                };
+                self.set_checked_state(checked, true);
+                node.fire_simple_event("input", true, false);
+                node.fire_simple_event("change", true, false);
+            }
+            "keydown" | "keypress" => self.handle_key_event(event),
+            "focus" => self.edited_since_focus.deref().set(false),
+            "blur" => {
+                if self.edited_since_focus.deref().get() {
+                    self.edited_since_focus.deref().set(false);
+                    node.fire_simple_event("change", true, false);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns true if this radio button or another one in its radio button group is checked.
+    fn radio_group_checked(&self) -> bool {
+        self.checked.deref().get() || self.radio_group().iter().any(|other| {
+            other.root().deref().checked.deref().get()
+        })
+    }
+
+    /// Checks a number against the `min`, `max` and `step` attributes.
+    // http://www.whatwg.org/html/#the-min-and-max-attributes
+    // http://www.whatwg.org/html/#the-step-attribute
+    fn number_flags(&self, number: f64, flags: &mut ValidityFlags) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let number_attribute = |name: &str| {
+            element.get_attribute(Null, name).root().and_then(|attr| {
+                parse_floating_point_number(attr.deref().Value().as_slice())
+            })
+        };
+        let min = number_attribute("min");
+        let max = number_attribute("max");
+        flags.range_underflow = min.map_or(false, |min| number < min);
+        flags.range_overflow = max.map_or(false, |max| number > max);
+
+        let step = match element.get_attribute(Null, "step").root() {
+            Some(ref attr) if attr.deref().Value().as_slice().eq_ignore_ascii_case("any") => {
+                return;
+            }
+            Some(_) => match number_attribute("step") {
+                Some(step) if step > 0.0 => step,
+                Some(_) | None => DEFAULT_STEP,
+            },
+            None => DEFAULT_STEP,
+        };
+        let base = min.or_else(|| number_attribute("value")).unwrap_or(0.0);
+        let steps = (number - base) / step;
+        flags.step_mismatch = (steps - steps.round()).abs() > STEP_EPSILON;
+    }
+
+    /// Returns the regular expression compiled from the `pattern` attribute, whose value is
+    /// given, compiling it only if the attribute changed since it was last compiled.
+    fn compiled_pattern(&self, pattern: &str) -> Option<*mut JSObject> {
+        let compiled = *self.compiled_pattern.deref().get();
+        if compiled.is_undefined() {
+            let window = window_from_node(self).root();
+            let regexp = compile_pattern(&*window, pattern);
+            let compiled = match regexp {
+                Some(regexp) => ObjectValue(unsafe { &*regexp }),
+                None => NullValue(),
+            };
+            self.compiled_pattern.deref().set(Traceable::new(compiled));
+            return regexp;
+        }
+        if compiled.is_null() { None } else { Some(compiled.to_object()) }
+    }
+}
+
 pub trait HTMLInputElementMethods {
+    fn DefaultChecked(&self) -> bool;
+    fn SetDefaultChecked(&self, default_checked: bool);
+    fn Checked(&self) -> bool;
+    fn SetChecked(&self, checked: bool);
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn Max(&self) -> DOMString;
+    fn SetMax(&self, max: DOMString);
+    fn MaxLength(&self) -> i32;
+    fn SetMaxLength(&self, max_length: i32) -> ErrorResult;
+    fn Min(&self) -> DOMString;
+    fn SetMin(&self, min: DOMString);
+    fn MinLength(&self) -> i32;
+    fn SetMinLength(&self, min_length: i32) -> ErrorResult;
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn Pattern(&self) -> DOMString;
+    fn SetPattern(&self, pattern: DOMString);
+    fn Placeholder(&self) -> DOMString;
+    fn SetPlaceholder(&self, placeholder: DOMString);
+    fn ReadOnly(&self) -> bool;
+    fn SetReadOnly(&self, read_only: bool);
+    fn Required(&self) -> bool;
+    fn SetRequired(&self, required: bool);
+    fn Size(&self) -> u32;
+    fn SetSize(&self, size: u32);
+    fn Step(&self) -> DOMString;
+    fn SetStep(&self, step: DOMString);
+    fn Type(&self) -> DOMString;
+    fn SetType(&self, type_: DOMString);
+    fn DefaultValue(&self) -> DOMString;
+    fn SetDefaultValue(&self, default_value: DOMString);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+    fn Select(&self);
+    fn SelectionStart(&self) -> u32;
+    fn SetSelectionStart(&self, start: u32);
+    fn SelectionEnd(&self) -> u32;
+    fn SetSelectionEnd(&self, end: u32);
+    fn SelectionDirection(&self) -> DOMString;
+    fn SetSelectionDirection(&self, direction: DOMString);
+    fn SetRangeText(&self, replacement: DOMString);
+    fn SetSelectionRange(&self, start: u32, end: u32, direction: Option<DOMString>);
+    fn WillValidate(&self) -> bool;
+    fn Validity(&self) -> Temporary<ValidityState>;
+    fn ValidationMessage(&self) -> DOMString;
+    fn CheckValidity(&self) -> bool;
+    fn ReportValidity(&self) -> bool;
+    fn SetCustomValidity(&self, error: DOMString);
+}
+
+impl<'a> HTMLInputElementMethods for JSRef<'a, HTMLInputElement> {
+    fn DefaultChecked(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("checked")
+    }
+
+    fn SetDefaultChecked(&self, default_checked: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("checked", default_checked)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-checked
+    fn Checked(&self) -> bool {
+        self.checked.deref().get()
+    }
+
+    fn SetChecked(&self, checked: bool) {
+        self.set_checked_state(checked, true);
+    }
+
+    fn Disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("disabled")
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("disabled", disabled)
+    }
+
+    fn Max(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("max")
+    }
+
+    fn SetMax(&self, max: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("max", max)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-maxlength
+    fn MaxLength(&self) -> i32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        length_limit(element, "maxlength").map_or(-1, |limit| limit as i32)
+    }
+
+    fn SetMaxLength(&self, max_length: i32) -> ErrorResult {
+        if max_length < 0 {
+            return Err(IndexSize);
+        }
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("maxlength", max_length.to_str());
+        Ok(())
+    }
+
+    fn Min(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("min")
+    }
+
+    fn SetMin(&self, min: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("min", min)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-minlength
+    fn MinLength(&self) -> i32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        length_limit(element, "minlength").map_or(-1, |limit| limit as i32)
+    }
+
+    fn SetMinLength(&self, min_length: i32) -> ErrorResult {
+        if min_length < 0 {
+            return Err(IndexSize);
+        }
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("minlength", min_length.to_str());
+        Ok(())
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    fn Pattern(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("pattern")
+    }
+
+    fn SetPattern(&self, pattern: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("pattern", pattern)
+    }
+
+    fn Placeholder(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("placeholder")
+    }
+
+    fn SetPlaceholder(&self, placeholder: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("placeholder", placeholder)
+    }
+
+    fn ReadOnly(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("readonly")
+    }
+
+    fn SetReadOnly(&self, read_only: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("readonly", read_only)
+    }
+
+    fn Required(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("required")
+    }
+
+    fn SetRequired(&self, required: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("required", required)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-size
+    fn Size(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match element.get_uint_attribute("size") {
+            0 => DEFAULT_INPUT_SIZE,
+            size => size,
+        }
+    }
+
+    fn SetSize(&self, size: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("size", size)
+    }
+
+    fn Step(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("step")
+    }
+
+    fn SetStep(&self, step: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("step", step)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-type
+    fn Type(&self) -> DOMString {
+        self.input_type.deref().get().to_str().to_string()
+    }
+
+    fn SetType(&self, type_: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("type", type_)
+    }
+
+    fn DefaultValue(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("value")
+    }
+
+    fn SetDefaultValue(&self, default_value: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("value", default_value)
+    }
+
+    // http://www.whatwg.org/html/#dom-input-value
+    fn Value(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match self.input_type.deref().get() {
+            InputText | InputPassword | InputEmail | InputUrl | InputNumber => {
+                self.textinput.deref().borrow().get_content()
+            }
+            InputCheckbox | InputRadio => {
+                element.get_attribute(Null, "value").root().map_or("on".to_string(), |attr| {
+                    attr.deref().Value()
+                })
+            }
+            InputHidden | InputSubmit | InputReset | InputButton => {
+                element.get_string_attribute("value")
+            }
+        }
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        if self.input_type.deref().get().is_textual() {
+            self.textinput_mut().set_content(value);
+            self.value_changed.deref().set(true);
+            self.force_relayout();
+        } else {
+            self.SetDefaultValue(value);
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-select
+    fn Select(&self) {
+        self.textinput_mut().select_all();
+        self.force_relayout();
+    }
+
+    // The selection APIs count characters rather than UTF-16 code units.
+    // http://www.whatwg.org/html/#dom-textarea/input-selectionstart
+    fn SelectionStart(&self) -> u32 {
+        self.textinput.deref().borrow().selection_start() as u32
+    }
+
+    fn SetSelectionStart(&self, start: u32) {
+        let end = self.SelectionEnd();
+        let direction = self.textinput.deref().borrow().selection_direction();
+        self.textinput_mut().set_selection_range(start as uint, max(start, end) as uint,
+                                                 direction);
+        self.force_relayout();
+    }
+
+    fn SelectionEnd(&self) -> u32 {
+        self.textinput.deref().borrow().selection_end() as u32
+    }
+
+    fn SetSelectionEnd(&self, end: u32) {
+        let start = self.SelectionStart();
+        let direction = self.textinput.deref().borrow().selection_direction();
+        self.textinput_mut().set_selection_range(start as uint, end as uint, direction);
+        self.force_relayout();
+    }
+
+    fn SelectionDirection(&self) -> DOMString {
+        self.textinput.deref().borrow().selection_direction().to_str().to_string()
+    }
+
+    fn SetSelectionDirection(&self, direction: DOMString) {
+        let (start, end) = (self.SelectionStart(), self.SelectionEnd());
+        self.SetSelectionRange(start, end, Some(direction));
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-setrangetext
+    fn SetRangeText(&self, replacement: DOMString) {
+        let (start, end) = (self.SelectionStart(), self.SelectionEnd());
+        self.textinput_mut().replace_range(start as uint, end as uint, replacement.as_slice());
+        self.value_changed.deref().set(true);
+        self.force_relayout();
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-setselectionrange
+    fn SetSelectionRange(&self, start: u32, end: u32, direction: Option<DOMString>) {
+        let direction = direction.map_or(NoDirection, |direction| {
+            SelectionDirection::from_str(direction.as_slice())
+        });
+        self.textinput_mut().set_selection_range(start as uint, end as uint, direction);
+        self.force_relayout();
+    }
+
+    fn WillValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        will_validate(element)
+    }
+
+    fn Validity(&self) -> Temporary<ValidityState> {
+        let window = window_from_node(self).root();
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
+    }
+
+    fn ValidationMessage(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        validation_message(element)
+    }
+
+    fn CheckValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        check_validity(element)
+    }
+
+    fn ReportValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        report_validity(element)
+    }
+
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
+}
+
+impl<'a> Validatable for JSRef<'a, HTMLInputElement> {
+    fn is_candidate_for_validation(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let input_type = self.input_type.deref().get();
+        match input_type {
+            InputHidden | InputReset | InputButton => false,
+            _ if input_type.is_textual() && element.get_bool_attribute("readonly") => false,
+            _ => !element.is_actually_disabled(),
+        }
+    }
+
+    fn validity_flags(&self) -> ValidityFlags {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let input_type = self.input_type.deref().get();
+        let value = self.Value();
+        let mut flags = ValidityFlags::new();
+        flags.custom_error = !self.custom_validity.deref().borrow().is_empty();
+
+        // http://www.whatwg.org/html/#attr-input-required
+        if element.get_bool_attribute("required") {
+            flags.value_missing = match input_type {
+                InputCheckbox => !self.checked.deref().get(),
+                InputRadio => !self.radio_group_checked(),
+                _ => input_type.is_textual() && value.is_empty(),
+            };
+        }
+        if !input_type.is_textual() || value.is_empty() {
+            return flags;
+        }
+
+        match input_type {
+            // http://www.whatwg.org/html/#e-mail-state-(type=email)
+            InputEmail => {
+                flags.type_mismatch = if element.get_bool_attribute("multiple") {
+                    !value.as_slice().split(',').all(|address| {
+                        is_valid_email_address(address.trim_chars(HTML_SPACE_CHARACTERS))
+                    })
+                } else {
+                    !is_valid_email_address(value.as_slice())
+                };
+            }
+            // http://www.whatwg.org/html/#url-state-(type=url)
+            InputUrl => {
+                flags.type_mismatch = url::get_scheme(value.as_slice()).is_err() ||
+                                      url::from_str(value.as_slice()).is_err();
+            }
+            // http://www.whatwg.org/html/#number-state-(type=number)
+            InputNumber => {
+                match parse_floating_point_number(value.as_slice()) {
+                    Some(number) => self.number_flags(number, &mut flags),
+                    None => flags.bad_input = true,
+                }
+            }
+            _ => {}
+        }
+
+        // http://www.whatwg.org/html/#attr-input-pattern
+        if input_type != InputNumber {
+            let regexp = element.get_attribute(Null, "pattern").root().and_then(|pattern| {
+                self.compiled_pattern(pattern.deref().Value().as_slice())
+            });
+            match regexp {
+                Some(regexp) => {
+                    let window = window_from_node(self).root();
+                    flags.pattern_mismatch = !matches_pattern(&*window, regexp, value.as_slice());
+                }
+                None => {}
+            }
+        }
+
+        // Only values that the user or script changed can be too long or too short.
+        if self.value_changed.deref().get() && input_type != InputNumber {
+            length_flags(element, value.as_slice(), &mut flags);
+        }
+        flags
+    }
+
+    fn custom_validity(&self) -> DOMString {
+        self.custom_validity.deref().borrow().clone()
+    }
+
+    fn set_custom_validity(&self, error: DOMString) {
+        *self.custom_validity.deref().borrow_mut() = error;
+        self.force_relayout();
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLInputElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    fn after_set_attr(&self, name: DOMString, value: DOMString) {
+        match self.super_type() {
+            Some(ref s) => s.after_set_attr(name.clone(), value.clone()),
+            _ => (),
+        }
+
+        match name.as_slice() {
+            "type" => self.input_type.deref().set(InputType::from_attribute(value.as_slice())),
+            "value" if !self.value_changed.deref().get() => {
+                self.textinput_mut().set_content(value);
+            }
+            "pattern" => self.compiled_pattern.deref().set(Traceable::new(UndefinedValue())),
+            "checked" if !self.checked_changed.deref().get() => {
+                self.set_checked_state(true, false);
+            }
+            _ => {}
+        }
+    }
+
+    fn before_remove_attr(&self, name: DOMString, value: DOMString) {
+        match self.super_type() {
+            Some(ref s) => s.before_remove_attr(name.clone(), value.clone()),
+            _ => (),
+        }
+
+        match name.as_slice() {
+            "type" => self.input_type.deref().set(InputText),
+            "value" if !self.value_changed.deref().get() => {
+                self.textinput_mut().set_content("".to_string());
+            }
+            "pattern" => self.compiled_pattern.deref().set(Traceable::new(UndefinedValue())),
+            "checked" if !self.checked_changed.deref().get() => {
+                self.set_checked_state(false, false);
+            }
+            _ => {}
+        }
+    }
+
+    fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
+        match name {
+            "size" => AttrValue::from_u32(value, DEFAULT_INPUT_SIZE),
+            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
+        }
+    }
+
+    fn handle_event(&self, event: &JSRef<Event>) {
+        match self.super_type() {
+            Some(s) => s.handle_event(event),
+            None => {}
+        }
+        self.handle_event_impl(event);
+    }
+}
+
+/// Returns true if `address` is a valid e-mail address.
+// http://www.whatwg.org/html/#valid-e-mail-address
+fn is_valid_email_address(address: &str) -> bool {
+    static LOCAL_PART_PUNCTUATION: &'static str = ".!#$%&'*+/=?^_`{|}~-";
+    let (local, domain) = match address.find('@') {
+        Some(index) => (address.slice_to(index), address.slice_from(index + 1)),
+        None => return false,
+    };
+    let is_local_char = |c: char| c.is_ascii() && (c.is_alphanumeric() ||
+                                                  LOCAL_PART_PUNCTUATION.contains_char(c));
+    let is_label = |label: &str| {
+        !label.is_empty() && label.len() <= 63 && !label.starts_with("-") &&
+            !label.ends_with("-") &&
+            label.chars().all(|c| c.is_ascii() && (c.is_alphanumeric() || c == '-'))
+    };
+    !local.is_empty() && local.chars().all(is_local_char) && domain.split('.').all(is_label)
+}
+
+/// Compiles the value of a `pattern` attribute into a regular expression that matches whole
+/// values, or returns `None` if it doesn't compile, in which case it imposes no constraint.
+// http://www.whatwg.org/html/#attr-input-pattern
+fn compile_pattern(window: &JSRef<Window>, pattern: &str) -> Option<*mut JSObject> {
+    let cx = window.get_cx();
+    let mut source = format!("^(?:{})$", pattern).to_utf16();
+    unsafe {
+        let regexp = JS_NewUCRegExpObjectNoStatics(cx,
+                                                   source.as_mut_ptr(),
+                                                   source.len() as size_t,
+                                                   0);
+        if regexp.is_null() {
+            JS_ClearPendingException(cx);
+            return None;
+        }
+        Some(regexp)
+    }
+}
+
+/// Returns true if `value` matches the regular expression compiled from a `pattern` attribute.
+fn matches_pattern(window: &JSRef<Window>, regexp: *mut JSObject, value: &str) -> bool {
+    let cx = window.get_cx();
+    let mut value = value.to_utf16();
+    unsafe {
+        let mut index: size_t = 0;
+        let mut result = NullValue();
+        let ok = JS_ExecuteRegExpNoStatics(cx,
+                                           regexp,
+                                           value.as_mut_ptr(),
+                                           value.len() as size_t,
+                                           &mut index,
+                                           1,
+                                           &mut result);
+        if ok == 0 {
+            JS_ClearPendingException(cx);
+            return true;
+        }
+        !result.is_null()
+    }
 }
@@ -80,7 +80,8 @@ pub trait HTMLObjectElementMethods {
 impl<'a> HTMLObjectElementMethods for JSRef<'a, HTMLObjectElement> {
     fn Validity(&self) -> Temporary<ValidityState> {
         let window = window_from_node(self).root();
-        ValidityState::new(&*window)
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
     }
 }
 
@@ -2,19 +2,33 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrMethods;
 use dom::bindings::codegen::Bindings::HTMLOptionElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLOptionElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLOptGroupElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLOptionElementDerived, HTMLSelectElementCast};
+use dom::bindings::codegen::InheritTypes::NodeCast;
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable};
+use dom::bindings::trace::Traceable;
 use dom::document::Document;
-use dom::element::HTMLOptionElementTypeId;
+use dom::element::{Element, AttributeHandlers, HTMLOptionElementTypeId};
+use dom::event::{Event, EventMethods};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
-use servo_util::str::DOMString;
+use dom::htmlselectelement::{HTMLSelectElement, HTMLSelectElementHelpers};
+use dom::node::{Node, NodeHelpers, NodeMethods, ElementNodeTypeId};
+use dom::virtualmethods::VirtualMethods;
+use servo_util::namespace::Null;
+use servo_util::str::{DOMString, split_html_space_chars};
+
+use std::cell::Cell;
 
 #[deriving(Encodable)]
 pub struct HTMLOptionElement {
-    pub htmlelement: HTMLElement
+    pub htmlelement: HTMLElement,
+    selectedness: Traceable<Cell<bool>>,
+    /// Whether the selectedness was changed by the user or by script, after which the
+    /// `selected` attribute no longer affects it.
+    dirtiness: Traceable<Cell<bool>>,
 }
 
 impl HTMLOptionElementDerived for EventTarget {
@@ -26,7 +40,9 @@ impl HTMLOptionElementDerived for EventTarget {
 impl HTMLOptionElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLOptionElement {
         HTMLOptionElement {
-            htmlelement: HTMLElement::new_inherited(HTMLOptionElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLOptionElementTypeId, localName, document),
+            selectedness: Traceable::new(Cell::new(false)),
+            dirtiness: Traceable::new(Cell::new(false)),
         }
     }
 
@@ -36,5 +52,235 @@ impl HTMLOptionElement {
     }
 }
 
+pub trait LayoutHTMLOptionElementHelpers {
+    unsafe fn get_selectedness_for_layout(&self) -> bool;
+}
+
+impl LayoutHTMLOptionElementHelpers for JS<HTMLOptionElement> {
+    unsafe fn get_selectedness_for_layout(&self) -> bool {
+        (*self.unsafe_get()).selectedness.deref().get()
+    }
+}
+
+pub trait HTMLOptionElementHelpers {
+    fn owner_select(&self) -> Option<Temporary<HTMLSelectElement>>;
+    fn selectedness(&self) -> bool;
+    fn set_selectedness(&self, selected: bool);
+    fn reset(&self);
+    fn is_disabled(&self) -> bool;
+}
+
+impl<'a> HTMLOptionElementHelpers for JSRef<'a, HTMLOptionElement> {
+    /// Returns the `<select>` element whose list of options this option is in: its parent, or
+    /// the parent of its `<optgroup>`.
+    // http://www.whatwg.org/html/#concept-select-option-list
+    fn owner_select(&self) -> Option<Temporary<HTMLSelectElement>> {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        for ancestor in node.ancestors() {
+            match HTMLSelectElementCast::to_ref(&ancestor) {
+                Some(select) => return Some(Temporary::from_rooted(select)),
+                None if HTMLOptGroupElementCast::to_ref(&ancestor).is_some() => continue,
+                None => return None,
+            }
+        }
+        None
+    }
+
+    fn selectedness(&self) -> bool {
+        self.selectedness.deref().get()
+    }
+
+    /// Sets the selectedness without marking it dirty, and without changing the selectedness of
+    /// the other options.
+    fn set_selectedness(&self, selected: bool) {
+        self.selectedness.deref().set(selected);
+    }
+
+    // http://www.whatwg.org/html/#the-option-element:concept-form-reset-control
+    fn reset(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        self.dirtiness.deref().set(false);
+        self.selectedness.deref().set(element.get_bool_attribute("selected"));
+    }
+
+    /// Returns true if the option or its `<optgroup>` has a `disabled` attribute.
+    // http://www.whatwg.org/html/#concept-option-disabled
+    fn is_disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.get_bool_attribute("disabled") {
+            return true;
+        }
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.parent_node().root().map_or(false, |parent| {
+            match HTMLOptGroupElementCast::to_ref(&*parent) {
+                Some(optgroup) => {
+                    let optgroup: &JSRef<Element> = ElementCast::from_ref(optgroup);
+                    optgroup.get_bool_attribute("disabled")
+                }
+                None => false,
+            }
+        })
+    }
+}
+
 pub trait HTMLOptionElementMethods {
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn Label(&self) -> DOMString;
+    fn SetLabel(&self, label: DOMString);
+    fn DefaultSelected(&self) -> bool;
+    fn SetDefaultSelected(&self, default_selected: bool);
+    fn Selected(&self) -> bool;
+    fn SetSelected(&self, selected: bool);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+    fn Text(&self) -> DOMString;
+    fn SetText(&self, text: DOMString);
+    fn Index(&self) -> i32;
+}
+
+impl<'a> HTMLOptionElementMethods for JSRef<'a, HTMLOptionElement> {
+    fn Disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("disabled")
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("disabled", disabled)
+    }
+
+    // http://www.whatwg.org/html/#dom-option-label
+    fn Label(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_attribute(Null, "label").root().map_or(self.Text(), |attr| {
+            attr.deref().Value()
+        })
+    }
+
+    fn SetLabel(&self, label: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("label", label)
+    }
+
+    fn DefaultSelected(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("selected")
+    }
+
+    fn SetDefaultSelected(&self, default_selected: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("selected", default_selected)
+    }
+
+    fn Selected(&self) -> bool {
+        self.selectedness.deref().get()
+    }
+
+    // http://www.whatwg.org/html/#dom-option-selected
+    fn SetSelected(&self, selected: bool) {
+        self.dirtiness.deref().set(true);
+        self.selectedness.deref().set(selected);
+        match self.owner_select().root() {
+            Some(select) => {
+                if selected {
+                    select.deref().pick(self);
+                }
+                select.deref().ask_for_reset();
+            }
+            None => {}
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-option-value
+    fn Value(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_attribute(Null, "value").root().map_or(self.Text(), |attr| {
+            attr.deref().Value()
+        })
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("value", value)
+    }
+
+    /// Returns the text content with its whitespace stripped and collapsed.
+    // http://www.whatwg.org/html/#dom-option-text
+    fn Text(&self) -> DOMString {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let content = node.GetTextContent().unwrap_or(String::new());
+        let words: Vec<&str> = split_html_space_chars(content.as_slice()).collect();
+        words.connect(" ")
+    }
+
+    fn SetText(&self, text: DOMString) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let _ = node.SetTextContent(Some(text));
+    }
+
+    // http://www.whatwg.org/html/#dom-option-index
+    fn Index(&self) -> i32 {
+        self.owner_select().root().and_then(|select| {
+            select.deref().list_of_options().iter().position(|option| {
+                *option.root() == *self
+            })
+        }).map_or(0, |index| index as i32)
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLOptionElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    fn after_set_attr(&self, name: DOMString, value: DOMString) {
+        match self.super_type() {
+            Some(ref s) => s.after_set_attr(name.clone(), value.clone()),
+            _ => (),
+        }
+
+        if name.as_slice() == "selected" && !self.dirtiness.deref().get() {
+            self.selectedness.deref().set(true);
+            match self.owner_select().root() {
+                Some(select) => {
+                    select.deref().pick(self);
+                    select.deref().ask_for_reset();
+                }
+                None => {}
+            }
+        }
+    }
+
+    fn before_remove_attr(&self, name: DOMString, value: DOMString) {
+        match self.super_type() {
+            Some(ref s) => s.before_remove_attr(name.clone(), value.clone()),
+            _ => (),
+        }
+
+        if name.as_slice() == "selected" && !self.dirtiness.deref().get() {
+            self.selectedness.deref().set(false);
+            match self.owner_select().root() {
+                Some(select) => select.deref().ask_for_reset(),
+                None => {}
+            }
+        }
+    }
+
+    /// Lets the `<select>` pick this option when the user clicks it in the list of options.
+    fn handle_event(&self, event: &JSRef<Event>) {
+        match self.super_type() {
+            Some(s) => s.handle_event(event),
+            None => {}
+        }
+
+        if event.Type().as_slice() != "click" || event.DefaultPrevented() {
+            return;
+        }
+        match self.owner_select().root() {
+            Some(select) => select.deref().option_clicked(self),
+            None => {}
+        }
+    }
 }
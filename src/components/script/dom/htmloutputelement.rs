@@ -3,10 +3,10 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::Bindings::HTMLOutputElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLOutputElementDerived;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLOutputElementDerived};
 use dom::bindings::js::{JSRef, Temporary};
 use dom::document::Document;
-use dom::element::HTMLOutputElementTypeId;
+use dom::element::{Element, HTMLOutputElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
 use dom::node::{Node, ElementNodeTypeId, window_from_node};
@@ -44,6 +44,7 @@ pub trait HTMLOutputElementMethods {
 impl<'a> HTMLOutputElementMethods for JSRef<'a, HTMLOutputElement> {
     fn Validity(&self) -> Temporary<ValidityState> {
         let window = window_from_node(self).root();
-        ValidityState::new(&*window)
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
     }
 }
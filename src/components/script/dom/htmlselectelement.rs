@@ -2,22 +2,50 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrValue;
 use dom::bindings::codegen::Bindings::HTMLSelectElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLSelectElementDerived;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLOptGroupElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLOptionElementCast, HTMLSelectElementDerived};
+use dom::bindings::codegen::InheritTypes::{KeyboardEventCast, NodeCast};
 use dom::bindings::codegen::UnionTypes::HTMLElementOrLong::HTMLElementOrLong;
 use dom::bindings::codegen::UnionTypes::HTMLOptionElementOrHTMLOptGroupElement::HTMLOptionElementOrHTMLOptGroupElement;
-use dom::bindings::js::{JSRef, Temporary};
-use dom::document::Document;
-use dom::element::HTMLSelectElementTypeId;
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable};
+use dom::bindings::trace::{Traceable, Untraceable};
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, HTMLSelectElementTypeId};
+use dom::element::RawLayoutElementHelpers;
+use dom::event::{Event, EventMethods};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
+use dom::htmlcollection::{HTMLCollection, CollectionFilter};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId, window_from_node};
-use dom::validitystate::ValidityState;
+use dom::htmloptionelement::{HTMLOptionElement, HTMLOptionElementHelpers};
+use dom::htmloptionelement::HTMLOptionElementMethods;
+use dom::keyboardevent::KeyboardEventMethods;
+use dom::node::{Node, NodeHelpers, NodeMethods, ElementNodeTypeId, document_from_node};
+use dom::node::window_from_node;
+use dom::validitystate::{Validatable, ValidityFlags, ValidityState, check_validity};
+use dom::validitystate::{report_validity, validation_message, will_validate};
+use dom::virtualmethods::VirtualMethods;
+use servo_util::namespace::Null;
 use servo_util::str::DOMString;
 
+use std::cell::{Cell, RefCell};
+use std::cmp;
+
+/// The number of options that a list box shows when it has the `multiple` attribute but no
+/// `size` attribute.
+static DEFAULT_MULTIPLE_SIZE: u32 = 4;
+
 #[deriving(Encodable)]
 pub struct HTMLSelectElement {
-    pub htmlelement: HTMLElement
+    pub htmlelement: HTMLElement,
+    /// The index of the option that the in-page list of options highlights, if the list is
+    /// open. Only a drop-down box, whose display size is 1, has such a list.
+    list_highlight: Untraceable<Cell<Option<uint>>>,
+    /// The index of the first option that a list box shows. The rows before it are scrolled out
+    /// of view.
+    list_scroll: Untraceable<Cell<uint>>,
+    custom_validity: Traceable<RefCell<DOMString>>,
 }
 
 impl HTMLSelectElementDerived for EventTarget {
@@ -29,7 +57,10 @@ impl HTMLSelectElementDerived for EventTarget {
 impl HTMLSelectElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLSelectElement {
         HTMLSelectElement {
-            htmlelement: HTMLElement::new_inherited(HTMLSelectElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLSelectElementTypeId, localName, document),
+            list_highlight: Untraceable::new(Cell::new(None)),
+            list_scroll: Untraceable::new(Cell::new(0)),
+            custom_validity: Traceable::new(RefCell::new(String::new())),
         }
     }
 
@@ -39,18 +70,660 @@ impl HTMLSelectElement {
     }
 }
 
+pub trait LayoutHTMLSelectElementHelpers {
+    unsafe fn get_list_highlight_for_layout(&self) -> Option<uint>;
+    unsafe fn get_display_size_for_layout(&self) -> u32;
+    unsafe fn get_list_scroll_for_layout(&self) -> uint;
+}
+
+impl LayoutHTMLSelectElementHelpers for JS<HTMLSelectElement> {
+    unsafe fn get_list_highlight_for_layout(&self) -> Option<uint> {
+        (*self.unsafe_get()).list_highlight.deref().get()
+    }
+
+    unsafe fn get_list_scroll_for_layout(&self) -> uint {
+        (*self.unsafe_get()).list_scroll.deref().get()
+    }
+
+    unsafe fn get_display_size_for_layout(&self) -> u32 {
+        let element = &(*self.unsafe_get()).htmlelement.element;
+        let size = element.get_attr_val_for_layout(&Null, "size").and_then(|size| from_str(size));
+        match size {
+            Some(size) if size != 0 => size,
+            _ if element.get_attr_val_for_layout(&Null, "multiple").is_some() => {
+                DEFAULT_MULTIPLE_SIZE
+            }
+            _ => 1,
+        }
+    }
+}
+
+pub trait HTMLSelectElementHelpers {
+    fn list_of_options(&self) -> Vec<JS<HTMLOptionElement>>;
+    fn display_size(&self) -> u32;
+    fn pick(&self, option: &JSRef<HTMLOptionElement>);
+    fn ask_for_reset(&self);
+    fn reset(&self);
+    fn option_clicked(&self, option: &JSRef<HTMLOptionElement>);
+}
+
+impl<'a> HTMLSelectElementHelpers for JSRef<'a, HTMLSelectElement> {
+    /// Returns the option children of the element, and the option children of its `<optgroup>`
+    /// children, in tree order.
+    // http://www.whatwg.org/html/#concept-select-option-list
+    fn list_of_options(&self) -> Vec<JS<HTMLOptionElement>> {
+        let mut options = vec!();
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        for child in node.children() {
+            match HTMLOptionElementCast::to_ref(&child) {
+                Some(option) => {
+                    options.push(JS::from_rooted(option));
+                    continue;
+                }
+                None => {}
+            }
+            if HTMLOptGroupElementCast::to_ref(&child).is_none() {
+                continue;
+            }
+            for grandchild in child.children() {
+                match HTMLOptionElementCast::to_ref(&grandchild) {
+                    Some(option) => options.push(JS::from_rooted(option)),
+                    None => {}
+                }
+            }
+        }
+        options
+    }
+
+    // http://www.whatwg.org/html/#concept-select-size
+    fn display_size(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match element.get_uint_attribute("size") {
+            0 if element.get_bool_attribute("multiple") => DEFAULT_MULTIPLE_SIZE,
+            0 => 1,
+            size => size,
+        }
+    }
+
+    /// Unselects the options other than the given one, which was just selected, unless several
+    /// options can be selected.
+    fn pick(&self, option: &JSRef<HTMLOptionElement>) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.get_bool_attribute("multiple") {
+            return;
+        }
+        for other in self.list_of_options().iter() {
+            let other = other.root();
+            if *other != *option {
+                other.deref().set_selectedness(false);
+            }
+        }
+    }
+
+    /// Makes sure that a drop-down box has exactly one option selected, if it has any that can
+    /// be.
+    // http://www.whatwg.org/html/#selectedness-setting-algorithm
+    fn ask_for_reset(&self) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if !element.get_bool_attribute("multiple") {
+            let options = self.list_of_options();
+            let selected: Vec<uint> = range(0, options.len()).filter(|&i| {
+                options.get(i).root().deref().selectedness()
+            }).collect();
+            if selected.is_empty() && self.display_size() == 1 {
+                let first_enabled = options.iter().map(|option| option.root()).find(|option| {
+                    !option.deref().is_disabled()
+                });
+                first_enabled.map(|option| option.deref().set_selectedness(true));
+            }
+            // Keep the last of the selected options.
+            if selected.len() > 1 {
+                for &i in selected.slice_to(selected.len() - 1).iter() {
+                    options.get(i).root().deref().set_selectedness(false);
+                }
+            }
+        }
+        self.force_relayout();
+    }
+
+    // http://www.whatwg.org/html/#the-select-element:concept-form-reset-control
+    fn reset(&self) {
+        for option in self.list_of_options().iter() {
+            option.root().deref().reset();
+        }
+        self.ask_for_reset();
+    }
+
+    /// Selects the option that the user clicked, in the list of options of a drop-down box or
+    /// in a list box, and closes the list.
+    fn option_clicked(&self, option: &JSRef<HTMLOptionElement>) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.is_actually_disabled() || option.is_disabled() {
+            return;
+        }
+        self.select_by_user(option);
+        self.set_list_highlight(None);
+    }
+}
+
+trait PrivateHTMLSelectElementHelpers {
+    fn selected_index(&self) -> Option<uint>;
+    fn placeholder_label_option(&self) -> Option<JS<HTMLOptionElement>>;
+    fn select_by_user(&self, option: &JSRef<HTMLOptionElement>);
+    fn next_enabled_option(&self, from: Option<uint>, forward: bool) -> Option<uint>;
+    fn set_list_highlight(&self, highlight: Option<uint>);
+    fn scroll_list_to(&self, index: uint);
+    fn force_relayout(&self);
+    fn handle_key_event(&self, event: &JSRef<Event>);
+}
+
+impl<'a> PrivateHTMLSelectElementHelpers for JSRef<'a, HTMLSelectElement> {
+    fn selected_index(&self) -> Option<uint> {
+        self.list_of_options().iter().position(|option| option.root().deref().selectedness())
+    }
+
+    /// Returns the first option if it stands for a placeholder rather than a value, which a
+    /// required drop-down box must not have selected.
+    // http://www.whatwg.org/html/#placeholder-label-option
+    fn placeholder_label_option(&self) -> Option<JS<HTMLOptionElement>> {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.get_bool_attribute("multiple") || self.display_size() != 1 {
+            return None;
+        }
+        let first = match self.list_of_options().move_iter().next() {
+            Some(first) => first,
+            None => return None,
+        };
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let option = first.root();
+        let option_node: &JSRef<Node> = NodeCast::from_ref(&*option);
+        let is_child = option_node.parent_node().root().map_or(false, |parent| &*parent == node);
+        if is_child && option.deref().Value().is_empty() {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    /// Changes the selection as the user asked, firing `input` and `change` if it did change.
+    /// Choosing an option in a list box that allows several selected options toggles it.
+    fn select_by_user(&self, option: &JSRef<HTMLOptionElement>) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if element.get_bool_attribute("multiple") {
+            option.SetSelected(!option.Selected());
+        } else if !option.Selected() {
+            option.SetSelected(true);
+        } else {
+            return;
+        }
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.fire_simple_event("input", true, false);
+        node.fire_simple_event("change", true, false);
+    }
+
+    /// Returns the index of the first option that isn't disabled after or before the given
+    /// one, or after the start of the list if none is given.
+    fn next_enabled_option(&self, from: Option<uint>, forward: bool) -> Option<uint> {
+        let options = self.list_of_options();
+        let is_enabled = |i: &uint| !options.get(*i).root().deref().is_disabled();
+        match (from, forward) {
+            (None, _) => range(0, options.len()).find(is_enabled),
+            (Some(from), true) => range(from + 1, options.len()).find(is_enabled),
+            (Some(from), false) => range(0, from).rev().find(is_enabled),
+        }
+    }
+
+    fn set_list_highlight(&self, highlight: Option<uint>) {
+        if self.list_highlight.deref().get() != highlight {
+            self.list_highlight.deref().set(highlight);
+            self.force_relayout();
+        }
+    }
+
+    /// Scrolls a list box as little as possible to show the option at the given index.
+    fn scroll_list_to(&self, index: uint) {
+        let display_size = self.display_size() as uint;
+        let scroll = self.list_scroll.deref().get();
+        let new_scroll = if index < scroll {
+            index
+        } else if index >= scroll + display_size {
+            index + 1 - display_size
+        } else {
+            return
+        };
+        self.list_scroll.deref().set(new_scroll);
+        self.force_relayout();
+    }
+
+    fn force_relayout(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = document_from_node(self).root();
+            document.deref().content_changed();
+        }
+    }
+
+    /// Moves the selection, or the highlight of the open list of options, with the arrow keys,
+    /// and opens and closes the list.
+    fn handle_key_event(&self, event: &JSRef<Event>) {
+        let keyboard_event = match KeyboardEventCast::to_ref(event) {
+            Some(keyboard_event) if event.IsTrusted() => keyboard_event,
+            _ => return,
+        };
+        let key = keyboard_event.Key();
+        let highlight = self.list_highlight.deref().get();
+        match (key.as_slice(), highlight) {
+            ("ArrowDown", Some(_)) | ("ArrowUp", Some(_)) => {
+                let next = self.next_enabled_option(highlight, key.as_slice() == "ArrowDown");
+                if next.is_some() {
+                    self.set_list_highlight(next);
+                }
+            }
+            ("Enter", Some(index)) | (" ", Some(index)) => {
+                let options = self.list_of_options();
+                if index < options.len() {
+                    self.option_clicked(&*options.get(index).root());
+                }
+            }
+            ("Escape", Some(_)) => self.set_list_highlight(None),
+            ("ArrowDown", None) if keyboard_event.AltKey() && self.display_size() == 1 => {
+                self.set_list_highlight(self.selected_index().or(Some(0)));
+            }
+            ("Enter", None) | (" ", None) if self.display_size() == 1 => {
+                self.set_list_highlight(self.selected_index().or(Some(0)));
+            }
+            ("ArrowDown", None) | ("ArrowUp", None) => {
+                let current = self.selected_index();
+                match self.next_enabled_option(current, key.as_slice() == "ArrowDown") {
+                    Some(next) => {
+                        let options = self.list_of_options();
+                        let option = options.get(next).root();
+                        // The arrow keys select a single option, even in a list box that allows
+                        // several.
+                        for other in options.iter() {
+                            other.root().deref().set_selectedness(false);
+                        }
+                        self.select_by_user(&*option);
+                        if self.display_size() != 1 {
+                            self.scroll_list_to(next);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            ("PageDown", None) | ("PageUp", None) if self.display_size() != 1 => {
+                // Scroll a list box by a page without changing the selection. Layout keeps the
+                // last page full.
+                let display_size = self.display_size() as uint;
+                let scroll = self.list_scroll.deref().get();
+                let last = self.list_of_options().len().saturating_sub(1);
+                let index = if key.as_slice() == "PageDown" {
+                    cmp::min(scroll + 2 * display_size - 1, last)
+                } else {
+                    scroll.saturating_sub(display_size)
+                };
+                self.scroll_list_to(index);
+            }
+            _ => return,
+        }
+        // Keep the page from scrolling.
+        event.PreventDefault();
+    }
+}
+
 pub trait HTMLSelectElementMethods {
-    fn Validity(&self) -> Temporary<ValidityState>;
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn Multiple(&self) -> bool;
+    fn SetMultiple(&self, multiple: bool);
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn Required(&self) -> bool;
+    fn SetRequired(&self, required: bool);
+    fn Size(&self) -> u32;
+    fn SetSize(&self, size: u32);
+    fn Type(&self) -> DOMString;
+    fn Options(&self) -> Temporary<HTMLCollection>;
+    fn Length(&self) -> u32;
+    fn SetLength(&self, length: u32);
+    fn Item(&self, index: u32) -> Option<Temporary<Element>>;
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<Element>>;
     fn Add(&self, _element: HTMLOptionElementOrHTMLOptGroupElement, _before: Option<HTMLElementOrLong>);
+    fn SelectedOptions(&self) -> Temporary<HTMLCollection>;
+    fn SelectedIndex(&self) -> i32;
+    fn SetSelectedIndex(&self, index: i32);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+    fn WillValidate(&self) -> bool;
+    fn Validity(&self) -> Temporary<ValidityState>;
+    fn ValidationMessage(&self) -> DOMString;
+    fn CheckValidity(&self) -> bool;
+    fn ReportValidity(&self) -> bool;
+    fn SetCustomValidity(&self, error: DOMString);
 }
 
 impl<'a> HTMLSelectElementMethods for JSRef<'a, HTMLSelectElement> {
-    fn Validity(&self) -> Temporary<ValidityState> {
+    fn Disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("disabled")
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("disabled", disabled)
+    }
+
+    fn Multiple(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("multiple")
+    }
+
+    fn SetMultiple(&self, multiple: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("multiple", multiple)
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    fn Required(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("required")
+    }
+
+    fn SetRequired(&self, required: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("required", required)
+    }
+
+    fn Size(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_uint_attribute("size")
+    }
+
+    fn SetSize(&self, size: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("size", size)
+    }
+
+    // http://www.whatwg.org/html/#dom-select-type
+    fn Type(&self) -> DOMString {
+        if self.Multiple() {
+            "select-multiple".to_string()
+        } else {
+            "select-one".to_string()
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-select-options
+    fn Options(&self) -> Temporary<HTMLCollection> {
+        struct OptionsFilter;
+        impl CollectionFilter for OptionsFilter {
+            fn filter(&self, elem: &JSRef<Element>, root: &JSRef<Node>) -> bool {
+                let option = match HTMLOptionElementCast::to_ref(elem) {
+                    Some(option) => option,
+                    None => return false,
+                };
+                option.owner_select().root().map_or(false, |select| {
+                    let select: &JSRef<Node> = NodeCast::from_ref(&*select);
+                    select == root
+                })
+            }
+        }
         let window = window_from_node(self).root();
-        ValidityState::new(&*window)
+        HTMLCollection::create(&*window, NodeCast::from_ref(self), box OptionsFilter)
+    }
+
+    fn Length(&self) -> u32 {
+        self.list_of_options().len() as u32
+    }
+
+    /// Adds empty options at the end of the list of options, or removes the last ones.
+    // http://www.whatwg.org/html/#dom-select-length
+    fn SetLength(&self, length: u32) {
+        let options = self.list_of_options();
+        let length = length as uint;
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if length > options.len() {
+            let document = document_from_node(self).root();
+            for _ in range(options.len(), length) {
+                let option = HTMLOptionElement::new("option".to_string(), &*document).root();
+                let _ = node.AppendChild(NodeCast::from_ref(&*option));
+            }
+        } else {
+            for option in options.slice_from(length).iter() {
+                let option = option.root();
+                let option: &JSRef<Node> = NodeCast::from_ref(&*option);
+                match option.parent_node().root() {
+                    Some(parent) => {
+                        let _ = parent.deref().RemoveChild(option);
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-select-item
+    fn Item(&self, index: u32) -> Option<Temporary<Element>> {
+        let options = self.list_of_options();
+        if (index as uint) < options.len() {
+            let option = options.get(index as uint).root();
+            Some(Temporary::from_rooted(ElementCast::from_ref(&*option)))
+        } else {
+            None
+        }
+    }
+
+    fn IndexedGetter(&self, index: u32, found: &mut bool) -> Option<Temporary<Element>> {
+        let maybe_option = self.Item(index);
+        *found = maybe_option.is_some();
+        maybe_option
     }
 
     // Note: this function currently only exists for test_union.html.
     fn Add(&self, _element: HTMLOptionElementOrHTMLOptGroupElement, _before: Option<HTMLElementOrLong>) {
     }
+
+    // http://www.whatwg.org/html/#dom-select-selectedoptions
+    fn SelectedOptions(&self) -> Temporary<HTMLCollection> {
+        struct SelectedOptionsFilter;
+        impl CollectionFilter for SelectedOptionsFilter {
+            fn filter(&self, elem: &JSRef<Element>, root: &JSRef<Node>) -> bool {
+                let option = match HTMLOptionElementCast::to_ref(elem) {
+                    Some(option) if option.selectedness() => option,
+                    _ => return false,
+                };
+                option.owner_select().root().map_or(false, |select| {
+                    let select: &JSRef<Node> = NodeCast::from_ref(&*select);
+                    select == root
+                })
+            }
+        }
+        let window = window_from_node(self).root();
+        HTMLCollection::create(&*window, NodeCast::from_ref(self), box SelectedOptionsFilter)
+    }
+
+    // http://www.whatwg.org/html/#dom-select-selectedindex
+    fn SelectedIndex(&self) -> i32 {
+        self.selected_index().map_or(-1, |index| index as i32)
+    }
+
+    fn SetSelectedIndex(&self, index: i32) {
+        for (i, option) in self.list_of_options().iter().enumerate() {
+            let option = option.root();
+            if i as i32 == index {
+                option.deref().SetSelected(true);
+            } else {
+                option.deref().set_selectedness(false);
+            }
+        }
+        self.force_relayout();
+    }
+
+    // http://www.whatwg.org/html/#dom-select-value
+    fn Value(&self) -> DOMString {
+        let options = self.list_of_options();
+        self.selected_index().map_or(String::new(), |index| {
+            options.get(index).root().deref().Value()
+        })
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        let mut found = false;
+        for option in self.list_of_options().iter() {
+            let option = option.root();
+            if !found && option.deref().Value() == value {
+                found = true;
+                option.deref().SetSelected(true);
+            } else {
+                option.deref().set_selectedness(false);
+            }
+        }
+        self.force_relayout();
+    }
+
+    fn WillValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        will_validate(element)
+    }
+
+    fn Validity(&self) -> Temporary<ValidityState> {
+        let window = window_from_node(self).root();
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
+    }
+
+    fn ValidationMessage(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        validation_message(element)
+    }
+
+    fn CheckValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        check_validity(element)
+    }
+
+    fn ReportValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        report_validity(element)
+    }
+
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
+}
+
+impl<'a> Validatable for JSRef<'a, HTMLSelectElement> {
+    fn is_candidate_for_validation(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        !element.is_actually_disabled()
+    }
+
+    fn validity_flags(&self) -> ValidityFlags {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let mut flags = ValidityFlags::new();
+        flags.custom_error = !self.custom_validity.deref().borrow().is_empty();
+        // http://www.whatwg.org/html/#attr-select-required
+        if element.get_bool_attribute("required") {
+            flags.value_missing = match self.selected_index() {
+                None => true,
+                Some(index) => index == 0 && self.placeholder_label_option().is_some(),
+            };
+        }
+        flags
+    }
+
+    fn custom_validity(&self) -> DOMString {
+        self.custom_validity.deref().borrow().clone()
+    }
+
+    fn set_custom_validity(&self, error: DOMString) {
+        *self.custom_validity.deref().borrow_mut() = error;
+        self.force_relayout();
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLSelectElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    fn after_set_attr(&self, name: DOMString, value: DOMString) {
+        match self.super_type() {
+            Some(ref s) => s.after_set_attr(name.clone(), value.clone()),
+            _ => (),
+        }
+
+        match name.as_slice() {
+            "multiple" | "size" => {
+                self.set_list_highlight(None);
+                self.ask_for_reset();
+            }
+            _ => {}
+        }
+    }
+
+    fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
+        match name {
+            "size" => AttrValue::from_u32(value, 0),
+            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
+        }
+    }
+
+    fn child_inserted(&self, child: &JSRef<Node>) {
+        match self.super_type() {
+            Some(ref s) => s.child_inserted(child),
+            _ => (),
+        }
+
+        match HTMLOptionElementCast::to_ref(child) {
+            Some(option) if option.selectedness() => self.pick(option),
+            _ => {}
+        }
+        self.ask_for_reset();
+    }
+
+    fn child_removed(&self, child: &JSRef<Node>) {
+        match self.super_type() {
+            Some(ref s) => s.child_removed(child),
+            _ => (),
+        }
+
+        // The indices of the options after the removed one have changed.
+        self.set_list_highlight(None);
+        self.ask_for_reset();
+    }
+
+    fn handle_event(&self, event: &JSRef<Event>) {
+        match self.super_type() {
+            Some(s) => s.handle_event(event),
+            None => {}
+        }
+
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        if event.DefaultPrevented() || element.is_actually_disabled() {
+            return;
+        }
+        match event.Type().as_slice() {
+            // Open or close the list of options of a drop-down box.
+            "click" if self.display_size() == 1 => {
+                match self.list_highlight.deref().get() {
+                    Some(_) => self.set_list_highlight(None),
+                    None => self.set_list_highlight(self.selected_index().or(Some(0))),
+                }
+            }
+            "keydown" => self.handle_key_event(event),
+            "blur" => self.set_list_highlight(None),
+            _ => {}
+        }
+    }
 }
@@ -2,13 +2,16 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
-use dom::bindings::codegen::InheritTypes::HTMLTableCellElementDerived;
+use dom::attr::AttrValue;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, HTMLTableCellElementDerived};
 use dom::bindings::js::JSRef;
 use dom::document::Document;
-use dom::element::{ElementTypeId, HTMLTableDataCellElementTypeId, HTMLTableHeaderCellElementTypeId};
+use dom::element::{AttributeHandlers, Element, ElementTypeId};
+use dom::element::{HTMLTableDataCellElementTypeId, HTMLTableHeaderCellElementTypeId};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
 use dom::node::ElementNodeTypeId;
+use dom::virtualmethods::VirtualMethods;
 use servo_util::str::DOMString;
 
 #[deriving(Encodable)]
@@ -35,4 +38,52 @@ impl HTMLTableCellElement {
 }
 
 pub trait HTMLTableCellElementMethods {
+    fn ColSpan(&self) -> u32;
+    fn SetColSpan(&self, colspan: u32);
+    fn RowSpan(&self) -> u32;
+    fn SetRowSpan(&self, rowspan: u32);
+}
+
+/// Returns the value of a span attribute, which is limited to numbers greater than zero.
+fn get_span_attribute(element: &JSRef<Element>, name: &str) -> u32 {
+    match element.get_uint_attribute(name) {
+        0 => 1,
+        span => span,
+    }
+}
+
+impl<'a> HTMLTableCellElementMethods for JSRef<'a, HTMLTableCellElement> {
+    fn ColSpan(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        get_span_attribute(element, "colspan")
+    }
+
+    fn SetColSpan(&self, colspan: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("colspan", colspan)
+    }
+
+    fn RowSpan(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        get_span_attribute(element, "rowspan")
+    }
+
+    fn SetRowSpan(&self, rowspan: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("rowspan", rowspan)
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLTableCellElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
+        match name {
+            "colspan" | "rowspan" => AttrValue::from_u32(value, 1),
+            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
+        }
+    }
 }
@@ -2,19 +2,49 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrValue;
 use dom::bindings::codegen::Bindings::HTMLTextAreaElementBinding;
-use dom::bindings::codegen::InheritTypes::HTMLTextAreaElementDerived;
-use dom::bindings::js::{JSRef, Temporary};
-use dom::document::Document;
-use dom::element::HTMLTextAreaElementTypeId;
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLElementCast, KeyboardEventCast};
+use dom::bindings::codegen::InheritTypes::{HTMLTextAreaElementDerived, NodeCast};
+use dom::bindings::error::{ErrorResult, IndexSize};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::trace::{Traceable, Untraceable};
+use dom::document::{Document, DocumentHelpers};
+use dom::element::{Element, AttributeHandlers, ElementHelpers, HTMLTextAreaElementTypeId};
+use dom::element::RawLayoutElementHelpers;
+use dom::event::{Event, EventMethods};
 use dom::eventtarget::{EventTarget, NodeTargetTypeId};
 use dom::htmlelement::HTMLElement;
-use dom::node::{Node, ElementNodeTypeId};
+use dom::node::{Node, NodeHelpers, NodeMethods, ElementNodeTypeId, RawLayoutNodeHelpers};
+use dom::node::{document_from_node, window_from_node};
+use dom::validitystate::{Validatable, ValidityFlags, ValidityState, check_validity};
+use dom::validitystate::{length_flags, length_limit, report_validity, validation_message};
+use dom::validitystate::will_validate;
+use dom::virtualmethods::VirtualMethods;
+use servo_util::namespace::Null;
 use servo_util::str::DOMString;
+use textinput::{TextInput, Multiple, SelectionDirection, NoDirection, NotHandled, Edited};
+
+use std::cell::{Cell, RefCell, RefMut};
+use std::cmp::max;
+
+/// The default width of a text area, in characters.
+static DEFAULT_COLS: u32 = 20;
+
+/// The default height of a text area, in lines.
+static DEFAULT_ROWS: u32 = 2;
 
 #[deriving(Encodable)]
 pub struct HTMLTextAreaElement {
     pub htmlelement: HTMLElement,
+    /// Whether the value was changed by the user or by script, after which the text content of
+    /// the element no longer affects it.
+    value_changed: Traceable<Cell<bool>>,
+    /// Whether the user edited the value since the element got the focus, in which case a
+    /// `change` event is due when it loses the focus.
+    edited_since_focus: Traceable<Cell<bool>>,
+    textinput: Untraceable<RefCell<TextInput>>,
+    custom_validity: Traceable<RefCell<DOMString>>,
 }
 
 impl HTMLTextAreaElementDerived for EventTarget {
@@ -26,7 +56,11 @@ impl HTMLTextAreaElementDerived for EventTarget {
 impl HTMLTextAreaElement {
     pub fn new_inherited(localName: DOMString, document: &JSRef<Document>) -> HTMLTextAreaElement {
         HTMLTextAreaElement {
-            htmlelement: HTMLElement::new_inherited(HTMLTextAreaElementTypeId, localName, document)
+            htmlelement: HTMLElement::new_inherited(HTMLTextAreaElementTypeId, localName, document),
+            value_changed: Traceable::new(Cell::new(false)),
+            edited_since_focus: Traceable::new(Cell::new(false)),
+            textinput: Untraceable::new(RefCell::new(TextInput::new(Multiple, "".to_string()))),
+            custom_validity: Traceable::new(RefCell::new(String::new())),
         }
     }
 
@@ -36,5 +70,468 @@ impl HTMLTextAreaElement {
     }
 }
 
+pub trait LayoutHTMLTextAreaElementHelpers {
+    unsafe fn get_value_for_layout(&self) -> String;
+    unsafe fn get_caret_for_layout(&self) -> Option<uint>;
+    unsafe fn get_cols_for_layout(&self) -> u32;
+    unsafe fn get_rows_for_layout(&self) -> u32;
+}
+
+impl LayoutHTMLTextAreaElementHelpers for JS<HTMLTextAreaElement> {
+    unsafe fn get_value_for_layout(&self) -> String {
+        (*self.unsafe_get()).textinput.deref().borrow().get_content()
+    }
+
+    /// Returns the position of the caret if the text area has the focus.
+    unsafe fn get_caret_for_layout(&self) -> Option<uint> {
+        let textarea = &*self.unsafe_get();
+        if textarea.htmlelement.element.node.get_focus_state_for_layout() {
+            Some(textarea.textinput.deref().borrow().caret())
+        } else {
+            None
+        }
+    }
+
+    unsafe fn get_cols_for_layout(&self) -> u32 {
+        let element = &(*self.unsafe_get()).htmlelement.element;
+        element.get_attr_val_for_layout(&Null, "cols")
+               .and_then(|cols| from_str(cols))
+               .and_then(|cols| if cols == 0 { None } else { Some(cols) })
+               .unwrap_or(DEFAULT_COLS)
+    }
+
+    unsafe fn get_rows_for_layout(&self) -> u32 {
+        let element = &(*self.unsafe_get()).htmlelement.element;
+        element.get_attr_val_for_layout(&Null, "rows")
+               .and_then(|rows| from_str(rows))
+               .and_then(|rows| if rows == 0 { None } else { Some(rows) })
+               .unwrap_or(DEFAULT_ROWS)
+    }
+}
+
+pub trait HTMLTextAreaElementHelpers {
+    fn reset(&self);
+}
+
+impl<'a> HTMLTextAreaElementHelpers for JSRef<'a, HTMLTextAreaElement> {
+    // http://www.whatwg.org/html/#the-textarea-element:concept-form-reset-control
+    fn reset(&self) {
+        self.value_changed.deref().set(false);
+        self.reset_value();
+    }
+}
+
+trait PrivateHTMLTextAreaElementHelpers {
+    fn reset_value(&self);
+    fn textinput_mut<'b>(&'b self) -> RefMut<'b, TextInput>;
+    fn force_relayout(&self);
+    fn handle_key_event(&self, event: &JSRef<Event>);
+    fn handle_event_impl(&self, event: &JSRef<Event>);
+}
+
+impl<'a> PrivateHTMLTextAreaElementHelpers for JSRef<'a, HTMLTextAreaElement> {
+    /// Takes the value from the text content again, unless the user or a script changed it.
+    fn reset_value(&self) {
+        if !self.value_changed.deref().get() {
+            let default_value = self.DefaultValue();
+            self.textinput_mut().set_content(default_value);
+            self.force_relayout();
+        }
+    }
+
+    /// Borrows the editing state for a change, once layout is done reading it.
+    fn textinput_mut<'b>(&'b self) -> RefMut<'b, TextInput> {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.wait_until_safe_to_modify_dom();
+        self.textinput.deref().borrow_mut()
+    }
+
+    fn force_relayout(&self) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        if node.is_in_doc() {
+            let document = document_from_node(self).root();
+            document.deref().content_changed();
+        }
+    }
+
+    /// Edits the value in response to a key that the user pressed.
+    fn handle_key_event(&self, event: &JSRef<Event>) {
+        if !event.IsTrusted() || self.ReadOnly() {
+            return;
+        }
+        let keyboard_event = match KeyboardEventCast::to_ref(event) {
+            Some(keyboard_event) => keyboard_event,
+            None => return,
+        };
+
+        let reaction = match event.Type().as_slice() {
+            "keydown" => self.textinput_mut().handle_keydown(keyboard_event),
+            "keypress" => self.textinput_mut().handle_keypress(keyboard_event),
+            _ => NotHandled,
+        };
+        if reaction == NotHandled {
+            return;
+        }
+
+        // Keep the page from scrolling or typing into anything else.
+        event.PreventDefault();
+        self.force_relayout();
+        if reaction == Edited {
+            self.value_changed.deref().set(true);
+            self.edited_since_focus.deref().set(true);
+            let node: &JSRef<Node> = NodeCast::from_ref(self);
+            node.fire_simple_event("input", true, false);
+        }
+    }
+
+    fn handle_event_impl(&self, event: &JSRef<Event>) {
+        if event.DefaultPrevented() {
+            return;
+        }
+
+        match event.Type().as_slice() {
+            "keydown" | "keypress" => self.handle_key_event(event),
+            "focus" => self.edited_since_focus.deref().set(false),
+            "blur" => {
+                if self.edited_since_focus.deref().get() {
+                    self.edited_since_focus.deref().set(false);
+                    let node: &JSRef<Node> = NodeCast::from_ref(self);
+                    node.fire_simple_event("change", true, false);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
 pub trait HTMLTextAreaElementMethods {
+    fn Cols(&self) -> u32;
+    fn SetCols(&self, cols: u32);
+    fn Disabled(&self) -> bool;
+    fn SetDisabled(&self, disabled: bool);
+    fn MaxLength(&self) -> i32;
+    fn SetMaxLength(&self, max_length: i32) -> ErrorResult;
+    fn MinLength(&self) -> i32;
+    fn SetMinLength(&self, min_length: i32) -> ErrorResult;
+    fn Name(&self) -> DOMString;
+    fn SetName(&self, name: DOMString);
+    fn Placeholder(&self) -> DOMString;
+    fn SetPlaceholder(&self, placeholder: DOMString);
+    fn ReadOnly(&self) -> bool;
+    fn SetReadOnly(&self, read_only: bool);
+    fn Required(&self) -> bool;
+    fn SetRequired(&self, required: bool);
+    fn Rows(&self) -> u32;
+    fn SetRows(&self, rows: u32);
+    fn Type(&self) -> DOMString;
+    fn DefaultValue(&self) -> DOMString;
+    fn SetDefaultValue(&self, default_value: DOMString);
+    fn Value(&self) -> DOMString;
+    fn SetValue(&self, value: DOMString);
+    fn TextLength(&self) -> u32;
+    fn WillValidate(&self) -> bool;
+    fn Validity(&self) -> Temporary<ValidityState>;
+    fn ValidationMessage(&self) -> DOMString;
+    fn CheckValidity(&self) -> bool;
+    fn ReportValidity(&self) -> bool;
+    fn SetCustomValidity(&self, error: DOMString);
+    fn Select(&self);
+    fn SelectionStart(&self) -> u32;
+    fn SetSelectionStart(&self, start: u32);
+    fn SelectionEnd(&self) -> u32;
+    fn SetSelectionEnd(&self, end: u32);
+    fn SelectionDirection(&self) -> DOMString;
+    fn SetSelectionDirection(&self, direction: DOMString);
+    fn SetRangeText(&self, replacement: DOMString);
+    fn SetSelectionRange(&self, start: u32, end: u32, direction: Option<DOMString>);
+}
+
+impl<'a> HTMLTextAreaElementMethods for JSRef<'a, HTMLTextAreaElement> {
+    // http://www.whatwg.org/html/#dom-textarea-cols
+    fn Cols(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match element.get_uint_attribute("cols") {
+            0 => DEFAULT_COLS,
+            cols => cols,
+        }
+    }
+
+    fn SetCols(&self, cols: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("cols", cols)
+    }
+
+    fn Disabled(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("disabled")
+    }
+
+    fn SetDisabled(&self, disabled: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("disabled", disabled)
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-maxlength
+    fn MaxLength(&self) -> i32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        length_limit(element, "maxlength").map_or(-1, |limit| limit as i32)
+    }
+
+    fn SetMaxLength(&self, max_length: i32) -> ErrorResult {
+        if max_length < 0 {
+            return Err(IndexSize);
+        }
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("maxlength", max_length.to_str());
+        Ok(())
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-minlength
+    fn MinLength(&self) -> i32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        length_limit(element, "minlength").map_or(-1, |limit| limit as i32)
+    }
+
+    fn SetMinLength(&self, min_length: i32) -> ErrorResult {
+        if min_length < 0 {
+            return Err(IndexSize);
+        }
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("minlength", min_length.to_str());
+        Ok(())
+    }
+
+    fn Name(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("name")
+    }
+
+    fn SetName(&self, name: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("name", name)
+    }
+
+    fn Placeholder(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_string_attribute("placeholder")
+    }
+
+    fn SetPlaceholder(&self, placeholder: DOMString) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_string_attribute("placeholder", placeholder)
+    }
+
+    fn ReadOnly(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("readonly")
+    }
+
+    fn SetReadOnly(&self, read_only: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("readonly", read_only)
+    }
+
+    fn Required(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.get_bool_attribute("required")
+    }
+
+    fn SetRequired(&self, required: bool) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_bool_attribute("required", required)
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-rows
+    fn Rows(&self) -> u32 {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        match element.get_uint_attribute("rows") {
+            0 => DEFAULT_ROWS,
+            rows => rows,
+        }
+    }
+
+    fn SetRows(&self, rows: u32) {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        element.set_uint_attribute("rows", rows)
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-type
+    fn Type(&self) -> DOMString {
+        "textarea".to_string()
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-defaultvalue
+    fn DefaultValue(&self) -> DOMString {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        node.GetTextContent().unwrap()
+    }
+
+    fn SetDefaultValue(&self, default_value: DOMString) {
+        let node: &JSRef<Node> = NodeCast::from_ref(self);
+        let _ = node.SetTextContent(Some(default_value));
+        self.reset_value();
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea-value
+    fn Value(&self) -> DOMString {
+        self.textinput.deref().borrow().get_content()
+    }
+
+    fn SetValue(&self, value: DOMString) {
+        self.textinput_mut().set_content(value);
+        self.value_changed.deref().set(true);
+        self.force_relayout();
+    }
+
+    fn TextLength(&self) -> u32 {
+        self.textinput.deref().borrow().len() as u32
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-select
+    fn WillValidate(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        will_validate(element)
+    }
+
+    fn Validity(&self) -> Temporary<ValidityState> {
+        let window = window_from_node(self).root();
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        ValidityState::new(&*window, element)
+    }
+
+    fn ValidationMessage(&self) -> DOMString {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        validation_message(element)
+    }
+
+    fn CheckValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        check_validity(element)
+    }
+
+    fn ReportValidity(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        report_validity(element)
+    }
+
+    fn SetCustomValidity(&self, error: DOMString) {
+        self.set_custom_validity(error);
+    }
+
+    fn Select(&self) {
+        self.textinput_mut().select_all();
+        self.force_relayout();
+    }
+
+    // The selection APIs count characters rather than UTF-16 code units.
+    // http://www.whatwg.org/html/#dom-textarea/input-selectionstart
+    fn SelectionStart(&self) -> u32 {
+        self.textinput.deref().borrow().selection_start() as u32
+    }
+
+    fn SetSelectionStart(&self, start: u32) {
+        let end = self.SelectionEnd();
+        let direction = self.textinput.deref().borrow().selection_direction();
+        self.textinput_mut().set_selection_range(start as uint, max(start, end) as uint,
+                                                 direction);
+        self.force_relayout();
+    }
+
+    fn SelectionEnd(&self) -> u32 {
+        self.textinput.deref().borrow().selection_end() as u32
+    }
+
+    fn SetSelectionEnd(&self, end: u32) {
+        let start = self.SelectionStart();
+        let direction = self.textinput.deref().borrow().selection_direction();
+        self.textinput_mut().set_selection_range(start as uint, end as uint, direction);
+        self.force_relayout();
+    }
+
+    fn SelectionDirection(&self) -> DOMString {
+        self.textinput.deref().borrow().selection_direction().to_str().to_string()
+    }
+
+    fn SetSelectionDirection(&self, direction: DOMString) {
+        let (start, end) = (self.SelectionStart(), self.SelectionEnd());
+        self.SetSelectionRange(start, end, Some(direction));
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-setrangetext
+    fn SetRangeText(&self, replacement: DOMString) {
+        let (start, end) = (self.SelectionStart(), self.SelectionEnd());
+        self.textinput_mut().replace_range(start as uint, end as uint, replacement.as_slice());
+        self.value_changed.deref().set(true);
+        self.force_relayout();
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-setselectionrange
+    fn SetSelectionRange(&self, start: u32, end: u32, direction: Option<DOMString>) {
+        let direction = direction.map_or(NoDirection, |direction| {
+            SelectionDirection::from_str(direction.as_slice())
+        });
+        self.textinput_mut().set_selection_range(start as uint, end as uint, direction);
+        self.force_relayout();
+    }
+}
+
+impl<'a> Validatable for JSRef<'a, HTMLTextAreaElement> {
+    fn is_candidate_for_validation(&self) -> bool {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        !element.get_bool_attribute("readonly") && !element.is_actually_disabled()
+    }
+
+    fn validity_flags(&self) -> ValidityFlags {
+        let element: &JSRef<Element> = ElementCast::from_ref(self);
+        let value = self.Value();
+        let mut flags = ValidityFlags::new();
+        flags.custom_error = !self.custom_validity.deref().borrow().is_empty();
+        // http://www.whatwg.org/html/#attr-textarea-required
+        flags.value_missing = element.get_bool_attribute("required") && value.is_empty();
+        // Only values that the user or script changed can be too long or too short.
+        if self.value_changed.deref().get() {
+            length_flags(element, value.as_slice(), &mut flags);
+        }
+        flags
+    }
+
+    fn custom_validity(&self) -> DOMString {
+        self.custom_validity.deref().borrow().clone()
+    }
+
+    fn set_custom_validity(&self, error: DOMString) {
+        *self.custom_validity.deref().borrow_mut() = error;
+        self.force_relayout();
+    }
+}
+
+impl<'a> VirtualMethods for JSRef<'a, HTMLTextAreaElement> {
+    fn super_type<'a>(&'a self) -> Option<&'a VirtualMethods:> {
+        let htmlelement: &JSRef<HTMLElement> = HTMLElementCast::from_ref(self);
+        Some(htmlelement as &VirtualMethods:)
+    }
+
+    fn child_inserted(&self, child: &JSRef<Node>) {
+        match self.super_type() {
+            Some(ref s) => s.child_inserted(child),
+            _ => (),
+        }
+        self.reset_value();
+    }
+
+    fn parse_plain_attribute(&self, name: &str, value: DOMString) -> AttrValue {
+        match name {
+            "cols" => AttrValue::from_u32(value, DEFAULT_COLS),
+            "rows" => AttrValue::from_u32(value, DEFAULT_ROWS),
+            _ => self.super_type().unwrap().parse_plain_attribute(name, value),
+        }
+    }
+
+    fn handle_event(&self, event: &JSRef<Event>) {
+        match self.super_type() {
+            Some(s) => s.handle_event(event),
+            None => {}
+        }
+        self.handle_event_impl(event);
+    }
 }
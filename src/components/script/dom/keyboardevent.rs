@@ -0,0 +1,532 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::KeyboardEventBinding;
+use dom::bindings::codegen::Bindings::KeyboardEventBinding::KeyboardEventConstants;
+use dom::bindings::codegen::InheritTypes::{EventCast, UIEventCast, KeyboardEventDerived};
+use dom::bindings::error::Fallible;
+use dom::bindings::js::{JSRef, RootedReference, Temporary};
+use dom::bindings::trace::Traceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::event::{Event, KeyboardEventTypeId};
+use dom::uievent::{UIEvent, UIEventMethods};
+use dom::window::Window;
+use servo_msg::constellation_msg::{Key, KeyModifiers, KeyState, Repeated};
+use servo_msg::constellation_msg::{Shift, Control, Alt, Super};
+use servo_msg::constellation_msg;
+use servo_util::str::{DOMString, split_html_space_chars};
+use std::cell::{Cell, RefCell};
+
+#[deriving(Encodable)]
+pub struct KeyboardEvent {
+    pub uievent: UIEvent,
+    pub key: Traceable<RefCell<DOMString>>,
+    pub code: Traceable<RefCell<DOMString>>,
+    pub location: Traceable<Cell<u32>>,
+    pub ctrl_key: Traceable<Cell<bool>>,
+    pub shift_key: Traceable<Cell<bool>>,
+    pub alt_key: Traceable<Cell<bool>>,
+    pub meta_key: Traceable<Cell<bool>>,
+    pub repeat: Traceable<Cell<bool>>,
+    pub is_composing: Traceable<Cell<bool>>,
+    pub char_code: Traceable<Cell<u32>>,
+    pub key_code: Traceable<Cell<u32>>,
+}
+
+impl KeyboardEventDerived for Event {
+    fn is_keyboardevent(&self) -> bool {
+        self.type_id == KeyboardEventTypeId
+    }
+}
+
+impl KeyboardEvent {
+    pub fn new_inherited() -> KeyboardEvent {
+        KeyboardEvent {
+            uievent: UIEvent::new_inherited(KeyboardEventTypeId),
+            key: Traceable::new(RefCell::new("".to_string())),
+            code: Traceable::new(RefCell::new("".to_string())),
+            location: Traceable::new(Cell::new(0)),
+            ctrl_key: Traceable::new(Cell::new(false)),
+            shift_key: Traceable::new(Cell::new(false)),
+            alt_key: Traceable::new(Cell::new(false)),
+            meta_key: Traceable::new(Cell::new(false)),
+            repeat: Traceable::new(Cell::new(false)),
+            is_composing: Traceable::new(Cell::new(false)),
+            char_code: Traceable::new(Cell::new(0)),
+            key_code: Traceable::new(Cell::new(0)),
+        }
+    }
+
+    pub fn new_uninitialized(window: &JSRef<Window>) -> Temporary<KeyboardEvent> {
+        reflect_dom_object(box KeyboardEvent::new_inherited(),
+                           window,
+                           KeyboardEventBinding::Wrap)
+    }
+
+    pub fn new(window: &JSRef<Window>,
+               type_: DOMString,
+               canBubble: bool,
+               cancelable: bool,
+               view: Option<JSRef<Window>>,
+               detail: i32,
+               key: DOMString,
+               code: DOMString,
+               location: u32,
+               ctrlKey: bool,
+               altKey: bool,
+               shiftKey: bool,
+               metaKey: bool,
+               repeat: bool,
+               isComposing: bool,
+               charCode: u32,
+               keyCode: u32) -> Temporary<KeyboardEvent> {
+        let ev = KeyboardEvent::new_uninitialized(window).root();
+        {
+            let uievent: &JSRef<UIEvent> = UIEventCast::from_ref(&*ev);
+            uievent.InitUIEvent(type_, canBubble, cancelable, view, detail);
+        }
+        *ev.key.deref().borrow_mut() = key;
+        *ev.code.deref().borrow_mut() = code;
+        ev.location.deref().set(location);
+        ev.ctrl_key.deref().set(ctrlKey);
+        ev.alt_key.deref().set(altKey);
+        ev.shift_key.deref().set(shiftKey);
+        ev.meta_key.deref().set(metaKey);
+        ev.repeat.deref().set(repeat);
+        ev.is_composing.deref().set(isComposing);
+        ev.char_code.deref().set(charCode);
+        ev.key_code.deref().set(keyCode);
+        Temporary::from_rooted(&*ev)
+    }
+
+    /// Creates the trusted `keydown`, `keypress` or `keyup` event for a key that the user pressed,
+    /// released or held down, typing the given character if any.
+    pub fn new_for_key(window: &JSRef<Window>,
+                       type_: DOMString,
+                       key: Key,
+                       state: KeyState,
+                       modifiers: KeyModifiers,
+                       c: Option<char>) -> Temporary<KeyboardEvent> {
+        let shift = modifiers.contains(Shift);
+        let (char_code, key_code) = if type_.as_slice() == "keypress" {
+            let char_code = match c {
+                Some(c) => c as u32,
+                None if key == constellation_msg::KeyEnter => 13,
+                None => 0,
+            };
+            (char_code, 0)
+        } else {
+            (0, key_code(key))
+        };
+        let ev = KeyboardEvent::new(window, type_, true, true, Some(window.clone()), 0,
+                                    key_value(key, c), code_value(key).to_string(),
+                                    key_location(key),
+                                    modifiers.contains(Control), modifiers.contains(Alt),
+                                    shift, modifiers.contains(Super),
+                                    state == Repeated, false, char_code, key_code).root();
+        {
+            let event: &JSRef<Event> = EventCast::from_ref(&*ev);
+            event.trusted.deref().set(true);
+        }
+        Temporary::from_rooted(&*ev)
+    }
+
+    pub fn Constructor(owner: &JSRef<Window>,
+                       type_: DOMString,
+                       init: &KeyboardEventBinding::KeyboardEventInit) -> Fallible<Temporary<KeyboardEvent>> {
+        let event = KeyboardEvent::new(owner, type_, init.parent.parent.bubbles,
+                                       init.parent.parent.cancelable,
+                                       init.parent.view.root_ref(),
+                                       init.parent.detail,
+                                       init.key.clone(), init.code.clone(), init.location,
+                                       init.ctrlKey, init.altKey, init.shiftKey, init.metaKey,
+                                       init.repeat, init.isComposing,
+                                       init.charCode, init.keyCode);
+        Ok(event)
+    }
+}
+
+/// Returns the character that a printable key types on a US keyboard, or `None` if the key
+/// doesn't type anything. This only gives the legacy `keyCode` of the key: the character that the
+/// key actually types depends on the keyboard layout and comes with the key event.
+fn key_char(key: Key, shift: bool) -> Option<char> {
+    let (unshifted, shifted) = match key {
+        constellation_msg::KeySpace => (' ', ' '),
+        constellation_msg::KeyApostrophe => ('\'', '"'),
+        constellation_msg::KeyComma => (',', '<'),
+        constellation_msg::KeyMinus => ('-', '_'),
+        constellation_msg::KeyPeriod => ('.', '>'),
+        constellation_msg::KeySlash => ('/', '?'),
+        constellation_msg::Key0 => ('0', ')'),
+        constellation_msg::Key1 => ('1', '!'),
+        constellation_msg::Key2 => ('2', '@'),
+        constellation_msg::Key3 => ('3', '#'),
+        constellation_msg::Key4 => ('4', '$'),
+        constellation_msg::Key5 => ('5', '%'),
+        constellation_msg::Key6 => ('6', '^'),
+        constellation_msg::Key7 => ('7', '&'),
+        constellation_msg::Key8 => ('8', '*'),
+        constellation_msg::Key9 => ('9', '('),
+        constellation_msg::KeySemicolon => (';', ':'),
+        constellation_msg::KeyEqual => ('=', '+'),
+        constellation_msg::KeyA => ('a', 'A'),
+        constellation_msg::KeyB => ('b', 'B'),
+        constellation_msg::KeyC => ('c', 'C'),
+        constellation_msg::KeyD => ('d', 'D'),
+        constellation_msg::KeyE => ('e', 'E'),
+        constellation_msg::KeyF => ('f', 'F'),
+        constellation_msg::KeyG => ('g', 'G'),
+        constellation_msg::KeyH => ('h', 'H'),
+        constellation_msg::KeyI => ('i', 'I'),
+        constellation_msg::KeyJ => ('j', 'J'),
+        constellation_msg::KeyK => ('k', 'K'),
+        constellation_msg::KeyL => ('l', 'L'),
+        constellation_msg::KeyM => ('m', 'M'),
+        constellation_msg::KeyN => ('n', 'N'),
+        constellation_msg::KeyO => ('o', 'O'),
+        constellation_msg::KeyP => ('p', 'P'),
+        constellation_msg::KeyQ => ('q', 'Q'),
+        constellation_msg::KeyR => ('r', 'R'),
+        constellation_msg::KeyS => ('s', 'S'),
+        constellation_msg::KeyT => ('t', 'T'),
+        constellation_msg::KeyU => ('u', 'U'),
+        constellation_msg::KeyV => ('v', 'V'),
+        constellation_msg::KeyW => ('w', 'W'),
+        constellation_msg::KeyX => ('x', 'X'),
+        constellation_msg::KeyY => ('y', 'Y'),
+        constellation_msg::KeyZ => ('z', 'Z'),
+        constellation_msg::KeyLeftBracket => ('[', '{'),
+        constellation_msg::KeyBackslash => ('\\', '|'),
+        constellation_msg::KeyRightBracket => (']', '}'),
+        constellation_msg::KeyGraveAccent => ('`', '~'),
+        _ => return None,
+    };
+    Some(if shift { shifted } else { unshifted })
+}
+
+// https://dvcs.w3.org/hg/dom3events/raw-file/tip/html/DOM3-Events.html#key-values-list
+fn key_value(key: Key, c: Option<char>) -> DOMString {
+    match c {
+        Some(c) => return c.to_str(),
+        None => {}
+    }
+    let value = match key {
+        constellation_msg::KeyEscape => "Escape",
+        constellation_msg::KeyEnter => "Enter",
+        constellation_msg::KeyTab => "Tab",
+        constellation_msg::KeyBackspace => "Backspace",
+        constellation_msg::KeyInsert => "Insert",
+        constellation_msg::KeyDelete => "Delete",
+        constellation_msg::KeyRight => "ArrowRight",
+        constellation_msg::KeyLeft => "ArrowLeft",
+        constellation_msg::KeyDown => "ArrowDown",
+        constellation_msg::KeyUp => "ArrowUp",
+        constellation_msg::KeyPageUp => "PageUp",
+        constellation_msg::KeyPageDown => "PageDown",
+        constellation_msg::KeyHome => "Home",
+        constellation_msg::KeyEnd => "End",
+        constellation_msg::KeyCapsLock => "CapsLock",
+        constellation_msg::KeyF1 => "F1",
+        constellation_msg::KeyF2 => "F2",
+        constellation_msg::KeyF3 => "F3",
+        constellation_msg::KeyF4 => "F4",
+        constellation_msg::KeyF5 => "F5",
+        constellation_msg::KeyF6 => "F6",
+        constellation_msg::KeyF7 => "F7",
+        constellation_msg::KeyF8 => "F8",
+        constellation_msg::KeyF9 => "F9",
+        constellation_msg::KeyF10 => "F10",
+        constellation_msg::KeyF11 => "F11",
+        constellation_msg::KeyF12 => "F12",
+        constellation_msg::KeyLeftShift | constellation_msg::KeyRightShift => "Shift",
+        constellation_msg::KeyLeftControl | constellation_msg::KeyRightControl => "Control",
+        constellation_msg::KeyLeftAlt | constellation_msg::KeyRightAlt => "Alt",
+        constellation_msg::KeyLeftSuper | constellation_msg::KeyRightSuper => "Meta",
+        constellation_msg::KeyMenu => "ContextMenu",
+        _ => "Unidentified",
+    };
+    value.to_string()
+}
+
+// https://dvcs.w3.org/hg/d4e/raw-file/tip/source_respec.htm#code-value-tables
+fn code_value(key: Key) -> &'static str {
+    match key {
+        constellation_msg::KeySpace => "Space",
+        constellation_msg::KeyApostrophe => "Quote",
+        constellation_msg::KeyComma => "Comma",
+        constellation_msg::KeyMinus => "Minus",
+        constellation_msg::KeyPeriod => "Period",
+        constellation_msg::KeySlash => "Slash",
+        constellation_msg::Key0 => "Digit0",
+        constellation_msg::Key1 => "Digit1",
+        constellation_msg::Key2 => "Digit2",
+        constellation_msg::Key3 => "Digit3",
+        constellation_msg::Key4 => "Digit4",
+        constellation_msg::Key5 => "Digit5",
+        constellation_msg::Key6 => "Digit6",
+        constellation_msg::Key7 => "Digit7",
+        constellation_msg::Key8 => "Digit8",
+        constellation_msg::Key9 => "Digit9",
+        constellation_msg::KeySemicolon => "Semicolon",
+        constellation_msg::KeyEqual => "Equal",
+        constellation_msg::KeyA => "KeyA",
+        constellation_msg::KeyB => "KeyB",
+        constellation_msg::KeyC => "KeyC",
+        constellation_msg::KeyD => "KeyD",
+        constellation_msg::KeyE => "KeyE",
+        constellation_msg::KeyF => "KeyF",
+        constellation_msg::KeyG => "KeyG",
+        constellation_msg::KeyH => "KeyH",
+        constellation_msg::KeyI => "KeyI",
+        constellation_msg::KeyJ => "KeyJ",
+        constellation_msg::KeyK => "KeyK",
+        constellation_msg::KeyL => "KeyL",
+        constellation_msg::KeyM => "KeyM",
+        constellation_msg::KeyN => "KeyN",
+        constellation_msg::KeyO => "KeyO",
+        constellation_msg::KeyP => "KeyP",
+        constellation_msg::KeyQ => "KeyQ",
+        constellation_msg::KeyR => "KeyR",
+        constellation_msg::KeyS => "KeyS",
+        constellation_msg::KeyT => "KeyT",
+        constellation_msg::KeyU => "KeyU",
+        constellation_msg::KeyV => "KeyV",
+        constellation_msg::KeyW => "KeyW",
+        constellation_msg::KeyX => "KeyX",
+        constellation_msg::KeyY => "KeyY",
+        constellation_msg::KeyZ => "KeyZ",
+        constellation_msg::KeyLeftBracket => "BracketLeft",
+        constellation_msg::KeyBackslash => "Backslash",
+        constellation_msg::KeyRightBracket => "BracketRight",
+        constellation_msg::KeyGraveAccent => "Backquote",
+        constellation_msg::KeyEscape => "Escape",
+        constellation_msg::KeyEnter => "Enter",
+        constellation_msg::KeyTab => "Tab",
+        constellation_msg::KeyBackspace => "Backspace",
+        constellation_msg::KeyInsert => "Insert",
+        constellation_msg::KeyDelete => "Delete",
+        constellation_msg::KeyRight => "ArrowRight",
+        constellation_msg::KeyLeft => "ArrowLeft",
+        constellation_msg::KeyDown => "ArrowDown",
+        constellation_msg::KeyUp => "ArrowUp",
+        constellation_msg::KeyPageUp => "PageUp",
+        constellation_msg::KeyPageDown => "PageDown",
+        constellation_msg::KeyHome => "Home",
+        constellation_msg::KeyEnd => "End",
+        constellation_msg::KeyCapsLock => "CapsLock",
+        constellation_msg::KeyF1 => "F1",
+        constellation_msg::KeyF2 => "F2",
+        constellation_msg::KeyF3 => "F3",
+        constellation_msg::KeyF4 => "F4",
+        constellation_msg::KeyF5 => "F5",
+        constellation_msg::KeyF6 => "F6",
+        constellation_msg::KeyF7 => "F7",
+        constellation_msg::KeyF8 => "F8",
+        constellation_msg::KeyF9 => "F9",
+        constellation_msg::KeyF10 => "F10",
+        constellation_msg::KeyF11 => "F11",
+        constellation_msg::KeyF12 => "F12",
+        constellation_msg::KeyLeftShift => "ShiftLeft",
+        constellation_msg::KeyLeftControl => "ControlLeft",
+        constellation_msg::KeyLeftAlt => "AltLeft",
+        constellation_msg::KeyLeftSuper => "OSLeft",
+        constellation_msg::KeyRightShift => "ShiftRight",
+        constellation_msg::KeyRightControl => "ControlRight",
+        constellation_msg::KeyRightAlt => "AltRight",
+        constellation_msg::KeyRightSuper => "OSRight",
+        constellation_msg::KeyMenu => "ContextMenu",
+        constellation_msg::KeyUnidentified => "",
+    }
+}
+
+fn key_location(key: Key) -> u32 {
+    match key {
+        constellation_msg::KeyLeftShift | constellation_msg::KeyLeftControl |
+        constellation_msg::KeyLeftAlt | constellation_msg::KeyLeftSuper => {
+            KeyboardEventConstants::DOM_KEY_LOCATION_LEFT
+        }
+        constellation_msg::KeyRightShift | constellation_msg::KeyRightControl |
+        constellation_msg::KeyRightAlt | constellation_msg::KeyRightSuper => {
+            KeyboardEventConstants::DOM_KEY_LOCATION_RIGHT
+        }
+        _ => KeyboardEventConstants::DOM_KEY_LOCATION_STANDARD,
+    }
+}
+
+// https://dvcs.w3.org/hg/dom3events/raw-file/tip/html/DOM3-Events.html#legacy-key-models
+fn key_code(key: Key) -> u32 {
+    match key {
+        constellation_msg::KeyBackspace => 8,
+        constellation_msg::KeyTab => 9,
+        constellation_msg::KeyEnter => 13,
+        constellation_msg::KeyLeftShift | constellation_msg::KeyRightShift => 16,
+        constellation_msg::KeyLeftControl | constellation_msg::KeyRightControl => 17,
+        constellation_msg::KeyLeftAlt | constellation_msg::KeyRightAlt => 18,
+        constellation_msg::KeyCapsLock => 20,
+        constellation_msg::KeyEscape => 27,
+        constellation_msg::KeySpace => 32,
+        constellation_msg::KeyPageUp => 33,
+        constellation_msg::KeyPageDown => 34,
+        constellation_msg::KeyEnd => 35,
+        constellation_msg::KeyHome => 36,
+        constellation_msg::KeyLeft => 37,
+        constellation_msg::KeyUp => 38,
+        constellation_msg::KeyRight => 39,
+        constellation_msg::KeyDown => 40,
+        constellation_msg::KeyInsert => 45,
+        constellation_msg::KeyDelete => 46,
+        constellation_msg::KeyLeftSuper => 91,
+        constellation_msg::KeyRightSuper => 92,
+        constellation_msg::KeyMenu => 93,
+        constellation_msg::KeyF1 => 112,
+        constellation_msg::KeyF2 => 113,
+        constellation_msg::KeyF3 => 114,
+        constellation_msg::KeyF4 => 115,
+        constellation_msg::KeyF5 => 116,
+        constellation_msg::KeyF6 => 117,
+        constellation_msg::KeyF7 => 118,
+        constellation_msg::KeyF8 => 119,
+        constellation_msg::KeyF9 => 120,
+        constellation_msg::KeyF10 => 121,
+        constellation_msg::KeyF11 => 122,
+        constellation_msg::KeyF12 => 123,
+        constellation_msg::KeySemicolon => 186,
+        constellation_msg::KeyEqual => 187,
+        constellation_msg::KeyComma => 188,
+        constellation_msg::KeyMinus => 189,
+        constellation_msg::KeyPeriod => 190,
+        constellation_msg::KeySlash => 191,
+        constellation_msg::KeyGraveAccent => 192,
+        constellation_msg::KeyLeftBracket => 219,
+        constellation_msg::KeyBackslash => 220,
+        constellation_msg::KeyRightBracket => 221,
+        constellation_msg::KeyApostrophe => 222,
+        // Letters and digits use the code of the uppercase character.
+        _ => match key_char(key, true) {
+            Some(c) if c.is_alphabetic() => c as u32,
+            Some(_) => key_char(key, false).map_or(0, |c| c as u32),
+            None => 0,
+        },
+    }
+}
+
+pub trait KeyboardEventMethods {
+    fn Key(&self) -> DOMString;
+    fn Code(&self) -> DOMString;
+    fn Location(&self) -> u32;
+    fn CtrlKey(&self) -> bool;
+    fn ShiftKey(&self) -> bool;
+    fn AltKey(&self) -> bool;
+    fn MetaKey(&self) -> bool;
+    fn Repeat(&self) -> bool;
+    fn IsComposing(&self) -> bool;
+    fn GetModifierState(&self, keyArg: DOMString) -> bool;
+    fn CharCode(&self) -> u32;
+    fn KeyCode(&self) -> u32;
+    fn InitKeyboardEvent(&self,
+                         typeArg: DOMString,
+                         canBubbleArg: bool,
+                         cancelableArg: bool,
+                         viewArg: Option<JSRef<Window>>,
+                         keyArg: DOMString,
+                         locationArg: u32,
+                         modifiersListArg: DOMString,
+                         repeat: bool,
+                         locale: DOMString);
+}
+
+impl<'a> KeyboardEventMethods for JSRef<'a, KeyboardEvent> {
+    fn Key(&self) -> DOMString {
+        self.key.deref().borrow().clone()
+    }
+
+    fn Code(&self) -> DOMString {
+        self.code.deref().borrow().clone()
+    }
+
+    fn Location(&self) -> u32 {
+        self.location.deref().get()
+    }
+
+    fn CtrlKey(&self) -> bool {
+        self.ctrl_key.deref().get()
+    }
+
+    fn ShiftKey(&self) -> bool {
+        self.shift_key.deref().get()
+    }
+
+    fn AltKey(&self) -> bool {
+        self.alt_key.deref().get()
+    }
+
+    fn MetaKey(&self) -> bool {
+        self.meta_key.deref().get()
+    }
+
+    fn Repeat(&self) -> bool {
+        self.repeat.deref().get()
+    }
+
+    fn IsComposing(&self) -> bool {
+        self.is_composing.deref().get()
+    }
+
+    // https://dvcs.w3.org/hg/dom3events/raw-file/tip/html/DOM3-Events.html#widl-KeyboardEvent-getModifierState
+    fn GetModifierState(&self, keyArg: DOMString) -> bool {
+        match keyArg.as_slice() {
+            "Control" => self.CtrlKey(),
+            "Shift" => self.ShiftKey(),
+            "Alt" => self.AltKey(),
+            "Meta" => self.MetaKey(),
+            _ => false,
+        }
+    }
+
+    fn CharCode(&self) -> u32 {
+        self.char_code.deref().get()
+    }
+
+    fn KeyCode(&self) -> u32 {
+        self.key_code.deref().get()
+    }
+
+    fn InitKeyboardEvent(&self,
+                         typeArg: DOMString,
+                         canBubbleArg: bool,
+                         cancelableArg: bool,
+                         viewArg: Option<JSRef<Window>>,
+                         keyArg: DOMString,
+                         locationArg: u32,
+                         modifiersListArg: DOMString,
+                         repeat: bool,
+                         _locale: DOMString) {
+        let uievent: &JSRef<UIEvent> = UIEventCast::from_ref(self);
+        uievent.InitUIEvent(typeArg, canBubbleArg, cancelableArg, viewArg, 0);
+        *self.key.deref().borrow_mut() = keyArg;
+        self.location.deref().set(locationArg);
+        self.repeat.deref().set(repeat);
+
+        let mut modifiers = KeyModifiers::empty();
+        for modifier in split_html_space_chars(modifiersListArg.as_slice()) {
+            match modifier {
+                "Control" => modifiers.insert(Control),
+                "Shift" => modifiers.insert(Shift),
+                "Alt" => modifiers.insert(Alt),
+                "Meta" => modifiers.insert(Super),
+                _ => {}
+            }
+        }
+        self.ctrl_key.deref().set(modifiers.contains(Control));
+        self.shift_key.deref().set(modifiers.contains(Shift));
+        self.alt_key.deref().set(modifiers.contains(Alt));
+        self.meta_key.deref().set(modifiers.contains(Super));
+    }
+}
+
+impl Reflectable for KeyboardEvent {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.uievent.reflector()
+    }
+}
@@ -3,13 +3,20 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use dom::bindings::codegen::Bindings::LocationBinding;
+use dom::bindings::error::{ErrorResult, Syntax};
 use dom::bindings::js::{JSRef, Temporary};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
-use dom::window::Window;
+use dom::window::{Window, WindowHelpers};
+use servo_msg::constellation_msg::{Load, NavigationType, Replace};
+use servo_net::resource_task::LoadData;
 use servo_util::str::DOMString;
+use servo_util::url::try_parse_url;
 
 use page::Page;
+use std::ascii::StrAsciiExt;
 use std::rc::Rc;
+use url;
+use url::Url;
 
 use serialize::{Encoder, Encodable};
 
@@ -35,14 +42,192 @@ impl Location {
     }
 }
 
+trait PrivateLocationHelpers {
+    fn window(&self) -> Temporary<Window>;
+    fn navigate(&self, url: Url, navigation_type: NavigationType);
+    fn navigate_to_href(&self, href: DOMString, navigation_type: NavigationType) -> ErrorResult;
+}
+
+impl<'a> PrivateLocationHelpers for JSRef<'a, Location> {
+    fn window(&self) -> Temporary<Window> {
+        let frame = self.page.frame();
+        Temporary::new(frame.get_ref().window.clone())
+    }
+
+    fn navigate(&self, url: Url, navigation_type: NavigationType) {
+        let window = self.window().root();
+        window.deref().navigate(url, navigation_type);
+    }
+
+    fn navigate_to_href(&self, href: DOMString, navigation_type: NavigationType) -> ErrorResult {
+        match try_parse_url(href.as_slice(), Some(self.page.get_url())) {
+            Ok(url) => {
+                self.navigate(url, navigation_type);
+                Ok(())
+            }
+            Err(_) => Err(Syntax),
+        }
+    }
+}
+
 pub trait LocationMethods {
+    fn Assign(&self, url: DOMString) -> ErrorResult;
+    fn Replace(&self, url: DOMString) -> ErrorResult;
+    fn Reload(&self);
     fn Href(&self) -> DOMString;
+    fn SetHref(&self, value: DOMString) -> ErrorResult;
+    fn Protocol(&self) -> DOMString;
+    fn SetProtocol(&self, value: DOMString);
+    fn Host(&self) -> DOMString;
+    fn SetHost(&self, value: DOMString);
+    fn Hostname(&self) -> DOMString;
+    fn SetHostname(&self, value: DOMString);
+    fn Port(&self) -> DOMString;
+    fn SetPort(&self, value: DOMString);
+    fn Pathname(&self) -> DOMString;
+    fn SetPathname(&self, value: DOMString);
+    fn Search(&self) -> DOMString;
+    fn SetSearch(&self, value: DOMString);
+    fn Hash(&self) -> DOMString;
+    fn SetHash(&self, value: DOMString);
 }
 
 impl<'a> LocationMethods for JSRef<'a, Location> {
+    // http://www.whatwg.org/html/#dom-location-assign
+    fn Assign(&self, url: DOMString) -> ErrorResult {
+        self.navigate_to_href(url, Load)
+    }
+
+    // http://www.whatwg.org/html/#dom-location-replace
+    fn Replace(&self, url: DOMString) -> ErrorResult {
+        self.navigate_to_href(url, Replace)
+    }
+
+    // http://www.whatwg.org/html/#dom-location-reload
+    fn Reload(&self) {
+        let window = self.window().root();
+        window.deref().load_data(LoadData::new(self.page.get_url()), Replace);
+    }
+
     fn Href(&self) -> DOMString {
         self.page.get_url().to_str()
     }
+
+    fn SetHref(&self, value: DOMString) -> ErrorResult {
+        self.navigate_to_href(value, Load)
+    }
+
+    fn Protocol(&self) -> DOMString {
+        format!("{}:", self.page.get_url().scheme)
+    }
+
+    fn SetProtocol(&self, value: DOMString) {
+        let scheme = value.as_slice().split(':').next().unwrap_or("");
+        if scheme.is_empty() {
+            return;
+        }
+        let mut url = self.page.get_url();
+        url.scheme = scheme.to_ascii_lower();
+        self.navigate(url, Load);
+    }
+
+    fn Host(&self) -> DOMString {
+        let url = self.page.get_url();
+        match url.port {
+            Some(ref port) => format!("{}:{}", url.host, port),
+            None => url.host.clone(),
+        }
+    }
+
+    fn SetHost(&self, value: DOMString) {
+        let value = value.as_slice();
+        let (host, port) = match value.find(':') {
+            Some(index) => (value.slice_to(index), Some(value.slice_from(index + 1).to_string())),
+            None => (value, None),
+        };
+        if host.is_empty() {
+            return;
+        }
+        let mut url = self.page.get_url();
+        url.host = host.to_string();
+        url.port = port;
+        self.navigate(url, Load);
+    }
+
+    fn Hostname(&self) -> DOMString {
+        self.page.get_url().host
+    }
+
+    fn SetHostname(&self, value: DOMString) {
+        if value.is_empty() {
+            return;
+        }
+        let mut url = self.page.get_url();
+        url.host = value;
+        self.navigate(url, Load);
+    }
+
+    fn Port(&self) -> DOMString {
+        self.page.get_url().port.unwrap_or(String::new())
+    }
+
+    fn SetPort(&self, value: DOMString) {
+        let mut url = self.page.get_url();
+        url.port = if value.is_empty() { None } else { Some(value) };
+        self.navigate(url, Load);
+    }
+
+    fn Pathname(&self) -> DOMString {
+        let url = self.page.get_url();
+        if url.path.is_empty() {
+            "/".to_string()
+        } else {
+            url.path.clone()
+        }
+    }
+
+    fn SetPathname(&self, value: DOMString) {
+        let mut url = self.page.get_url();
+        url.path = if value.as_slice().starts_with("/") {
+            value
+        } else {
+            format!("/{}", value)
+        };
+        self.navigate(url, Load);
+    }
+
+    fn Search(&self) -> DOMString {
+        let url = self.page.get_url();
+        if url.query.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", url::query_to_str(&url.query))
+        }
+    }
+
+    fn SetSearch(&self, value: DOMString) {
+        let mut url = self.page.get_url();
+        let value = value.as_slice();
+        let value = if value.starts_with("?") { value.slice_from(1) } else { value };
+        url.query = url::query_from_str(value);
+        self.navigate(url, Load);
+    }
+
+    fn Hash(&self) -> DOMString {
+        match self.page.get_url().fragment {
+            Some(ref fragment) if !fragment.is_empty() => format!("#{}", fragment),
+            _ => String::new(),
+        }
+    }
+
+    // http://url.spec.whatwg.org/#dom-urlutils-hash
+    fn SetHash(&self, value: DOMString) {
+        let mut url = self.page.get_url();
+        let value = value.as_slice();
+        let value = if value.starts_with("#") { value.slice_from(1) } else { value };
+        url.fragment = Some(value.to_string());
+        self.navigate(url, Load);
+    }
 }
 
 impl Reflectable for Location {
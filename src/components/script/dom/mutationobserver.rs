@@ -0,0 +1,449 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::MutationObserverBinding;
+use dom::bindings::codegen::Bindings::MutationObserverBinding::MutationObserverInit;
+use dom::bindings::conversions::{FromJSValConvertible, ToJSValConvertible, Default};
+use dom::bindings::error::{ErrorResult, Fallible, Syntax};
+use dom::bindings::js::{JS, JSRef, Root, Temporary, TemporaryPushable};
+use dom::bindings::trace::Traceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::mutationrecord::MutationRecord;
+use dom::document::DocumentHelpers;
+use dom::node::{Node, NodeHelpers, document_from_node, window_from_node};
+use dom::window::Window;
+use servo_util::namespace;
+use servo_util::namespace::Namespace;
+use servo_util::str::DOMString;
+
+use js::jsapi::{JSContext, JS_CallFunctionValue, JS_GetArrayLength, JS_GetElement};
+use js::jsapi::JS_NewArrayObject;
+use js::jsval::{JSVal, NullValue, ObjectValue};
+use js::rust::with_compartment;
+
+use libc::c_int;
+use std::cell::RefCell;
+use std::mem::replace;
+
+use serialize::{Encoder, Encodable};
+
+/// What a mutation observer watches a node for, as given to `observe()`.
+#[deriving(Clone, Encodable)]
+pub struct MutationObserverOptions {
+    pub child_list: bool,
+    pub attributes: bool,
+    pub character_data: bool,
+    pub subtree: bool,
+    pub attribute_old_value: bool,
+    pub character_data_old_value: bool,
+    pub attribute_filter: Option<Vec<DOMString>>,
+}
+
+/// An entry in the list of observers that a node keeps.
+// http://dom.spec.whatwg.org/#registered-observer
+#[deriving(Encodable)]
+pub struct RegisteredObserver {
+    pub observer: JS<MutationObserver>,
+    pub options: MutationObserverOptions,
+    /// For a transient registered observer, which a node removed from an observed subtree gets
+    /// until the next delivery, the node that the observer is really registered on.
+    pub source: Option<JS<Node>>,
+}
+
+/// A change to a node that mutation observers may be told about.
+pub enum Mutation<'a> {
+    /// The attribute with the given local name and namespace was added, changed or removed. The
+    /// old value is `None` if the attribute was added.
+    AttributeMutation(DOMString, Namespace, Option<DOMString>),
+    /// The data of a text node, comment or processing instruction changed from the given value.
+    CharacterDataMutation(DOMString),
+    /// The given nodes were added and removed between the given previous and next siblings.
+    ChildListMutation(Vec<JSRef<'a, Node>>, Vec<JSRef<'a, Node>>,
+                      Option<JSRef<'a, Node>>, Option<JSRef<'a, Node>>),
+}
+
+#[deriving(Encodable)]
+pub struct MutationObserver {
+    pub reflector_: Reflector,
+    pub window: JS<Window>,
+    pub callback: Traceable<JSVal>,
+    /// The records queued for the next delivery to the callback.
+    pub record_queue: Traceable<RefCell<Vec<JS<MutationRecord>>>>,
+    /// The nodes this observer is registered on, so that `disconnect()` can find them.
+    // FIXME: these should be weak references.
+    pub nodes: Traceable<RefCell<Vec<JS<Node>>>>,
+}
+
+impl MutationObserver {
+    pub fn new_inherited(window: &JSRef<Window>, callback: JSVal) -> MutationObserver {
+        MutationObserver {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            callback: Traceable::new(callback),
+            record_queue: Traceable::new(RefCell::new(vec!())),
+            nodes: Traceable::new(RefCell::new(vec!())),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, callback: JSVal) -> Temporary<MutationObserver> {
+        reflect_dom_object(box MutationObserver::new_inherited(window, callback),
+                           window,
+                           MutationObserverBinding::Wrap)
+    }
+
+    pub fn Constructor(owner: &JSRef<Window>, callback: JSVal)
+                       -> Fallible<Temporary<MutationObserver>> {
+        Ok(MutationObserver::new(owner, callback))
+    }
+}
+
+pub trait MutationObserverMethods {
+    fn Observe(&self, target: &JSRef<Node>, options: &MutationObserverInit) -> ErrorResult;
+    fn Disconnect(&self);
+    fn TakeRecords(&self, cx: *mut JSContext) -> JSVal;
+}
+
+impl<'a> MutationObserverMethods for JSRef<'a, MutationObserver> {
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-observe
+    fn Observe(&self, target: &JSRef<Node>, options: &MutationObserverInit) -> ErrorResult {
+        let attribute_filter = match options.attributeFilter {
+            Some(filter) => Some(try!(self.string_list_from_jsval(filter))),
+            None => None,
+        };
+
+        // Steps 1-2.
+        let attributes = options.attributes.unwrap_or(
+            options.attributeOldValue.is_some() || attribute_filter.is_some());
+        let character_data = options.characterData.unwrap_or(
+            options.characterDataOldValue.is_some());
+        let options = MutationObserverOptions {
+            child_list: options.childList,
+            attributes: attributes,
+            character_data: character_data,
+            subtree: options.subtree,
+            attribute_old_value: options.attributeOldValue.unwrap_or(false),
+            character_data_old_value: options.characterDataOldValue.unwrap_or(false),
+            attribute_filter: attribute_filter,
+        };
+
+        // Steps 3-6. The spec throws a TypeError here, as Gecko throws a SyntaxError.
+        if !options.child_list && !options.attributes && !options.character_data {
+            return Err(Syntax);
+        }
+        if !options.attributes &&
+           (options.attribute_old_value || options.attribute_filter.is_some()) {
+            return Err(Syntax);
+        }
+        if !options.character_data && options.character_data_old_value {
+            return Err(Syntax);
+        }
+
+        let index = target.registered_observers.deref().borrow().iter().position(|registered| {
+            registered.source.is_none() && registered.observer.root().deref() == self
+        });
+        match index {
+            // Step 7.
+            Some(index) => {
+                self.remove_transient_registered_observers(Some(target));
+                target.registered_observers.deref().borrow_mut().get_mut(index).options = options;
+            }
+            // Step 8.
+            None => {
+                add_registered_observer(target, RegisteredObserver {
+                    observer: JS::from_rooted(self),
+                    options: options,
+                    source: None,
+                });
+                self.nodes.deref().borrow_mut().push_unrooted(target);
+            }
+        }
+        Ok(())
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-disconnect
+    fn Disconnect(&self) {
+        let nodes = replace(&mut *self.nodes.deref().borrow_mut(), vec!());
+        for node in nodes.iter() {
+            let node = node.root();
+            remove_registered_observers(&*node, |registered| {
+                registered.observer.root().deref() == self
+            });
+        }
+        self.record_queue.deref().borrow_mut().clear();
+    }
+
+    // http://dom.spec.whatwg.org/#dom-mutationobserver-takerecords
+    fn TakeRecords(&self, cx: *mut JSContext) -> JSVal {
+        let records = replace(&mut *self.record_queue.deref().borrow_mut(), vec!());
+        records_to_jsval(cx, records)
+    }
+}
+
+pub trait MutationObserverHelpers {
+    fn enqueue_record(&self, record: &JSRef<MutationRecord>);
+    fn deliver_records(&self) -> bool;
+}
+
+impl<'a> MutationObserverHelpers for JSRef<'a, MutationObserver> {
+    /// Adds a record to the queue, and makes sure the window will deliver it at the next
+    /// microtask checkpoint.
+    fn enqueue_record(&self, record: &JSRef<MutationRecord>) {
+        let mut record_queue = self.record_queue.deref().borrow_mut();
+        if record_queue.is_empty() {
+            self.schedule_delivery();
+        }
+        record_queue.push_unrooted(record);
+    }
+
+    /// Calls the callback with the queued records, if there are any, and returns whether it
+    /// did.
+    // http://dom.spec.whatwg.org/#notify-mutation-observers
+    fn deliver_records(&self) -> bool {
+        let records = replace(&mut *self.record_queue.deref().borrow_mut(), vec!());
+        self.remove_transient_registered_observers(None);
+        if records.is_empty() {
+            return false;
+        }
+
+        let window = self.window.root();
+        let cx = window.deref().get_cx();
+        let this_value = self.reflector().get_jsobject();
+        with_compartment(cx, window.deref().reflector().get_jsobject(), || {
+            let mut argv = [records_to_jsval(cx, records), ObjectValue(unsafe { &*this_value })];
+            let mut rval = NullValue();
+            unsafe {
+                JS_CallFunctionValue(cx, this_value, *self.callback,
+                                     2, argv.as_mut_ptr(), &mut rval);
+            }
+        });
+        true
+    }
+}
+
+trait PrivateMutationObserverHelpers {
+    fn string_list_from_jsval(&self, value: JSVal) -> Fallible<Vec<DOMString>>;
+    fn schedule_delivery(&self);
+    fn remove_transient_registered_observers(&self, source: Option<&JSRef<Node>>);
+}
+
+impl<'a> PrivateMutationObserverHelpers for JSRef<'a, MutationObserver> {
+    /// Makes sure the window will deliver this observer's records, and drop its transient
+    /// registered observers, at the next microtask checkpoint.
+    fn schedule_delivery(&self) {
+        let window = self.window.root();
+        let mut pending_observers = window.deref().pending_mutation_observers.deref().borrow_mut();
+        if !pending_observers.iter().any(|observer| observer.root().deref() == self) {
+            pending_observers.push_unrooted(self);
+        }
+    }
+
+    /// Removes this observer's transient registered observers, or only those that stand in for
+    /// its registration on the given node.
+    fn remove_transient_registered_observers(&self, source: Option<&JSRef<Node>>) {
+        let source = source.map(|source| JS::from_rooted(source));
+        let mut nodes = self.nodes.deref().borrow_mut();
+        for node in nodes.iter() {
+            let node = node.root();
+            remove_registered_observers(&*node, |registered| {
+                registered.source.is_some() &&
+                    (source.is_none() || registered.source == source) &&
+                    registered.observer.root().deref() == self
+            });
+        }
+        nodes.retain(|node| {
+            node.root().deref().registered_observers.deref().borrow().iter().any(|registered| {
+                registered.observer.root().deref() == self
+            })
+        });
+    }
+
+    /// Converts the `attributeFilter` option, which should be a sequence of strings.
+    fn string_list_from_jsval(&self, value: JSVal) -> Fallible<Vec<DOMString>> {
+        if !value.is_object() {
+            return Err(Syntax);
+        }
+        let window = self.window.root();
+        let cx = window.deref().get_cx();
+        let object = value.to_object();
+        let mut length = 0;
+        if unsafe { JS_GetArrayLength(cx, object, &mut length) } == 0 {
+            return Err(Syntax);
+        }
+        let mut strings = vec!();
+        for index in range(0, length) {
+            let mut element = NullValue();
+            if unsafe { JS_GetElement(cx, object, index, &mut element) } == 0 {
+                return Err(Syntax);
+            }
+            match FromJSValConvertible::from_jsval(cx, element, Default) {
+                Ok(string) => strings.push(string),
+                Err(()) => return Err(Syntax),
+            }
+        }
+        Ok(strings)
+    }
+}
+
+impl Reflectable for MutationObserver {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
+
+/// Wraps mutation records in a JavaScript array.
+fn records_to_jsval(cx: *mut JSContext, records: Vec<JS<MutationRecord>>) -> JSVal {
+    // Once they leave the queue nothing else keeps the records alive, and creating the array can
+    // trigger a garbage collection.
+    let records: Vec<Root<MutationRecord>> = records.iter().map(|record| record.root()).collect();
+    let mut values: Vec<JSVal> = records.iter().map(|record| record.to_jsval(cx)).collect();
+    unsafe {
+        let array = JS_NewArrayObject(cx, values.len() as c_int, values.as_mut_ptr());
+        ObjectValue(&*array)
+    }
+}
+
+/// Adds an entry to the given node's list of registered observers.
+fn add_registered_observer(node: &JSRef<Node>, registered: RegisteredObserver) {
+    node.registered_observers.deref().borrow_mut().push(registered);
+    let document = document_from_node(node).root();
+    document.deref().add_registered_observers(1);
+}
+
+/// Removes the entries of the given node's list of registered observers that `f` returns true
+/// for.
+fn remove_registered_observers(node: &JSRef<Node>, f: |&RegisteredObserver| -> bool) {
+    let removed = {
+        let mut registered_observers = node.registered_observers.deref().borrow_mut();
+        let length = registered_observers.len();
+        registered_observers.retain(|registered| !f(registered));
+        length - registered_observers.len()
+    };
+    if removed > 0 {
+        let document = document_from_node(node).root();
+        document.deref().remove_registered_observers(removed);
+    }
+}
+
+/// Gives `node`, which is being removed from `parent`, a transient registered observer for each
+/// observer of `parent` or its ancestors that watches their subtree, so that mutations of the
+/// removed subtree are still reported until the next delivery.
+// http://dom.spec.whatwg.org/#concept-node-remove
+pub fn add_transient_registered_observers(node: &JSRef<Node>, parent: &JSRef<Node>) {
+    let document = document_from_node(parent).root();
+    if !document.deref().has_registered_observers() {
+        return;
+    }
+
+    let mut transient_observers = vec!();
+    for ancestor in Some(parent.clone()).move_iter().chain(parent.ancestors()) {
+        for registered in ancestor.registered_observers.deref().borrow().iter() {
+            if !registered.options.subtree {
+                continue;
+            }
+            let source = match registered.source {
+                Some(ref source) => source.clone(),
+                None => JS::from_rooted(&ancestor),
+            };
+            transient_observers.push(RegisteredObserver {
+                observer: registered.observer.clone(),
+                options: registered.options.clone(),
+                source: Some(source),
+            });
+        }
+    }
+
+    for transient in transient_observers.move_iter() {
+        let observer = transient.observer.root();
+        observer.deref().nodes.deref().borrow_mut().push_unrooted(node);
+        observer.deref().schedule_delivery();
+        add_registered_observer(node, transient);
+    }
+}
+
+/// Tells the observers of `target` and, for those that watch its subtree, of its ancestors
+/// about a mutation, by queuing a record for each interested observer.
+// http://dom.spec.whatwg.org/#queue-a-mutation-record
+pub fn queue_mutation_record(target: &JSRef<Node>, mutation: Mutation) {
+    // Most documents are not observed at all, so there is nobody to look for.
+    let document = document_from_node(target).root();
+    if !document.deref().has_registered_observers() {
+        return;
+    }
+
+    // Steps 1-3.
+    let mut interested_observers: Vec<(JS<MutationObserver>, Option<DOMString>)> = vec!();
+    for node in Some(target.clone()).move_iter().chain(target.ancestors()) {
+        for registered in node.registered_observers.deref().borrow().iter() {
+            let options = &registered.options;
+            if node != *target && !options.subtree {
+                continue;
+            }
+            let old_value = match mutation {
+                AttributeMutation(ref name, ref attr_namespace, ref old_value) => {
+                    if !options.attributes {
+                        continue;
+                    }
+                    match options.attribute_filter {
+                        Some(ref filter) if *attr_namespace != namespace::Null ||
+                                            !filter.contains(name) => continue,
+                        _ => {}
+                    }
+                    if options.attribute_old_value { old_value.clone() } else { None }
+                }
+                CharacterDataMutation(ref old_value) => {
+                    if !options.character_data {
+                        continue;
+                    }
+                    if options.character_data_old_value { Some(old_value.clone()) } else { None }
+                }
+                ChildListMutation(..) => {
+                    if !options.child_list {
+                        continue;
+                    }
+                    None
+                }
+            };
+            let index = interested_observers.iter().position(|&(ref observer, _)| {
+                *observer == registered.observer
+            });
+            match index {
+                Some(index) => match *interested_observers.get_mut(index) {
+                    (_, ref mut interested_old_value) if old_value.is_some() => {
+                        *interested_old_value = old_value;
+                    }
+                    _ => {}
+                },
+                None => interested_observers.push((registered.observer.clone(), old_value)),
+            }
+        }
+    }
+    if interested_observers.is_empty() {
+        return;
+    }
+
+    // Step 4.
+    let window = window_from_node(target).root();
+    for (observer, old_value) in interested_observers.move_iter() {
+        let record = match mutation {
+            AttributeMutation(ref name, ref attr_namespace, _) => {
+                let attr_namespace = match attr_namespace.to_str() {
+                    "" => None,
+                    url => Some(url.to_string()),
+                };
+                MutationRecord::new(&*window, "attributes".to_string(), target, vec!(), vec!(),
+                                    None, None, Some(name.clone()), attr_namespace, old_value)
+            }
+            CharacterDataMutation(_) => {
+                MutationRecord::new(&*window, "characterData".to_string(), target, vec!(),
+                                    vec!(), None, None, None, None, old_value)
+            }
+            ChildListMutation(ref added, ref removed, ref previous, ref next) => {
+                MutationRecord::new(&*window, "childList".to_string(), target, added.clone(),
+                                    removed.clone(), previous.clone(), next.clone(), None, None,
+                                    None)
+            }
+        }.root();
+        observer.root().enqueue_record(&*record);
+    }
+}
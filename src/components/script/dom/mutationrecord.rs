@@ -0,0 +1,119 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::MutationRecordBinding;
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::node::Node;
+use dom::nodelist::NodeList;
+use dom::window::Window;
+use servo_util::str::DOMString;
+
+/// A change that was made to a node, as handed to the callbacks of mutation observers.
+#[deriving(Encodable)]
+pub struct MutationRecord {
+    pub reflector_: Reflector,
+    pub type_: DOMString,
+    pub target: JS<Node>,
+    pub added_nodes: JS<NodeList>,
+    pub removed_nodes: JS<NodeList>,
+    pub previous_sibling: Option<JS<Node>>,
+    pub next_sibling: Option<JS<Node>>,
+    pub attribute_name: Option<DOMString>,
+    pub attribute_namespace: Option<DOMString>,
+    pub old_value: Option<DOMString>,
+}
+
+impl MutationRecord {
+    pub fn new_inherited(type_: DOMString, target: &JSRef<Node>,
+                         added_nodes: &JSRef<NodeList>, removed_nodes: &JSRef<NodeList>,
+                         previous_sibling: Option<JSRef<Node>>,
+                         next_sibling: Option<JSRef<Node>>,
+                         attribute_name: Option<DOMString>,
+                         attribute_namespace: Option<DOMString>,
+                         old_value: Option<DOMString>) -> MutationRecord {
+        MutationRecord {
+            reflector_: Reflector::new(),
+            type_: type_,
+            target: JS::from_rooted(target),
+            added_nodes: JS::from_rooted(added_nodes),
+            removed_nodes: JS::from_rooted(removed_nodes),
+            previous_sibling: previous_sibling.map(|node| JS::from_rooted(&node)),
+            next_sibling: next_sibling.map(|node| JS::from_rooted(&node)),
+            attribute_name: attribute_name,
+            attribute_namespace: attribute_namespace,
+            old_value: old_value,
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, type_: DOMString, target: &JSRef<Node>,
+               added_nodes: Vec<JSRef<Node>>, removed_nodes: Vec<JSRef<Node>>,
+               previous_sibling: Option<JSRef<Node>>, next_sibling: Option<JSRef<Node>>,
+               attribute_name: Option<DOMString>, attribute_namespace: Option<DOMString>,
+               old_value: Option<DOMString>) -> Temporary<MutationRecord> {
+        let added_nodes = NodeList::new_simple_list(window, added_nodes).root();
+        let removed_nodes = NodeList::new_simple_list(window, removed_nodes).root();
+        let record = MutationRecord::new_inherited(type_, target, &*added_nodes,
+                                                   &*removed_nodes, previous_sibling,
+                                                   next_sibling, attribute_name,
+                                                   attribute_namespace, old_value);
+        reflect_dom_object(box record, window, MutationRecordBinding::Wrap)
+    }
+}
+
+pub trait MutationRecordMethods {
+    fn Type(&self) -> DOMString;
+    fn Target(&self) -> Temporary<Node>;
+    fn AddedNodes(&self) -> Temporary<NodeList>;
+    fn RemovedNodes(&self) -> Temporary<NodeList>;
+    fn GetPreviousSibling(&self) -> Option<Temporary<Node>>;
+    fn GetNextSibling(&self) -> Option<Temporary<Node>>;
+    fn GetAttributeName(&self) -> Option<DOMString>;
+    fn GetAttributeNamespace(&self) -> Option<DOMString>;
+    fn GetOldValue(&self) -> Option<DOMString>;
+}
+
+impl<'a> MutationRecordMethods for JSRef<'a, MutationRecord> {
+    fn Type(&self) -> DOMString {
+        self.type_.clone()
+    }
+
+    fn Target(&self) -> Temporary<Node> {
+        Temporary::new(self.target.clone())
+    }
+
+    fn AddedNodes(&self) -> Temporary<NodeList> {
+        Temporary::new(self.added_nodes.clone())
+    }
+
+    fn RemovedNodes(&self) -> Temporary<NodeList> {
+        Temporary::new(self.removed_nodes.clone())
+    }
+
+    fn GetPreviousSibling(&self) -> Option<Temporary<Node>> {
+        self.previous_sibling.as_ref().map(|node| Temporary::new(node.clone()))
+    }
+
+    fn GetNextSibling(&self) -> Option<Temporary<Node>> {
+        self.next_sibling.as_ref().map(|node| Temporary::new(node.clone()))
+    }
+
+    fn GetAttributeName(&self) -> Option<DOMString> {
+        self.attribute_name.clone()
+    }
+
+    fn GetAttributeNamespace(&self) -> Option<DOMString> {
+        self.attribute_namespace.clone()
+    }
+
+    fn GetOldValue(&self) -> Option<DOMString> {
+        self.old_value.clone()
+    }
+}
+
+impl Reflectable for MutationRecord {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
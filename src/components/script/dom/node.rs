@@ -25,7 +25,10 @@ use dom::documentfragment::DocumentFragment;
 use dom::documenttype::DocumentType;
 use dom::element::{AttributeHandlers, Element, ElementMethods, ElementTypeId};
 use dom::element::{HTMLAnchorElementTypeId, ElementHelpers};
-use dom::eventtarget::{EventTarget, NodeTargetTypeId};
+use dom::event::Event;
+use dom::mutationobserver::{CharacterDataMutation, ChildListMutation, RegisteredObserver};
+use dom::mutationobserver::{add_transient_registered_observers, queue_mutation_record};
+use dom::eventtarget::{EventTarget, EventTargetHelpers, NodeTargetTypeId};
 use dom::nodelist::{NodeList};
 use dom::processinginstruction::{ProcessingInstruction, ProcessingInstructionMethods};
 use dom::text::Text;
@@ -89,6 +92,9 @@ pub struct Node {
     /// A bitfield of flags for node items.
     flags: Traceable<RefCell<NodeFlags>>,
 
+    /// The mutation observers that were asked to observe this node.
+    pub registered_observers: Traceable<RefCell<Vec<RegisteredObserver>>>,
+
     /// Layout information. Only the layout task may touch this data.
     ///
     /// FIXME(pcwalton): We need to send these back to the layout task to be destroyed when this
@@ -118,7 +124,13 @@ bitflags! {
         #[doc = "Specifies whether this node is in a document."]
         static IsInDoc = 0x01,
         #[doc = "Specifies whether this node is hover state for this node"]
-        static InHoverState = 0x02
+        static InHoverState = 0x02,
+        #[doc = "Specifies whether this node has the focus"]
+        static InFocusState = 0x04,
+        #[doc = "Specifies whether this node is a form control that satisfies its constraints"]
+        static InValidState = 0x08,
+        #[doc = "Specifies whether this node is a form control that fails its constraints"]
+        static InInvalidState = 0x10
     }
 }
 
@@ -238,7 +250,7 @@ pub enum NodeTypeId {
 
 trait PrivateNodeHelpers {
     fn node_inserted(&self);
-    fn node_removed(&self);
+    fn node_removed(&self, parent: &JSRef<Node>);
     fn add_child(&self, new_child: &JSRef<Node>, before: Option<JSRef<Node>>);
     fn remove_child(&self, child: &JSRef<Node>);
 }
@@ -262,7 +274,7 @@ impl<'a> PrivateNodeHelpers for JSRef<'a, Node> {
     }
 
     // http://dom.spec.whatwg.org/#node-is-removed
-    fn node_removed(&self) {
+    fn node_removed(&self, parent: &JSRef<Node>) {
         assert!(self.parent_node().is_none());
         let document = document_from_node(self).root();
 
@@ -271,6 +283,8 @@ impl<'a> PrivateNodeHelpers for JSRef<'a, Node> {
             vtable_for(&node).unbind_from_tree();
         }
 
+        vtable_for(parent).child_removed(self);
+
         document.deref().content_changed();
     }
 
@@ -384,6 +398,11 @@ pub trait NodeHelpers {
 
     fn get_hover_state(&self) -> bool;
     fn set_hover_state(&self, state: bool);
+    fn get_focus_state(&self) -> bool;
+    fn set_focus_state(&self, state: bool);
+    fn set_validity_state(&self, state: Option<bool>);
+
+    fn fire_simple_event(&self, type_: &str, bubbles: bool, cancelable: bool) -> bool;
 
     fn dump(&self);
     fn dump_indent(&self, indent: uint);
@@ -502,6 +521,41 @@ impl<'a> NodeHelpers for JSRef<'a, Node> {
         }
     }
 
+    fn get_focus_state(&self) -> bool {
+        self.flags.deref().borrow().contains(InFocusState)
+    }
+
+    fn set_focus_state(&self, state: bool) {
+        if state {
+            self.flags.deref().borrow_mut().insert(InFocusState);
+        } else {
+            self.flags.deref().borrow_mut().remove(InFocusState);
+        }
+    }
+
+    /// Records whether this node matches `:valid`, if `state` is `Some(true)`, or `:invalid`, if
+    /// it is `Some(false)`, for layout to read.
+    fn set_validity_state(&self, state: Option<bool>) {
+        let mut flags = self.flags.deref().borrow_mut();
+        flags.remove(InValidState | InInvalidState);
+        match state {
+            Some(true) => flags.insert(InValidState),
+            Some(false) => flags.insert(InInvalidState),
+            None => {}
+        }
+    }
+
+    /// Fires a trusted event that uses the plain `Event` interface at this node. Returns false if
+    /// the event was cancelled.
+    /// http://www.whatwg.org/html/#fire-a-simple-event
+    fn fire_simple_event(&self, type_: &str, bubbles: bool, cancelable: bool) -> bool {
+        let window = window_from_node(self).root();
+        let event = Event::new(&*window, type_.to_string(), bubbles, cancelable).root();
+        event.deref().trusted.deref().set(true);
+        let target: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        target.dispatch_event_with_target(None, &*event).unwrap_or(false)
+    }
+
     /// Iterates over this node and all its descendants, in preorder.
     fn traverse_preorder<'a>(&'a self) -> TreeIterator<'a> {
         let mut nodes = vec!();
@@ -730,12 +784,27 @@ impl LayoutNodeHelpers for JS<Node> {
 
 pub trait RawLayoutNodeHelpers {
     unsafe fn get_hover_state_for_layout(&self) -> bool;
+    unsafe fn get_focus_state_for_layout(&self) -> bool;
+    unsafe fn get_valid_state_for_layout(&self) -> bool;
+    unsafe fn get_invalid_state_for_layout(&self) -> bool;
 }
 
 impl RawLayoutNodeHelpers for Node {
     unsafe fn get_hover_state_for_layout(&self) -> bool {
         self.flags.deref().borrow().contains(InHoverState)
     }
+
+    unsafe fn get_focus_state_for_layout(&self) -> bool {
+        self.flags.deref().borrow().contains(InFocusState)
+    }
+
+    unsafe fn get_valid_state_for_layout(&self) -> bool {
+        self.flags.deref().borrow().contains(InValidState)
+    }
+
+    unsafe fn get_invalid_state_for_layout(&self) -> bool {
+        self.flags.deref().borrow().contains(InInvalidState)
+    }
 }
 
 
@@ -945,6 +1014,8 @@ impl Node {
 
             flags: Traceable::new(RefCell::new(NodeFlags::new(type_id))),
 
+            registered_observers: Traceable::new(RefCell::new(vec!())),
+
             layout_data: LayoutDataRef::new(),
         }
     }
@@ -963,6 +1034,9 @@ impl Node {
         let node_doc = document_from_node(node).root();
         if &*node_doc != document {
             for descendant in node.traverse_preorder() {
+                let registered_observers = descendant.registered_observers.deref().borrow().len();
+                node_doc.deref().remove_registered_observers(registered_observers);
+                document.add_registered_observers(registered_observers);
                 descendant.set_owner_doc(document);
             }
         }
@@ -1121,10 +1195,10 @@ impl Node {
             _ => vec!(node.clone()),
         };
 
-        // Step 5: DocumentFragment, mutation records.
-        // Step 6: DocumentFragment.
+        // Step 5-6: DocumentFragment.
         match node.type_id() {
             DocumentFragmentNodeTypeId => {
+                queue_mutation_record(node, ChildListMutation(vec!(), nodes.clone(), None, None));
                 for c in node.children() {
                     Node::remove(&c, node, Suppressed);
                 }
@@ -1132,7 +1206,19 @@ impl Node {
             _ => (),
         }
 
-        // Step 7: mutation records.
+        // Step 7.
+        match suppress_observers {
+            Unsuppressed => {
+                let previous_sibling = match child {
+                    Some(ref child) => child.prev_sibling().map(|node| (*node.root()).clone()),
+                    None => parent.last_child().map(|node| (*node.root()).clone()),
+                };
+                queue_mutation_record(parent, ChildListMutation(nodes.clone(), vec!(),
+                                                                previous_sibling, child));
+            }
+            Suppressed => (),
+        }
+
         // Step 8.
         for node in nodes.mut_iter() {
             parent.add_child(node, child);
@@ -1192,11 +1278,15 @@ impl Node {
             None => (),
         }
 
-        // Step 6: mutation records.
+        // Step 6.
+        if !addedNodes.is_empty() || !removedNodes.is_empty() {
+            queue_mutation_record(parent, ChildListMutation(addedNodes.clone(),
+                                                            removedNodes.clone(), None, None));
+        }
 
         // Step 7.
         for removedNode in removedNodes.iter() {
-            removedNode.node_removed();
+            removedNode.node_removed(parent);
         }
         for addedNode in addedNodes.iter() {
             addedNode.node_inserted();
@@ -1223,7 +1313,20 @@ impl Node {
         assert!(node.parent_node().map_or(false, |node_parent| node_parent == Temporary::from_rooted(parent)));
 
         // Step 1-5: ranges.
-        // Step 6-7: mutation observers.
+        // Step 6.
+        add_transient_registered_observers(node, parent);
+
+        // Step 7.
+        match suppress_observers {
+            Unsuppressed => {
+                let previous_sibling = node.prev_sibling().map(|node| (*node.root()).clone());
+                let next_sibling = node.next_sibling().map(|node| (*node.root()).clone());
+                queue_mutation_record(parent, ChildListMutation(vec!(), vec!(node.clone()),
+                                                                previous_sibling, next_sibling));
+            }
+            Suppressed => (),
+        }
+
         // Step 8.
         parent.remove_child(node);
 
@@ -1232,7 +1335,7 @@ impl Node {
         // Step 9.
         match suppress_observers {
             Suppressed => (),
-            Unsuppressed => node.node_removed(),
+            Unsuppressed => node.node_removed(parent),
         }
     }
 
@@ -1274,7 +1377,7 @@ impl Node {
                     false => NonHTMLDocument
                 };
                 let window = document.window.root();
-                let document = Document::new(&*window, Some(document.url().clone()),
+                let document = Document::new(&*window, Some(document.url()),
                                              is_html_doc, None);
                 NodeCast::from_temporary(document)
             },
@@ -1583,6 +1686,7 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
                 self.wait_until_safe_to_modify_dom();
 
                 let characterdata: &JSRef<CharacterData> = CharacterDataCast::to_ref(self).unwrap();
+                queue_mutation_record(self, CharacterDataMutation(characterdata.Data()));
                 *characterdata.data.deref().borrow_mut() = value;
 
                 // Notify the document that the content of this node is different
@@ -1708,6 +1812,7 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
         };
 
         // Step 9.
+        let previous_sibling = child.prev_sibling().map(|node| (*node.root()).clone());
         let document = document_from_node(self).root();
         Node::adopt(node, &*document);
 
@@ -1716,12 +1821,21 @@ impl<'a> NodeMethods for JSRef<'a, Node> {
             Node::remove(child, self, Suppressed);
 
             // Step 11.
+            let nodes = match node.type_id() {
+                DocumentFragmentNodeTypeId => node.children().collect(),
+                _ => vec!(node.clone()),
+            };
+
+            // Step 12.
             Node::insert(node, self, reference_child, Suppressed);
+
+            // Step 13.
+            queue_mutation_record(self, ChildListMutation(nodes, vec!(child.clone()),
+                                                          previous_sibling, reference_child));
         }
 
-        // Step 12-14.
-        // Step 13: mutation records.
-        child.node_removed();
+        // Step 14.
+        child.node_removed(self);
         if node.type_id() == DocumentFragmentNodeTypeId {
             for child_node in node.children() {
                 child_node.node_inserted();
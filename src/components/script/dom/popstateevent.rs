@@ -0,0 +1,70 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::PopStateEventBinding;
+use dom::bindings::codegen::InheritTypes::{EventCast, PopStateEventDerived};
+use dom::bindings::error::Fallible;
+use dom::bindings::js::{JSRef, Temporary};
+use dom::bindings::trace::Traceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::event::{Event, EventMethods, PopStateEventTypeId};
+use dom::window::Window;
+use js::jsapi::JSContext;
+use js::jsval::JSVal;
+use servo_util::str::DOMString;
+
+#[deriving(Encodable)]
+pub struct PopStateEvent {
+    event: Event,
+    state: Traceable<JSVal>,
+}
+
+impl PopStateEventDerived for Event {
+    fn is_popstateevent(&self) -> bool {
+        self.type_id == PopStateEventTypeId
+    }
+}
+
+impl PopStateEvent {
+    pub fn new_inherited(state: JSVal) -> PopStateEvent {
+        PopStateEvent {
+            event: Event::new_inherited(PopStateEventTypeId),
+            state: Traceable::new(state),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, type_: DOMString,
+               can_bubble: bool, cancelable: bool, state: JSVal) -> Temporary<PopStateEvent> {
+        let ev = reflect_dom_object(box PopStateEvent::new_inherited(state),
+                                    window,
+                                    PopStateEventBinding::Wrap).root();
+        let event: &JSRef<Event> = EventCast::from_ref(&*ev);
+        event.InitEvent(type_, can_bubble, cancelable);
+        Temporary::from_rooted(&*ev)
+    }
+
+    pub fn Constructor(owner: &JSRef<Window>,
+                       type_: DOMString,
+                       init: &PopStateEventBinding::PopStateEventInit)
+                       -> Fallible<Temporary<PopStateEvent>> {
+        Ok(PopStateEvent::new(owner, type_, init.parent.bubbles, init.parent.cancelable,
+                              init.state))
+    }
+}
+
+pub trait PopStateEventMethods {
+    fn State(&self, _cx: *mut JSContext) -> JSVal;
+}
+
+impl<'a> PopStateEventMethods for JSRef<'a, PopStateEvent> {
+    fn State(&self, _cx: *mut JSContext) -> JSVal {
+        *self.state.deref()
+    }
+}
+
+impl Reflectable for PopStateEvent {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        self.event.reflector()
+    }
+}
@@ -0,0 +1,148 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use dom::bindings::codegen::Bindings::StorageBinding;
+use dom::bindings::error::{ErrorResult, QuotaExceeded};
+use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::trace::Untraceable;
+use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::window::Window;
+use servo_msg::constellation_msg::{ConstellationChan, StorageChangedMsg};
+use servo_net::storage_task::{Clear, GetItem, Key, Length, RemoveItem, SetItem};
+use servo_net::storage_task::{StorageTask, StorageType};
+use servo_util::str::DOMString;
+use servo_util::url::origin;
+
+use std::comm::channel;
+
+use serialize::{Encoder, Encodable};
+
+/// The `localStorage` or `sessionStorage` area of a window's origin. The items live in the
+/// storage task, so that every window of the origin sees the same ones.
+#[deriving(Encodable)]
+pub struct Storage {
+    pub reflector_: Reflector,
+    pub window: JS<Window>,
+    pub storage_type: Untraceable<StorageType>,
+}
+
+impl Storage {
+    pub fn new_inherited(window: &JSRef<Window>, storage_type: StorageType) -> Storage {
+        Storage {
+            reflector_: Reflector::new(),
+            window: JS::from_rooted(window),
+            storage_type: Untraceable::new(storage_type),
+        }
+    }
+
+    pub fn new(window: &JSRef<Window>, storage_type: StorageType) -> Temporary<Storage> {
+        reflect_dom_object(box Storage::new_inherited(window, storage_type),
+                           window,
+                           StorageBinding::Wrap)
+    }
+}
+
+pub trait StorageMethods {
+    fn Length(&self) -> u32;
+    fn Key(&self, index: u32) -> Option<DOMString>;
+    fn GetItem(&self, name: DOMString) -> Option<DOMString>;
+    fn SetItem(&self, name: DOMString, value: DOMString) -> ErrorResult;
+    fn RemoveItem(&self, name: DOMString);
+    fn Clear(&self);
+}
+
+impl<'a> StorageMethods for JSRef<'a, Storage> {
+    // http://www.whatwg.org/html/#dom-storage-length
+    fn Length(&self) -> u32 {
+        let (sender, receiver) = channel();
+        self.storage_task().send(Length(sender, self.origin(), *self.storage_type));
+        receiver.recv() as u32
+    }
+
+    // http://www.whatwg.org/html/#dom-storage-key
+    fn Key(&self, index: u32) -> Option<DOMString> {
+        let (sender, receiver) = channel();
+        self.storage_task().send(Key(sender, self.origin(), *self.storage_type, index as uint));
+        receiver.recv()
+    }
+
+    // http://www.whatwg.org/html/#dom-storage-getitem
+    fn GetItem(&self, name: DOMString) -> Option<DOMString> {
+        let (sender, receiver) = channel();
+        self.storage_task().send(GetItem(sender, self.origin(), *self.storage_type, name));
+        receiver.recv()
+    }
+
+    // http://www.whatwg.org/html/#dom-storage-setitem
+    fn SetItem(&self, name: DOMString, value: DOMString) -> ErrorResult {
+        let (sender, receiver) = channel();
+        self.storage_task().send(SetItem(sender, self.origin(), *self.storage_type,
+                                         name.clone(), value.clone()));
+        match receiver.recv() {
+            Ok((true, old_value)) => {
+                self.broadcast_change(Some(name), old_value, Some(value));
+                Ok(())
+            }
+            Ok((false, _)) => Ok(()),
+            Err(()) => Err(QuotaExceeded),
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-storage-removeitem
+    fn RemoveItem(&self, name: DOMString) {
+        let (sender, receiver) = channel();
+        self.storage_task().send(RemoveItem(sender, self.origin(), *self.storage_type,
+                                            name.clone()));
+        match receiver.recv() {
+            Some(old_value) => self.broadcast_change(Some(name), Some(old_value), None),
+            None => {}
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-storage-clear
+    fn Clear(&self) {
+        let (sender, receiver) = channel();
+        self.storage_task().send(Clear(sender, self.origin(), *self.storage_type));
+        if receiver.recv() {
+            self.broadcast_change(None, None, None);
+        }
+    }
+}
+
+trait PrivateStorageHelpers {
+    fn origin(&self) -> String;
+    fn storage_task(&self) -> StorageTask;
+    fn broadcast_change(&self, name: Option<DOMString>, old_value: Option<DOMString>,
+                        new_value: Option<DOMString>);
+}
+
+impl<'a> PrivateStorageHelpers for JSRef<'a, Storage> {
+    fn origin(&self) -> String {
+        let window = self.window.root();
+        origin(&window.deref().get_url())
+    }
+
+    fn storage_task(&self) -> StorageTask {
+        let window = self.window.root();
+        let page = window.deref().page();
+        page.storage_task.deref().clone()
+    }
+
+    /// Asks the constellation to fire `storage` events in the other documents of this origin.
+    // http://www.whatwg.org/html/#send-a-storage-notification
+    fn broadcast_change(&self, name: Option<DOMString>, old_value: Option<DOMString>,
+                        new_value: Option<DOMString>) {
+        let window = self.window.root();
+        let page = window.deref().page();
+        let ConstellationChan(ref chan) = *page.constellation_chan;
+        chan.send(StorageChangedMsg(page.id, *self.storage_type, window.deref().get_url(),
+                                    name, old_value, new_value));
+    }
+}
+
+impl Reflectable for Storage {
+    fn reflector<'a>(&'a self) -> &'a Reflector {
+        &self.reflector_
+    }
+}
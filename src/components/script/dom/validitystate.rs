@@ -2,36 +2,341 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use dom::attr::AttrMethods;
 use dom::bindings::codegen::Bindings::ValidityStateBinding;
-use dom::bindings::js::{JS, JSRef, Temporary};
+use dom::bindings::codegen::InheritTypes::{ElementCast, HTMLButtonElementCast, HTMLElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLInputElementCast, HTMLSelectElementCast};
+use dom::bindings::codegen::InheritTypes::{HTMLTextAreaElementCast, NodeCast};
+use dom::bindings::js::{JS, JSRef, Temporary, OptionalRootable};
 use dom::bindings::utils::{Reflectable, Reflector, reflect_dom_object};
+use dom::element::{Element, AttributeHandlers, HTMLButtonElementTypeId, HTMLInputElementTypeId};
+use dom::element::{HTMLSelectElementTypeId, HTMLTextAreaElementTypeId};
+use dom::htmlbuttonelement::HTMLButtonElement;
+use dom::htmlelement::HTMLElementMethods;
+use dom::htmlinputelement::HTMLInputElement;
+use dom::htmlselectelement::HTMLSelectElement;
+use dom::htmltextareaelement::HTMLTextAreaElement;
+use dom::node::{Node, NodeHelpers, ElementNodeTypeId};
 use dom::window::Window;
+use servo_util::namespace::Null;
+use servo_util::str::{DOMString, parse_integer};
 use std::cell::Cell;
 
+/// The constraints that the value of a form control can fail.
+// http://www.whatwg.org/html/#suffering-from-being-missing
+#[deriving(Clone, Eq)]
+pub struct ValidityFlags {
+    pub value_missing: bool,
+    pub type_mismatch: bool,
+    pub pattern_mismatch: bool,
+    pub too_long: bool,
+    pub too_short: bool,
+    pub range_underflow: bool,
+    pub range_overflow: bool,
+    pub step_mismatch: bool,
+    pub bad_input: bool,
+    pub custom_error: bool,
+}
+
+impl ValidityFlags {
+    pub fn new() -> ValidityFlags {
+        ValidityFlags {
+            value_missing: false,
+            type_mismatch: false,
+            pattern_mismatch: false,
+            too_long: false,
+            too_short: false,
+            range_underflow: false,
+            range_overflow: false,
+            step_mismatch: false,
+            bad_input: false,
+            custom_error: false,
+        }
+    }
+
+    /// Returns true if the value fails none of the constraints.
+    pub fn is_valid(&self) -> bool {
+        !(self.value_missing || self.type_mismatch || self.pattern_mismatch || self.too_long ||
+          self.too_short || self.range_underflow || self.range_overflow || self.step_mismatch ||
+          self.bad_input || self.custom_error)
+    }
+}
+
+/// The form controls that take part in constraint validation: `<button>`, `<input>`, `<select>`
+/// and `<textarea>`.
+pub trait Validatable {
+    /// Returns true unless the element is barred from constraint validation, for instance by
+    /// being disabled.
+    // http://www.whatwg.org/html/#candidate-for-constraint-validation
+    fn is_candidate_for_validation(&self) -> bool;
+
+    /// Returns the constraints that the value of the element fails, including the custom error.
+    fn validity_flags(&self) -> ValidityFlags;
+
+    /// Returns the message that `setCustomValidity()` set, which is empty if there is no custom
+    /// error.
+    fn custom_validity(&self) -> DOMString;
+
+    fn set_custom_validity(&self, error: DOMString);
+}
+
+/// Returns the `Validatable` implementation of a form control, or `None` if the element doesn't
+/// take part in constraint validation.
+pub fn validatable_for<'a>(element: &'a JSRef<Element>) -> Option<&'a Validatable:> {
+    let node: &JSRef<Node> = NodeCast::from_ref(element);
+    match node.type_id() {
+        ElementNodeTypeId(HTMLButtonElementTypeId) => {
+            let element: &JSRef<HTMLButtonElement> = HTMLButtonElementCast::to_ref(node).unwrap();
+            Some(element as &Validatable:)
+        }
+        ElementNodeTypeId(HTMLInputElementTypeId) => {
+            let element: &JSRef<HTMLInputElement> = HTMLInputElementCast::to_ref(node).unwrap();
+            Some(element as &Validatable:)
+        }
+        ElementNodeTypeId(HTMLSelectElementTypeId) => {
+            let element: &JSRef<HTMLSelectElement> = HTMLSelectElementCast::to_ref(node).unwrap();
+            Some(element as &Validatable:)
+        }
+        ElementNodeTypeId(HTMLTextAreaElementTypeId) => {
+            let element: &JSRef<HTMLTextAreaElement> =
+                HTMLTextAreaElementCast::to_ref(node).unwrap();
+            Some(element as &Validatable:)
+        }
+        _ => None,
+    }
+}
+
+/// Returns true if the element is a candidate for constraint validation.
+// http://www.whatwg.org/html/#dom-cva-willvalidate
+pub fn will_validate(element: &JSRef<Element>) -> bool {
+    validatable_for(element).map_or(false, |validatable| validatable.is_candidate_for_validation())
+}
+
+/// Returns `Some(true)` if the element is a candidate for constraint validation that satisfies
+/// its constraints, `Some(false)` if it is one that doesn't, and `None` if it isn't one. This is
+/// what the `:valid` and `:invalid` pseudo-classes match.
+pub fn validity_state(element: &JSRef<Element>) -> Option<bool> {
+    match validatable_for(element) {
+        Some(validatable) if validatable.is_candidate_for_validation() => {
+            Some(validatable.validity_flags().is_valid())
+        }
+        Some(_) | None => None,
+    }
+}
+
+/// Returns `Ok` unless the element is a candidate for constraint validation that fails its
+/// constraints, in which case it fires `invalid` at the element and returns whether the event
+/// was not canceled, which tells the caller whether to report the problem.
+pub fn check_constraints(element: &JSRef<Element>) -> Result<(), bool> {
+    match validity_state(element) {
+        Some(false) => {
+            let node: &JSRef<Node> = NodeCast::from_ref(element);
+            Err(node.fire_simple_event("invalid", false, true))
+        }
+        Some(true) | None => Ok(()),
+    }
+}
+
+/// Checks the constraints of the element, firing `invalid` at it if it fails them.
+// http://www.whatwg.org/html/#dom-cva-checkvalidity
+pub fn check_validity(element: &JSRef<Element>) -> bool {
+    check_constraints(element).is_ok()
+}
+
+/// Checks the constraints of the element like `check_validity()`, and reports the problem to
+/// the user unless the `invalid` event was canceled.
+// http://www.whatwg.org/html/#dom-cva-reportvalidity
+pub fn report_validity(element: &JSRef<Element>) -> bool {
+    match check_constraints(element) {
+        Ok(()) => true,
+        Err(not_canceled) => {
+            if not_canceled {
+                report_problem(element);
+            }
+            false
+        }
+    }
+}
+
+/// Reports to the user that the value of the element fails its constraints. With no native
+/// validation bubble to show, this focuses the element so that the user can fix its value.
+pub fn report_problem(element: &JSRef<Element>) {
+    debug!("constraint validation failed: {:s}", validation_message(element));
+    match HTMLElementCast::to_ref(element) {
+        Some(htmlelement) => htmlelement.Focus(),
+        None => {}
+    }
+}
+
+/// Returns the message that describes how the element fails its constraints, or the empty
+/// string if it doesn't fail them or isn't a candidate for constraint validation.
+// http://www.whatwg.org/html/#dom-cva-validationmessage
+pub fn validation_message(element: &JSRef<Element>) -> DOMString {
+    let validatable = match validatable_for(element) {
+        Some(validatable) if validatable.is_candidate_for_validation() => validatable,
+        Some(_) | None => return String::new(),
+    };
+    let flags = validatable.validity_flags();
+    if flags.custom_error {
+        return validatable.custom_validity();
+    }
+    let message = if flags.value_missing {
+        "Please fill out this field."
+    } else if flags.type_mismatch {
+        match element.get_string_attribute("type").as_slice() {
+            "email" => "Please enter an email address.",
+            _ => "Please enter a URL.",
+        }
+    } else if flags.bad_input {
+        "Please enter a number."
+    } else if flags.pattern_mismatch {
+        "Please match the requested format."
+    } else if flags.too_long {
+        "Please shorten this text."
+    } else if flags.too_short {
+        "Please lengthen this text."
+    } else if flags.range_underflow || flags.range_overflow {
+        "Please enter a value within the allowed range."
+    } else if flags.step_mismatch {
+        "Please enter a valid value."
+    } else {
+        ""
+    };
+    message.to_string()
+}
+
+/// Returns the value of a `maxlength` or a `minlength` attribute, which must be a non-negative
+/// integer.
+// http://www.whatwg.org/html/#attr-fe-maxlength
+pub fn length_limit(element: &JSRef<Element>, name: &str) -> Option<uint> {
+    element.get_attribute(Null, name).root().and_then(|attr| {
+        parse_integer(attr.deref().Value().as_slice())
+    }).and_then(|limit| {
+        if limit >= 0 { Some(limit as uint) } else { None }
+    })
+}
+
+/// Returns the constraints on the length of a value that the user edited, given as the number
+/// of UTF-16 code units in the value.
+// http://www.whatwg.org/html/#limiting-user-input-length:-the-maxlength-attribute
+pub fn length_flags(element: &JSRef<Element>, value: &str, flags: &mut ValidityFlags) {
+    if value.is_empty() {
+        return;
+    }
+    let length = value.to_utf16().len();
+    flags.too_long = length_limit(element, "maxlength").map_or(false, |limit| length > limit);
+    flags.too_short = length_limit(element, "minlength").map_or(false, |limit| length < limit);
+}
+
+/// Updates the node flags that layout reads to match `:valid` and `:invalid` for every form
+/// control in the tree. The validity of a control depends on its attributes and its value and
+/// sometimes on those of other controls, so this runs before a reflow that follows changes to the
+/// content rather than after each change.
+pub fn update_validity_states(root: &JSRef<Node>) {
+    for node in root.traverse_preorder() {
+        let state = match ElementCast::to_ref(&node) {
+            Some(element) => validity_state(element),
+            None => continue,
+        };
+        node.set_validity_state(state);
+    }
+}
+
 #[deriving(Encodable)]
 pub struct ValidityState {
     pub reflector_: Reflector,
     pub window: Cell<JS<Window>>,
-    pub state: u8,
+    element: JS<Element>,
 }
 
 impl ValidityState {
-    pub fn new_inherited(window: &JSRef<Window>) -> ValidityState {
+    pub fn new_inherited(window: &JSRef<Window>, element: &JSRef<Element>) -> ValidityState {
         ValidityState {
             reflector_: Reflector::new(),
             window: Cell::new(JS::from_rooted(window)),
-            state: 0,
+            element: JS::from_rooted(element),
         }
     }
 
-    pub fn new(window: &JSRef<Window>) -> Temporary<ValidityState> {
-        reflect_dom_object(box ValidityState::new_inherited(window),
+    pub fn new(window: &JSRef<Window>, element: &JSRef<Element>) -> Temporary<ValidityState> {
+        reflect_dom_object(box ValidityState::new_inherited(window, element),
                            window,
                            ValidityStateBinding::Wrap)
     }
 }
 
+trait PrivateValidityStateHelpers {
+    fn flags(&self) -> ValidityFlags;
+}
+
+impl<'a> PrivateValidityStateHelpers for JSRef<'a, ValidityState> {
+    /// Returns the constraints that the element fails now: a `ValidityState` is live.
+    fn flags(&self) -> ValidityFlags {
+        let element = self.element.root();
+        validatable_for(&*element).map_or(ValidityFlags::new(), |validatable| {
+            validatable.validity_flags()
+        })
+    }
+}
+
 pub trait ValidityStateMethods {
+    fn ValueMissing(&self) -> bool;
+    fn TypeMismatch(&self) -> bool;
+    fn PatternMismatch(&self) -> bool;
+    fn TooLong(&self) -> bool;
+    fn TooShort(&self) -> bool;
+    fn RangeUnderflow(&self) -> bool;
+    fn RangeOverflow(&self) -> bool;
+    fn StepMismatch(&self) -> bool;
+    fn BadInput(&self) -> bool;
+    fn CustomError(&self) -> bool;
+    fn Valid(&self) -> bool;
+}
+
+impl<'a> ValidityStateMethods for JSRef<'a, ValidityState> {
+    fn ValueMissing(&self) -> bool {
+        self.flags().value_missing
+    }
+
+    fn TypeMismatch(&self) -> bool {
+        self.flags().type_mismatch
+    }
+
+    fn PatternMismatch(&self) -> bool {
+        self.flags().pattern_mismatch
+    }
+
+    fn TooLong(&self) -> bool {
+        self.flags().too_long
+    }
+
+    fn TooShort(&self) -> bool {
+        self.flags().too_short
+    }
+
+    fn RangeUnderflow(&self) -> bool {
+        self.flags().range_underflow
+    }
+
+    fn RangeOverflow(&self) -> bool {
+        self.flags().range_overflow
+    }
+
+    fn StepMismatch(&self) -> bool {
+        self.flags().step_mismatch
+    }
+
+    fn BadInput(&self) -> bool {
+        self.flags().bad_input
+    }
+
+    fn CustomError(&self) -> bool {
+        self.flags().custom_error
+    }
+
+    fn Valid(&self) -> bool {
+        self.flags().is_valid()
+    }
 }
 
 impl Reflectable for ValidityState {
@@ -6,23 +6,38 @@ use dom::attr::{AttrValue, StringAttrValue};
 use dom::bindings::codegen::InheritTypes::ElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLAnchorElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLBodyElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLButtonElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLIFrameElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLImageElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLInputElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLObjectElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLOptionElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLSelectElementCast;
 use dom::bindings::codegen::InheritTypes::HTMLStyleElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLTableCellElementCast;
+use dom::bindings::codegen::InheritTypes::HTMLTextAreaElementCast;
 use dom::bindings::js::JSRef;
 use dom::element::Element;
 use dom::element::{ElementTypeId, HTMLAnchorElementTypeId, HTMLBodyElementTypeId, HTMLImageElementTypeId};
 use dom::element::{HTMLIFrameElementTypeId, HTMLObjectElementTypeId, HTMLStyleElementTypeId};
+use dom::element::{HTMLInputElementTypeId, HTMLTextAreaElementTypeId};
+use dom::element::{HTMLButtonElementTypeId, HTMLOptionElementTypeId, HTMLSelectElementTypeId};
+use dom::element::{HTMLTableDataCellElementTypeId, HTMLTableHeaderCellElementTypeId};
 use dom::event::Event;
 use dom::htmlanchorelement::HTMLAnchorElement;
 use dom::htmlbodyelement::HTMLBodyElement;
+use dom::htmlbuttonelement::HTMLButtonElement;
 use dom::htmlelement::HTMLElement;
 use dom::htmliframeelement::HTMLIFrameElement;
 use dom::htmlimageelement::HTMLImageElement;
+use dom::htmlinputelement::HTMLInputElement;
 use dom::htmlobjectelement::HTMLObjectElement;
+use dom::htmloptionelement::HTMLOptionElement;
+use dom::htmlselectelement::HTMLSelectElement;
 use dom::htmlstyleelement::HTMLStyleElement;
+use dom::htmltablecellelement::HTMLTableCellElement;
+use dom::htmltextareaelement::HTMLTextAreaElement;
 use dom::node::{Node, NodeHelpers, ElementNodeTypeId};
 use servo_util::str::DOMString;
 
@@ -84,6 +99,14 @@ pub trait VirtualMethods {
         }
     }
 
+    /// Called on the parent when a node is removed from its child list.
+    fn child_removed(&self, child: &JSRef<Node>) {
+        match self.super_type() {
+            Some(ref s) => s.child_removed(child),
+            _ => (),
+        }
+    }
+
     /// Called during event dispatch after the bubbling phase completes.
     fn handle_event(&self, event: &JSRef<Event>) {
         match self.super_type() {
@@ -109,10 +132,18 @@ pub fn vtable_for<'a>(node: &'a JSRef<Node>) -> &'a VirtualMethods: {
             let element: &JSRef<HTMLBodyElement> = HTMLBodyElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
         }
+        ElementNodeTypeId(HTMLButtonElementTypeId) => {
+            let element: &JSRef<HTMLButtonElement> = HTMLButtonElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
         ElementNodeTypeId(HTMLImageElementTypeId) => {
             let element: &JSRef<HTMLImageElement> = HTMLImageElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
         }
+        ElementNodeTypeId(HTMLInputElementTypeId) => {
+            let element: &JSRef<HTMLInputElement> = HTMLInputElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
         ElementNodeTypeId(HTMLIFrameElementTypeId) => {
             let element: &JSRef<HTMLIFrameElement> = HTMLIFrameElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
@@ -121,10 +152,27 @@ pub fn vtable_for<'a>(node: &'a JSRef<Node>) -> &'a VirtualMethods: {
             let element: &JSRef<HTMLObjectElement> = HTMLObjectElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
         }
+        ElementNodeTypeId(HTMLOptionElementTypeId) => {
+            let element: &JSRef<HTMLOptionElement> = HTMLOptionElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
+        ElementNodeTypeId(HTMLSelectElementTypeId) => {
+            let element: &JSRef<HTMLSelectElement> = HTMLSelectElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
         ElementNodeTypeId(HTMLStyleElementTypeId) => {
             let element: &JSRef<HTMLStyleElement> = HTMLStyleElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
         }
+        ElementNodeTypeId(HTMLTableDataCellElementTypeId) |
+        ElementNodeTypeId(HTMLTableHeaderCellElementTypeId) => {
+            let element: &JSRef<HTMLTableCellElement> = HTMLTableCellElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
+        ElementNodeTypeId(HTMLTextAreaElementTypeId) => {
+            let element: &JSRef<HTMLTextAreaElement> = HTMLTextAreaElementCast::to_ref(node).unwrap();
+            element as &VirtualMethods:
+        }
         ElementNodeTypeId(ElementTypeId) => {
             let element: &JSRef<Element> = ElementCast::to_ref(node).unwrap();
             element as &VirtualMethods:
@@ -10,17 +10,24 @@ use dom::bindings::trace::{Traceable, Untraceable};
 use dom::bindings::utils::{Reflectable, Reflector};
 use dom::browsercontext::BrowserContext;
 use dom::console::Console;
-use dom::document::Document;
+use dom::document::{Document, DocumentHelpers};
 use dom::eventtarget::{EventTarget, WindowTypeId, EventTargetHelpers};
+use dom::history::{History, HistoryHelpers};
 use dom::location::Location;
+use dom::mutationobserver::MutationObserver;
 use dom::navigator::Navigator;
 use dom::performance::Performance;
+use dom::storage::Storage;
 
 use layout_interface::{ReflowForDisplay, DocumentDamageLevel};
 use page::Page;
 use script_task::{ExitWindowMsg, FireTimerMsg, ScriptChan, TriggerLoadMsg, TriggerFragmentMsg};
 use servo_msg::compositor_msg::ScriptListener;
+use servo_msg::constellation_msg::{ConstellationChan, Load, NavigationType, Replace};
+use servo_msg::constellation_msg::{RequestAnimationFrameMsg, SameDocumentNavigationMsg};
 use servo_net::image_cache_task::ImageCacheTask;
+use servo_net::resource_task::LoadData;
+use servo_net::storage_task::{Local, Session};
 use servo_util::str::DOMString;
 use servo_util::task::{spawn_named};
 use servo_util::url::parse_url;
@@ -66,16 +73,31 @@ impl TimerHandle {
     }
 }
 
+/// A callback passed to `requestAnimationFrame()`, which runs at the next animation frame unless
+/// it is cancelled.
+#[deriving(Encodable)]
+pub struct AnimationFrameCallback {
+    pub handle: i32,
+    pub callback: Traceable<JSVal>,
+}
+
 #[deriving(Encodable)]
 pub struct Window {
     pub eventtarget: EventTarget,
     pub script_chan: ScriptChan,
     pub console: Cell<Option<JS<Console>>>,
     pub location: Cell<Option<JS<Location>>>,
+    pub history: Cell<Option<JS<History>>>,
     pub navigator: Cell<Option<JS<Navigator>>>,
+    pub local_storage: Cell<Option<JS<Storage>>>,
+    pub session_storage: Cell<Option<JS<Storage>>>,
     pub image_cache_task: ImageCacheTask,
     pub active_timers: Traceable<RefCell<HashMap<TimerId, TimerHandle>>>,
     pub next_timer_handle: Traceable<Cell<i32>>,
+    pub animation_frame_callbacks: Traceable<RefCell<Vec<AnimationFrameCallback>>>,
+    pub next_animation_frame_handle: Traceable<Cell<i32>>,
+    /// The mutation observers that have records waiting for the next microtask checkpoint.
+    pub pending_mutation_observers: Traceable<RefCell<Vec<JS<MutationObserver>>>>,
     pub compositor: Untraceable<Box<ScriptListener>>,
     pub browser_context: Traceable<RefCell<Option<BrowserContext>>>,
     pub page: Rc<Page>,
@@ -121,12 +143,17 @@ pub trait WindowMethods {
     fn Close(&self);
     fn Document(&self) -> Temporary<Document>;
     fn Location(&self) -> Temporary<Location>;
+    fn History(&self) -> Temporary<History>;
     fn Console(&self) -> Temporary<Console>;
     fn Navigator(&self) -> Temporary<Navigator>;
+    fn LocalStorage(&self) -> Temporary<Storage>;
+    fn SessionStorage(&self) -> Temporary<Storage>;
     fn SetTimeout(&self, _cx: *mut JSContext, callback: JSVal, timeout: i32) -> i32;
     fn ClearTimeout(&self, handle: i32);
     fn SetInterval(&self, _cx: *mut JSContext, callback: JSVal, timeout: i32) -> i32;
     fn ClearInterval(&self, handle: i32);
+    fn RequestAnimationFrame(&self, _cx: *mut JSContext, callback: JSVal) -> i32;
+    fn CancelAnimationFrame(&self, handle: i32);
     fn Window(&self) -> Temporary<Window>;
     fn Self(&self) -> Temporary<Window>;
     fn Performance(&self) -> Temporary<Performance>;
@@ -134,6 +161,12 @@ pub trait WindowMethods {
     fn SetOnclick(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnload(&self) -> Option<EventHandlerNonNull>;
     fn SetOnload(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOnhashchange(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnhashchange(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOnpopstate(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnpopstate(&self, listener: Option<EventHandlerNonNull>);
+    fn GetOnstorage(&self) -> Option<EventHandlerNonNull>;
+    fn SetOnstorage(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnunload(&self) -> Option<EventHandlerNonNull>;
     fn SetOnunload(&self, listener: Option<EventHandlerNonNull>);
     fn GetOnerror(&self) -> Option<OnErrorEventHandlerNonNull>;
@@ -167,6 +200,14 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         Temporary::new(self.location.get().get_ref().clone())
     }
 
+    fn History(&self) -> Temporary<History> {
+        if self.history.get().is_none() {
+            let history = History::new(self);
+            self.history.assign(Some(history));
+        }
+        Temporary::new(self.history.get().get_ref().clone())
+    }
+
     fn Console(&self) -> Temporary<Console> {
         if self.console.get().is_none() {
             let console = Console::new(self);
@@ -183,6 +224,22 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         Temporary::new(self.navigator.get().get_ref().clone())
     }
 
+    fn LocalStorage(&self) -> Temporary<Storage> {
+        if self.local_storage.get().is_none() {
+            let local_storage = Storage::new(self, Local);
+            self.local_storage.assign(Some(local_storage));
+        }
+        Temporary::new(self.local_storage.get().get_ref().clone())
+    }
+
+    fn SessionStorage(&self) -> Temporary<Storage> {
+        if self.session_storage.get().is_none() {
+            let session_storage = Storage::new(self, Session);
+            self.session_storage.assign(Some(session_storage));
+        }
+        Temporary::new(self.session_storage.get().get_ref().clone())
+    }
+
     fn SetTimeout(&self, _cx: *mut JSContext, callback: JSVal, timeout: i32) -> i32 {
         self.set_timeout_or_interval(callback, timeout, false)
     }
@@ -205,6 +262,32 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         self.ClearTimeout(handle);
     }
 
+    // http://www.w3.org/TR/animation-timing/#dom-windowanimationtiming-requestanimationframe
+    fn RequestAnimationFrame(&self, _cx: *mut JSContext, callback: JSVal) -> i32 {
+        let handle = self.next_animation_frame_handle.deref().get() + 1;
+        self.next_animation_frame_handle.deref().set(handle);
+
+        let mut callbacks = self.animation_frame_callbacks.deref().borrow_mut();
+        if callbacks.is_empty() {
+            self.request_animation_frame();
+        }
+        callbacks.push(AnimationFrameCallback {
+            handle: handle,
+            callback: Traceable::new(callback),
+        });
+        handle
+    }
+
+    fn CancelAnimationFrame(&self, handle: i32) {
+        let mut callbacks = self.animation_frame_callbacks.deref().borrow_mut();
+        match callbacks.iter().position(|callback| callback.handle == handle) {
+            Some(index) => {
+                callbacks.remove(index);
+            }
+            None => {}
+        }
+    }
+
     fn Window(&self) -> Temporary<Window> {
         Temporary::from_rooted(self)
     }
@@ -241,6 +324,36 @@ impl<'a> WindowMethods for JSRef<'a, Window> {
         eventtarget.set_event_handler_common("load", listener)
     }
 
+    fn GetOnhashchange(&self) -> Option<EventHandlerNonNull> {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("hashchange")
+    }
+
+    fn SetOnhashchange(&self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("hashchange", listener)
+    }
+
+    fn GetOnpopstate(&self) -> Option<EventHandlerNonNull> {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("popstate")
+    }
+
+    fn SetOnpopstate(&self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("popstate", listener)
+    }
+
+    fn GetOnstorage(&self) -> Option<EventHandlerNonNull> {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.get_event_handler_common("storage")
+    }
+
+    fn SetOnstorage(&self, listener: Option<EventHandlerNonNull>) {
+        let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
+        eventtarget.set_event_handler_common("storage", listener)
+    }
+
     fn GetOnunload(&self) -> Option<EventHandlerNonNull> {
         let eventtarget: &JSRef<EventTarget> = EventTargetCast::from_ref(self);
         eventtarget.get_event_handler_common("unload")
@@ -283,6 +396,10 @@ pub trait WindowHelpers {
     fn wait_until_safe_to_modify_dom(&self);
     fn init_browser_context(&self, doc: &JSRef<Document>);
     fn load_url(&self, href: DOMString);
+    fn navigate(&self, url: Url, navigation_type: NavigationType);
+    fn load_data(&self, load_data: LoadData, navigation_type: NavigationType);
+    fn set_url(&self, url: Url);
+    fn request_animation_frame(&self);
 }
 
 trait PrivateWindowHelpers {
@@ -313,12 +430,62 @@ impl<'a> WindowHelpers for JSRef<'a, Window> {
         let base_url = Some(self.page().get_url());
         debug!("current page url is {:?}", base_url);
         let url = parse_url(href.as_slice(), base_url);
+        self.navigate(url, Load);
+    }
+
+    /// Navigates to the given URL. A URL that only differs from the current one in its fragment
+    /// adds a session history entry for the same document and scrolls to the fragment; any
+    /// other URL is loaded.
+    // http://www.whatwg.org/html/#navigate
+    fn navigate(&self, url: Url, navigation_type: NavigationType) {
+        let old_url = self.page().get_url();
+        let mut old_without_fragment = old_url.clone();
+        old_without_fragment.fragment = None;
+        let mut new_without_fragment = url.clone();
+        new_without_fragment.fragment = None;
+        if url.fragment.is_none() || old_without_fragment != new_without_fragment {
+            self.load_data(LoadData::new(url), navigation_type);
+            return;
+        }
+
+        // Navigating to the current URL again only scrolls.
+        let navigation_type = if url == old_url { Replace } else { navigation_type };
+        self.set_url(url.clone());
+        let history = self.History().root();
+        history.deref().set_current_state(None);
+        let ConstellationChan(ref constellation_chan) = *self.page().constellation_chan;
+        constellation_chan.send(SameDocumentNavigationMsg(self.page.id, url.clone(), None,
+                                                          navigation_type));
         let ScriptChan(ref script_chan) = self.script_chan;
-        if href.as_slice().starts_with("#") {
-            script_chan.send(TriggerFragmentMsg(self.page.id, url));
-        } else {
-            script_chan.send(TriggerLoadMsg(self.page.id, url));
+        script_chan.send(TriggerFragmentMsg(self.page.id, old_url, url));
+    }
+
+    /// Commence a new load of the given request, which will replace this window.
+    fn load_data(&self, load_data: LoadData, navigation_type: NavigationType) {
+        let ScriptChan(ref script_chan) = self.script_chan;
+        script_chan.send(TriggerLoadMsg(self.page.id, load_data, navigation_type));
+    }
+
+    /// Changes the address of this window's document without loading anything.
+    fn set_url(&self, url: Url) {
+        let frame = self.page().frame();
+        match *frame {
+            Some(ref frame) => frame.document.root().deref().set_url(url.clone()),
+            None => (),
         }
+        let mut page_url = self.page().mut_url();
+        let needs_reflow = match *page_url {
+            Some((_, needs_reflow)) => needs_reflow,
+            None => false,
+        };
+        *page_url = Some((url, needs_reflow));
+    }
+
+    /// Asks the constellation to run the animation frame callbacks of this window at the next
+    /// animation frame.
+    fn request_animation_frame(&self) {
+        let ConstellationChan(ref chan) = *self.page().constellation_chan;
+        chan.send(RequestAnimationFrameMsg(self.page.id));
     }
 }
 
@@ -396,10 +563,16 @@ impl Window {
             compositor: Untraceable::new(compositor),
             page: page,
             location: Cell::new(None),
+            history: Cell::new(None),
             navigator: Cell::new(None),
+            local_storage: Cell::new(None),
+            session_storage: Cell::new(None),
             image_cache_task: image_cache_task,
             active_timers: Traceable::new(RefCell::new(HashMap::new())),
             next_timer_handle: Traceable::new(Cell::new(0)),
+            animation_frame_callbacks: Traceable::new(RefCell::new(vec!())),
+            next_animation_frame_handle: Traceable::new(Cell::new(0)),
+            pending_mutation_observers: Traceable::new(RefCell::new(vec!())),
             browser_context: Traceable::new(RefCell::new(None)),
             performance: Cell::new(None),
             navigationStart: time::get_time().sec as u64,
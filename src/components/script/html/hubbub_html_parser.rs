@@ -292,9 +292,10 @@ pub fn build_element_from_tag(tag: DOMString, ns: Namespace, document: &JSRef<Do
 
 pub fn parse_html(page: &Page,
                   document: &JSRef<Document>,
-                  url: Url,
+                  load_data: LoadData,
                   resource_task: ResourceTask)
                   -> HtmlParserResult {
+    let url = load_data.url.clone();
     debug!("Hubbub: parsing {:?}", url);
     // Spawn a CSS parser to receive links to CSS style sheets.
 
@@ -315,7 +316,7 @@ pub fn parse_html(page: &Page,
 
     // Wait for the LoadResponse so that the parser knows the final URL.
     let (input_chan, input_port) = channel();
-    resource_task.send(Load(LoadData::new(url.clone()), input_chan));
+    resource_task.send(Load(load_data, input_chan));
     let load_response = input_port.recv();
 
     debug!("Fetched page; metadata is {:?}", load_response.metadata);
@@ -42,6 +42,10 @@ pub enum Msg {
     /// TODO(pcwalton): Maybe think about batching to avoid message traffic.
     ReapLayoutDataMsg(LayoutDataRef),
 
+    /// Delivers the data downloaded from the URL of an `@font-face` source, or `None` if it could
+    /// not be loaded.
+    WebFontLoadedMsg(String, Option<Vec<u8>>),
+
     /// Requests that the layout task enter a quiescent state in which no more messages are
     /// accepted except `ExitMsg`. A response message will be sent on the supplied channel when
     /// this happens.
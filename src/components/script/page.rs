@@ -10,8 +10,9 @@ use dom::bindings::utils::GlobalStaticData;
 use dom::document::{Document, DocumentMethods, DocumentHelpers};
 use dom::element::{Element, AttributeHandlers};
 use dom::node::{Node, NodeHelpers};
+use dom::validitystate::update_validity_states;
 use dom::window::Window;
-use layout_interface::{DocumentDamage};
+use layout_interface::{ContentChangedDocumentDamage, DocumentDamage};
 use layout_interface::{DocumentDamageLevel, HitTestQuery, HitTestResponse, LayoutQuery, MouseOverQuery, MouseOverResponse};
 use layout_interface::{LayoutChan, QueryMsg};
 use layout_interface::{Reflow, ReflowGoal, ReflowMsg};
@@ -26,6 +27,7 @@ use servo_msg::compositor_msg::ScriptListener;
 use servo_msg::constellation_msg::ConstellationChan;
 use servo_msg::constellation_msg::{PipelineId, SubpageId};
 use servo_net::resource_task::ResourceTask;
+use servo_net::storage_task::StorageTask;
 use servo_util::geometry::PagePx;
 use servo_util::namespace::Null;
 use servo_util::str::DOMString;
@@ -61,6 +63,10 @@ pub struct Page {
     /// What parts of the document are dirty, if any.
     damage: Traceable<RefCell<Option<DocumentDamage>>>,
 
+    /// Whether the content of the document changed since the validity states of its form
+    /// controls were last updated.
+    validity_states_dirty: Untraceable<Cell<bool>>,
+
     /// The current size of the window, in pixels.
     pub window_size: Untraceable<Cell<TypedSize2D<PagePx, f32>>>,
 
@@ -83,6 +89,9 @@ pub struct Page {
     /// Associated resource task for use by DOM objects like XMLHttpRequest
     pub resource_task: Untraceable<ResourceTask>,
 
+    /// Associated storage task for use by `localStorage` and `sessionStorage`
+    pub storage_task: Untraceable<StorageTask>,
+
     /// A handle for communicating messages to the constellation task.
     pub constellation_chan: Untraceable<ConstellationChan>,
 
@@ -120,6 +129,7 @@ impl Page {
     pub fn new(id: PipelineId, subpage_id: Option<SubpageId>,
            layout_chan: LayoutChan,
            window_size: TypedSize2D<PagePx, f32>, resource_task: ResourceTask,
+           storage_task: StorageTask,
            constellation_chan: ConstellationChan,
            js_context: Rc<Cx>) -> Page {
         let js_info = JSPageInfo {
@@ -133,6 +143,7 @@ impl Page {
             layout_chan: Untraceable::new(layout_chan),
             layout_join_port: Untraceable::new(RefCell::new(None)),
             damage: Traceable::new(RefCell::new(None)),
+            validity_states_dirty: Untraceable::new(Cell::new(true)),
             window_size: Untraceable::new(Cell::new(window_size)),
             js_info: Traceable::new(RefCell::new(Some(js_info))),
             url: Untraceable::new(RefCell::new(None)),
@@ -141,6 +152,7 @@ impl Page {
             fragment_node: Cell::new(None),
             last_reflow_id: Traceable::new(Cell::new(0)),
             resource_task: Untraceable::new(resource_task),
+            storage_task: Untraceable::new(storage_task),
             constellation_chan: Untraceable::new(constellation_chan),
             children: Traceable::new(RefCell::new(vec!())),
         }
@@ -224,6 +236,12 @@ impl Page {
 
     /// Adds the given damage.
     pub fn damage(&self, level: DocumentDamageLevel) {
+        // The validity of a form control depends only on values, attributes, and the tree, all of
+        // whose changes are content changes.
+        if level == ContentChangedDocumentDamage {
+            self.validity_states_dirty.deref().set(true);
+        }
+
         let root = match *self.frame() {
             None => return,
             Some(ref frame) => frame.document.root().GetDocumentElement()
@@ -331,6 +349,10 @@ impl Page {
                 last_reflow_id.set(last_reflow_id.get() + 1);
 
                 let root: &JSRef<Node> = NodeCast::from_ref(&*root);
+                if self.validity_states_dirty.deref().get() {
+                    update_validity_states(root);
+                    self.validity_states_dirty.deref().set(false);
+                }
                 let mut damage = self.damage.deref().borrow_mut();
                 let window_size = self.window_size.deref().get();
 
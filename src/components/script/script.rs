@@ -83,7 +83,10 @@ pub mod dom {
     pub mod event;
     pub mod eventdispatcher;
     pub mod eventtarget;
+    pub mod focusevent;
     pub mod formdata;
+    pub mod hashchangeevent;
+    pub mod history;
     pub mod htmlanchorelement;
     pub mod htmlappletelement;
     pub mod htmlareaelement;
@@ -154,15 +157,21 @@ pub mod dom {
     pub mod htmlulistelement;
     pub mod htmlvideoelement;
     pub mod htmlunknownelement;
+    pub mod keyboardevent;
     pub mod location;
     pub mod mouseevent;
+    pub mod mutationobserver;
+    pub mod mutationrecord;
     pub mod navigator;
     pub mod node;
     pub mod nodelist;
     pub mod processinginstruction;
     pub mod performance;
     pub mod performancetiming;
+    pub mod popstateevent;
     pub mod progressevent;
+    pub mod storage;
+    pub mod storageevent;
     pub mod uievent;
     pub mod text;
     pub mod validitystate;
@@ -183,3 +192,4 @@ pub mod html {
 pub mod layout_interface;
 pub mod page;
 pub mod script_task;
+pub mod textinput;
@@ -6,20 +6,27 @@
 //! and layout tasks.
 
 use dom::bindings::codegen::RegisterBindings;
-use dom::bindings::codegen::InheritTypes::{EventTargetCast, NodeCast, EventCast};
+use dom::bindings::codegen::InheritTypes::{EventTargetCast, NodeCast, EventCast, ElementCast};
 use dom::bindings::js::{JS, JSRef, RootCollection, Temporary, OptionalSettable};
 use dom::bindings::js::OptionalRootable;
 use dom::bindings::utils::Reflectable;
 use dom::bindings::utils::{wrap_for_same_compartment, pre_wrap};
-use dom::document::{Document, HTMLDocument, DocumentHelpers};
-use dom::element::{Element};
+use dom::document::{Document, HTMLDocument, DocumentHelpers, DocumentMethods};
+use dom::element::{Element, ElementHelpers};
 use dom::event::{Event_, ResizeEvent, ReflowEvent, ClickEvent, MouseDownEvent, MouseMoveEvent, MouseUpEvent};
-use dom::event::Event;
+use dom::event::{Event, KeyEvent};
+use dom::hashchangeevent::HashChangeEvent;
+use dom::history::HistoryHelpers;
+use dom::keyboardevent::KeyboardEvent;
+use dom::mutationobserver::MutationObserverHelpers;
 use dom::uievent::UIEvent;
 use dom::eventtarget::{EventTarget, EventTargetHelpers};
 use dom::node;
 use dom::node::{Node, NodeHelpers};
-use dom::window::{TimerId, Window, WindowHelpers};
+use dom::performance::PerformanceMethods;
+use dom::popstateevent::PopStateEvent;
+use dom::storageevent::StorageEvent;
+use dom::window::{TimerId, Window, WindowHelpers, WindowMethods};
 use dom::xmlhttprequest::{TrustedXHRAddress, XMLHttpRequest, XHRProgress};
 use html::hubbub_html_parser::HtmlParserResult;
 use html::hubbub_html_parser::{HtmlDiscoveredStyle, HtmlDiscoveredScript};
@@ -36,21 +43,26 @@ use geom::size::TypedSize2D;
 use js::jsapi::JS_CallFunctionValue;
 use js::jsapi::{JS_SetWrapObjectCallbacks, JS_SetGCZeal, JS_DEFAULT_ZEAL_FREQ, JS_GC};
 use js::jsapi::{JSContext, JSRuntime};
-use js::jsval::NullValue;
+use js::jsval::{DoubleValue, NullValue};
 use js::rust::{Cx, RtUtils};
 use js::rust::with_compartment;
 use js;
 use servo_msg::compositor_msg::{FinishedLoading, LayerId, Loading};
 use servo_msg::compositor_msg::{ScriptListener};
 use servo_msg::constellation_msg::{ConstellationChan, LoadCompleteMsg, LoadUrlMsg, NavigationDirection};
+use servo_msg::constellation_msg::{HistoryStateId, NavigationType};
 use servo_msg::constellation_msg::{PipelineId, SubpageId, Failure, FailureMsg};
+use servo_msg::constellation_msg::{Key, KeyModifiers, KeyState, Pressed, Released, Repeated};
+use servo_msg::constellation_msg::{Shift, Control, Alt, Super};
 use servo_msg::constellation_msg;
 use servo_net::image_cache_task::ImageCacheTask;
-use servo_net::resource_task::ResourceTask;
+use servo_net::resource_task::{LoadData, ResourceTask};
+use servo_net::storage_task::{Local, Session, StorageTask, StorageType};
 use servo_util::geometry::{PagePx, to_frac_px};
 use servo_util::task::send_on_failure;
 use std::cell::RefCell;
 use std::comm::{channel, Sender, Receiver};
+use std::f32;
 use std::mem::replace;
 use std::ptr;
 use std::rc::Rc;
@@ -61,14 +73,26 @@ use serialize::{Encoder, Encodable};
 
 local_data_key!(pub StackRoots: *RootCollection)
 
+/// How far the arrow keys scroll the page, in page px.
+static KEY_SCROLL_DISTANCE: f32 = 30.0;
+
+/// How much of the height of the window Space, Page Up and Page Down scroll the page by.
+static PAGE_SCROLL_FRACTION: f32 = 0.9;
+
 /// Messages used to control the script task.
 pub enum ScriptMsg {
     /// Loads a new URL on the specified pipeline.
-    LoadMsg(PipelineId, Url),
-    /// Acts on a fragment URL load on the specified pipeline.
-    TriggerFragmentMsg(PipelineId, Url),
+    LoadMsg(PipelineId, LoadData),
+    /// Shows the document that the specified pipeline loaded earlier again, after a traversal of
+    /// the session history returned to it.
+    LoadFromHistoryMsg(PipelineId),
+    /// Acts on a fragment URL load on the specified pipeline, given the URLs before and after.
+    TriggerFragmentMsg(PipelineId, Url, Url),
     /// Begins a content-initiated load on the specified pipeline.
-    TriggerLoadMsg(PipelineId, Url),
+    TriggerLoadMsg(PipelineId, LoadData, NavigationType),
+    /// Restores the URL and state object of a session history entry the constellation traversed
+    /// to on the specified pipeline.
+    RestoreHistoryEntryMsg(PipelineId, Url, Option<HistoryStateId>),
     /// Gives a channel and ID to a layout task, as well as the ID of that layout's parent
     AttachLayoutMsg(NewLayoutInfo),
     /// Instructs the script task to send a navigate message to the constellation.
@@ -88,7 +112,16 @@ pub enum ScriptMsg {
     /// Notifies the script that a window associated with a particular pipeline should be closed.
     ExitWindowMsg(PipelineId),
     /// Notifies the script of progress on a fetch
-    XHRProgressMsg(TrustedXHRAddress, XHRProgress)
+    XHRProgressMsg(TrustedXHRAddress, XHRProgress),
+    /// Runs the animation frame callbacks of the specified pipeline.
+    AnimationFrameMsg(PipelineId),
+    /// Fires a `storage` event at the window of the specified pipeline, after another document
+    /// at the given URL changed a storage area of the same origin.
+    StorageEventMsg(PipelineId, StorageType, Url, Option<String>, Option<String>,
+                    Option<String>),
+    /// Reflows the specified pipeline and replies once layout has finished, so that the sender
+    /// can wait for the effects of the messages it sent before.
+    ReflowAndReplyMsg(PipelineId, Sender<()>),
 }
 
 pub struct NewLayoutInfo {
@@ -207,12 +240,14 @@ impl ScriptTask {
                chan: ScriptChan,
                constellation_chan: ConstellationChan,
                resource_task: ResourceTask,
+               storage_task: StorageTask,
                img_cache_task: ImageCacheTask,
                window_size: TypedSize2D<PagePx, f32>)
                -> Rc<ScriptTask> {
         let (js_runtime, js_context) = ScriptTask::new_rt_and_cx();
         let page = Page::new(id, None, layout_chan, window_size,
                              resource_task.clone(),
+                             storage_task,
                              constellation_chan.clone(),
                              js_context.clone());
         Rc::new(ScriptTask {
@@ -288,6 +323,7 @@ impl ScriptTask {
                   constellation_chan: ConstellationChan,
                   failure_msg: Failure,
                   resource_task: ResourceTask,
+                  storage_task: StorageTask,
                   image_cache_task: ImageCacheTask,
                   window_size: TypedSize2D<PagePx, f32>) {
         let mut builder = TaskBuilder::new().named("ScriptTask");
@@ -301,6 +337,7 @@ impl ScriptTask {
                                               chan,
                                               constellation_chan,
                                               resource_task,
+                                              storage_task,
                                               image_cache_task,
                                               window_size);
             let mut failsafe = ScriptMemoryFailsafe::new(&*script_task);
@@ -338,6 +375,7 @@ impl ScriptTask {
 
         for (id, size) in resizes.move_iter() {
             self.handle_event(id, ResizeEvent(size));
+            self.perform_a_microtask_checkpoint();
         }
 
         // Store new resizes, and gather all other events.
@@ -369,9 +407,17 @@ impl ScriptTask {
             match msg {
                 // TODO(tkuehn) need to handle auxiliary layouts for iframes
                 AttachLayoutMsg(new_layout_info) => self.handle_new_layout(new_layout_info),
-                LoadMsg(id, url) => self.load(id, url),
-                TriggerLoadMsg(id, url) => self.trigger_load(id, url),
-                TriggerFragmentMsg(id, url) => self.trigger_fragment(id, url),
+                LoadMsg(id, load_data) => self.load(id, load_data),
+                LoadFromHistoryMsg(id) => self.load_from_history(id),
+                TriggerLoadMsg(id, load_data, navigation_type) => {
+                    self.trigger_load(id, load_data, navigation_type)
+                }
+                TriggerFragmentMsg(id, old_url, new_url) => {
+                    self.trigger_fragment(id, old_url, new_url)
+                }
+                RestoreHistoryEntryMsg(id, url, state_id) => {
+                    self.handle_restore_history_entry_msg(id, url, state_id)
+                }
                 SendEventMsg(id, event) => self.handle_event(id, event),
                 FireTimerMsg(id, timer_id) => self.handle_fire_timer_msg(id, timer_id),
                 NavigateMsg(direction) => self.handle_navigate_msg(direction),
@@ -381,12 +427,58 @@ impl ScriptTask {
                 ExitWindowMsg(id) => self.handle_exit_window_msg(id),
                 ResizeMsg(..) => fail!("should have handled ResizeMsg already"),
                 XHRProgressMsg(addr, progress) => XMLHttpRequest::handle_xhr_progress(addr, progress),
+                AnimationFrameMsg(id) => self.handle_animation_frame_msg(id),
+                StorageEventMsg(id, storage_type, url, key, old_value, new_value) => {
+                    self.handle_storage_event_msg(id, storage_type, url, key, old_value,
+                                                  new_value)
+                }
+                ReflowAndReplyMsg(id, reply) => self.handle_reflow_and_reply_msg(id, reply),
             }
+
+            self.perform_a_microtask_checkpoint();
         }
 
         true
     }
 
+    /// Delivers the mutation records that were queued while handling the last task to the
+    /// callbacks of their observers, until the callbacks stop causing new ones.
+    ///
+    /// http://www.whatwg.org/html/#perform-a-microtask-checkpoint
+    fn perform_a_microtask_checkpoint(&self) {
+        loop {
+            let mut delivered = false;
+            let pages: Vec<Rc<Page>> = self.page.borrow().iter().collect();
+            for page in pages.iter() {
+                let window = {
+                    let frame = page.frame();
+                    match *frame {
+                        Some(ref frame) => Temporary::new(frame.window.clone()),
+                        None => continue,
+                    }
+                }.root();
+
+                // Observers stay in the window's list until their turn, so that the garbage
+                // collector finds them while earlier callbacks run.
+                loop {
+                    let observer = window.deref().pending_mutation_observers.deref()
+                                         .borrow_mut().remove(0);
+                    match observer {
+                        Some(observer) => {
+                            if observer.root().deliver_records() {
+                                delivered = true;
+                            }
+                        }
+                        None => break,
+                    }
+                }
+            }
+            if !delivered {
+                break;
+            }
+        }
+    }
+
     fn handle_new_layout(&self, new_layout_info: NewLayoutInfo) {
         debug!("Script: new layout: {:?}", new_layout_info);
         let NewLayoutInfo {
@@ -404,6 +496,7 @@ impl ScriptTask {
             let window_size = parent_page.window_size.deref().get();
             Page::new(new_pipeline_id, Some(subpage_id), layout_chan, window_size,
                       parent_page.resource_task.deref().clone(),
+                      parent_page.storage_task.deref().clone(),
                       self.constellation_chan.clone(),
                       self.js_context.borrow().get_ref().clone())
         };
@@ -440,6 +533,99 @@ impl ScriptTask {
         }
     }
 
+    /// Runs the animation frame callbacks that were requested before this frame began, passing
+    /// them all the same time, as `performance.now()` would return it.
+    ///
+    /// http://www.w3.org/TR/animation-timing/#processingmodel
+    fn handle_animation_frame_msg(&self, id: PipelineId) {
+        // The pipeline may have gone away since the compositor asked for the frame.
+        let page = match self.page.borrow().find(id) {
+            Some(page) => page,
+            None => return,
+        };
+        let window = {
+            let frame = page.frame();
+            match *frame {
+                Some(ref frame) => Temporary::new(frame.window.clone()),
+                None => return,
+            }
+        }.root();
+
+        let handles: Vec<i32> = window.deref().animation_frame_callbacks.deref().borrow()
+                                      .iter().map(|callback| callback.handle).collect();
+        let time = window.deref().Performance().root().deref().Now();
+        let this_value = window.deref().reflector().get_jsobject();
+        let cx = self.get_cx();
+        for handle in handles.iter() {
+            // Callbacks that an earlier callback cancelled are gone by now.
+            let callback = {
+                let mut callbacks = window.deref().animation_frame_callbacks.deref().borrow_mut();
+                match callbacks.iter().position(|callback| callback.handle == *handle) {
+                    Some(index) => callbacks.remove(index).unwrap().callback,
+                    None => continue,
+                }
+            };
+            with_compartment(cx, this_value, || {
+                let mut argv = [DoubleValue(time)];
+                let mut rval = NullValue();
+                unsafe {
+                    JS_CallFunctionValue(cx, this_value, *callback,
+                                         1, argv.as_mut_ptr(), &mut rval);
+                }
+            });
+        }
+
+        // Callbacks that were requested during this frame wait for the next one.
+        if !window.deref().animation_frame_callbacks.deref().borrow().is_empty() {
+            window.deref().request_animation_frame();
+        }
+    }
+
+    /// Fires a `storage` event at the window of the given pipeline, unless it went away since the
+    /// constellation sent the message.
+    ///
+    /// http://www.whatwg.org/html/#send-a-storage-notification
+    /// Reflows the specified pipeline, if it still exists, and replies once layout has finished.
+    fn handle_reflow_and_reply_msg(&self, id: PipelineId, reply: Sender<()>) {
+        let page = match self.page.borrow().find(id) {
+            Some(page) => page,
+            None => {
+                let _ = reply.send_opt(());
+                return
+            }
+        };
+        self.handle_event(id, ReflowEvent);
+        page.join_layout();
+        let _ = reply.send_opt(());
+    }
+
+    fn handle_storage_event_msg(&self, id: PipelineId, storage_type: StorageType, url: Url,
+                                key: Option<String>, old_value: Option<String>,
+                                new_value: Option<String>) {
+        let page = match self.page.borrow().find(id) {
+            Some(page) => page,
+            None => return,
+        };
+        let window = {
+            let frame = page.frame();
+            match *frame {
+                Some(ref frame) => Temporary::new(frame.window.clone()),
+                None => return,
+            }
+        }.root();
+
+        let storage_area = match storage_type {
+            Local => window.deref().LocalStorage(),
+            Session => window.deref().SessionStorage(),
+        }.root();
+        let event = StorageEvent::new(&*window, "storage".to_string(), false, false, key,
+                                      old_value, new_value, url.to_str(),
+                                      Some((*storage_area).clone())).root();
+        let event: &JSRef<Event> = EventCast::from_ref(&*event);
+        let wintarget: &JSRef<EventTarget> = EventTargetCast::from_ref(&*window);
+        let _ = wintarget.dispatch_event_with_target(None, event);
+    }
+
     /// Handles a notification that reflow completed.
     fn handle_reflow_complete_msg(&self, pipeline_id: PipelineId, reflow_id: uint) {
         debug!("Script: Reflow {:?} complete for {:?}", reflow_id, pipeline_id);
@@ -515,9 +701,34 @@ impl ScriptTask {
 
     }
 
+    /// Shows the document of the given pipeline again after the session history returned to it,
+    /// laying it out if it changed size while it was hidden. The document keeps its state; its
+    /// URL, which may have changed through `pushState()`, is restored separately.
+    fn load_from_history(&self, pipeline_id: PipelineId) {
+        let page = match self.page.borrow().find(pipeline_id) {
+            Some(page) => page,
+            None => return,
+        };
+        let needs_reflow = {
+            let mut page_url = page.mut_url();
+            match replace(&mut *page_url, None) {
+                Some((loaded, needs_reflow)) => {
+                    *page_url = Some((loaded, false));
+                    needs_reflow
+                }
+                None => false,
+            }
+        };
+        if needs_reflow {
+            page.damage(ContentChangedDocumentDamage);
+            page.reflow(ReflowForDisplay, self.chan.clone(), self.compositor);
+        }
+    }
+
     /// The entry point to document loading. Defines bindings, sets up the window and document
     /// objects, parses HTML and CSS, and kicks off initial layout.
-    fn load(&self, pipeline_id: PipelineId, url: Url) {
+    fn load(&self, pipeline_id: PipelineId, load_data: LoadData) {
+        let url = load_data.url.clone();
         debug!("ScriptTask: loading {:?} on page {:?}", url, pipeline_id);
 
         let mut page = self.page.borrow_mut();
@@ -525,19 +736,6 @@ impl ScriptTask {
             message for a layout channel that is not associated with this script task. This
             is a bug.");
 
-        let last_loaded_url = replace(&mut *page.mut_url(), None);
-        match last_loaded_url {
-            Some((ref loaded, needs_reflow)) if *loaded == url => {
-                *page.mut_url() = Some((loaded.clone(), false));
-                if needs_reflow {
-                    page.damage(ContentChangedDocumentDamage);
-                    page.reflow(ReflowForDisplay, self.chan.clone(), self.compositor);
-                }
-                return;
-            },
-            _ => (),
-        }
-
         let cx = self.js_context.borrow();
         let cx = cx.get_ref();
         // Create the window and document objects.
@@ -560,7 +758,7 @@ impl ScriptTask {
         // Note: We can parse the next document in parallel with any previous documents.
         let html_parsing_result = hubbub_html_parser::parse_html(&*page,
                                                                  &*document,
-                                                                 url.clone(),
+                                                                 load_data,
                                                                  self.resource_task.clone());
 
         let HtmlParserResult {
@@ -716,9 +914,11 @@ impl ScriptTask {
 
                         let temp_node =
                                 node::from_untrusted_node_address(
-                                    self.js_runtime.deref().ptr, node_address);
+                                    self.js_runtime.deref().ptr, node_address).root();
+                        let node = temp_node.deref();
 
-                        let maybe_node = temp_node.root().ancestors().find(|node| node.is_element());
+                        let maybe_node = Some(node.clone()).move_iter().chain(node.ancestors())
+                                                           .find(|node| node.is_element());
                         match maybe_node {
                             Some(node) => {
                                 debug!("clicked on {:s}", node.debug_str());
@@ -742,7 +942,9 @@ impl ScriptTask {
                     None => {}
                 }
             }
-            MouseDownEvent(..) => {}
+            MouseDownEvent(_button, point) => {
+                self.handle_mouse_down_event(pipeline_id, point);
+            }
             MouseUpEvent(..) => {}
             MouseMoveEvent(point) => {
                 let page = get_page(&*self.page.borrow(), pipeline_id);
@@ -808,27 +1010,189 @@ impl ScriptTask {
                     None => {}
               }
             }
+
+            KeyEvent(key, state, modifiers, c) => {
+                self.handle_key_event(pipeline_id, key, state, modifiers, c);
+            }
+        }
+    }
+
+    /// Dispatches the `keydown`, `keypress` and `keyup` events for a key that the user pressed,
+    /// released or held down, typing the given character if any, and performs the default action
+    /// of the key unless the page cancels it.
+    fn handle_key_event(&self,
+                        pipeline_id: PipelineId,
+                        key: Key,
+                        state: KeyState,
+                        modifiers: KeyModifiers,
+                        c: Option<char>) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let frame = page.frame();
+        let (window, document) = match *frame {
+            Some(ref frame) => (frame.window.root(), frame.document.root()),
+            None => return,
+        };
+
+        // Key events go to the focused element; if nothing has the focus, to the body.
+        let focused = document.deref().focused_element().root();
+        let body = document.deref().GetBody().root();
+        let target: &JSRef<EventTarget> = match (&focused, &body) {
+            (&Some(ref focused), _) => EventTargetCast::from_ref(&**focused),
+            (&None, &Some(ref body)) => EventTargetCast::from_ref(&**body),
+            (&None, &None) => EventTargetCast::from_ref(&*document),
+        };
+
+        match state {
+            Pressed | Repeated => {
+                let mut prevented = !dispatch_key_event(&*window, target, "keydown",
+                                                        key, state, modifiers, c);
+
+                // Only keys that type something fire `keypress`, and not while a shortcut
+                // modifier is held down.
+                let shortcut = modifiers.intersects(Control | Alt | Super);
+                let types = c.is_some() || key == constellation_msg::KeyEnter;
+                if !prevented && !shortcut && types {
+                    prevented = !dispatch_key_event(&*window, target, "keypress",
+                                                    key, state, modifiers, c);
+                }
+
+                if !prevented {
+                    if key == constellation_msg::KeyTab {
+                        document.deref().focus_next_sequentially(modifiers.contains(Shift));
+                    } else {
+                        self.scroll_for_key(pipeline_id, &*page, key, modifiers);
+                    }
+                }
+            }
+            Released => {
+                dispatch_key_event(&*window, target, "keyup", key, state, modifiers, c);
+            }
+        }
+    }
+
+    /// Moves the focus to the focusable element under the mouse, or clears it if there is none.
+    fn handle_mouse_down_event(&self, pipeline_id: PipelineId, point: Point2D<f32>) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let document = match *page.frame() {
+            Some(ref frame) => frame.document.root(),
+            None => return,
+        };
+
+        let node_address = match page.hit_test(&point) {
+            Some(node_address) => node_address,
+            None => return,
+        };
+        let temp_node = node::from_untrusted_node_address(self.js_runtime.deref().ptr,
+                                                          node_address).root();
+        let node = temp_node.deref();
+        let focusable = Some(node.clone()).move_iter().chain(node.ancestors()).find(|node| {
+            ElementCast::to_ref(node).map_or(false, |element| element.is_focusable_area())
+        });
+        match focusable {
+            Some(ref node) => document.deref().request_focus(ElementCast::to_ref(node)),
+            None => document.deref().request_focus(None),
         }
     }
 
+    /// Performs the default action of the keys that scroll the page.
+    fn scroll_for_key(&self, pipeline_id: PipelineId, page: &Page, key: Key,
+                      modifiers: KeyModifiers) {
+        let page_height = page.window_size.deref().get().height.get();
+        let delta = match key {
+            constellation_msg::KeyUp => Point2D(0.0, -KEY_SCROLL_DISTANCE),
+            constellation_msg::KeyDown => Point2D(0.0, KEY_SCROLL_DISTANCE),
+            constellation_msg::KeyLeft => Point2D(-KEY_SCROLL_DISTANCE, 0.0),
+            constellation_msg::KeyRight => Point2D(KEY_SCROLL_DISTANCE, 0.0),
+            constellation_msg::KeySpace if modifiers.contains(Shift) => {
+                Point2D(0.0, -page_height * PAGE_SCROLL_FRACTION)
+            }
+            constellation_msg::KeySpace | constellation_msg::KeyPageDown => {
+                Point2D(0.0, page_height * PAGE_SCROLL_FRACTION)
+            }
+            constellation_msg::KeyPageUp => Point2D(0.0, -page_height * PAGE_SCROLL_FRACTION),
+            // The compositor clamps the scroll position to the page.
+            constellation_msg::KeyHome => Point2D(0.0, -f32::INFINITY),
+            constellation_msg::KeyEnd => Point2D(0.0, f32::INFINITY),
+            _ => return,
+        };
+        self.compositor.scroll_fragment_by(pipeline_id, LayerId::null(), delta);
+    }
+
     /// The entry point for content to notify that a new load has been requested
     /// for the given pipeline.
-    fn trigger_load(&self, pipeline_id: PipelineId, url: Url) {
+    fn trigger_load(&self, pipeline_id: PipelineId, load_data: LoadData,
+                    navigation_type: NavigationType) {
         let ConstellationChan(ref const_chan) = self.constellation_chan;
-        const_chan.send(LoadUrlMsg(pipeline_id, url));
+        const_chan.send(LoadUrlMsg(pipeline_id, load_data, navigation_type));
     }
 
     /// The entry point for content to notify that a fragment url has been requested
-    /// for the given pipeline.
-    fn trigger_fragment(&self, pipeline_id: PipelineId, url: Url) {
+    /// for the given pipeline. Scrolls to the fragment, and fires `hashchange` if the URL
+    /// changed.
+    fn trigger_fragment(&self, pipeline_id: PipelineId, old_url: Url, new_url: Url) {
         let page = get_page(&*self.page.borrow(), pipeline_id);
-        match page.find_fragment_node(url.fragment.unwrap()).root() {
-            Some(node) => {
-                self.scroll_fragment_point(pipeline_id, &*node);
-            }
+        match new_url.fragment {
+            Some(ref fragment) => match page.find_fragment_node(fragment.clone()).root() {
+                Some(node) => {
+                    self.scroll_fragment_point(pipeline_id, &*node);
+                }
+                None => {}
+            },
             None => {}
-         }
-     }
+        }
+
+        if old_url != new_url {
+            let window = {
+                let frame = page.frame();
+                match *frame {
+                    Some(ref frame) => Temporary::new(frame.window.clone()),
+                    None => return,
+                }
+            }.root();
+            let event = HashChangeEvent::new(&*window, "hashchange".to_string(), true, false,
+                                             old_url.to_str(), new_url.to_str()).root();
+            let event: &JSRef<Event> = EventCast::from_ref(&*event);
+            let wintarget: &JSRef<EventTarget> = EventTargetCast::from_ref(&*window);
+            let _ = wintarget.dispatch_event_with_target(None, event);
+        }
+    }
+
+    /// Handles the constellation traversing the session history to an entry of the specified
+    /// pipeline: restores the entry's URL and state object and fires `popstate`, as well as
+    /// `hashchange` if only the fragment changed.
+    ///
+    /// http://www.whatwg.org/html/#traverse-the-history
+    fn handle_restore_history_entry_msg(&self, pipeline_id: PipelineId, url: Url,
+                                        state_id: Option<HistoryStateId>) {
+        let page = get_page(&*self.page.borrow(), pipeline_id);
+        let window = {
+            let frame = page.frame();
+            match *frame {
+                Some(ref frame) => Temporary::new(frame.window.clone()),
+                None => return,
+            }
+        }.root();
+
+        let old_url = page.get_url();
+        window.deref().set_url(url.clone());
+        let history = window.deref().History().root();
+        history.deref().set_current_state(state_id);
+
+        let state = history.deref().current_state();
+        let event = PopStateEvent::new(&*window, "popstate".to_string(), true, false,
+                                       state).root();
+        let event: &JSRef<Event> = EventCast::from_ref(&*event);
+        let wintarget: &JSRef<EventTarget> = EventTargetCast::from_ref(&*window);
+        let _ = wintarget.dispatch_event_with_target(None, event);
+
+        let mut old_without_fragment = old_url.clone();
+        old_without_fragment.fragment = None;
+        let mut new_without_fragment = url.clone();
+        new_without_fragment.fragment = None;
+        if old_url != url && old_without_fragment == new_without_fragment {
+            self.trigger_fragment(pipeline_id, old_url, url);
+        }
+    }
 }
 
 /// Shuts down layout for the given page tree.
@@ -868,6 +1232,21 @@ fn shut_down_layout(page_tree: &Rc<Page>, rt: *mut JSRuntime) {
 }
 
 
+/// Dispatches a trusted keyboard event of the given type at the target. Returns false if the
+/// event was cancelled.
+fn dispatch_key_event(window: &JSRef<Window>,
+                      target: &JSRef<EventTarget>,
+                      type_: &str,
+                      key: Key,
+                      state: KeyState,
+                      modifiers: KeyModifiers,
+                      c: Option<char>) -> bool {
+    let event = KeyboardEvent::new_for_key(window, type_.to_string(), key, state, modifiers,
+                                           c).root();
+    let event: &JSRef<Event> = EventCast::from_ref(&*event);
+    target.dispatch_event_with_target(None, event).unwrap_or(false)
+}
+
 fn get_page(page: &Rc<Page>, pipeline_id: PipelineId) -> Rc<Page> {
     page.find(pipeline_id).expect("ScriptTask: received an event \
         message for a layout channel that is not associated with this script task.\
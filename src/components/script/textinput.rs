@@ -0,0 +1,316 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! The editing state shared by `<input>` and `<textarea>`: the value, the selection and the
+//! handling of the keys that edit them.
+
+use dom::bindings::js::JSRef;
+use dom::keyboardevent::{KeyboardEvent, KeyboardEventMethods};
+use servo_util::str::DOMString;
+
+use std::char;
+use std::cmp::{max, min};
+
+/// Whether a text control holds one line of text or several.
+#[deriving(Eq)]
+pub enum Lines {
+    Single,
+    Multiple,
+}
+
+/// The direction in which a selection was made. The caret is at the end of the selection unless
+/// the selection was made backward.
+#[deriving(Eq, Clone)]
+pub enum SelectionDirection {
+    Forward,
+    Backward,
+    NoDirection,
+}
+
+impl SelectionDirection {
+    // http://www.whatwg.org/html/#dom-textarea/input-selectiondirection
+    pub fn from_str(direction: &str) -> SelectionDirection {
+        match direction {
+            "forward" => Forward,
+            "backward" => Backward,
+            _ => NoDirection,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            Forward => "forward",
+            Backward => "backward",
+            NoDirection => "none",
+        }
+    }
+}
+
+/// What handling a key did to a `TextInput`.
+#[deriving(Eq)]
+pub enum KeyReaction {
+    /// The key does nothing in a text control.
+    NotHandled,
+    /// The key moved the caret or changed the selection.
+    Moved,
+    /// The key changed the content.
+    Edited,
+}
+
+/// The content of a text control and the selection within it. Positions count characters.
+pub struct TextInput {
+    content: Vec<char>,
+    selection_start: uint,
+    selection_end: uint,
+    selection_direction: SelectionDirection,
+    lines: Lines,
+}
+
+impl TextInput {
+    pub fn new(lines: Lines, content: DOMString) -> TextInput {
+        let mut input = TextInput {
+            content: vec!(),
+            selection_start: 0,
+            selection_end: 0,
+            selection_direction: NoDirection,
+            lines: lines,
+        };
+        input.set_content(content);
+        input
+    }
+
+    pub fn get_content(&self) -> DOMString {
+        String::from_chars(self.content.as_slice())
+    }
+
+    /// Replaces the content, putting the caret at its end.
+    pub fn set_content(&mut self, content: DOMString) {
+        self.content = content.as_slice().chars().filter(|&c| self.accepts(c)).collect();
+        let end = self.content.len();
+        self.set_selection_range(end, end, NoDirection);
+    }
+
+    pub fn len(&self) -> uint {
+        self.content.len()
+    }
+
+    pub fn selection_start(&self) -> uint {
+        self.selection_start
+    }
+
+    pub fn selection_end(&self) -> uint {
+        self.selection_end
+    }
+
+    pub fn selection_direction(&self) -> SelectionDirection {
+        self.selection_direction
+    }
+
+    /// Returns the position of the caret.
+    pub fn caret(&self) -> uint {
+        if self.selection_direction == Backward {
+            self.selection_start
+        } else {
+            self.selection_end
+        }
+    }
+
+    /// Returns the end of the selection opposite the caret.
+    fn anchor(&self) -> uint {
+        if self.selection_direction == Backward {
+            self.selection_end
+        } else {
+            self.selection_start
+        }
+    }
+
+    // http://www.whatwg.org/html/#dom-textarea/input-setselectionrange
+    pub fn set_selection_range(&mut self, start: uint, end: uint,
+                               direction: SelectionDirection) {
+        let end = min(end, self.content.len());
+        self.selection_start = min(start, end);
+        self.selection_end = end;
+        self.selection_direction = direction;
+    }
+
+    pub fn select_all(&mut self) {
+        let end = self.content.len();
+        self.set_selection_range(0, end, NoDirection);
+    }
+
+    /// Replaces the selection with the given text and puts the caret after it.
+    pub fn replace_selection(&mut self, text: &str) {
+        let insertion: Vec<char> = text.chars().filter(|&c| self.accepts(c)).collect();
+        let after = self.content.slice_from(self.selection_end).to_owned();
+        self.content.truncate(self.selection_start);
+        self.content.push_all(insertion.as_slice());
+        self.content.push_all(after.as_slice());
+        let caret = self.selection_start + insertion.len();
+        self.set_selection_range(caret, caret, NoDirection);
+    }
+
+    /// Replaces the text between the given positions, keeping the selection where it was
+    /// relative to the text around it.
+    // http://www.whatwg.org/html/#dom-textarea/input-setrangetext
+    pub fn replace_range(&mut self, start: uint, end: uint, text: &str) {
+        let len = self.content.len();
+        let (start, end) = (min(start, len), min(max(start, end), len));
+        let insertion: Vec<char> = text.chars().filter(|&c| self.accepts(c)).collect();
+        let after = self.content.slice_from(end).to_owned();
+        self.content.truncate(start);
+        self.content.push_all(insertion.as_slice());
+        self.content.push_all(after.as_slice());
+
+        let adjust = |position: uint| {
+            if position <= start {
+                position
+            } else if position >= end {
+                position - (end - start) + insertion.len()
+            } else {
+                start
+            }
+        };
+        let (selection_start, selection_end) = (adjust(self.selection_start),
+                                                adjust(self.selection_end));
+        let direction = self.selection_direction;
+        self.set_selection_range(selection_start, selection_end, direction);
+    }
+
+    /// Moves the caret to the given position, extending the selection from its anchor if
+    /// `selecting` is true and collapsing it otherwise.
+    fn move_caret_to(&mut self, position: uint, selecting: bool) {
+        let position = min(position, self.content.len());
+        if !selecting {
+            self.set_selection_range(position, position, NoDirection);
+            return
+        }
+        let anchor = self.anchor();
+        if position < anchor {
+            self.set_selection_range(position, anchor, Backward);
+        } else {
+            self.set_selection_range(anchor, position, Forward);
+        }
+    }
+
+    /// Returns the positions of the start of the line that contains the given position and of
+    /// the line break that ends it, or the end of the content.
+    fn line_bounds(&self, position: uint) -> (uint, uint) {
+        let start = self.content.slice_to(position).iter().rposition(|&c| c == '\n')
+                                                          .map_or(0, |index| index + 1);
+        let end = self.content.slice_from(position).iter().position(|&c| c == '\n')
+                                                   .map_or(self.content.len(),
+                                                           |index| position + index);
+        (start, end)
+    }
+
+    /// Moves the caret to the same column of the previous or the next line.
+    fn move_caret_vertically(&mut self, down: bool, selecting: bool) {
+        let caret = self.caret();
+        let (start, end) = self.line_bounds(caret);
+        let column = caret - start;
+        let position = if down {
+            if end == self.content.len() {
+                self.content.len()
+            } else {
+                let (next_start, next_end) = self.line_bounds(end + 1);
+                min(next_start + column, next_end)
+            }
+        } else if start == 0 {
+            0
+        } else {
+            let (previous_start, previous_end) = self.line_bounds(start - 1);
+            min(previous_start + column, previous_end)
+        };
+        self.move_caret_to(position, selecting);
+    }
+
+    /// Deletes the selection or, if it is collapsed, the character before or after the caret.
+    fn delete(&mut self, forward: bool) -> KeyReaction {
+        if self.selection_start == self.selection_end {
+            let caret = self.selection_start;
+            if forward && caret < self.content.len() {
+                self.set_selection_range(caret, caret + 1, NoDirection);
+            } else if !forward && caret > 0 {
+                self.set_selection_range(caret - 1, caret, NoDirection);
+            } else {
+                return Moved
+            }
+        }
+        self.replace_selection("");
+        Edited
+    }
+
+    /// Returns whether the given character can be part of the content.
+    fn accepts(&self, c: char) -> bool {
+        match c {
+            '\n' => self.lines == Multiple,
+            '\r' => false,
+            _ => true,
+        }
+    }
+
+    /// Performs the editing action of a `keydown` event.
+    pub fn handle_keydown(&mut self, event: &JSRef<KeyboardEvent>) -> KeyReaction {
+        let selecting = event.ShiftKey();
+        if event.CtrlKey() || event.MetaKey() {
+            return match event.Key().as_slice() {
+                "a" | "A" => {
+                    self.select_all();
+                    Moved
+                }
+                _ => NotHandled,
+            }
+        }
+
+        let caret = self.caret();
+        match event.Key().as_slice() {
+            "Backspace" => self.delete(false),
+            "Delete" => self.delete(true),
+            "ArrowLeft" => {
+                let position = if !selecting && self.selection_start != self.selection_end {
+                    self.selection_start
+                } else {
+                    max(caret, 1) - 1
+                };
+                self.move_caret_to(position, selecting);
+                Moved
+            }
+            "ArrowRight" => {
+                let position = if !selecting && self.selection_start != self.selection_end {
+                    self.selection_end
+                } else {
+                    caret + 1
+                };
+                self.move_caret_to(position, selecting);
+                Moved
+            }
+            "ArrowUp" | "ArrowDown" if self.lines == Multiple => {
+                self.move_caret_vertically(event.Key().as_slice() == "ArrowDown", selecting);
+                Moved
+            }
+            "Home" => {
+                let (start, _) = self.line_bounds(caret);
+                self.move_caret_to(start, selecting);
+                Moved
+            }
+            "End" => {
+                let (_, end) = self.line_bounds(caret);
+                self.move_caret_to(end, selecting);
+                Moved
+            }
+            _ => NotHandled,
+        }
+    }
+
+    /// Performs the editing action of a `keypress` event, which types a character.
+    pub fn handle_keypress(&mut self, event: &JSRef<KeyboardEvent>) -> KeyReaction {
+        let c = match char::from_u32(event.CharCode()) {
+            Some('\r') if self.lines == Multiple => '\n',
+            Some(c) if c >= ' ' => c,
+            _ => return NotHandled,
+        };
+        self.replace_selection(c.to_str().as_slice());
+        Edited
+    }
+}
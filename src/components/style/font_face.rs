@@ -0,0 +1,250 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+use std::ascii::StrAsciiExt;
+use std::cmp;
+use cssparser::{parse_declaration_list, ToCss};
+use cssparser::ast::*;
+use cssparser::ast;
+
+use errors::{ErrorLoggerIterator, log_css_error};
+use stylesheets::{CSSRule, CSSStyleRule, CSSMediaRule, CSSFontFaceRule};
+use media_queries::Device;
+use parsing_utils::one_component_value;
+use properties::longhands;
+use properties::computed_values::{font_family, font_style, font_weight};
+use servo_util::url::parse_url;
+use url::Url;
+
+
+/// The largest Unicode code point, which ends the default `unicode-range`.
+static MAX_CODE_POINT: u32 = 0x10FFFF;
+
+
+pub struct FontFaceRule {
+    pub family: String,
+    /// The sources of font data, in order of preference.
+    pub sources: Vec<Source>,
+    pub weight: font_weight::T,
+    pub style: font_style::T,
+    /// Inclusive ranges of the code points this face may render.
+    pub unicode_range: Vec<(u32, u32)>,
+}
+
+
+#[deriving(Clone)]
+pub enum Source {
+    UrlSource(UrlSource),
+    LocalSource(String),
+}
+
+
+#[deriving(Clone)]
+pub struct UrlSource {
+    pub url: Url,
+    /// The lowercased `format()` hints, if any. An empty list means the format is unknown.
+    pub format_hints: Vec<String>,
+}
+
+
+pub fn parse_font_face_rule(rule: AtRule, parent_rules: &mut Vec<CSSRule>, base_url: &Url) {
+    let location = rule.location;
+    if rule.prelude.as_slice().skip_whitespace().next().is_some() {
+        log_css_error(location, "@font-face rules must not have a prelude");
+        return
+    }
+    let block = match rule.block {
+        Some(block) => block,
+        None => {
+            log_css_error(location, "Invalid @font-face rule");
+            return
+        }
+    };
+
+    let mut maybe_family = None;
+    let mut maybe_sources = None;
+    let mut maybe_weight = None;
+    let mut maybe_style = None;
+    let mut maybe_unicode_range = None;
+    for item in ErrorLoggerIterator(parse_declaration_list(block.move_iter())) {
+        match item {
+            DeclAtRule(rule) => log_css_error(
+                rule.location, format!("Unsupported at-rule in @font-face: @{:s}", rule.name).as_slice()),
+            Declaration(Declaration{ location: l, name: n, value: v, important: _}) => {
+                let value = v.as_slice();
+                let valid = match n.as_slice().to_ascii_lower().as_slice() {
+                    "font-family" => set(&mut maybe_family, parse_family(value)),
+                    "src" => set(&mut maybe_sources, parse_sources(value, base_url)),
+                    "font-weight" => set(&mut maybe_weight, parse_weight(value, base_url)),
+                    "font-style" => set(&mut maybe_style, one_component_value(value).and_then(|v| {
+                        longhands::font_style::from_component_value(v, base_url)
+                    })),
+                    "unicode-range" => set(&mut maybe_unicode_range, parse_unicode_range(value)),
+                    _ => {
+                        log_css_error(l, format!("Unsupported @font-face descriptor: {}:{}",
+                                                 n, v.iter().to_css()).as_slice());
+                        continue
+                    }
+                };
+                if !valid {
+                    log_css_error(l, format!("Invalid value: {}:{}", n, v.iter().to_css()).as_slice())
+                }
+            }
+        }
+    }
+
+    match (maybe_family, maybe_sources) {
+        (Some(family), Some(sources)) => parent_rules.push(CSSFontFaceRule(FontFaceRule {
+            family: family,
+            sources: sources,
+            weight: maybe_weight.unwrap_or(font_weight::Weight400),
+            style: maybe_style.unwrap_or(font_style::normal),
+            unicode_range: maybe_unicode_range.unwrap_or(vec!((0, MAX_CODE_POINT))),
+        })),
+        _ => log_css_error(location, "@font-face rules need font-family and src descriptors"),
+    }
+}
+
+
+pub fn iter_font_face_rules(rules: &[CSSRule], device: &Device, callback: |&FontFaceRule|) {
+    for rule in rules.iter() {
+        match *rule {
+            CSSStyleRule(_) => {},
+            CSSMediaRule(ref rule) => if rule.media_queries.evaluate(device) {
+                iter_font_face_rules(rule.rules.as_slice(), device, |f| callback(f))
+            },
+            CSSFontFaceRule(ref rule) => callback(rule),
+        }
+    }
+}
+
+
+/// Stores a successfully parsed descriptor value, overriding any earlier one, and returns whether
+/// parsing succeeded.
+fn set<T>(descriptor: &mut Option<T>, value: Option<T>) -> bool {
+    match value {
+        Some(value) => {
+            *descriptor = Some(value);
+            true
+        }
+        None => false,
+    }
+}
+
+
+fn is_comma(component_value: &ComponentValue) -> bool {
+    match *component_value {
+        Comma => true,
+        _ => false,
+    }
+}
+
+
+/// <family-name>, the same as a single `font-family` value.
+fn parse_family(input: &[ComponentValue]) -> Option<String> {
+    longhands::font_family::from_iter(input.skip_whitespace()).and_then(|mut families| {
+        if families.len() != 1 {
+            return None
+        }
+        match families.pop() {
+            Some(font_family::FamilyName(name)) => Some(name),
+            None => None,
+        }
+    })
+}
+
+
+/// [ <url> [format(<string>#)]? | local(<family-name>) ]#
+fn parse_sources(input: &[ComponentValue], base_url: &Url) -> Option<Vec<Source>> {
+    let mut sources = vec!();
+    for source_values in input.split(is_comma) {
+        let mut iter = source_values.skip_whitespace();
+        let source = match iter.next() {
+            Some(&URL(ref url)) => {
+                let format_hints = match iter.next() {
+                    None => vec!(),
+                    Some(&Function(ref name, ref arguments))
+                            if name.as_slice().eq_ignore_ascii_case("format") => {
+                        match parse_format_hints(arguments.as_slice()) {
+                            Some(format_hints) => format_hints,
+                            None => return None,
+                        }
+                    }
+                    Some(_) => return None,
+                };
+                UrlSource(UrlSource {
+                    url: parse_url(url.as_slice(), Some(base_url.clone())),
+                    format_hints: format_hints,
+                })
+            }
+            Some(&Function(ref name, ref arguments))
+                    if name.as_slice().eq_ignore_ascii_case("local") => {
+                match parse_family(arguments.as_slice()) {
+                    Some(family) => LocalSource(family),
+                    None => return None,
+                }
+            }
+            _ => return None,
+        };
+        if iter.next().is_some() {
+            return None
+        }
+        sources.push(source)
+    }
+    Some(sources)
+}
+
+
+/// <string>#
+fn parse_format_hints(input: &[ComponentValue]) -> Option<Vec<String>> {
+    let mut format_hints = vec!();
+    for hint_values in input.split(is_comma) {
+        let mut iter = hint_values.skip_whitespace();
+        match (iter.next(), iter.next()) {
+            (Some(&ast::String(ref hint)), None) => {
+                format_hints.push(hint.as_slice().to_ascii_lower())
+            }
+            _ => return None,
+        }
+    }
+    Some(format_hints)
+}
+
+
+/// normal | bold | 100 | 200 | 300 | 400 | 500 | 600 | 700 | 800 | 900
+fn parse_weight(input: &[ComponentValue], base_url: &Url) -> Option<font_weight::T> {
+    one_component_value(input).and_then(|v| {
+        longhands::font_weight::from_component_value(v, base_url)
+    }).and_then(|weight| {
+        match weight {
+            longhands::font_weight::SpecifiedWeight100 => Some(font_weight::Weight100),
+            longhands::font_weight::SpecifiedWeight200 => Some(font_weight::Weight200),
+            longhands::font_weight::SpecifiedWeight300 => Some(font_weight::Weight300),
+            longhands::font_weight::SpecifiedWeight400 => Some(font_weight::Weight400),
+            longhands::font_weight::SpecifiedWeight500 => Some(font_weight::Weight500),
+            longhands::font_weight::SpecifiedWeight600 => Some(font_weight::Weight600),
+            longhands::font_weight::SpecifiedWeight700 => Some(font_weight::Weight700),
+            longhands::font_weight::SpecifiedWeight800 => Some(font_weight::Weight800),
+            longhands::font_weight::SpecifiedWeight900 => Some(font_weight::Weight900),
+            // Relative weights are meaningless without a parent element.
+            longhands::font_weight::Bolder | longhands::font_weight::Lighter => None,
+        }
+    })
+}
+
+
+/// <urange>#
+fn parse_unicode_range(input: &[ComponentValue]) -> Option<Vec<(u32, u32)>> {
+    let mut ranges = vec!();
+    for range_values in input.split(is_comma) {
+        let mut iter = range_values.skip_whitespace();
+        match (iter.next(), iter.next()) {
+            (Some(&UnicodeRange(start, end)), None) if start <= end => {
+                ranges.push((start, cmp::min(end, MAX_CODE_POINT)))
+            }
+            _ => return None,
+        }
+    }
+    Some(ranges)
+}
@@ -25,5 +25,8 @@ pub trait TElement {
     fn get_local_name<'a>(&'a self) -> &'a str;
     fn get_namespace<'a>(&'a self) -> &'a Namespace;
     fn get_hover_state(&self) -> bool;
+    fn get_focus_state(&self) -> bool;
+    fn get_valid_state(&self) -> bool;
+    fn get_invalid_state(&self) -> bool;
 }
 
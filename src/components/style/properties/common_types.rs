@@ -174,7 +174,9 @@ pub mod computed {
         pub inherited_text_decorations_in_effect: longhands::_servo_text_decorations_in_effect::T,
         pub inherited_height: longhands::height::T,
         pub color: longhands::color::computed_value::T,
-        pub text_decoration: longhands::text_decoration::computed_value::T,
+        pub text_decoration_line: longhands::text_decoration_line::computed_value::T,
+        pub text_decoration_style: longhands::text_decoration_style::computed_value::T,
+        pub text_decoration_color: longhands::text_decoration_color::computed_value::T,
         pub font_size: longhands::font_size::computed_value::T,
         pub display: longhands::display::computed_value::T,
         pub positioned: bool,
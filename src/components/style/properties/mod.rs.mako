@@ -7,6 +7,7 @@
 pub use std::ascii::StrAsciiExt;
 use serialize::{Encodable, Encoder};
 
+use servo_util::font_prefs::FontPrefs;
 pub use servo_util::url::parse_url;
 use sync::Arc;
 pub use url::Url;
@@ -294,6 +295,7 @@ pub mod longhands {
             table inline-table table-row-group table-header-group table-footer-group
             table-row table-column-group table-column table-cell table-caption
             list-item
+            flex inline-flex
             none">
         #[inline]
         pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
@@ -304,6 +306,7 @@ pub mod longhands {
             if context.positioned || context.floated || context.is_root_element {
                 match value {
                     inline_table => table,
+                    inline_flex => flex,
                     inline | inline_block
                     | table_row_group | table_column | table_column_group
                     | table_header_group | table_footer_group | table_row
@@ -325,10 +328,12 @@ pub mod longhands {
 
     ${single_keyword("direction", "ltr rtl")}
 
-    // CSS 2.1, Section 10 - Visual formatting model details
-
     ${switch_to_style_struct("Box")}
 
+    ${single_keyword("unicode-bidi", "normal embed bidi-override")}
+
+    // CSS 2.1, Section 10 - Visual formatting model details
+
     ${predefined_type("width", "LengthOrPercentageOrAuto",
                       "computed::LPA_Auto",
                       "parse_non_negative")}
@@ -529,9 +534,29 @@ pub mod longhands {
     <%self:longhand name="content">
             pub use to_computed_value = super::computed_as_specified;
             pub mod computed_value {
+                pub use url::Url;
+
+                #[allow(non_camel_case_types)]
+                #[deriving(Eq, Clone)]
+                pub enum CounterStyle {
+                    decimal,
+                    lower_alpha,
+                    upper_alpha,
+                    lower_roman,
+                    upper_roman,
+                    none,
+                }
                 #[deriving(Eq, Clone)]
                 pub enum Content {
                     StringContent(String),
+                    CounterContent(String, CounterStyle),
+                    CountersContent(String, String, CounterStyle),
+                    AttrContent(String),
+                    UrlContent(Url),
+                    OpenQuote,
+                    CloseQuote,
+                    NoOpenQuote,
+                    NoCloseQuote,
                 }
                 #[allow(non_camel_case_types)]
                 #[deriving(Eq, Clone)]
@@ -544,9 +569,9 @@ pub mod longhands {
             pub type SpecifiedValue = computed_value::T;
             #[inline] pub fn get_initial_value() -> computed_value::T  { normal }
 
-            // normal | none | [ <string> ]+
-            // TODO: <uri>, <counter>, attr(<identifier>), open-quote, close-quote, no-open-quote, no-close-quote
-            pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Option<SpecifiedValue> {
+            // normal | none | [ <string> | <uri> | <counter> | attr(<identifier>) | open-quote |
+            //                   close-quote | no-open-quote | no-close-quote ]+
+            pub fn parse(input: &[ComponentValue], base_url: &Url) -> Option<SpecifiedValue> {
                 match one_component_value(input) {
                     Some(&Ident(ref keyword)) => {
                         match keyword.as_slice().to_ascii_lower().as_slice() {
@@ -559,15 +584,193 @@ pub mod longhands {
                 }
                 let mut content = vec!();
                 for component_value in input.skip_whitespace() {
-                    match component_value {
-                        &String(ref value)
-                        => content.push(StringContent(value.clone())),
+                    let item = match component_value {
+                        &String(ref value) => StringContent(value.clone()),
+                        &URL(ref url) => UrlContent(parse_url(url.as_slice(), Some(base_url.clone()))),
+                        &Ident(ref keyword) => {
+                            match keyword.as_slice().to_ascii_lower().as_slice() {
+                                "open-quote" => OpenQuote,
+                                "close-quote" => CloseQuote,
+                                "no-open-quote" => NoOpenQuote,
+                                "no-close-quote" => NoCloseQuote,
+                                _ => return None,
+                            }
+                        }
+                        &Function(ref name, ref arguments) => {
+                            let item = match name.as_slice().to_ascii_lower().as_slice() {
+                                "counter" => parse_counter(arguments.as_slice(), false),
+                                "counters" => parse_counter(arguments.as_slice(), true),
+                                "attr" => {
+                                    match one_component_value(arguments.as_slice()) {
+                                        Some(&Ident(ref name)) => Some(AttrContent(name.clone())),
+                                        _ => None,
+                                    }
+                                }
+                                _ => None,
+                            };
+                            match item {
+                                Some(item) => item,
+                                None => return None,
+                            }
+                        }
                         _ => return None  // invalid/unsupported value
-                    }
+                    };
+                    content.push(item)
                 }
                 Some(Content(content))
             }
+
+            // counter(<identifier>[, <counter-style>]?) |
+            // counters(<identifier>, <string>[, <counter-style>]?)
+            fn parse_counter(arguments: &[ComponentValue], has_separator: bool) -> Option<Content> {
+                let mut iter = arguments.skip_whitespace();
+                let name = match iter.next() {
+                    Some(&Ident(ref name)) => name.clone(),
+                    _ => return None,
+                };
+                let separator = if has_separator {
+                    match (iter.next(), iter.next()) {
+                        (Some(&Comma), Some(&String(ref separator))) => Some(separator.clone()),
+                        _ => return None,
+                    }
+                } else {
+                    None
+                };
+                let style = match iter.next() {
+                    None => computed_value::decimal,
+                    Some(&Comma) => {
+                        let style = match iter.next() {
+                            Some(&Ident(ref style)) => {
+                                match style.as_slice().to_ascii_lower().as_slice() {
+                                    "decimal" => computed_value::decimal,
+                                    "lower-alpha" | "lower-latin" => computed_value::lower_alpha,
+                                    "upper-alpha" | "upper-latin" => computed_value::upper_alpha,
+                                    "lower-roman" => computed_value::lower_roman,
+                                    "upper-roman" => computed_value::upper_roman,
+                                    "none" => computed_value::none,
+                                    _ => return None,
+                                }
+                            }
+                            _ => return None,
+                        };
+                        if iter.next().is_some() {
+                            return None
+                        }
+                        style
+                    }
+                    Some(_) => return None,
+                };
+                match separator {
+                    Some(separator) => Some(CountersContent(name, separator, style)),
+                    None => Some(CounterContent(name, style)),
+                }
+            }
+    </%self:longhand>
+
+    ${new_style_struct("Counters", is_inherited=False)}
+
+    <%self:longhand name="counter-increment">
+        pub use to_computed_value = super::computed_as_specified;
+        pub mod computed_value {
+            pub type T = Vec<(String, int)>;
+        }
+        pub type SpecifiedValue = computed_value::T;
+        #[inline] pub fn get_initial_value() -> computed_value::T { vec!() }
+
+        // none | [ <identifier> <integer>? ]+
+        pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Option<SpecifiedValue> {
+            parse_counter_list(input, 1)
+        }
+
+        /// Parses a list of counter names, each optionally followed by an integer. Counters
+        /// without an integer get `default_value`.
+        pub fn parse_counter_list(input: &[ComponentValue], default_value: int)
+                                  -> Option<SpecifiedValue> {
+            match one_component_value(input) {
+                Some(&Ident(ref keyword)) if keyword.as_slice().eq_ignore_ascii_case("none") => {
+                    return Some(vec!())
+                }
+                _ => {}
+            }
+
+            let mut counters: Vec<(String, int)> = vec!();
+            let mut expecting_name = true;
+            for component_value in input.skip_whitespace() {
+                match component_value {
+                    &Ident(ref name) => {
+                        counters.push((name.clone(), default_value));
+                        expecting_name = false
+                    }
+                    &Number(ref value) if !expecting_name => {
+                        let int_value = match value.int_value {
+                            Some(int_value) => int_value as int,
+                            None => return None,
+                        };
+                        let (name, _) = counters.pop().unwrap();
+                        counters.push((name, int_value));
+                        expecting_name = true
+                    }
+                    _ => return None,
+                }
+            }
+            if counters.is_empty() {
+                None
+            } else {
+                Some(counters)
+            }
+        }
+    </%self:longhand>
+
+    <%self:longhand name="counter-reset">
+        pub use to_computed_value = super::computed_as_specified;
+        pub mod computed_value {
+            pub type T = Vec<(String, int)>;
+        }
+        pub type SpecifiedValue = computed_value::T;
+        #[inline] pub fn get_initial_value() -> computed_value::T { vec!() }
+
+        // none | [ <identifier> <integer>? ]+
+        pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Option<SpecifiedValue> {
+            super::counter_increment::parse_counter_list(input, 0)
+        }
+    </%self:longhand>
+
+    ${new_style_struct("List", is_inherited=True)}
+
+    <%self:longhand name="quotes">
+        pub use to_computed_value = super::computed_as_specified;
+        pub mod computed_value {
+            pub type T = Vec<(String, String)>;
+        }
+        pub type SpecifiedValue = computed_value::T;
+        #[inline] pub fn get_initial_value() -> computed_value::T {
+            vec!(("\u201c".to_string(), "\u201d".to_string()),
+                 ("\u2018".to_string(), "\u2019".to_string()))
+        }
+
+        // none | [ <string> <string> ]+
+        pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Option<SpecifiedValue> {
+            match one_component_value(input) {
+                Some(&Ident(ref keyword)) if keyword.as_slice().eq_ignore_ascii_case("none") => {
+                    return Some(vec!())
+                }
+                _ => {}
+            }
+
+            let mut quotes = vec!();
+            let mut iter = input.skip_whitespace();
+            loop {
+                match (iter.next(), iter.next()) {
+                    (Some(&String(ref open)), Some(&String(ref close))) => {
+                        quotes.push((open.clone(), close.clone()))
+                    }
+                    (None, _) if !quotes.is_empty() => return Some(quotes),
+                    _ => return None,
+                }
+            }
+        }
     </%self:longhand>
+
     // CSS 2.1, Section 13 - Paged media
 
     // CSS 2.1, Section 14 - Colors and Backgrounds
@@ -776,7 +979,7 @@ pub mod longhands {
 
 
     ${single_keyword("font-style", "normal italic oblique")}
-    ${single_keyword("font-variant", "normal")}  // Add small-caps when supported
+    ${single_keyword("font-variant", "normal small-caps")}
 
     <%self:single_component_value name="font-weight">
         #[deriving(Clone)]
@@ -894,6 +1097,67 @@ pub mod longhands {
         }
     </%self:single_component_value>
 
+    // CSS Fonts Level 3, § 6.4
+    ${single_keyword("font-kerning", "auto normal none")}
+
+    // CSS Fonts Level 3, § 6.12
+    <%self:longhand name="font-feature-settings">
+        pub use to_computed_value = super::computed_as_specified;
+        pub use self::computed_value::FeatureTagValue;
+        pub mod computed_value {
+            #[deriving(Eq, Clone)]
+            pub struct FeatureTagValue {
+                /// The OpenType feature tag: four characters in the range U+20 to U+7E.
+                pub tag: String,
+                pub value: u32,
+            }
+            /// The feature settings, in order. `normal` is the empty list.
+            pub type T = Vec<FeatureTagValue>;
+        }
+        pub type SpecifiedValue = computed_value::T;
+        #[inline] pub fn get_initial_value() -> computed_value::T { vec!() }
+        /// normal | <feature-tag-value>#
+        /// <feature-tag-value> = <string> [ <integer> | on | off ]?
+        pub fn parse(input: &[ComponentValue], _base_url: &Url) -> Option<SpecifiedValue> {
+            match one_component_value(input).and_then(get_ident_lower) {
+                Some(ref keyword) if keyword.as_slice() == "normal" => return Some(vec!()),
+                _ => {}
+            }
+            let mut result = vec!();
+            for setting in input.split(|value| match *value { Comma => true, _ => false }) {
+                let mut iter = setting.skip_whitespace();
+                let tag = match iter.next() {
+                    Some(&String(ref tag)) if tag.len() == 4 &&
+                            tag.as_slice().chars().all(|ch| ch >= '\x20' && ch <= '\x7e') => {
+                        tag.clone()
+                    }
+                    _ => return None,
+                };
+                let value = match iter.next() {
+                    None => 1,
+                    Some(&Number(ref value)) => match value.int_value {
+                        Some(value) if value >= 0 => value as u32,
+                        _ => return None,
+                    },
+                    Some(&Ident(ref value)) => match value.as_slice().to_ascii_lower().as_slice() {
+                        "on" => 1,
+                        "off" => 0,
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                if iter.next().is_some() {
+                    return None
+                }
+                result.push(FeatureTagValue {
+                    tag: tag,
+                    value: value,
+                })
+            }
+            Some(result)
+        }
+    </%self:longhand>
+
     // CSS 2.1, Section 16 - Text
 
     ${new_style_struct("InheritedText", is_inherited=True)}
@@ -903,7 +1167,8 @@ pub mod longhands {
 
     ${new_style_struct("Text", is_inherited=False)}
 
-    <%self:longhand name="text-decoration">
+    // CSS Text Decoration Level 3, § 2
+    <%self:longhand name="text-decoration-line">
         pub use to_computed_value = super::computed_as_specified;
         #[deriving(Eq, Clone)]
         pub struct SpecifiedValue {
@@ -948,20 +1213,31 @@ pub mod longhands {
         }
     </%self:longhand>
 
+    ${single_keyword("text-decoration-style", "solid double dotted dashed wavy")}
+    ${predefined_type("text-decoration-color", "CSSColor", "CurrentColor")}
+
     ${switch_to_style_struct("InheritedText")}
 
     <%self:longhand name="-servo-text-decorations-in-effect"
-                    derived_from="display text-decoration">
-        use super::RGBA;
-        use super::super::longhands::display;
+                    derived_from="display position float text-decoration-line
+                                  text-decoration-style text-decoration-color">
+        use super::{RGBA, CurrentColor};
+        use super::super::longhands::{display, float, position};
 
         pub use to_computed_value = super::computed_as_specified;
 
+        /// The color and style of a line drawn over, under or through text.
+        #[deriving(Clone, Eq)]
+        pub struct Decoration {
+            pub color: RGBA,
+            pub style: text_decoration_style::computed_value::T,
+        }
+
         #[deriving(Clone, Eq)]
         pub struct SpecifiedValue {
-            pub underline: Option<RGBA>,
-            pub overline: Option<RGBA>,
-            pub line_through: Option<RGBA>,
+            pub underline: Option<Decoration>,
+            pub overline: Option<Decoration>,
+            pub line_through: Option<Decoration>,
         }
 
         pub mod computed_value {
@@ -977,45 +1253,65 @@ pub mod longhands {
             }
         }
 
-        fn maybe(flag: bool, context: &computed::Context) -> Option<RGBA> {
+        fn maybe(flag: bool, context: &computed::Context) -> Option<Decoration> {
             if flag {
-                Some(context.color)
+                let color = match context.text_decoration_color {
+                    RGBA(rgba) => rgba,
+                    CurrentColor => context.color,
+                };
+                Some(Decoration {
+                    color: color,
+                    style: context.text_decoration_style,
+                })
             } else {
                 None
             }
         }
 
         fn derive(context: &computed::Context) -> computed_value::T {
-            // Start with no declarations if this is a block; otherwise, start with the
-            // declarations in effect and add in the text decorations that this inline specifies.
+            // Decorations propagate to in-flow children, so start with the decorations in effect
+            // and add in the ones that this box specifies. Floats, absolutely positioned boxes
+            // and atomic inlines do not take the decorations of their ancestors (CSS Text
+            // Decoration 3 § 2.1).
             let mut result = match context.display {
-                display::computed_value::inline => context.inherited_text_decorations_in_effect,
-                _ => {
-                    SpecifiedValue {
-                        underline: None,
-                        overline: None,
-                        line_through: None,
-                    }
-                }
+                display::computed_value::inline_block |
+                display::computed_value::inline_table |
+                display::computed_value::inline_flex => get_initial_value(),
+                _ if context.positioned || context.floated => get_initial_value(),
+                _ => context.inherited_text_decorations_in_effect,
             };
 
             if result.underline.is_none() {
-                result.underline = maybe(context.text_decoration.underline, context)
+                result.underline = maybe(context.text_decoration_line.underline, context)
             }
             if result.overline.is_none() {
-                result.overline = maybe(context.text_decoration.overline, context)
+                result.overline = maybe(context.text_decoration_line.overline, context)
             }
             if result.line_through.is_none() {
-                result.line_through = maybe(context.text_decoration.line_through, context)
+                result.line_through = maybe(context.text_decoration_line.line_through, context)
             }
 
             result
         }
 
         #[inline]
-        pub fn derive_from_text_decoration(_: text_decoration::computed_value::T,
-                                           context: &computed::Context)
-                                           -> computed_value::T {
+        pub fn derive_from_text_decoration_line(_: text_decoration_line::computed_value::T,
+                                                context: &computed::Context)
+                                                -> computed_value::T {
+            derive(context)
+        }
+
+        #[inline]
+        pub fn derive_from_text_decoration_style(_: text_decoration_style::computed_value::T,
+                                                 context: &computed::Context)
+                                                 -> computed_value::T {
+            derive(context)
+        }
+
+        #[inline]
+        pub fn derive_from_text_decoration_color(_: text_decoration_color::computed_value::T,
+                                                 context: &computed::Context)
+                                                 -> computed_value::T {
             derive(context)
         }
 
@@ -1024,15 +1320,182 @@ pub mod longhands {
                                    -> computed_value::T {
             derive(context)
         }
+
+        #[inline]
+        pub fn derive_from_position(_: position::computed_value::T, context: &computed::Context)
+                                    -> computed_value::T {
+            derive(context)
+        }
+
+        #[inline]
+        pub fn derive_from_float(_: float::computed_value::T, context: &computed::Context)
+                                 -> computed_value::T {
+            derive(context)
+        }
     </%self:longhand>
 
     ${single_keyword("white-space", "normal pre")}
 
+    // CSS Text Level 3, § 5.2 and § 6.2
+    ${single_keyword("word-break", "normal break-all keep-all")}
+    ${single_keyword("overflow-wrap", "normal break-word")}
+
+    // CSS Text Level 3, § 2.1 and § 9.1
+    ${single_keyword("text-transform", "none capitalize uppercase lowercase full-width")}
+    ${predefined_type("text-indent", "LengthOrPercentage", "computed::LP_Length(Au(0))")}
+
+    // CSS Text Level 3, § 8.1 and § 8.2
+    % for property in ["word-spacing", "letter-spacing"]:
+        <%self:single_component_value name="${property}">
+            pub type SpecifiedValue = Option<specified::Length>;
+            pub mod computed_value {
+                use super::super::Au;
+                pub type T = Option<Au>;
+            }
+            #[inline]
+            pub fn get_initial_value() -> computed_value::T {
+                None  // normal
+            }
+            #[inline]
+            pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                     -> computed_value::T {
+                value.map(|value| computed::compute_Au(value, context))
+            }
+            /// normal | <length>
+            pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                        -> Option<SpecifiedValue> {
+                match input {
+                    &Ident(ref value) if value.as_slice().eq_ignore_ascii_case("normal") => {
+                        Some(None)
+                    }
+                    _ => specified::Length::parse(input).map(|value| Some(value)),
+                }
+            }
+        </%self:single_component_value>
+    % endfor
+
     // CSS 2.1, Section 17 - Tables
     ${new_style_struct("Table", is_inherited=False)}
 
     ${single_keyword("table-layout", "auto fixed")}
 
+    ${new_style_struct("InheritedTable", is_inherited=True)}
+
+    ${single_keyword("border-collapse", "separate collapse")}
+
+    <%self:longhand name="border-spacing">
+        use super::super::common_types::specified;
+
+        pub mod computed_value {
+            use super::super::Au;
+
+            #[deriving(Eq, Clone)]
+            pub struct T {
+                pub horizontal: Au,
+                pub vertical: Au,
+            }
+        }
+
+        #[deriving(Clone)]
+        pub struct SpecifiedValue {
+            pub horizontal: specified::Length,
+            pub vertical: specified::Length,
+        }
+
+        #[inline]
+        pub fn to_computed_value(value: SpecifiedValue, context: &computed::Context)
+                                 -> computed_value::T {
+            computed_value::T {
+                horizontal: computed::compute_Au(value.horizontal, context),
+                vertical: computed::compute_Au(value.vertical, context),
+            }
+        }
+
+        #[inline]
+        pub fn get_initial_value() -> computed_value::T {
+            computed_value::T {
+                horizontal: Au(0),
+                vertical: Au(0),
+            }
+        }
+
+        /// Parses one or two non-negative lengths. A single length applies to both directions.
+        pub fn parse(input: &[ComponentValue], _: &Url) -> Option<SpecifiedValue> {
+            let mut input_iter = input.skip_whitespace();
+            let horizontal = match input_iter.next() {
+                Some(value) => specified::Length::parse_non_negative(value),
+                None => None,
+            };
+            let vertical = match input_iter.next() {
+                Some(value) => specified::Length::parse_non_negative(value),
+                None => horizontal,
+            };
+            if input_iter.next().is_some() {
+                return None
+            }
+
+            match (horizontal, vertical) {
+                (Some(horizontal), Some(vertical)) => {
+                    Some(SpecifiedValue {
+                        horizontal: horizontal,
+                        vertical: vertical,
+                    })
+                }
+                _ => None
+            }
+        }
+    </%self:longhand>
+
+    // CSS Flexible Box Layout Module Level 1
+    ${new_style_struct("Flex", is_inherited=False)}
+
+    ${single_keyword("flex-direction", "row row-reverse column column-reverse")}
+    ${single_keyword("flex-wrap", "nowrap wrap wrap-reverse")}
+    ${single_keyword("justify-content", "flex-start flex-end center space-between space-around")}
+    ${single_keyword("align-items", "stretch flex-start flex-end center baseline")}
+    ${single_keyword("align-self", "auto stretch flex-start flex-end center baseline")}
+
+    % for name, initial_value in [("flex-grow", "0."), ("flex-shrink", "1.")]:
+        <%self:single_component_value name="${name}">
+            pub use to_computed_value = super::computed_as_specified;
+            pub type SpecifiedValue = computed_value::T;
+            pub mod computed_value {
+                use super::super::CSSFloat;
+                pub type T = CSSFloat;
+            }
+            #[inline] pub fn get_initial_value() -> computed_value::T { ${initial_value} }
+            /// <number>, which must not be negative
+            pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                        -> Option<SpecifiedValue> {
+                match input {
+                    &Number(ref value) if value.value >= 0. => Some(value.value),
+                    _ => None,
+                }
+            }
+        </%self:single_component_value>
+    % endfor
+
+    ${predefined_type("flex-basis", "LengthOrPercentageOrAuto",
+                      "computed::LPA_Auto",
+                      "parse_non_negative")}
+
+    <%self:single_component_value name="order">
+        pub use to_computed_value = super::computed_as_specified;
+        pub type SpecifiedValue = computed_value::T;
+        pub mod computed_value {
+            pub type T = int;
+        }
+        #[inline] pub fn get_initial_value() -> computed_value::T { 0 }
+        /// <integer>
+        pub fn from_component_value(input: &ComponentValue, _base_url: &Url)
+                                    -> Option<SpecifiedValue> {
+            match input {
+                &Number(ref value) => value.int_value.map(|value| value as int),
+                _ => None,
+            }
+        }
+    </%self:single_component_value>
+
     // CSS 2.1, Section 18 - User interface
 }
 
@@ -1332,6 +1795,101 @@ pub mod shorthands {
         })
     </%self:shorthand>
 
+    <%self:shorthand name="text-decoration" sub_properties="text-decoration-line
+                                                            text-decoration-style
+                                                            text-decoration-color">
+        // <text-decoration-line> || <text-decoration-style> || <text-decoration-color>
+        let mut lines = vec!();
+        let mut style = None;
+        let mut color = None;
+        for component_value in input.skip_whitespace() {
+            if style.is_none() {
+                match text_decoration_style::from_component_value(component_value, base_url) {
+                    Some(s) => { style = Some(s); continue }
+                    None => ()
+                }
+            }
+            if color.is_none() {
+                match specified::CSSColor::parse(component_value) {
+                    Some(c) => { color = Some(c); continue }
+                    None => ()
+                }
+            }
+            lines.push(component_value.clone())
+        }
+        let line = if lines.is_empty() {
+            None
+        } else {
+            match text_decoration_line::parse(lines.as_slice(), base_url) {
+                Some(line) => Some(line),
+                None => return None,
+            }
+        };
+        if line.is_none() && style.is_none() && color.is_none() {
+            return None
+        }
+        Some(Longhands {
+            text_decoration_line: line,
+            text_decoration_style: style,
+            text_decoration_color: color,
+        })
+    </%self:shorthand>
+
+    <%self:shorthand name="flex" sub_properties="flex-grow flex-shrink flex-basis">
+        // none | [ <'flex-grow'> <'flex-shrink'>? || <'flex-basis'> ]
+        let mut iter = input.skip_whitespace().peekable();
+        let is_none = match iter.peek() {
+            Some(component_value) => match get_ident_lower(*component_value) {
+                Some(ref ident) => ident.as_slice() == "none",
+                None => false,
+            },
+            None => return None,
+        };
+        if is_none {
+            drop(iter.next());
+            if iter.next().is_some() { return None }
+            return Some(Longhands {
+                flex_grow: Some(0.),
+                flex_shrink: Some(0.),
+                flex_basis: Some(specified::LPA_Auto),
+            })
+        }
+
+        let mut grow = None;
+        let mut shrink = None;
+        let mut basis = None;
+        let mut last_was_grow = false;
+        for component_value in iter {
+            if grow.is_none() {
+                match flex_grow::from_component_value(component_value, base_url) {
+                    Some(value) => { grow = Some(value); last_was_grow = true; continue },
+                    None => ()
+                }
+            }
+            if last_was_grow && shrink.is_none() {
+                match flex_shrink::from_component_value(component_value, base_url) {
+                    Some(value) => { shrink = Some(value); last_was_grow = false; continue },
+                    None => ()
+                }
+            }
+            last_was_grow = false;
+            if basis.is_none() {
+                match flex_basis::from_component_value(component_value, base_url) {
+                    Some(value) => { basis = Some(value); continue },
+                    None => ()
+                }
+            }
+            return None
+        }
+
+        // Omitted components are 1 for `flex-grow` and `flex-shrink` and 0% for `flex-basis`.
+        Some(Longhands {
+            flex_grow: Some(grow.unwrap_or(1.)),
+            flex_shrink: Some(shrink.unwrap_or(1.)),
+            flex_basis: Some(basis.unwrap_or(specified::LPA_Percentage(0.))),
+        })
+    </%self:shorthand>
+
 }
 
 
@@ -1711,6 +2269,11 @@ fn cascade_with_cached_declarations(applicable_declarations: &[MatchedProperty],
         }
     }
 
+    // `font-size: initial` and the minimum font size are resolved in the context.
+    if style_font.font_size != context.font_size {
+        style_font.make_unique_experimental().font_size = context.font_size;
+    }
+
     ComputedValues {
         % for style_struct in STYLE_STRUCTS:
             ${style_struct.ident}: style_${style_struct.ident},
@@ -1734,11 +2297,14 @@ fn cascade_with_cached_declarations(applicable_declarations: &[MatchedProperty],
 ///     this that it is safe to only provide inherited declarations. If `parent_style` is `None`,
 ///     this is ignored.
 ///
+///   * `font_prefs`: The font preferences, which give the initial and minimum font sizes.
+///
 /// Returns the computed values and a boolean indicating whether the result is cacheable.
 pub fn cascade(applicable_declarations: &[MatchedProperty],
                shareable: bool,
                parent_style: Option< &ComputedValues >,
-               cached_style: Option< &ComputedValues >)
+               cached_style: Option< &ComputedValues >,
+               font_prefs: &FontPrefs)
                -> (ComputedValues, bool) {
     let initial_values = &*INITIAL_VALUES;
     let (is_root_element, inherited_style) = match parent_style {
@@ -1748,20 +2314,27 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
 
     let mut context = {
         let inherited_font_style = inherited_style.get_font();
+        let inherited_font_size = if is_root_element {
+            font_prefs.default_font_size
+        } else {
+            inherited_font_style.font_size
+        };
         computed::Context {
             is_root_element: is_root_element,
             inherited_font_weight: inherited_font_style.font_weight,
-            inherited_font_size: inherited_font_style.font_size,
+            inherited_font_size: inherited_font_size,
             inherited_height: inherited_style.get_box().height,
             inherited_minimum_line_height: inherited_style.get_inheritedbox()
                                                           ._servo_minimum_line_height,
             inherited_text_decorations_in_effect:
                 inherited_style.get_inheritedtext()._servo_text_decorations_in_effect,
             // To be overridden by applicable declarations:
-            font_size: inherited_font_style.font_size,
+            font_size: inherited_font_size,
             display: longhands::display::get_initial_value(),
             color: inherited_style.get_color().color,
-            text_decoration: longhands::text_decoration::get_initial_value(),
+            text_decoration_line: longhands::text_decoration_line::get_initial_value(),
+            text_decoration_style: longhands::text_decoration_style::get_initial_value(),
+            text_decoration_color: longhands::text_decoration_color::get_initial_value(),
             positioned: false,
             floated: false,
             border_top_present: false,
@@ -1792,7 +2365,7 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
                     context.font_size = match *value {
                         SpecifiedValue(specified_value) => computed::compute_Au_with_font_size(
                             specified_value, context.inherited_font_size),
-                        CSSWideKeyword(Initial) => longhands::font_size::get_initial_value(),
+                        CSSWideKeyword(Initial) => font_prefs.default_font_size,
                         CSSWideKeyword(Inherit) => context.inherited_font_size,
                     }
                 }
@@ -1812,8 +2385,20 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
                     context.floated = get_specified!(get_box, float, value)
                                       != longhands::float::none;
                 }
-                TextDecorationDeclaration(ref value) => {
-                    context.text_decoration = get_specified!(get_text, text_decoration, value);
+                TextDecorationLineDeclaration(ref value) => {
+                    context.text_decoration_line = get_specified!(get_text,
+                                                                  text_decoration_line,
+                                                                  value);
+                }
+                TextDecorationStyleDeclaration(ref value) => {
+                    context.text_decoration_style = get_specified!(get_text,
+                                                                   text_decoration_style,
+                                                                   value);
+                }
+                TextDecorationColorDeclaration(ref value) => {
+                    context.text_decoration_color = get_specified!(get_text,
+                                                                   text_decoration_color,
+                                                                   value);
                 }
                 % for side in ["top", "right", "bottom", "left"]:
                     Border${side.capitalize()}StyleDeclaration(ref value) => {
@@ -1830,6 +2415,10 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
         }
     }
 
+    if context.font_size < font_prefs.minimum_font_size {
+        context.font_size = font_prefs.minimum_font_size
+    }
+
     match (cached_style, parent_style) {
         (Some(cached_style), Some(parent_style)) => {
             return (cascade_with_cached_declarations(applicable_declarations,
@@ -1923,6 +2512,11 @@ pub fn cascade(applicable_declarations: &[MatchedProperty],
         % endfor
     }
 
+    // `font-size: initial` and the minimum font size are resolved in the context.
+    if style_font.font_size != context.font_size {
+        style_font.make_unique_experimental().font_size = context.font_size;
+    }
+
     // The initial value of display may be changed at computed value time.
     if !seen.get_display() {
         let box_ = style_box_.make_unique_experimental();
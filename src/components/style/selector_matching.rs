@@ -767,6 +767,21 @@ fn matches_simple_selector<E:TElement,
             let elem = element.as_element();
             elem.get_hover_state()
         },
+        Focus => {
+            *shareable = false;
+            let elem = element.as_element();
+            elem.get_focus_state()
+        },
+        Valid => {
+            *shareable = false;
+            let elem = element.as_element();
+            elem.get_valid_state()
+        },
+        Invalid => {
+            *shareable = false;
+            let elem = element.as_element();
+            elem.get_invalid_state()
+        },
         FirstChild => {
             *shareable = false;
             matches_first_child(element)
@@ -76,6 +76,9 @@ pub enum SimpleSelector {
     Link,
     Visited,
     Hover,
+    Focus,
+    Valid,
+    Invalid,
     FirstChild, LastChild, OnlyChild,
 //    Empty,
     Root,
@@ -217,7 +220,7 @@ fn compute_specificity(mut selector: &CompoundSelector,
                 &ClassSelector(..)
                 | &AttrExists(..) | &AttrEqual(..) | &AttrIncludes(..) | &AttrDashMatch(..)
                 | &AttrPrefixMatch(..) | &AttrSubstringMatch(..) | &AttrSuffixMatch(..)
-                | &AnyLink | &Link | &Visited | &Hover
+                | &AnyLink | &Link | &Visited | &Hover | &Focus | &Valid | &Invalid
                 | &FirstChild | &LastChild | &OnlyChild | &Root
 //                | &Empty | &Lang(*)
                 | &NthChild(..) | &NthLastChild(..)
@@ -478,6 +481,9 @@ fn parse_simple_pseudo_class(name: &str) -> Option<SimpleSelector> {
         "link" => Some(Link),
         "visited" => Some(Visited),
         "hover" => Some(Hover),
+        "focus" => Some(Focus),
+        "valid" => Some(Valid),
+        "invalid" => Some(Invalid),
         "first-child" => Some(FirstChild),
         "last-child"  => Some(LastChild),
         "only-child"  => Some(OnlyChild),
@@ -46,6 +46,8 @@ pub use selectors::{NamespaceConstraint, Selector, CompoundSelector, SimpleSelec
 pub use selectors::{parse_selector_list};
 pub use namespaces::NamespaceMap;
 pub use media_queries::{MediaRule, MediaQueryList, MediaQuery, Device, MediaType, MediaQueryType};
+pub use media_queries::{Screen, Print};
+pub use font_face::{FontFaceRule, Source, UrlSource, LocalSource, iter_font_face_rules};
 
 mod stylesheets;
 mod errors;
@@ -55,4 +57,5 @@ mod properties;
 mod namespaces;
 mod node;
 mod media_queries;
+mod font_face;
 mod parsing_utils;
@@ -3,6 +3,7 @@
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
 use collections::HashMap;
+use collections::treemap::TreeMap;
 use rand;
 use rand::Rng;
 use std::hash::{Hash, sip};
@@ -177,6 +178,85 @@ impl<K: Clone + Eq, V: Clone> Cache<K,V> for LRUCache<K,V> {
     }
 }
 
+/// A least-recently-used cache that finds entries by hashing their keys, for caches that are too
+/// large to search linearly like `LRUCache`.
+pub struct HashLRUCache<K, V> {
+    /// The entries, along with when each was last used.
+    entries: HashMap<K, (V, u64)>,
+    /// The keys of the entries, ordered by when they were last used.
+    recency: TreeMap<u64, K>,
+    next_use: u64,
+    cache_size: uint,
+}
+
+impl<K: Clone + Eq + TotalEq + Hash, V: Clone> HashLRUCache<K,V> {
+    pub fn new(size: uint) -> HashLRUCache<K, V> {
+        HashLRUCache {
+            entries: HashMap::new(),
+            recency: TreeMap::new(),
+            next_use: 0,
+            cache_size: size,
+        }
+    }
+
+    /// Marks the entry for the given key, which must be in the cache, as the most recently used
+    /// and returns its value.
+    fn touch(&mut self, key: &K) -> V {
+        let next_use = self.next_use;
+        self.next_use += 1;
+        let (ref value, ref mut last_use) = *self.entries.get_mut(key);
+        self.recency.remove(last_use);
+        self.recency.insert(next_use, key.clone());
+        *last_use = next_use;
+        value.clone()
+    }
+}
+
+impl<K: Clone + Eq + TotalEq + Hash, V: Clone> Cache<K,V> for HashLRUCache<K,V> {
+    fn insert(&mut self, key: K, value: V) {
+        match self.entries.pop(&key) {
+            Some((_, last_use)) => {
+                self.recency.remove(&last_use);
+            }
+            None if self.entries.len() == self.cache_size => {
+                let (oldest_use, oldest_key) = match self.recency.iter().next() {
+                    Some((&oldest_use, oldest_key)) => (oldest_use, oldest_key.clone()),
+                    None => return,
+                };
+                self.recency.remove(&oldest_use);
+                self.entries.remove(&oldest_key);
+            }
+            None => {}
+        }
+        let next_use = self.next_use;
+        self.next_use += 1;
+        self.recency.insert(next_use, key.clone());
+        self.entries.insert(key, (value, next_use));
+    }
+
+    fn find(&mut self, key: &K) -> Option<V> {
+        if !self.entries.contains_key(key) {
+            return None
+        }
+        Some(self.touch(key))
+    }
+
+    fn find_or_create(&mut self, key: &K, blk: |&K| -> V) -> V {
+        match self.find(key) {
+            Some(value) => return value,
+            None => {}
+        }
+        let value = blk(key);
+        self.insert(key.clone(), value.clone());
+        value
+    }
+
+    fn evict_all(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
 pub struct SimpleHashCache<K,V> {
     entries: Vec<Option<(K,V)>>,
     k0: u64,
@@ -277,3 +357,31 @@ fn test_lru_cache() {
     assert!(cache.find(&3).is_none()); // (4, 1) (no change)
     assert!(cache.find(&4).is_some()); // (1, 4)
 }
+
+#[test]
+fn test_hash_lru_cache() {
+    let mut cache = HashLRUCache::new(2);
+    cache.insert(1, "one");     // (1, _)
+    cache.insert(2, "two");     // (1, 2)
+    cache.insert(3, "three");   // (2, 3)
+
+    assert!(cache.find(&1).is_none());
+    assert!(cache.find(&2).is_some());  // (3, 2)
+
+    // Inserting an entry that is already cached replaces its value and marks it as used.
+    cache.insert(3, "THREE");   // (2, 3)
+    assert_eq!(cache.find(&3), Some("THREE"));
+
+    // An entry that keeps being used survives any number of inserts past the capacity.
+    for i in range(4, 100) {
+        assert!(cache.find(&2).is_some());
+        cache.insert(i, "many");
+    }
+    assert!(cache.find(&2).is_some());
+    assert!(cache.find(&98).is_none());
+    assert_eq!(cache.find(&99), Some("many"));
+
+    cache.evict_all();
+    assert!(cache.find(&2).is_none());
+    assert!(cache.find(&99).is_none());
+}
@@ -0,0 +1,227 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Font preferences, optionally read from a font configuration file (`--font-config`).
+//!
+//! The file is a JSON object in which every member is optional:
+//!
+//! ```json
+//! {
+//!     "generic-families": { "serif": "DejaVu Serif", "sans-serif": "DejaVu Sans" },
+//!     "generic-families-by-language": { "ja": { "sans-serif": "IPAGothic" } },
+//!     "last-resort-families": [ "DejaVu Sans" ],
+//!     "default-font-size": 16,
+//!     "minimum-font-size": 9,
+//!     "font-directories": [ "/opt/kiosk/fonts" ]
+//! }
+//! ```
+
+use geometry::Au;
+
+use collections::HashMap;
+use serialize::json;
+use serialize::json::Json;
+use std::ascii::StrAsciiExt;
+use std::io::File;
+
+/// The generic family names of CSS 2.1 § 15.3.1.
+static GENERIC_FAMILIES: &'static [&'static str] = &[
+    "serif", "sans-serif", "cursive", "fantasy", "monospace"
+];
+
+#[deriving(Clone)]
+pub struct FontPrefs {
+    /// The families that the generic families map to, unless the language of the text has its own
+    /// mapping.
+    pub generic_families: HashMap<String, String>,
+    /// The generic family mappings for text in particular languages, keyed by lowercase language
+    /// tag.
+    pub generic_families_by_language: HashMap<String, HashMap<String, String>>,
+    /// Families to try, in order, before the last resort families of the platform.
+    pub last_resort_families: Vec<String>,
+    /// The initial value of `font-size`.
+    pub default_font_size: Au,
+    /// The smallest computed `font-size`.
+    pub minimum_font_size: Au,
+    /// Directories of fonts to make available in addition to the installed ones.
+    pub font_directories: Vec<Path>,
+}
+
+impl FontPrefs {
+    /// Returns the preferences used without a font configuration file.
+    pub fn new() -> FontPrefs {
+        let mut generic_families = HashMap::with_capacity(GENERIC_FAMILIES.len());
+        generic_families.insert("serif".to_string(), "Times New Roman".to_string());
+        generic_families.insert("sans-serif".to_string(), "Arial".to_string());
+        generic_families.insert("cursive".to_string(), "Apple Chancery".to_string());
+        generic_families.insert("fantasy".to_string(), "Papyrus".to_string());
+        generic_families.insert("monospace".to_string(), "Menlo".to_string());
+
+        FontPrefs {
+            generic_families: generic_families,
+            generic_families_by_language: HashMap::new(),
+            last_resort_families: vec!(),
+            default_font_size: Au::from_px(16),
+            minimum_font_size: Au(0),
+            font_directories: vec!(),
+        }
+    }
+
+    /// Reads the preferences from the font configuration file at the given path. Preferences that
+    /// the file does not set keep their default values.
+    pub fn from_file(path: &Path) -> Result<FontPrefs, String> {
+        let contents = match File::open(path).read_to_str() {
+            Ok(contents) => contents,
+            Err(error) => return Err(format!("couldn't read {}: {}", path.display(), error)),
+        };
+        FontPrefs::from_json(contents.as_slice())
+    }
+
+    /// Parses the preferences from the contents of a font configuration file.
+    pub fn from_json(contents: &str) -> Result<FontPrefs, String> {
+        let json = match json::from_str(contents) {
+            Ok(json) => json,
+            Err(error) => return Err(format!("invalid font configuration: {}", error)),
+        };
+        let members = match json.as_object() {
+            Some(members) => members,
+            None => return Err("the font configuration must be an object".to_string()),
+        };
+
+        let mut prefs = FontPrefs::new();
+        for (name, value) in members.iter() {
+            match name.as_slice() {
+                "generic-families" => {
+                    let families = try!(parse_generic_families(name.as_slice(), value));
+                    for (generic, family) in families.move_iter() {
+                        prefs.generic_families.insert(generic, family);
+                    }
+                }
+                "generic-families-by-language" => {
+                    let languages = match value.as_object() {
+                        Some(languages) => languages,
+                        None => return Err(format!("`{}` must be an object", name)),
+                    };
+                    for (language, value) in languages.iter() {
+                        let families = try!(parse_generic_families(language.as_slice(), value));
+                        prefs.generic_families_by_language.insert(language.as_slice()
+                                                                          .to_ascii_lower(),
+                                                                  families);
+                    }
+                }
+                "last-resort-families" => {
+                    prefs.last_resort_families = try!(parse_string_list(name.as_slice(), value))
+                }
+                "default-font-size" => {
+                    prefs.default_font_size = try!(parse_font_size(name.as_slice(), value))
+                }
+                "minimum-font-size" => {
+                    prefs.minimum_font_size = try!(parse_font_size(name.as_slice(), value))
+                }
+                "font-directories" => {
+                    let directories = try!(parse_string_list(name.as_slice(), value));
+                    prefs.font_directories = directories.iter().map(|directory| {
+                        Path::new(directory.as_slice())
+                    }).collect()
+                }
+                _ => return Err(format!("unknown font configuration setting `{}`", name)),
+            }
+        }
+        Ok(prefs)
+    }
+
+    /// Returns the family that the given generic family maps to for text in the given language,
+    /// or `None` if the family is not generic. The most specific mapping for the language wins:
+    /// for `zh-Hant-TW`, mappings for `zh-hant-tw`, `zh-hant` and `zh` are tried in that order
+    /// before the mappings for all languages.
+    pub fn generic_family(&self, family: &str, language: Option<&str>) -> Option<String> {
+        let family = family.to_string();
+        let mut tag = language.map(|language| language.to_ascii_lower());
+        loop {
+            tag = match tag {
+                None => break,
+                Some(tag) => {
+                    let by_language = self.generic_families_by_language.find(&tag);
+                    match by_language.and_then(|families| families.find(&family)) {
+                        Some(mapped_family) => return Some(mapped_family.clone()),
+                        None => {}
+                    }
+                    tag.as_slice().rfind('-').map(|end| tag.as_slice().slice_to(end).to_string())
+                }
+            }
+        }
+        self.generic_families.find(&family).map(|mapped_family| mapped_family.clone())
+    }
+}
+
+/// Parses an object that maps generic family names to family names.
+fn parse_generic_families(name: &str, value: &Json) -> Result<HashMap<String, String>, String> {
+    let members = match value.as_object() {
+        Some(members) => members,
+        None => return Err(format!("`{}` must be an object", name)),
+    };
+    let mut families = HashMap::new();
+    for (generic, family) in members.iter() {
+        if !GENERIC_FAMILIES.contains(&generic.as_slice()) {
+            return Err(format!("`{}` is not a generic font family", generic))
+        }
+        match family.as_string() {
+            Some(family) => families.insert(generic.clone(), family.to_string()),
+            None => return Err(format!("the family for `{}` must be a string", generic)),
+        };
+    }
+    Ok(families)
+}
+
+fn parse_string_list(name: &str, value: &Json) -> Result<Vec<String>, String> {
+    let error = format!("`{}` must be a list of strings", name);
+    let values = match value.as_list() {
+        Some(values) => values,
+        None => return Err(error),
+    };
+    let mut strings = vec!();
+    for value in values.iter() {
+        match value.as_string() {
+            Some(string) => strings.push(string.to_string()),
+            None => return Err(error),
+        }
+    }
+    Ok(strings)
+}
+
+/// Parses a font size in px.
+fn parse_font_size(name: &str, value: &Json) -> Result<Au, String> {
+    match value.as_number() {
+        Some(px) if px >= 0.0 => Ok(Au::from_frac_px(px)),
+        _ => Err(format!("`{}` must be a nonnegative number of px", name)),
+    }
+}
+
+#[test]
+fn test_font_prefs_from_json() {
+    let prefs = FontPrefs::from_json(r#"{
+        "generic-families": { "serif": "DejaVu Serif" },
+        "generic-families-by-language": { "ja": { "serif": "IPAMincho" } },
+        "last-resort-families": [ "DejaVu Sans" ],
+        "minimum-font-size": 9.5,
+        "font-directories": [ "/opt/fonts" ]
+    }"#).unwrap();
+    assert_eq!(prefs.generic_family("serif", None), Some("DejaVu Serif".to_string()));
+    assert_eq!(prefs.generic_family("serif", Some("ja-JP")), Some("IPAMincho".to_string()));
+    assert_eq!(prefs.generic_family("serif", Some("ko")), Some("DejaVu Serif".to_string()));
+    assert_eq!(prefs.generic_family("sans-serif", Some("ja")), Some("Arial".to_string()));
+    assert_eq!(prefs.generic_family("DejaVu Serif", None), None);
+    assert_eq!(prefs.last_resort_families, vec!("DejaVu Sans".to_string()));
+    assert_eq!(prefs.default_font_size, Au::from_px(16));
+    assert_eq!(prefs.minimum_font_size, Au::from_frac_px(9.5));
+    assert_eq!(prefs.font_directories, vec!(Path::new("/opt/fonts")));
+}
+
+#[test]
+fn test_font_prefs_errors() {
+    assert!(FontPrefs::from_json("[]").is_err());
+    assert!(FontPrefs::from_json(r#"{ "generic-families": { "sans": "Arial" } }"#).is_err());
+    assert!(FontPrefs::from_json(r#"{ "default-font-size": -1 }"#).is_err());
+    assert!(FontPrefs::from_json(r#"{ "font-size": 12 }"#).is_err());
+}
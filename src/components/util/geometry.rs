@@ -33,12 +33,19 @@ pub enum DevicePixel {}
 /// `servo::windowing::WindowMethods::hidpi_factor`.
 pub enum ScreenPx {}
 
-/// One CSS "px" in the root coordinate system for the content document.
+/// One CSS "px" in the coordinate system of the layout viewport.
 ///
+/// ViewportPx is equal to ScreenPx multiplied by a "page zoom" factor controlled by the user.
+/// This is the desktop-style zoom that enlarges content and then reflows it, so that the layout
+/// viewport still exactly fits the window.  Page zoom levels are kept per origin.
+pub enum ViewportPx {}
+
+/// One CSS "px" in the root coordinate system for the content document.
 ///
-/// PagePx is equal to ScreenPx multiplied by a "zoom" factor controlled by the user.  At the
-/// default zoom level of 100%, one PagePx is equal to one ScreenPx.  However, if the document
-/// is zoomed in or out then this scale may be larger or smaller.
+/// PagePx is equal to ViewportPx multiplied by a "viewport zoom" factor controlled by the user.
+/// This is the mobile-style "pinch zoom" that enlarges content without reflowing it.  At the
+/// default zoom level of 100%, one PagePx is equal to one ViewportPx.  However, if the document
+/// is pinch zoomed then only part of the layout viewport is visible in the window.
 pub enum PagePx {}
 
 // An Au is an "App Unit" and represents 1/60th of a CSS pixel.  It was
@@ -46,7 +53,7 @@ pub enum PagePx {}
 // See https://bugzilla.mozilla.org/show_bug.cgi?id=177805 for more info.
 //
 // FIXME: Implement Au using Length and ScaleFactor instead of a custom type.
-#[deriving(Clone, Eq, Ord, Zero)]
+#[deriving(Clone, Eq, TotalEq, Hash, Ord, Zero)]
 pub struct Au(pub i32);
 
 impl Default for Au {
@@ -5,11 +5,14 @@
 //! Configuration options for a single run of the servo application. Created
 //! from command line arguments.
 
+use font_prefs::FontPrefs;
 use geometry::{DevicePixel, ScreenPx};
 
 use azure::azure_hl::{BackendType, CairoBackend, CoreGraphicsBackend};
 use azure::azure_hl::{CoreGraphicsAcceleratedBackend, Direct2DBackend, SkiaBackend};
+use geom::point::Point2D;
 use geom::scale_factor::ScaleFactor;
+use geom::size::TypedSize2D;
 use getopts;
 use std::cmp;
 use std::io;
@@ -37,6 +40,10 @@ pub struct Opts {
     /// The maximum size of each tile in pixels (`-s`).
     pub tile_size: uint,
 
+    /// The size of the window, or of the window that the headless compositor pretends to have,
+    /// in px when Servo starts (`--resolution`).
+    pub initial_window_size: TypedSize2D<ScreenPx, uint>,
+
     /// The ratio of device pixels per px at the default scale. If unspecified, will use the
     /// platform default setting.
     pub device_pixels_per_px: Option<ScaleFactor<ScreenPx, DevicePixel, f32>>,
@@ -63,7 +70,23 @@ pub struct Opts {
     pub bubble_widths_separately: bool,
 
     /// Use native threads instead of green threads
-    pub native_threading: bool
+    pub native_threading: bool,
+
+    /// The font preferences, read from the font configuration file given with `--font-config`.
+    pub font_prefs: FontPrefs,
+
+    /// Text that the headless compositor types into the page once it has loaded and after
+    /// clicking any points (`--send-keys`). This lets tests exercise keyboard input.
+    pub send_keys: Option<String>,
+
+    /// Points, in page coordinates, that the headless compositor clicks in order once the page
+    /// has loaded (`--send-click`). This lets tests exercise hit testing and focus elements to
+    /// type into.
+    pub send_clicks: Vec<Point2D<f32>>,
+
+    /// The directory that `localStorage` is kept in (`--profile-dir`). Without one, it is lost
+    /// when Servo exits.
+    pub profile_dir: Option<String>,
 }
 
 fn print_usage(app: &str, opts: &[getopts::OptGroup]) {
@@ -86,6 +109,7 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         getopts::optopt("r", "rendering", "Rendering backend", "direct2d|core-graphics|core-graphics-accelerated|cairo|skia."),
         getopts::optopt("s", "size", "Size of tiles", "512"),
         getopts::optopt("", "device-pixel-ratio", "Device pixels per px", ""),
+        getopts::optopt("", "resolution", "Initial window size", "800x600"),
         getopts::optflagopt("p", "profile", "Profiler flag and output interval", "10"),
         getopts::optflag("x", "exit", "Exit after load flag"),
         getopts::optopt("t", "threads", "Number of render threads", "[n-cores]"),
@@ -94,6 +118,10 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         getopts::optflag("f", "hard-fail", "Exit on task failure instead of displaying about:failure"),
         getopts::optflag("b", "bubble-widths", "Bubble intrinsic widths separately like other engines"),
         getopts::optflag("n", "native-threading", "Use native threading instead of green threading"),
+        getopts::optopt("", "font-config", "Font configuration file", "fonts.json"),
+        getopts::optopt("", "send-keys", "Text to type into the page after it loads", "text"),
+        getopts::optmulti("", "send-click", "Point to click in the page after it loads", "x,y"),
+        getopts::optopt("", "profile-dir", "Directory to keep local storage in", "profile"),
         getopts::optflag("h", "help", "Print this message")
     ];
 
@@ -175,12 +203,56 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
 
     let native_threading = opt_match.opt_present("h") || opt_match.opt_present("help");
 
+    let font_prefs = match opt_match.opt_str("font-config") {
+        Some(font_config_str) => {
+            match FontPrefs::from_file(&Path::new(font_config_str.as_slice())) {
+                Ok(font_prefs) => font_prefs,
+                Err(error) => {
+                    args_fail(error.as_slice());
+                    return None;
+                }
+            }
+        }
+        None => FontPrefs::new(),
+    };
+
+    let initial_window_size = match opt_match.opt_str("resolution") {
+        Some(resolution) => {
+            let dimensions: Vec<Option<uint>> = resolution.as_slice().split('x').map(|dimension| {
+                from_str(dimension.trim())
+            }).collect();
+            match dimensions.as_slice() {
+                [Some(width), Some(height)] => TypedSize2D(width, height),
+                _ => {
+                    args_fail(format!("invalid resolution: {}", resolution).as_slice());
+                    return None;
+                }
+            }
+        }
+        None => TypedSize2D(800, 600),
+    };
+
+    let mut send_clicks = vec!();
+    for point in opt_match.opt_strs("send-click").iter() {
+        let coords: Vec<Option<f32>> = point.as_slice().split(',').map(|coord| {
+            from_str(coord.trim())
+        }).collect();
+        match coords.as_slice() {
+            [Some(x), Some(y)] => send_clicks.push(Point2D(x, y)),
+            _ => {
+                args_fail(format!("invalid point to click: {}", point).as_slice());
+                return None;
+            }
+        }
+    }
+
     Some(Opts {
         urls: urls,
         render_backend: render_backend,
         n_render_threads: n_render_threads,
         cpu_painting: cpu_painting,
         tile_size: tile_size,
+        initial_window_size: initial_window_size,
         device_pixels_per_px: device_pixels_per_px,
         profiler_period: profiler_period,
         layout_threads: layout_threads,
@@ -189,6 +261,10 @@ pub fn from_cmdline_args(args: &[String]) -> Option<Opts> {
         headless: opt_match.opt_present("z"),
         hard_fail: opt_match.opt_present("f"),
         bubble_widths_separately: opt_match.opt_present("b"),
-        native_threading: native_threading
+        native_threading: native_threading,
+        font_prefs: font_prefs,
+        send_keys: opt_match.opt_str("send-keys"),
+        send_clicks: send_clicks,
+        profile_dir: opt_match.opt_str("profile-dir"),
     })
 }
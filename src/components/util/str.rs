@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::i32;
 use std::iter::Filter;
 use std::str::CharSplits;
 
@@ -46,3 +47,138 @@ pub static HTML_SPACE_CHARACTERS: StaticCharVec = &[
 pub fn split_html_space_chars<'a>(s: &'a str) -> Filter<'a, &'a str, CharSplits<'a, StaticCharVec>> {
     s.split(HTML_SPACE_CHARACTERS).filter(|&split| !split.is_empty())
 }
+
+/// Parses a string as a signed integer, following the rules for parsing integers:
+///
+///     http://www.whatwg.org/html/#rules-for-parsing-integers
+pub fn parse_integer(input: &str) -> Option<i32> {
+    let mut chars = input.trim_left_chars(HTML_SPACE_CHARACTERS).chars().peekable();
+    let negative = match chars.peek() {
+        Some(&'-') => {
+            chars.next();
+            true
+        }
+        Some(&'+') => {
+            chars.next();
+            false
+        }
+        _ => false,
+    };
+
+    let mut value: i64 = 0;
+    let mut seen_digit = false;
+    for c in chars {
+        match c.to_digit(10) {
+            Some(digit) => {
+                value = value * 10 + digit as i64;
+                if value > (i32::MAX as i64) + 1 {
+                    return None
+                }
+                seen_digit = true;
+            }
+            None => break,
+        }
+    }
+    if !seen_digit {
+        return None
+    }
+
+    let value = if negative { -value } else { value };
+    if value > i32::MAX as i64 {
+        return None
+    }
+    Some(value as i32)
+}
+
+#[test]
+fn test_parse_integer() {
+    assert_eq!(parse_integer("42"), Some(42));
+    assert_eq!(parse_integer("  -7"), Some(-7));
+    assert_eq!(parse_integer("+3px"), Some(3));
+    assert_eq!(parse_integer("-2147483648"), Some(i32::MIN));
+    assert_eq!(parse_integer("2147483648"), None);
+    assert_eq!(parse_integer("x1"), None);
+    assert_eq!(parse_integer("-"), None);
+    assert_eq!(parse_integer(""), None);
+}
+
+/// Parses a string as a number if it is a valid floating-point number:
+///
+///     http://www.whatwg.org/html/#valid-floating-point-number
+///
+/// Unlike the rules for parsing floating-point number values, this accepts no leading
+/// whitespace or trailing garbage, as the validity of a number input requires.
+pub fn parse_floating_point_number(input: &str) -> Option<f64> {
+    fn is_digits(s: &str) -> bool {
+        !s.is_empty() && s.chars().all(|c| c.is_digit())
+    }
+
+    let (mantissa, exponent) = match input.find(|c: char| c == 'e' || c == 'E') {
+        Some(index) => (input.slice_to(index), Some(input.slice_from(index + 1))),
+        None => (input, None),
+    };
+    let (negative, mantissa) = if mantissa.starts_with("-") {
+        (true, mantissa.slice_from(1))
+    } else {
+        (false, mantissa)
+    };
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(index) => (mantissa.slice_to(index), mantissa.slice_from(index + 1)),
+        None => (mantissa, ""),
+    };
+    if (!integer.is_empty() && !is_digits(integer)) ||
+            (mantissa.contains_char('.') && !is_digits(fraction)) ||
+            (integer.is_empty() && fraction.is_empty()) {
+        return None
+    }
+    let exponent = match exponent {
+        None => 0,
+        Some(exponent) => {
+            let (exponent_negative, digits) = match exponent.slice_shift_char() {
+                (Some('-'), rest) => (true, rest),
+                (Some('+'), rest) => (false, rest),
+                _ => (false, exponent),
+            };
+            if !is_digits(digits) {
+                return None
+            }
+            let value = digits.chars().fold(0i32, |value, c| {
+                value.saturating_mul(10).saturating_add(c.to_digit(10).unwrap() as i32)
+            });
+            if exponent_negative { -value } else { value }
+        }
+    };
+
+    let digits = integer.chars().chain(fraction.chars());
+    let value = digits.fold(0.0f64, |value, c| value * 10.0 + c.to_digit(10).unwrap() as f64);
+    let scale = exponent.saturating_sub(fraction.len() as i32);
+    let value = if scale < 0 {
+        value / 10.0f64.powi(-scale)
+    } else {
+        value * 10.0f64.powi(scale)
+    };
+    if value.is_infinite() {
+        return None
+    }
+    Some(if negative { -value } else { value })
+}
+
+#[test]
+fn test_parse_floating_point_number() {
+    assert_eq!(parse_floating_point_number("42"), Some(42.0));
+    assert_eq!(parse_floating_point_number("-0.5"), Some(-0.5));
+    assert_eq!(parse_floating_point_number(".25"), Some(0.25));
+    assert_eq!(parse_floating_point_number("1e3"), Some(1000.0));
+    assert_eq!(parse_floating_point_number("25E-2"), Some(0.25));
+    assert_eq!(parse_floating_point_number("1e+2"), Some(100.0));
+    assert_eq!(parse_floating_point_number("+1"), None);
+    assert_eq!(parse_floating_point_number(" 1"), None);
+    assert_eq!(parse_floating_point_number("1."), None);
+    assert_eq!(parse_floating_point_number("-.5e1"), Some(-5.0));
+    assert_eq!(parse_floating_point_number("1.2.3"), None);
+    assert_eq!(parse_floating_point_number("1e"), None);
+    assert_eq!(parse_floating_point_number("e1"), None);
+    assert_eq!(parse_floating_point_number("-"), None);
+    assert_eq!(parse_floating_point_number("1e999"), None);
+    assert_eq!(parse_floating_point_number(""), None);
+}
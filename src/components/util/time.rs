@@ -26,6 +26,8 @@ impl ProfilerChan {
 pub enum ProfilerMsg {
     /// Normal message used for reporting time
     TimeMsg(ProfilerCategory, f64),
+    /// Message used to report that an event, such as a cache hit, occurred some number of times
+    CountMsg(ProfilerCounter, uint),
     /// Message used to force print the profiling metrics
     PrintMsg,
     /// Tells the profiler to shut down.
@@ -102,10 +104,28 @@ impl ProfilerCategory {
 
 type ProfilerBuckets = TreeMap<ProfilerCategory, Vec<f64>>;
 
+/// Events that are counted rather than timed.
+#[deriving(Eq, Clone, Ord, TotalEq, TotalOrd)]
+pub enum ProfilerCounter {
+    ShapedRunCacheHitCounter,
+    ShapedRunCacheMissCounter,
+}
+
+impl ProfilerCounter {
+    /// The name of this counter in the profiler output.
+    pub fn format(self) -> String {
+        match self {
+            ShapedRunCacheHitCounter => "Shaped run cache hits",
+            ShapedRunCacheMissCounter => "Shaped run cache misses",
+        }.to_string()
+    }
+}
+
 // back end of the profiler that handles data aggregation and performance metrics
 pub struct Profiler {
     pub port: Receiver<ProfilerMsg>,
     buckets: ProfilerBuckets,
+    counters: TreeMap<ProfilerCounter, uint>,
     pub last_msg: Option<ProfilerMsg>,
 }
 
@@ -150,6 +170,7 @@ impl Profiler {
         Profiler {
             port: port,
             buckets: ProfilerCategory::empty_buckets(),
+            counters: TreeMap::new(),
             last_msg: None,
         }
     }
@@ -171,9 +192,13 @@ impl Profiler {
     fn handle_msg(&mut self, msg: ProfilerMsg) -> bool {
         match msg {
             TimeMsg(category, t) => self.buckets.find_mut(&category).unwrap().push(t),
+            CountMsg(counter, count) => {
+                let total = self.counters.find(&counter).map_or(0, |total| *total);
+                self.counters.insert(counter, total + count);
+            }
             PrintMsg => match self.last_msg {
                 // only print if more data has arrived since the last printout
-                Some(TimeMsg(..)) => self.print_buckets(),
+                Some(TimeMsg(..)) | Some(CountMsg(..)) => self.print_buckets(),
                 _ => ()
             },
             ExitMsg => return false,
@@ -205,6 +230,9 @@ impl Profiler {
                          category.format(), mean, median, min, max, data_len);
             }
         }
+        for (counter, total) in self.counters.iter() {
+            println!("{:-35s}: {:15u}", counter.format(), *total);
+        }
         println!("");
     }
 }
@@ -239,3 +267,14 @@ fn check_order() {
     let buckets = ProfilerCategory::empty_buckets();
     assert!(buckets.len() == NumBuckets as uint);
 }
+
+#[test]
+fn test_counters_accumulate() {
+    let (_chan, port) = channel();
+    let mut profiler = Profiler::new(port);
+    profiler.handle_msg(CountMsg(ShapedRunCacheHitCounter, 2));
+    profiler.handle_msg(CountMsg(ShapedRunCacheMissCounter, 1));
+    profiler.handle_msg(CountMsg(ShapedRunCacheHitCounter, 3));
+    assert_eq!(profiler.counters.find(&ShapedRunCacheHitCounter), Some(&5));
+    assert_eq!(profiler.counters.find(&ShapedRunCacheMissCounter), Some(&1));
+}
@@ -195,6 +195,62 @@ mod parse_url_tests {
 
 }
 
+/// Serializes name-value pairs as `application/x-www-form-urlencoded`, the encoding of HTML
+/// form submissions.
+// http://url.spec.whatwg.org/#concept-urlencoded-serializer
+pub fn form_urlencode(pairs: &[(String, String)]) -> String {
+    fn serialize_bytes(output: &mut String, input: &str) {
+        for &byte in input.as_bytes().iter() {
+            match byte {
+                0x20 => output.push_char('+'),
+                0x2A | 0x2D | 0x2E | 0x30..0x39 | 0x41..0x5A | 0x5F | 0x61..0x7A => {
+                    output.push_char(byte as char)
+                }
+                _ => output.push_str(format!("%{:02X}", byte).as_slice()),
+            }
+        }
+    }
+
+    let mut output = String::new();
+    for (i, &(ref name, ref value)) in pairs.iter().enumerate() {
+        if i != 0 {
+            output.push_char('&');
+        }
+        serialize_bytes(&mut output, name.as_slice());
+        output.push_char('=');
+        serialize_bytes(&mut output, value.as_slice());
+    }
+    output
+}
+
+#[cfg(test)]
+mod form_urlencode_tests {
+    use super::form_urlencode;
+
+    #[test]
+    fn should_percent_encode_reserved_and_non_ascii_bytes() {
+        let pairs = vec!(("name".to_string(), "J. Doe".to_string()),
+                         ("q".to_string(), "a&b=c/d".to_string()),
+                         ("note".to_string(), "caf\u00e9\r\n*-._".to_string()));
+        assert_eq!(form_urlencode(pairs.as_slice()).as_slice(),
+                   "name=J.+Doe&q=a%26b%3Dc%2Fd&note=caf%C3%A9%0D%0A*-._");
+    }
+
+    #[test]
+    fn should_serialize_no_pairs_as_empty_string() {
+        assert_eq!(form_urlencode([]).as_slice(), "");
+    }
+}
+
+/// Returns the origin of the given URL, serialized as `scheme://host[:port]`. Page zoom levels
+/// and storage areas are kept per origin.
+pub fn origin(url: &std_url::Url) -> String {
+    match url.port {
+        Some(ref port) => format!("{}://{}:{}", url.scheme, url.host, port),
+        None => format!("{}://{}", url.scheme, url.host),
+    }
+}
+
 pub type UrlMap<T> = HashMap<std_url::Url, T>;
 
 pub fn url_map<T: Clone + 'static>() -> UrlMap<T> {
@@ -28,6 +28,7 @@ extern crate std_url = "url";
 
 pub mod cache;
 pub mod debug_utils;
+pub mod font_prefs;
 pub mod geometry;
 pub mod namespace;
 pub mod opts;
@@ -15,6 +15,7 @@ extern crate test;
 use test::{TestOpts, run_tests_console, TestDesc, TestDescAndFn, DynTestFn, DynTestName};
 use getopts::{getopts, reqopt};
 use std::{os, str};
+use std::io::File;
 use std::io::fs;
 use std::io::Reader;
 use std::io::process::{Command, Ignored, CreatePipe, InheritFd, ExitStatus};
@@ -88,6 +89,24 @@ fn make_test(file: String) -> TestDescAndFn {
     }
 }
 
+/// Returns the extra command line arguments that a test asks Servo to run it with, in a comment
+/// of the form `<!-- servo-args: --send-click 10,10 -->`.
+fn servo_args(path: &Path) -> Vec<String> {
+    let contents = match File::open(path).read_to_str() {
+        Ok(contents) => contents,
+        _ => fail!("Unable to read test file."),
+    };
+    let mut args = vec!();
+    for line in contents.as_slice().lines() {
+        let line = line.trim();
+        if line.starts_with("<!-- servo-args:") && line.ends_with("-->") {
+            let line = line.slice("<!-- servo-args:".len(), line.len() - "-->".len());
+            args.extend(line.words().map(|arg| arg.to_string()));
+        }
+    }
+    args
+}
+
 fn run_test(file: String) {
     let path = os::make_absolute(&Path::new(file));
     // FIXME (#1094): not the right way to transform a path
@@ -95,8 +114,12 @@ fn run_test(file: String) {
     let stdout = CreatePipe(false, true);
     let stderr = InheritFd(2);
 
+    let mut args = vec!("-z".to_string(), "-f".to_string());
+    args.push_all_move(servo_args(&path));
+    args.push(infile);
+
     let mut prc = match Command::new("./servo")
-        .args(["-z", "-f", infile.as_slice()])
+        .args(args.as_slice())
         .stdin(Ignored)
         .stdout(stdout)
         .stderr(stderr)